@@ -5,7 +5,8 @@ use combine::parser::range::{take_while, take_while1};
 use combine::parser::repeat::escaped;
 use combine::parser::Parser;
 use combine::{
-    attempt, choice, eof, many, many1, one_of, optional, parser, satisfy, skip_many1, value,
+    any, attempt, choice, eof, many, many1, not_followed_by, one_of, optional, parser, satisfy,
+    skip_many1, value,
 };
 use once_cell::sync::Lazy;
 use regex::Regex;
@@ -45,15 +46,17 @@ fn field_name<'a>() -> impl Parser<&'a str, Output = String> {
     })
 }
 
+fn is_word_char(c: char) -> bool {
+    !c.is_whitespace() && ![':', '^', '{', '}', '"', '[', ']', '(', ')'].contains(&c)
+}
+
 fn word<'a>() -> impl Parser<&'a str, Output = String> {
     (
         satisfy(|c: char| {
             !c.is_whitespace()
                 && !['-', '^', '`', ':', '{', '}', '"', '[', ']', '(', ')'].contains(&c)
         }),
-        many(satisfy(|c: char| {
-            !c.is_whitespace() && ![':', '^', '{', '}', '"', '[', ']', '(', ')'].contains(&c)
-        })),
+        many(satisfy(is_word_char)),
     )
         .map(|(s1, s2): (char, String)| format!("{}{}", s1, s2))
         .and_then(|s: String| match s.as_str() {
@@ -169,6 +172,119 @@ fn literal<'a>() -> impl Parser<&'a str, Output = UserInputLeaf> {
         .map(UserInputLeaf::from)
 }
 
+/// Returns true if the word contains a `*` or a `?` wildcard which is not escaped
+/// with a backslash.
+///
+/// A trailing `?` is not a wildcard, so that questions like `what?` keep being
+/// plain terms.
+fn has_unescaped_wildcard(word: &str) -> bool {
+    let mut chars = word.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' => return true,
+            '?' if chars.peek().is_some() => return true,
+            '\\' => {
+                chars.next();
+            }
+            _ => {}
+        }
+    }
+    false
+}
+
+/// Parses a word containing wildcards, like `foo*` or `title:f?o`.
+///
+/// A lone `*` is not a wildcard, but the query matching all documents.
+fn wildcard<'a>() -> impl Parser<&'a str, Output = UserInputLeaf> {
+    (optional(attempt(field_name())), word()).and_then(
+        |(field_name, pattern): (Option<String>, String)| {
+            if pattern != "*" && has_unescaped_wildcard(&pattern) {
+                Ok(UserInputLeaf::Wildcard {
+                    field_name,
+                    pattern,
+                })
+            } else {
+                Err(StringStreamError::UnexpectedParse)
+            }
+        },
+    )
+}
+
+/// Default edit distance of a fuzzy term written without distance, like `foo~`.
+const DEFAULT_FUZZY_DISTANCE: u8 = 2;
+
+/// Splits a word like `foo~1` into the term `foo` and the edit distance `1`.
+///
+/// Returns `None` if the word does not end with a `~`, optionally followed by a distance,
+/// which is not escaped with a backslash.
+fn split_fuzzy_distance(word: &str) -> Option<(&str, u8)> {
+    let mut tilde_pos = None;
+    let mut char_indices = word.char_indices();
+    while let Some((pos, c)) = char_indices.next() {
+        match c {
+            '~' => tilde_pos = Some(pos),
+            '\\' => {
+                char_indices.next();
+            }
+            _ => {}
+        }
+    }
+    let tilde_pos = tilde_pos?;
+    let (term, distance) = (&word[..tilde_pos], &word[tilde_pos + 1..]);
+    if term.is_empty() {
+        return None;
+    }
+    if distance.is_empty() {
+        return Some((term, DEFAULT_FUZZY_DISTANCE));
+    }
+    if !distance.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    Some((term, distance.parse().ok()?))
+}
+
+/// Parses a fuzzy term, like `foo~` or `title:foo~1`.
+fn fuzzy<'a>() -> impl Parser<&'a str, Output = UserInputLeaf> {
+    (optional(attempt(field_name())), word()).and_then(
+        |(field_name, word): (Option<String>, String)| match split_fuzzy_distance(&word) {
+            Some((term, distance)) => Ok(UserInputLeaf::Fuzzy {
+                field_name,
+                term: term.to_string(),
+                distance,
+            }),
+            None => Err(StringStreamError::UnexpectedParse),
+        },
+    )
+}
+
+/// Parses a regular expression enclosed in slashes, like `title:/jo.*n/`.
+///
+/// Within the regular expression, `\/` stands for a slash. The closing slash
+/// must end the leaf, so that `/a/b` is not a regular expression.
+fn regex<'a>() -> impl Parser<&'a str, Output = UserInputLeaf> {
+    let regex_char = choice((
+        char('\\').with(any()).map(|c: char| {
+            if c == '/' {
+                "/".to_string()
+            } else {
+                format!("\\{}", c)
+            }
+        }),
+        satisfy(|c: char| c != '/' && c != '\\').map(|c: char| c.to_string()),
+    ));
+    (
+        optional(attempt(field_name())),
+        char('/')
+            .with(many1::<String, _, _>(regex_char))
+            .skip(char('/'))
+            .skip(not_followed_by(satisfy(is_word_char))),
+    )
+        .map(|(field_name, pattern)| UserInputLeaf::Regex {
+            field_name,
+            pattern,
+        })
+}
+
 fn negative_number<'a>() -> impl Parser<&'a str, Output = String> {
     (
         char('-'),
@@ -273,11 +389,18 @@ fn leaf<'a>() -> impl Parser<&'a str, Output = UserInputAst> {
         char('(')
             .with(ast())
             .skip(char(')'))
-            .or(char('*').map(|_| UserInputAst::from(UserInputLeaf::All)))
+            .or(attempt(
+                char('*')
+                    .skip(not_followed_by(satisfy(is_word_char)))
+                    .map(|_| UserInputAst::from(UserInputLeaf::All)),
+            ))
             .or(attempt(
                 string("NOT").skip(spaces1()).with(leaf()).map(negate),
             ))
             .or(attempt(range().map(UserInputAst::from)))
+            .or(attempt(regex().map(UserInputAst::from)))
+            .or(attempt(wildcard().map(UserInputAst::from)))
+            .or(attempt(fuzzy().map(UserInputAst::from)))
             .or(literal().map(UserInputAst::from))
             .parse_stream(input)
             .into_result()
@@ -774,6 +897,55 @@ mod test {
         test_is_parse_err("abc +    ");
     }
 
+    #[test]
+    fn test_parse_query_wildcard() {
+        test_parse_query_to_ast_helper("foo*", "foo*");
+        test_parse_query_to_ast_helper("f?o", "f?o");
+        test_parse_query_to_ast_helper("*foo", "*foo");
+        test_parse_query_to_ast_helper("title:foo*", "\"title\":foo*");
+        test_parse_query_to_ast_helper("foo* bar", "(*foo* *\"bar\")");
+        test_parse_query_to_ast_helper("+fo?o -bar", "(+fo?o -\"bar\")");
+        test_parse_query_to_ast_helper("foo*^2", "(foo*)^2");
+        test_parse_query_to_ast_helper("*", "*");
+        test_parse_query_to_ast_helper("* foo", "(** *\"foo\")");
+        test_parse_query_to_ast_helper("\"foo*\"", "\"foo*\"");
+        test_parse_query_to_ast_helper(r"foo\*", r#""foo\*""#);
+        test_parse_query_to_ast_helper("foo:>a*", "\"foo\":{\"a*\" TO \"*\"}");
+        test_parse_query_to_ast_helper("rust?", "\"rust?\"");
+        test_parse_query_to_ast_helper("what? rust", "(*\"what?\" *\"rust\")");
+        test_parse_query_to_ast_helper("fo??", "fo??");
+        test_parse_query_to_ast_helper("c++*", "c++*");
+    }
+
+    #[test]
+    fn test_parse_query_fuzzy() {
+        test_parse_query_to_ast_helper("foo~", "foo~2");
+        test_parse_query_to_ast_helper("foo~1", "foo~1");
+        test_parse_query_to_ast_helper("foo~0", "foo~0");
+        test_parse_query_to_ast_helper("title:foo~1", "\"title\":foo~1");
+        test_parse_query_to_ast_helper("foo~1 bar", "(*foo~1 *\"bar\")");
+        test_parse_query_to_ast_helper("-foo~1^2", "(-(foo~1)^2)");
+        test_parse_query_to_ast_helper("~foo", "\"~foo\"");
+        test_parse_query_to_ast_helper("foo~bar", "\"foo~bar\"");
+        test_parse_query_to_ast_helper("foo~0.8", "\"foo~0.8\"");
+        test_parse_query_to_ast_helper(r"foo\~1", r#""foo\~1""#);
+        test_parse_query_to_ast_helper("\"foo\"~1", "\"foo\"~1");
+        test_parse_query_to_ast_helper("foo~1000", "\"foo~1000\"");
+    }
+
+    #[test]
+    fn test_parse_query_regex() {
+        test_parse_query_to_ast_helper("/jo.*n/", "/jo.*n/");
+        test_parse_query_to_ast_helper("title:/jo.*n/", "\"title\":/jo.*n/");
+        test_parse_query_to_ast_helper("title:/[a-z]+ [0-9]?/", "\"title\":/[a-z]+ [0-9]?/");
+        test_parse_query_to_ast_helper(r"url:/a\/b/", "\"url\":/a/b/");
+        test_parse_query_to_ast_helper(r"/a\.b/", r"/a\.b/");
+        test_parse_query_to_ast_helper("/jo.*n/^2 doe", "(*(/jo.*n/)^2 *\"doe\")");
+        test_parse_query_to_ast_helper("(title:/jo.*n/)", "\"title\":/jo.*n/");
+        test_parse_query_to_ast_helper("facet:/a/b", "\"facet\":\"/a/b\"");
+        test_parse_query_to_ast_helper("/a/b", "\"/a/b\"");
+    }
+
     #[test]
     fn test_slop() {
         assert!(parse_to_ast().parse("\"a b\"~").is_err());
@@ -784,7 +956,7 @@ mod test {
         test_parse_query_to_ast_helper("\"a b\"^2~4", "(*(\"a b\")^2 *\"~4\")");
         test_parse_query_to_ast_helper("\"~Document\"", "\"~Document\"");
         test_parse_query_to_ast_helper("~Document", "\"~Document\"");
-        test_parse_query_to_ast_helper("a~2", "\"a~2\"");
+        test_parse_query_to_ast_helper("a~2", "a~2");
         test_parse_query_to_ast_helper("\"a b\"~0", "\"a b\"");
         test_parse_query_to_ast_helper("\"a b\"~1", "\"a b\"~1");
         test_parse_query_to_ast_helper("\"a b\"~3", "\"a b\"~3");
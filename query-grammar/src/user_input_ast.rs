@@ -12,6 +12,22 @@ pub enum UserInputLeaf {
         lower: UserInputBound,
         upper: UserInputBound,
     },
+    /// A word containing `*` or `?` wildcards.
+    Wildcard {
+        field_name: Option<String>,
+        pattern: String,
+    },
+    /// A word followed by `~` and an optional edit distance, like `foo~1`.
+    Fuzzy {
+        field_name: Option<String>,
+        term: String,
+        distance: u8,
+    },
+    /// A regular expression enclosed in slashes, like `/jo.*n/`.
+    Regex {
+        field_name: Option<String>,
+        pattern: String,
+    },
 }
 
 impl Debug for UserInputLeaf {
@@ -32,6 +48,34 @@ impl Debug for UserInputLeaf {
                 Ok(())
             }
             UserInputLeaf::All => write!(formatter, "*"),
+            UserInputLeaf::Wildcard {
+                ref field_name,
+                ref pattern,
+            } => {
+                if let Some(ref field) = field_name {
+                    write!(formatter, "\"{}\":", field)?;
+                }
+                write!(formatter, "{}", pattern)
+            }
+            UserInputLeaf::Fuzzy {
+                ref field_name,
+                ref term,
+                distance,
+            } => {
+                if let Some(ref field) = field_name {
+                    write!(formatter, "\"{}\":", field)?;
+                }
+                write!(formatter, "{}~{}", term, distance)
+            }
+            UserInputLeaf::Regex {
+                ref field_name,
+                ref pattern,
+            } => {
+                if let Some(ref field) = field_name {
+                    write!(formatter, "\"{}\":", field)?;
+                }
+                write!(formatter, "/{}/", pattern)
+            }
         }
     }
 }
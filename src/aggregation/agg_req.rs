@@ -50,8 +50,20 @@ use std::collections::{HashMap, HashSet};
 use serde::{Deserialize, Serialize};
 
 pub use super::bucket::RangeAggregation;
-use super::bucket::{HistogramAggregation, TermsAggregation};
-use super::metric::{AverageAggregation, StatsAggregation};
+use super::bucket::{
+    CompositeAggregation, CompositeSource, DateHistogramAggregation, FilterAggregation,
+    FiltersAggregation, GeoDistanceAggregation, GeoTileGridAggregation, GeohashGridAggregation,
+    HistogramAggregation, SignificantTermsAggregation, TermsAggregation,
+};
+use super::metric::{
+    AverageAggregation, CardinalityAggregation, GeoBoundsAggregation, GeoCentroidAggregation,
+    MaxAggregation, MinAggregation, PercentileRanksAggregation, PercentilesAggregation,
+    StatsAggregation, SumAggregation, TopHitsAggregation, ValueCountAggregation,
+};
+use super::pipeline::{
+    AvgBucketAggregation, BucketSelectorAggregation, BucketSortAggregation,
+    CumulativeSumAggregation, DerivativeAggregation, MaxBucketAggregation, MovingAvgAggregation,
+};
 use super::VecWithNames;
 
 /// The top-level aggregation request structure, which contains [`Aggregation`] and their user
@@ -81,6 +93,8 @@ impl From<Aggregations> for AggregationsInternal {
                     },
                 )),
                 Aggregation::Metric(metric) => metrics.push((key, metric)),
+                // Pipeline aggregations are computed on the final results.
+                Aggregation::Pipeline(_) => {}
             }
         }
         Self {
@@ -113,12 +127,50 @@ impl BucketAggregationInternal {
             _ => None,
         }
     }
+    pub(crate) fn as_date_histogram(&self) -> Option<&DateHistogramAggregation> {
+        match &self.bucket_agg {
+            BucketAggregationType::DateHistogram(date_histogram) => Some(date_histogram),
+            _ => None,
+        }
+    }
+    pub(crate) fn as_filters(&self) -> Option<&FiltersAggregation> {
+        match &self.bucket_agg {
+            BucketAggregationType::Filters(filters) => Some(filters),
+            _ => None,
+        }
+    }
     pub(crate) fn as_term(&self) -> Option<&TermsAggregation> {
         match &self.bucket_agg {
             BucketAggregationType::Terms(terms) => Some(terms),
             _ => None,
         }
     }
+    pub(crate) fn as_significant_terms(&self) -> Option<&SignificantTermsAggregation> {
+        match &self.bucket_agg {
+            BucketAggregationType::SignificantTerms(significant_terms) => Some(significant_terms),
+            _ => None,
+        }
+    }
+    pub(crate) fn as_composite(&self) -> Option<&CompositeAggregation> {
+        match &self.bucket_agg {
+            BucketAggregationType::Composite(composite) => Some(composite),
+            _ => None,
+        }
+    }
+    pub(crate) fn as_geo_distance(&self) -> Option<&GeoDistanceAggregation> {
+        match &self.bucket_agg {
+            BucketAggregationType::GeoDistance(geo_distance) => Some(geo_distance),
+            _ => None,
+        }
+    }
+    /// Returns the maximum number of buckets of a geohash or geotile grid aggregation.
+    pub(crate) fn geo_grid_size(&self) -> Option<usize> {
+        match &self.bucket_agg {
+            BucketAggregationType::GeohashGrid(geohash_grid) => Some(geohash_grid.size()),
+            BucketAggregationType::GeoTileGrid(geotile_grid) => Some(geotile_grid.size()),
+            _ => None,
+        }
+    }
 }
 
 /// Extract all fields, where the term directory is used in the tree.
@@ -139,9 +191,19 @@ pub fn get_fast_field_names(aggs: &Aggregations) -> HashSet<String> {
     fast_field_names
 }
 
-/// Aggregation request of [`BucketAggregation`] or [`MetricAggregation`].
+/// Returns true if an aggregation in the tree needs the scores of the documents.
+pub(crate) fn requires_scoring(aggs: &Aggregations) -> bool {
+    aggs.values().any(|agg| match agg {
+        Aggregation::Bucket(bucket) => requires_scoring(&bucket.sub_aggregation),
+        Aggregation::Metric(MetricAggregation::TopHits(top_hits)) => top_hits.requires_scoring(),
+        Aggregation::Metric(_) | Aggregation::Pipeline(_) => false,
+    })
+}
+
+/// Aggregation request of [`BucketAggregation`], [`MetricAggregation`] or
+/// [`PipelineAggregation`].
 ///
-/// An aggregation is either a bucket or a metric.
+/// An aggregation is either a bucket, a metric or a pipeline.
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum Aggregation {
@@ -149,12 +211,16 @@ pub enum Aggregation {
     Bucket(BucketAggregation),
     /// Metric aggregation, see [`MetricAggregation`] for details.
     Metric(MetricAggregation),
+    /// Pipeline aggregation, see [`PipelineAggregation`] for details.
+    Pipeline(PipelineAggregation),
 }
 
 impl Aggregation {
     fn get_term_dict_field_names(&self, term_field_names: &mut HashSet<String>) {
-        if let Aggregation::Bucket(bucket) = self {
-            bucket.get_term_dict_field_names(term_field_names)
+        match self {
+            Aggregation::Bucket(bucket) => bucket.get_term_dict_field_names(term_field_names),
+            Aggregation::Metric(metric) => metric.get_term_dict_field_names(term_field_names),
+            Aggregation::Pipeline(_) => {}
         }
     }
 
@@ -162,6 +228,7 @@ impl Aggregation {
         match self {
             Aggregation::Bucket(bucket) => bucket.get_fast_field_names(fast_field_names),
             Aggregation::Metric(metric) => metric.get_fast_field_names(fast_field_names),
+            Aggregation::Pipeline(_) => {}
         }
     }
 }
@@ -191,8 +258,21 @@ pub struct BucketAggregation {
 
 impl BucketAggregation {
     fn get_term_dict_field_names(&self, term_dict_field_names: &mut HashSet<String>) {
-        if let BucketAggregationType::Terms(terms) = &self.bucket_agg {
-            term_dict_field_names.insert(terms.field.to_string());
+        match &self.bucket_agg {
+            BucketAggregationType::Terms(terms) => {
+                term_dict_field_names.insert(terms.field.to_string());
+            }
+            BucketAggregationType::SignificantTerms(significant_terms) => {
+                term_dict_field_names.insert(significant_terms.field.to_string());
+            }
+            BucketAggregationType::Composite(composite) => {
+                for named_source in &composite.sources {
+                    if let CompositeSource::Terms(terms) = &named_source.source {
+                        term_dict_field_names.insert(terms.field.to_string());
+                    }
+                }
+            }
+            _ => {}
         }
         term_dict_field_names.extend(get_term_dict_field_names(&self.sub_aggregation));
     }
@@ -211,19 +291,75 @@ pub enum BucketAggregationType {
     /// Put data into buckets of user-defined ranges.
     #[serde(rename = "histogram")]
     Histogram(HistogramAggregation),
+    /// Put data into buckets of calendar or fixed time intervals.
+    #[serde(rename = "date_histogram")]
+    DateHistogram(DateHistogramAggregation),
     /// Put data into buckets of terms.
     #[serde(rename = "terms")]
     Terms(TermsAggregation),
+    /// Put data into buckets of terms, which are unusually frequent compared to the whole index.
+    #[serde(rename = "significant_terms")]
+    SignificantTerms(SignificantTermsAggregation),
+    /// Put data matching a query into a single bucket.
+    #[serde(rename = "filter")]
+    Filter(FilterAggregation),
+    /// Put data into one bucket per query.
+    #[serde(rename = "filters")]
+    Filters(FiltersAggregation),
+    /// Put data into buckets of compound keys, that can be paged through.
+    #[serde(rename = "composite")]
+    Composite(CompositeAggregation),
+    /// Put data into buckets of user-defined distance ranges from an origin.
+    #[serde(rename = "geo_distance")]
+    GeoDistance(GeoDistanceAggregation),
+    /// Put data into buckets of geohash cells.
+    #[serde(rename = "geohash_grid")]
+    GeohashGrid(GeohashGridAggregation),
+    /// Put data into buckets of web mercator map tiles.
+    #[serde(rename = "geotile_grid")]
+    GeoTileGrid(GeoTileGridAggregation),
 }
 
 impl BucketAggregationType {
     fn get_fast_field_names(&self, fast_field_names: &mut HashSet<String>) {
         match self {
             BucketAggregationType::Terms(terms) => fast_field_names.insert(terms.field.to_string()),
+            BucketAggregationType::SignificantTerms(significant_terms) => {
+                fast_field_names.insert(significant_terms.field.to_string())
+            }
             BucketAggregationType::Range(range) => fast_field_names.insert(range.field.to_string()),
             BucketAggregationType::Histogram(histogram) => {
                 fast_field_names.insert(histogram.field.to_string())
             }
+            BucketAggregationType::DateHistogram(date_histogram) => {
+                fast_field_names.insert(date_histogram.field.to_string())
+            }
+            BucketAggregationType::Composite(composite) => {
+                for named_source in &composite.sources {
+                    fast_field_names.insert(named_source.source.field().to_string());
+                }
+                true
+            }
+            BucketAggregationType::GeoDistance(GeoDistanceAggregation {
+                lat_field,
+                lon_field,
+                ..
+            })
+            | BucketAggregationType::GeohashGrid(GeohashGridAggregation {
+                lat_field,
+                lon_field,
+                ..
+            })
+            | BucketAggregationType::GeoTileGrid(GeoTileGridAggregation {
+                lat_field,
+                lon_field,
+                ..
+            }) => {
+                fast_field_names.insert(lat_field.to_string());
+                fast_field_names.insert(lon_field.to_string())
+            }
+            // Filters are evaluated with queries and don't read fast fields.
+            BucketAggregationType::Filter(_) | BucketAggregationType::Filters(_) => false,
         };
     }
 }
@@ -243,17 +379,167 @@ pub enum MetricAggregation {
     /// Calculates stats sum, average, min, max, standard_deviation on a field.
     #[serde(rename = "stats")]
     Stats(StatsAggregation),
+    /// Computes the minimum of the extracted values.
+    #[serde(rename = "min")]
+    Min(MinAggregation),
+    /// Computes the maximum of the extracted values.
+    #[serde(rename = "max")]
+    Max(MaxAggregation),
+    /// Computes the sum of the extracted values.
+    #[serde(rename = "sum")]
+    Sum(SumAggregation),
+    /// Counts the number of extracted values.
+    #[serde(rename = "value_count")]
+    ValueCount(ValueCountAggregation),
+    /// Estimates the number of distinct values of a field.
+    #[serde(rename = "cardinality")]
+    Cardinality(CardinalityAggregation),
+    /// Estimates the values below which given percentages of the values fall.
+    #[serde(rename = "percentiles")]
+    Percentiles(PercentilesAggregation),
+    /// Estimates the percentages of values below given values.
+    #[serde(rename = "percentile_ranks")]
+    PercentileRanks(PercentileRanksAggregation),
+    /// Keeps the top hits of the documents, sorted by score or fast fields.
+    #[serde(rename = "top_hits")]
+    TopHits(TopHitsAggregation),
+    /// Computes the bounding box of the geo points.
+    #[serde(rename = "geo_bounds")]
+    GeoBounds(GeoBoundsAggregation),
+    /// Computes the centroid of the geo points.
+    #[serde(rename = "geo_centroid")]
+    GeoCentroid(GeoCentroidAggregation),
 }
 
 impl MetricAggregation {
+    fn get_term_dict_field_names(&self, term_dict_field_names: &mut HashSet<String>) {
+        // On text fields, the cardinality aggregation resolves term ordinals via the term
+        // dictionary.
+        if let MetricAggregation::Cardinality(cardinality) = self {
+            term_dict_field_names.insert(cardinality.field.to_string());
+        }
+    }
+
     fn get_fast_field_names(&self, fast_field_names: &mut HashSet<String>) {
         match self {
             MetricAggregation::Average(avg) => fast_field_names.insert(avg.field.to_string()),
             MetricAggregation::Stats(stats) => fast_field_names.insert(stats.field.to_string()),
+            MetricAggregation::Min(min) => fast_field_names.insert(min.field.to_string()),
+            MetricAggregation::Max(max) => fast_field_names.insert(max.field.to_string()),
+            MetricAggregation::Sum(sum) => fast_field_names.insert(sum.field.to_string()),
+            MetricAggregation::ValueCount(value_count) => {
+                fast_field_names.insert(value_count.field.to_string())
+            }
+            MetricAggregation::Cardinality(cardinality) => {
+                fast_field_names.insert(cardinality.field.to_string())
+            }
+            MetricAggregation::Percentiles(percentiles) => {
+                fast_field_names.insert(percentiles.field.to_string())
+            }
+            MetricAggregation::PercentileRanks(percentile_ranks) => {
+                fast_field_names.insert(percentile_ranks.field.to_string())
+            }
+            MetricAggregation::TopHits(top_hits) => {
+                fast_field_names.extend(top_hits.fast_field_names().map(str::to_string));
+                true
+            }
+            MetricAggregation::GeoBounds(GeoBoundsAggregation {
+                lat_field,
+                lon_field,
+            })
+            | MetricAggregation::GeoCentroid(GeoCentroidAggregation {
+                lat_field,
+                lon_field,
+            }) => {
+                fast_field_names.insert(lat_field.to_string());
+                fast_field_names.insert(lon_field.to_string())
+            }
         };
     }
 }
 
+/// Pipeline aggregations compute values from the results of other aggregations, instead of from
+/// documents. They are computed after the results of all segments have been merged.
+///
+/// Parent pipeline aggregations are sub aggregations of a multi bucket aggregation. They compute a
+/// value for each bucket of their parent or modify its buckets. Sibling pipeline aggregations
+/// compute a single value from the buckets of a sibling multi bucket aggregation.
+///
+/// See [`pipeline`](super::pipeline) for details.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum PipelineAggregation {
+    /// Sorts and truncates the buckets of the parent aggregation.
+    #[serde(rename = "bucket_sort")]
+    BucketSort(BucketSortAggregation),
+    /// Removes the buckets of the parent aggregation, whose value is outside of given bounds.
+    #[serde(rename = "bucket_selector")]
+    BucketSelector(BucketSelectorAggregation),
+    /// Computes the difference to the value of the previous bucket of the parent histogram.
+    #[serde(rename = "derivative")]
+    Derivative(DerivativeAggregation),
+    /// Computes the sum of the values of all buckets up to the bucket of the parent histogram.
+    #[serde(rename = "cumulative_sum")]
+    CumulativeSum(CumulativeSumAggregation),
+    /// Computes the average of the values of the previous buckets of the parent histogram.
+    #[serde(rename = "moving_avg")]
+    MovingAvg(MovingAvgAggregation),
+    /// Computes the average of the values of the buckets of a sibling aggregation.
+    #[serde(rename = "avg_bucket")]
+    AvgBucket(AvgBucketAggregation),
+    /// Finds the maximum of the values of the buckets of a sibling aggregation.
+    #[serde(rename = "max_bucket")]
+    MaxBucket(MaxBucketAggregation),
+}
+
+impl PipelineAggregation {
+    /// Returns the paths to the values the aggregation depends on.
+    pub(crate) fn buckets_paths(&self) -> Vec<&str> {
+        match self {
+            PipelineAggregation::BucketSort(bucket_sort) => bucket_sort
+                .sort
+                .iter()
+                .map(|sort_field| sort_field.path.as_str())
+                .collect(),
+            PipelineAggregation::BucketSelector(bucket_selector) => {
+                vec![&bucket_selector.buckets_path]
+            }
+            PipelineAggregation::Derivative(derivative) => vec![&derivative.buckets_path],
+            PipelineAggregation::CumulativeSum(cumulative_sum) => {
+                vec![&cumulative_sum.buckets_path]
+            }
+            PipelineAggregation::MovingAvg(moving_avg) => vec![&moving_avg.buckets_path],
+            PipelineAggregation::AvgBucket(avg_bucket) => vec![&avg_bucket.buckets_path],
+            PipelineAggregation::MaxBucket(max_bucket) => vec![&max_bucket.buckets_path],
+        }
+    }
+
+    /// Returns true for pipeline aggregations that compute a single value from the buckets of a
+    /// sibling aggregation.
+    pub(crate) fn is_sibling(&self) -> bool {
+        matches!(
+            self,
+            PipelineAggregation::AvgBucket(_) | PipelineAggregation::MaxBucket(_)
+        )
+    }
+
+    /// Returns true for parent pipeline aggregations that compute a value for each bucket of
+    /// their parent histogram, which depends on the previous buckets.
+    pub(crate) fn is_sequential(&self) -> bool {
+        matches!(
+            self,
+            PipelineAggregation::Derivative(_)
+                | PipelineAggregation::CumulativeSum(_)
+                | PipelineAggregation::MovingAvg(_)
+        )
+    }
+
+    /// Returns true for pipeline aggregations that compute a value, which can be referenced by
+    /// the `buckets_path` of other pipeline aggregations.
+    pub(crate) fn has_value(&self) -> bool {
+        self.is_sibling() || self.is_sequential()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1,19 +1,31 @@
 //! This will enhance the request tree with access to the fastfield and metadata.
 
-use std::rc::Rc;
-use std::sync::atomic::AtomicU32;
 use std::sync::Arc;
 
+use common::BitSet;
 use fastfield_codecs::Column;
 
 use super::agg_req::{Aggregation, Aggregations, BucketAggregationType, MetricAggregation};
-use super::bucket::{HistogramAggregation, RangeAggregation, TermsAggregation};
-use super::metric::{AverageAggregation, StatsAggregation};
+use super::bucket::{
+    get_filter_doc_sets, CompositeSource, DateHistogramAggregation, GeoDistanceAggregation,
+    GeoTileGridAggregation, GeohashGridAggregation, HistogramAggregation, RangeAggregation,
+    SignificantTermsAggregation, TermsAggregation,
+};
+use super::geo::GeoPointAccessor;
+use super::json_path::{split_json_path, JsonPathValues};
+use super::metric::{
+    AverageAggregation, CardinalityAggregation, DocScores, GeoBoundsAggregation,
+    GeoCentroidAggregation, MaxAggregation, MinAggregation, PercentileRanksAggregation,
+    PercentilesAggregation, StatsAggregation, SumAggregation, TopHitsAccessor,
+    ValueCountAggregation,
+};
 use super::segment_agg_result::BucketCount;
-use super::VecWithNames;
-use crate::fastfield::{type_and_cardinality, FastType, MultiValuedFastFieldReader};
+use super::{IntermediateKey, VecWithNames};
+use crate::fastfield::{
+    type_and_cardinality, FastType, MultiValuedFastFieldReader, MultiValuedU128FastFieldReader,
+};
 use crate::schema::{Cardinality, Type};
-use crate::{InvertedIndexReader, SegmentReader, TantivyError};
+use crate::{InvertedIndexReader, SegmentOrdinal, SegmentReader, TantivyError};
 
 #[derive(Clone, Default)]
 pub(crate) struct AggregationsWithAccessor {
@@ -34,42 +46,88 @@ impl AggregationsWithAccessor {
     }
 }
 
+/// Segment level information, which is shared by all aggregations of a request.
+#[derive(Clone, Default)]
+pub(crate) struct SegmentAggregationContext {
+    /// The ordinal of the segment in the searcher.
+    pub segment_ord: SegmentOrdinal,
+    /// The scores of the collected documents. Only set if an aggregation requires scoring.
+    pub doc_scores: Option<DocScores>,
+}
+
 #[derive(Clone)]
 pub(crate) enum FastFieldAccessor {
     Multi(MultiValuedFastFieldReader<u64>),
     Single(Arc<dyn Column<u64>>),
+    MultiU128(MultiValuedU128FastFieldReader<u128>),
+    SingleU128(Arc<dyn Column<u128>>),
 }
 impl FastFieldAccessor {
     pub fn as_single(&self) -> Option<&dyn Column<u64>> {
         match self {
-            FastFieldAccessor::Multi(_) => None,
             FastFieldAccessor::Single(reader) => Some(&**reader),
+            _ => None,
         }
     }
-    pub fn into_single(self) -> Option<Arc<dyn Column<u64>>> {
-        match self {
-            FastFieldAccessor::Multi(_) => None,
-            FastFieldAccessor::Single(reader) => Some(reader),
-        }
-    }
-    pub fn as_multi(&self) -> Option<&MultiValuedFastFieldReader<u64>> {
+
+    /// Returns the minimum and maximum value of a `u64` accessor, `None` if there are no values.
+    pub fn min_max_value(&self) -> Option<(u64, u64)> {
         match self {
-            FastFieldAccessor::Multi(reader) => Some(reader),
-            FastFieldAccessor::Single(_) => None,
+            FastFieldAccessor::Single(reader) => Some((reader.min_value(), reader.max_value())),
+            FastFieldAccessor::Multi(reader) if reader.total_num_vals() > 0 => {
+                Some((reader.min_value(), reader.max_value()))
+            }
+            _ => None,
         }
     }
 }
 
 #[derive(Clone)]
 pub struct BucketAggregationWithAccessor {
-    /// In general there can be buckets without fast field access, e.g. buckets that are created
-    /// based on search terms. So eventually this needs to be Option or moved.
-    pub(crate) accessor: FastFieldAccessor,
+    /// Buckets created based on queries, e.g. filter buckets, have no fast field access.
+    pub(crate) accessor: Option<FastFieldAccessor>,
     pub(crate) inverted_index: Option<Arc<InvertedIndexReader>>,
     pub(crate) field_type: Type,
     pub(crate) bucket_agg: BucketAggregationType,
     pub(crate) sub_aggregation: AggregationsWithAccessor,
     pub(crate) bucket_count: BucketCount,
+    /// The documents matching the queries of filter buckets in bucket order. Empty for other
+    /// bucket aggregations.
+    pub(crate) filters: Vec<BitSet>,
+    /// The distinct values of a json path, indexed by the value ordinals of the accessor. Only
+    /// set for terms aggregations on json paths.
+    pub(crate) json_path_keys: Vec<IntermediateKey>,
+    /// The values of the sources of a composite aggregation in source order. Empty for other
+    /// bucket aggregations.
+    pub(crate) composite_sources: Vec<ValuesAccessor>,
+    /// The number of alive documents of the segment, the background set of significant terms.
+    pub(crate) num_docs: u32,
+    /// Only set for the geo bucket aggregations.
+    pub(crate) geo_point: Option<GeoPointAccessor>,
+}
+
+/// Access to the values of a field, which may be a path inside a json field.
+#[derive(Clone)]
+pub(crate) struct ValuesAccessor {
+    pub accessor: FastFieldAccessor,
+    /// [`Type::Json`] for paths inside json fields, whose values are ordinals into
+    /// `json_path_keys`.
+    pub field_type: Type,
+    /// Only set for text fields, to resolve the term ordinals.
+    pub inverted_index: Option<Arc<InvertedIndexReader>>,
+    /// The distinct values of a json path, indexed by the value ordinals.
+    pub json_path_keys: Vec<IntermediateKey>,
+}
+
+impl ValuesAccessor {
+    fn from_fast_field(accessor: FastFieldAccessor, field_type: Type) -> Self {
+        ValuesAccessor {
+            accessor,
+            field_type,
+            inverted_index: None,
+            json_path_keys: Vec::new(),
+        }
+    }
 }
 
 impl BucketAggregationWithAccessor {
@@ -77,28 +135,86 @@ impl BucketAggregationWithAccessor {
         bucket: &BucketAggregationType,
         sub_aggregation: &Aggregations,
         reader: &SegmentReader,
-        bucket_count: Rc<AtomicU32>,
-        max_bucket_count: u32,
+        context: &SegmentAggregationContext,
+        bucket_count: &BucketCount,
     ) -> crate::Result<BucketAggregationWithAccessor> {
         let mut inverted_index = None;
-        let (accessor, field_type) = match &bucket {
+        let mut json_path_keys = Vec::new();
+        let mut composite_sources = Vec::new();
+        let mut geo_point = None;
+        let accessor_and_field_type = match &bucket {
             BucketAggregationType::Range(RangeAggregation {
                 field: field_name, ..
-            }) => get_ff_reader_and_validate(reader, field_name, Cardinality::SingleValue)?,
+            }) => Some(get_numeric_ff_reader(reader, field_name, bucket_count)?),
             BucketAggregationType::Histogram(HistogramAggregation {
                 field: field_name, ..
-            }) => get_ff_reader_and_validate(reader, field_name, Cardinality::SingleValue)?,
+            }) => Some(get_numeric_ff_reader(reader, field_name, bucket_count)?),
+            BucketAggregationType::DateHistogram(DateHistogramAggregation {
+                field: field_name,
+                ..
+            }) => Some(get_date_ff_reader(reader, field_name)?),
+            BucketAggregationType::Filter(_) | BucketAggregationType::Filters(_) => None,
             BucketAggregationType::Terms(TermsAggregation {
                 field: field_name, ..
             }) => {
-                let field = reader
-                    .schema()
-                    .get_field(field_name)
-                    .ok_or_else(|| TantivyError::FieldNotFound(field_name.to_string()))?;
-                inverted_index = Some(reader.inverted_index(field)?);
-                get_ff_reader_and_validate(reader, field_name, Cardinality::MultiValues)?
+                let values = get_values_accessor(reader, field_name, bucket_count)?;
+                inverted_index = values.inverted_index;
+                json_path_keys = values.json_path_keys;
+                Some((values.accessor, values.field_type))
+            }
+            BucketAggregationType::SignificantTerms(SignificantTermsAggregation {
+                field: field_name,
+                ..
+            }) => {
+                let values = get_values_accessor(reader, field_name, bucket_count)?;
+                inverted_index = values.inverted_index;
+                Some((values.accessor, values.field_type))
             }
+            BucketAggregationType::GeoDistance(GeoDistanceAggregation {
+                lat_field,
+                lon_field,
+                ..
+            })
+            | BucketAggregationType::GeohashGrid(GeohashGridAggregation {
+                lat_field,
+                lon_field,
+                ..
+            })
+            | BucketAggregationType::GeoTileGrid(GeoTileGridAggregation {
+                lat_field,
+                lon_field,
+                ..
+            }) => {
+                geo_point = Some(get_geo_point_accessor(reader, lat_field, lon_field)?);
+                None
+            }
+            BucketAggregationType::Composite(composite) => {
+                composite_sources = composite
+                    .sources
+                    .iter()
+                    .map(|named_source| match &named_source.source {
+                        CompositeSource::Terms(terms) => {
+                            get_values_accessor(reader, &terms.field, bucket_count)
+                        }
+                        CompositeSource::Histogram(histogram) => {
+                            get_numeric_values_accessor(reader, &histogram.field, bucket_count)
+                        }
+                        CompositeSource::DateHistogram(date_histogram) => {
+                            let (accessor, field_type) =
+                                get_date_ff_reader(reader, &date_histogram.field)?;
+                            Ok(ValuesAccessor::from_fast_field(accessor, field_type))
+                        }
+                    })
+                    .collect::<crate::Result<_>>()?;
+                None
+            }
+        };
+        let (accessor, field_type) = match accessor_and_field_type {
+            Some((accessor, field_type)) => (Some(accessor), field_type),
+            // The field type is not used by filter, composite and geo buckets.
+            None => (None, Type::U64),
         };
+        let filters = get_filter_doc_sets(bucket, reader)?;
         let sub_aggregation = sub_aggregation.clone();
         Ok(BucketAggregationWithAccessor {
             accessor,
@@ -106,15 +222,17 @@ impl BucketAggregationWithAccessor {
             sub_aggregation: get_aggs_with_accessor_and_validate(
                 &sub_aggregation,
                 reader,
-                bucket_count.clone(),
-                max_bucket_count,
+                context,
+                bucket_count,
             )?,
             bucket_agg: bucket.clone(),
             inverted_index,
-            bucket_count: BucketCount {
-                bucket_count,
-                max_bucket_count,
-            },
+            bucket_count: bucket_count.clone(),
+            filters,
+            json_path_keys,
+            composite_sources,
+            num_docs: reader.num_docs(),
+            geo_point,
         })
     }
 }
@@ -124,28 +242,93 @@ impl BucketAggregationWithAccessor {
 pub struct MetricAggregationWithAccessor {
     pub metric: MetricAggregation,
     pub field_type: Type,
-    pub accessor: Arc<dyn Column>,
+    /// Not set for metrics which don't aggregate the values of a single field, e.g. top hits.
+    pub(crate) accessor: Option<FastFieldAccessor>,
+    /// Only set for metrics that need to resolve term ordinals, e.g. cardinality on text fields.
+    pub(crate) inverted_index: Option<Arc<InvertedIndexReader>>,
+    /// Only set for the top hits aggregation.
+    pub(crate) top_hits: Option<TopHitsAccessor>,
+    /// Only set for the geo metric aggregations.
+    pub(crate) geo_point: Option<GeoPointAccessor>,
 }
 
 impl MetricAggregationWithAccessor {
     fn try_from_metric(
         metric: &MetricAggregation,
         reader: &SegmentReader,
+        context: &SegmentAggregationContext,
+        bucket_count: &BucketCount,
     ) -> crate::Result<MetricAggregationWithAccessor> {
         match &metric {
             MetricAggregation::Average(AverageAggregation { field: field_name })
-            | MetricAggregation::Stats(StatsAggregation { field: field_name }) => {
+            | MetricAggregation::Stats(StatsAggregation { field: field_name })
+            | MetricAggregation::Min(MinAggregation { field: field_name })
+            | MetricAggregation::Max(MaxAggregation { field: field_name })
+            | MetricAggregation::Sum(SumAggregation { field: field_name })
+            | MetricAggregation::ValueCount(ValueCountAggregation { field: field_name })
+            | MetricAggregation::Percentiles(PercentilesAggregation {
+                field: field_name, ..
+            })
+            | MetricAggregation::PercentileRanks(PercentileRanksAggregation {
+                field: field_name,
+                ..
+            }) => {
                 let (accessor, field_type) =
-                    get_ff_reader_and_validate(reader, field_name, Cardinality::SingleValue)?;
+                    get_numeric_ff_reader(reader, field_name, bucket_count)?;
 
                 Ok(MetricAggregationWithAccessor {
-                    accessor: accessor
-                        .into_single()
-                        .expect("unexpected fast field cardinality"),
+                    accessor: Some(accessor),
                     field_type,
                     metric: metric.clone(),
+                    inverted_index: None,
+                    top_hits: None,
+                    geo_point: None,
                 })
             }
+            MetricAggregation::Cardinality(CardinalityAggregation { field: field_name }) => {
+                let (accessor, field_type, inverted_index) = get_any_ff_reader(reader, field_name)?;
+                Ok(MetricAggregationWithAccessor {
+                    accessor: Some(accessor),
+                    field_type,
+                    metric: metric.clone(),
+                    inverted_index,
+                    top_hits: None,
+                    geo_point: None,
+                })
+            }
+            MetricAggregation::TopHits(top_hits) => {
+                let top_hits = TopHitsAccessor::try_new(
+                    top_hits,
+                    reader,
+                    context.segment_ord,
+                    context.doc_scores.as_ref(),
+                )?;
+                Ok(MetricAggregationWithAccessor {
+                    accessor: None,
+                    // The field type is not used by the top hits aggregation.
+                    field_type: Type::U64,
+                    metric: metric.clone(),
+                    inverted_index: None,
+                    top_hits: Some(top_hits),
+                    geo_point: None,
+                })
+            }
+            MetricAggregation::GeoBounds(GeoBoundsAggregation {
+                lat_field,
+                lon_field,
+            })
+            | MetricAggregation::GeoCentroid(GeoCentroidAggregation {
+                lat_field,
+                lon_field,
+            }) => Ok(MetricAggregationWithAccessor {
+                accessor: None,
+                // The field type is not used by the geo aggregations.
+                field_type: Type::F64,
+                metric: metric.clone(),
+                inverted_index: None,
+                top_hits: None,
+                geo_point: Some(get_geo_point_accessor(reader, lat_field, lon_field)?),
+            }),
         }
     }
 }
@@ -153,8 +336,8 @@ impl MetricAggregationWithAccessor {
 pub(crate) fn get_aggs_with_accessor_and_validate(
     aggs: &Aggregations,
     reader: &SegmentReader,
-    bucket_count: Rc<AtomicU32>,
-    max_bucket_count: u32,
+    context: &SegmentAggregationContext,
+    bucket_count: &BucketCount,
 ) -> crate::Result<AggregationsWithAccessor> {
     let mut metrics = vec![];
     let mut buckets = vec![];
@@ -166,14 +349,21 @@ pub(crate) fn get_aggs_with_accessor_and_validate(
                     &bucket.bucket_agg,
                     &bucket.sub_aggregation,
                     reader,
-                    Rc::clone(&bucket_count),
-                    max_bucket_count,
+                    context,
+                    bucket_count,
                 )?,
             )),
             Aggregation::Metric(metric) => metrics.push((
                 key.to_string(),
-                MetricAggregationWithAccessor::try_from_metric(metric, reader)?,
+                MetricAggregationWithAccessor::try_from_metric(
+                    metric,
+                    reader,
+                    context,
+                    bucket_count,
+                )?,
             )),
+            // Pipeline aggregations don't collect documents.
+            Aggregation::Pipeline(_) => {}
         }
     }
     Ok(AggregationsWithAccessor::from_data(
@@ -182,6 +372,30 @@ pub(crate) fn get_aggs_with_accessor_and_validate(
     ))
 }
 
+/// Get the accessor of the geo points, whose coordinates are stored in two f64 fast fields.
+fn get_geo_point_accessor(
+    reader: &SegmentReader,
+    lat_field: &str,
+    lon_field: &str,
+) -> crate::Result<GeoPointAccessor> {
+    let get_column = |field_name: &str| match get_ff_reader_and_validate(
+        reader,
+        field_name,
+        Cardinality::SingleValue,
+    )? {
+        (FastFieldAccessor::Single(column), Type::F64) => Ok(column),
+        (_, field_type) => Err(TantivyError::InvalidArgument(format!(
+            "geo aggregations require f64 fast fields for the coordinates, but {} is of type \
+             {:?}",
+            field_name, field_type
+        ))),
+    };
+    Ok(GeoPointAccessor {
+        lat: get_column(lat_field)?,
+        lon: get_column(lon_field)?,
+    })
+}
+
 /// Get fast field reader with given cardinatility.
 fn get_ff_reader_and_validate(
     reader: &SegmentReader,
@@ -200,6 +414,11 @@ fn get_ff_reader_and_validate(
                 "Unsupported field type date in aggregation".to_string(),
             ));
         }
+        if ff_type == FastType::GeoPoint {
+            return Err(TantivyError::InvalidArgument(
+                "Unsupported field type geo point in aggregation".to_string(),
+            ));
+        }
 
         if cardinality != field_cardinality {
             return Err(TantivyError::InvalidArgument(format!(
@@ -224,3 +443,193 @@ fn get_ff_reader_and_validate(
             .map(|field| (FastFieldAccessor::Multi(field), field_type.value_type())),
     }
 }
+
+/// Get the fast field reader for a terms aggregation.
+///
+/// Text fields and `u64`, `i64`, `f64` fields are supported with any cardinality.
+fn get_terms_ff_reader(
+    reader: &SegmentReader,
+    field_name: &str,
+) -> crate::Result<(FastFieldAccessor, Type)> {
+    let field = reader
+        .schema()
+        .get_field(field_name)
+        .ok_or_else(|| TantivyError::FieldNotFound(field_name.to_string()))?;
+    let field_type = reader.schema().get_field_entry(field).field_type();
+
+    match (field_type.value_type(), type_and_cardinality(field_type)) {
+        (Type::Str | Type::U64 | Type::I64 | Type::F64, Some((_, cardinality))) => {
+            get_ff_reader_and_validate(reader, field_name, cardinality)
+        }
+        _ => Err(TantivyError::InvalidArgument(format!(
+            "terms aggregation requires a text or numeric fast field, but {} is of type {:?}",
+            field_name,
+            field_type.value_type()
+        ))),
+    }
+}
+
+/// Get the values of `field_name` if it is a path inside a json field.
+///
+/// Returns `None` if `field_name` is a field of the schema.
+fn get_json_path_values(
+    reader: &SegmentReader,
+    field_name: &str,
+    bucket_count: &BucketCount,
+) -> crate::Result<Option<JsonPathValues>> {
+    let (field, json_path) = split_json_path(reader.schema(), field_name)
+        .ok_or_else(|| TantivyError::FieldNotFound(field_name.to_string()))?;
+    let field_type = reader.schema().get_field_entry(field).field_type();
+    if field_type.value_type() == Type::Json {
+        return JsonPathValues::open(reader, field, json_path, bucket_count).map(Some);
+    }
+    if !json_path.is_empty() {
+        return Err(TantivyError::FieldNotFound(field_name.to_string()));
+    }
+    Ok(None)
+}
+
+/// Get the values of a single valued `u64`, `i64` or `f64` fast field, or the numeric values of
+/// a path inside a json field.
+///
+/// Json numbers are aggregated as `f64`, and a document has several values if the path is
+/// contained in an array.
+fn get_numeric_ff_reader(
+    reader: &SegmentReader,
+    field_name: &str,
+    bucket_count: &BucketCount,
+) -> crate::Result<(FastFieldAccessor, Type)> {
+    if let Some(json_path_values) = get_json_path_values(reader, field_name, bucket_count)? {
+        return Ok((
+            FastFieldAccessor::Multi(json_path_values.numeric_values(bucket_count)?),
+            Type::F64,
+        ));
+    }
+    get_ff_reader_and_validate(reader, field_name, Cardinality::SingleValue)
+}
+
+/// Get the values of a text or numeric field, or of a path inside a json field.
+///
+/// Fast fields of any cardinality are supported.
+fn get_values_accessor(
+    reader: &SegmentReader,
+    field_name: &str,
+    bucket_count: &BucketCount,
+) -> crate::Result<ValuesAccessor> {
+    if let Some(json_path_values) = get_json_path_values(reader, field_name, bucket_count)? {
+        return Ok(ValuesAccessor {
+            accessor: FastFieldAccessor::Multi(json_path_values.ordinals),
+            field_type: Type::Json,
+            inverted_index: None,
+            json_path_keys: json_path_values.keys,
+        });
+    }
+    let (accessor, field_type) = get_terms_ff_reader(reader, field_name)?;
+    let mut values = ValuesAccessor::from_fast_field(accessor, field_type);
+    if field_type == Type::Str {
+        let field = reader
+            .schema()
+            .get_field(field_name)
+            .expect("field was resolved");
+        values.inverted_index = Some(reader.inverted_index(field)?);
+    }
+    Ok(values)
+}
+
+/// Get the values of a `u64`, `i64` or `f64` fast field of any cardinality, or the numeric values
+/// of a path inside a json field.
+fn get_numeric_values_accessor(
+    reader: &SegmentReader,
+    field_name: &str,
+    bucket_count: &BucketCount,
+) -> crate::Result<ValuesAccessor> {
+    if let Some(json_path_values) = get_json_path_values(reader, field_name, bucket_count)? {
+        return Ok(ValuesAccessor::from_fast_field(
+            FastFieldAccessor::Multi(json_path_values.numeric_values(bucket_count)?),
+            Type::F64,
+        ));
+    }
+    let values = get_values_accessor(reader, field_name, bucket_count)?;
+    match values.field_type {
+        Type::U64 | Type::I64 | Type::F64 => Ok(values),
+        field_type => Err(TantivyError::InvalidArgument(format!(
+            "histogram requires a numeric fast field, but {} is of type {:?}",
+            field_name, field_type
+        ))),
+    }
+}
+
+/// Get the fast field reader of a single valued date field.
+fn get_date_ff_reader(
+    reader: &SegmentReader,
+    field_name: &str,
+) -> crate::Result<(FastFieldAccessor, Type)> {
+    let field = reader
+        .schema()
+        .get_field(field_name)
+        .ok_or_else(|| TantivyError::FieldNotFound(field_name.to_string()))?;
+    let field_type = reader.schema().get_field_entry(field).field_type();
+
+    match type_and_cardinality(field_type) {
+        Some((FastType::Date, Cardinality::SingleValue)) => {}
+        Some((FastType::Date, Cardinality::MultiValues)) => {
+            return Err(TantivyError::InvalidArgument(format!(
+                "Invalid field cardinality on field {} expected {:?}, but got {:?}",
+                field_name,
+                Cardinality::SingleValue,
+                Cardinality::MultiValues
+            )));
+        }
+        _ => {
+            return Err(TantivyError::InvalidArgument(format!(
+                "date_histogram requires a date fast field, but {} is of type {:?}",
+                field_name,
+                field_type.value_type()
+            )));
+        }
+    }
+
+    let accessor = reader.fast_fields().u64_lenient(field)?;
+    Ok((FastFieldAccessor::Single(accessor), field_type.value_type()))
+}
+
+/// Get fast field reader for any fast field type and cardinality.
+///
+/// The values are returned in their `u64` (or `u128` for ip addresses) fast field representation.
+/// For text and facet fields, the inverted index is loaded as well, so that the term ordinals
+/// stored in the fast field can be resolved.
+fn get_any_ff_reader(
+    reader: &SegmentReader,
+    field_name: &str,
+) -> crate::Result<(FastFieldAccessor, Type, Option<Arc<InvertedIndexReader>>)> {
+    let field = reader
+        .schema()
+        .get_field(field_name)
+        .ok_or_else(|| TantivyError::FieldNotFound(field_name.to_string()))?;
+    let field_type = reader.schema().get_field_entry(field).field_type();
+
+    let (ff_type, cardinality) = type_and_cardinality(field_type).ok_or_else(|| {
+        TantivyError::InvalidArgument(format!(
+            "Field {} is not a fast field, but of type {:?}",
+            field_name,
+            field_type.value_type()
+        ))
+    })?;
+
+    let ff_fields = reader.fast_fields();
+    let accessor = match (ff_type, cardinality) {
+        (FastType::U128, Cardinality::SingleValue) => {
+            FastFieldAccessor::SingleU128(ff_fields.u128(field)?)
+        }
+        (FastType::U128, Cardinality::MultiValues) => {
+            FastFieldAccessor::MultiU128(ff_fields.u128s(field)?)
+        }
+        (_, Cardinality::SingleValue) => FastFieldAccessor::Single(ff_fields.u64_lenient(field)?),
+        (_, Cardinality::MultiValues) => FastFieldAccessor::Multi(ff_fields.u64s_lenient(field)?),
+    };
+    let inverted_index = match field_type.value_type() {
+        Type::Str | Type::Facet => Some(reader.inverted_index(field)?),
+        _ => None,
+    };
+    Ok((accessor, field_type.value_type(), inverted_index))
+}
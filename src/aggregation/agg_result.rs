@@ -4,13 +4,20 @@
 //! intermediate average results, which is the sum and the number of values. The actual average is
 //! calculated on the step from intermediate to final aggregation result tree.
 
+use std::collections::HashMap;
+
 use rustc_hash::FxHashMap;
 use serde::{Deserialize, Serialize};
 
 use super::agg_req::BucketAggregationInternal;
 use super::bucket::GetDocCount;
-use super::intermediate_agg_result::{IntermediateBucketResult, IntermediateMetricResult};
-use super::metric::{SingleMetricResult, Stats};
+use super::intermediate_agg_result::IntermediateBucketResult;
+use super::metric::{
+    GeoBoundsMetricResult, GeoCentroidMetricResult, PercentilesMetricResult, SingleMetricResult,
+    Stats, TopHitsMetricResult, ValueCountMetricResult,
+};
+use super::pipeline::BucketMetricValueResult;
+use super::segment_agg_result::BucketCount;
 use super::Key;
 use crate::TantivyError;
 
@@ -38,12 +45,14 @@ impl AggregationResults {
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 #[serde(untagged)]
-/// An aggregation is either a bucket or a metric.
+/// An aggregation is either a bucket, a metric or a pipeline.
 pub enum AggregationResult {
     /// Bucket result variant.
     BucketResult(BucketResult),
     /// Metric result variant.
     MetricResult(MetricResult),
+    /// Pipeline result variant.
+    PipelineResult(PipelineResult),
 }
 
 impl AggregationResult {
@@ -59,6 +68,7 @@ impl AggregationResult {
                     .to_string(),
             )),
             AggregationResult::MetricResult(metric) => metric.get_value(agg_property),
+            AggregationResult::PipelineResult(pipeline) => Ok(pipeline.get_value()),
         }
     }
 }
@@ -71,6 +81,26 @@ pub enum MetricResult {
     Average(SingleMetricResult),
     /// Stats metric result.
     Stats(Stats),
+    /// Cardinality metric result.
+    Cardinality(SingleMetricResult),
+    /// Min metric result.
+    Min(SingleMetricResult),
+    /// Max metric result.
+    Max(SingleMetricResult),
+    /// Sum metric result.
+    Sum(SingleMetricResult),
+    /// Value count metric result.
+    ValueCount(ValueCountMetricResult),
+    /// Percentiles metric result.
+    Percentiles(PercentilesMetricResult),
+    /// Percentile ranks metric result.
+    PercentileRanks(PercentilesMetricResult),
+    /// Top hits metric result.
+    TopHits(TopHitsMetricResult),
+    /// Geo bounds metric result.
+    GeoBounds(GeoBoundsMetricResult),
+    /// Geo centroid metric result.
+    GeoCentroid(GeoCentroidMetricResult),
 }
 
 impl MetricResult {
@@ -78,18 +108,56 @@ impl MetricResult {
         match self {
             MetricResult::Average(avg) => Ok(avg.value),
             MetricResult::Stats(stats) => stats.get_value(agg_property),
+            MetricResult::Cardinality(cardinality) => Ok(cardinality.value),
+            MetricResult::Min(min) => Ok(min.value),
+            MetricResult::Max(max) => Ok(max.value),
+            MetricResult::Sum(sum) => Ok(sum.value),
+            MetricResult::ValueCount(value_count) => Ok(Some(value_count.value as f64)),
+            MetricResult::Percentiles(percentiles) | MetricResult::PercentileRanks(percentiles) => {
+                percentiles.get_value(agg_property)
+            }
+            MetricResult::TopHits(_) => Err(TantivyError::InvalidArgument(
+                "top_hits aggregation can't be used to order".to_string(),
+            )),
+            MetricResult::GeoBounds(_) => Err(TantivyError::InvalidArgument(
+                "geo_bounds aggregation can't be used to order".to_string(),
+            )),
+            MetricResult::GeoCentroid(geo_centroid) => match agg_property {
+                "count" => Ok(Some(geo_centroid.count as f64)),
+                _ => Err(TantivyError::InvalidArgument(format!(
+                    "unknown property {} of geo_centroid aggregation, only count can be used to \
+                     order",
+                    agg_property
+                ))),
+            },
         }
     }
 }
-impl From<IntermediateMetricResult> for MetricResult {
-    fn from(metric: IntermediateMetricResult) -> Self {
-        match metric {
-            IntermediateMetricResult::Average(avg_data) => {
-                MetricResult::Average(avg_data.finalize().into())
-            }
-            IntermediateMetricResult::Stats(intermediate_stats) => {
-                MetricResult::Stats(intermediate_stats.finalize())
-            }
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+/// PipelineResult
+pub enum PipelineResult {
+    /// Derivative pipeline result.
+    Derivative(SingleMetricResult),
+    /// Cumulative sum pipeline result.
+    CumulativeSum(SingleMetricResult),
+    /// Moving average pipeline result.
+    MovingAvg(SingleMetricResult),
+    /// Average bucket pipeline result.
+    AvgBucket(SingleMetricResult),
+    /// Max bucket pipeline result.
+    MaxBucket(BucketMetricValueResult),
+}
+
+impl PipelineResult {
+    fn get_value(&self) -> Option<f64> {
+        match self {
+            PipelineResult::Derivative(derivative) => derivative.value,
+            PipelineResult::CumulativeSum(cumulative_sum) => cumulative_sum.value,
+            PipelineResult::MovingAvg(moving_avg) => moving_avg.value,
+            PipelineResult::AvgBucket(avg_bucket) => avg_bucket.value,
+            PipelineResult::MaxBucket(max_bucket) => max_bucket.value,
         }
     }
 }
@@ -126,12 +194,54 @@ pub enum BucketResult {
         /// The upper bound error for the doc count of each term.
         doc_count_error_upper_bound: Option<u64>,
     },
+    /// This is the significant terms result, with the buckets sorted by their score.
+    SignificantTerms {
+        /// The number of documents in the foreground set.
+        doc_count: u64,
+        /// The number of documents in the background set.
+        bg_count: u64,
+        /// The buckets.
+        ///
+        /// See [`SignificantTermsAggregation`](super::bucket::SignificantTermsAggregation)
+        buckets: Vec<SignificantTermBucketEntry>,
+    },
+    /// This is the geohash or geotile grid result, with the buckets sorted by their doc count.
+    GeoGrid {
+        /// The buckets, keyed by the geohashes or `zoom/x/y` of the tiles.
+        ///
+        /// See [`GeohashGridAggregation`](super::bucket::GeohashGridAggregation)
+        buckets: Vec<BucketEntry>,
+    },
+    /// This is the composite result, with the buckets sorted by their compound keys.
+    Composite {
+        /// The key of the last bucket, to request the next buckets with.
+        ///
+        /// See [`CompositeAggregation`](super::bucket::CompositeAggregation)
+        #[serde(skip_serializing_if = "Option::is_none", default)]
+        after_key: Option<HashMap<String, Key>>,
+        /// The buckets in the order of their keys.
+        buckets: Vec<CompositeBucketEntry>,
+    },
+    /// This is the filters result, with one bucket per filter.
+    Filters {
+        /// The buckets, keyed by the filter keys or in the order of the filters.
+        ///
+        /// See [`FiltersAggregation`](super::bucket::FiltersAggregation)
+        buckets: BucketEntries<FilterBucketEntry>,
+    },
+    /// This is the filter result, which contains a count, and optionally sub_aggregations.
+    ///
+    /// See [`FilterAggregation`](super::bucket::FilterAggregation)
+    Filter(FilterBucketEntry),
 }
 
 impl BucketResult {
-    pub(crate) fn empty_from_req(req: &BucketAggregationInternal) -> crate::Result<Self> {
+    pub(crate) fn empty_from_req(
+        req: &BucketAggregationInternal,
+        bucket_count: &BucketCount,
+    ) -> crate::Result<Self> {
         let empty_bucket = IntermediateBucketResult::empty_from_req(&req.bucket_agg);
-        empty_bucket.into_final_bucket_result(req)
+        empty_bucket.into_final_bucket_result(req, bucket_count)
     }
 }
 
@@ -176,6 +286,10 @@ pub enum BucketEntries<T> {
 pub struct BucketEntry {
     /// The identifier of the bucket.
     pub key: Key,
+    /// The identifier of the bucket formatted as string, e.g. the RFC3339 date of a date
+    /// histogram bucket.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub key_as_string: Option<String>,
     /// Number of documents in the bucket.
     pub doc_count: u64,
     #[serde(flatten)]
@@ -239,3 +353,103 @@ pub struct RangeBucketEntry {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub to: Option<f64>,
 }
+
+/// This is the entry of a composite bucket, which contains a compound key, count, and optionally
+/// sub_aggregations.
+///
+/// # JSON Format
+/// ```json
+/// {
+///   ...
+///     "my_composite": {
+///       "after_key": { "product": "socks", "size": 42.0 },
+///       "buckets": [
+///         {
+///           "key": { "product": "shoes", "size": 42.0 },
+///           "doc_count": 5
+///         },
+///         {
+///           "key": { "product": "socks", "size": 42.0 },
+///           "doc_count": 2
+///         }
+///       ]
+///    }
+///    ...
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CompositeBucketEntry {
+    /// The values of the sources, keyed by the source names.
+    pub key: HashMap<String, Key>,
+    /// Number of documents in the bucket.
+    pub doc_count: u64,
+    #[serde(flatten)]
+    /// Sub-aggregations in this bucket.
+    pub sub_aggregation: AggregationResults,
+}
+
+/// This is the entry of a significant terms bucket, which contains a key, count, score, the
+/// count in the background set, and optionally sub_aggregations.
+///
+/// # JSON Format
+/// ```json
+/// {
+///   ...
+///     "unusual_errors": {
+///       "doc_count": 120,
+///       "bg_count": 52000,
+///       "buckets": [
+///         {
+///           "key": "E503",
+///           "doc_count": 48,
+///           "score": 6.91,
+///           "bg_count": 310
+///         }
+///       ]
+///    }
+///    ...
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SignificantTermBucketEntry {
+    /// The term of the bucket.
+    pub key: Key,
+    /// Number of foreground documents in the bucket.
+    pub doc_count: u64,
+    /// The significance score of the term.
+    pub score: f64,
+    /// Number of background documents containing the term.
+    pub bg_count: u64,
+    #[serde(flatten)]
+    /// Sub-aggregations in this bucket.
+    pub sub_aggregation: AggregationResults,
+}
+
+/// This is the entry of a filter bucket, which contains a count, and optionally
+/// sub_aggregations.
+///
+/// # JSON Format
+/// ```json
+/// {
+///   ...
+///     "my_filters": {
+///       "buckets": {
+///         "errors": {
+///           "doc_count": 5
+///         },
+///         "warnings": {
+///           "doc_count": 2
+///         }
+///       }
+///    }
+///    ...
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FilterBucketEntry {
+    /// Number of documents in the bucket.
+    pub doc_count: u64,
+    #[serde(flatten)]
+    /// Sub-aggregations in this bucket.
+    pub sub_aggregation: AggregationResults,
+}
@@ -0,0 +1,1024 @@
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Debug;
+
+use fastfield_codecs::MonotonicallyMappableToU64;
+use itertools::Itertools;
+use rustc_hash::FxHashMap;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    get_bucket_val, get_distinct_vals, CalendarInterval, DateHistogramAggregation,
+    DateHistogramRounding, Order,
+};
+use crate::aggregation::agg_req_with_accessor::{BucketAggregationWithAccessor, ValuesAccessor};
+use crate::aggregation::intermediate_agg_result::{
+    IntermediateBucketResult, IntermediateCompositeBucketEntry, IntermediateCompositeBucketResult,
+};
+use crate::aggregation::segment_agg_result::{BucketCount, SegmentAggregationResultsCollector};
+use crate::aggregation::{f64_from_fastfield_u64, IntermediateKey, Key};
+use crate::error::DataCorruption;
+use crate::schema::Type;
+use crate::{DocId, TantivyError};
+
+/// Creates a bucket for every combination of the values of multiple sources and returns the
+/// buckets sorted by their compound key.
+///
+/// In contrast to the other multi bucket aggregations, all buckets can be retrieved efficiently by
+/// paging through them. Every response contains the `after_key`, which is the key of the last
+/// returned bucket. Passing it as [after](CompositeAggregation::after) returns the next
+/// [size](CompositeAggregation::size) buckets. When no buckets are returned anymore, all buckets
+/// have been retrieved.
+///
+/// The sources are:
+/// - `terms`: The values of a text or numeric field, or of a path inside a json field, like in the
+///   [TermsAggregation](super::TermsAggregation).
+/// - `histogram`: The values of a numeric field rounded down to a multiple of `interval`, like in
+///   the [HistogramAggregation](super::HistogramAggregation).
+/// - `date_histogram`: The values of a date field rounded down to a calendar or fixed interval,
+///   like in the [DateHistogramAggregation](super::DateHistogramAggregation). The key is the start
+///   of the bucket in milliseconds since the unix epoch.
+///
+/// Buckets are sorted by the value of the first source, then by the value of the second source
+/// etc. Each source can be sorted `asc` (default) or `desc`. A document with multiple values
+/// for a source is put into a bucket for each combination of its values. Documents without a
+/// value for a source are not put into any bucket.
+///
+/// # Result
+/// Result type is [`BucketResult`](crate::aggregation::agg_result::BucketResult) with
+/// [`CompositeBucketEntry`](crate::aggregation::agg_result::CompositeBucketEntry) on the
+/// `AggregationCollector`.
+///
+/// Result type is
+/// [`IntermediateBucketResult`](crate::aggregation::intermediate_agg_result::IntermediateBucketResult) with
+/// [`IntermediateCompositeBucketEntry`](crate::aggregation::intermediate_agg_result::IntermediateCompositeBucketEntry) on the
+/// `DistributedAggregationCollector`.
+///
+/// # Limitations/Compatibility
+/// `missing_bucket` and `format` are not supported.
+///
+/// # JSON Format
+/// ```json
+/// {
+///     "sales_by_product": {
+///         "composite": {
+///             "size": 2,
+///             "sources": [
+///                 { "date": { "date_histogram": { "field": "timestamp", "calendar_interval": "day" } } },
+///                 { "product": { "terms": { "field": "product" } } }
+///             ]
+///         }
+///     }
+/// }
+/// ```
+///
+/// Response
+/// ```json
+/// {
+///     "sales_by_product": {
+///         "after_key": { "date": 1577923200000.0, "product": "shoes" },
+///         "buckets": [
+///             { "key": { "date": 1577836800000.0, "product": "shoes" }, "doc_count": 3 },
+///             { "key": { "date": 1577923200000.0, "product": "shoes" }, "doc_count": 1 }
+///         ]
+///     }
+/// }
+/// ```
+///
+/// The next page is requested with `"after": { "date": 1577923200000.0, "product": "shoes" }`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CompositeAggregation {
+    /// The sources of the compound keys, in the order they are sorted by.
+    pub sources: Vec<NamedCompositeSource>,
+    /// The number of buckets to return. Defaults to 10.
+    #[serde(default = "default_size")]
+    pub size: u32,
+    /// Only buckets with a key after this key are returned. Has to contain a value for every
+    /// source.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub after: Option<HashMap<String, Key>>,
+}
+
+fn default_size() -> u32 {
+    10
+}
+
+impl CompositeAggregation {
+    pub(crate) fn validate(&self) -> crate::Result<()> {
+        if self.sources.is_empty() {
+            return Err(TantivyError::InvalidArgument(
+                "composite aggregation requires at least one source".to_string(),
+            ));
+        }
+        if self.size == 0 {
+            return Err(TantivyError::InvalidArgument(
+                "size of composite aggregation must be greater than 0".to_string(),
+            ));
+        }
+        let mut names = HashSet::new();
+        for named_source in &self.sources {
+            if !names.insert(named_source.name.as_str()) {
+                return Err(TantivyError::InvalidArgument(format!(
+                    "duplicate source name {} in composite aggregation",
+                    named_source.name
+                )));
+            }
+            match &named_source.source {
+                CompositeSource::Terms(_) => {}
+                CompositeSource::Histogram(histogram) => {
+                    if histogram.interval <= 0.0f64 || histogram.interval.is_nan() {
+                        return Err(TantivyError::InvalidArgument(
+                            "interval must be a positive value".to_string(),
+                        ));
+                    }
+                }
+                CompositeSource::DateHistogram(date_histogram) => {
+                    date_histogram.rounding()?;
+                }
+            }
+        }
+        if let Some(after) = &self.after {
+            if after.len() != self.sources.len()
+                || !names.iter().all(|name| after.contains_key(*name))
+            {
+                return Err(TantivyError::InvalidArgument(format!(
+                    "the after key of a composite aggregation requires a value for each of the \
+                     sources {}",
+                    self.sources.iter().map(|source| &source.name).join(", ")
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the values of the after key in source order.
+    fn after_values(&self) -> Option<Vec<IntermediateKey>> {
+        let after = self.after.as_ref()?;
+        Some(
+            self.sources
+                .iter()
+                .map(|named_source| after[&named_source.name].clone().into())
+                .collect(),
+        )
+    }
+
+    /// Compares two compound keys in the order of the buckets.
+    pub(crate) fn cmp_keys(&self, left: &[IntermediateKey], right: &[IntermediateKey]) -> Ordering {
+        cmp_compound_keys(
+            self.sources
+                .iter()
+                .map(|named_source| named_source.source.order()),
+            left,
+            right,
+        )
+    }
+}
+
+fn cmp_compound_keys<T: Ord>(
+    orders: impl Iterator<Item = Order>,
+    left: &[T],
+    right: &[T],
+) -> Ordering {
+    for ((order, left_val), right_val) in orders.zip(left).zip(right) {
+        let ordering = match order {
+            Order::Asc => left_val.cmp(right_val),
+            Order::Desc => right_val.cmp(left_val),
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    Ordering::Equal
+}
+
+/// A source of a [`CompositeAggregation`] with its name.
+///
+/// Serializes to a map with the name as the only key, e.g. `{ "product": { "terms": { "field":
+/// "product" } } }`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(
+    try_from = "HashMap<String, CompositeSource>",
+    into = "HashMap<String, CompositeSource>"
+)]
+pub struct NamedCompositeSource {
+    /// The name of the source, which identifies its value in the keys of the buckets.
+    pub name: String,
+    /// The source of the values.
+    pub source: CompositeSource,
+}
+
+impl TryFrom<HashMap<String, CompositeSource>> for NamedCompositeSource {
+    type Error = String;
+
+    fn try_from(named_source: HashMap<String, CompositeSource>) -> Result<Self, Self::Error> {
+        if named_source.len() != 1 {
+            return Err(format!(
+                "a composite source requires exactly one name, but got {}",
+                named_source.len()
+            ));
+        }
+        let (name, source) = named_source.into_iter().next().unwrap();
+        Ok(NamedCompositeSource { name, source })
+    }
+}
+
+impl From<NamedCompositeSource> for HashMap<String, CompositeSource> {
+    fn from(named_source: NamedCompositeSource) -> Self {
+        HashMap::from([(named_source.name, named_source.source)])
+    }
+}
+
+/// The source of the values of a [`CompositeAggregation`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum CompositeSource {
+    /// The values of a field.
+    #[serde(rename = "terms")]
+    Terms(TermsCompositeSource),
+    /// The values of a numeric field rounded to an interval.
+    #[serde(rename = "histogram")]
+    Histogram(HistogramCompositeSource),
+    /// The values of a date field rounded to a calendar or fixed interval.
+    #[serde(rename = "date_histogram")]
+    DateHistogram(DateHistogramCompositeSource),
+}
+
+impl CompositeSource {
+    /// Returns the field of the source.
+    pub fn field(&self) -> &str {
+        match self {
+            CompositeSource::Terms(terms) => &terms.field,
+            CompositeSource::Histogram(histogram) => &histogram.field,
+            CompositeSource::DateHistogram(date_histogram) => &date_histogram.field,
+        }
+    }
+
+    /// Returns the sort order of the values of the source.
+    pub fn order(&self) -> Order {
+        let order = match self {
+            CompositeSource::Terms(terms) => terms.order,
+            CompositeSource::Histogram(histogram) => histogram.order,
+            CompositeSource::DateHistogram(date_histogram) => date_histogram.order,
+        };
+        order.unwrap_or(Order::Asc)
+    }
+}
+
+/// The values of a text or numeric field, or of a path inside a json field.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct TermsCompositeSource {
+    /// The field to aggregate on.
+    pub field: String,
+    /// The sort order of the values. Defaults to `asc`.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub order: Option<Order>,
+}
+
+/// The values of a numeric field, rounded down to a multiple of `interval`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct HistogramCompositeSource {
+    /// The field to aggregate on.
+    pub field: String,
+    /// The interval of the buckets, has to be positive.
+    pub interval: f64,
+    /// The sort order of the values. Defaults to `asc`.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub order: Option<Order>,
+}
+
+/// The values of a date field, rounded down to a calendar or fixed interval.
+///
+/// See [`DateHistogramAggregation`] for the parameters.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct DateHistogramCompositeSource {
+    /// The field to aggregate on. Has to be a date fast field.
+    pub field: String,
+    /// Calendar-aware interval of the buckets.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub calendar_interval: Option<CalendarInterval>,
+    /// Fixed interval of the buckets, e.g. `30s` or `12h`.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub fixed_interval: Option<String>,
+    /// The time zone in which the buckets are computed. Defaults to `UTC`.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub time_zone: Option<String>,
+    /// Shifts the start of the buckets by the given duration.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub offset: Option<String>,
+    /// The sort order of the values. Defaults to `asc`.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub order: Option<Order>,
+}
+
+impl DateHistogramCompositeSource {
+    fn rounding(&self) -> crate::Result<DateHistogramRounding> {
+        DateHistogramAggregation {
+            field: self.field.clone(),
+            calendar_interval: self.calendar_interval,
+            fixed_interval: self.fixed_interval.clone(),
+            time_zone: self.time_zone.clone(),
+            offset: self.offset.clone(),
+            ..Default::default()
+        }
+        .rounding()
+    }
+}
+
+/// The position of a value of the after key among the values of a source in a segment.
+#[derive(Clone, Debug, PartialEq)]
+enum AfterValue {
+    /// The after value is the value with the ordinal, or if it is not `exact`, it lies right
+    /// before it.
+    Ordinal { ordinal: u64, exact: bool },
+    /// A numeric after value.
+    F64(f64),
+    /// The after value is before all values of the source.
+    Min,
+}
+
+/// How the values of a source are computed from the values of the field.
+#[derive(Clone, Debug, PartialEq)]
+enum SourceValues {
+    /// The values of text and json fields are ordinals, numeric values are fast field values.
+    Terms { field_type: Type },
+    /// The `f64` values of the bucket keys, mapped to `u64`.
+    Histogram { field_type: Type, interval: f64 },
+    /// The bucket keys in milliseconds, mapped to `u64`.
+    DateHistogram {
+        rounding: DateHistogramRounding,
+        /// The time range `[start, end)` of the last computed bucket, to avoid rounding close
+        /// dates over and over again.
+        last_bucket_range: Option<(i64, i64)>,
+    },
+}
+
+impl SourceValues {
+    /// Writes the distinct values of the document into `vals` in ascending order.
+    fn get_vals(
+        &mut self,
+        accessor: &ValuesAccessor,
+        doc: DocId,
+        vals: &mut Vec<u64>,
+    ) -> crate::Result<()> {
+        get_distinct_vals(&accessor.accessor, doc, vals);
+        match self {
+            SourceValues::Terms { .. } => {}
+            SourceValues::Histogram {
+                field_type,
+                interval,
+            } => {
+                for val in vals.iter_mut() {
+                    let bucket_val =
+                        get_bucket_val(f64_from_fastfield_u64(*val, field_type), *interval, 0.0);
+                    *val = bucket_val.to_u64();
+                }
+                vals.dedup();
+            }
+            SourceValues::DateHistogram {
+                rounding,
+                last_bucket_range,
+            } => {
+                for val in vals.iter_mut() {
+                    let millis = i64::from_u64(*val).div_euclid(1_000);
+                    let key = match *last_bucket_range {
+                        Some((start, end)) if start <= millis && millis < end => start,
+                        _ => {
+                            let key = rounding.round(millis)?;
+                            *last_bucket_range = Some((key, rounding.next_key(key)?));
+                            key
+                        }
+                    };
+                    *val = key.to_u64();
+                }
+                vals.dedup();
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the numeric value of a value, which is not an ordinal.
+    fn to_f64(&self, val: u64) -> f64 {
+        match self {
+            SourceValues::Terms { field_type } => f64_from_fastfield_u64(val, field_type),
+            SourceValues::Histogram { .. } => f64::from_u64(val),
+            SourceValues::DateHistogram { .. } => i64::from_u64(val) as f64,
+        }
+    }
+
+    fn to_key(&self, val: u64, accessor: &ValuesAccessor) -> crate::Result<IntermediateKey> {
+        match self {
+            SourceValues::Terms {
+                field_type: Type::Str,
+            } => {
+                let inverted_index = accessor
+                    .inverted_index
+                    .as_ref()
+                    .expect("internal error: inverted index not loaded for composite source");
+                let mut buffer = Vec::new();
+                inverted_index
+                    .terms()
+                    .ord_to_term(val, &mut buffer)
+                    .expect("could not find term");
+                let text = String::from_utf8(buffer)
+                    .map_err(|utf8_err| DataCorruption::comment_only(utf8_err.to_string()))?;
+                Ok(IntermediateKey::Str(text))
+            }
+            SourceValues::Terms {
+                field_type: Type::Json,
+            } => Ok(accessor.json_path_keys[val as usize].clone()),
+            _ => Ok(IntermediateKey::F64(self.to_f64(val))),
+        }
+    }
+
+    fn after_value(
+        &self,
+        after: &IntermediateKey,
+        accessor: &ValuesAccessor,
+    ) -> crate::Result<AfterValue> {
+        Ok(match self {
+            SourceValues::Terms {
+                field_type: Type::Str,
+            } => {
+                let term_dict = accessor
+                    .inverted_index
+                    .as_ref()
+                    .expect("internal error: inverted index not loaded for composite source")
+                    .terms();
+                match after {
+                    IntermediateKey::Str(text) => {
+                        let mut stream = term_dict.range().ge(text.as_bytes()).into_stream()?;
+                        if stream.advance() {
+                            AfterValue::Ordinal {
+                                ordinal: stream.term_ord(),
+                                exact: stream.key() == text.as_bytes(),
+                            }
+                        } else {
+                            AfterValue::Ordinal {
+                                ordinal: term_dict.num_terms() as u64,
+                                exact: false,
+                            }
+                        }
+                    }
+                    // Numeric keys are sorted after text keys.
+                    IntermediateKey::F64(_) => AfterValue::Ordinal {
+                        ordinal: term_dict.num_terms() as u64,
+                        exact: false,
+                    },
+                }
+            }
+            SourceValues::Terms {
+                field_type: Type::Json,
+            } => match accessor.json_path_keys.binary_search(after) {
+                Ok(ordinal) => AfterValue::Ordinal {
+                    ordinal: ordinal as u64,
+                    exact: true,
+                },
+                Err(ordinal) => AfterValue::Ordinal {
+                    ordinal: ordinal as u64,
+                    exact: false,
+                },
+            },
+            _ => match after {
+                IntermediateKey::Str(_) => AfterValue::Min,
+                IntermediateKey::F64(val) => AfterValue::F64(*val),
+            },
+        })
+    }
+
+    /// Compares a value with the after value in ascending order.
+    fn cmp_to_after(&self, val: u64, after: &AfterValue) -> Ordering {
+        match after {
+            AfterValue::Ordinal { ordinal, exact } => match val.cmp(ordinal) {
+                Ordering::Equal if !exact => Ordering::Greater,
+                ordering => ordering,
+            },
+            AfterValue::F64(after_val) => self.to_f64(val).total_cmp(after_val),
+            AfterValue::Min => Ordering::Greater,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct SegmentCompositeSource {
+    values: SourceValues,
+    order: Order,
+    /// Only set if the request has an after key.
+    after: Option<AfterValue>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct SegmentCompositeBucketEntry {
+    doc_count: u64,
+    sub_aggregation: Option<SegmentAggregationResultsCollector>,
+}
+
+/// The collector creates a bucket for every compound key of the segment after the after key.
+///
+/// The values of the compound keys are ordinals or fast field values, whose `u64` order is the
+/// order of the values. Since only the first `size` buckets are required, the buckets after
+/// them are discarded regularly.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SegmentCompositeCollector {
+    buckets: FxHashMap<Vec<u64>, SegmentCompositeBucketEntry>,
+    sources: Vec<SegmentCompositeSource>,
+    size: usize,
+    /// Set when buckets have been discarded. Keys from this key on can't be among the first
+    /// `size` keys of the segment.
+    upper_bound: Option<Vec<u64>>,
+    blueprint: Option<SegmentAggregationResultsCollector>,
+}
+
+impl SegmentCompositeCollector {
+    /// Returns the estimated memory in bytes of the collector before it collects any document.
+    pub(crate) fn memory_estimate(&self) -> u64 {
+        (std::mem::size_of::<Self>()
+            + self.sources.len() * std::mem::size_of::<SegmentCompositeSource>()) as u64
+            + self
+                .blueprint
+                .as_ref()
+                .map_or(0, |blueprint| blueprint.memory_estimate())
+    }
+
+    pub(crate) fn from_req_and_validate(
+        req: &CompositeAggregation,
+        bucket_with_accessor: &BucketAggregationWithAccessor,
+    ) -> crate::Result<Self> {
+        req.validate()?;
+        let after_values = req.after_values();
+        let sources = req
+            .sources
+            .iter()
+            .zip(&bucket_with_accessor.composite_sources)
+            .enumerate()
+            .map(|(source_ord, (named_source, accessor))| {
+                let values = match &named_source.source {
+                    CompositeSource::Terms(_) => SourceValues::Terms {
+                        field_type: accessor.field_type,
+                    },
+                    CompositeSource::Histogram(histogram) => SourceValues::Histogram {
+                        field_type: accessor.field_type,
+                        interval: histogram.interval,
+                    },
+                    CompositeSource::DateHistogram(date_histogram) => SourceValues::DateHistogram {
+                        rounding: date_histogram.rounding()?,
+                        last_bucket_range: None,
+                    },
+                };
+                let after = after_values
+                    .as_ref()
+                    .map(|after_values| values.after_value(&after_values[source_ord], accessor))
+                    .transpose()?;
+                Ok(SegmentCompositeSource {
+                    values,
+                    order: named_source.source.order(),
+                    after,
+                })
+            })
+            .collect::<crate::Result<_>>()?;
+        let sub_aggregation = &bucket_with_accessor.sub_aggregation;
+        let blueprint = if sub_aggregation.is_empty() {
+            None
+        } else {
+            Some(SegmentAggregationResultsCollector::from_req_and_validate(
+                sub_aggregation,
+            )?)
+        };
+        Ok(SegmentCompositeCollector {
+            buckets: Default::default(),
+            sources,
+            size: req.size as usize,
+            upper_bound: None,
+            blueprint,
+        })
+    }
+
+    fn cmp_keys(&self, left: &[u64], right: &[u64]) -> Ordering {
+        cmp_compound_keys(self.sources.iter().map(|source| source.order), left, right)
+    }
+
+    /// Returns true if the key is after the after key and before the upper bound.
+    fn is_in_range(&self, key: &[u64]) -> bool {
+        if let Some(upper_bound) = &self.upper_bound {
+            if self.cmp_keys(key, upper_bound) != Ordering::Less {
+                return false;
+            }
+        }
+        for (source, &val) in self.sources.iter().zip(key) {
+            let after = match &source.after {
+                Some(after) => after,
+                None => return true,
+            };
+            let ordering = match source.order {
+                Order::Asc => source.values.cmp_to_after(val, after),
+                Order::Desc => source.values.cmp_to_after(val, after).reverse(),
+            };
+            if ordering != Ordering::Equal {
+                return ordering == Ordering::Greater;
+            }
+        }
+        // The key is the after key.
+        false
+    }
+
+    /// The estimated memory of a bucket in the bucket map, including its compound key and its
+    /// sub aggregations.
+    fn bucket_memory(&self) -> u64 {
+        (std::mem::size_of::<(Vec<u64>, SegmentCompositeBucketEntry)>()
+            + self.sources.len() * std::mem::size_of::<u64>()) as u64
+            + self
+                .blueprint
+                .as_ref()
+                .map_or(0, |blueprint| blueprint.memory_estimate())
+    }
+
+    /// Discards all buckets except the first `size` buckets.
+    fn discard_buckets(&mut self, bucket_count: &BucketCount) {
+        let mut keys: Vec<Vec<u64>> = self.buckets.keys().cloned().collect();
+        keys.select_nth_unstable_by(self.size, |left, right| self.cmp_keys(left, right));
+        let discarded_keys = keys.split_off(self.size);
+        for key in &discarded_keys {
+            self.buckets.remove(key);
+        }
+        bucket_count.remove_count(discarded_keys.len() as u32);
+        bucket_count.remove_memory_consumed(discarded_keys.len() as u64 * self.bucket_memory());
+        self.upper_bound = discarded_keys.into_iter().next();
+    }
+
+    pub fn into_intermediate_bucket_result(
+        self,
+        agg_with_accessor: &BucketAggregationWithAccessor,
+    ) -> crate::Result<IntermediateBucketResult> {
+        let mut buckets = self.buckets.into_iter().collect_vec();
+        buckets.sort_unstable_by(|(left, _), (right, _)| {
+            cmp_compound_keys(self.sources.iter().map(|source| source.order), left, right)
+        });
+        buckets.truncate(self.size);
+        let buckets = buckets
+            .into_iter()
+            .map(|(key, bucket)| {
+                let key = key
+                    .into_iter()
+                    .zip(&self.sources)
+                    .zip(&agg_with_accessor.composite_sources)
+                    .map(|((val, source), accessor)| source.values.to_key(val, accessor))
+                    .collect::<crate::Result<_>>()?;
+                let sub_aggregation = match bucket.sub_aggregation {
+                    Some(sub_aggregation) => sub_aggregation
+                        .into_intermediate_aggregations_result(
+                            &agg_with_accessor.sub_aggregation,
+                        )?,
+                    None => Default::default(),
+                };
+                Ok(IntermediateCompositeBucketEntry {
+                    key,
+                    doc_count: bucket.doc_count,
+                    sub_aggregation,
+                })
+            })
+            .collect::<crate::Result<_>>()?;
+        Ok(IntermediateBucketResult::Composite(
+            IntermediateCompositeBucketResult { buckets },
+        ))
+    }
+
+    #[inline]
+    pub(crate) fn collect_block(
+        &mut self,
+        docs: &[DocId],
+        bucket_with_accessor: &BucketAggregationWithAccessor,
+        force_flush: bool,
+    ) -> crate::Result<()> {
+        let mut source_vals = vec![Vec::new(); self.sources.len()];
+        let bucket_memory = self.bucket_memory();
+        for &doc in docs {
+            for ((source, accessor), vals) in self
+                .sources
+                .iter_mut()
+                .zip(&bucket_with_accessor.composite_sources)
+                .zip(source_vals.iter_mut())
+            {
+                source.values.get_vals(accessor, doc, vals)?;
+            }
+            // A document is put into a bucket for each combination of its values.
+            for key in source_vals
+                .iter()
+                .map(|vals| vals.iter().copied())
+                .multi_cartesian_product()
+            {
+                if !self.is_in_range(&key) {
+                    continue;
+                }
+                let blueprint = &self.blueprint;
+                let bucket = self.buckets.entry(key).or_insert_with(|| {
+                    bucket_with_accessor.bucket_count.add_count(1);
+                    bucket_with_accessor
+                        .bucket_count
+                        .add_memory_consumed(bucket_memory);
+                    SegmentCompositeBucketEntry {
+                        doc_count: 0,
+                        sub_aggregation: blueprint.clone(),
+                    }
+                });
+                bucket.doc_count += 1;
+                if let Some(sub_aggregation) = bucket.sub_aggregation.as_mut() {
+                    sub_aggregation.collect(doc, &bucket_with_accessor.sub_aggregation)?;
+                }
+            }
+            if self.buckets.len() >= 2 * self.size {
+                self.discard_buckets(&bucket_with_accessor.bucket_count);
+            }
+        }
+        bucket_with_accessor.bucket_count.validate_limits()?;
+
+        if force_flush {
+            for bucket in self.buckets.values_mut() {
+                if let Some(sub_aggregation) = bucket.sub_aggregation.as_mut() {
+                    sub_aggregation
+                        .flush_staged_docs(&bucket_with_accessor.sub_aggregation, force_flush)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+    use time::format_description::well_known::Rfc3339;
+    use time::OffsetDateTime;
+
+    use super::*;
+    use crate::aggregation::agg_req::Aggregations;
+    use crate::aggregation::tests::{exec_request, get_test_index_from_values_and_terms};
+    use crate::schema::{Schema, FAST, STRING};
+    use crate::{DateTime, Index};
+
+    fn get_test_index(merge_segments: bool) -> crate::Result<Index> {
+        let segment_and_terms = vec![
+            vec![
+                (1.0, "a".to_string()),
+                (12.0, "b".to_string()),
+                (5.0, "a".to_string()),
+                (25.0, "c".to_string()),
+            ],
+            vec![
+                (3.0, "b".to_string()),
+                (15.0, "a".to_string()),
+                (1.0, "a".to_string()),
+            ],
+            vec![(22.0, "c".to_string()), (7.0, "b".to_string())],
+        ];
+        get_test_index_from_values_and_terms(merge_segments, &segment_and_terms)
+    }
+
+    fn composite_request(sources: Value, size: u32, after: Option<&Value>) -> Aggregations {
+        let mut composite = json!({ "sources": sources, "size": size });
+        if let Some(after) = after {
+            composite["after"] = after.clone();
+        }
+        serde_json::from_value(json!({ "my_composite": { "composite": composite } })).unwrap()
+    }
+
+    /// Requests all pages and returns the keys and doc counts of all buckets.
+    fn get_all_buckets(index: &Index, sources: Value, size: u32) -> crate::Result<Vec<Value>> {
+        let mut buckets = Vec::new();
+        let mut after = None;
+        loop {
+            let res = exec_request(
+                composite_request(sources.clone(), size, after.as_ref()),
+                index,
+            )?;
+            let page = res["my_composite"]["buckets"].as_array().unwrap().clone();
+            assert!(page.len() <= size as usize);
+            if page.is_empty() {
+                assert_eq!(res["my_composite"].get("after_key"), None);
+                return Ok(buckets);
+            }
+            assert_eq!(
+                res["my_composite"]["after_key"],
+                page.last().unwrap()["key"]
+            );
+            after = Some(res["my_composite"]["after_key"].clone());
+            buckets.extend(
+                page.into_iter()
+                    .map(|bucket| json!([bucket["key"], bucket["doc_count"]])),
+            );
+        }
+    }
+
+    #[test]
+    fn composite_aggregation_paging_test() -> crate::Result<()> {
+        composite_aggregation_paging_merge_segments(false)?;
+        composite_aggregation_paging_merge_segments(true)
+    }
+
+    fn composite_aggregation_paging_merge_segments(merge_segments: bool) -> crate::Result<()> {
+        let index = get_test_index(merge_segments)?;
+        let sources = json!([
+            { "term": { "terms": { "field": "string_id" } } },
+            { "hist": { "histogram": { "field": "score", "interval": 10.0 } } }
+        ]);
+        let expected = vec![
+            json!([{ "term": "a", "hist": 0.0 }, 3]),
+            json!([{ "term": "a", "hist": 10.0 }, 1]),
+            json!([{ "term": "b", "hist": 0.0 }, 2]),
+            json!([{ "term": "b", "hist": 10.0 }, 1]),
+            json!([{ "term": "c", "hist": 20.0 }, 2]),
+        ];
+        for size in [1, 2, 10] {
+            assert_eq!(get_all_buckets(&index, sources.clone(), size)?, expected);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn composite_aggregation_desc_order_test() -> crate::Result<()> {
+        let index = get_test_index(false)?;
+        let sources = json!([
+            { "term": { "terms": { "field": "string_id", "order": "desc" } } },
+            { "score": { "terms": { "field": "score_i64" } } }
+        ]);
+        let expected = vec![
+            json!([{ "term": "c", "score": 22.0 }, 1]),
+            json!([{ "term": "c", "score": 25.0 }, 1]),
+            json!([{ "term": "b", "score": 3.0 }, 1]),
+            json!([{ "term": "b", "score": 7.0 }, 1]),
+            json!([{ "term": "b", "score": 12.0 }, 1]),
+            json!([{ "term": "a", "score": 1.0 }, 2]),
+            json!([{ "term": "a", "score": 5.0 }, 1]),
+            json!([{ "term": "a", "score": 15.0 }, 1]),
+        ];
+        assert_eq!(get_all_buckets(&index, sources.clone(), 3)?, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn composite_aggregation_after_key_not_in_index_test() -> crate::Result<()> {
+        let index = get_test_index(false)?;
+        let sources = json!([
+            { "term": { "terms": { "field": "string_id" } } },
+            { "score": { "terms": { "field": "score_f64" } } }
+        ]);
+
+        let after = json!({ "term": "aa", "score": 0.0 });
+        let res = exec_request(composite_request(sources.clone(), 1, Some(&after)), &index)?;
+        assert_eq!(
+            res["my_composite"]["buckets"],
+            json!([{ "key": { "term": "b", "score": 3.0 }, "doc_count": 1 }])
+        );
+
+        let after = json!({ "term": "a", "score": 6.5 });
+        let res = exec_request(composite_request(sources.clone(), 1, Some(&after)), &index)?;
+        assert_eq!(
+            res["my_composite"]["buckets"],
+            json!([{ "key": { "term": "a", "score": 15.0 }, "doc_count": 1 }])
+        );
+
+        let after = json!({ "term": "d", "score": 0.0 });
+        let res = exec_request(composite_request(sources, 1, Some(&after)), &index)?;
+        assert_eq!(res["my_composite"]["buckets"], json!([]));
+        Ok(())
+    }
+
+    #[test]
+    fn composite_aggregation_sub_aggregation_test() -> crate::Result<()> {
+        let index = get_test_index(true)?;
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "my_composite": {
+                "composite": {
+                    "sources": [{ "term": { "terms": { "field": "string_id" } } }],
+                    "size": 2,
+                    "after": { "term": "a" }
+                },
+                "aggs": {
+                    "avg_score": { "avg": { "field": "score" } }
+                }
+            }
+        }))
+        .unwrap();
+        let res = exec_request(agg_req, &index)?;
+        assert_eq!(
+            res["my_composite"],
+            json!({
+                "after_key": { "term": "c" },
+                "buckets": [
+                    { "key": { "term": "b" }, "doc_count": 3, "avg_score": { "value": 22.0 / 3.0 } },
+                    { "key": { "term": "c" }, "doc_count": 2, "avg_score": { "value": 23.5 } }
+                ]
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn composite_aggregation_date_histogram_test() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let date_field = schema_builder.add_date_field("date", FAST);
+        let product_field = schema_builder.add_text_field("product", STRING | FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_for_tests()?;
+            let segments = [
+                vec![
+                    ("2022-01-01T10:00:00Z", "shoes"),
+                    ("2022-01-02T10:00:00Z", "socks"),
+                ],
+                vec![
+                    ("2022-01-01T23:00:00Z", "shoes"),
+                    ("2022-01-01T12:00:00Z", "socks"),
+                ],
+            ];
+            for segment in segments {
+                for (date, product) in segment {
+                    let date = OffsetDateTime::parse(date, &Rfc3339).unwrap();
+                    index_writer.add_document(doc!(
+                        date_field => DateTime::from_utc(date),
+                        product_field => product,
+                    ))?;
+                }
+                index_writer.commit()?;
+            }
+        }
+        let sources = json!([
+            { "day": { "date_histogram": { "field": "date", "calendar_interval": "day" } } },
+            { "product": { "terms": { "field": "product", "order": "desc" } } }
+        ]);
+        let day_1 = 1640995200000.0;
+        let day_2 = 1641081600000.0;
+        assert_eq!(
+            get_all_buckets(&index, sources, 2)?,
+            vec![
+                json!([{ "day": day_1, "product": "socks" }, 1]),
+                json!([{ "day": day_1, "product": "shoes" }, 2]),
+                json!([{ "day": day_2, "product": "socks" }, 1]),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn composite_aggregation_serde_test() {
+        let req: CompositeAggregation = serde_json::from_value(json!({
+            "sources": [
+                { "term": { "terms": { "field": "string_id", "order": "desc" } } },
+                { "hist": { "histogram": { "field": "score", "interval": 10.0 } } }
+            ],
+            "after": { "term": "a", "hist": 10.0 }
+        }))
+        .unwrap();
+        assert_eq!(req.size, 10);
+        assert_eq!(req.sources[0].name, "term");
+        assert_eq!(req.sources[0].source.order(), Order::Desc);
+        assert_eq!(req.sources[1].source.field(), "score");
+        assert_eq!(req.sources[1].source.order(), Order::Asc);
+        let roundtrip: CompositeAggregation =
+            serde_json::from_str(&serde_json::to_string(&req).unwrap()).unwrap();
+        assert_eq!(roundtrip, req);
+
+        let err = serde_json::from_value::<CompositeAggregation>(json!({
+            "sources": [{
+                "term": { "terms": { "field": "string_id" } },
+                "hist": { "histogram": { "field": "score", "interval": 10.0 } }
+            }]
+        }))
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "a composite source requires exactly one name, but got 2"
+        );
+    }
+
+    #[test]
+    fn composite_aggregation_invalid_request_test() -> crate::Result<()> {
+        let index = get_test_index(false)?;
+
+        let agg_req = composite_request(json!([]), 10, None);
+        let err = exec_request(agg_req, &index).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "An invalid argument was passed: 'composite aggregation requires at least one source'"
+        );
+
+        let sources = json!([
+            { "term": { "terms": { "field": "string_id" } } },
+            { "hist": { "histogram": { "field": "score", "interval": 10.0 } } }
+        ]);
+        let agg_req = composite_request(sources, 10, Some(&json!({ "term": "a" })));
+        let err = exec_request(agg_req, &index).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "An invalid argument was passed: 'the after key of a composite aggregation requires a \
+             value for each of the sources term, hist'"
+        );
+
+        let sources =
+            json!([{ "hist": { "histogram": { "field": "string_id", "interval": 1.0 } } }]);
+        let err = exec_request(composite_request(sources, 10, None), &index).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "An invalid argument was passed: 'histogram requires a numeric fast field, but \
+             string_id is of type Str'"
+        );
+        Ok(())
+    }
+}
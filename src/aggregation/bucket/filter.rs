@@ -0,0 +1,820 @@
+use std::collections::HashMap;
+use std::fmt::Debug;
+
+use common::BitSet;
+use rustc_hash::FxHashMap;
+use serde::de::Deserializer;
+use serde::ser::{Error, Serializer};
+use serde::{Deserialize, Serialize};
+
+use crate::aggregation::agg_req::{Aggregation, Aggregations, BucketAggregationType};
+use crate::aggregation::agg_req_with_accessor::{
+    AggregationsWithAccessor, BucketAggregationWithAccessor,
+};
+use crate::aggregation::intermediate_agg_result::{
+    IntermediateBucketResult, IntermediateFilterBucketEntry, IntermediateFiltersBucketResult,
+};
+use crate::aggregation::segment_agg_result::{BucketCount, SegmentAggregationResultsCollector};
+use crate::aggregation::SerializedKey;
+use crate::query::{EnableScoring, Query, QueryParser};
+use crate::{DocId, SegmentReader, TantivyError};
+
+/// The default key of the bucket containing the documents, which match none of the filters of a
+/// [`FiltersAggregation`].
+pub const DEFAULT_OTHER_BUCKET_KEY: &str = "_other_";
+
+/// The query of a [`FilterAggregation`] or of a bucket of a [`FiltersAggregation`].
+///
+/// A query is either a [`Query`] object, or a query string in the format of the
+/// [`QueryParser`]. Query strings need to be resolved to a [`Query`] before the aggregation is
+/// run, see [`AggregationCollector::with_query_parser`](crate::aggregation::AggregationCollector::with_query_parser).
+///
+/// # JSON Format
+/// Only query strings can be (de)serialized. The format is compatible with the `query_string`
+/// query of elasticsearch.
+/// ```json
+/// { "query_string": { "query": "severity:error" } }
+/// ```
+pub enum FilterQuery {
+    /// A query in the query parser format, which is not yet resolved.
+    QueryString(String),
+    /// A query object.
+    Query(Box<dyn Query>),
+}
+
+impl FilterQuery {
+    /// Parses the query string with the given query parser, if the query is not yet resolved.
+    fn resolve(&mut self, query_parser: &QueryParser) -> crate::Result<()> {
+        if let FilterQuery::QueryString(query_string) = self {
+            *self = FilterQuery::Query(query_parser.parse_query(query_string)?);
+        }
+        Ok(())
+    }
+
+    /// Returns the documents of the segment matching the query.
+    ///
+    /// Deleted documents may be part of the returned set, they are never collected though.
+    pub(crate) fn doc_set(&self, reader: &SegmentReader) -> crate::Result<BitSet> {
+        let query = match self {
+            FilterQuery::Query(query) => query,
+            FilterQuery::QueryString(query_string) => {
+                return Err(TantivyError::InvalidArgument(format!(
+                    "The filter query string {:?} needs to be resolved with a query parser, see \
+                     `AggregationCollector::with_query_parser`",
+                    query_string
+                )));
+            }
+        };
+        let weight = query.weight(EnableScoring::Disabled(reader.schema()))?;
+        let mut doc_set = BitSet::with_max_value(reader.max_doc());
+        weight.for_each_no_score(reader, &mut |doc| doc_set.insert(doc))?;
+        Ok(doc_set)
+    }
+}
+
+impl From<Box<dyn Query>> for FilterQuery {
+    fn from(query: Box<dyn Query>) -> Self {
+        FilterQuery::Query(query)
+    }
+}
+
+impl From<&str> for FilterQuery {
+    fn from(query_string: &str) -> Self {
+        FilterQuery::QueryString(query_string.to_string())
+    }
+}
+
+impl Clone for FilterQuery {
+    fn clone(&self) -> Self {
+        match self {
+            FilterQuery::QueryString(query_string) => {
+                FilterQuery::QueryString(query_string.clone())
+            }
+            FilterQuery::Query(query) => FilterQuery::Query(query.box_clone()),
+        }
+    }
+}
+
+impl Debug for FilterQuery {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FilterQuery::QueryString(query_string) => {
+                f.debug_tuple("QueryString").field(query_string).finish()
+            }
+            FilterQuery::Query(query) => f.debug_tuple("Query").field(query).finish(),
+        }
+    }
+}
+
+impl PartialEq for FilterQuery {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (FilterQuery::QueryString(left), FilterQuery::QueryString(right)) => left == right,
+            // Queries don't implement `PartialEq`, their debug representation is the best we have.
+            (FilterQuery::Query(left), FilterQuery::Query(right)) => {
+                format!("{:?}", left) == format!("{:?}", right)
+            }
+            _ => false,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct QueryStringQuery {
+    query: String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FilterQueryJson {
+    query_string: QueryStringQuery,
+}
+
+impl Serialize for FilterQuery {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where S: Serializer {
+        match self {
+            FilterQuery::QueryString(query_string) => FilterQueryJson {
+                query_string: QueryStringQuery {
+                    query: query_string.clone(),
+                },
+            }
+            .serialize(serializer),
+            FilterQuery::Query(_) => Err(S::Error::custom(
+                "filter aggregations with query objects can't be serialized, use a query string \
+                 instead",
+            )),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for FilterQuery {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where D: Deserializer<'de> {
+        let query = FilterQueryJson::deserialize(deserializer)?;
+        Ok(FilterQuery::QueryString(query.query_string.query))
+    }
+}
+
+/// A single bucket aggregation, containing all documents matching a query.
+///
+/// Result type is [`BucketResult::Filter`](crate::aggregation::agg_result::BucketResult::Filter)
+/// on the `AggregationCollector`.
+///
+/// # Request JSON Format
+/// ```json
+/// {
+///     "errors": {
+///         "filter": { "query_string": { "query": "severity:error" } },
+///         "aggs": {
+///             "avg_response_time": { "avg": { "field": "response_time" } }
+///         }
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct FilterAggregation {
+    /// The query documents need to match to be part of the bucket.
+    pub query: FilterQuery,
+}
+
+/// A multi bucket aggregation, with one bucket per query. A document is part of every bucket
+/// whose query it matches, so buckets may overlap.
+///
+/// Result type is [`BucketResult::Filters`](crate::aggregation::agg_result::BucketResult::Filters)
+/// with [`FilterBucketEntry`](crate::aggregation::agg_result::FilterBucketEntry) on the
+/// `AggregationCollector`.
+///
+/// # Request JSON Format
+/// ```json
+/// {
+///     "severities": {
+///         "filters": {
+///             "filters": {
+///                 "errors": { "query_string": { "query": "severity:error" } },
+///                 "warnings": { "query_string": { "query": "severity:warning" } }
+///             },
+///             "other_bucket": true
+///         }
+///     }
+/// }
+/// ```
+/// When `filters` is a map, the buckets are returned as a map with the same keys. When it is a
+/// list, the buckets are returned as a list in the same order.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FiltersAggregation {
+    /// The queries defining the buckets.
+    pub filters: FiltersBuckets,
+    /// Adds a bucket containing the documents matching none of the filters. It is the last
+    /// bucket, if the filters are anonymous.
+    #[serde(default)]
+    pub other_bucket: bool,
+    /// The key of the other bucket. Defaults to `_other_`. Setting it enables the other bucket.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub other_bucket_key: Option<String>,
+}
+
+/// The queries of a [`FiltersAggregation`], either named or anonymous.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum FiltersBuckets {
+    /// Queries with a bucket key.
+    Keyed(HashMap<String, FilterQuery>),
+    /// Queries identified by their position.
+    Anonymous(Vec<FilterQuery>),
+}
+
+impl FiltersAggregation {
+    pub(crate) fn is_keyed(&self) -> bool {
+        matches!(self.filters, FiltersBuckets::Keyed(_))
+    }
+
+    /// Returns the bucket keys and queries in bucket order.
+    ///
+    /// Keyed buckets are sorted by key, anonymous buckets are keyed by their position.
+    pub(crate) fn filters_in_bucket_order(&self) -> Vec<(SerializedKey, &FilterQuery)> {
+        match &self.filters {
+            FiltersBuckets::Keyed(filters) => {
+                let mut filters: Vec<_> = filters
+                    .iter()
+                    .map(|(key, query)| (key.to_string(), query))
+                    .collect();
+                filters.sort_by(|(left, _), (right, _)| left.cmp(right));
+                filters
+            }
+            FiltersBuckets::Anonymous(filters) => filters
+                .iter()
+                .enumerate()
+                .map(|(pos, query)| (pos.to_string(), query))
+                .collect(),
+        }
+    }
+
+    /// Returns the key of the other bucket, if it is enabled.
+    pub(crate) fn other_bucket_key(&self) -> Option<&str> {
+        match &self.other_bucket_key {
+            Some(key) => Some(key),
+            None if self.other_bucket => Some(DEFAULT_OTHER_BUCKET_KEY),
+            None => None,
+        }
+    }
+
+    /// Returns all bucket keys in bucket order, including the other bucket.
+    pub(crate) fn bucket_keys(&self) -> Vec<SerializedKey> {
+        let mut keys: Vec<_> = self
+            .filters_in_bucket_order()
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect();
+        keys.extend(self.other_bucket_key().map(str::to_string));
+        keys
+    }
+
+    fn validate(&self) -> crate::Result<()> {
+        if let (FiltersBuckets::Keyed(filters), Some(other_bucket_key)) =
+            (&self.filters, self.other_bucket_key())
+        {
+            if filters.contains_key(other_bucket_key) {
+                return Err(TantivyError::InvalidArgument(format!(
+                    "The other bucket key {:?} is also used as filter key",
+                    other_bucket_key
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Resolves the query strings of all filter aggregations in the request tree with the given
+/// query parser.
+pub(crate) fn resolve_filter_queries(
+    aggs: &mut Aggregations,
+    query_parser: &QueryParser,
+) -> crate::Result<()> {
+    for agg in aggs.values_mut() {
+        if let Aggregation::Bucket(bucket) = agg {
+            match &mut bucket.bucket_agg {
+                BucketAggregationType::Filter(filter) => filter.query.resolve(query_parser)?,
+                BucketAggregationType::Filters(filters) => match &mut filters.filters {
+                    FiltersBuckets::Keyed(filters) => {
+                        for query in filters.values_mut() {
+                            query.resolve(query_parser)?;
+                        }
+                    }
+                    FiltersBuckets::Anonymous(filters) => {
+                        for query in filters.iter_mut() {
+                            query.resolve(query_parser)?;
+                        }
+                    }
+                },
+                _ => {}
+            }
+            resolve_filter_queries(&mut bucket.sub_aggregation, query_parser)?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns the matching documents of the segment for every bucket of the filter aggregation, in
+/// bucket order. The other bucket has no document set.
+pub(crate) fn get_filter_doc_sets(
+    bucket_agg: &BucketAggregationType,
+    reader: &SegmentReader,
+) -> crate::Result<Vec<BitSet>> {
+    match bucket_agg {
+        BucketAggregationType::Filter(filter) => Ok(vec![filter.query.doc_set(reader)?]),
+        BucketAggregationType::Filters(filters) => {
+            filters.validate()?;
+            filters
+                .filters_in_bucket_order()
+                .into_iter()
+                .map(|(_, query)| query.doc_set(reader))
+                .collect()
+        }
+        _ => Ok(Vec::new()),
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct SegmentFilterBucketEntry {
+    doc_count: u64,
+    sub_aggregation: Option<SegmentAggregationResultsCollector>,
+}
+
+impl SegmentFilterBucketEntry {
+    fn from_req(sub_aggregation: &AggregationsWithAccessor) -> crate::Result<Self> {
+        let sub_aggregation = if sub_aggregation.is_empty() {
+            None
+        } else {
+            Some(SegmentAggregationResultsCollector::from_req_and_validate(
+                sub_aggregation,
+            )?)
+        };
+        Ok(SegmentFilterBucketEntry {
+            doc_count: 0,
+            sub_aggregation,
+        })
+    }
+
+    #[inline]
+    fn collect(
+        &mut self,
+        doc: DocId,
+        sub_aggregation: &AggregationsWithAccessor,
+    ) -> crate::Result<()> {
+        self.doc_count += 1;
+        if let Some(sub_aggregation_collector) = &mut self.sub_aggregation {
+            sub_aggregation_collector.collect(doc, sub_aggregation)?;
+        }
+        Ok(())
+    }
+
+    fn flush_staged_docs(
+        &mut self,
+        sub_aggregation: &AggregationsWithAccessor,
+    ) -> crate::Result<()> {
+        if let Some(sub_aggregation_collector) = &mut self.sub_aggregation {
+            sub_aggregation_collector.flush_staged_docs(sub_aggregation, true)?;
+        }
+        Ok(())
+    }
+
+    fn into_intermediate_bucket_entry(
+        self,
+        sub_aggregation: &AggregationsWithAccessor,
+    ) -> crate::Result<IntermediateFilterBucketEntry> {
+        let sub_aggregation = if let Some(sub_aggregation_collector) = self.sub_aggregation {
+            sub_aggregation_collector.into_intermediate_aggregations_result(sub_aggregation)?
+        } else {
+            Default::default()
+        };
+        Ok(IntermediateFilterBucketEntry {
+            doc_count: self.doc_count,
+            sub_aggregation,
+        })
+    }
+}
+
+/// The collector for the filter and filters aggregations.
+///
+/// The documents matching the queries are precomputed per segment, see
+/// [`get_filter_doc_sets`].
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct SegmentFilterCollector {
+    /// One bucket per filter, in the order of the document sets.
+    buckets: Vec<SegmentFilterBucketEntry>,
+    /// Collects the documents matching none of the filters, if enabled.
+    other_bucket: Option<SegmentFilterBucketEntry>,
+}
+
+impl SegmentFilterCollector {
+    /// Returns the estimated memory in bytes of the collector, including its buckets.
+    pub(crate) fn memory_estimate(&self) -> u64 {
+        let buckets_memory: u64 = self
+            .buckets
+            .iter()
+            .chain(self.other_bucket.iter())
+            .map(|bucket| {
+                std::mem::size_of::<SegmentFilterBucketEntry>() as u64
+                    + bucket
+                        .sub_aggregation
+                        .as_ref()
+                        .map_or(0, |sub_aggregation| sub_aggregation.memory_estimate())
+            })
+            .sum();
+        std::mem::size_of::<Self>() as u64 + buckets_memory
+    }
+
+    pub(crate) fn from_req_and_validate(
+        req: &BucketAggregationWithAccessor,
+        bucket_count: &BucketCount,
+    ) -> crate::Result<Self> {
+        let buckets = req
+            .filters
+            .iter()
+            .map(|_| SegmentFilterBucketEntry::from_req(&req.sub_aggregation))
+            .collect::<crate::Result<Vec<_>>>()?;
+        let other_bucket = match &req.bucket_agg {
+            BucketAggregationType::Filters(filters) if filters.other_bucket_key().is_some() => {
+                Some(SegmentFilterBucketEntry::from_req(&req.sub_aggregation)?)
+            }
+            _ => None,
+        };
+
+        bucket_count.add_count((buckets.len() + other_bucket.iter().len()) as u32);
+        bucket_count.validate_limits()?;
+
+        Ok(SegmentFilterCollector {
+            buckets,
+            other_bucket,
+        })
+    }
+
+    #[inline]
+    pub(crate) fn collect_block(
+        &mut self,
+        docs: &[DocId],
+        bucket_with_accessor: &BucketAggregationWithAccessor,
+        force_flush: bool,
+    ) -> crate::Result<()> {
+        let sub_aggregation = &bucket_with_accessor.sub_aggregation;
+        for &doc in docs {
+            let mut has_match = false;
+            for (bucket, doc_set) in self.buckets.iter_mut().zip(&bucket_with_accessor.filters) {
+                if doc_set.contains(doc) {
+                    has_match = true;
+                    bucket.collect(doc, sub_aggregation)?;
+                }
+            }
+            if !has_match {
+                if let Some(other_bucket) = &mut self.other_bucket {
+                    other_bucket.collect(doc, sub_aggregation)?;
+                }
+            }
+        }
+        if force_flush {
+            for bucket in self.buckets.iter_mut().chain(self.other_bucket.as_mut()) {
+                bucket.flush_staged_docs(sub_aggregation)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn into_intermediate_bucket_result(
+        self,
+        agg_with_accessor: &BucketAggregationWithAccessor,
+    ) -> crate::Result<IntermediateBucketResult> {
+        let sub_aggregation = &agg_with_accessor.sub_aggregation;
+        match &agg_with_accessor.bucket_agg {
+            BucketAggregationType::Filter(_) => {
+                let bucket = self
+                    .buckets
+                    .into_iter()
+                    .next()
+                    .expect("filter aggregation has exactly one bucket");
+                Ok(IntermediateBucketResult::Filter(
+                    bucket.into_intermediate_bucket_entry(sub_aggregation)?,
+                ))
+            }
+            BucketAggregationType::Filters(filters) => {
+                let buckets: FxHashMap<SerializedKey, IntermediateFilterBucketEntry> = filters
+                    .bucket_keys()
+                    .into_iter()
+                    .zip(self.buckets.into_iter().chain(self.other_bucket))
+                    .map(|(key, bucket)| {
+                        Ok((key, bucket.into_intermediate_bucket_entry(sub_aggregation)?))
+                    })
+                    .collect::<crate::Result<_>>()?;
+                Ok(IntermediateBucketResult::Filters(
+                    IntermediateFiltersBucketResult { buckets },
+                ))
+            }
+            _ => panic!("unexpected aggregation, expected filter aggregation"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::aggregation::agg_req::BucketAggregation;
+    use crate::aggregation::intermediate_agg_result::IntermediateAggregationResults;
+    use crate::aggregation::tests::{
+        exec_request_with_query, get_test_index_from_values_and_terms,
+    };
+    use crate::aggregation::{AggregationCollector, DistributedAggregationCollector};
+    use crate::query::{AllQuery, TermQuery};
+    use crate::schema::IndexRecordOption;
+    use crate::{Index, Term};
+
+    fn get_test_index() -> crate::Result<Index> {
+        let segment_and_terms = vec![
+            vec![
+                (1.0, "error".to_string()),
+                (5.0, "warning".to_string()),
+                (7.0, "info".to_string()),
+            ],
+            vec![
+                (9.0, "error".to_string()),
+                (11.0, "error".to_string()),
+                (3.0, "info".to_string()),
+            ],
+            vec![(20.0, "debug".to_string())],
+        ];
+        get_test_index_from_values_and_terms(false, &segment_and_terms)
+    }
+
+    fn exec_request_with_query_parser(
+        agg_req: Aggregations,
+        index: &Index,
+    ) -> crate::Result<Value> {
+        let query_parser = QueryParser::for_index(index, vec![]);
+        let collector =
+            AggregationCollector::from_aggs(agg_req, None).with_query_parser(&query_parser)?;
+
+        let reader = index.reader()?;
+        let searcher = reader.searcher();
+        let agg_res = searcher.search(&AllQuery, &collector)?;
+        Ok(serde_json::to_value(agg_res)?)
+    }
+
+    #[test]
+    fn filter_aggregation_with_query_string_test() -> crate::Result<()> {
+        let index = get_test_index()?;
+
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "errors": {
+                "filter": { "query_string": { "query": "string_id:error" } },
+                "aggs": {
+                    "stats": { "stats": { "field": "score" } }
+                }
+            }
+        }))
+        .unwrap();
+
+        let res = exec_request_with_query_parser(agg_req, &index)?;
+        assert_eq!(res["errors"]["doc_count"], 3);
+        assert_eq!(res["errors"]["stats"]["count"], 3);
+        assert_eq!(res["errors"]["stats"]["min"], 1.0);
+        assert_eq!(res["errors"]["stats"]["max"], 11.0);
+        Ok(())
+    }
+
+    #[test]
+    fn filter_aggregation_with_query_object_test() -> crate::Result<()> {
+        let index = get_test_index()?;
+        let string_id = index.schema().get_field("string_id").unwrap();
+
+        let query: Box<dyn Query> = Box::new(TermQuery::new(
+            Term::from_field_text(string_id, "warning"),
+            IndexRecordOption::Basic,
+        ));
+        let agg_req: Aggregations = vec![(
+            "warnings".to_string(),
+            Aggregation::Bucket(BucketAggregation {
+                bucket_agg: BucketAggregationType::Filter(FilterAggregation {
+                    query: query.into(),
+                }),
+                sub_aggregation: Default::default(),
+            }),
+        )]
+        .into_iter()
+        .collect();
+
+        let res = exec_request_with_query(agg_req, &index, None)?;
+        assert_eq!(res["warnings"]["doc_count"], 1);
+        Ok(())
+    }
+
+    #[test]
+    fn filters_aggregation_keyed_test() -> crate::Result<()> {
+        let index = get_test_index()?;
+
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "levels": {
+                "filters": {
+                    "filters": {
+                        "errors": { "query_string": { "query": "string_id:error" } },
+                        "warnings": { "query_string": { "query": "string_id:warning" } },
+                        "problems": {
+                            "query_string": { "query": "string_id:error string_id:warning" }
+                        }
+                    },
+                    "other_bucket": true
+                },
+                "aggs": {
+                    "max_score": { "max": { "field": "score" } }
+                }
+            }
+        }))
+        .unwrap();
+
+        let res = exec_request_with_query_parser(agg_req, &index)?;
+        assert_eq!(
+            res,
+            json!({
+                "levels": {
+                    "buckets": {
+                        "errors": { "doc_count": 3, "max_score": { "value": 11.0 } },
+                        "warnings": { "doc_count": 1, "max_score": { "value": 5.0 } },
+                        "problems": { "doc_count": 4, "max_score": { "value": 11.0 } },
+                        "_other_": { "doc_count": 3, "max_score": { "value": 20.0 } }
+                    }
+                }
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn filters_aggregation_anonymous_test() -> crate::Result<()> {
+        let index = get_test_index()?;
+
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "levels": {
+                "filters": {
+                    "filters": [
+                        { "query_string": { "query": "string_id:warning" } },
+                        { "query_string": { "query": "string_id:info" } }
+                    ],
+                    "other_bucket_key": "others"
+                }
+            }
+        }))
+        .unwrap();
+
+        let res = exec_request_with_query_parser(agg_req, &index)?;
+        assert_eq!(
+            res,
+            json!({
+                "levels": {
+                    "buckets": [
+                        { "doc_count": 1 },
+                        { "doc_count": 2 },
+                        { "doc_count": 4 }
+                    ]
+                }
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn filter_aggregation_nested_in_range_test() -> crate::Result<()> {
+        let index = get_test_index()?;
+
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "scores": {
+                "range": {
+                    "field": "score",
+                    "ranges": [{ "to": 8.0 }, { "from": 8.0 }]
+                },
+                "aggs": {
+                    "errors": {
+                        "filter": { "query_string": { "query": "string_id:error" } }
+                    }
+                }
+            }
+        }))
+        .unwrap();
+
+        let res = exec_request_with_query_parser(agg_req, &index)?;
+        assert_eq!(res["scores"]["buckets"][0]["key"], "*-8");
+        assert_eq!(res["scores"]["buckets"][0]["doc_count"], 4);
+        assert_eq!(res["scores"]["buckets"][0]["errors"]["doc_count"], 1);
+        assert_eq!(res["scores"]["buckets"][1]["key"], "8-*");
+        assert_eq!(res["scores"]["buckets"][1]["doc_count"], 3);
+        assert_eq!(res["scores"]["buckets"][1]["errors"]["doc_count"], 2);
+        Ok(())
+    }
+
+    #[test]
+    fn filters_aggregation_distributed_test() -> crate::Result<()> {
+        let index = get_test_index()?;
+
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "levels": {
+                "filters": {
+                    "filters": {
+                        "errors": { "query_string": { "query": "string_id:error" } },
+                        "warnings": { "query_string": { "query": "string_id:warning" } }
+                    }
+                }
+            }
+        }))
+        .unwrap();
+
+        let query_parser = QueryParser::for_index(&index, vec![]);
+        let collector = DistributedAggregationCollector::from_aggs(agg_req.clone(), None)
+            .with_query_parser(&query_parser)?;
+        let reader = index.reader()?;
+        let searcher = reader.searcher();
+        let intermediate_res = searcher.search(&AllQuery, &collector)?;
+
+        let intermediate_res: IntermediateAggregationResults =
+            serde_json::from_str(&serde_json::to_string(&intermediate_res)?)?;
+        let mut merged_res = intermediate_res.clone();
+        merged_res.merge_fruits(intermediate_res);
+
+        let res = serde_json::to_value(merged_res.into_final_bucket_result(agg_req)?)?;
+        assert_eq!(res["levels"]["buckets"]["errors"]["doc_count"], 6);
+        assert_eq!(res["levels"]["buckets"]["warnings"]["doc_count"], 2);
+        Ok(())
+    }
+
+    #[test]
+    fn filter_aggregation_errors_test() -> crate::Result<()> {
+        let index = get_test_index()?;
+
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "errors": {
+                "filter": { "query_string": { "query": "string_id:error" } }
+            }
+        }))
+        .unwrap();
+        let err = exec_request_with_query(agg_req, &index, None).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("needs to be resolved with a query parser"));
+
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "levels": {
+                "filters": {
+                    "filters": { "_other_": { "query_string": { "query": "string_id:error" } } },
+                    "other_bucket": true
+                }
+            }
+        }))
+        .unwrap();
+        let err = exec_request_with_query_parser(agg_req, &index).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "An invalid argument was passed: 'The other bucket key \"_other_\" is also used as \
+             filter key'"
+        );
+
+        let query: Box<dyn Query> = Box::new(AllQuery);
+        let agg_req: Aggregations = vec![(
+            "all".to_string(),
+            Aggregation::Bucket(BucketAggregation {
+                bucket_agg: BucketAggregationType::Filter(FilterAggregation {
+                    query: query.into(),
+                }),
+                sub_aggregation: Default::default(),
+            }),
+        )]
+        .into_iter()
+        .collect();
+        assert!(serde_json::to_string(&agg_req).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn filters_aggregation_serde_test() {
+        let req_json = json!({
+            "levels": {
+                "filters": {
+                    "filters": [{ "query_string": { "query": "string_id:error" } }],
+                    "other_bucket": true
+                }
+            }
+        });
+        let agg_req: Aggregations = serde_json::from_value(req_json.clone()).unwrap();
+        assert_eq!(
+            agg_req["levels"],
+            Aggregation::Bucket(BucketAggregation {
+                bucket_agg: BucketAggregationType::Filters(FiltersAggregation {
+                    filters: FiltersBuckets::Anonymous(vec!["string_id:error".into()]),
+                    other_bucket: true,
+                    other_bucket_key: None,
+                }),
+                sub_aggregation: Default::default(),
+            })
+        );
+        assert_eq!(serde_json::to_value(&agg_req).unwrap(), req_json);
+    }
+}
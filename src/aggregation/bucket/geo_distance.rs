@@ -0,0 +1,290 @@
+use serde::{Deserialize, Serialize};
+
+use super::{RangeAggregation, RangeAggregationRange, SegmentRangeCollector};
+use crate::aggregation::agg_req_with_accessor::{
+    AggregationsWithAccessor, BucketAggregationWithAccessor,
+};
+use crate::aggregation::f64_to_fastfield_u64;
+use crate::aggregation::intermediate_agg_result::IntermediateBucketResult;
+use crate::aggregation::segment_agg_result::BucketCount;
+use crate::aggregation::GeoPoint;
+use crate::schema::Type;
+use crate::{DocId, TantivyError};
+
+/// Provide user-defined buckets of distances from an origin to aggregate on.
+///
+/// The geo point of a document is read from the two `f64` fast fields `lat_field` and
+/// `lon_field`, and its distance to `origin` is put into the bucket containing it, like in the
+/// [`RangeAggregation`]. The distance is the great circle distance in `unit`. Two special buckets
+/// will automatically be created to cover the whole range of distances.
+///
+/// Result type is [`BucketResult`](crate::aggregation::agg_result::BucketResult) with
+/// [`RangeBucketEntry`](crate::aggregation::agg_result::RangeBucketEntry) on the
+/// `AggregationCollector`.
+///
+/// # Limitations/Compatibility
+/// The origin only supports the object format. Documents without coordinates read as `0.0`,
+/// `0.0`, like missing values in the other single valued fast field aggregations.
+///
+/// # Request JSON Format
+/// ```json
+/// {
+///     "lat_field": "latitude",
+///     "lon_field": "longitude",
+///     "origin": { "lat": 52.3760, "lon": 4.894 },
+///     "unit": "km",
+///     "ranges": [
+///         { "to": 1.0 },
+///         { "from": 1.0, "to": 5.0 },
+///         { "from": 5.0, "to": 10.0 }
+///     ]
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GeoDistanceAggregation {
+    /// The f64 fast field containing the latitude of the documents.
+    pub lat_field: String,
+    /// The f64 fast field containing the longitude of the documents.
+    pub lon_field: String,
+    /// The point to compute the distances from.
+    pub origin: GeoPoint,
+    /// The unit of the distances in the ranges and the results. Defaults to meters.
+    #[serde(default)]
+    pub unit: DistanceUnit,
+    /// Note that this aggregation includes the from value and excludes the to value for each
+    /// range. Extra buckets will be created until the first to, and last from, if necessary.
+    pub ranges: Vec<RangeAggregationRange>,
+    /// Whether to return the buckets as a hash map
+    #[serde(default)]
+    pub keyed: bool,
+}
+
+impl GeoDistanceAggregation {
+    fn validate(&self) -> crate::Result<()> {
+        self.origin.validate()?;
+        if self.ranges.is_empty() {
+            return Err(TantivyError::InvalidArgument(
+                "geo_distance aggregation requires at least one range".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// The range aggregation on the distances in `unit`.
+    fn to_range_aggregation(&self) -> RangeAggregation {
+        RangeAggregation {
+            field: self.lat_field.to_string(),
+            ranges: self.ranges.clone(),
+            keyed: self.keyed,
+        }
+    }
+}
+
+/// The unit of the distances of a [`GeoDistanceAggregation`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DistanceUnit {
+    /// Meters
+    #[default]
+    #[serde(rename = "m")]
+    Meters,
+    /// Kilometers
+    #[serde(rename = "km")]
+    Kilometers,
+    /// Miles
+    #[serde(rename = "mi")]
+    Miles,
+    /// Yards
+    #[serde(rename = "yd")]
+    Yards,
+    /// Feet
+    #[serde(rename = "ft")]
+    Feet,
+    /// Nautical miles
+    #[serde(rename = "nmi")]
+    NauticalMiles,
+}
+
+impl DistanceUnit {
+    fn meters_per_unit(self) -> f64 {
+        match self {
+            DistanceUnit::Meters => 1.0,
+            DistanceUnit::Kilometers => 1_000.0,
+            DistanceUnit::Miles => 1_609.344,
+            DistanceUnit::Yards => 0.9144,
+            DistanceUnit::Feet => 0.3048,
+            DistanceUnit::NauticalMiles => 1_852.0,
+        }
+    }
+}
+
+/// The collector puts the distances of the documents into the buckets of a
+/// [`SegmentRangeCollector`] on `f64` values.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct SegmentGeoDistanceCollector {
+    range: SegmentRangeCollector,
+    origin: GeoPoint,
+    meters_per_unit: f64,
+}
+
+impl SegmentGeoDistanceCollector {
+    /// Returns the estimated memory in bytes of the collector, including its buckets.
+    pub(crate) fn memory_estimate(&self) -> u64 {
+        std::mem::size_of::<Self>() as u64 + self.range.memory_estimate()
+    }
+
+    pub(crate) fn from_req_and_validate(
+        req: &GeoDistanceAggregation,
+        sub_aggregation: &AggregationsWithAccessor,
+        bucket_count: &BucketCount,
+    ) -> crate::Result<Self> {
+        req.validate()?;
+        let range = SegmentRangeCollector::from_req_and_validate(
+            &req.to_range_aggregation(),
+            sub_aggregation,
+            bucket_count,
+            Type::F64,
+        )?;
+        Ok(SegmentGeoDistanceCollector {
+            range,
+            origin: req.origin,
+            meters_per_unit: req.unit.meters_per_unit(),
+        })
+    }
+
+    pub(crate) fn into_intermediate_bucket_result(
+        self,
+        agg_with_accessor: &BucketAggregationWithAccessor,
+    ) -> crate::Result<IntermediateBucketResult> {
+        self.range
+            .into_intermediate_bucket_result(agg_with_accessor)
+    }
+
+    #[inline]
+    pub(crate) fn collect_block(
+        &mut self,
+        docs: &[DocId],
+        bucket_with_accessor: &BucketAggregationWithAccessor,
+        force_flush: bool,
+    ) -> crate::Result<()> {
+        let geo_point = bucket_with_accessor
+            .geo_point
+            .as_ref()
+            .expect("missing geo point accessor for geo_distance aggregation");
+        for &doc in docs {
+            let distance = self.origin.distance(&geo_point.get(doc)) / self.meters_per_unit;
+            let val = f64_to_fastfield_u64(distance, &Type::F64)
+                .expect("f64 values can always be converted");
+            self.range
+                .collect_value(doc, val, &bucket_with_accessor.sub_aggregation)?;
+        }
+        if force_flush {
+            self.range
+                .flush_sub_aggregations(&bucket_with_accessor.sub_aggregation)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use crate::aggregation::agg_req::Aggregations;
+    use crate::aggregation::tests::{exec_request, get_test_index_with_geo_points};
+
+    #[test]
+    fn geo_distance_test() -> crate::Result<()> {
+        let index = get_test_index_with_geo_points(false)?;
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "distances": {
+                "geo_distance": {
+                    "lat_field": "lat",
+                    "lon_field": "lon",
+                    "origin": { "lat": 52.3676, "lon": 4.9041 },
+                    "ranges": [
+                        { "to": 1000.0 },
+                        { "from": 1000.0, "to": 5000.0 },
+                        { "from": 5000.0, "to": 50000.0 }
+                    ]
+                },
+                "aggs": { "avg_speed": { "avg": { "field": "speed" } } }
+            }
+        }))
+        .unwrap();
+
+        let res = exec_request(agg_req, &index)?;
+        let buckets = &res["distances"]["buckets"];
+        assert_eq!(buckets[0]["key"], "*-1000");
+        assert_eq!(buckets[0]["doc_count"], 2);
+        assert_eq!(buckets[0]["avg_speed"]["value"], 15.0);
+        assert_eq!(buckets[1]["key"], "1000-5000");
+        assert_eq!(buckets[1]["doc_count"], 1);
+        assert_eq!(buckets[2]["key"], "5000-50000");
+        assert_eq!(buckets[2]["doc_count"], 1);
+        assert_eq!(buckets[3]["key"], "50000-*");
+        assert_eq!(buckets[3]["doc_count"], 1);
+        assert_eq!(buckets[3]["avg_speed"]["value"], 80.0);
+        assert_eq!(buckets[4], Value::Null);
+        Ok(())
+    }
+
+    #[test]
+    fn geo_distance_unit_keyed_test() -> crate::Result<()> {
+        let index = get_test_index_with_geo_points(true)?;
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "distances": {
+                "geo_distance": {
+                    "lat_field": "lat",
+                    "lon_field": "lon",
+                    "origin": { "lat": 52.3676, "lon": 4.9041 },
+                    "unit": "km",
+                    "ranges": [
+                        { "key": "nearby", "to": 5.0 },
+                        { "key": "region", "from": 5.0, "to": 50.0 }
+                    ],
+                    "keyed": true
+                }
+            }
+        }))
+        .unwrap();
+
+        let res = exec_request(agg_req, &index)?;
+        let buckets = &res["distances"]["buckets"];
+        assert_eq!(buckets["nearby"]["doc_count"], 3);
+        assert_eq!(buckets["nearby"]["to"], 5.0);
+        assert_eq!(buckets["region"]["doc_count"], 1);
+        assert_eq!(buckets["50-*"]["doc_count"], 1);
+        Ok(())
+    }
+
+    #[test]
+    fn geo_distance_invalid_request_test() -> crate::Result<()> {
+        let index = get_test_index_with_geo_points(false)?;
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "distances": {
+                "geo_distance": {
+                    "lat_field": "lat",
+                    "lon_field": "lon",
+                    "origin": { "lat": 95.0, "lon": 4.9041 },
+                    "ranges": [{ "to": 1000.0 }]
+                }
+            }
+        }))
+        .unwrap();
+        assert!(exec_request(agg_req, &index).is_err());
+
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "distances": {
+                "geo_distance": {
+                    "lat_field": "fleet",
+                    "lon_field": "lon",
+                    "origin": { "lat": 52.0, "lon": 4.9041 },
+                    "ranges": [{ "to": 1000.0 }]
+                }
+            }
+        }))
+        .unwrap();
+        assert!(exec_request(agg_req, &index).is_err());
+        Ok(())
+    }
+}
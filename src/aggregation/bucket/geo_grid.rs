@@ -0,0 +1,321 @@
+use rustc_hash::FxHashMap;
+use serde::{Deserialize, Serialize};
+
+use super::TermBuckets;
+use crate::aggregation::agg_req_with_accessor::{
+    AggregationsWithAccessor, BucketAggregationWithAccessor,
+};
+use crate::aggregation::geo::{
+    geohash_cell, geohash_to_string, geotile_cell, geotile_to_string, MAX_GEOHASH_PRECISION,
+    MAX_GEOTILE_ZOOM,
+};
+use crate::aggregation::intermediate_agg_result::{
+    IntermediateBucketResult, IntermediateGeoGridBucketResult,
+};
+use crate::aggregation::segment_agg_result::SegmentAggregationResultsCollector;
+use crate::aggregation::GeoPoint;
+use crate::{DocId, TantivyError};
+
+const DEFAULT_GEOHASH_PRECISION: u8 = 5;
+const DEFAULT_GEOTILE_ZOOM: u8 = 7;
+const DEFAULT_GEO_GRID_SIZE: u32 = 10_000;
+
+/// Groups the documents into buckets of geohash cells.
+///
+/// The geo point of a document is read from the two `f64` fast fields `lat_field` and
+/// `lon_field`. The key of a bucket is the geohash of its cell, with `precision` characters.
+///
+/// The buckets are sorted by their doc count descending, and only the `size` biggest buckets are
+/// returned.
+///
+/// Result type is [`BucketResult`](crate::aggregation::agg_result::BucketResult) with
+/// [`BucketEntry`](crate::aggregation::agg_result::BucketEntry) on the
+/// `AggregationCollector`.
+///
+/// # Limitations/Compatibility
+/// Documents without coordinates read as `0.0`, `0.0`, like missing values in the other single
+/// valued fast field aggregations. `bounds` and `shard_size` are not supported.
+///
+/// # Request JSON Format
+/// ```json
+/// {
+///     "lat_field": "latitude",
+///     "lon_field": "longitude",
+///     "precision": 5
+/// }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct GeohashGridAggregation {
+    /// The f64 fast field containing the latitude of the documents.
+    pub lat_field: String,
+    /// The f64 fast field containing the longitude of the documents.
+    pub lon_field: String,
+    /// The length of the geohashes between 1 and 12. Defaults to 5.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub precision: Option<u8>,
+    /// The maximum number of buckets returned. Defaults to 10000.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub size: Option<u32>,
+}
+
+impl GeohashGridAggregation {
+    pub(crate) fn size(&self) -> usize {
+        self.size.unwrap_or(DEFAULT_GEO_GRID_SIZE) as usize
+    }
+
+    pub(crate) fn cells(&self) -> crate::Result<GeoGridCells> {
+        let precision = self.precision.unwrap_or(DEFAULT_GEOHASH_PRECISION);
+        if !(1..=MAX_GEOHASH_PRECISION).contains(&precision) {
+            return Err(TantivyError::InvalidArgument(format!(
+                "geohash_grid precision must be between 1 and {}, but got {}",
+                MAX_GEOHASH_PRECISION, precision
+            )));
+        }
+        Ok(GeoGridCells::Geohash { precision })
+    }
+}
+
+/// Groups the documents into buckets of web mercator map tiles.
+///
+/// The geo point of a document is read from the two `f64` fast fields `lat_field` and
+/// `lon_field`. The key of a bucket is the `zoom/x/y` of its tile, with the zoom level
+/// `precision`.
+///
+/// The buckets are sorted by their doc count descending, and only the `size` biggest buckets are
+/// returned.
+///
+/// Result type is [`BucketResult`](crate::aggregation::agg_result::BucketResult) with
+/// [`BucketEntry`](crate::aggregation::agg_result::BucketEntry) on the
+/// `AggregationCollector`.
+///
+/// # Limitations/Compatibility
+/// Documents without coordinates read as `0.0`, `0.0`, like missing values in the other single
+/// valued fast field aggregations. `bounds` and `shard_size` are not supported.
+///
+/// # Request JSON Format
+/// ```json
+/// {
+///     "lat_field": "latitude",
+///     "lon_field": "longitude",
+///     "precision": 8
+/// }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct GeoTileGridAggregation {
+    /// The f64 fast field containing the latitude of the documents.
+    pub lat_field: String,
+    /// The f64 fast field containing the longitude of the documents.
+    pub lon_field: String,
+    /// The zoom level of the tiles between 0 and 29. Defaults to 7.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub precision: Option<u8>,
+    /// The maximum number of buckets returned. Defaults to 10000.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub size: Option<u32>,
+}
+
+impl GeoTileGridAggregation {
+    pub(crate) fn size(&self) -> usize {
+        self.size.unwrap_or(DEFAULT_GEO_GRID_SIZE) as usize
+    }
+
+    pub(crate) fn cells(&self) -> crate::Result<GeoGridCells> {
+        let zoom = self.precision.unwrap_or(DEFAULT_GEOTILE_ZOOM);
+        if zoom > MAX_GEOTILE_ZOOM {
+            return Err(TantivyError::InvalidArgument(format!(
+                "geotile_grid precision must be between 0 and {}, but got {}",
+                MAX_GEOTILE_ZOOM, zoom
+            )));
+        }
+        Ok(GeoGridCells::GeoTile { zoom })
+    }
+}
+
+/// The cells of a geo grid aggregation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum GeoGridCells {
+    Geohash { precision: u8 },
+    GeoTile { zoom: u8 },
+}
+
+impl GeoGridCells {
+    #[inline]
+    fn cell(self, point: &GeoPoint) -> u64 {
+        match self {
+            GeoGridCells::Geohash { precision } => geohash_cell(point, precision),
+            GeoGridCells::GeoTile { zoom } => geotile_cell(point, zoom),
+        }
+    }
+
+    fn cell_to_key(self, cell: u64) -> String {
+        match self {
+            GeoGridCells::Geohash { precision } => geohash_to_string(cell, precision),
+            GeoGridCells::GeoTile { zoom } => geotile_to_string(cell, zoom),
+        }
+    }
+}
+
+/// The collector counts the documents per cell id.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct SegmentGeoGridCollector {
+    term_buckets: TermBuckets,
+    cells: GeoGridCells,
+    blueprint: Option<SegmentAggregationResultsCollector>,
+}
+
+impl SegmentGeoGridCollector {
+    /// Returns the estimated memory in bytes of the collector before it collects any document.
+    pub(crate) fn memory_estimate(&self) -> u64 {
+        std::mem::size_of::<Self>() as u64
+            + self
+                .blueprint
+                .as_ref()
+                .map_or(0, |blueprint| blueprint.memory_estimate())
+    }
+
+    pub(crate) fn from_req_and_validate(
+        cells: GeoGridCells,
+        sub_aggregations: &AggregationsWithAccessor,
+    ) -> crate::Result<Self> {
+        let blueprint = if sub_aggregations.is_empty() {
+            None
+        } else {
+            Some(SegmentAggregationResultsCollector::from_req_and_validate(
+                sub_aggregations,
+            )?)
+        };
+        Ok(SegmentGeoGridCollector {
+            term_buckets: TermBuckets::from_req_and_validate(sub_aggregations)?,
+            cells,
+            blueprint,
+        })
+    }
+
+    pub(crate) fn into_intermediate_bucket_result(
+        self,
+        agg_with_accessor: &BucketAggregationWithAccessor,
+    ) -> crate::Result<IntermediateBucketResult> {
+        let buckets = self
+            .term_buckets
+            .entries
+            .into_iter()
+            .map(|(cell, entry)| {
+                Ok((
+                    self.cells.cell_to_key(cell),
+                    entry.into_intermediate_bucket_entry(&agg_with_accessor.sub_aggregation)?,
+                ))
+            })
+            .collect::<crate::Result<FxHashMap<_, _>>>()?;
+        Ok(IntermediateBucketResult::GeoGrid(
+            IntermediateGeoGridBucketResult { buckets },
+        ))
+    }
+
+    #[inline]
+    pub(crate) fn collect_block(
+        &mut self,
+        docs: &[DocId],
+        bucket_with_accessor: &BucketAggregationWithAccessor,
+        force_flush: bool,
+    ) -> crate::Result<()> {
+        let geo_point = bucket_with_accessor
+            .geo_point
+            .as_ref()
+            .expect("missing geo point accessor for geo grid aggregation");
+        for &doc in docs {
+            let cell = self.cells.cell(&geo_point.get(doc));
+            self.term_buckets.increment_bucket(
+                &[cell],
+                doc,
+                &bucket_with_accessor.sub_aggregation,
+                &bucket_with_accessor.bucket_count,
+                &self.blueprint,
+            )?;
+        }
+        if force_flush {
+            self.term_buckets
+                .force_flush(&bucket_with_accessor.sub_aggregation)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use crate::aggregation::agg_req::Aggregations;
+    use crate::aggregation::tests::{exec_request, get_test_index_with_geo_points};
+
+    #[test]
+    fn geohash_grid_test() -> crate::Result<()> {
+        let index = get_test_index_with_geo_points(false)?;
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "cells": {
+                "geohash_grid": {
+                    "lat_field": "lat",
+                    "lon_field": "lon",
+                    "precision": 4
+                },
+                "aggs": { "max_speed": { "max": { "field": "speed" } } }
+            }
+        }))
+        .unwrap();
+
+        let res = exec_request(agg_req, &index)?;
+        let buckets = &res["cells"]["buckets"];
+        assert_eq!(buckets[0]["key"], "u173");
+        assert_eq!(buckets[0]["doc_count"], 3);
+        assert_eq!(buckets[0]["max_speed"]["value"], 30.0);
+        assert_eq!(buckets[1]["key"], "u178");
+        assert_eq!(buckets[1]["doc_count"], 1);
+        assert_eq!(buckets[2]["key"], "u33d");
+        assert_eq!(buckets[2]["doc_count"], 1);
+        assert_eq!(buckets[3], Value::Null);
+        Ok(())
+    }
+
+    #[test]
+    fn geotile_grid_test() -> crate::Result<()> {
+        let index = get_test_index_with_geo_points(true)?;
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "tiles": {
+                "geotile_grid": {
+                    "lat_field": "lat",
+                    "lon_field": "lon",
+                    "precision": 8,
+                    "size": 1
+                }
+            }
+        }))
+        .unwrap();
+
+        let res = exec_request(agg_req, &index)?;
+        let buckets = &res["tiles"]["buckets"];
+        assert_eq!(buckets[0]["key"], "8/131/84");
+        assert_eq!(buckets[0]["doc_count"], 4);
+        assert_eq!(buckets[1], Value::Null);
+        Ok(())
+    }
+
+    #[test]
+    fn geo_grid_invalid_precision_test() -> crate::Result<()> {
+        let index = get_test_index_with_geo_points(false)?;
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "cells": {
+                "geohash_grid": { "lat_field": "lat", "lon_field": "lon", "precision": 13 }
+            }
+        }))
+        .unwrap();
+        assert!(exec_request(agg_req, &index).is_err());
+
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "tiles": {
+                "geotile_grid": { "lat_field": "lat", "lon_field": "lon", "precision": 30 }
+            }
+        }))
+        .unwrap();
+        assert!(exec_request(agg_req, &index).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,1021 @@
+use std::cmp::Ordering;
+use std::fmt::Debug;
+
+use fastfield_codecs::MonotonicallyMappableToU64;
+use itertools::Itertools;
+use rustc_hash::FxHashMap;
+use serde::{Deserialize, Serialize};
+use time::format_description::well_known::Rfc3339;
+use time::{Date, Duration, Month, OffsetDateTime, PrimitiveDateTime, Time, UtcOffset};
+use time_tz::{timezones, Offset, OffsetResult, TimeZone, Tz};
+
+use crate::aggregation::agg_req::AggregationsInternal;
+use crate::aggregation::agg_req_with_accessor::{
+    AggregationsWithAccessor, BucketAggregationWithAccessor,
+};
+use crate::aggregation::agg_result::BucketEntry;
+use crate::aggregation::intermediate_agg_result::{
+    IntermediateAggregationResults, IntermediateBucketResult, IntermediateHistogramBucketEntry,
+};
+use crate::aggregation::segment_agg_result::{BucketCount, SegmentAggregationResultsCollector};
+use crate::{DocId, TantivyError};
+
+/// DateHistogram is a bucket aggregation on date fast fields, where buckets are created for
+/// calendar-aware or fixed time intervals.
+///
+/// Exactly one of [calendar_interval](DateHistogramAggregation::calendar_interval) and
+/// [fixed_interval](DateHistogramAggregation::fixed_interval) has to be set.
+///
+/// Calendar intervals take the varying length of months and years, and daylight saving time
+/// transitions of the [time_zone](DateHistogramAggregation::time_zone) into account, e.g. a `day`
+/// bucket always starts at midnight in the given time zone, even if the day has 23 or 25 hours.
+/// Fixed intervals are multiples of a fixed duration like `90m` or `2d`.
+///
+/// # Result
+/// Result type is [`BucketResult`](crate::aggregation::agg_result::BucketResult) with
+/// [`BucketEntry`](crate::aggregation::agg_result::BucketEntry) on the
+/// `AggregationCollector`. The key of a bucket is the start of the bucket in milliseconds since
+/// the unix epoch, `key_as_string` is the start of the bucket formatted as RFC3339 in the
+/// requested time zone.
+///
+/// Result type is
+/// [`IntermediateBucketResult`](crate::aggregation::intermediate_agg_result::IntermediateBucketResult) with
+/// [`IntermediateHistogramBucketEntry`](crate::aggregation::intermediate_agg_result::IntermediateHistogramBucketEntry) on the
+/// `DistributedAggregationCollector`.
+///
+/// # Limitations/Compatibility
+/// Only single valued date fast fields are supported. `extended_bounds`, `hard_bounds` and
+/// `format` are not supported.
+///
+/// # JSON Format
+/// ```json
+/// {
+///     "sales_over_time": {
+///         "date_histogram": {
+///             "field": "date",
+///             "calendar_interval": "month",
+///             "time_zone": "Europe/Berlin"
+///         }
+///     }
+/// }
+/// ```
+///
+/// Response
+/// ```json
+/// {
+///     "sales_over_time": {
+///         "buckets": [
+///             {
+///                 "key": 1577833200000.0,
+///                 "key_as_string": "2020-01-01T00:00:00+01:00",
+///                 "doc_count": 3
+///             }
+///         ]
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct DateHistogramAggregation {
+    /// The field to aggregate on. Has to be a date fast field.
+    pub field: String,
+    /// Calendar-aware interval of the buckets.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub calendar_interval: Option<CalendarInterval>,
+    /// Fixed interval of the buckets, as a positive number followed by a unit of `ms`, `s`, `m`,
+    /// `h` or `d`, e.g. `30s` or `12h`.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub fixed_interval: Option<String>,
+    /// The time zone in which the buckets are computed and the keys are formatted.
+    ///
+    /// Either an IANA time zone name like `Europe/Berlin`, a fixed offset like `+01:00`, or
+    /// `UTC`. Defaults to `UTC`.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub time_zone: Option<String>,
+    /// Shifts the start of the buckets by the given duration, e.g. `+6h` to start daily buckets
+    /// at 6am. Uses the same units as `fixed_interval`.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub offset: Option<String>,
+    /// The minimum number of documents in a bucket to be returned. Defaults to 0.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub min_doc_count: Option<u64>,
+    /// Whether to return the buckets as a hash map, keyed by `key_as_string`.
+    #[serde(default)]
+    pub keyed: bool,
+}
+
+impl DateHistogramAggregation {
+    /// Returns the minimum number of documents required for a bucket to be returned.
+    pub fn min_doc_count(&self) -> u64 {
+        self.min_doc_count.unwrap_or(0)
+    }
+
+    pub(crate) fn rounding(&self) -> crate::Result<DateHistogramRounding> {
+        let interval = match (&self.calendar_interval, &self.fixed_interval) {
+            (Some(calendar_interval), None) => DateInterval::Calendar(*calendar_interval),
+            (None, Some(fixed_interval)) => {
+                let interval_millis = parse_duration_millis(fixed_interval)?;
+                if interval_millis <= 0 {
+                    return Err(TantivyError::InvalidArgument(format!(
+                        "fixed_interval must be a positive duration, but got {}",
+                        fixed_interval
+                    )));
+                }
+                DateInterval::Fixed(interval_millis)
+            }
+            _ => {
+                return Err(TantivyError::InvalidArgument(
+                    "date_histogram requires exactly one of calendar_interval and fixed_interval"
+                        .to_string(),
+                ))
+            }
+        };
+        let time_zone = self
+            .time_zone
+            .as_deref()
+            .map(DateTimeZone::parse)
+            .transpose()?
+            .unwrap_or(DateTimeZone::Fixed(UtcOffset::UTC));
+        let offset_millis = self
+            .offset
+            .as_deref()
+            .map(parse_duration_millis)
+            .transpose()?
+            .unwrap_or(0);
+        Ok(DateHistogramRounding {
+            interval,
+            time_zone,
+            offset_millis,
+        })
+    }
+}
+
+/// Calendar-aware interval of a [`DateHistogramAggregation`].
+///
+/// Serializes to the unit name, e.g. `month`, and also deserializes from the single unit
+/// form like `1M`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CalendarInterval {
+    /// One minute, starting at second 0.
+    #[serde(rename = "minute", alias = "1m")]
+    Minute,
+    /// One hour, starting at minute 0.
+    #[serde(rename = "hour", alias = "1h")]
+    Hour,
+    /// One day, starting at midnight.
+    #[serde(rename = "day", alias = "1d")]
+    Day,
+    /// One week, starting on monday.
+    #[serde(rename = "week", alias = "1w")]
+    Week,
+    /// One month, starting on the first day of the month.
+    #[serde(rename = "month", alias = "1M")]
+    Month,
+    /// One quarter, starting on the first day of January, April, July or October.
+    #[serde(rename = "quarter", alias = "1q")]
+    Quarter,
+    /// One year, starting on the first day of January.
+    #[serde(rename = "year", alias = "1y")]
+    Year,
+}
+
+/// Parses a duration like `30s`, `-1d` or `+6h` into milliseconds.
+fn parse_duration_millis(duration: &str) -> crate::Result<i64> {
+    let invalid_duration = || {
+        TantivyError::InvalidArgument(format!(
+            "invalid duration {}, expected a number followed by one of the units ms, s, m, h, d",
+            duration
+        ))
+    };
+    let (sign, unsigned_duration) = if let Some(stripped) = duration.strip_prefix('-') {
+        (-1, stripped)
+    } else {
+        (1, duration.strip_prefix('+').unwrap_or(duration))
+    };
+    let unit_start = unsigned_duration
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(invalid_duration)?;
+    let (number, unit) = unsigned_duration.split_at(unit_start);
+    let number: i64 = number.parse().map_err(|_| invalid_duration())?;
+    let unit_millis = match unit {
+        "ms" => 1,
+        "s" => 1_000,
+        "m" => 60 * 1_000,
+        "h" => 60 * 60 * 1_000,
+        "d" => 24 * 60 * 60 * 1_000,
+        _ => return Err(invalid_duration()),
+    };
+    number
+        .checked_mul(unit_millis)
+        .map(|millis| sign * millis)
+        .ok_or_else(invalid_duration)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum DateInterval {
+    Calendar(CalendarInterval),
+    Fixed(i64),
+}
+
+#[derive(Clone, Copy)]
+enum DateTimeZone {
+    Fixed(UtcOffset),
+    Named(&'static Tz),
+}
+
+impl Debug for DateTimeZone {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DateTimeZone::Fixed(offset) => f.debug_tuple("Fixed").field(offset).finish(),
+            DateTimeZone::Named(tz) => f.debug_tuple("Named").field(&tz.name()).finish(),
+        }
+    }
+}
+
+impl PartialEq for DateTimeZone {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (DateTimeZone::Fixed(left), DateTimeZone::Fixed(right)) => left == right,
+            (DateTimeZone::Named(left), DateTimeZone::Named(right)) => left.name() == right.name(),
+            _ => false,
+        }
+    }
+}
+
+impl DateTimeZone {
+    fn parse(time_zone: &str) -> crate::Result<Self> {
+        if time_zone == "UTC" || time_zone == "Z" {
+            return Ok(DateTimeZone::Fixed(UtcOffset::UTC));
+        }
+        if time_zone.starts_with('+') || time_zone.starts_with('-') {
+            return parse_utc_offset(time_zone).map(DateTimeZone::Fixed);
+        }
+        timezones::get_by_name(time_zone)
+            .map(DateTimeZone::Named)
+            .ok_or_else(|| {
+                TantivyError::InvalidArgument(format!("unknown time zone {}", time_zone))
+            })
+    }
+
+    fn utc_offset_at(&self, date_time: &OffsetDateTime) -> UtcOffset {
+        match self {
+            DateTimeZone::Fixed(offset) => *offset,
+            DateTimeZone::Named(tz) => tz.get_offset_utc(date_time).to_utc(),
+        }
+    }
+
+    /// Converts a local date time in this time zone to an absolute point in time.
+    ///
+    /// Ambiguous local times, e.g. when the clocks are turned back, resolve to the earlier point
+    /// in time. Local times skipped by a transition are interpreted with the offset in effect
+    /// before the transition.
+    fn local_to_utc(&self, local: PrimitiveDateTime) -> OffsetDateTime {
+        let tz = match self {
+            DateTimeZone::Fixed(offset) => return local.assume_offset(*offset),
+            DateTimeZone::Named(tz) => tz,
+        };
+        match tz.get_offset_local(&local.assume_utc()) {
+            OffsetResult::Some(offset) => local.assume_offset(offset.to_utc()),
+            OffsetResult::Ambiguous(first, second) => local
+                .assume_offset(first.to_utc())
+                .min(local.assume_offset(second.to_utc())),
+            OffsetResult::None => {
+                let offset_before = tz.get_offset_utc(&(local.assume_utc() - Duration::DAY));
+                local.assume_offset(offset_before.to_utc())
+            }
+        }
+    }
+}
+
+fn parse_utc_offset(offset: &str) -> crate::Result<UtcOffset> {
+    let invalid_offset = || {
+        TantivyError::InvalidArgument(format!(
+            "invalid time zone offset {}, expected e.g. +01:00",
+            offset
+        ))
+    };
+    let sign: i8 = if offset.starts_with('-') { -1 } else { 1 };
+    let digits: String = offset[1..].chars().filter(|c| *c != ':').collect();
+    if !(digits.len() == 2 || digits.len() == 4) || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return Err(invalid_offset());
+    }
+    let hours: i8 = digits[..2].parse().map_err(|_| invalid_offset())?;
+    let minutes: i8 = digits
+        .get(2..)
+        .map_or(Ok(0), str::parse)
+        .map_err(|_| invalid_offset())?;
+    UtcOffset::from_hms(sign * hours, sign * minutes, 0).map_err(|_| invalid_offset())
+}
+
+fn millis_to_date_time(millis: i64) -> crate::Result<OffsetDateTime> {
+    OffsetDateTime::from_unix_timestamp_nanos(millis as i128 * 1_000_000).map_err(|_| {
+        TantivyError::InvalidArgument(format!(
+            "date {} (milliseconds since epoch) is out of the supported range",
+            millis
+        ))
+    })
+}
+
+fn date_time_to_millis(date_time: OffsetDateTime) -> i64 {
+    (date_time.unix_timestamp_nanos() / 1_000_000) as i64
+}
+
+fn first_day_of_month(year: i32, month: u8) -> Date {
+    Date::from_calendar_date(year, Month::try_from(month).expect("invalid month"), 1)
+        .expect("invalid date")
+}
+
+/// Computes the bucket keys of a date histogram, in milliseconds since epoch.
+///
+/// Each bucket covers the time range from its key to the key of the next bucket.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct DateHistogramRounding {
+    interval: DateInterval,
+    time_zone: DateTimeZone,
+    offset_millis: i64,
+}
+
+impl DateHistogramRounding {
+    fn to_local(&self, millis: i64) -> crate::Result<PrimitiveDateTime> {
+        let date_time = millis_to_date_time(millis)?;
+        let date_time = date_time.to_offset(self.time_zone.utc_offset_at(&date_time));
+        Ok(PrimitiveDateTime::new(date_time.date(), date_time.time()))
+    }
+
+    fn to_utc_millis(&self, local: PrimitiveDateTime) -> i64 {
+        date_time_to_millis(self.time_zone.local_to_utc(local))
+    }
+
+    /// Returns the key of the bucket `millis` falls into.
+    pub(crate) fn round(&self, millis: i64) -> crate::Result<i64> {
+        let local = self.to_local(millis - self.offset_millis)?;
+        let rounded_local = match self.interval {
+            DateInterval::Fixed(interval_millis) => {
+                let local_millis = date_time_to_millis(local.assume_utc());
+                let rounded_millis = local_millis.div_euclid(interval_millis) * interval_millis;
+                let rounded = millis_to_date_time(rounded_millis)?;
+                PrimitiveDateTime::new(rounded.date(), rounded.time())
+            }
+            DateInterval::Calendar(calendar_interval) => {
+                let date = local.date();
+                match calendar_interval {
+                    CalendarInterval::Minute => PrimitiveDateTime::new(
+                        date,
+                        Time::from_hms(local.hour(), local.minute(), 0).expect("invalid time"),
+                    ),
+                    CalendarInterval::Hour => PrimitiveDateTime::new(
+                        date,
+                        Time::from_hms(local.hour(), 0, 0).expect("invalid time"),
+                    ),
+                    CalendarInterval::Day => date.midnight(),
+                    CalendarInterval::Week => {
+                        let days_from_monday = date.weekday().number_days_from_monday();
+                        (date - Duration::days(days_from_monday as i64)).midnight()
+                    }
+                    CalendarInterval::Month => {
+                        first_day_of_month(date.year(), date.month() as u8).midnight()
+                    }
+                    CalendarInterval::Quarter => {
+                        let first_month_of_quarter = (date.month() as u8 - 1) / 3 * 3 + 1;
+                        first_day_of_month(date.year(), first_month_of_quarter).midnight()
+                    }
+                    CalendarInterval::Year => first_day_of_month(date.year(), 1).midnight(),
+                }
+            }
+        };
+        Ok(self.to_utc_millis(rounded_local) + self.offset_millis)
+    }
+
+    /// Returns the key of the bucket following the bucket with the key `key`.
+    pub(crate) fn next_key(&self, key: i64) -> crate::Result<i64> {
+        let mut local = self.to_local(key - self.offset_millis)?;
+        // Local times skipped by a daylight saving time transition map to the same point in time
+        // as the following bucket, so we advance until we reach a new key.
+        loop {
+            local = match self.interval {
+                DateInterval::Fixed(interval_millis) => {
+                    local + Duration::milliseconds(interval_millis)
+                }
+                DateInterval::Calendar(CalendarInterval::Minute) => local + Duration::MINUTE,
+                DateInterval::Calendar(CalendarInterval::Hour) => local + Duration::HOUR,
+                DateInterval::Calendar(CalendarInterval::Day) => local + Duration::DAY,
+                DateInterval::Calendar(CalendarInterval::Week) => local + Duration::WEEK,
+                DateInterval::Calendar(CalendarInterval::Month) => {
+                    add_months(local.date(), 1).midnight()
+                }
+                DateInterval::Calendar(CalendarInterval::Quarter) => {
+                    add_months(local.date(), 3).midnight()
+                }
+                DateInterval::Calendar(CalendarInterval::Year) => {
+                    add_months(local.date(), 12).midnight()
+                }
+            };
+            let next_key = self.to_utc_millis(local) + self.offset_millis;
+            if next_key > key {
+                return Ok(next_key);
+            }
+        }
+    }
+
+    /// Formats the bucket key as RFC3339 in the time zone of the request.
+    fn format(&self, key: i64) -> crate::Result<String> {
+        let date_time = millis_to_date_time(key)?;
+        let date_time = date_time.to_offset(self.time_zone.utc_offset_at(&date_time));
+        date_time
+            .format(&Rfc3339)
+            .map_err(|err| TantivyError::InvalidArgument(err.to_string()))
+    }
+}
+
+/// Adds months to a date, that is the first day of a month.
+fn add_months(date: Date, months: u8) -> Date {
+    let month_index = date.month() as i32 - 1 + months as i32;
+    first_day_of_month(
+        date.year() + month_index.div_euclid(12),
+        (month_index.rem_euclid(12) + 1) as u8,
+    )
+}
+
+/// The estimated memory of a bucket in the bucket map, without its sub aggregations.
+const DATE_HISTOGRAM_BUCKET_MEMORY: u64 =
+    std::mem::size_of::<(i64, SegmentDateHistogramBucketEntry)>() as u64;
+
+#[derive(Clone, PartialEq, Default)]
+struct SegmentDateHistogramBucketEntry {
+    doc_count: u64,
+    sub_aggregations: Option<SegmentAggregationResultsCollector>,
+}
+
+impl Debug for SegmentDateHistogramBucketEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SegmentDateHistogramBucketEntry")
+            .field("doc_count", &self.doc_count)
+            .finish()
+    }
+}
+
+impl SegmentDateHistogramBucketEntry {
+    fn into_intermediate_bucket_entry(
+        self,
+        key: i64,
+        agg_with_accessor: &AggregationsWithAccessor,
+    ) -> crate::Result<IntermediateHistogramBucketEntry> {
+        let sub_aggregation = if let Some(sub_aggregation) = self.sub_aggregations {
+            sub_aggregation.into_intermediate_aggregations_result(agg_with_accessor)?
+        } else {
+            Default::default()
+        };
+        Ok(IntermediateHistogramBucketEntry {
+            key: key as f64,
+            doc_count: self.doc_count,
+            sub_aggregation,
+        })
+    }
+}
+
+/// The collector puts the dates from the fast field into their calendar or fixed interval
+/// buckets.
+///
+/// Since the buckets are not necessarily of equal length, they are kept in a map by their key.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SegmentDateHistogramCollector {
+    buckets: FxHashMap<i64, SegmentDateHistogramBucketEntry>,
+    rounding: DateHistogramRounding,
+    /// The time range `[start, end)` of the last bucket a document was put into, to avoid
+    /// rounding documents with close dates over and over again.
+    last_bucket_range: Option<(i64, i64)>,
+    blueprint: Option<SegmentAggregationResultsCollector>,
+}
+
+impl SegmentDateHistogramCollector {
+    /// Returns the estimated memory in bytes of the collector before it collects any document.
+    pub(crate) fn memory_estimate(&self) -> u64 {
+        std::mem::size_of::<Self>() as u64
+            + self
+                .blueprint
+                .as_ref()
+                .map_or(0, |blueprint| blueprint.memory_estimate())
+    }
+
+    pub(crate) fn from_req_and_validate(
+        req: &DateHistogramAggregation,
+        sub_aggregation: &AggregationsWithAccessor,
+    ) -> crate::Result<Self> {
+        let rounding = req.rounding()?;
+        let blueprint = if sub_aggregation.is_empty() {
+            None
+        } else {
+            Some(SegmentAggregationResultsCollector::from_req_and_validate(
+                sub_aggregation,
+            )?)
+        };
+        Ok(Self {
+            buckets: Default::default(),
+            rounding,
+            last_bucket_range: None,
+            blueprint,
+        })
+    }
+
+    pub fn into_intermediate_bucket_result(
+        self,
+        agg_with_accessor: &BucketAggregationWithAccessor,
+    ) -> crate::Result<IntermediateBucketResult> {
+        let buckets = self
+            .buckets
+            .into_iter()
+            .sorted_unstable_by_key(|(key, _)| *key)
+            .map(|(key, bucket)| {
+                bucket.into_intermediate_bucket_entry(key, &agg_with_accessor.sub_aggregation)
+            })
+            .collect::<crate::Result<Vec<_>>>()?;
+        Ok(IntermediateBucketResult::Histogram { buckets })
+    }
+
+    #[inline]
+    pub(crate) fn collect_block(
+        &mut self,
+        doc: &[DocId],
+        bucket_with_accessor: &BucketAggregationWithAccessor,
+        force_flush: bool,
+    ) -> crate::Result<()> {
+        let accessor = bucket_with_accessor
+            .accessor
+            .as_ref()
+            .and_then(|accessor| accessor.as_single())
+            .expect("unexpected fast field cardinality");
+        for &doc in doc {
+            let timestamp_micros = i64::from_u64(accessor.get_val(doc));
+            let millis = timestamp_micros.div_euclid(1_000);
+            let key = match self.last_bucket_range {
+                Some((start, end)) if start <= millis && millis < end => start,
+                _ => {
+                    let key = self.rounding.round(millis)?;
+                    self.last_bucket_range = Some((key, self.rounding.next_key(key)?));
+                    key
+                }
+            };
+
+            let blueprint = &self.blueprint;
+            let bucket = self.buckets.entry(key).or_insert_with(|| {
+                bucket_with_accessor.bucket_count.add_count(1);
+                bucket_with_accessor.bucket_count.add_memory_consumed(
+                    DATE_HISTOGRAM_BUCKET_MEMORY
+                        + blueprint
+                            .as_ref()
+                            .map_or(0, |blueprint| blueprint.memory_estimate()),
+                );
+                SegmentDateHistogramBucketEntry {
+                    doc_count: 0,
+                    sub_aggregations: blueprint.clone(),
+                }
+            });
+            bucket.doc_count += 1;
+            if let Some(sub_aggregations) = bucket.sub_aggregations.as_mut() {
+                sub_aggregations.collect(doc, &bucket_with_accessor.sub_aggregation)?;
+            }
+        }
+        bucket_with_accessor.bucket_count.validate_limits()?;
+
+        if force_flush {
+            for bucket in self.buckets.values_mut() {
+                if let Some(sub_aggregations) = bucket.sub_aggregations.as_mut() {
+                    sub_aggregations
+                        .flush_staged_docs(&bucket_with_accessor.sub_aggregation, force_flush)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// Convert to BucketEntry, fill gaps if requested and add the formatted keys
+pub(crate) fn intermediate_date_histogram_buckets_to_final_buckets(
+    buckets: Vec<IntermediateHistogramBucketEntry>,
+    date_histogram_req: &DateHistogramAggregation,
+    sub_aggregation: &AggregationsInternal,
+    bucket_count: &BucketCount,
+) -> crate::Result<Vec<BucketEntry>> {
+    let rounding = date_histogram_req.rounding()?;
+    let buckets = if date_histogram_req.min_doc_count() == 0 {
+        // The intermediate result does not contain empty buckets, so we add them between the
+        // first and the last bucket.
+        let fill_gaps_keys = match (buckets.first(), buckets.last()) {
+            (Some(first), Some(last)) => {
+                generate_keys(&rounding, first.key as i64, last.key as i64, bucket_count)?
+            }
+            _ => Vec::new(),
+        };
+        let empty_sub_aggregation = IntermediateAggregationResults::empty_from_req(sub_aggregation);
+        buckets
+            .into_iter()
+            .merge_join_by(fill_gaps_keys, |existing_bucket, fill_gaps_key| {
+                existing_bucket
+                    .key
+                    .partial_cmp(&(*fill_gaps_key as f64))
+                    .unwrap_or(Ordering::Equal)
+            })
+            .map(|either| match either {
+                itertools::EitherOrBoth::Both(existing, _) => existing,
+                itertools::EitherOrBoth::Left(existing) => existing,
+                itertools::EitherOrBoth::Right(missing_key) => IntermediateHistogramBucketEntry {
+                    key: missing_key as f64,
+                    doc_count: 0,
+                    sub_aggregation: empty_sub_aggregation.clone(),
+                },
+            })
+            .collect_vec()
+    } else {
+        buckets
+            .into_iter()
+            .filter(|bucket| bucket.doc_count >= date_histogram_req.min_doc_count())
+            .collect_vec()
+    };
+
+    buckets
+        .into_iter()
+        .map(|bucket| {
+            let key_as_string = rounding.format(bucket.key as i64)?;
+            let mut bucket_entry = bucket.into_final_bucket_entry(sub_aggregation, bucket_count)?;
+            bucket_entry.key_as_string = Some(key_as_string);
+            Ok(bucket_entry)
+        })
+        .collect::<crate::Result<Vec<_>>>()
+}
+
+/// Generates all bucket keys from `first_key` to `last_key` inclusive.
+///
+/// The keys are counted against the bucket limit before they are allocated, so that a small
+/// interval over a wide range fails early.
+fn generate_keys(
+    rounding: &DateHistogramRounding,
+    first_key: i64,
+    last_key: i64,
+    bucket_count: &BucketCount,
+) -> crate::Result<Vec<i64>> {
+    let mut num_keys = 1;
+    bucket_count.add_count(1);
+    bucket_count.validate_limits()?;
+    let mut key = first_key;
+    while key < last_key {
+        key = rounding.next_key(key)?;
+        num_keys += 1;
+        bucket_count.add_count(1);
+        bucket_count.validate_limits()?;
+    }
+
+    let mut keys = Vec::with_capacity(num_keys);
+    keys.push(first_key);
+    let mut key = first_key;
+    while key < last_key {
+        key = rounding.next_key(key)?;
+        keys.push(key);
+    }
+    Ok(keys)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use pretty_assertions::assert_eq;
+    use serde_json::Value;
+
+    use super::*;
+    use crate::aggregation::agg_req::Aggregations;
+    use crate::aggregation::intermediate_agg_result::IntermediateAggregationResults;
+    use crate::aggregation::tests::exec_request;
+    use crate::aggregation::{AggregationError, DistributedAggregationCollector};
+    use crate::query::AllQuery;
+    use crate::schema::{Schema, FAST};
+    use crate::{DateTime, Index};
+
+    fn rounding(req: Value) -> DateHistogramRounding {
+        let req: DateHistogramAggregation = serde_json::from_value(req).unwrap();
+        req.rounding().unwrap()
+    }
+
+    fn millis(rfc3339: &str) -> i64 {
+        date_time_to_millis(OffsetDateTime::parse(rfc3339, &Rfc3339).unwrap())
+    }
+
+    fn get_test_index_from_dates(dates: &[&[&str]]) -> crate::Result<Index> {
+        let mut schema_builder = Schema::builder();
+        let date_field = schema_builder.add_date_field("date", FAST);
+        let score_field = schema_builder.add_u64_field("score", FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_for_tests()?;
+            for (segment_id, segment_dates) in dates.iter().enumerate() {
+                for date in segment_dates.iter() {
+                    let date = OffsetDateTime::parse(date, &Rfc3339).unwrap();
+                    index_writer.add_document(doc!(
+                        date_field => DateTime::from_utc(date),
+                        score_field => segment_id as u64,
+                    ))?;
+                }
+                index_writer.commit()?;
+            }
+        }
+        Ok(index)
+    }
+
+    #[test]
+    fn parse_duration_test() {
+        assert_eq!(parse_duration_millis("30s").unwrap(), 30_000);
+        assert_eq!(parse_duration_millis("+6h").unwrap(), 6 * 3_600_000);
+        assert_eq!(parse_duration_millis("-1d").unwrap(), -86_400_000);
+        assert_eq!(parse_duration_millis("500ms").unwrap(), 500);
+        assert!(parse_duration_millis("1w").is_err());
+        assert!(parse_duration_millis("m").is_err());
+        assert!(parse_duration_millis("10").is_err());
+    }
+
+    #[test]
+    fn parse_time_zone_test() {
+        assert_eq!(
+            DateTimeZone::parse("+01:30").unwrap(),
+            DateTimeZone::Fixed(UtcOffset::from_hms(1, 30, 0).unwrap())
+        );
+        assert_eq!(
+            DateTimeZone::parse("-0500").unwrap(),
+            DateTimeZone::Fixed(UtcOffset::from_hms(-5, 0, 0).unwrap())
+        );
+        assert_eq!(
+            DateTimeZone::parse("UTC").unwrap(),
+            DateTimeZone::Fixed(UtcOffset::UTC)
+        );
+        assert!(DateTimeZone::parse("Europe/Berlin").is_ok());
+        assert!(DateTimeZone::parse("Mars/Olympus").is_err());
+        assert!(DateTimeZone::parse("+1:00").is_err());
+    }
+
+    #[test]
+    fn calendar_interval_serde_test() {
+        let interval: CalendarInterval = serde_json::from_str("\"1M\"").unwrap();
+        assert_eq!(interval, CalendarInterval::Month);
+        let interval: CalendarInterval = serde_json::from_str("\"quarter\"").unwrap();
+        assert_eq!(interval, CalendarInterval::Quarter);
+        assert_eq!(
+            serde_json::to_string(&CalendarInterval::Week).unwrap(),
+            "\"week\""
+        );
+    }
+
+    #[test]
+    fn calendar_rounding_test() {
+        let month = rounding(json!({ "field": "date", "calendar_interval": "month" }));
+        let key = month.round(millis("2020-02-29T23:59:59Z")).unwrap();
+        assert_eq!(key, millis("2020-02-01T00:00:00Z"));
+        assert_eq!(month.next_key(key).unwrap(), millis("2020-03-01T00:00:00Z"));
+
+        let quarter = rounding(json!({ "field": "date", "calendar_interval": "quarter" }));
+        let key = quarter.round(millis("2020-12-31T10:00:00Z")).unwrap();
+        assert_eq!(key, millis("2020-10-01T00:00:00Z"));
+        assert_eq!(
+            quarter.next_key(key).unwrap(),
+            millis("2021-01-01T00:00:00Z")
+        );
+
+        let week = rounding(json!({ "field": "date", "calendar_interval": "week" }));
+        // 2022-10-16 is a sunday
+        let key = week.round(millis("2022-10-16T12:00:00Z")).unwrap();
+        assert_eq!(key, millis("2022-10-10T00:00:00Z"));
+
+        let year = rounding(json!({ "field": "date", "calendar_interval": "1y" }));
+        let key = year.round(millis("1969-07-20T20:17:00Z")).unwrap();
+        assert_eq!(key, millis("1969-01-01T00:00:00Z"));
+        assert_eq!(year.next_key(key).unwrap(), millis("1970-01-01T00:00:00Z"));
+    }
+
+    #[test]
+    fn rounding_with_time_zone_and_offset_test() {
+        let day = rounding(json!({
+            "field": "date",
+            "calendar_interval": "day",
+            "time_zone": "-05:00",
+        }));
+        let key = day.round(millis("2022-01-02T03:00:00Z")).unwrap();
+        assert_eq!(key, millis("2022-01-01T00:00:00-05:00"));
+        assert_eq!(day.format(key).unwrap(), "2022-01-01T00:00:00-05:00");
+
+        let day = rounding(json!({
+            "field": "date",
+            "calendar_interval": "day",
+            "offset": "+6h",
+        }));
+        let key = day.round(millis("2022-01-02T03:00:00Z")).unwrap();
+        assert_eq!(key, millis("2022-01-01T06:00:00Z"));
+        assert_eq!(day.next_key(key).unwrap(), millis("2022-01-02T06:00:00Z"));
+
+        let fixed = rounding(json!({ "field": "date", "fixed_interval": "90m" }));
+        let key = fixed.round(millis("2022-01-01T02:00:00Z")).unwrap();
+        assert_eq!(key, millis("2022-01-01T01:30:00Z"));
+        assert_eq!(fixed.next_key(key).unwrap(), millis("2022-01-01T03:00:00Z"));
+    }
+
+    #[test]
+    fn rounding_daylight_saving_time_test() {
+        let day = rounding(json!({
+            "field": "date",
+            "calendar_interval": "day",
+            "time_zone": "Europe/Berlin",
+        }));
+        // The clocks are turned forward on 2022-03-27, the day has 23 hours.
+        let key = day.round(millis("2022-03-27T12:00:00+02:00")).unwrap();
+        assert_eq!(key, millis("2022-03-27T00:00:00+01:00"));
+        let next_key = day.next_key(key).unwrap();
+        assert_eq!(next_key, millis("2022-03-28T00:00:00+02:00"));
+        assert_eq!(next_key - key, 23 * 3_600_000);
+        assert_eq!(day.format(next_key).unwrap(), "2022-03-28T00:00:00+02:00");
+
+        // The clocks are turned back on 2022-10-30, the day has 25 hours.
+        let key = day.round(millis("2022-10-30T23:30:00+01:00")).unwrap();
+        assert_eq!(key, millis("2022-10-30T00:00:00+02:00"));
+        assert_eq!(day.next_key(key).unwrap() - key, 25 * 3_600_000);
+
+        // The hour from 2:00 to 3:00 is skipped.
+        let hour = rounding(json!({
+            "field": "date",
+            "calendar_interval": "hour",
+            "time_zone": "Europe/Berlin",
+        }));
+        let key = hour.round(millis("2022-03-27T01:30:00+01:00")).unwrap();
+        assert_eq!(key, millis("2022-03-27T01:00:00+01:00"));
+        assert_eq!(
+            hour.next_key(key).unwrap(),
+            millis("2022-03-27T03:00:00+02:00")
+        );
+    }
+
+    #[test]
+    fn date_histogram_test() -> crate::Result<()> {
+        let index = get_test_index_from_dates(&[
+            &["2022-01-15T10:00:00Z", "2022-01-31T23:00:00Z"],
+            &["2022-03-01T00:00:00Z", "2022-03-31T12:00:00Z"],
+        ])?;
+
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "histogram": {
+                "date_histogram": { "field": "date", "calendar_interval": "month" },
+                "aggs": { "max_score": { "max": { "field": "score" } } }
+            },
+            "histogram_berlin": {
+                "date_histogram": {
+                    "field": "date",
+                    "calendar_interval": "month",
+                    "time_zone": "Europe/Berlin",
+                    "min_doc_count": 1
+                }
+            }
+        }))
+        .unwrap();
+
+        let res = exec_request(agg_req, &index)?;
+        assert_eq!(
+            res["histogram"]["buckets"],
+            json!([
+                {
+                    "key": 1640995200000.0,
+                    "key_as_string": "2022-01-01T00:00:00Z",
+                    "doc_count": 2,
+                    "max_score": { "value": 0.0 }
+                },
+                {
+                    "key": 1643673600000.0,
+                    "key_as_string": "2022-02-01T00:00:00Z",
+                    "doc_count": 0,
+                    "max_score": { "value": Value::Null }
+                },
+                {
+                    "key": 1646092800000.0,
+                    "key_as_string": "2022-03-01T00:00:00Z",
+                    "doc_count": 2,
+                    "max_score": { "value": 1.0 }
+                }
+            ])
+        );
+        // 2022-01-31T23:00:00Z is in February in Berlin, 2022-03-31T12:00:00Z still in March.
+        assert_eq!(
+            res["histogram_berlin"]["buckets"],
+            json!([
+                {
+                    "key": 1640991600000.0,
+                    "key_as_string": "2022-01-01T00:00:00+01:00",
+                    "doc_count": 1
+                },
+                {
+                    "key": 1643670000000.0,
+                    "key_as_string": "2022-02-01T00:00:00+01:00",
+                    "doc_count": 1
+                },
+                {
+                    "key": 1646089200000.0,
+                    "key_as_string": "2022-03-01T00:00:00+01:00",
+                    "doc_count": 2
+                }
+            ])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn date_histogram_keyed_and_distributed_test() -> crate::Result<()> {
+        let index = get_test_index_from_dates(&[
+            &["2022-01-01T10:00:00Z", "2022-01-01T11:00:00Z"],
+            &["2022-01-01T10:30:00Z", "2022-01-01T13:59:59Z"],
+        ])?;
+
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "histogram": {
+                "date_histogram": { "field": "date", "fixed_interval": "2h", "keyed": true }
+            }
+        }))
+        .unwrap();
+
+        let collector = DistributedAggregationCollector::from_aggs(agg_req.clone(), None);
+        let searcher = index.reader()?.searcher();
+        let intermediate_res: IntermediateAggregationResults =
+            searcher.search(&AllQuery, &collector)?;
+        let mut merged_res: IntermediateAggregationResults =
+            serde_json::from_str(&serde_json::to_string(&intermediate_res)?)?;
+        merged_res.merge_fruits(intermediate_res);
+        let res: Value = serde_json::to_value(merged_res.into_final_bucket_result(agg_req)?)?;
+
+        assert_eq!(
+            res["histogram"]["buckets"],
+            json!({
+                "2022-01-01T10:00:00Z": {
+                    "key": 1641031200000.0,
+                    "key_as_string": "2022-01-01T10:00:00Z",
+                    "doc_count": 6
+                },
+                "2022-01-01T12:00:00Z": {
+                    "key": 1641038400000.0,
+                    "key_as_string": "2022-01-01T12:00:00Z",
+                    "doc_count": 2
+                }
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn date_histogram_fill_gaps_bucket_limit_test() -> crate::Result<()> {
+        let index =
+            get_test_index_from_dates(&[&["2022-01-01T00:00:00Z"], &["2022-01-02T00:00:00Z"]])?;
+
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "histogram": {
+                "date_histogram": { "field": "date", "fixed_interval": "1ms" }
+            }
+        }))
+        .unwrap();
+        let err = exec_request(agg_req, &index).unwrap_err();
+        assert!(matches!(
+            err,
+            TantivyError::AggregationError(AggregationError::BucketLimitExceeded {
+                limit: 65000,
+                ..
+            })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn date_histogram_invalid_request_test() -> crate::Result<()> {
+        let index = get_test_index_from_dates(&[&["2022-01-01T10:00:00Z"]])?;
+
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "histogram": {
+                "date_histogram": {
+                    "field": "date",
+                    "calendar_interval": "day",
+                    "fixed_interval": "1d"
+                }
+            }
+        }))
+        .unwrap();
+        let err = exec_request(agg_req, &index).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "An invalid argument was passed: 'date_histogram requires exactly one of \
+             calendar_interval and fixed_interval'"
+        );
+
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "histogram": {
+                "date_histogram": {
+                    "field": "score",
+                    "calendar_interval": "day"
+                }
+            }
+        }))
+        .unwrap();
+        let err = exec_request(agg_req, &index).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "An invalid argument was passed: 'date_histogram requires a date fast field, but \
+             score is of type U64'"
+        );
+        Ok(())
+    }
+}
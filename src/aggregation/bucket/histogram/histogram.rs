@@ -7,14 +7,15 @@ use serde::{Deserialize, Serialize};
 
 use crate::aggregation::agg_req::AggregationsInternal;
 use crate::aggregation::agg_req_with_accessor::{
-    AggregationsWithAccessor, BucketAggregationWithAccessor,
+    AggregationsWithAccessor, BucketAggregationWithAccessor, FastFieldAccessor,
 };
 use crate::aggregation::agg_result::BucketEntry;
 use crate::aggregation::f64_from_fastfield_u64;
 use crate::aggregation::intermediate_agg_result::{
     IntermediateAggregationResults, IntermediateBucketResult, IntermediateHistogramBucketEntry,
 };
-use crate::aggregation::segment_agg_result::SegmentAggregationResultsCollector;
+use crate::aggregation::segment_agg_result::{BucketCount, SegmentAggregationResultsCollector};
+use crate::fastfield::MultiValuedFastFieldReader;
 use crate::schema::Type;
 use crate::{DocId, TantivyError};
 
@@ -27,6 +28,9 @@ use crate::{DocId, TantivyError};
 ///
 /// For this calculation all fastfield values are converted to f64.
 ///
+/// The field can also be a path inside a json field, whose numeric values are aggregated. A
+/// document with several values is counted once in each bucket containing one of them.
+///
 /// # Returned Buckets
 /// By default buckets are returned between the min and max value of the documents, including empty
 /// buckets.
@@ -211,6 +215,21 @@ pub struct SegmentHistogramCollector {
 }
 
 impl SegmentHistogramCollector {
+    /// Returns the estimated memory in bytes of the collector, including its buckets, which are
+    /// allocated upfront.
+    pub(crate) fn memory_estimate(&self) -> u64 {
+        let sub_aggregations_memory: u64 = self
+            .sub_aggregations
+            .iter()
+            .flatten()
+            .map(|sub_aggregation| sub_aggregation.memory_estimate())
+            .sum();
+        (std::mem::size_of::<Self>()
+            + self.buckets.len() * std::mem::size_of::<SegmentHistogramBucketEntry>())
+            as u64
+            + sub_aggregations_memory
+    }
+
     pub fn into_intermediate_bucket_result(
         self,
         agg_with_accessor: &BucketAggregationWithAccessor,
@@ -254,7 +273,7 @@ impl SegmentHistogramCollector {
         agg_with_accessor
             .bucket_count
             .add_count(buckets.len() as u32);
-        agg_with_accessor.bucket_count.validate_bucket_count()?;
+        agg_with_accessor.bucket_count.validate_limits()?;
 
         Ok(IntermediateBucketResult::Histogram { buckets })
     }
@@ -263,26 +282,46 @@ impl SegmentHistogramCollector {
         req: &HistogramAggregation,
         sub_aggregation: &AggregationsWithAccessor,
         field_type: Type,
-        accessor: &dyn Column<u64>,
+        min_max_value: Option<(u64, u64)>,
+        bucket_count: &BucketCount,
     ) -> crate::Result<Self> {
         req.validate()?;
-        let min = f64_from_fastfield_u64(accessor.min_value(), &field_type);
-        let max = f64_from_fastfield_u64(accessor.max_value(), &field_type);
+        let min_max = min_max_value.map(|(min, max)| {
+            (
+                f64_from_fastfield_u64(min, &field_type),
+                f64_from_fastfield_u64(max, &field_type),
+            )
+        });
 
-        let (min, max) = get_req_min_max(req, Some((min, max)));
+        let (min, max) = get_req_min_max(req, min_max);
+
+        let first_bucket_num =
+            get_bucket_num_f64(min, req.interval, req.offset.unwrap_or(0.0)) as i64;
+        let num_buckets = get_num_buckets(req, min, max);
+        let blueprint = if sub_aggregation.is_empty() {
+            None
+        } else {
+            Some(SegmentAggregationResultsCollector::from_req_and_validate(
+                sub_aggregation,
+            )?)
+        };
+        // The buckets are allocated upfront, so a small interval over a wide range must fail
+        // before the allocation.
+        let bucket_memory = (std::mem::size_of::<SegmentHistogramBucketEntry>()
+            + std::mem::size_of::<f64>()) as u64
+            + blueprint
+                .as_ref()
+                .map_or(0, |blueprint| blueprint.memory_estimate());
+        bucket_count.add_memory_consumed(num_buckets.saturating_mul(bucket_memory));
+        bucket_count.validate_limits()?;
 
         // We compute and generate the buckets range (min, max) based on the request and the min
         // max in the fast field, but this is likely not ideal when this is a subbucket, where many
         // unnecessary buckets may be generated.
         let buckets = generate_buckets(req, min, max);
 
-        let sub_aggregations = if sub_aggregation.is_empty() {
-            None
-        } else {
-            let sub_aggregation =
-                SegmentAggregationResultsCollector::from_req_and_validate(sub_aggregation)?;
-            Some(buckets.iter().map(|_| sub_aggregation.clone()).collect())
-        };
+        let sub_aggregations =
+            blueprint.map(|blueprint| buckets.iter().map(|_| blueprint.clone()).collect());
 
         let buckets = buckets
             .iter()
@@ -292,9 +331,6 @@ impl SegmentHistogramCollector {
             })
             .collect();
 
-        let first_bucket_num =
-            get_bucket_num_f64(min, req.interval, req.offset.unwrap_or(0.0)) as i64;
-
         let bounds = req.hard_bounds.unwrap_or(HistogramBounds {
             min: f64::MIN,
             max: f64::MAX,
@@ -317,6 +353,40 @@ impl SegmentHistogramCollector {
         doc: &[DocId],
         bucket_with_accessor: &BucketAggregationWithAccessor,
         force_flush: bool,
+    ) -> crate::Result<()> {
+        match bucket_with_accessor
+            .accessor
+            .as_ref()
+            .expect("missing fast field accessor")
+        {
+            FastFieldAccessor::Multi(accessor) => {
+                self.collect_block_multi(doc, accessor, &bucket_with_accessor.sub_aggregation)?
+            }
+            accessor => self.collect_block_single(
+                doc,
+                accessor
+                    .as_single()
+                    .expect("unexpected fast field cardinatility"),
+                &bucket_with_accessor.sub_aggregation,
+            )?,
+        }
+        if force_flush {
+            if let Some(sub_aggregations) = self.sub_aggregations.as_mut() {
+                for sub_aggregation in sub_aggregations {
+                    sub_aggregation
+                        .flush_staged_docs(&bucket_with_accessor.sub_aggregation, force_flush)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    #[inline]
+    fn collect_block_single(
+        &mut self,
+        doc: &[DocId],
+        accessor: &dyn Column<u64>,
+        sub_aggregation: &AggregationsWithAccessor,
     ) -> crate::Result<()> {
         let bounds = self.bounds;
         let interval = self.interval;
@@ -325,10 +395,6 @@ impl SegmentHistogramCollector {
         let get_bucket_num =
             |val| (get_bucket_num_f64(val, interval, offset) as i64 - first_bucket_num) as usize;
 
-        let accessor = bucket_with_accessor
-            .accessor
-            .as_single()
-            .expect("unexpected fast field cardinatility");
         let mut iter = doc.chunks_exact(4);
         for docs in iter.by_ref() {
             let val0 = self.f64_from_fastfield_u64(accessor.get_val(docs[0]));
@@ -346,28 +412,28 @@ impl SegmentHistogramCollector {
                 &bounds,
                 bucket_pos0,
                 docs[0],
-                &bucket_with_accessor.sub_aggregation,
+                sub_aggregation,
             )?;
             self.increment_bucket_if_in_bounds(
                 val1,
                 &bounds,
                 bucket_pos1,
                 docs[1],
-                &bucket_with_accessor.sub_aggregation,
+                sub_aggregation,
             )?;
             self.increment_bucket_if_in_bounds(
                 val2,
                 &bounds,
                 bucket_pos2,
                 docs[2],
-                &bucket_with_accessor.sub_aggregation,
+                sub_aggregation,
             )?;
             self.increment_bucket_if_in_bounds(
                 val3,
                 &bounds,
                 bucket_pos3,
                 docs[3],
-                &bucket_with_accessor.sub_aggregation,
+                sub_aggregation,
             )?;
         }
         for &doc in iter.remainder() {
@@ -382,15 +448,37 @@ impl SegmentHistogramCollector {
                 self.buckets[bucket_pos].key,
                 get_bucket_val(val, self.interval, self.offset) as f64
             );
-            self.increment_bucket(bucket_pos, doc, &bucket_with_accessor.sub_aggregation)?;
+            self.increment_bucket(bucket_pos, doc, sub_aggregation)?;
         }
-        if force_flush {
-            if let Some(sub_aggregations) = self.sub_aggregations.as_mut() {
-                for sub_aggregation in sub_aggregations {
-                    sub_aggregation
-                        .flush_staged_docs(&bucket_with_accessor.sub_aggregation, force_flush)?;
+        Ok(())
+    }
+
+    /// Puts each document once into every bucket containing one of its values.
+    fn collect_block_multi(
+        &mut self,
+        docs: &[DocId],
+        accessor: &MultiValuedFastFieldReader<u64>,
+        sub_aggregation: &AggregationsWithAccessor,
+    ) -> crate::Result<()> {
+        let mut vals = Vec::new();
+        let mut bucket_positions = Vec::new();
+        for &doc in docs {
+            accessor.get_vals(doc, &mut vals);
+            bucket_positions.clear();
+            for &val in &vals {
+                let val = self.f64_from_fastfield_u64(val);
+                if self.bounds.contains(val) {
+                    bucket_positions.push(
+                        (get_bucket_num_f64(val, self.interval, self.offset) as i64
+                            - self.first_bucket_num) as usize,
+                    );
                 }
             }
+            bucket_positions.sort_unstable();
+            bucket_positions.dedup();
+            for &bucket_pos in &bucket_positions {
+                self.increment_bucket(bucket_pos, doc, sub_aggregation)?;
+            }
         }
         Ok(())
     }
@@ -441,16 +529,28 @@ fn get_bucket_num_f64(val: f64, interval: f64, offset: f64) -> f64 {
 }
 
 #[inline]
-fn get_bucket_val(val: f64, interval: f64, offset: f64) -> f64 {
+pub(crate) fn get_bucket_val(val: f64, interval: f64, offset: f64) -> f64 {
     let bucket_pos = get_bucket_num_f64(val, interval, offset);
     bucket_pos * interval + offset
 }
 
+/// Returns the number of buckets spanning `min..=max` with req.interval
+fn get_num_buckets(req: &HistogramAggregation, min: f64, max: f64) -> u64 {
+    let offset = req.offset.unwrap_or(0.0);
+    let first_bucket_num = get_bucket_num_f64(min, req.interval, offset) as i64;
+    let last_bucket_num = get_bucket_num_f64(max, req.interval, offset) as i64;
+    last_bucket_num
+        .saturating_sub(first_bucket_num)
+        .saturating_add(1)
+        .max(0) as u64
+}
+
 // Convert to BucketEntry and fill gaps
 fn intermediate_buckets_to_final_buckets_fill_gaps(
     buckets: Vec<IntermediateHistogramBucketEntry>,
     histogram_req: &HistogramAggregation,
     sub_aggregation: &AggregationsInternal,
+    bucket_count: &BucketCount,
 ) -> crate::Result<Vec<BucketEntry>> {
     // Generate the full list of buckets without gaps.
     //
@@ -463,6 +563,11 @@ fn intermediate_buckets_to_final_buckets_fill_gaps(
         let max = buckets[buckets.len() - 1].key;
         Some((min, max))
     };
+    // A small interval over wide extended_bounds must fail before generating the keys.
+    let (min, max) = get_req_min_max(histogram_req, min_max);
+    let num_buckets = get_num_buckets(histogram_req, min, max);
+    bucket_count.add_count(u32::try_from(num_buckets).unwrap_or(u32::MAX));
+    bucket_count.validate_limits()?;
     let fill_gaps_buckets = generate_buckets_with_opt_minmax(histogram_req, min_max);
 
     let empty_sub_aggregation = IntermediateAggregationResults::empty_from_req(sub_aggregation);
@@ -491,7 +596,9 @@ fn intermediate_buckets_to_final_buckets_fill_gaps(
                 sub_aggregation: empty_sub_aggregation.clone(),
             },
         })
-        .map(|intermediate_bucket| intermediate_bucket.into_final_bucket_entry(sub_aggregation))
+        .map(|intermediate_bucket| {
+            intermediate_bucket.into_final_bucket_entry(sub_aggregation, bucket_count)
+        })
         .collect::<crate::Result<Vec<_>>>()
 }
 
@@ -500,18 +607,26 @@ pub(crate) fn intermediate_histogram_buckets_to_final_buckets(
     buckets: Vec<IntermediateHistogramBucketEntry>,
     histogram_req: &HistogramAggregation,
     sub_aggregation: &AggregationsInternal,
+    bucket_count: &BucketCount,
 ) -> crate::Result<Vec<BucketEntry>> {
     if histogram_req.min_doc_count() == 0 {
         // With min_doc_count != 0, we may need to add buckets, so that there are no
         // gaps, since intermediate result does not contain empty buckets (filtered to
         // reduce serialization size).
 
-        intermediate_buckets_to_final_buckets_fill_gaps(buckets, histogram_req, sub_aggregation)
+        intermediate_buckets_to_final_buckets_fill_gaps(
+            buckets,
+            histogram_req,
+            sub_aggregation,
+            bucket_count,
+        )
     } else {
         buckets
             .into_iter()
             .filter(|histogram_bucket| histogram_bucket.doc_count >= histogram_req.min_doc_count())
-            .map(|histogram_bucket| histogram_bucket.into_final_bucket_entry(sub_aggregation))
+            .map(|histogram_bucket| {
+                histogram_bucket.into_final_bucket_entry(sub_aggregation, bucket_count)
+            })
             .collect::<crate::Result<Vec<_>>>()
     }
 }
@@ -553,7 +668,7 @@ pub(crate) fn generate_buckets_with_opt_minmax(
     let offset = req.offset.unwrap_or(0.0);
     let first_bucket_num = get_bucket_num_f64(min, req.interval, offset) as i64;
     let last_bucket_num = get_bucket_num_f64(max, req.interval, offset) as i64;
-    let mut buckets = Vec::with_capacity((first_bucket_num..=last_bucket_num).count());
+    let mut buckets = Vec::with_capacity(get_num_buckets(req, min, max) as usize);
     for bucket_pos in first_bucket_num..=last_bucket_num {
         let bucket_key = bucket_pos as f64 * req.interval + offset;
         buckets.push(bucket_key);
@@ -678,7 +793,7 @@ fn generate_buckets_test() {
 mod tests {
 
     use pretty_assertions::assert_eq;
-    use serde_json::Value;
+    use serde_json::{json, Value};
 
     use super::*;
     use crate::aggregation::agg_req::{
@@ -687,8 +802,9 @@ mod tests {
     use crate::aggregation::metric::{AverageAggregation, StatsAggregation};
     use crate::aggregation::tests::{
         exec_request, exec_request_with_query, get_test_index_2_segments,
-        get_test_index_from_values, get_test_index_with_num_docs,
+        get_test_index_from_values, get_test_index_with_json_events, get_test_index_with_num_docs,
     };
+    use crate::aggregation::AggregationError;
 
     #[test]
     fn histogram_test_crooked_values() -> crate::Result<()> {
@@ -757,6 +873,48 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn histogram_json_path_test() -> crate::Result<()> {
+        histogram_json_path_merge_segments(false)?;
+        histogram_json_path_merge_segments(true)
+    }
+
+    fn histogram_json_path_merge_segments(merge_segments: bool) -> crate::Result<()> {
+        let index = get_test_index_with_json_events(merge_segments)?;
+
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "price": {
+                "histogram": { "field": "event.items.price", "interval": 10.0 },
+                "aggs": { "latency": { "stats": { "field": "event.latency" } } }
+            },
+            "price_stats": { "stats": { "field": "event.items.price" } }
+        }))
+        .unwrap();
+
+        let res = exec_request(agg_req, &index)?;
+        let buckets = &res["price"]["buckets"];
+        assert_eq!(buckets[0]["key"], -10.0);
+        assert_eq!(buckets[0]["doc_count"], 1);
+        assert_eq!(buckets[0]["latency"]["max"], 120.0);
+        assert_eq!(buckets[1]["key"], 0.0);
+        assert_eq!(buckets[1]["doc_count"], 1);
+        assert_eq!(buckets[2]["key"], 10.0);
+        assert_eq!(buckets[2]["doc_count"], 1);
+        assert_eq!(buckets[2]["latency"]["max"], 12.0);
+        // The prices 25 and 28 of the same document are counted once.
+        assert_eq!(buckets[3]["key"], 20.0);
+        assert_eq!(buckets[3]["doc_count"], 1);
+        assert_eq!(buckets[3]["latency"]["count"], 1);
+        assert_eq!(buckets[4], Value::Null);
+
+        let stats = &res["price_stats"];
+        assert_eq!(stats["count"], 5);
+        assert_eq!(stats["sum"], 70.5);
+        assert_eq!(stats["min"], -3.0);
+        assert_eq!(stats["max"], 28.0);
+        Ok(())
+    }
+
     #[test]
     fn histogram_test_min_value_positive_force_merge_segments() -> crate::Result<()> {
         histogram_test_min_value_positive_merge_segments(true)
@@ -1438,4 +1596,40 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn histogram_fill_gaps_bucket_limit_test() -> crate::Result<()> {
+        // The gaps are filled at finalization, e.g. when merging distributed results, so the
+        // extended bounds must not generate more buckets than allowed.
+        let agg_req: Aggregations = vec![(
+            "histogram".to_string(),
+            Aggregation::Bucket(BucketAggregation {
+                bucket_agg: BucketAggregationType::Histogram(HistogramAggregation {
+                    field: "score_f64".to_string(),
+                    interval: 1.0,
+                    extended_bounds: Some(HistogramBounds {
+                        min: 0.0,
+                        max: 1_000_000.0,
+                    }),
+                    ..Default::default()
+                }),
+                sub_aggregation: Default::default(),
+            }),
+        )]
+        .into_iter()
+        .collect();
+
+        let err = IntermediateAggregationResults::default()
+            .into_final_bucket_result(agg_req)
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            TantivyError::AggregationError(AggregationError::BucketLimitExceeded {
+                limit: 65000,
+                ..
+            })
+        ));
+
+        Ok(())
+    }
 }
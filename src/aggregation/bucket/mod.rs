@@ -7,16 +7,32 @@
 //! Results of intermediate buckets are
 //! [`IntermediateBucketResult`](super::intermediate_agg_result::IntermediateBucketResult)
 
+mod composite;
+mod filter;
+mod geo_distance;
+mod geo_grid;
 mod histogram;
 mod range;
+mod significant_terms;
 mod term_agg;
 
 use std::collections::HashMap;
 
+pub use composite::*;
+pub use filter::*;
+pub(crate) use filter::{get_filter_doc_sets, resolve_filter_queries, SegmentFilterCollector};
+pub(crate) use geo_distance::SegmentGeoDistanceCollector;
+pub use geo_distance::*;
+pub(crate) use geo_grid::SegmentGeoGridCollector;
+pub use geo_grid::*;
 pub(crate) use histogram::SegmentHistogramCollector;
 pub use histogram::*;
 pub(crate) use range::SegmentRangeCollector;
 pub use range::*;
+pub(crate) use significant_terms::SegmentSignificantTermsCollector;
+pub use significant_terms::{
+    ChiSquareHeuristic, JlhHeuristic, SignificanceHeuristic, SignificantTermsAggregation,
+};
 use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 pub use term_agg::*;
 
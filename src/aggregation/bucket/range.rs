@@ -1,17 +1,19 @@
 use std::fmt::Debug;
 use std::ops::Range;
 
+use fastfield_codecs::Column;
 use rustc_hash::FxHashMap;
 use serde::{Deserialize, Serialize};
 
 use crate::aggregation::agg_req_with_accessor::{
-    AggregationsWithAccessor, BucketAggregationWithAccessor,
+    AggregationsWithAccessor, BucketAggregationWithAccessor, FastFieldAccessor,
 };
 use crate::aggregation::intermediate_agg_result::{
     IntermediateBucketResult, IntermediateRangeBucketEntry, IntermediateRangeBucketResult,
 };
 use crate::aggregation::segment_agg_result::{BucketCount, SegmentAggregationResultsCollector};
 use crate::aggregation::{f64_from_fastfield_u64, f64_to_fastfield_u64, Key, SerializedKey};
+use crate::fastfield::MultiValuedFastFieldReader;
 use crate::schema::Type;
 use crate::{DocId, TantivyError};
 
@@ -31,6 +33,9 @@ use crate::{DocId, TantivyError};
 /// [`IntermediateRangeBucketEntry`](crate::aggregation::intermediate_agg_result::IntermediateRangeBucketEntry) on the
 /// `DistributedAggregationCollector`.
 ///
+/// The field can also be a path inside a json field, whose numeric values are aggregated as
+/// `f64`. A document with several values is counted once in each bucket containing one of them.
+///
 /// # Limitations/Compatibility
 /// Overlapping ranges are not yet supported.
 ///
@@ -170,6 +175,23 @@ impl SegmentRangeBucketEntry {
 }
 
 impl SegmentRangeCollector {
+    /// Returns the estimated memory in bytes of the collector, including its buckets.
+    pub(crate) fn memory_estimate(&self) -> u64 {
+        let buckets_memory: u64 = self
+            .buckets
+            .iter()
+            .map(|bucket| {
+                std::mem::size_of::<SegmentRangeAndBucketEntry>() as u64
+                    + bucket
+                        .bucket
+                        .sub_aggregation
+                        .as_ref()
+                        .map_or(0, |sub_aggregation| sub_aggregation.memory_estimate())
+            })
+            .sum();
+        std::mem::size_of::<Self>() as u64 + buckets_memory
+    }
+
     pub fn into_intermediate_bucket_result(
         self,
         agg_with_accessor: &BucketAggregationWithAccessor,
@@ -242,7 +264,7 @@ impl SegmentRangeCollector {
             .collect::<crate::Result<_>>()?;
 
         bucket_count.add_count(buckets.len() as u32);
-        bucket_count.validate_bucket_count()?;
+        bucket_count.validate_limits()?;
 
         Ok(SegmentRangeCollector {
             buckets,
@@ -257,11 +279,36 @@ impl SegmentRangeCollector {
         bucket_with_accessor: &BucketAggregationWithAccessor,
         force_flush: bool,
     ) -> crate::Result<()> {
-        let mut iter = doc.chunks_exact(4);
-        let accessor = bucket_with_accessor
+        match bucket_with_accessor
             .accessor
-            .as_single()
-            .expect("unexpected fast field cardinality");
+            .as_ref()
+            .expect("missing fast field accessor")
+        {
+            FastFieldAccessor::Multi(accessor) => {
+                self.collect_block_multi(doc, accessor, &bucket_with_accessor.sub_aggregation)?
+            }
+            accessor => self.collect_block_single(
+                doc,
+                accessor
+                    .as_single()
+                    .expect("unexpected fast field cardinality"),
+                &bucket_with_accessor.sub_aggregation,
+            )?,
+        }
+        if force_flush {
+            self.flush_sub_aggregations(&bucket_with_accessor.sub_aggregation)?;
+        }
+        Ok(())
+    }
+
+    #[inline]
+    fn collect_block_single(
+        &mut self,
+        doc: &[DocId],
+        accessor: &dyn Column<u64>,
+        sub_aggregation: &AggregationsWithAccessor,
+    ) -> crate::Result<()> {
+        let mut iter = doc.chunks_exact(4);
         for docs in iter.by_ref() {
             let val1 = accessor.get_val(docs[0]);
             let val2 = accessor.get_val(docs[1]);
@@ -272,22 +319,61 @@ impl SegmentRangeCollector {
             let bucket_pos3 = self.get_bucket_pos(val3);
             let bucket_pos4 = self.get_bucket_pos(val4);
 
-            self.increment_bucket(bucket_pos1, docs[0], &bucket_with_accessor.sub_aggregation)?;
-            self.increment_bucket(bucket_pos2, docs[1], &bucket_with_accessor.sub_aggregation)?;
-            self.increment_bucket(bucket_pos3, docs[2], &bucket_with_accessor.sub_aggregation)?;
-            self.increment_bucket(bucket_pos4, docs[3], &bucket_with_accessor.sub_aggregation)?;
+            self.increment_bucket(bucket_pos1, docs[0], sub_aggregation)?;
+            self.increment_bucket(bucket_pos2, docs[1], sub_aggregation)?;
+            self.increment_bucket(bucket_pos3, docs[2], sub_aggregation)?;
+            self.increment_bucket(bucket_pos4, docs[3], sub_aggregation)?;
         }
         for &doc in iter.remainder() {
             let val = accessor.get_val(doc);
             let bucket_pos = self.get_bucket_pos(val);
-            self.increment_bucket(bucket_pos, doc, &bucket_with_accessor.sub_aggregation)?;
+            self.increment_bucket(bucket_pos, doc, sub_aggregation)?;
         }
-        if force_flush {
-            for bucket in &mut self.buckets {
-                if let Some(sub_aggregation) = &mut bucket.bucket.sub_aggregation {
-                    sub_aggregation
-                        .flush_staged_docs(&bucket_with_accessor.sub_aggregation, force_flush)?;
-                }
+        Ok(())
+    }
+
+    /// Puts each document once into every bucket containing one of its values.
+    fn collect_block_multi(
+        &mut self,
+        docs: &[DocId],
+        accessor: &MultiValuedFastFieldReader<u64>,
+        sub_aggregation: &AggregationsWithAccessor,
+    ) -> crate::Result<()> {
+        let mut vals = Vec::new();
+        let mut bucket_positions = Vec::new();
+        for &doc in docs {
+            accessor.get_vals(doc, &mut vals);
+            bucket_positions.clear();
+            bucket_positions.extend(vals.iter().map(|&val| self.get_bucket_pos(val)));
+            bucket_positions.sort_unstable();
+            bucket_positions.dedup();
+            for &bucket_pos in &bucket_positions {
+                self.increment_bucket(bucket_pos, doc, sub_aggregation)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Puts a document with a value computed by the caller into its bucket, e.g. the distance of
+    /// a geo point. The value has to be in fast field value space of the collector's field type.
+    #[inline]
+    pub(crate) fn collect_value(
+        &mut self,
+        doc: DocId,
+        val: u64,
+        sub_aggregation: &AggregationsWithAccessor,
+    ) -> crate::Result<()> {
+        let bucket_pos = self.get_bucket_pos(val);
+        self.increment_bucket(bucket_pos, doc, sub_aggregation)
+    }
+
+    pub(crate) fn flush_sub_aggregations(
+        &mut self,
+        sub_aggregation_with_accessor: &AggregationsWithAccessor,
+    ) -> crate::Result<()> {
+        for bucket in &mut self.buckets {
+            if let Some(sub_aggregation) = &mut bucket.bucket.sub_aggregation {
+                sub_aggregation.flush_staged_docs(sub_aggregation_with_accessor, true)?;
             }
         }
         Ok(())
@@ -424,12 +510,16 @@ pub(crate) fn range_to_key(range: &Range<u64>, field_type: &Type) -> Key {
 mod tests {
 
     use fastfield_codecs::MonotonicallyMappableToU64;
+    use serde_json::{json, Value};
 
     use super::*;
     use crate::aggregation::agg_req::{
         Aggregation, Aggregations, BucketAggregation, BucketAggregationType,
     };
-    use crate::aggregation::tests::{exec_request_with_query, get_test_index_with_num_docs};
+    use crate::aggregation::tests::{
+        exec_request, exec_request_with_query, get_test_index_with_json_events,
+        get_test_index_with_num_docs,
+    };
 
     pub fn get_collector_from_ranges(
         ranges: Vec<RangeAggregationRange>,
@@ -607,6 +697,51 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn range_json_path_test() -> crate::Result<()> {
+        range_json_path_merge_segments(false)?;
+        range_json_path_merge_segments(true)
+    }
+
+    fn range_json_path_merge_segments(merge_segments: bool) -> crate::Result<()> {
+        let index = get_test_index_with_json_events(merge_segments)?;
+
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "latency": {
+                "range": {
+                    "field": "event.latency",
+                    "ranges": [{ "to": 20.0 }, { "from": 20.0, "to": 100.0 }, { "from": 100.0 }]
+                },
+                "aggs": { "avg_price": { "avg": { "field": "event.items.price" } } }
+            },
+            "price": {
+                "range": { "field": "event.items.price", "ranges": [{ "to": 10.0 }] }
+            }
+        }))
+        .unwrap();
+
+        let res = exec_request(agg_req, &index)?;
+        let buckets = &res["latency"]["buckets"];
+        assert_eq!(buckets[0]["key"], "*-20");
+        assert_eq!(buckets[0]["doc_count"], 1);
+        assert_eq!(buckets[0]["avg_price"]["value"], 10.25);
+        assert_eq!(buckets[1]["key"], "20-100");
+        assert_eq!(buckets[1]["doc_count"], 1);
+        assert_eq!(buckets[2]["key"], "100-*");
+        assert_eq!(buckets[2]["doc_count"], 1);
+        // The text price is ignored.
+        assert_eq!(buckets[2]["avg_price"]["value"], -3.0);
+        assert_eq!(buckets[3], Value::Null);
+
+        // A document with several prices in a bucket is counted once.
+        let buckets = &res["price"]["buckets"];
+        assert_eq!(buckets[0]["key"], "*-10");
+        assert_eq!(buckets[0]["doc_count"], 2);
+        assert_eq!(buckets[1]["key"], "10-*");
+        assert_eq!(buckets[1]["doc_count"], 2);
+        Ok(())
+    }
+
     #[test]
     fn bucket_test_extend_range_hole() {
         let buckets = vec![(10f64..20f64).into(), (30f64..40f64).into()];
@@ -0,0 +1,552 @@
+use rustc_hash::FxHashMap;
+use serde::{Deserialize, Serialize};
+
+use super::TermBuckets;
+use crate::aggregation::agg_req_with_accessor::{
+    AggregationsWithAccessor, BucketAggregationWithAccessor,
+};
+use crate::aggregation::bucket::get_distinct_vals;
+use crate::aggregation::intermediate_agg_result::{
+    IntermediateBucketResult, IntermediateSignificantTermsBucketResult, SegmentInvertedIndexes,
+};
+use crate::aggregation::segment_agg_result::SegmentAggregationResultsCollector;
+use crate::aggregation::IntermediateKey;
+use crate::error::DataCorruption;
+use crate::schema::Type;
+use crate::{DocId, TantivyError};
+
+const DEFAULT_SIZE: u32 = 10;
+const DEFAULT_MIN_DOC_COUNT: u64 = 3;
+
+/// Creates a bucket for every term, which is unusually frequent in the aggregated documents (the
+/// foreground set) compared to all documents of the index (the background set), e.g. the error
+/// codes that are unusually frequent in the last hour.
+///
+/// The terms are scored by a significance heuristic, which compares the fraction of foreground
+/// documents containing the term with the fraction of background documents containing it. The
+/// background document frequencies are read from the term dictionary of the inverted index, so
+/// they include deleted documents that haven't been merged away yet. Supported are text fields,
+/// that are indexed and have a fast field.
+///
+/// The buckets are sorted by their score, terms with a score of 0 or less are not returned.
+///
+/// Result type is [`BucketResult`](crate::aggregation::agg_result::BucketResult) with
+/// [`SignificantTermBucketEntry`](crate::aggregation::agg_result::SignificantTermBucketEntry) on
+/// the `AggregationCollector`.
+///
+/// # Limitations/Compatibility
+///
+/// The background frequencies are only looked up for the terms of the foreground set, in the term
+/// dictionaries of all segments. With the `DistributedAggregationCollector`, every node returns
+/// the background frequencies of its own foreground terms, so a term which is only part of the
+/// foreground set on some of the nodes gets a lower background frequency, like with the shards of
+/// elasticsearch.
+///
+/// # Request JSON Format
+/// ```json
+/// {
+///     "unusual_errors": {
+///         "significant_terms": {
+///             "field": "error_code",
+///             "chi_square": {}
+///         }
+///     }
+/// }
+/// ```
+///
+/// # Response JSON Format
+/// ```json
+/// {
+///     ...
+///     "aggregations": {
+///         "unusual_errors": {
+///             "doc_count": 120,
+///             "bg_count": 52000,
+///             "buckets": [
+///                 { "key": "E503", "doc_count": 48, "score": 6.91, "bg_count": 310 }
+///             ]
+///         }
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SignificantTermsAggregation {
+    /// The field to aggregate on.
+    pub field: String,
+    /// By default, the 10 terms with the highest score are returned.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub size: Option<u32>,
+    /// Filter all terms that are in less than `min_doc_count` foreground documents. Defaults to 3.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub min_doc_count: Option<u64>,
+    /// Score the terms with the JLH heuristic. This is the default.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub jlh: Option<JlhHeuristic>,
+    /// Score the terms with the chi-square heuristic.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub chi_square: Option<ChiSquareHeuristic>,
+}
+
+impl SignificantTermsAggregation {
+    pub(crate) fn validate(&self) -> crate::Result<()> {
+        if self.jlh.is_some() && self.chi_square.is_some() {
+            return Err(TantivyError::InvalidArgument(
+                "significant_terms accepts only one significance heuristic".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn size(&self) -> usize {
+        self.size.unwrap_or(DEFAULT_SIZE) as usize
+    }
+
+    pub(crate) fn min_doc_count(&self) -> u64 {
+        self.min_doc_count.unwrap_or(DEFAULT_MIN_DOC_COUNT)
+    }
+
+    /// Returns the significance heuristic, JLH if none is set.
+    pub fn heuristic(&self) -> SignificanceHeuristic {
+        match &self.chi_square {
+            Some(chi_square) => SignificanceHeuristic::ChiSquare(chi_square.clone()),
+            None => SignificanceHeuristic::Jlh(self.jlh.clone().unwrap_or_default()),
+        }
+    }
+}
+
+/// The JLH heuristic multiplies the absolute and the relative change of the fraction of documents
+/// containing a term from the background to the foreground set. Terms which are rarer in the
+/// foreground set have a score of 0.
+///
+/// # JSON Format
+/// ```json
+/// { "jlh": {} }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct JlhHeuristic {}
+
+/// The chi-square heuristic tests how much the frequency of a term in the foreground set deviates
+/// from its frequency in the background set.
+///
+/// # JSON Format
+/// ```json
+/// { "chi_square": { "include_negatives": false, "background_is_superset": true } }
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ChiSquareHeuristic {
+    /// Whether to score terms, which are rarer in the foreground set than in the background set.
+    /// Defaults to false.
+    #[serde(default)]
+    pub include_negatives: bool,
+    /// Whether the foreground documents are part of the background set. This is the case, if the
+    /// background set is the whole index. Defaults to true.
+    #[serde(default = "default_background_is_superset")]
+    pub background_is_superset: bool,
+}
+
+fn default_background_is_superset() -> bool {
+    true
+}
+
+impl Default for ChiSquareHeuristic {
+    fn default() -> Self {
+        ChiSquareHeuristic {
+            include_negatives: false,
+            background_is_superset: true,
+        }
+    }
+}
+
+/// The heuristic to score the significance of a term.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SignificanceHeuristic {
+    /// See [`JlhHeuristic`].
+    Jlh(JlhHeuristic),
+    /// See [`ChiSquareHeuristic`].
+    ChiSquare(ChiSquareHeuristic),
+}
+
+impl SignificanceHeuristic {
+    /// Computes the score of a term, which is contained in `subset_freq` of the `subset_size`
+    /// foreground documents and in `superset_freq` of the `superset_size` background documents.
+    pub fn score(
+        &self,
+        subset_freq: u64,
+        subset_size: u64,
+        superset_freq: u64,
+        superset_size: u64,
+    ) -> f64 {
+        match self {
+            SignificanceHeuristic::Jlh(_) => {
+                if subset_size == 0 || superset_size == 0 || superset_freq == 0 {
+                    return 0.0;
+                }
+                let subset_probability = subset_freq as f64 / subset_size as f64;
+                let superset_probability = superset_freq as f64 / superset_size as f64;
+                let absolute_change = subset_probability - superset_probability;
+                if absolute_change <= 0.0 {
+                    return 0.0;
+                }
+                absolute_change * (subset_probability / superset_probability)
+            }
+            SignificanceHeuristic::ChiSquare(chi_square) => {
+                // The contingency table of the documents: n_<in foreground><contains term>.
+                let (n_11, n_10, n_01, n_00) = if chi_square.background_is_superset {
+                    (
+                        subset_freq as f64,
+                        (subset_size - subset_freq) as f64,
+                        superset_freq.saturating_sub(subset_freq) as f64,
+                        superset_size
+                            .saturating_sub(superset_freq)
+                            .saturating_sub(subset_size - subset_freq)
+                            as f64,
+                    )
+                } else {
+                    (
+                        subset_freq as f64,
+                        (subset_size - subset_freq) as f64,
+                        superset_freq as f64,
+                        superset_size.saturating_sub(superset_freq) as f64,
+                    )
+                };
+                let n_1x = n_11 + n_10;
+                let n_0x = n_01 + n_00;
+                let n_x1 = n_11 + n_01;
+                let n_x0 = n_10 + n_00;
+                let n = n_1x + n_0x;
+                if n_1x == 0.0 || n_0x == 0.0 || n_x1 == 0.0 || n_x0 == 0.0 {
+                    return 0.0;
+                }
+                if !chi_square.include_negatives && n_11 / n_1x < n_01 / n_0x {
+                    return 0.0;
+                }
+                let numerator = n_11 * n_00 - n_10 * n_01;
+                n * numerator * numerator / (n_1x * n_0x * n_x1 * n_x0)
+            }
+        }
+    }
+}
+
+/// The collector counts the foreground documents per term ordinal.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SegmentSignificantTermsCollector {
+    term_buckets: TermBuckets,
+    subset_size: u64,
+    blueprint: Option<SegmentAggregationResultsCollector>,
+}
+
+impl SegmentSignificantTermsCollector {
+    /// Returns the estimated memory in bytes of the collector before it collects any document.
+    pub(crate) fn memory_estimate(&self) -> u64 {
+        std::mem::size_of::<Self>() as u64
+            + self
+                .blueprint
+                .as_ref()
+                .map_or(0, |blueprint| blueprint.memory_estimate())
+    }
+
+    pub(crate) fn from_req_and_validate(
+        req: &SignificantTermsAggregation,
+        sub_aggregations: &AggregationsWithAccessor,
+        field_type: Type,
+    ) -> crate::Result<Self> {
+        req.validate()?;
+        if field_type != Type::Str {
+            return Err(TantivyError::InvalidArgument(format!(
+                "significant_terms requires a text field, but {} is of type {:?}",
+                req.field, field_type
+            )));
+        }
+        let blueprint = if sub_aggregations.is_empty() {
+            None
+        } else {
+            Some(SegmentAggregationResultsCollector::from_req_and_validate(
+                sub_aggregations,
+            )?)
+        };
+        Ok(SegmentSignificantTermsCollector {
+            term_buckets: TermBuckets::from_req_and_validate(sub_aggregations)?,
+            subset_size: 0,
+            blueprint,
+        })
+    }
+
+    pub(crate) fn into_intermediate_bucket_result(
+        self,
+        agg_with_accessor: &BucketAggregationWithAccessor,
+    ) -> crate::Result<IntermediateBucketResult> {
+        let inverted_index = agg_with_accessor
+            .inverted_index
+            .as_ref()
+            .expect("internal error: inverted index not loaded for significant terms aggregation");
+        let term_dict = inverted_index.terms();
+
+        let to_key = |term: &[u8]| {
+            std::str::from_utf8(term)
+                .map(|term| IntermediateKey::Str(term.to_string()))
+                .map_err(|utf8_err| DataCorruption::comment_only(utf8_err.to_string()))
+        };
+
+        let mut entries = FxHashMap::default();
+        let mut buffer = vec![];
+        for (term_id, entry) in self.term_buckets.entries {
+            term_dict
+                .ord_to_term(term_id, &mut buffer)
+                .expect("could not find term");
+            entries.insert(
+                to_key(&buffer)?,
+                entry.into_intermediate_bucket_entry(&agg_with_accessor.sub_aggregation)?,
+            );
+        }
+
+        // A term may only be part of the foreground set of some segments, so the background
+        // frequencies are looked up once the results of all segments are merged.
+        Ok(IntermediateBucketResult::SignificantTerms(
+            IntermediateSignificantTermsBucketResult {
+                entries,
+                background: FxHashMap::default(),
+                inverted_indexes: SegmentInvertedIndexes(vec![inverted_index.clone()]),
+                subset_size: self.subset_size,
+                superset_size: agg_with_accessor.num_docs as u64,
+            },
+        ))
+    }
+
+    #[inline]
+    pub(crate) fn collect_block(
+        &mut self,
+        docs: &[DocId],
+        bucket_with_accessor: &BucketAggregationWithAccessor,
+        force_flush: bool,
+    ) -> crate::Result<()> {
+        let accessor = bucket_with_accessor
+            .accessor
+            .as_ref()
+            .expect("missing fast field accessor for significant terms aggregation");
+        let mut vals = vec![];
+        for &doc in docs {
+            self.subset_size += 1;
+            get_distinct_vals(accessor, doc, &mut vals);
+            self.term_buckets.increment_bucket(
+                &vals,
+                doc,
+                &bucket_with_accessor.sub_aggregation,
+                &bucket_with_accessor.bucket_count,
+                &self.blueprint,
+            )?;
+        }
+        if force_flush {
+            self.term_buckets
+                .force_flush(&bucket_with_accessor.sub_aggregation)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::aggregation::agg_req::Aggregations;
+    use crate::aggregation::intermediate_agg_result::IntermediateAggregationResults;
+    use crate::aggregation::tests::exec_request_with_query;
+    use crate::aggregation::DistributedAggregationCollector;
+    use crate::query::TermQuery;
+    use crate::schema::{IndexRecordOption, Schema, FAST, STRING};
+    use crate::{Index, Term};
+
+    // 4 segments with errors of the api service and one segment with errors of the db service,
+    // which is the foreground set in the tests. The "rare" error is unusually frequent for the db
+    // service.
+    fn get_test_index(merge_segments: bool) -> crate::Result<Index> {
+        let mut schema_builder = Schema::builder();
+        let service_field = schema_builder.add_text_field("service", STRING);
+        let error_field = schema_builder.add_text_field("error_code", STRING | FAST);
+        let latency_field = schema_builder.add_f64_field("latency", FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_for_tests()?;
+            let mut segments = vec![];
+            for _ in 0..4 {
+                let mut docs = vec![("api", "common"); 10];
+                docs.push(("api", "rare"));
+                segments.push(docs);
+            }
+            let mut docs = vec![("db", "common"); 4];
+            docs.extend(vec![("db", "rare"); 4]);
+            segments.push(docs);
+            for segment in segments {
+                for (service, error_code) in segment {
+                    index_writer.add_document(doc!(
+                        service_field => service,
+                        error_field => error_code,
+                        latency_field => if service == "db" { 1.0 } else { 0.0 },
+                    ))?;
+                }
+                index_writer.commit()?;
+            }
+        }
+        if merge_segments {
+            let segment_ids = index.searchable_segment_ids()?;
+            let mut index_writer = index.writer_for_tests()?;
+            index_writer.merge(&segment_ids).wait()?;
+            index_writer.wait_merging_threads()?;
+        }
+        Ok(index)
+    }
+
+    fn exec_significant_terms(
+        significant_terms: Value,
+        merge_segments: bool,
+    ) -> crate::Result<Value> {
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "significant": {
+                "significant_terms": significant_terms,
+                "aggs": { "avg_latency": { "avg": { "field": "latency" } } }
+            }
+        }))
+        .unwrap();
+        let index = get_test_index(merge_segments)?;
+        exec_request_with_query(agg_req, &index, Some(("service", "db")))
+    }
+
+    fn keys(res: &Value) -> Vec<&Value> {
+        res["significant"]["buckets"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|bucket| &bucket["key"])
+            .collect()
+    }
+
+    #[test]
+    fn significant_terms_jlh_test() -> crate::Result<()> {
+        for merge_segments in [false, true] {
+            let res = exec_significant_terms(json!({ "field": "error_code" }), merge_segments)?;
+            let expected_score = {
+                let subset_probability = 4.0 / 8.0;
+                let superset_probability = 8.0 / 52.0;
+                (subset_probability - superset_probability)
+                    * (subset_probability / superset_probability)
+            };
+            assert_eq!(
+                res["significant"],
+                json!({
+                    "doc_count": 8,
+                    "bg_count": 52,
+                    "buckets": [{
+                        "key": "rare",
+                        "doc_count": 4,
+                        "score": expected_score,
+                        "bg_count": 8,
+                        "avg_latency": { "value": 1.0 }
+                    }]
+                })
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn significant_terms_chi_square_test() -> crate::Result<()> {
+        let res =
+            exec_significant_terms(json!({ "field": "error_code", "chi_square": {} }), false)?;
+        assert_eq!(keys(&res), vec!["rare"]);
+        assert!(res["significant"]["buckets"][0]["score"].as_f64().unwrap() > 0.0);
+
+        // The "common" error is less frequent for the db service than overall.
+        let res = exec_significant_terms(
+            json!({ "field": "error_code", "chi_square": { "include_negatives": true } }),
+            false,
+        )?;
+        let mut keys = keys(&res);
+        keys.sort_by_key(|key| key.to_string());
+        assert_eq!(keys, vec!["common", "rare"]);
+        Ok(())
+    }
+
+    #[test]
+    fn significant_terms_min_doc_count_and_size_test() -> crate::Result<()> {
+        let res =
+            exec_significant_terms(json!({ "field": "error_code", "min_doc_count": 5 }), false)?;
+        assert_eq!(res["significant"]["buckets"], json!([]));
+
+        let res = exec_significant_terms(
+            json!({
+                "field": "error_code",
+                "chi_square": { "include_negatives": true },
+                "size": 1
+            }),
+            false,
+        )?;
+        assert_eq!(keys(&res).len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn significant_terms_distributed_collector_test() -> crate::Result<()> {
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "significant": { "significant_terms": { "field": "error_code" } }
+        }))
+        .unwrap();
+        let index = get_test_index(false)?;
+        let searcher = index.reader()?.searcher();
+        let service_field = searcher.schema().get_field("service").unwrap();
+        let query = TermQuery::new(
+            Term::from_field_text(service_field, "db"),
+            IndexRecordOption::Basic,
+        );
+        let collector = DistributedAggregationCollector::from_aggs(agg_req.clone(), None);
+        let intermediate_res = searcher.search(&query, &collector)?;
+        // The foreground and background sets of both results are merged, the background
+        // frequencies of the serialized result are looked up before serializing it.
+        let mut merged: IntermediateAggregationResults =
+            serde_json::from_str(&serde_json::to_string(&intermediate_res)?)?;
+        merged.merge_fruits(intermediate_res);
+        let res: Value = serde_json::to_value(merged.into_final_bucket_result(agg_req)?)?;
+        assert_eq!(res["significant"]["doc_count"], 16);
+        assert_eq!(res["significant"]["bg_count"], 104);
+        assert_eq!(keys(&res), vec!["rare"]);
+        assert_eq!(res["significant"]["buckets"][0]["bg_count"], 16);
+        Ok(())
+    }
+
+    #[test]
+    fn significant_terms_invalid_request_test() -> crate::Result<()> {
+        let index = get_test_index(true)?;
+        let exec = |significant_terms: Value| {
+            let agg_req: Aggregations = serde_json::from_value(json!({
+                "significant": { "significant_terms": significant_terms }
+            }))
+            .unwrap();
+            exec_request_with_query(agg_req, &index, None)
+        };
+
+        let err = exec(json!({ "field": "latency" })).unwrap_err();
+        assert!(err.to_string().contains("requires a text field"));
+
+        let err = exec(json!({ "field": "error_code", "jlh": {}, "chi_square": {} })).unwrap_err();
+        assert!(err.to_string().contains("only one significance heuristic"));
+        Ok(())
+    }
+
+    #[test]
+    fn significance_heuristic_test() {
+        let jlh = SignificanceHeuristic::Jlh(JlhHeuristic {});
+        assert_eq!(jlh.score(5, 10, 5, 100), 0.45 * 10.0);
+        assert_eq!(jlh.score(1, 10, 10, 100), 0.0);
+        assert_eq!(jlh.score(0, 0, 10, 100), 0.0);
+
+        let chi_square = SignificanceHeuristic::ChiSquare(ChiSquareHeuristic::default());
+        // No deviation from the background frequency.
+        assert_eq!(chi_square.score(1, 10, 10, 100), 0.0);
+        assert!(chi_square.score(5, 10, 5, 100) > 0.0);
+        assert_eq!(chi_square.score(0, 10, 50, 100), 0.0);
+        let chi_square = SignificanceHeuristic::ChiSquare(ChiSquareHeuristic {
+            include_negatives: true,
+            background_is_superset: true,
+        });
+        assert!(chi_square.score(0, 10, 50, 100) > 0.0);
+    }
+}
@@ -1,3 +1,4 @@
+use std::collections::hash_map::Entry;
 use std::fmt::Debug;
 
 use itertools::Itertools;
@@ -6,22 +7,28 @@ use serde::{Deserialize, Serialize};
 
 use super::{CustomOrder, Order, OrderTarget};
 use crate::aggregation::agg_req_with_accessor::{
-    AggregationsWithAccessor, BucketAggregationWithAccessor,
+    AggregationsWithAccessor, BucketAggregationWithAccessor, FastFieldAccessor,
 };
 use crate::aggregation::intermediate_agg_result::{
     IntermediateBucketResult, IntermediateTermBucketEntry, IntermediateTermBucketResult,
 };
 use crate::aggregation::segment_agg_result::{BucketCount, SegmentAggregationResultsCollector};
+use crate::aggregation::{f64_from_fastfield_u64, IntermediateKey};
 use crate::error::DataCorruption;
-use crate::fastfield::MultiValuedFastFieldReader;
 use crate::schema::Type;
 use crate::{DocId, TantivyError};
 
-/// Creates a bucket for every unique term and counts the number of occurences.
-/// Note that doc_count in the response buckets equals term count here.
+/// Creates a bucket for every unique term and counts the number of documents containing it.
 ///
-/// If the text is untokenized and single value, that means one term per document and therefore it
-/// is in fact doc count.
+/// Supported are text fields, `u64`, `i64` and `f64` fast fields, single and multivalued, as well
+/// as paths inside json fields, e.g. `attributes.status`. A document is counted once per distinct
+/// value, even if it contains the value multiple times.
+///
+/// The buckets of text values have a string key, the buckets of numeric values a `f64` key.
+///
+/// Json fields don't have fast fields. Therefore the values of a json path are read from the
+/// inverted index of each segment, which is considerably slower and requires memory proportional
+/// to the number of values of the path. Only text and numeric json values are aggregated.
 ///
 /// ### Terminology
 /// Shard parameters are supposed to be equivalent to elasticsearch shard parameter.
@@ -198,13 +205,16 @@ impl TermsAggregationInternal {
 
 #[derive(Clone, Debug, PartialEq)]
 /// Container to store term_ids and their buckets.
-struct TermBuckets {
-    pub(crate) entries: FxHashMap<u32, TermBucketEntry>,
+pub(crate) struct TermBuckets {
+    pub(crate) entries: FxHashMap<u64, TermBucketEntry>,
     blueprint: Option<SegmentAggregationResultsCollector>,
 }
 
+/// The estimated memory of a bucket in the term map, without its sub aggregations.
+const TERM_BUCKET_MEMORY: u64 = std::mem::size_of::<(u64, TermBucketEntry)>() as u64;
+
 #[derive(Clone, PartialEq, Default)]
-struct TermBucketEntry {
+pub(crate) struct TermBucketEntry {
     doc_count: u64,
     sub_aggregations: Option<SegmentAggregationResultsCollector>,
 }
@@ -245,7 +255,6 @@ impl TermBucketEntry {
 impl TermBuckets {
     pub(crate) fn from_req_and_validate(
         sub_aggregation: &AggregationsWithAccessor,
-        _max_term_id: usize,
     ) -> crate::Result<Self> {
         let has_sub_aggregations = sub_aggregation.is_empty();
 
@@ -263,7 +272,7 @@ impl TermBuckets {
         })
     }
 
-    fn increment_bucket(
+    pub(crate) fn increment_bucket(
         &mut self,
         term_ids: &[u64],
         doc: DocId,
@@ -272,8 +281,14 @@ impl TermBuckets {
         blueprint: &Option<SegmentAggregationResultsCollector>,
     ) -> crate::Result<()> {
         for &term_id in term_ids {
-            let entry = self.entries.entry(term_id as u32).or_insert_with(|| {
+            let entry = self.entries.entry(term_id).or_insert_with(|| {
                 bucket_count.add_count(1);
+                bucket_count.add_memory_consumed(
+                    TERM_BUCKET_MEMORY
+                        + blueprint
+                            .as_ref()
+                            .map_or(0, |blueprint| blueprint.memory_estimate()),
+                );
 
                 TermBucketEntry::from_blueprint(blueprint)
             });
@@ -282,12 +297,15 @@ impl TermBuckets {
                 sub_aggregations.collect(doc, sub_aggregation)?;
             }
         }
-        bucket_count.validate_bucket_count()?;
+        bucket_count.validate_limits()?;
 
         Ok(())
     }
 
-    fn force_flush(&mut self, agg_with_accessor: &AggregationsWithAccessor) -> crate::Result<()> {
+    pub(crate) fn force_flush(
+        &mut self,
+        agg_with_accessor: &AggregationsWithAccessor,
+    ) -> crate::Result<()> {
         for entry in &mut self.entries.values_mut() {
             if let Some(sub_aggregations) = entry.sub_aggregations.as_mut() {
                 sub_aggregations.flush_staged_docs(agg_with_accessor, false)?;
@@ -314,15 +332,21 @@ pub(crate) fn get_agg_name_and_property(name: &str) -> (&str, &str) {
 }
 
 impl SegmentTermCollector {
+    /// Returns the estimated memory in bytes of the collector before it collects any document.
+    pub(crate) fn memory_estimate(&self) -> u64 {
+        std::mem::size_of::<Self>() as u64
+            + self
+                .blueprint
+                .as_ref()
+                .map_or(0, |blueprint| blueprint.memory_estimate())
+    }
+
     pub(crate) fn from_req_and_validate(
         req: &TermsAggregation,
         sub_aggregations: &AggregationsWithAccessor,
         field_type: Type,
-        accessor: &MultiValuedFastFieldReader<u64>,
     ) -> crate::Result<Self> {
-        let max_term_id = accessor.max_value();
-        let term_buckets =
-            TermBuckets::from_req_and_validate(sub_aggregations, max_term_id as usize)?;
+        let term_buckets = TermBuckets::from_req_and_validate(sub_aggregations)?;
 
         if let Some(custom_order) = req.order.as_ref() {
             // Validate sub aggregtion exists
@@ -359,7 +383,7 @@ impl SegmentTermCollector {
         self,
         agg_with_accessor: &BucketAggregationWithAccessor,
     ) -> crate::Result<IntermediateBucketResult> {
-        let mut entries: Vec<(u32, TermBucketEntry)> =
+        let mut entries: Vec<(u64, TermBucketEntry)> =
             self.term_buckets.entries.into_iter().collect();
 
         let order_by_key = self.req.order.target == OrderTarget::Key;
@@ -391,31 +415,72 @@ impl SegmentTermCollector {
                 cut_off_buckets(&mut entries, self.req.segment_size as usize)
             };
 
-        let inverted_index = agg_with_accessor
-            .inverted_index
-            .as_ref()
-            .expect("internal error: inverted index not loaded for term aggregation");
-        let term_dict = inverted_index.terms();
-
-        let mut dict: FxHashMap<String, IntermediateTermBucketEntry> = Default::default();
-        let mut buffer = vec![];
-        for (term_id, entry) in entries {
-            term_dict
-                .ord_to_term(term_id as u64, &mut buffer)
-                .expect("could not find term");
-            dict.insert(
-                String::from_utf8(buffer.to_vec())
-                    .map_err(|utf8_err| DataCorruption::comment_only(utf8_err.to_string()))?,
-                entry.into_intermediate_bucket_entry(&agg_with_accessor.sub_aggregation)?,
-            );
-        }
-        if self.req.min_doc_count == 0 {
-            let mut stream = term_dict.stream()?;
-            while let Some((key, _ord)) = stream.next() {
-                let key = std::str::from_utf8(key)
-                    .map_err(|utf8_err| DataCorruption::comment_only(utf8_err.to_string()))?;
-                if !dict.contains_key(key) {
-                    dict.insert(key.to_owned(), Default::default());
+        let mut dict: FxHashMap<IntermediateKey, IntermediateTermBucketEntry> = Default::default();
+        match self.field_type {
+            Type::Str => {
+                let inverted_index = agg_with_accessor
+                    .inverted_index
+                    .as_ref()
+                    .expect("internal error: inverted index not loaded for term aggregation");
+                let term_dict = inverted_index.terms();
+
+                let mut buffer = vec![];
+                for (term_id, entry) in entries {
+                    term_dict
+                        .ord_to_term(term_id, &mut buffer)
+                        .expect("could not find term");
+                    dict.insert(
+                        IntermediateKey::Str(String::from_utf8(buffer.to_vec()).map_err(
+                            |utf8_err| DataCorruption::comment_only(utf8_err.to_string()),
+                        )?),
+                        entry.into_intermediate_bucket_entry(&agg_with_accessor.sub_aggregation)?,
+                    );
+                }
+                if self.req.min_doc_count == 0 {
+                    let mut stream = term_dict.stream()?;
+                    while let Some((key, _ord)) = stream.next() {
+                        let key = std::str::from_utf8(key).map_err(|utf8_err| {
+                            DataCorruption::comment_only(utf8_err.to_string())
+                        })?;
+                        add_empty_bucket(
+                            &mut dict,
+                            IntermediateKey::Str(key.to_string()),
+                            &agg_with_accessor.bucket_count,
+                        )?;
+                    }
+                }
+            }
+            Type::Json => {
+                let keys = &agg_with_accessor.json_path_keys;
+                for (term_id, entry) in entries {
+                    dict.insert(
+                        keys[term_id as usize].clone(),
+                        entry.into_intermediate_bucket_entry(&agg_with_accessor.sub_aggregation)?,
+                    );
+                }
+                if self.req.min_doc_count == 0 {
+                    for key in keys {
+                        add_empty_bucket(&mut dict, key.clone(), &agg_with_accessor.bucket_count)?;
+                    }
+                }
+            }
+            field_type => {
+                let to_key =
+                    |val: u64| IntermediateKey::F64(f64_from_fastfield_u64(val, &field_type));
+                for (val, entry) in entries {
+                    dict.insert(
+                        to_key(val),
+                        entry.into_intermediate_bucket_entry(&agg_with_accessor.sub_aggregation)?,
+                    );
+                }
+                if self.req.min_doc_count == 0 {
+                    let accessor = agg_with_accessor
+                        .accessor
+                        .as_ref()
+                        .expect("missing fast field accessor for term aggregation");
+                    for val in all_values(accessor) {
+                        add_empty_bucket(&mut dict, to_key(val), &agg_with_accessor.bucket_count)?;
+                    }
                 }
             }
         }
@@ -446,59 +511,19 @@ impl SegmentTermCollector {
     #[inline]
     pub(crate) fn collect_block(
         &mut self,
-        doc: &[DocId],
+        docs: &[DocId],
         bucket_with_accessor: &BucketAggregationWithAccessor,
         force_flush: bool,
     ) -> crate::Result<()> {
         let accessor = bucket_with_accessor
             .accessor
-            .as_multi()
-            .expect("unexpected fast field cardinatility");
-        let mut iter = doc.chunks_exact(4);
-        let mut vals1 = vec![];
-        let mut vals2 = vec![];
-        let mut vals3 = vec![];
-        let mut vals4 = vec![];
-        for docs in iter.by_ref() {
-            accessor.get_vals(docs[0], &mut vals1);
-            accessor.get_vals(docs[1], &mut vals2);
-            accessor.get_vals(docs[2], &mut vals3);
-            accessor.get_vals(docs[3], &mut vals4);
-
-            self.term_buckets.increment_bucket(
-                &vals1,
-                docs[0],
-                &bucket_with_accessor.sub_aggregation,
-                &bucket_with_accessor.bucket_count,
-                &self.blueprint,
-            )?;
-            self.term_buckets.increment_bucket(
-                &vals2,
-                docs[1],
-                &bucket_with_accessor.sub_aggregation,
-                &bucket_with_accessor.bucket_count,
-                &self.blueprint,
-            )?;
-            self.term_buckets.increment_bucket(
-                &vals3,
-                docs[2],
-                &bucket_with_accessor.sub_aggregation,
-                &bucket_with_accessor.bucket_count,
-                &self.blueprint,
-            )?;
-            self.term_buckets.increment_bucket(
-                &vals4,
-                docs[3],
-                &bucket_with_accessor.sub_aggregation,
-                &bucket_with_accessor.bucket_count,
-                &self.blueprint,
-            )?;
-        }
-        for &doc in iter.remainder() {
-            accessor.get_vals(doc, &mut vals1);
-
+            .as_ref()
+            .expect("missing fast field accessor for term aggregation");
+        let mut vals = vec![];
+        for &doc in docs {
+            get_distinct_vals(accessor, doc, &mut vals);
             self.term_buckets.increment_bucket(
-                &vals1,
+                &vals,
                 doc,
                 &bucket_with_accessor.sub_aggregation,
                 &bucket_with_accessor.bucket_count,
@@ -513,15 +538,74 @@ impl SegmentTermCollector {
     }
 }
 
+/// Writes the distinct values of the document into `vals`.
+#[inline]
+pub(crate) fn get_distinct_vals(accessor: &FastFieldAccessor, doc: DocId, vals: &mut Vec<u64>) {
+    match accessor {
+        FastFieldAccessor::Single(column) => {
+            vals.clear();
+            vals.push(column.get_val(doc));
+        }
+        FastFieldAccessor::Multi(reader) => {
+            reader.get_vals(doc, vals);
+            if vals.len() > 1 {
+                vals.sort_unstable();
+                vals.dedup();
+            }
+        }
+        FastFieldAccessor::SingleU128(_) | FastFieldAccessor::MultiU128(_) => {
+            panic!("unexpected u128 fast field in term aggregation")
+        }
+    }
+}
+
+/// Adds an empty bucket for `key`, unless there is one already. The empty buckets requested by
+/// `min_doc_count: 0` are counted against the limits like the collected buckets.
+fn add_empty_bucket(
+    dict: &mut FxHashMap<IntermediateKey, IntermediateTermBucketEntry>,
+    key: IntermediateKey,
+    bucket_count: &BucketCount,
+) -> crate::Result<()> {
+    if let Entry::Vacant(entry) = dict.entry(key) {
+        bucket_count.add_count(1);
+        bucket_count
+            .add_memory_consumed(
+                std::mem::size_of::<(IntermediateKey, IntermediateTermBucketEntry)>() as u64,
+            );
+        bucket_count.validate_limits()?;
+        entry.insert(IntermediateTermBucketEntry::default());
+    }
+    Ok(())
+}
+
+/// Returns all values of the fast field in the segment.
+fn all_values(accessor: &FastFieldAccessor) -> Vec<u64> {
+    match accessor {
+        FastFieldAccessor::Single(column) => column.iter().collect(),
+        FastFieldAccessor::Multi(reader) => {
+            let mut all_vals = vec![];
+            let mut vals = vec![];
+            for doc in 0..reader.get_index_reader().num_docs() {
+                reader.get_vals(doc, &mut vals);
+                all_vals.extend_from_slice(&vals);
+            }
+            all_vals
+        }
+        FastFieldAccessor::SingleU128(_) | FastFieldAccessor::MultiU128(_) => {
+            panic!("unexpected u128 fast field in term aggregation")
+        }
+    }
+}
+
 pub(crate) trait GetDocCount {
     fn doc_count(&self) -> u64;
 }
-impl GetDocCount for (u32, TermBucketEntry) {
+impl GetDocCount for (u64, TermBucketEntry) {
     fn doc_count(&self) -> u64 {
         self.1.doc_count
     }
 }
-impl GetDocCount for (String, IntermediateTermBucketEntry) {
+impl GetDocCount for (IntermediateKey, IntermediateTermBucketEntry) {
     fn doc_count(&self) -> u64 {
         self.1.doc_count
     }
@@ -547,16 +631,25 @@ pub(crate) fn cut_off_buckets<T: GetDocCount + Debug>(
 
 #[cfg(test)]
 mod tests {
+    use serde_json::json;
+
     use super::*;
     use crate::aggregation::agg_req::{
         get_term_dict_field_names, Aggregation, Aggregations, BucketAggregation,
         BucketAggregationType, MetricAggregation,
     };
+    use crate::aggregation::intermediate_agg_result::IntermediateAggregationResults;
     use crate::aggregation::metric::{AverageAggregation, StatsAggregation};
     use crate::aggregation::tests::{
         exec_request, exec_request_with_query, get_test_index_from_terms,
         get_test_index_from_values_and_terms,
     };
+    use crate::aggregation::{
+        AggregationCollector, AggregationError, DistributedAggregationCollector,
+    };
+    use crate::query::{AllQuery, TermQuery};
+    use crate::schema::{Cardinality, IndexRecordOption, NumericOptions, Schema, STRING};
+    use crate::{doc, Index, TantivyError, Term};
 
     #[test]
     fn terms_aggregation_test_single_segment() -> crate::Result<()> {
@@ -1235,9 +1328,44 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn terms_aggregation_min_doc_count_zero_bucket_limit() -> crate::Result<()> {
+        let index = get_test_index_from_terms(true, &[vec!["a", "b", "c", "d"]])?;
+
+        let agg_req: Aggregations = vec![(
+            "my_texts".to_string(),
+            Aggregation::Bucket(BucketAggregation {
+                bucket_agg: BucketAggregationType::Terms(TermsAggregation {
+                    field: "string_id".to_string(),
+                    min_doc_count: Some(0),
+                    ..Default::default()
+                }),
+                sub_aggregation: Default::default(),
+            }),
+        )]
+        .into_iter()
+        .collect();
+
+        // Only one document matches, the empty buckets of the other terms exceed the limit.
+        let searcher = index.reader()?.searcher();
+        let string_id = searcher.schema().get_field("string_id").unwrap();
+        let query = TermQuery::new(
+            Term::from_field_text(string_id, "a"),
+            IndexRecordOption::Basic,
+        );
+        let collector = AggregationCollector::from_aggs(agg_req, Some(2));
+        let err = searcher.search(&query, &collector).unwrap_err();
+        assert!(matches!(
+            err,
+            TantivyError::AggregationError(AggregationError::BucketLimitExceeded { limit: 2, .. })
+        ));
+
+        Ok(())
+    }
+
     #[test]
     fn terms_aggregation_multi_token_per_doc() -> crate::Result<()> {
-        let terms = vec!["Hello Hello", "Hallo Hallo"];
+        let terms = vec!["Hello Hello", "Hallo Hallo", "Hallo"];
 
         let index = get_test_index_from_terms(true, &[terms])?;
 
@@ -1257,12 +1385,238 @@ mod tests {
 
         let res = exec_request_with_query(agg_req, &index, None).unwrap();
 
-        assert_eq!(res["my_texts"]["buckets"][0]["key"], "hello");
+        // Every distinct term is counted once per document.
+        assert_eq!(res["my_texts"]["buckets"][0]["key"], "hallo");
         assert_eq!(res["my_texts"]["buckets"][0]["doc_count"], 2);
 
-        assert_eq!(res["my_texts"]["buckets"][1]["key"], "hallo");
-        assert_eq!(res["my_texts"]["buckets"][1]["doc_count"], 2);
+        assert_eq!(res["my_texts"]["buckets"][1]["key"], "hello");
+        assert_eq!(res["my_texts"]["buckets"][1]["doc_count"], 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn terms_aggregation_numeric_test() -> crate::Result<()> {
+        let segment_and_values = vec![
+            vec![(5.0, "terma".to_string()), (7.5, "termb".to_string())],
+            vec![(5.0, "termc".to_string()), (-1.0, "terma".to_string())],
+            vec![(5.0, "terma".to_string())],
+        ];
+        let index = get_test_index_from_values_and_terms(false, &segment_and_values)?;
+
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "by_f64": {
+                "terms": { "field": "score_f64" },
+                "aggs": { "texts": { "terms": { "field": "string_id" } } }
+            },
+            "by_i64": { "terms": { "field": "score_i64", "order": { "_key": "desc" } } }
+        }))
+        .unwrap();
+
+        let res = exec_request(agg_req, &index)?;
+        let buckets = &res["by_f64"]["buckets"];
+        assert_eq!(buckets[0]["key"], 5.0);
+        assert_eq!(buckets[0]["doc_count"], 3);
+        assert_eq!(buckets[0]["texts"]["buckets"][0]["key"], "terma");
+        assert_eq!(buckets[0]["texts"]["buckets"][0]["doc_count"], 2);
+        assert_eq!(buckets[1]["doc_count"], 1);
+        assert_eq!(buckets[2]["doc_count"], 1);
+
+        let keys: Vec<f64> = res["by_i64"]["buckets"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|bucket| bucket["key"].as_f64().unwrap())
+            .collect();
+        assert_eq!(keys, vec![-1.0, 5.0, 7.0]);
+        Ok(())
+    }
+
+    #[test]
+    fn terms_aggregation_numeric_distributed_test() -> crate::Result<()> {
+        let segment_and_values = vec![
+            vec![(5.0, "terma".to_string()), (7.5, "termb".to_string())],
+            vec![(5.0, "termc".to_string()), (-1.0, "terma".to_string())],
+        ];
+        let index = get_test_index_from_values_and_terms(false, &segment_and_values)?;
+
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "by_f64": {
+                "terms": { "field": "score_f64" },
+                "aggs": { "texts": { "terms": { "field": "string_id" } } }
+            }
+        }))
+        .unwrap();
+
+        let collector = DistributedAggregationCollector::from_aggs(agg_req.clone(), None);
+        let searcher = index.reader()?.searcher();
+        let intermediate_res = searcher.search(&AllQuery, &collector)?;
+
+        // The numeric keys have to survive the serialization to be merged with the ones of
+        // another node.
+        let intermediate_res: IntermediateAggregationResults =
+            serde_json::from_str(&serde_json::to_string(&intermediate_res)?)?;
+        let mut merged_res = intermediate_res.clone();
+        merged_res.merge_fruits(intermediate_res);
+
+        let res = serde_json::to_value(merged_res.into_final_bucket_result(agg_req)?)?;
+        let buckets = &res["by_f64"]["buckets"];
+        assert_eq!(buckets.as_array().unwrap().len(), 3);
+        assert_eq!(buckets[0]["key"], 5.0);
+        assert_eq!(buckets[0]["doc_count"], 4);
+        assert_eq!(buckets[0]["texts"]["buckets"][0]["doc_count"], 2);
+        assert_eq!(buckets[1]["doc_count"], 2);
+        assert!(buckets[1]["key"].is_f64());
+        assert_eq!(buckets[2]["doc_count"], 2);
+        Ok(())
+    }
+
+    fn get_multi_value_and_json_index(merge_segments: bool) -> crate::Result<Index> {
+        let mut schema_builder = Schema::builder();
+        let tags = schema_builder.add_u64_field(
+            "tags",
+            NumericOptions::default().set_fast(Cardinality::MultiValues),
+        );
+        let attributes = schema_builder.add_json_field("attributes", STRING);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_for_tests()?;
+            index_writer.add_document(doc!(
+                tags => 1u64,
+                tags => 2u64,
+                tags => 1u64,
+                attributes => json!({"status": "ok", "code": 200}).as_object().unwrap().clone(),
+            ))?;
+            index_writer.add_document(doc!(
+                tags => 2u64,
+                attributes => json!({"status": ["ok", "ok"], "code": 200.0})
+                    .as_object()
+                    .unwrap()
+                    .clone(),
+            ))?;
+            index_writer.commit()?;
+            index_writer.add_document(doc!(
+                tags => 3u64,
+                attributes => json!({"status": "error", "code": 500}).as_object().unwrap().clone(),
+            ))?;
+            index_writer.add_document(doc!(
+                attributes => json!({"other": "ok"}).as_object().unwrap().clone(),
+            ))?;
+            index_writer.commit()?;
+        }
+        if merge_segments {
+            let segment_ids = index
+                .searchable_segment_ids()
+                .expect("Searchable segments failed.");
+            let mut index_writer = index.writer_for_tests()?;
+            index_writer.merge(&segment_ids).wait()?;
+            index_writer.wait_merging_threads()?;
+        }
+        Ok(index)
+    }
+
+    #[test]
+    fn terms_aggregation_multi_value_test() -> crate::Result<()> {
+        terms_aggregation_multi_value_merge_segments(false)?;
+        terms_aggregation_multi_value_merge_segments(true)
+    }
+
+    fn terms_aggregation_multi_value_merge_segments(merge_segments: bool) -> crate::Result<()> {
+        let index = get_multi_value_and_json_index(merge_segments)?;
+
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "tags": { "terms": { "field": "tags", "order": { "_count": "desc" } } }
+        }))
+        .unwrap();
+
+        let res = exec_request(agg_req, &index)?;
+        let buckets = &res["tags"]["buckets"];
+        assert_eq!(buckets[0]["key"], 2.0);
+        assert_eq!(buckets[0]["doc_count"], 2);
+        // The first document contains the tag 1 twice, but is counted once.
+        assert_eq!(buckets[1]["doc_count"], 1);
+        assert_eq!(buckets[2]["doc_count"], 1);
+        assert_eq!(buckets.as_array().unwrap().len(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn terms_aggregation_json_path_test() -> crate::Result<()> {
+        terms_aggregation_json_path_merge_segments(false)?;
+        terms_aggregation_json_path_merge_segments(true)
+    }
+
+    fn terms_aggregation_json_path_merge_segments(merge_segments: bool) -> crate::Result<()> {
+        let index = get_multi_value_and_json_index(merge_segments)?;
+
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "status": {
+                "terms": { "field": "attributes.status" },
+                "aggs": { "codes": { "terms": { "field": "attributes.code" } } }
+            },
+            "all_codes": { "terms": { "field": "attributes.code", "min_doc_count": 0 } }
+        }))
+        .unwrap();
+
+        let res = exec_request(agg_req, &index)?;
+        let buckets = &res["status"]["buckets"];
+        assert_eq!(buckets[0]["key"], "ok");
+        assert_eq!(buckets[0]["doc_count"], 2);
+        // `200` and `200.0` are the same value.
+        assert_eq!(buckets[0]["codes"]["buckets"][0]["key"], 200.0);
+        assert_eq!(buckets[0]["codes"]["buckets"][0]["doc_count"], 2);
+        assert_eq!(buckets[1]["key"], "error");
+        assert_eq!(buckets[1]["doc_count"], 1);
+        assert_eq!(buckets[1]["codes"]["buckets"][0]["key"], 500.0);
+        assert_eq!(buckets.as_array().unwrap().len(), 2);
+
+        assert_eq!(res["all_codes"]["buckets"].as_array().unwrap().len(), 2);
+        Ok(())
+    }
 
+    #[test]
+    fn terms_aggregation_json_path_invalid_test() -> crate::Result<()> {
+        let index = get_multi_value_and_json_index(false)?;
+
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "attributes": { "terms": { "field": "attributes" } }
+        }))
+        .unwrap();
+        let err = exec_request(agg_req, &index).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "An invalid argument was passed: 'aggregations on the json field attributes require a \
+             path inside the field'"
+        );
+
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "tags": { "terms": { "field": "tags.inner" } }
+        }))
+        .unwrap();
+        let err = exec_request(agg_req, &index).unwrap_err();
+        assert_eq!(err.to_string(), "The field does not exist: 'tags.inner'");
+        Ok(())
+    }
+
+    #[test]
+    fn terms_aggregation_json_path_memory_limit_test() -> crate::Result<()> {
+        let index = get_multi_value_and_json_index(true)?;
+        let searcher = index.reader()?.searcher();
+
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "avg_code": { "avg": { "field": "attributes.code" } }
+        }))
+        .unwrap();
+        let collector = AggregationCollector::from_aggs(agg_req.clone(), None);
+        assert!(searcher.search(&AllQuery, &collector).is_ok());
+
+        // The value columns of the path are held in memory for the segment.
+        let collector = AggregationCollector::from_aggs(agg_req, None).with_memory_limit(32);
+        let err = searcher.search(&AllQuery, &collector).unwrap_err();
+        assert!(matches!(
+            err,
+            TantivyError::AggregationError(AggregationError::MemoryExceeded { limit: 32, .. })
+        ));
         Ok(())
     }
 
@@ -1363,7 +1717,7 @@ mod bench {
     use super::*;
 
     fn get_collector_with_buckets(num_docs: u64) -> TermBuckets {
-        TermBuckets::from_req_and_validate(&Default::default(), num_docs as usize).unwrap()
+        TermBuckets::from_req_and_validate(&Default::default()).unwrap()
     }
 
     fn get_rand_terms(total_terms: u64, num_terms_returned: u64) -> Vec<u64> {
@@ -1385,8 +1739,8 @@ mod bench {
         let vals = get_rand_terms(total_terms, num_terms);
         let aggregations_with_accessor: AggregationsWithAccessor = Default::default();
         let bucket_count: BucketCount = BucketCount {
-            bucket_count: Default::default(),
             max_bucket_count: 1_000_001u32,
+            ..Default::default()
         };
         b.iter(|| {
             for &val in &vals {
@@ -1,23 +1,61 @@
-use std::rc::Rc;
-
-use super::agg_req::Aggregations;
-use super::agg_req_with_accessor::AggregationsWithAccessor;
+use super::agg_req::{requires_scoring, Aggregations};
+use super::agg_req_with_accessor::{AggregationsWithAccessor, SegmentAggregationContext};
 use super::agg_result::AggregationResults;
+use super::bucket::resolve_filter_queries;
 use super::intermediate_agg_result::IntermediateAggregationResults;
-use super::segment_agg_result::SegmentAggregationResultsCollector;
+use super::metric::{new_doc_scores, DocScores};
+use super::pipeline::validate_pipeline_aggregations;
+use super::segment_agg_result::{BucketCount, SegmentAggregationResultsCollector};
 use crate::aggregation::agg_req_with_accessor::get_aggs_with_accessor_and_validate;
 use crate::collector::{Collector, SegmentCollector};
-use crate::{SegmentReader, TantivyError};
+use crate::query::QueryParser;
+use crate::{SegmentOrdinal, SegmentReader, TantivyError};
 
 /// The default max bucket count, before the aggregation fails.
 pub const MAX_BUCKET_COUNT: u32 = 65000;
 
+/// The default memory limit in bytes of the buckets of a segment, before the aggregation fails.
+pub const DEFAULT_MEMORY_LIMIT: u64 = 500_000_000;
+
+/// The limits of an aggregation request, which are enforced during the collection of a segment.
+///
+/// Exceeding a limit aborts the aggregation with an
+/// [`AggregationError`](super::AggregationError).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AggregationLimits {
+    /// The maximum number of buckets.
+    pub max_bucket_count: u32,
+    /// The maximum estimated memory in bytes of the buckets.
+    ///
+    /// The estimate covers the bucket maps and vectors of the bucket aggregations, e.g. the
+    /// buckets of a histogram, which are allocated upfront for the range of the values.
+    pub memory_limit: u64,
+}
+
+impl Default for AggregationLimits {
+    fn default() -> Self {
+        AggregationLimits {
+            max_bucket_count: MAX_BUCKET_COUNT,
+            memory_limit: DEFAULT_MEMORY_LIMIT,
+        }
+    }
+}
+
+impl AggregationLimits {
+    fn from_max_bucket_count(max_bucket_count: Option<u32>) -> Self {
+        AggregationLimits {
+            max_bucket_count: max_bucket_count.unwrap_or(MAX_BUCKET_COUNT),
+            ..Default::default()
+        }
+    }
+}
+
 /// Collector for aggregations.
 ///
 /// The collector collects all aggregations by the underlying aggregation request.
 pub struct AggregationCollector {
     agg: Aggregations,
-    max_bucket_count: u32,
+    limits: AggregationLimits,
 }
 
 impl AggregationCollector {
@@ -28,9 +66,27 @@ impl AggregationCollector {
     pub fn from_aggs(agg: Aggregations, max_bucket_count: Option<u32>) -> Self {
         Self {
             agg,
-            max_bucket_count: max_bucket_count.unwrap_or(MAX_BUCKET_COUNT),
+            limits: AggregationLimits::from_max_bucket_count(max_bucket_count),
         }
     }
+
+    /// Sets the maximum estimated memory in bytes of the buckets of a segment.
+    ///
+    /// Aggregation fails when the memory is higher than memory_limit.
+    /// memory_limit defaults to `DEFAULT_MEMORY_LIMIT` (500MB).
+    pub fn with_memory_limit(mut self, memory_limit: u64) -> Self {
+        self.limits.memory_limit = memory_limit;
+        self
+    }
+
+    /// Parses the query strings of filter aggregations in the request with the given query
+    /// parser.
+    ///
+    /// Filter aggregations with query strings fail without a query parser.
+    pub fn with_query_parser(mut self, query_parser: &QueryParser) -> crate::Result<Self> {
+        resolve_filter_queries(&mut self.agg, query_parser)?;
+        Ok(self)
+    }
 }
 
 /// Collector for distributed aggregations.
@@ -43,7 +99,7 @@ impl AggregationCollector {
 /// into the final `AggregationResults` via the `into_final_result()` method.
 pub struct DistributedAggregationCollector {
     agg: Aggregations,
-    max_bucket_count: u32,
+    limits: AggregationLimits,
 }
 
 impl DistributedAggregationCollector {
@@ -53,9 +109,27 @@ impl DistributedAggregationCollector {
     pub fn from_aggs(agg: Aggregations, max_bucket_count: Option<u32>) -> Self {
         Self {
             agg,
-            max_bucket_count: max_bucket_count.unwrap_or(MAX_BUCKET_COUNT),
+            limits: AggregationLimits::from_max_bucket_count(max_bucket_count),
         }
     }
+
+    /// Sets the maximum estimated memory in bytes of the buckets of a segment.
+    ///
+    /// Aggregation fails when the memory is higher than memory_limit.
+    /// memory_limit defaults to `DEFAULT_MEMORY_LIMIT` (500MB).
+    pub fn with_memory_limit(mut self, memory_limit: u64) -> Self {
+        self.limits.memory_limit = memory_limit;
+        self
+    }
+
+    /// Parses the query strings of filter aggregations in the request with the given query
+    /// parser.
+    ///
+    /// Filter aggregations with query strings fail without a query parser.
+    pub fn with_query_parser(mut self, query_parser: &QueryParser) -> crate::Result<Self> {
+        resolve_filter_queries(&mut self.agg, query_parser)?;
+        Ok(self)
+    }
 }
 
 impl Collector for DistributedAggregationCollector {
@@ -65,18 +139,19 @@ impl Collector for DistributedAggregationCollector {
 
     fn for_segment(
         &self,
-        _segment_local_id: crate::SegmentOrdinal,
+        segment_local_id: crate::SegmentOrdinal,
         reader: &crate::SegmentReader,
     ) -> crate::Result<Self::Child> {
         AggregationSegmentCollector::from_agg_req_and_reader(
             &self.agg,
             reader,
-            self.max_bucket_count,
+            segment_local_id,
+            self.limits,
         )
     }
 
     fn requires_scoring(&self) -> bool {
-        false
+        requires_scoring(&self.agg)
     }
 
     fn merge_fruits(
@@ -94,18 +169,19 @@ impl Collector for AggregationCollector {
 
     fn for_segment(
         &self,
-        _segment_local_id: crate::SegmentOrdinal,
+        segment_local_id: crate::SegmentOrdinal,
         reader: &crate::SegmentReader,
     ) -> crate::Result<Self::Child> {
         AggregationSegmentCollector::from_agg_req_and_reader(
             &self.agg,
             reader,
-            self.max_bucket_count,
+            segment_local_id,
+            self.limits,
         )
     }
 
     fn requires_scoring(&self) -> bool {
-        false
+        requires_scoring(&self.agg)
     }
 
     fn merge_fruits(
@@ -113,7 +189,7 @@ impl Collector for AggregationCollector {
         segment_fruits: Vec<<Self::Child as SegmentCollector>::Fruit>,
     ) -> crate::Result<Self::Fruit> {
         let res = merge_fruits(segment_fruits)?;
-        res.into_final_bucket_result(self.agg.clone())
+        res.into_final_bucket_result_with_limits(self.agg.clone(), self.limits)
     }
 }
 
@@ -135,6 +211,8 @@ fn merge_fruits(
 pub struct AggregationSegmentCollector {
     aggs_with_accessor: AggregationsWithAccessor,
     result: SegmentAggregationResultsCollector,
+    /// Only set if an aggregation requires the scores of the collected documents.
+    doc_scores: Option<DocScores>,
     error: Option<TantivyError>,
 }
 
@@ -144,15 +222,26 @@ impl AggregationSegmentCollector {
     pub fn from_agg_req_and_reader(
         agg: &Aggregations,
         reader: &SegmentReader,
-        max_bucket_count: u32,
+        segment_ordinal: SegmentOrdinal,
+        limits: AggregationLimits,
     ) -> crate::Result<Self> {
-        let aggs_with_accessor =
-            get_aggs_with_accessor_and_validate(agg, reader, Rc::default(), max_bucket_count)?;
+        validate_pipeline_aggregations(agg)?;
+        let context = SegmentAggregationContext {
+            segment_ord: segment_ordinal,
+            doc_scores: requires_scoring(agg).then(|| new_doc_scores(reader.max_doc())),
+        };
+        let aggs_with_accessor = get_aggs_with_accessor_and_validate(
+            agg,
+            reader,
+            &context,
+            &BucketCount::from_limits(limits),
+        )?;
         let result =
             SegmentAggregationResultsCollector::from_req_and_validate(&aggs_with_accessor)?;
         Ok(AggregationSegmentCollector {
             aggs_with_accessor,
             result,
+            doc_scores: context.doc_scores,
             error: None,
         })
     }
@@ -162,10 +251,13 @@ impl SegmentCollector for AggregationSegmentCollector {
     type Fruit = crate::Result<IntermediateAggregationResults>;
 
     #[inline]
-    fn collect(&mut self, doc: crate::DocId, _score: crate::Score) {
+    fn collect(&mut self, doc: crate::DocId, score: crate::Score) {
         if self.error.is_some() {
             return;
         }
+        if let Some(doc_scores) = &self.doc_scores {
+            doc_scores[doc as usize].set(score);
+        }
         if let Err(err) = self.result.collect(doc, &self.aggs_with_accessor) {
             self.error = Some(err);
         }
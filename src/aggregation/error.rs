@@ -0,0 +1,26 @@
+//! Errors of aggregations, which exceed the limits of the request.
+
+use thiserror::Error;
+
+/// Error that is returned when an aggregation exceeds a limit of the request.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum AggregationError {
+    /// The estimated memory of the buckets exceeded the memory limit.
+    ///
+    /// See [`AggregationCollector::with_memory_limit`](super::AggregationCollector::with_memory_limit).
+    #[error("Aborting aggregation because memory limit was exceeded. Limit: {limit}, Current: {current}")]
+    MemoryExceeded {
+        /// The memory limit in bytes.
+        limit: u64,
+        /// The estimated memory in bytes, when the limit was exceeded.
+        current: u64,
+    },
+    /// The number of buckets exceeded the bucket limit.
+    #[error("Aborting aggregation because too many buckets were created. Limit: {limit}, Current: {current}")]
+    BucketLimitExceeded {
+        /// The maximum number of buckets.
+        limit: u32,
+        /// The number of buckets, when the limit was exceeded.
+        current: u32,
+    },
+}
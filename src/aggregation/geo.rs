@@ -0,0 +1,156 @@
+//! Helpers for the geo aggregations.
+//!
+//! The aggregations read the latitude and the longitude of a document from two single valued
+//! `f64` fast fields named in the request.
+
+use std::sync::Arc;
+
+use fastfield_codecs::{Column, MonotonicallyMappableToU64};
+
+pub use crate::schema::GeoPoint;
+use crate::DocId;
+
+/// The latitude bounds of the web mercator projection used by geotiles.
+const MAX_MERCATOR_LATITUDE: f64 = 85.051_128_779_806_59;
+
+const GEOHASH_BASE32: &[u8; 32] = b"0123456789bcdefghjkmnpqrstuvwxyz";
+
+/// The maximum precision of a geohash, which fits in 60 bits.
+pub(crate) const MAX_GEOHASH_PRECISION: u8 = 12;
+/// The maximum zoom level of a geotile.
+pub(crate) const MAX_GEOTILE_ZOOM: u8 = 29;
+
+/// Reads the geo point of a document from a latitude and a longitude fast field.
+///
+/// Documents without a value in the fast fields read as `0.0`, like for the other single valued
+/// fast field aggregations.
+#[derive(Clone)]
+pub(crate) struct GeoPointAccessor {
+    pub lat: Arc<dyn Column<u64>>,
+    pub lon: Arc<dyn Column<u64>>,
+}
+
+impl GeoPointAccessor {
+    #[inline]
+    pub(crate) fn get(&self, doc: DocId) -> GeoPoint {
+        GeoPoint {
+            lat: f64::from_u64(self.lat.get_val(doc)),
+            lon: f64::from_u64(self.lon.get_val(doc)),
+        }
+    }
+}
+
+/// Returns the id of the geohash cell with `precision` characters containing `point`.
+///
+/// The id contains the interleaved bits of the cell, 5 bits per character, starting with the
+/// longitude.
+pub(crate) fn geohash_cell(point: &GeoPoint, precision: u8) -> u64 {
+    let (mut lat_min, mut lat_max) = (-90.0, 90.0);
+    let (mut lon_min, mut lon_max) = (-180.0, 180.0);
+    let mut cell = 0u64;
+    for bit in 0..u32::from(precision) * 5 {
+        cell <<= 1;
+        if bit % 2 == 0 {
+            let mid = (lon_min + lon_max) / 2.0;
+            if point.lon >= mid {
+                cell |= 1;
+                lon_min = mid;
+            } else {
+                lon_max = mid;
+            }
+        } else {
+            let mid = (lat_min + lat_max) / 2.0;
+            if point.lat >= mid {
+                cell |= 1;
+                lat_min = mid;
+            } else {
+                lat_max = mid;
+            }
+        }
+    }
+    cell
+}
+
+/// Returns the base32 geohash of a cell returned by [`geohash_cell`].
+pub(crate) fn geohash_to_string(cell: u64, precision: u8) -> String {
+    (0..precision)
+        .rev()
+        .map(|pos| GEOHASH_BASE32[((cell >> (u32::from(pos) * 5)) & 31) as usize] as char)
+        .collect()
+}
+
+/// Returns the id of the web mercator tile on `zoom` level containing `point`.
+///
+/// The id contains the x coordinate of the tile in the upper 32 bits and the y coordinate in the
+/// lower 32 bits. Latitudes beyond the bounds of the projection are clamped.
+pub(crate) fn geotile_cell(point: &GeoPoint, zoom: u8) -> u64 {
+    let num_tiles = (1u64 << zoom) as f64;
+    let max_tile = (1u64 << zoom) - 1;
+    let x = ((point.lon + 180.0) / 360.0 * num_tiles).floor();
+    let lat = point
+        .lat
+        .clamp(-MAX_MERCATOR_LATITUDE, MAX_MERCATOR_LATITUDE)
+        .to_radians();
+    let y = ((1.0 - (lat.tan() + 1.0 / lat.cos()).ln() / std::f64::consts::PI) / 2.0 * num_tiles)
+        .floor();
+    let x = (x.max(0.0) as u64).min(max_tile);
+    let y = (y.max(0.0) as u64).min(max_tile);
+    x << 32 | y
+}
+
+/// Returns the `zoom/x/y` key of a tile returned by [`geotile_cell`].
+pub(crate) fn geotile_to_string(cell: u64, zoom: u8) -> String {
+    format!("{}/{}/{}", zoom, cell >> 32, cell & u64::from(u32::MAX))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn geo_distance_test() {
+        let amsterdam = GeoPoint {
+            lat: 52.3676,
+            lon: 4.9041,
+        };
+        let berlin = GeoPoint {
+            lat: 52.52,
+            lon: 13.405,
+        };
+        let distance = amsterdam.distance(&berlin);
+        assert!((distance - 577_000.0).abs() < 2_000.0, "{}", distance);
+        assert_eq!(amsterdam.distance(&amsterdam), 0.0);
+    }
+
+    #[test]
+    fn geohash_test() {
+        let point = GeoPoint {
+            lat: 57.64911,
+            lon: 10.40744,
+        };
+        assert_eq!(
+            geohash_to_string(geohash_cell(&point, 11), 11),
+            "u4pruydqqvj"
+        );
+        assert_eq!(geohash_to_string(geohash_cell(&point, 1), 1), "u");
+        assert_eq!(
+            geohash_to_string(geohash_cell(&point, MAX_GEOHASH_PRECISION), 12).len(),
+            12
+        );
+    }
+
+    #[test]
+    fn geotile_test() {
+        let point = GeoPoint {
+            lat: 52.3676,
+            lon: 4.9041,
+        };
+        assert_eq!(geotile_to_string(geotile_cell(&point, 0), 0), "0/0/0");
+        assert_eq!(geotile_to_string(geotile_cell(&point, 8), 8), "8/131/84");
+        let pole = GeoPoint {
+            lat: 90.0,
+            lon: 180.0,
+        };
+        assert_eq!(geotile_to_string(geotile_cell(&pole, 2), 2), "2/3/0");
+    }
+}
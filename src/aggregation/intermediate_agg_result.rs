@@ -3,25 +3,39 @@
 //! indices.
 
 use std::cmp::Ordering;
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::sync::Arc;
 
 use itertools::Itertools;
 use rustc_hash::FxHashMap;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Serialize, Serializer};
 
 use super::agg_req::{
     Aggregations, AggregationsInternal, BucketAggregationInternal, BucketAggregationType,
     MetricAggregation,
 };
-use super::agg_result::{AggregationResult, BucketResult, RangeBucketEntry};
+use super::agg_result::{
+    AggregationResult, BucketResult, CompositeBucketEntry, FilterBucketEntry, MetricResult,
+    RangeBucketEntry, SignificantTermBucketEntry,
+};
 use super::bucket::{
-    cut_off_buckets, get_agg_name_and_property, intermediate_histogram_buckets_to_final_buckets,
-    GetDocCount, Order, OrderTarget, SegmentHistogramBucketEntry, TermsAggregation,
+    cut_off_buckets, get_agg_name_and_property,
+    intermediate_date_histogram_buckets_to_final_buckets,
+    intermediate_histogram_buckets_to_final_buckets, CompositeAggregation, FiltersAggregation,
+    GetDocCount, Order, OrderTarget, SegmentHistogramBucketEntry, SignificantTermsAggregation,
+    TermsAggregation,
+};
+use super::metric::{
+    IntermediateAverage, IntermediateCardinality, IntermediateGeoBounds, IntermediateGeoCentroid,
+    IntermediatePercentiles, IntermediateStats, IntermediateTopHits, ValueCountMetricResult,
 };
-use super::metric::{IntermediateAverage, IntermediateStats};
-use super::segment_agg_result::SegmentMetricResultCollector;
-use super::{Key, SerializedKey, VecWithNames};
+use super::pipeline::apply_pipeline_aggregations;
+use super::segment_agg_result::BucketCount;
+use super::{AggregationLimits, IntermediateKey, Key, SerializedKey, VecWithNames};
 use crate::aggregation::agg_result::{AggregationResults, BucketEntries, BucketEntry};
 use crate::aggregation::bucket::TermsAggregationInternal;
+use crate::InvertedIndexReader;
 
 /// Contains the intermediate aggregation result, which is optimized to be merged with other
 /// intermediate results.
@@ -35,8 +49,26 @@ pub struct IntermediateAggregationResults {
 
 impl IntermediateAggregationResults {
     /// Convert intermediate result and its aggregation request to the final result.
+    ///
+    /// The pipeline aggregations of the request are computed on the final result.
     pub fn into_final_bucket_result(self, req: Aggregations) -> crate::Result<AggregationResults> {
-        self.into_final_bucket_result_internal(&(req.into()))
+        self.into_final_bucket_result_with_limits(req, AggregationLimits::default())
+    }
+
+    /// Convert intermediate result and its aggregation request to the final result.
+    ///
+    /// Buckets created during the conversion, e.g. to fill the gaps of a histogram, are checked
+    /// against the provided limits.
+    pub fn into_final_bucket_result_with_limits(
+        self,
+        req: Aggregations,
+        limits: AggregationLimits,
+    ) -> crate::Result<AggregationResults> {
+        let bucket_count = BucketCount::from_limits(limits);
+        let mut results =
+            self.into_final_bucket_result_internal(&(req.clone().into()), &bucket_count)?;
+        apply_pipeline_aggregations(&req, &mut results)?;
+        Ok(results)
     }
 
     /// Convert intermediate result and its aggregation request to the final result.
@@ -46,6 +78,7 @@ impl IntermediateAggregationResults {
     pub(crate) fn into_final_bucket_result_internal(
         self,
         req: &AggregationsInternal,
+        bucket_count: &BucketCount,
     ) -> crate::Result<AggregationResults> {
         // Important assumption:
         // When the tree contains buckets/metric, we expect it to have all buckets/metrics from the
@@ -53,15 +86,20 @@ impl IntermediateAggregationResults {
         let mut results: FxHashMap<String, AggregationResult> = FxHashMap::default();
 
         if let Some(buckets) = self.buckets {
-            convert_and_add_final_buckets_to_result(&mut results, buckets, &req.buckets)?
+            convert_and_add_final_buckets_to_result(
+                &mut results,
+                buckets,
+                &req.buckets,
+                bucket_count,
+            )?
         } else {
             // When there are no buckets, we create empty buckets, so that the serialized json
             // format is constant
-            add_empty_final_buckets_to_result(&mut results, &req.buckets)?
+            add_empty_final_buckets_to_result(&mut results, &req.buckets, bucket_count)?
         };
 
         if let Some(metrics) = self.metrics {
-            convert_and_add_final_metrics_to_result(&mut results, metrics);
+            convert_and_add_final_metrics_to_result(&mut results, metrics, &req.metrics);
         } else {
             // When there are no metrics, we create empty metric results, so that the serialized
             // json format is constant
@@ -133,11 +171,20 @@ impl IntermediateAggregationResults {
 fn convert_and_add_final_metrics_to_result(
     results: &mut FxHashMap<String, AggregationResult>,
     metrics: VecWithNames<IntermediateMetricResult>,
+    req_metrics: &VecWithNames<MetricAggregation>,
 ) {
+    assert_eq!(metrics.len(), req_metrics.len());
+
     results.extend(
         metrics
             .into_iter()
-            .map(|(key, metric)| (key, AggregationResult::MetricResult(metric.into()))),
+            .zip(req_metrics.values())
+            .map(|((key, metric), req)| {
+                (
+                    key,
+                    AggregationResult::MetricResult(metric.into_final_metric_result(req)),
+                )
+            }),
     );
 }
 
@@ -149,7 +196,7 @@ fn add_empty_final_metrics_to_result(
         let empty_bucket = IntermediateMetricResult::empty_from_req(req);
         (
             key.to_string(),
-            AggregationResult::MetricResult(empty_bucket.into()),
+            AggregationResult::MetricResult(empty_bucket.into_final_metric_result(req)),
         )
     }));
     Ok(())
@@ -158,10 +205,12 @@ fn add_empty_final_metrics_to_result(
 fn add_empty_final_buckets_to_result(
     results: &mut FxHashMap<String, AggregationResult>,
     req_buckets: &VecWithNames<BucketAggregationInternal>,
+    bucket_count: &BucketCount,
 ) -> crate::Result<()> {
     let requested_buckets = req_buckets.iter();
     for (key, req) in requested_buckets {
-        let empty_bucket = AggregationResult::BucketResult(BucketResult::empty_from_req(req)?);
+        let empty_bucket =
+            AggregationResult::BucketResult(BucketResult::empty_from_req(req, bucket_count)?);
         results.insert(key.to_string(), empty_bucket);
     }
     Ok(())
@@ -171,12 +220,14 @@ fn convert_and_add_final_buckets_to_result(
     results: &mut FxHashMap<String, AggregationResult>,
     buckets: VecWithNames<IntermediateBucketResult>,
     req_buckets: &VecWithNames<BucketAggregationInternal>,
+    bucket_count: &BucketCount,
 ) -> crate::Result<()> {
     assert_eq!(buckets.len(), req_buckets.len());
 
     let buckets_with_request = buckets.into_iter().zip(req_buckets.values());
     for ((key, bucket), req) in buckets_with_request {
-        let result = AggregationResult::BucketResult(bucket.into_final_bucket_result(req)?);
+        let result =
+            AggregationResult::BucketResult(bucket.into_final_bucket_result(req, bucket_count)?);
         results.insert(key, result);
     }
     Ok(())
@@ -198,22 +249,79 @@ pub enum IntermediateMetricResult {
     Average(IntermediateAverage),
     /// AverageData variant
     Stats(IntermediateStats),
+    /// Intermediate min result
+    Min(IntermediateStats),
+    /// Intermediate max result
+    Max(IntermediateStats),
+    /// Intermediate sum result
+    Sum(IntermediateStats),
+    /// Intermediate value count result
+    ValueCount(IntermediateStats),
+    /// Cardinality sketch variant
+    Cardinality(IntermediateCardinality),
+    /// Quantile sketch variant, used by percentiles and percentile ranks
+    Percentiles(IntermediatePercentiles),
+    /// Intermediate top hits result
+    TopHits(IntermediateTopHits),
+    /// Intermediate geo bounds result
+    GeoBounds(IntermediateGeoBounds),
+    /// Intermediate geo centroid result
+    GeoCentroid(IntermediateGeoCentroid),
 }
 
-impl From<SegmentMetricResultCollector> for IntermediateMetricResult {
-    fn from(tree: SegmentMetricResultCollector) -> Self {
-        match tree {
-            SegmentMetricResultCollector::Average(collector) => {
-                IntermediateMetricResult::Average(IntermediateAverage::from_collector(collector))
+impl IntermediateMetricResult {
+    pub(crate) fn into_final_metric_result(self, req: &MetricAggregation) -> MetricResult {
+        match self {
+            IntermediateMetricResult::Average(intermediate_avg) => {
+                MetricResult::Average(intermediate_avg.finalize().into())
+            }
+            IntermediateMetricResult::Stats(intermediate_stats) => {
+                MetricResult::Stats(intermediate_stats.finalize())
+            }
+            IntermediateMetricResult::Min(intermediate_stats) => {
+                MetricResult::Min(intermediate_stats.finalize().min.into())
             }
-            SegmentMetricResultCollector::Stats(collector) => {
-                IntermediateMetricResult::Stats(collector.stats)
+            IntermediateMetricResult::Max(intermediate_stats) => {
+                MetricResult::Max(intermediate_stats.finalize().max.into())
+            }
+            IntermediateMetricResult::Sum(intermediate_stats) => {
+                // Like in elasticsearch, the sum of no values is 0.
+                MetricResult::Sum(intermediate_stats.finalize().sum.into())
+            }
+            IntermediateMetricResult::ValueCount(intermediate_stats) => {
+                MetricResult::ValueCount(ValueCountMetricResult {
+                    value: intermediate_stats.finalize().count as u64,
+                })
+            }
+            IntermediateMetricResult::Cardinality(intermediate_cardinality) => {
+                MetricResult::Cardinality(intermediate_cardinality.finalize().into())
+            }
+            IntermediateMetricResult::Percentiles(intermediate_percentiles) => match req {
+                MetricAggregation::PercentileRanks(percentile_ranks_req) => {
+                    MetricResult::PercentileRanks(
+                        intermediate_percentiles.finalize_ranks(percentile_ranks_req),
+                    )
+                }
+                MetricAggregation::Percentiles(percentiles_req) => {
+                    MetricResult::Percentiles(intermediate_percentiles.finalize(percentiles_req))
+                }
+                _ => panic!("unexpected aggregation, expected percentiles aggregation"),
+            },
+            IntermediateMetricResult::TopHits(intermediate_top_hits) => match req {
+                MetricAggregation::TopHits(top_hits_req) => {
+                    MetricResult::TopHits(intermediate_top_hits.finalize(top_hits_req))
+                }
+                _ => panic!("unexpected aggregation, expected top hits aggregation"),
+            },
+            IntermediateMetricResult::GeoBounds(intermediate_geo_bounds) => {
+                MetricResult::GeoBounds(intermediate_geo_bounds.finalize())
+            }
+            IntermediateMetricResult::GeoCentroid(intermediate_geo_centroid) => {
+                MetricResult::GeoCentroid(intermediate_geo_centroid.finalize())
             }
         }
     }
-}
 
-impl IntermediateMetricResult {
     pub(crate) fn empty_from_req(req: &MetricAggregation) -> Self {
         match req {
             MetricAggregation::Average(_) => {
@@ -222,6 +330,33 @@ impl IntermediateMetricResult {
             MetricAggregation::Stats(_) => {
                 IntermediateMetricResult::Stats(IntermediateStats::default())
             }
+            MetricAggregation::Min(_) => {
+                IntermediateMetricResult::Min(IntermediateStats::default())
+            }
+            MetricAggregation::Max(_) => {
+                IntermediateMetricResult::Max(IntermediateStats::default())
+            }
+            MetricAggregation::Sum(_) => {
+                IntermediateMetricResult::Sum(IntermediateStats::default())
+            }
+            MetricAggregation::ValueCount(_) => {
+                IntermediateMetricResult::ValueCount(IntermediateStats::default())
+            }
+            MetricAggregation::Cardinality(_) => {
+                IntermediateMetricResult::Cardinality(IntermediateCardinality::default())
+            }
+            MetricAggregation::Percentiles(_) | MetricAggregation::PercentileRanks(_) => {
+                IntermediateMetricResult::Percentiles(IntermediatePercentiles::default())
+            }
+            MetricAggregation::TopHits(top_hits) => {
+                IntermediateMetricResult::TopHits(IntermediateTopHits::from_req(top_hits))
+            }
+            MetricAggregation::GeoBounds(_) => {
+                IntermediateMetricResult::GeoBounds(IntermediateGeoBounds::default())
+            }
+            MetricAggregation::GeoCentroid(_) => {
+                IntermediateMetricResult::GeoCentroid(IntermediateGeoCentroid::default())
+            }
         }
     }
     fn merge_fruits(&mut self, other: IntermediateMetricResult) {
@@ -238,6 +373,51 @@ impl IntermediateMetricResult {
             ) => {
                 stats_left.merge_fruits(stats_right);
             }
+            (
+                IntermediateMetricResult::Cardinality(cardinality_left),
+                IntermediateMetricResult::Cardinality(cardinality_right),
+            ) => {
+                cardinality_left.merge_fruits(cardinality_right);
+            }
+            (IntermediateMetricResult::Min(min_left), IntermediateMetricResult::Min(min_right)) => {
+                min_left.merge_fruits(min_right);
+            }
+            (IntermediateMetricResult::Max(max_left), IntermediateMetricResult::Max(max_right)) => {
+                max_left.merge_fruits(max_right);
+            }
+            (IntermediateMetricResult::Sum(sum_left), IntermediateMetricResult::Sum(sum_right)) => {
+                sum_left.merge_fruits(sum_right);
+            }
+            (
+                IntermediateMetricResult::ValueCount(value_count_left),
+                IntermediateMetricResult::ValueCount(value_count_right),
+            ) => {
+                value_count_left.merge_fruits(value_count_right);
+            }
+            (
+                IntermediateMetricResult::Percentiles(percentiles_left),
+                IntermediateMetricResult::Percentiles(percentiles_right),
+            ) => {
+                percentiles_left.merge_fruits(percentiles_right);
+            }
+            (
+                IntermediateMetricResult::TopHits(top_hits_left),
+                IntermediateMetricResult::TopHits(top_hits_right),
+            ) => {
+                top_hits_left.merge_fruits(top_hits_right);
+            }
+            (
+                IntermediateMetricResult::GeoBounds(geo_bounds_left),
+                IntermediateMetricResult::GeoBounds(geo_bounds_right),
+            ) => {
+                geo_bounds_left.merge_fruits(geo_bounds_right);
+            }
+            (
+                IntermediateMetricResult::GeoCentroid(geo_centroid_left),
+                IntermediateMetricResult::GeoCentroid(geo_centroid_right),
+            ) => {
+                geo_centroid_left.merge_fruits(geo_centroid_right);
+            }
             _ => {
                 panic!("incompatible fruit types in tree");
             }
@@ -260,19 +440,33 @@ pub enum IntermediateBucketResult {
     },
     /// Term aggregation
     Terms(IntermediateTermBucketResult),
+    /// Significant terms aggregation, including the sizes and term frequencies of the
+    /// background set.
+    SignificantTerms(IntermediateSignificantTermsBucketResult),
+    /// Filter aggregation, a single bucket containing a count, and optionally sub_aggregations.
+    Filter(IntermediateFilterBucketEntry),
+    /// Filters aggregation, the buckets are identified by the filter keys.
+    Filters(IntermediateFiltersBucketResult),
+    /// Composite aggregation, the buckets are identified by compound keys.
+    Composite(IntermediateCompositeBucketResult),
+    /// Geohash or geotile grid aggregation, the buckets are identified by the keys of the cells.
+    GeoGrid(IntermediateGeoGridBucketResult),
 }
 
 impl IntermediateBucketResult {
     pub(crate) fn into_final_bucket_result(
         self,
         req: &BucketAggregationInternal,
+        bucket_count: &BucketCount,
     ) -> crate::Result<BucketResult> {
         match self {
             IntermediateBucketResult::Range(range_res) => {
                 let mut buckets: Vec<RangeBucketEntry> = range_res
                     .buckets
                     .into_iter()
-                    .map(|(_, bucket)| bucket.into_final_bucket_entry(&req.sub_aggregation))
+                    .map(|(_, bucket)| {
+                        bucket.into_final_bucket_entry(&req.sub_aggregation, bucket_count)
+                    })
                     .collect::<crate::Result<Vec<_>>>()?;
 
                 buckets.sort_by(|left, right| {
@@ -283,8 +477,9 @@ impl IntermediateBucketResult {
 
                 let is_keyed = req
                     .as_range()
-                    .expect("unexpected aggregation, expected range aggregation")
-                    .keyed;
+                    .map(|range_req| range_req.keyed)
+                    .or_else(|| req.as_geo_distance().map(|geo_distance| geo_distance.keyed))
+                    .expect("unexpected aggregation, expected range aggregation");
                 let buckets = if is_keyed {
                     let mut bucket_map =
                         FxHashMap::with_capacity_and_hasher(buckets.len(), Default::default());
@@ -298,18 +493,37 @@ impl IntermediateBucketResult {
                 Ok(BucketResult::Range { buckets })
             }
             IntermediateBucketResult::Histogram { buckets } => {
-                let buckets = intermediate_histogram_buckets_to_final_buckets(
-                    buckets,
-                    req.as_histogram()
-                        .expect("unexpected aggregation, expected histogram aggregation"),
-                    &req.sub_aggregation,
-                )?;
+                let (buckets, is_keyed) = if let Some(date_histogram_req) = req.as_date_histogram()
+                {
+                    let buckets = intermediate_date_histogram_buckets_to_final_buckets(
+                        buckets,
+                        date_histogram_req,
+                        &req.sub_aggregation,
+                        bucket_count,
+                    )?;
+                    (buckets, date_histogram_req.keyed)
+                } else {
+                    let histogram_req = req
+                        .as_histogram()
+                        .expect("unexpected aggregation, expected histogram aggregation");
+                    let buckets = intermediate_histogram_buckets_to_final_buckets(
+                        buckets,
+                        histogram_req,
+                        &req.sub_aggregation,
+                        bucket_count,
+                    )?;
+                    (buckets, histogram_req.keyed)
+                };
 
-                let buckets = if req.as_histogram().unwrap().keyed {
+                let buckets = if is_keyed {
                     let mut bucket_map =
                         FxHashMap::with_capacity_and_hasher(buckets.len(), Default::default());
                     for bucket in buckets {
-                        bucket_map.insert(bucket.key.to_string(), bucket);
+                        let key = bucket
+                            .key_as_string
+                            .clone()
+                            .unwrap_or_else(|| bucket.key.to_string());
+                        bucket_map.insert(key, bucket);
                     }
                     BucketEntries::HashMap(bucket_map)
                 } else {
@@ -321,6 +535,35 @@ impl IntermediateBucketResult {
                 req.as_term()
                     .expect("unexpected aggregation, expected term aggregation"),
                 &req.sub_aggregation,
+                bucket_count,
+            ),
+            IntermediateBucketResult::SignificantTerms(significant_terms) => significant_terms
+                .into_final_result(
+                    req.as_significant_terms()
+                        .expect("unexpected aggregation, expected significant terms aggregation"),
+                    &req.sub_aggregation,
+                    bucket_count,
+                ),
+            IntermediateBucketResult::Filter(bucket) => Ok(BucketResult::Filter(
+                bucket.into_final_bucket_entry(&req.sub_aggregation, bucket_count)?,
+            )),
+            IntermediateBucketResult::Filters(filters) => filters.into_final_result(
+                req.as_filters()
+                    .expect("unexpected aggregation, expected filters aggregation"),
+                &req.sub_aggregation,
+                bucket_count,
+            ),
+            IntermediateBucketResult::Composite(composite) => composite.into_final_result(
+                req.as_composite()
+                    .expect("unexpected aggregation, expected composite aggregation"),
+                &req.sub_aggregation,
+                bucket_count,
+            ),
+            IntermediateBucketResult::GeoGrid(geo_grid) => geo_grid.into_final_result(
+                req.geo_grid_size()
+                    .expect("unexpected aggregation, expected geo grid aggregation"),
+                &req.sub_aggregation,
+                bucket_count,
             ),
         }
     }
@@ -328,10 +571,27 @@ impl IntermediateBucketResult {
     pub(crate) fn empty_from_req(req: &BucketAggregationType) -> Self {
         match req {
             BucketAggregationType::Terms(_) => IntermediateBucketResult::Terms(Default::default()),
-            BucketAggregationType::Range(_) => IntermediateBucketResult::Range(Default::default()),
-            BucketAggregationType::Histogram(_) => {
+            BucketAggregationType::SignificantTerms(_) => {
+                IntermediateBucketResult::SignificantTerms(Default::default())
+            }
+            BucketAggregationType::Range(_) | BucketAggregationType::GeoDistance(_) => {
+                IntermediateBucketResult::Range(Default::default())
+            }
+            BucketAggregationType::Histogram(_) | BucketAggregationType::DateHistogram(_) => {
                 IntermediateBucketResult::Histogram { buckets: vec![] }
             }
+            BucketAggregationType::Filter(_) => {
+                IntermediateBucketResult::Filter(Default::default())
+            }
+            BucketAggregationType::Filters(_) => {
+                IntermediateBucketResult::Filters(Default::default())
+            }
+            BucketAggregationType::Composite(_) => {
+                IntermediateBucketResult::Composite(Default::default())
+            }
+            BucketAggregationType::GeohashGrid(_) | BucketAggregationType::GeoTileGrid(_) => {
+                IntermediateBucketResult::GeoGrid(Default::default())
+            }
         }
     }
     fn merge_fruits(&mut self, other: IntermediateBucketResult) {
@@ -345,6 +605,12 @@ impl IntermediateBucketResult {
                 term_res_left.doc_count_error_upper_bound +=
                     term_res_right.doc_count_error_upper_bound;
             }
+            (
+                IntermediateBucketResult::SignificantTerms(significant_terms_left),
+                IntermediateBucketResult::SignificantTerms(significant_terms_right),
+            ) => {
+                significant_terms_left.merge_fruits(significant_terms_right);
+            }
 
             (
                 IntermediateBucketResult::Range(range_res_left),
@@ -379,6 +645,30 @@ impl IntermediateBucketResult {
 
                 *buckets_left = buckets;
             }
+            (
+                IntermediateBucketResult::Filter(filter_res_left),
+                IntermediateBucketResult::Filter(filter_res_right),
+            ) => {
+                filter_res_left.merge_fruits(filter_res_right);
+            }
+            (
+                IntermediateBucketResult::Filters(filters_res_left),
+                IntermediateBucketResult::Filters(filters_res_right),
+            ) => {
+                merge_maps(&mut filters_res_left.buckets, filters_res_right.buckets);
+            }
+            (
+                IntermediateBucketResult::Composite(composite_res_left),
+                IntermediateBucketResult::Composite(composite_res_right),
+            ) => {
+                composite_res_left.merge_fruits(composite_res_right);
+            }
+            (
+                IntermediateBucketResult::GeoGrid(geo_grid_res_left),
+                IntermediateBucketResult::GeoGrid(geo_grid_res_right),
+            ) => {
+                merge_maps(&mut geo_grid_res_left.buckets, geo_grid_res_right.buckets);
+            }
             (IntermediateBucketResult::Range(_), _) => {
                 panic!("try merge on different types")
             }
@@ -388,6 +678,21 @@ impl IntermediateBucketResult {
             (IntermediateBucketResult::Terms { .. }, _) => {
                 panic!("try merge on different types")
             }
+            (IntermediateBucketResult::SignificantTerms(_), _) => {
+                panic!("try merge on different types")
+            }
+            (IntermediateBucketResult::Filter(_), _) => {
+                panic!("try merge on different types")
+            }
+            (IntermediateBucketResult::Filters(_), _) => {
+                panic!("try merge on different types")
+            }
+            (IntermediateBucketResult::Composite(_), _) => {
+                panic!("try merge on different types")
+            }
+            (IntermediateBucketResult::GeoGrid(_), _) => {
+                panic!("try merge on different types")
+            }
         }
     }
 }
@@ -398,10 +703,55 @@ pub struct IntermediateRangeBucketResult {
     pub(crate) buckets: FxHashMap<SerializedKey, IntermediateRangeBucketEntry>,
 }
 
+/// (De)serializes a map keyed by [`IntermediateKey`] as a list of key-value pairs.
+///
+/// As map keys, the keys would be serialized as strings, so that a numeric key would come back
+/// as a string key.
+mod intermediate_key_map {
+    use rustc_hash::FxHashMap;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use crate::aggregation::IntermediateKey;
+
+    pub fn serialize<V, S>(
+        map: &FxHashMap<IntermediateKey, V>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        V: Serialize,
+        S: Serializer,
+    {
+        serializer.collect_seq(map.iter())
+    }
+
+    pub fn serialize_ref<V, S>(
+        map: &&FxHashMap<IntermediateKey, V>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        V: Serialize,
+        S: Serializer,
+    {
+        serialize(map, serializer)
+    }
+
+    pub fn deserialize<'de, V, D>(
+        deserializer: D,
+    ) -> Result<FxHashMap<IntermediateKey, V>, D::Error>
+    where
+        V: Deserialize<'de>,
+        D: Deserializer<'de>,
+    {
+        let entries: Vec<(IntermediateKey, V)> = Vec::deserialize(deserializer)?;
+        Ok(entries.into_iter().collect())
+    }
+}
+
 #[derive(Default, Clone, Debug, PartialEq, Serialize, Deserialize)]
 /// Term aggregation including error counts
 pub struct IntermediateTermBucketResult {
-    pub(crate) entries: FxHashMap<String, IntermediateTermBucketEntry>,
+    #[serde(with = "intermediate_key_map")]
+    pub(crate) entries: FxHashMap<IntermediateKey, IntermediateTermBucketEntry>,
     pub(crate) sum_other_doc_count: u64,
     pub(crate) doc_count_error_upper_bound: u64,
 }
@@ -411,6 +761,7 @@ impl IntermediateTermBucketResult {
         self,
         req: &TermsAggregation,
         sub_aggregation_req: &AggregationsInternal,
+        bucket_count: &BucketCount,
     ) -> crate::Result<BucketResult> {
         let req = TermsAggregationInternal::from_req(req);
         let mut buckets: Vec<BucketEntry> = self
@@ -419,11 +770,12 @@ impl IntermediateTermBucketResult {
             .filter(|bucket| bucket.1.doc_count >= req.min_doc_count)
             .map(|(key, entry)| {
                 Ok(BucketEntry {
-                    key: Key::Str(key),
+                    key: key.into(),
+                    key_as_string: None,
                     doc_count: entry.doc_count,
                     sub_aggregation: entry
                         .sub_aggregation
-                        .into_final_bucket_result_internal(sub_aggregation_req)?,
+                        .into_final_bucket_result_internal(sub_aggregation_req, bucket_count)?,
                 })
             })
             .collect::<crate::Result<_>>()?;
@@ -437,7 +789,7 @@ impl IntermediateTermBucketResult {
                     } else {
                         right.key.partial_cmp(&left.key)
                     }
-                    .expect("expected type string, which is always sortable")
+                    .unwrap_or(Ordering::Equal)
                 });
             }
             OrderTarget::Count => {
@@ -493,13 +845,296 @@ impl IntermediateTermBucketResult {
     }
 }
 
+/// The estimated memory of a background frequency, without the bytes of its term.
+const BACKGROUND_ENTRY_MEMORY: usize = std::mem::size_of::<(IntermediateKey, u64)>();
+
+#[derive(Default, Clone, Debug, PartialEq, Deserialize)]
+/// Significant terms aggregation, the foreground buckets and the background frequencies of their
+/// terms.
+///
+/// The background frequencies of the segments collected by this process are looked up in their
+/// term dictionaries, once the foreground terms of all segments are known. They are added to
+/// `background` when the result is serialized, e.g. to be merged on another node.
+pub struct IntermediateSignificantTermsBucketResult {
+    #[serde(deserialize_with = "intermediate_key_map::deserialize")]
+    pub(crate) entries: FxHashMap<IntermediateKey, IntermediateTermBucketEntry>,
+    /// The number of background documents per foreground term of deserialized results.
+    #[serde(deserialize_with = "intermediate_key_map::deserialize")]
+    pub(crate) background: FxHashMap<IntermediateKey, u64>,
+    /// The inverted indexes of the merged segments.
+    #[serde(skip)]
+    pub(crate) inverted_indexes: SegmentInvertedIndexes,
+    /// The number of foreground documents.
+    pub(crate) subset_size: u64,
+    /// The number of background documents.
+    pub(crate) superset_size: u64,
+}
+
+/// The inverted indexes of the segments merged into a significant terms result.
+#[derive(Default, Clone)]
+pub(crate) struct SegmentInvertedIndexes(pub(crate) Vec<Arc<InvertedIndexReader>>);
+
+impl Debug for SegmentInvertedIndexes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("SegmentInvertedIndexes")
+            .field(&self.0.len())
+            .finish()
+    }
+}
+
+impl PartialEq for SegmentInvertedIndexes {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.len() == other.0.len()
+            && self
+                .0
+                .iter()
+                .zip(other.0.iter())
+                .all(|(left, right)| Arc::ptr_eq(left, right))
+    }
+}
+
+impl Serialize for IntermediateSignificantTermsBucketResult {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        struct SerializedSignificantTerms<'a> {
+            #[serde(serialize_with = "intermediate_key_map::serialize_ref")]
+            entries: &'a FxHashMap<IntermediateKey, IntermediateTermBucketEntry>,
+            #[serde(serialize_with = "intermediate_key_map::serialize")]
+            background: FxHashMap<IntermediateKey, u64>,
+            subset_size: u64,
+            superset_size: u64,
+        }
+
+        let bucket_count = BucketCount::from_limits(AggregationLimits::default());
+        let background = self
+            .background_frequencies(&bucket_count)
+            .map_err(serde::ser::Error::custom)?;
+        SerializedSignificantTerms {
+            entries: &self.entries,
+            background,
+            subset_size: self.subset_size,
+            superset_size: self.superset_size,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl IntermediateSignificantTermsBucketResult {
+    /// Returns the background frequencies of the foreground terms, including the document
+    /// frequencies in the inverted indexes of the merged segments.
+    fn background_frequencies(
+        &self,
+        bucket_count: &BucketCount,
+    ) -> crate::Result<FxHashMap<IntermediateKey, u64>> {
+        let mut background = FxHashMap::default();
+        for key in self.entries.keys() {
+            let mut bg_count = self.background.get(key).copied().unwrap_or(0);
+            if let IntermediateKey::Str(term) = key {
+                bucket_count.add_memory_consumed((BACKGROUND_ENTRY_MEMORY + term.len()) as u64);
+                bucket_count.validate_limits()?;
+                for inverted_index in &self.inverted_indexes.0 {
+                    if let Some(term_info) = inverted_index.terms().get(term)? {
+                        bg_count += term_info.doc_freq as u64;
+                    }
+                }
+            }
+            background.insert(key.clone(), bg_count);
+        }
+        Ok(background)
+    }
+
+    pub(crate) fn into_final_result(
+        self,
+        req: &SignificantTermsAggregation,
+        sub_aggregation_req: &AggregationsInternal,
+        bucket_count: &BucketCount,
+    ) -> crate::Result<BucketResult> {
+        let heuristic = req.heuristic();
+        let min_doc_count = req.min_doc_count();
+        let background = self.background_frequencies(bucket_count)?;
+        let mut buckets = Vec::new();
+        for (key, entry) in self.entries {
+            if entry.doc_count < min_doc_count {
+                continue;
+            }
+            let bg_count = background.get(&key).copied().unwrap_or(0);
+            let score = heuristic.score(
+                entry.doc_count,
+                self.subset_size,
+                bg_count,
+                self.superset_size,
+            );
+            if score <= 0.0 {
+                continue;
+            }
+            buckets.push(SignificantTermBucketEntry {
+                key: key.into(),
+                doc_count: entry.doc_count,
+                score,
+                bg_count,
+                sub_aggregation: entry
+                    .sub_aggregation
+                    .into_final_bucket_result_internal(sub_aggregation_req, bucket_count)?,
+            });
+        }
+        buckets.sort_by(|left, right| {
+            right
+                .score
+                .total_cmp(&left.score)
+                .then_with(|| left.key.partial_cmp(&right.key).unwrap_or(Ordering::Equal))
+        });
+        buckets.truncate(req.size());
+        Ok(BucketResult::SignificantTerms {
+            doc_count: self.subset_size,
+            bg_count: self.superset_size,
+            buckets,
+        })
+    }
+
+    fn merge_fruits(&mut self, other: IntermediateSignificantTermsBucketResult) {
+        merge_maps(&mut self.entries, other.entries);
+        for (key, bg_count) in other.background {
+            *self.background.entry(key).or_insert(0) += bg_count;
+        }
+        self.inverted_indexes.0.extend(other.inverted_indexes.0);
+        self.subset_size += other.subset_size;
+        self.superset_size += other.superset_size;
+    }
+}
+
+#[derive(Default, Clone, Debug, PartialEq, Serialize, Deserialize)]
+/// Filters aggregation, the buckets are keyed by the filter keys.
+pub struct IntermediateFiltersBucketResult {
+    pub(crate) buckets: FxHashMap<SerializedKey, IntermediateFilterBucketEntry>,
+}
+
+impl IntermediateFiltersBucketResult {
+    pub(crate) fn into_final_result(
+        mut self,
+        req: &FiltersAggregation,
+        sub_aggregation_req: &AggregationsInternal,
+        bucket_count: &BucketCount,
+    ) -> crate::Result<BucketResult> {
+        let buckets = req
+            .bucket_keys()
+            .into_iter()
+            .map(|key| {
+                let bucket = self.buckets.remove(&key).unwrap_or_default();
+                Ok((
+                    key,
+                    bucket.into_final_bucket_entry(sub_aggregation_req, bucket_count)?,
+                ))
+            })
+            .collect::<crate::Result<Vec<_>>>()?;
+
+        let buckets = if req.is_keyed() {
+            BucketEntries::HashMap(buckets.into_iter().collect())
+        } else {
+            BucketEntries::Vec(buckets.into_iter().map(|(_, bucket)| bucket).collect())
+        };
+        Ok(BucketResult::Filters { buckets })
+    }
+}
+
+#[derive(Default, Clone, Debug, PartialEq, Serialize, Deserialize)]
+/// Composite aggregation, the buckets are sorted by their keys in the segment results.
+pub struct IntermediateCompositeBucketResult {
+    pub(crate) buckets: Vec<IntermediateCompositeBucketEntry>,
+}
+
+impl IntermediateCompositeBucketResult {
+    pub(crate) fn into_final_result(
+        self,
+        req: &CompositeAggregation,
+        sub_aggregation_req: &AggregationsInternal,
+        bucket_count: &BucketCount,
+    ) -> crate::Result<BucketResult> {
+        let mut buckets = self.buckets;
+        buckets.sort_unstable_by(|left, right| req.cmp_keys(&left.key, &right.key));
+        buckets.truncate(req.size as usize);
+        let buckets = buckets
+            .into_iter()
+            .map(|bucket| {
+                let key = req
+                    .sources
+                    .iter()
+                    .map(|named_source| named_source.name.clone())
+                    .zip(bucket.key.into_iter().map(Key::from))
+                    .collect();
+                Ok(CompositeBucketEntry {
+                    key,
+                    doc_count: bucket.doc_count,
+                    sub_aggregation: bucket
+                        .sub_aggregation
+                        .into_final_bucket_result_internal(sub_aggregation_req, bucket_count)?,
+                })
+            })
+            .collect::<crate::Result<Vec<_>>>()?;
+        let after_key = buckets.last().map(|bucket| bucket.key.clone());
+        Ok(BucketResult::Composite { after_key, buckets })
+    }
+
+    fn merge_fruits(&mut self, other: IntermediateCompositeBucketResult) {
+        let mut buckets: FxHashMap<Vec<IntermediateKey>, IntermediateCompositeBucketEntry> = self
+            .buckets
+            .drain(..)
+            .map(|bucket| (bucket.key.clone(), bucket))
+            .collect();
+        let other_buckets = other
+            .buckets
+            .into_iter()
+            .map(|bucket| (bucket.key.clone(), bucket))
+            .collect();
+        merge_maps(&mut buckets, other_buckets);
+        self.buckets = buckets.into_values().collect();
+    }
+}
+
+#[derive(Default, Clone, Debug, PartialEq, Serialize, Deserialize)]
+/// Geohash or geotile grid aggregation, the buckets are keyed by the keys of the cells.
+pub struct IntermediateGeoGridBucketResult {
+    pub(crate) buckets: FxHashMap<SerializedKey, IntermediateTermBucketEntry>,
+}
+
+impl IntermediateGeoGridBucketResult {
+    pub(crate) fn into_final_result(
+        self,
+        size: usize,
+        sub_aggregation_req: &AggregationsInternal,
+        bucket_count: &BucketCount,
+    ) -> crate::Result<BucketResult> {
+        let mut buckets = self.buckets.into_iter().collect::<Vec<_>>();
+        buckets.sort_unstable_by(|(left_key, left), (right_key, right)| {
+            right
+                .doc_count
+                .cmp(&left.doc_count)
+                .then_with(|| left_key.cmp(right_key))
+        });
+        buckets.truncate(size);
+        let buckets = buckets
+            .into_iter()
+            .map(|(key, entry)| {
+                Ok(BucketEntry {
+                    key: Key::Str(key),
+                    key_as_string: None,
+                    doc_count: entry.doc_count,
+                    sub_aggregation: entry
+                        .sub_aggregation
+                        .into_final_bucket_result_internal(sub_aggregation_req, bucket_count)?,
+                })
+            })
+            .collect::<crate::Result<Vec<_>>>()?;
+        Ok(BucketResult::GeoGrid { buckets })
+    }
+}
+
 trait MergeFruits {
     fn merge_fruits(&mut self, other: Self);
 }
 
-fn merge_maps<V: MergeFruits + Clone>(
-    entries_left: &mut FxHashMap<SerializedKey, V>,
-    mut entries_right: FxHashMap<SerializedKey, V>,
+fn merge_maps<K: Hash + Eq, V: MergeFruits + Clone>(
+    entries_left: &mut FxHashMap<K, V>,
+    mut entries_right: FxHashMap<K, V>,
 ) {
     for (name, entry_left) in entries_left.iter_mut() {
         if let Some(entry_right) = entries_right.remove(name) {
@@ -528,13 +1163,15 @@ impl IntermediateHistogramBucketEntry {
     pub(crate) fn into_final_bucket_entry(
         self,
         req: &AggregationsInternal,
+        bucket_count: &BucketCount,
     ) -> crate::Result<BucketEntry> {
         Ok(BucketEntry {
             key: Key::F64(self.key),
+            key_as_string: None,
             doc_count: self.doc_count,
             sub_aggregation: self
                 .sub_aggregation
-                .into_final_bucket_result_internal(req)?,
+                .into_final_bucket_result_internal(req, bucket_count)?,
         })
     }
 }
@@ -571,13 +1208,14 @@ impl IntermediateRangeBucketEntry {
     pub(crate) fn into_final_bucket_entry(
         self,
         req: &AggregationsInternal,
+        bucket_count: &BucketCount,
     ) -> crate::Result<RangeBucketEntry> {
         Ok(RangeBucketEntry {
             key: self.key,
             doc_count: self.doc_count,
             sub_aggregation: self
                 .sub_aggregation
-                .into_final_bucket_result_internal(req)?,
+                .into_final_bucket_result_internal(req, bucket_count)?,
             to: self.to,
             from: self.from,
         })
@@ -594,6 +1232,57 @@ pub struct IntermediateTermBucketEntry {
     pub sub_aggregation: IntermediateAggregationResults,
 }
 
+/// This is the entry of a filter bucket, which contains a count, and optionally
+/// sub_aggregations.
+#[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+pub struct IntermediateFilterBucketEntry {
+    /// The number of documents in the bucket.
+    pub doc_count: u64,
+    /// The sub_aggregation in this bucket.
+    pub sub_aggregation: IntermediateAggregationResults,
+}
+
+impl IntermediateFilterBucketEntry {
+    pub(crate) fn into_final_bucket_entry(
+        self,
+        req: &AggregationsInternal,
+        bucket_count: &BucketCount,
+    ) -> crate::Result<FilterBucketEntry> {
+        Ok(FilterBucketEntry {
+            doc_count: self.doc_count,
+            sub_aggregation: self
+                .sub_aggregation
+                .into_final_bucket_result_internal(req, bucket_count)?,
+        })
+    }
+}
+
+/// This is the composite entry for a bucket, which contains a compound key, count, and optionally
+/// sub_aggregations.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct IntermediateCompositeBucketEntry {
+    /// The values of the sources, in the order of the sources.
+    pub key: Vec<IntermediateKey>,
+    /// The number of documents in the bucket.
+    pub doc_count: u64,
+    /// The sub_aggregation in this bucket.
+    pub sub_aggregation: IntermediateAggregationResults,
+}
+
+impl MergeFruits for IntermediateCompositeBucketEntry {
+    fn merge_fruits(&mut self, other: IntermediateCompositeBucketEntry) {
+        self.doc_count += other.doc_count;
+        self.sub_aggregation.merge_fruits(other.sub_aggregation);
+    }
+}
+
+impl MergeFruits for IntermediateFilterBucketEntry {
+    fn merge_fruits(&mut self, other: IntermediateFilterBucketEntry) {
+        self.doc_count += other.doc_count;
+        self.sub_aggregation.merge_fruits(other.sub_aggregation);
+    }
+}
+
 impl MergeFruits for IntermediateTermBucketEntry {
     fn merge_fruits(&mut self, other: IntermediateTermBucketEntry) {
         self.doc_count += other.doc_count;
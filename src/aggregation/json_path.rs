@@ -0,0 +1,254 @@
+//! Access to the values of a path inside a json field.
+//!
+//! Json fields don't have fast fields, so the values of a path are uninverted from the inverted
+//! index of the segment when the aggregation is created.
+
+use std::sync::Arc;
+
+use fastfield_codecs::{Column, MonotonicallyMappableToU64};
+use rustc_hash::FxHashMap;
+
+use super::segment_agg_result::BucketCount;
+use super::IntermediateKey;
+use crate::fastfield::MultiValuedFastFieldReader;
+use crate::schema::term::{JSON_END_OF_PATH, JSON_PATH_SEGMENT_SEP_STR};
+use crate::schema::{Field, IndexRecordOption, Schema, Type};
+use crate::{DocId, SegmentReader, TantivyError};
+
+/// Splits a path like `attributes.status` into the json field `attributes` and the path `status`
+/// inside of it.
+///
+/// Returns `None` if no prefix of the path is a field of the schema.
+pub(crate) fn split_json_path<'a>(schema: &Schema, full_path: &'a str) -> Option<(Field, &'a str)> {
+    if let Some(field) = schema.get_field(full_path) {
+        return Some((field, ""));
+    }
+    full_path.match_indices('.').rev().find_map(|(pos, _)| {
+        let (field_name, json_path) = full_path.split_at(pos);
+        schema
+            .get_field(field_name)
+            .map(|field| (field, &json_path[1..]))
+    })
+}
+
+/// The values of a path inside a json field of a segment.
+///
+/// Every distinct value gets an ordinal, and the ordinals are accessible per document like a
+/// multivalued fast field. Each value is contained at most once per document. The ordinals are
+/// assigned in the order of the values, like term ordinals.
+///
+/// Only text and numeric values are supported, other values are ignored. Numeric values are
+/// converted to `f64`, so that e.g. `5` and `5.0` are the same value.
+///
+/// The columns are held in memory for the collection of the segment, so they are counted against
+/// the memory limit of the aggregation.
+#[derive(Clone)]
+pub(crate) struct JsonPathValues {
+    /// The distinct values of the path in ascending order, indexed by their ordinal.
+    pub keys: Vec<IntermediateKey>,
+    /// The value ordinals of each document.
+    pub ordinals: MultiValuedFastFieldReader<u64>,
+}
+
+impl JsonPathValues {
+    pub(crate) fn open(
+        reader: &SegmentReader,
+        field: Field,
+        json_path: &str,
+        bucket_count: &BucketCount,
+    ) -> crate::Result<JsonPathValues> {
+        if json_path.is_empty() {
+            return Err(TantivyError::InvalidArgument(format!(
+                "aggregations on the json field {} require a path inside the field",
+                reader.schema().get_field_name(field)
+            )));
+        }
+        let mut path_prefix = json_path
+            .replace('.', JSON_PATH_SEGMENT_SEP_STR)
+            .into_bytes();
+        path_prefix.push(JSON_END_OF_PATH);
+        let mut path_prefix_end = path_prefix.clone();
+        *path_prefix_end.last_mut().unwrap() += 1;
+
+        let inverted_index = reader.inverted_index(field)?;
+        let mut term_stream = inverted_index
+            .terms()
+            .range()
+            .ge(&path_prefix)
+            .lt(&path_prefix_end)
+            .into_stream()?;
+
+        let mut keys = Vec::new();
+        let mut key_to_ordinal: FxHashMap<IntermediateKey, u64> = FxHashMap::default();
+        let mut doc_and_ordinals: Vec<(DocId, u64)> = Vec::new();
+        while term_stream.advance() {
+            let (typ, value_bytes) = term_stream.key()[path_prefix.len()..]
+                .split_first()
+                .expect("json term without type");
+            let key = match decode_key(*typ, value_bytes) {
+                Some(key) => key,
+                None => continue,
+            };
+            let ordinal = *key_to_ordinal.entry(key.clone()).or_insert_with(|| {
+                keys.push(key);
+                keys.len() as u64 - 1
+            });
+            let term_info = term_stream.value();
+            // The documents of the term are counted against the memory limit before they are
+            // collected.
+            let num_bytes = term_info.doc_freq as usize * std::mem::size_of::<(DocId, u64)>();
+            bucket_count.add_memory_consumed(num_bytes as u64);
+            bucket_count.validate_limits()?;
+            let mut block_postings = inverted_index
+                .read_block_postings_from_terminfo(term_info, IndexRecordOption::Basic)?;
+            while !block_postings.docs().is_empty() {
+                doc_and_ordinals.extend(block_postings.docs().iter().map(|&doc| (doc, ordinal)));
+                block_postings.advance();
+            }
+        }
+        // Renumber the values, so that the order of the ordinals matches the order of the keys.
+        let mut sorted_ordinals: Vec<usize> = (0..keys.len()).collect();
+        sorted_ordinals.sort_unstable_by(|&left, &right| keys[left].cmp(&keys[right]));
+        let mut new_ordinals = vec![0u64; keys.len()];
+        for (new_ordinal, &ordinal) in sorted_ordinals.iter().enumerate() {
+            new_ordinals[ordinal] = new_ordinal as u64;
+        }
+        let keys = sorted_ordinals
+            .into_iter()
+            .map(|ordinal| keys[ordinal].clone())
+            .collect();
+        for (_doc, ordinal) in doc_and_ordinals.iter_mut() {
+            *ordinal = new_ordinals[*ordinal as usize];
+        }
+        doc_and_ordinals.sort_unstable();
+        doc_and_ordinals.dedup();
+
+        let max_doc = reader.max_doc();
+        let mut offsets = Vec::with_capacity(max_doc as usize + 1);
+        let mut num_vals = 0u64;
+        let mut doc_and_ordinals_iter = doc_and_ordinals.iter().peekable();
+        for doc in 0..max_doc {
+            offsets.push(num_vals);
+            while doc_and_ordinals_iter
+                .next_if(|(val_doc, _)| *val_doc == doc)
+                .is_some()
+            {
+                num_vals += 1;
+            }
+        }
+        offsets.push(num_vals);
+        let ordinals = doc_and_ordinals
+            .into_iter()
+            .map(|(_doc, ordinal)| ordinal)
+            .collect();
+
+        Ok(JsonPathValues {
+            keys,
+            ordinals: open_multi_valued(offsets, ordinals, bucket_count)?,
+        })
+    }
+
+    /// Returns the numeric values of each document in their `f64` fast field representation,
+    /// like a multivalued `f64` fast field. Text values are skipped.
+    pub(crate) fn numeric_values(
+        &self,
+        bucket_count: &BucketCount,
+    ) -> crate::Result<MultiValuedFastFieldReader<u64>> {
+        let ordinal_to_value: Vec<Option<u64>> = self
+            .keys
+            .iter()
+            .map(|key| match key {
+                IntermediateKey::F64(value) => Some(value.to_u64()),
+                IntermediateKey::Str(_) => None,
+            })
+            .collect();
+        let num_docs = self.ordinals.get_index_reader().num_docs();
+        let mut offsets = Vec::with_capacity(num_docs as usize + 1);
+        let mut values = Vec::new();
+        let mut ordinals = Vec::new();
+        for doc in 0..num_docs {
+            offsets.push(values.len() as u64);
+            self.ordinals.get_vals(doc, &mut ordinals);
+            values.extend(
+                ordinals
+                    .iter()
+                    .filter_map(|&ordinal| ordinal_to_value[ordinal as usize]),
+            );
+        }
+        offsets.push(values.len() as u64);
+        open_multi_valued(offsets, values, bucket_count)
+    }
+}
+
+/// Wraps the columns in a multivalued reader, after counting their memory against the limit.
+fn open_multi_valued(
+    offsets: Vec<u64>,
+    values: Vec<u64>,
+    bucket_count: &BucketCount,
+) -> crate::Result<MultiValuedFastFieldReader<u64>> {
+    let num_bytes = (offsets.len() + values.len()) * std::mem::size_of::<u64>();
+    bucket_count.add_memory_consumed(num_bytes as u64);
+    bucket_count.validate_limits()?;
+    Ok(MultiValuedFastFieldReader::open(
+        Arc::new(OwnedColumn::new(offsets)),
+        Arc::new(OwnedColumn::new(values)),
+    ))
+}
+
+fn decode_key(type_code: u8, value_bytes: &[u8]) -> Option<IntermediateKey> {
+    let typ = Type::from_code(type_code)?;
+    if typ == Type::Str {
+        return std::str::from_utf8(value_bytes)
+            .ok()
+            .map(|text| IntermediateKey::Str(text.to_string()));
+    }
+    let value = u64::from_be_bytes(value_bytes.try_into().ok()?);
+    let value = match typ {
+        Type::U64 => u64::from_u64(value) as f64,
+        Type::I64 => i64::from_u64(value) as f64,
+        Type::F64 => f64::from_u64(value),
+        _ => return None,
+    };
+    Some(IntermediateKey::F64(value))
+}
+
+/// A column over values, which are computed at search time.
+struct OwnedColumn {
+    values: Vec<u64>,
+    min_value: u64,
+    max_value: u64,
+}
+
+impl OwnedColumn {
+    fn new(values: Vec<u64>) -> Self {
+        let min_value = values.iter().copied().min().unwrap_or(0);
+        let max_value = values.iter().copied().max().unwrap_or(0);
+        OwnedColumn {
+            values,
+            min_value,
+            max_value,
+        }
+    }
+}
+
+impl Column<u64> for OwnedColumn {
+    fn get_val(&self, idx: u32) -> u64 {
+        self.values[idx as usize]
+    }
+
+    fn get_range(&self, start: u64, output: &mut [u64]) {
+        output.copy_from_slice(&self.values[start as usize..][..output.len()])
+    }
+
+    fn min_value(&self) -> u64 {
+        self.min_value
+    }
+
+    fn max_value(&self) -> u64 {
+        self.max_value
+    }
+
+    fn num_vals(&self) -> u32 {
+        self.values.len() as u32
+    }
+}
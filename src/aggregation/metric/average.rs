@@ -4,6 +4,7 @@ use fastfield_codecs::Column;
 use serde::{Deserialize, Serialize};
 
 use crate::aggregation::f64_from_fastfield_u64;
+use crate::fastfield::MultiValuedFastFieldReader;
 use crate::schema::Type;
 use crate::DocId;
 
@@ -79,6 +80,21 @@ impl SegmentAverageCollector {
             self.data.collect(val);
         }
     }
+
+    pub(crate) fn collect_block_multi(
+        &mut self,
+        doc: &[DocId],
+        field: &MultiValuedFastFieldReader<u64>,
+    ) {
+        let mut vals = Vec::new();
+        for &doc in doc {
+            field.get_vals(doc, &mut vals);
+            for &val in &vals {
+                let val = f64_from_fastfield_u64(val, &self.field_type);
+                self.data.collect(val);
+            }
+        }
+    }
 }
 
 /// Contains mergeable version of average data.
@@ -0,0 +1,569 @@
+use std::fmt::Debug;
+
+use rustc_hash::FxHashSet;
+use serde::{Deserialize, Serialize};
+
+use crate::aggregation::agg_req_with_accessor::{FastFieldAccessor, MetricAggregationWithAccessor};
+use crate::schema::Type;
+use crate::DocId;
+
+/// A single-value metric aggregation that estimates the number of distinct values of a field.
+/// Supported field types are `u64`, `i64`, `f64`, `bool`, `date`, `ip` and text or facet fast
+/// fields, single or multi-valued.
+/// See [super::SingleMetricResult] for return value.
+///
+/// The estimation is based on the HyperLogLog++ algorithm. Small cardinalities are counted exactly,
+/// larger ones are estimated with a relative standard error of about 0.8%.
+/// The intermediate sketches can be merged losslessly, so the results of several segments or
+/// indices are the same as if the values had been collected in one segment.
+///
+/// # JSON Format
+/// ```json
+/// {
+///     "cardinality": {
+///         "field": "user_id",
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CardinalityAggregation {
+    /// The field name to compute the cardinality on.
+    pub field: String,
+}
+
+impl CardinalityAggregation {
+    /// Create new CardinalityAggregation from a field.
+    pub fn from_field_name(field_name: String) -> Self {
+        CardinalityAggregation { field: field_name }
+    }
+    /// Return the field name.
+    pub fn field_name(&self) -> &str {
+        &self.field
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub(crate) struct SegmentCardinalityCollector {
+    sketch: HyperLogLog,
+    /// Term ordinals are local to a segment, so for text fields they are collected first and
+    /// resolved to their term bytes when converting to the intermediate result.
+    term_ords: FxHashSet<u64>,
+    field_type: Type,
+    vals: Vec<u64>,
+    vals_u128: Vec<u128>,
+}
+
+impl Debug for SegmentCardinalityCollector {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CardinalityCollector")
+            .field("sketch", &self.sketch)
+            .field("num_term_ords", &self.term_ords.len())
+            .finish()
+    }
+}
+
+impl SegmentCardinalityCollector {
+    pub fn from_req(field_type: Type) -> Self {
+        Self {
+            sketch: HyperLogLog::default(),
+            term_ords: Default::default(),
+            field_type,
+            vals: Vec::new(),
+            vals_u128: Vec::new(),
+        }
+    }
+
+    /// Returns the estimated memory in bytes of the collector once the sketch is dense.
+    ///
+    /// The term ordinals of text fields are not accounted for.
+    pub(crate) fn memory_estimate(&self) -> u64 {
+        (std::mem::size_of::<Self>() + NUM_REGISTERS) as u64
+    }
+
+    fn uses_term_ords(&self) -> bool {
+        matches!(self.field_type, Type::Str | Type::Facet)
+    }
+
+    pub(crate) fn collect_block(&mut self, docs: &[DocId], accessor: &FastFieldAccessor) {
+        match accessor {
+            FastFieldAccessor::Single(column) => {
+                for &doc in docs {
+                    self.sketch.insert_hash(hash_u64(column.get_val(doc)));
+                }
+            }
+            FastFieldAccessor::Multi(reader) => {
+                let uses_term_ords = self.uses_term_ords();
+                for &doc in docs {
+                    reader.get_vals(doc, &mut self.vals);
+                    if uses_term_ords {
+                        self.term_ords.extend(self.vals.iter().copied());
+                    } else {
+                        for &val in &self.vals {
+                            self.sketch.insert_hash(hash_u64(val));
+                        }
+                    }
+                }
+            }
+            FastFieldAccessor::SingleU128(column) => {
+                for &doc in docs {
+                    self.sketch.insert_hash(hash_u128(column.get_val(doc)));
+                }
+            }
+            FastFieldAccessor::MultiU128(reader) => {
+                for &doc in docs {
+                    reader.get_vals(doc, &mut self.vals_u128);
+                    for &val in &self.vals_u128 {
+                        self.sketch.insert_hash(hash_u128(val));
+                    }
+                }
+            }
+        }
+    }
+
+    pub(crate) fn into_intermediate_cardinality(
+        self,
+        agg_with_accessor: &MetricAggregationWithAccessor,
+    ) -> crate::Result<IntermediateCardinality> {
+        let mut sketch = self.sketch;
+        if !self.term_ords.is_empty() {
+            let inverted_index = agg_with_accessor
+                .inverted_index
+                .as_ref()
+                .expect("internal error: inverted index not loaded for cardinality aggregation");
+            let term_dict = inverted_index.terms();
+            let mut term_ords: Vec<u64> = self.term_ords.into_iter().collect();
+            term_ords.sort_unstable();
+            let mut buffer = Vec::new();
+            for term_ord in term_ords {
+                term_dict.ord_to_term(term_ord, &mut buffer)?;
+                sketch.insert_hash(hash_bytes(&buffer));
+            }
+        }
+        Ok(IntermediateCardinality { sketch })
+    }
+}
+
+/// Contains the mergeable sketch of the cardinality aggregation.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct IntermediateCardinality {
+    sketch: HyperLogLog,
+}
+
+impl IntermediateCardinality {
+    /// Merge the sketch of another cardinality aggregation into this instance.
+    pub fn merge_fruits(&mut self, other: IntermediateCardinality) {
+        self.sketch.merge(other.sketch);
+    }
+
+    /// Compute the estimated number of distinct values.
+    pub fn finalize(&self) -> f64 {
+        self.sketch.estimate().round()
+    }
+}
+
+/// Number of bits of the hash used to select the register.
+const PRECISION: u32 = 14;
+const NUM_REGISTERS: usize = 1 << PRECISION;
+/// Maximum register value, reached if all bits after the register index are zero.
+const MAX_REGISTER_VALUE: u8 = (64 - PRECISION + 1) as u8;
+/// As long as there are fewer distinct hashes than this, they are stored as is.
+/// The threshold is chosen so that the sparse representation never uses more memory than the
+/// dense registers.
+const SPARSE_THRESHOLD: usize = NUM_REGISTERS / 8;
+
+/// HyperLogLog++ sketch over 64 bit hashes.
+///
+/// Like HyperLogLog++, the sketch starts with a sparse representation, which is exact, and
+/// switches to the dense register representation once the sparse representation grows too large.
+/// The dense estimate uses the improved estimator by Otmar Ertl ("New cardinality estimation
+/// algorithms for HyperLogLog sketches", 2017), which does not require empirical bias
+/// correction.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+enum HyperLogLog {
+    Sparse(FxHashSet<u64>),
+    Dense(Vec<u8>),
+}
+
+impl Default for HyperLogLog {
+    fn default() -> Self {
+        HyperLogLog::Sparse(FxHashSet::default())
+    }
+}
+
+impl HyperLogLog {
+    #[inline]
+    fn insert_hash(&mut self, hash: u64) {
+        match self {
+            HyperLogLog::Sparse(hashes) => {
+                hashes.insert(hash);
+                if hashes.len() > SPARSE_THRESHOLD {
+                    self.convert_to_dense();
+                }
+            }
+            HyperLogLog::Dense(registers) => insert_into_registers(registers, hash),
+        }
+    }
+
+    fn convert_to_dense(&mut self) {
+        if let HyperLogLog::Sparse(hashes) = self {
+            let mut registers = vec![0u8; NUM_REGISTERS];
+            for &hash in hashes.iter() {
+                insert_into_registers(&mut registers, hash);
+            }
+            *self = HyperLogLog::Dense(registers);
+        }
+    }
+
+    fn merge(&mut self, other: HyperLogLog) {
+        match other {
+            HyperLogLog::Sparse(hashes) => {
+                for hash in hashes {
+                    self.insert_hash(hash);
+                }
+            }
+            HyperLogLog::Dense(other_registers) => {
+                self.convert_to_dense();
+                if let HyperLogLog::Dense(registers) = self {
+                    for (register, other_register) in registers.iter_mut().zip(other_registers) {
+                        *register = (*register).max(other_register);
+                    }
+                }
+            }
+        }
+    }
+
+    fn estimate(&self) -> f64 {
+        match self {
+            HyperLogLog::Sparse(hashes) => hashes.len() as f64,
+            HyperLogLog::Dense(registers) => estimate_from_registers(registers),
+        }
+    }
+}
+
+#[inline]
+fn insert_into_registers(registers: &mut [u8], hash: u64) {
+    let index = (hash >> (64 - PRECISION)) as usize;
+    let remaining_bits = hash << PRECISION;
+    let value = if remaining_bits == 0 {
+        MAX_REGISTER_VALUE
+    } else {
+        remaining_bits.leading_zeros() as u8 + 1
+    };
+    if registers[index] < value {
+        registers[index] = value;
+    }
+}
+
+fn estimate_from_registers(registers: &[u8]) -> f64 {
+    let num_registers = registers.len() as f64;
+    let max_value = MAX_REGISTER_VALUE as usize;
+    let mut histogram = [0u32; MAX_REGISTER_VALUE as usize + 1];
+    for &register in registers {
+        histogram[register as usize] += 1;
+    }
+    let mut z = num_registers * tau(1.0 - histogram[max_value] as f64 / num_registers);
+    for &count in histogram[1..max_value].iter().rev() {
+        z = 0.5 * (z + count as f64);
+    }
+    z += num_registers * sigma(histogram[0] as f64 / num_registers);
+    let alpha_inf = 0.5 / std::f64::consts::LN_2;
+    alpha_inf * num_registers * num_registers / z
+}
+
+fn sigma(mut x: f64) -> f64 {
+    if x == 1.0 {
+        return f64::INFINITY;
+    }
+    let mut y = 1.0;
+    let mut z = x;
+    loop {
+        x *= x;
+        let previous_z = z;
+        z += x * y;
+        y += y;
+        if previous_z == z {
+            return z;
+        }
+    }
+}
+
+fn tau(mut x: f64) -> f64 {
+    if x == 0.0 || x == 1.0 {
+        return 0.0;
+    }
+    let mut y = 1.0;
+    let mut z = 1.0 - x;
+    loop {
+        x = x.sqrt();
+        let previous_z = z;
+        y *= 0.5;
+        z -= (1.0 - x).powi(2) * y;
+        if previous_z == z {
+            return z / 3.0;
+        }
+    }
+}
+
+/// Finalizer of murmur3, a bijection on `u64` with good avalanche behaviour.
+#[inline]
+fn fmix64(mut val: u64) -> u64 {
+    val ^= val >> 33;
+    val = val.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    val ^= val >> 33;
+    val = val.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+    val ^= val >> 33;
+    val
+}
+
+// The hashes have to be stable, since intermediate results may be merged across processes.
+
+#[inline]
+fn hash_u64(val: u64) -> u64 {
+    // Offset the value, so that the common value 0 does not hash to 0.
+    fmix64(val.wrapping_add(0x9e37_79b9_7f4a_7c15))
+}
+
+#[inline]
+fn hash_u128(val: u128) -> u64 {
+    hash_u64((val as u64) ^ fmix64((val >> 64) as u64))
+}
+
+/// FNV-1a, followed by a finalizer to spread the bits.
+fn hash_bytes(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for &byte in bytes {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    fmix64(hash)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use serde_json::Value;
+
+    use super::*;
+    use crate::aggregation::agg_req::{
+        Aggregation, Aggregations, BucketAggregation, BucketAggregationType, MetricAggregation,
+    };
+    use crate::aggregation::bucket::TermsAggregation;
+    use crate::aggregation::intermediate_agg_result::IntermediateAggregationResults;
+    use crate::aggregation::tests::{
+        exec_request, get_test_index_2_segments, get_test_index_from_values,
+        get_test_index_from_values_and_terms,
+    };
+    use crate::aggregation::DistributedAggregationCollector;
+    use crate::query::AllQuery;
+
+    fn get_cardinality_req(field_name: &str) -> Aggregation {
+        Aggregation::Metric(MetricAggregation::Cardinality(
+            CardinalityAggregation::from_field_name(field_name.to_string()),
+        ))
+    }
+
+    fn assert_relative_error(estimate: f64, expected: f64, max_error: f64) {
+        let error = (estimate - expected).abs() / expected;
+        assert!(
+            error < max_error,
+            "estimate {} for {} exceeds relative error {}",
+            estimate,
+            expected,
+            max_error
+        );
+    }
+
+    #[test]
+    fn test_hyperloglog_exact_in_sparse_mode() {
+        let mut sketch = HyperLogLog::default();
+        for val in 0..SPARSE_THRESHOLD as u64 {
+            sketch.insert_hash(hash_u64(val));
+            sketch.insert_hash(hash_u64(val));
+        }
+        assert!(matches!(sketch, HyperLogLog::Sparse(_)));
+        assert_eq!(sketch.estimate(), SPARSE_THRESHOLD as f64);
+    }
+
+    #[test]
+    fn test_hyperloglog_estimate() {
+        for &num_vals in &[3_000u64, 20_000, 100_000, 1_000_000] {
+            let mut sketch = HyperLogLog::default();
+            for val in 0..num_vals {
+                sketch.insert_hash(hash_u64(val));
+            }
+            assert!(matches!(sketch, HyperLogLog::Dense(_)));
+            assert_relative_error(sketch.estimate(), num_vals as f64, 0.03);
+        }
+    }
+
+    #[test]
+    fn test_hyperloglog_merge() {
+        let mut left = HyperLogLog::default();
+        let mut right = HyperLogLog::default();
+        let mut small = HyperLogLog::default();
+        let mut all = HyperLogLog::default();
+        for val in 0..50_000u64 {
+            left.insert_hash(hash_u64(val));
+            all.insert_hash(hash_u64(val));
+        }
+        for val in 25_000..80_000u64 {
+            right.insert_hash(hash_u64(val));
+            all.insert_hash(hash_u64(val));
+        }
+        for val in 79_900..80_100u64 {
+            small.insert_hash(hash_u64(val));
+            all.insert_hash(hash_u64(val));
+        }
+        left.merge(right);
+        left.merge(small);
+        assert_eq!(left, all);
+        assert_relative_error(left.estimate(), 80_100.0, 0.03);
+    }
+
+    #[test]
+    fn test_aggregation_cardinality_empty_index() -> crate::Result<()> {
+        let index = get_test_index_from_values(false, &[])?;
+        let agg_req: Aggregations = vec![("cardinality".to_string(), get_cardinality_req("score"))]
+            .into_iter()
+            .collect();
+
+        let res = exec_request(agg_req, &index)?;
+        assert_eq!(res["cardinality"]["value"], 0.0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_aggregation_cardinality() -> crate::Result<()> {
+        let segment_and_values = vec![
+            vec![(1.0, "a".to_string()), (2.0, "b".to_string())],
+            vec![(2.0, "b".to_string()), (3.0, "c".to_string())],
+            vec![(1.0, "c".to_string()), (2.0, "a".to_string())],
+        ];
+        for merge_segments in [false, true] {
+            let index = get_test_index_from_values_and_terms(merge_segments, &segment_and_values)?;
+            let agg_req: Aggregations = serde_json::from_value(json!({
+                "card_u64": { "cardinality": { "field": "score" } },
+                "card_i64": { "cardinality": { "field": "score_i64" } },
+                "card_f64": { "cardinality": { "field": "fraction_f64" } },
+                "card_text": { "cardinality": { "field": "string_id" } },
+                "terms": {
+                    "terms": { "field": "string_id" },
+                    "aggs": {
+                        "card": { "cardinality": { "field": "score" } }
+                    }
+                }
+            }))
+            .unwrap();
+
+            let res = exec_request(agg_req, &index)?;
+            assert_eq!(res["card_u64"]["value"], 3.0);
+            assert_eq!(res["card_i64"]["value"], 3.0);
+            assert_eq!(res["card_f64"]["value"], 3.0);
+            assert_eq!(res["card_text"]["value"], 3.0);
+
+            let buckets = res["terms"]["buckets"].as_array().unwrap();
+            let card_for_key = |key: &str| {
+                buckets
+                    .iter()
+                    .find(|bucket| bucket["key"] == key)
+                    .map(|bucket| bucket["card"]["value"].clone())
+                    .unwrap()
+            };
+            assert_eq!(card_for_key("a"), 2.0);
+            assert_eq!(card_for_key("b"), 1.0);
+            assert_eq!(card_for_key("c"), 2.0);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_aggregation_cardinality_multi_value() -> crate::Result<()> {
+        let index = get_test_index_2_segments(false)?;
+        let agg_req: Aggregations =
+            vec![("cardinality".to_string(), get_cardinality_req("scores_i64"))]
+                .into_iter()
+                .collect();
+
+        let res = exec_request(agg_req, &index)?;
+        // values are 1, 2, 5, 5
+        assert_eq!(res["cardinality"]["value"], 3.0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_aggregation_cardinality_distributed() -> crate::Result<()> {
+        let values: Vec<f64> = (0..5_000).map(|val| (val % 3_000) as f64).collect();
+        let segment_and_values: Vec<Vec<(f64, String)>> = values
+            .chunks(1_000)
+            .map(|chunk| {
+                chunk
+                    .iter()
+                    .map(|val| (*val, format!("term{}", val)))
+                    .collect()
+            })
+            .collect();
+        let index = get_test_index_from_values_and_terms(false, &segment_and_values)?;
+        let agg_req: Aggregations = vec![
+            ("card_u64".to_string(), get_cardinality_req("score")),
+            ("card_text".to_string(), get_cardinality_req("text_id")),
+        ]
+        .into_iter()
+        .collect();
+
+        let collector = DistributedAggregationCollector::from_aggs(agg_req.clone(), None);
+        let searcher = index.reader()?.searcher();
+        let intermediate_res: IntermediateAggregationResults =
+            searcher.search(&AllQuery, &collector)?;
+
+        // Simulate the result being sent over the wire and merged with an identical result.
+        let mut merged_res: IntermediateAggregationResults =
+            serde_json::from_str(&serde_json::to_string(&intermediate_res)?)?;
+        merged_res.merge_fruits(intermediate_res);
+
+        let res: Value = serde_json::to_value(merged_res.into_final_bucket_result(agg_req)?)?;
+        assert_relative_error(res["card_u64"]["value"].as_f64().unwrap(), 3_000.0, 0.03);
+        assert_relative_error(res["card_text"]["value"].as_f64().unwrap(), 3_000.0, 0.03);
+        Ok(())
+    }
+
+    #[test]
+    fn test_aggregation_cardinality_order_terms_by_sub_agg() -> crate::Result<()> {
+        let index = get_test_index_from_values_and_terms(
+            false,
+            &[vec![
+                (1.0, "a".to_string()),
+                (2.0, "a".to_string()),
+                (3.0, "b".to_string()),
+                (3.0, "b".to_string()),
+                (3.0, "b".to_string()),
+            ]],
+        )?;
+        let agg_req: Aggregations = vec![(
+            "terms".to_string(),
+            Aggregation::Bucket(BucketAggregation {
+                bucket_agg: BucketAggregationType::Terms(TermsAggregation {
+                    field: "string_id".to_string(),
+                    order: Some(crate::aggregation::bucket::CustomOrder {
+                        target: crate::aggregation::bucket::OrderTarget::SubAggregation(
+                            "card".to_string(),
+                        ),
+                        order: crate::aggregation::bucket::Order::Desc,
+                    }),
+                    ..Default::default()
+                }),
+                sub_aggregation: vec![("card".to_string(), get_cardinality_req("score"))]
+                    .into_iter()
+                    .collect(),
+            }),
+        )]
+        .into_iter()
+        .collect();
+
+        let res = exec_request(agg_req, &index)?;
+        assert_eq!(res["terms"]["buckets"][0]["key"], "a");
+        assert_eq!(res["terms"]["buckets"][0]["card"]["value"], 2.0);
+        assert_eq!(res["terms"]["buckets"][1]["key"], "b");
+        assert_eq!(res["terms"]["buckets"][1]["card"]["value"], 1.0);
+        Ok(())
+    }
+}
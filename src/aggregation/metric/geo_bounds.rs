@@ -0,0 +1,213 @@
+use serde::{Deserialize, Serialize};
+
+use crate::aggregation::geo::GeoPointAccessor;
+use crate::aggregation::GeoPoint;
+use crate::DocId;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+/// A metric aggregation that computes the bounding box containing the geo points of the
+/// aggregated documents.
+/// The geo point of a document is read from the two `f64` fast fields `lat_field` and
+/// `lon_field`.
+/// See [`GeoBoundsMetricResult`] for return value.
+///
+/// # Limitations/Compatibility
+/// The bounding box never wraps around the date line, like `wrap_longitude: false` in
+/// elasticsearch.
+///
+/// # JSON Format
+/// ```json
+/// {
+///     "geo_bounds": {
+///         "lat_field": "latitude",
+///         "lon_field": "longitude"
+///     }
+/// }
+/// ```
+pub struct GeoBoundsAggregation {
+    /// The f64 fast field containing the latitude of the documents.
+    pub lat_field: String,
+    /// The f64 fast field containing the longitude of the documents.
+    pub lon_field: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct SegmentGeoBoundsCollector {
+    pub bounds: IntermediateGeoBounds,
+}
+
+impl SegmentGeoBoundsCollector {
+    pub fn from_req() -> Self {
+        Self {
+            bounds: Default::default(),
+        }
+    }
+
+    pub(crate) fn collect_block(&mut self, docs: &[DocId], geo_point: &GeoPointAccessor) {
+        for &doc in docs {
+            self.bounds.collect(geo_point.get(doc));
+        }
+    }
+}
+
+/// Contains the mergeable bounding box of geo points.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct IntermediateGeoBounds {
+    count: u64,
+    top: f64,
+    bottom: f64,
+    left: f64,
+    right: f64,
+}
+
+impl Default for IntermediateGeoBounds {
+    fn default() -> Self {
+        Self {
+            count: 0,
+            top: f64::MIN,
+            bottom: f64::MAX,
+            left: f64::MAX,
+            right: f64::MIN,
+        }
+    }
+}
+
+impl IntermediateGeoBounds {
+    /// Merge the bounding box into this instance.
+    pub fn merge_fruits(&mut self, other: IntermediateGeoBounds) {
+        self.count += other.count;
+        self.top = self.top.max(other.top);
+        self.bottom = self.bottom.min(other.bottom);
+        self.left = self.left.min(other.left);
+        self.right = self.right.max(other.right);
+    }
+
+    /// Compute the final result.
+    pub fn finalize(&self) -> GeoBoundsMetricResult {
+        let bounds = if self.count == 0 {
+            None
+        } else {
+            Some(GeoBounds {
+                top_left: GeoPoint {
+                    lat: self.top,
+                    lon: self.left,
+                },
+                bottom_right: GeoPoint {
+                    lat: self.bottom,
+                    lon: self.right,
+                },
+            })
+        };
+        GeoBoundsMetricResult { bounds }
+    }
+
+    #[inline]
+    fn collect(&mut self, point: GeoPoint) {
+        self.count += 1;
+        self.top = self.top.max(point.lat);
+        self.bottom = self.bottom.min(point.lat);
+        self.left = self.left.min(point.lon);
+        self.right = self.right.max(point.lon);
+    }
+}
+
+/// The result of the geo bounds aggregation.
+///
+/// # JSON Format
+/// ```json
+/// {
+///     "bounds": {
+///         "top_left": { "lat": 52.52, "lon": 4.895 },
+///         "bottom_right": { "lat": 52.0907, "lon": 13.405 }
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GeoBoundsMetricResult {
+    /// The bounding box, `None` if no document was aggregated.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub bounds: Option<GeoBounds>,
+}
+
+/// A bounding box of geo points.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GeoBounds {
+    /// The north western corner of the bounding box.
+    pub top_left: GeoPoint,
+    /// The south eastern corner of the bounding box.
+    pub bottom_right: GeoPoint,
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::aggregation::agg_req::Aggregations;
+    use crate::aggregation::tests::{
+        exec_request, exec_request_with_query, get_test_index_with_geo_points,
+    };
+
+    #[test]
+    fn geo_bounds_and_centroid_test() -> crate::Result<()> {
+        let index = get_test_index_with_geo_points(false)?;
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "bounds": {
+                "geo_bounds": { "lat_field": "lat", "lon_field": "lon" }
+            },
+            "centroid": {
+                "geo_centroid": { "lat_field": "lat", "lon_field": "lon" }
+            },
+            "fleets": {
+                "terms": { "field": "fleet" },
+                "aggs": {
+                    "centroid": {
+                        "geo_centroid": { "lat_field": "lat", "lon_field": "lon" }
+                    }
+                }
+            }
+        }))
+        .unwrap();
+
+        let res = exec_request(agg_req, &index)?;
+        assert_eq!(
+            res["bounds"],
+            json!({
+                "bounds": {
+                    "top_left": { "lat": 52.52, "lon": 4.895 },
+                    "bottom_right": { "lat": 52.0907, "lon": 13.405 }
+                }
+            })
+        );
+
+        let centroid = &res["centroid"];
+        assert_eq!(centroid["count"], 5);
+        let lat = centroid["location"]["lat"].as_f64().unwrap();
+        let lon = centroid["location"]["lon"].as_f64().unwrap();
+        assert!((lat - 52.33966).abs() < 1e-6, "{}", lat);
+        assert!((lon - 6.6491).abs() < 1e-6, "{}", lon);
+
+        let fleets = &res["fleets"]["buckets"];
+        assert_eq!(fleets[0]["key"], "trucks");
+        assert_eq!(fleets[0]["centroid"]["count"], 3);
+        assert_eq!(fleets[1]["key"], "vans");
+        assert_eq!(fleets[1]["centroid"]["count"], 2);
+        let lat = fleets[1]["centroid"]["location"]["lat"].as_f64().unwrap();
+        assert!((lat - 52.445).abs() < 1e-6, "{}", lat);
+        Ok(())
+    }
+
+    #[test]
+    fn geo_bounds_empty_test() -> crate::Result<()> {
+        let index = get_test_index_with_geo_points(false)?;
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "bounds": { "geo_bounds": { "lat_field": "lat", "lon_field": "lon" } },
+            "centroid": { "geo_centroid": { "lat_field": "lat", "lon_field": "lon" } }
+        }))
+        .unwrap();
+
+        let res = exec_request_with_query(agg_req, &index, Some(("fleet", "bikes")))?;
+        assert_eq!(res["bounds"], json!({}));
+        assert_eq!(res["centroid"], json!({ "count": 0 }));
+        Ok(())
+    }
+}
@@ -0,0 +1,105 @@
+use serde::{Deserialize, Serialize};
+
+use crate::aggregation::geo::GeoPointAccessor;
+use crate::aggregation::GeoPoint;
+use crate::DocId;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+/// A metric aggregation that computes the centroid of the geo points of the aggregated
+/// documents, as the mean of their latitudes and longitudes.
+/// The geo point of a document is read from the two `f64` fast fields `lat_field` and
+/// `lon_field`.
+/// See [`GeoCentroidMetricResult`] for return value.
+///
+/// # JSON Format
+/// ```json
+/// {
+///     "geo_centroid": {
+///         "lat_field": "latitude",
+///         "lon_field": "longitude"
+///     }
+/// }
+/// ```
+pub struct GeoCentroidAggregation {
+    /// The f64 fast field containing the latitude of the documents.
+    pub lat_field: String,
+    /// The f64 fast field containing the longitude of the documents.
+    pub lon_field: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct SegmentGeoCentroidCollector {
+    pub centroid: IntermediateGeoCentroid,
+}
+
+impl SegmentGeoCentroidCollector {
+    pub fn from_req() -> Self {
+        Self {
+            centroid: Default::default(),
+        }
+    }
+
+    pub(crate) fn collect_block(&mut self, docs: &[DocId], geo_point: &GeoPointAccessor) {
+        for &doc in docs {
+            self.centroid.collect(geo_point.get(doc));
+        }
+    }
+}
+
+/// Contains the mergeable sums of the coordinates of geo points.
+#[derive(Default, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct IntermediateGeoCentroid {
+    count: u64,
+    lat_sum: f64,
+    lon_sum: f64,
+}
+
+impl IntermediateGeoCentroid {
+    /// Merge the sums into this instance.
+    pub fn merge_fruits(&mut self, other: IntermediateGeoCentroid) {
+        self.count += other.count;
+        self.lat_sum += other.lat_sum;
+        self.lon_sum += other.lon_sum;
+    }
+
+    /// Compute the final result.
+    pub fn finalize(&self) -> GeoCentroidMetricResult {
+        let location = if self.count == 0 {
+            None
+        } else {
+            Some(GeoPoint {
+                lat: self.lat_sum / self.count as f64,
+                lon: self.lon_sum / self.count as f64,
+            })
+        };
+        GeoCentroidMetricResult {
+            location,
+            count: self.count,
+        }
+    }
+
+    #[inline]
+    fn collect(&mut self, point: GeoPoint) {
+        self.count += 1;
+        self.lat_sum += point.lat;
+        self.lon_sum += point.lon;
+    }
+}
+
+/// The result of the geo centroid aggregation.
+///
+/// # JSON Format
+/// ```json
+/// {
+///     "location": { "lat": 52.3397, "lon": 6.6491 },
+///     "count": 5
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GeoCentroidMetricResult {
+    /// The centroid, `None` if no document was aggregated.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub location: Option<GeoPoint>,
+    /// The number of aggregated documents.
+    pub count: u64,
+}
@@ -3,10 +3,20 @@
 //! The aggregations in this family compute metrics, see [super::agg_req::MetricAggregation] for
 //! details.
 mod average;
+mod cardinality;
+mod geo_bounds;
+mod geo_centroid;
+mod percentiles;
 mod stats;
+mod top_hits;
 pub use average::*;
+pub use cardinality::*;
+pub use geo_bounds::*;
+pub use geo_centroid::*;
+pub use percentiles::*;
 use serde::{Deserialize, Serialize};
 pub use stats::*;
+pub use top_hits::*;
 
 /// Single-metric aggregations use this common result structure.
 ///
@@ -28,3 +38,12 @@ impl From<Option<f64>> for SingleMetricResult {
         Self { value }
     }
 }
+
+/// The result of the value count aggregation.
+///
+/// Like in elasticsearch, the count is serialized as an integer.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ValueCountMetricResult {
+    /// The number of values.
+    pub value: u64,
+}
@@ -0,0 +1,818 @@
+use std::fmt::Debug;
+
+use fastfield_codecs::Column;
+use serde::{Deserialize, Serialize};
+
+use crate::aggregation::f64_from_fastfield_u64;
+use crate::fastfield::MultiValuedFastFieldReader;
+use crate::schema::Type;
+use crate::{DocId, TantivyError};
+
+const DEFAULT_PERCENTS: [f64; 7] = [1.0, 5.0, 25.0, 50.0, 75.0, 95.0, 99.0];
+
+/// A multi-value metric aggregation that estimates percentiles of numeric values that are
+/// extracted from the aggregated documents.
+/// Supported field types are `u64`, `i64`, and `f64`.
+/// See [`PercentilesMetricResult`] for the returned values.
+///
+/// The percentiles are estimated with a DDSketch, which guarantees a relative error of at most 1%
+/// for every returned percentile. The intermediate sketches can be merged losslessly.
+///
+/// # JSON Format
+/// ```json
+/// {
+///     "percentiles": {
+///         "field": "load_time",
+///         "percents": [95, 99, 99.9]
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PercentilesAggregation {
+    /// The field name to compute the percentiles on.
+    pub field: String,
+    /// The percentiles to compute, in the range `[0, 100]`.
+    ///
+    /// Defaults to `[1, 5, 25, 50, 75, 95, 99]`.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub percents: Option<Vec<f64>>,
+    /// Whether to return the values as a map keyed by percentile. Defaults to true.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub keyed: Option<bool>,
+}
+
+impl PercentilesAggregation {
+    /// Create new PercentilesAggregation from a field.
+    pub fn from_field_name(field_name: String) -> Self {
+        PercentilesAggregation {
+            field: field_name,
+            percents: None,
+            keyed: None,
+        }
+    }
+    /// Return the field name.
+    pub fn field_name(&self) -> &str {
+        &self.field
+    }
+
+    pub(crate) fn validate(&self) -> crate::Result<()> {
+        for &percent in self.percents() {
+            if !(0.0..=100.0).contains(&percent) {
+                return Err(TantivyError::InvalidArgument(format!(
+                    "percents must be in the range [0, 100], but got {}",
+                    percent
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn percents(&self) -> &[f64] {
+        self.percents.as_deref().unwrap_or(&DEFAULT_PERCENTS)
+    }
+}
+
+/// A multi-value metric aggregation that estimates for each of the given values the percentage of
+/// values extracted from the aggregated documents that are lower or equal to it.
+/// Supported field types are `u64`, `i64`, and `f64`.
+/// See [`PercentilesMetricResult`] for the returned values.
+///
+/// Like [`PercentilesAggregation`], this is estimated with a DDSketch.
+///
+/// # JSON Format
+/// ```json
+/// {
+///     "percentile_ranks": {
+///         "field": "load_time",
+///         "values": [500, 600]
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PercentileRanksAggregation {
+    /// The field name to compute the percentile ranks on.
+    pub field: String,
+    /// The values to compute the percentile rank for.
+    pub values: Vec<f64>,
+    /// Whether to return the values as a map keyed by value. Defaults to true.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub keyed: Option<bool>,
+}
+
+impl PercentileRanksAggregation {
+    /// Create new PercentileRanksAggregation from a field and the values to compute the rank for.
+    pub fn from_field_name_and_values(field_name: String, values: Vec<f64>) -> Self {
+        PercentileRanksAggregation {
+            field: field_name,
+            values,
+            keyed: None,
+        }
+    }
+    /// Return the field name.
+    pub fn field_name(&self) -> &str {
+        &self.field
+    }
+
+    pub(crate) fn validate(&self) -> crate::Result<()> {
+        if self.values.is_empty() {
+            return Err(TantivyError::InvalidArgument(
+                "percentile_ranks requires at least one value".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// The result of a percentiles or percentile ranks aggregation.
+///
+/// # JSON Format
+/// ```json
+/// {
+///     "values": {
+///         "95.0": 60.2,
+///         "99.0": 150.3
+///     }
+/// }
+/// ```
+/// or, with `keyed` set to false:
+/// ```json
+/// {
+///     "values": [
+///         { "key": 95.0, "value": 60.2 },
+///         { "key": 99.0, "value": 150.3 }
+///     ]
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PercentilesMetricResult {
+    /// The values, which are `None` when there were no values to compute them on.
+    pub values: PercentileValues,
+}
+
+impl PercentilesMetricResult {
+    pub(crate) fn get_value(&self, agg_property: &str) -> crate::Result<Option<f64>> {
+        let key: f64 = agg_property.parse().map_err(|_| {
+            TantivyError::InvalidArgument(format!(
+                "invalid property {} on percentiles metric aggregation, expected a number",
+                agg_property
+            ))
+        })?;
+        let value = match &self.values {
+            PercentileValues::Keyed(values) => {
+                let key = format_key(key);
+                values
+                    .iter()
+                    .find(|(entry_key, _)| *entry_key == key)
+                    .map(|(_, value)| *value)
+            }
+            PercentileValues::Vec(values) => values
+                .iter()
+                .find(|entry| entry.key == key)
+                .map(|entry| entry.value),
+        };
+        value.ok_or_else(|| {
+            TantivyError::InvalidArgument(format!(
+                "unknown property {} on percentiles metric aggregation",
+                agg_property
+            ))
+        })
+    }
+}
+
+/// The values of a percentiles result, either keyed or as a list of entries.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum PercentileValues {
+    /// Vector format values
+    Vec(Vec<PercentileValuesVecEntry>),
+    /// Keyed format values, in the order of the request. They are serialized as a map.
+    #[serde(with = "keyed_values")]
+    Keyed(Vec<(String, Option<f64>)>),
+}
+
+/// (De)serializes keyed percentile values as a map, preserving their order.
+mod keyed_values {
+    use std::fmt;
+
+    use serde::de::{MapAccess, Visitor};
+    use serde::ser::SerializeMap;
+    use serde::{Deserializer, Serializer};
+
+    pub fn serialize<S>(values: &[(String, Option<f64>)], serializer: S) -> Result<S::Ok, S::Error>
+    where S: Serializer {
+        let mut map = serializer.serialize_map(Some(values.len()))?;
+        for (key, value) in values {
+            map.serialize_entry(key, value)?;
+        }
+        map.end()
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<(String, Option<f64>)>, D::Error>
+    where D: Deserializer<'de> {
+        struct KeyedValuesVisitor;
+
+        impl<'de> Visitor<'de> for KeyedValuesVisitor {
+            type Value = Vec<(String, Option<f64>)>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+                formatter.write_str("a map of percentile values")
+            }
+
+            fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+            where A: MapAccess<'de> {
+                let mut values = Vec::with_capacity(map.size_hint().unwrap_or(0));
+                while let Some(entry) = map.next_entry()? {
+                    values.push(entry);
+                }
+                Ok(values)
+            }
+        }
+
+        deserializer.deserialize_map(KeyedValuesVisitor)
+    }
+}
+
+/// One value of a percentiles result in the vector format.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PercentileValuesVecEntry {
+    /// The percentile or value requested.
+    pub key: f64,
+    /// The computed value.
+    pub value: Option<f64>,
+}
+
+fn format_key(key: f64) -> String {
+    // Debug formatting keeps the fractional part, e.g. "99.0" like elasticsearch.
+    format!("{:?}", key)
+}
+
+fn to_percentile_values(
+    keys_and_values: impl Iterator<Item = (f64, Option<f64>)>,
+    keyed: bool,
+) -> PercentileValues {
+    if keyed {
+        PercentileValues::Keyed(
+            keys_and_values
+                .map(|(key, value)| (format_key(key), value))
+                .collect(),
+        )
+    } else {
+        PercentileValues::Vec(
+            keys_and_values
+                .map(|(key, value)| PercentileValuesVecEntry { key, value })
+                .collect(),
+        )
+    }
+}
+
+/// Contains the mergeable quantile sketch of the percentiles and percentile ranks aggregations.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct IntermediatePercentiles {
+    sketch: DDSketch,
+}
+
+impl IntermediatePercentiles {
+    /// Merge the sketch of another percentiles aggregation into this instance.
+    pub fn merge_fruits(&mut self, other: IntermediatePercentiles) {
+        self.sketch.merge(other.sketch);
+    }
+
+    /// Compute the final percentiles.
+    pub fn finalize(&self, req: &PercentilesAggregation) -> PercentilesMetricResult {
+        let values = req
+            .percents()
+            .iter()
+            .map(|&percent| (percent, self.sketch.quantile(percent / 100.0)));
+        PercentilesMetricResult {
+            values: to_percentile_values(values, req.keyed.unwrap_or(true)),
+        }
+    }
+
+    /// Compute the final percentile ranks.
+    pub fn finalize_ranks(&self, req: &PercentileRanksAggregation) -> PercentilesMetricResult {
+        let values = req
+            .values
+            .iter()
+            .map(|&value| (value, self.sketch.rank(value).map(|rank| rank * 100.0)));
+        PercentilesMetricResult {
+            values: to_percentile_values(values, req.keyed.unwrap_or(true)),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct SegmentPercentilesCollector {
+    pub(crate) percentiles: IntermediatePercentiles,
+    field_type: Type,
+}
+
+impl SegmentPercentilesCollector {
+    pub fn from_req(field_type: Type) -> Self {
+        Self {
+            percentiles: IntermediatePercentiles::default(),
+            field_type,
+        }
+    }
+
+    /// Returns the estimated memory in bytes of the collector once both stores of the sketch
+    /// are full.
+    pub(crate) fn memory_estimate(&self) -> u64 {
+        (std::mem::size_of::<Self>() + 2 * MAX_NUM_BINS * std::mem::size_of::<u64>()) as u64
+    }
+
+    pub(crate) fn collect_block(&mut self, doc: &[DocId], field: &dyn Column<u64>) {
+        for &doc in doc {
+            let val = f64_from_fastfield_u64(field.get_val(doc), &self.field_type);
+            self.percentiles.sketch.insert(val);
+        }
+    }
+
+    pub(crate) fn collect_block_multi(
+        &mut self,
+        doc: &[DocId],
+        field: &MultiValuedFastFieldReader<u64>,
+    ) {
+        let mut vals = Vec::new();
+        for &doc in doc {
+            field.get_vals(doc, &mut vals);
+            for &val in &vals {
+                let val = f64_from_fastfield_u64(val, &self.field_type);
+                self.percentiles.sketch.insert(val);
+            }
+        }
+    }
+}
+
+/// Relative accuracy of the sketch.
+const RELATIVE_ACCURACY: f64 = 0.01;
+/// Maximum number of bins per store. With 1% accuracy this covers a value range of more than
+/// 1e17 at full accuracy, values outside are collapsed into the lowest bin.
+const MAX_NUM_BINS: usize = 2048;
+
+/// DDSketch (Masson et al. "DDSketch: A Fast and Fully-Mergeable Quantile Sketch with
+/// Relative-Error Guarantees", 2019).
+///
+/// Values are mapped to logarithmically sized bins, which guarantees a relative error of at most
+/// `RELATIVE_ACCURACY` for quantiles. Positive and negative values are kept in separate stores.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct DDSketch {
+    positive: DenseStore,
+    negative: DenseStore,
+    zero_count: u64,
+    count: u64,
+    min: f64,
+    max: f64,
+}
+
+impl Default for DDSketch {
+    fn default() -> Self {
+        DDSketch {
+            positive: DenseStore::default(),
+            negative: DenseStore::default(),
+            zero_count: 0,
+            count: 0,
+            min: f64::MAX,
+            max: f64::MIN,
+        }
+    }
+}
+
+fn gamma() -> f64 {
+    (1.0 + RELATIVE_ACCURACY) / (1.0 - RELATIVE_ACCURACY)
+}
+
+/// Values with an absolute value below this threshold are counted as zero.
+fn min_indexable_value() -> f64 {
+    f64::MIN_POSITIVE * gamma()
+}
+
+#[inline]
+fn key_for_value(val: f64) -> i32 {
+    (val.ln() / gamma().ln()).ceil() as i32
+}
+
+fn value_for_key(key: i32) -> f64 {
+    // The bin with key `k` contains the values in `(gamma^(k-1), gamma^k]`, this representative
+    // has a relative error of at most `RELATIVE_ACCURACY` to all of them.
+    let gamma = gamma();
+    gamma.powi(key) * 2.0 / (1.0 + gamma)
+}
+
+impl DDSketch {
+    #[inline]
+    fn insert(&mut self, val: f64) {
+        if val.is_nan() {
+            return;
+        }
+        if val > min_indexable_value() {
+            self.positive.add(key_for_value(val), 1);
+        } else if val < -min_indexable_value() {
+            self.negative.add(key_for_value(-val), 1);
+        } else {
+            self.zero_count += 1;
+        }
+        self.count += 1;
+        self.min = self.min.min(val);
+        self.max = self.max.max(val);
+    }
+
+    fn merge(&mut self, other: DDSketch) {
+        self.positive.merge(&other.positive);
+        self.negative.merge(&other.negative);
+        self.zero_count += other.zero_count;
+        self.count += other.count;
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+    }
+
+    /// Returns the estimated value at quantile `q` in `[0, 1]`.
+    fn quantile(&self, q: f64) -> Option<f64> {
+        if self.count == 0 {
+            return None;
+        }
+        let rank = (q * (self.count - 1) as f64).round() as u64;
+        // The extremes are tracked exactly.
+        if rank == 0 {
+            return Some(self.min);
+        }
+        if rank == self.count - 1 {
+            return Some(self.max);
+        }
+        let value = if rank < self.negative.count {
+            let reversed_rank = self.negative.count - 1 - rank;
+            -value_for_key(self.negative.key_at_rank(reversed_rank))
+        } else if rank < self.negative.count + self.zero_count {
+            0.0
+        } else {
+            let rank = rank - self.negative.count - self.zero_count;
+            value_for_key(self.positive.key_at_rank(rank))
+        };
+        Some(value.clamp(self.min, self.max))
+    }
+
+    /// Returns the estimated fraction of values lower or equal to `val`.
+    fn rank(&self, val: f64) -> Option<f64> {
+        if self.count == 0 {
+            return None;
+        }
+        if val < self.min {
+            return Some(0.0);
+        }
+        if val >= self.max {
+            return Some(1.0);
+        }
+        let num_lower_or_equal = if val < -min_indexable_value() {
+            self.negative.count_from_key(key_for_value(-val))
+        } else if val <= min_indexable_value() {
+            self.negative.count + self.zero_count
+        } else {
+            self.negative.count + self.zero_count + self.positive.count_to_key(key_for_value(val))
+        };
+        Some(num_lower_or_equal as f64 / self.count as f64)
+    }
+}
+
+/// Counts per key, stored contiguously starting at `offset`.
+///
+/// If the keys span more than `MAX_NUM_BINS`, the lowest keys are collapsed into one bin.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+struct DenseStore {
+    bins: Vec<u64>,
+    offset: i32,
+    count: u64,
+}
+
+impl DenseStore {
+    fn max_key(&self) -> i32 {
+        self.offset + self.bins.len() as i32 - 1
+    }
+
+    #[inline]
+    fn add(&mut self, key: i32, count: u64) {
+        let pos = self.get_or_create_pos(key);
+        self.bins[pos] += count;
+        self.count += count;
+    }
+
+    fn get_or_create_pos(&mut self, key: i32) -> usize {
+        if self.bins.is_empty() {
+            self.bins.push(0);
+            self.offset = key;
+            return 0;
+        }
+        if key > self.max_key() {
+            let num_new_bins = (key - self.max_key()) as usize;
+            self.bins.extend(std::iter::repeat(0).take(num_new_bins));
+            if self.bins.len() > MAX_NUM_BINS {
+                self.collapse_lowest(self.bins.len() - MAX_NUM_BINS);
+            }
+        } else if key < self.offset {
+            let num_new_bins = (self.offset - key) as usize;
+            let num_new_bins = num_new_bins.min(MAX_NUM_BINS - self.bins.len());
+            self.bins
+                .splice(0..0, std::iter::repeat(0).take(num_new_bins));
+            self.offset -= num_new_bins as i32;
+        }
+        (key.max(self.offset) - self.offset) as usize
+    }
+
+    fn collapse_lowest(&mut self, num_bins: usize) {
+        let collapsed_count: u64 = self.bins.drain(..num_bins).sum();
+        self.bins[0] += collapsed_count;
+        self.offset += num_bins as i32;
+    }
+
+    fn merge(&mut self, other: &DenseStore) {
+        for (pos, &count) in other.bins.iter().enumerate() {
+            if count != 0 {
+                self.add(other.offset + pos as i32, count);
+            }
+        }
+    }
+
+    /// Returns the key of the bin containing the value with the given rank (zero based).
+    fn key_at_rank(&self, rank: u64) -> i32 {
+        let mut num_seen = 0;
+        for (pos, &count) in self.bins.iter().enumerate() {
+            num_seen += count;
+            if num_seen > rank {
+                return self.offset + pos as i32;
+            }
+        }
+        self.max_key()
+    }
+
+    /// Returns the number of values in bins with keys lower or equal to `key`.
+    fn count_to_key(&self, key: i32) -> u64 {
+        let num_bins = (key - self.offset + 1).clamp(0, self.bins.len() as i32) as usize;
+        self.bins[..num_bins].iter().sum()
+    }
+
+    /// Returns the number of values in bins with keys greater or equal to `key`.
+    fn count_from_key(&self, key: i32) -> u64 {
+        self.count - self.count_to_key(key - 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use serde_json::Value;
+
+    use super::*;
+    use crate::aggregation::agg_req::{Aggregation, Aggregations, MetricAggregation};
+    use crate::aggregation::agg_result::{AggregationResult, MetricResult};
+    use crate::aggregation::intermediate_agg_result::IntermediateAggregationResults;
+    use crate::aggregation::tests::{
+        exec_request, exec_request_with_query, get_test_index_2_segments,
+        get_test_index_from_values, get_test_index_with_num_docs,
+    };
+    use crate::aggregation::{AggregationCollector, DistributedAggregationCollector};
+    use crate::query::AllQuery;
+
+    fn assert_relative_error(estimate: f64, expected: f64) {
+        let error = (estimate - expected).abs() / expected.abs();
+        assert!(
+            error <= RELATIVE_ACCURACY + 1e-9,
+            "estimate {} for {} exceeds the relative accuracy",
+            estimate,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_ddsketch_quantiles() {
+        let mut sketch = DDSketch::default();
+        let values: Vec<f64> = (-500..=1000).map(|val| val as f64 * 0.37).collect();
+        for &val in &values {
+            sketch.insert(val);
+        }
+        for &q in &[0.0, 0.01, 0.1, 0.25, 0.5, 0.75, 0.9, 0.99, 1.0] {
+            let rank = (q * (values.len() - 1) as f64).round() as usize;
+            let expected = values[rank];
+            let estimate = sketch.quantile(q).unwrap();
+            if expected == 0.0 {
+                assert_eq!(estimate, 0.0);
+            } else {
+                assert_relative_error(estimate, expected);
+            }
+        }
+        assert_eq!(sketch.quantile(0.0), Some(-500.0 * 0.37));
+        assert_eq!(sketch.quantile(1.0), Some(1000.0 * 0.37));
+    }
+
+    #[test]
+    fn test_ddsketch_rank() {
+        let mut sketch = DDSketch::default();
+        for val in 1..=1000 {
+            sketch.insert(val as f64);
+        }
+        assert_eq!(sketch.rank(0.5), Some(0.0));
+        assert_eq!(sketch.rank(1000.0), Some(1.0));
+        let rank = sketch.rank(500.0).unwrap();
+        assert!((rank - 0.5).abs() < 0.01, "rank {}", rank);
+        assert_eq!(DDSketch::default().rank(1.0), None);
+    }
+
+    #[test]
+    fn test_ddsketch_merge() {
+        let mut left = DDSketch::default();
+        let mut right = DDSketch::default();
+        let mut all = DDSketch::default();
+        for val in 0..1000 {
+            let val = val as f64 - 100.0;
+            if val as i64 % 3 == 0 {
+                left.insert(val);
+            } else {
+                right.insert(val);
+            }
+            all.insert(val);
+        }
+        left.merge(right);
+        for &q in &[0.0, 0.1, 0.5, 0.9, 1.0] {
+            assert_eq!(left.quantile(q), all.quantile(q));
+        }
+        assert_eq!(left.count, 1000);
+    }
+
+    #[test]
+    fn test_ddsketch_collapse() {
+        let mut sketch = DDSketch::default();
+        sketch.insert(1e-200);
+        sketch.insert(1e199);
+        sketch.insert(1e200);
+        assert!(sketch.positive.bins.len() <= MAX_NUM_BINS);
+        assert_eq!(sketch.quantile(0.0), Some(1e-200));
+        assert_eq!(sketch.quantile(1.0), Some(1e200));
+        // the lowest values are collapsed, high quantiles stay accurate
+        assert_relative_error(sketch.quantile(0.5).unwrap(), 1e199);
+    }
+
+    #[test]
+    fn test_aggregation_percentiles_empty_index() -> crate::Result<()> {
+        let index = get_test_index_from_values(false, &[])?;
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "percentiles": { "percentiles": { "field": "score", "percents": [50] } },
+            "ranks": { "percentile_ranks": { "field": "score", "values": [5] } }
+        }))
+        .unwrap();
+
+        let res = exec_request(agg_req, &index)?;
+        assert_eq!(
+            res["percentiles"],
+            json!({ "values": { "50.0": Value::Null } })
+        );
+        assert_eq!(res["ranks"], json!({ "values": { "5.0": Value::Null } }));
+        Ok(())
+    }
+
+    #[test]
+    fn test_aggregation_percentiles() -> crate::Result<()> {
+        let index = get_test_index_2_segments(false)?;
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "percentiles": { "percentiles": { "field": "score_f64" } },
+            "percentiles_vec": {
+                "percentiles": { "field": "score_i64", "percents": [0, 100], "keyed": false }
+            },
+            "ranks": { "percentile_ranks": { "field": "score", "values": [0, 7, 100] } }
+        }))
+        .unwrap();
+
+        // values are 1, 3, 5, 7, 11, 14, 44.5
+        let res = exec_request_with_query(agg_req, &index, Some(("text", "cool")))?;
+        let percentiles = &res["percentiles"]["values"];
+        assert_eq!(
+            percentiles.as_object().unwrap().len(),
+            DEFAULT_PERCENTS.len()
+        );
+        assert_eq!(percentiles["1.0"], 1.0);
+        assert_relative_error(percentiles["50.0"].as_f64().unwrap(), 7.0);
+        assert_relative_error(percentiles["75.0"].as_f64().unwrap(), 14.0);
+        assert_eq!(percentiles["99.0"], 44.5);
+
+        assert_eq!(
+            res["percentiles_vec"],
+            json!({
+                "values": [
+                    { "key": 0.0, "value": 1.0 },
+                    { "key": 100.0, "value": 44.0 }
+                ]
+            })
+        );
+
+        let ranks = &res["ranks"]["values"];
+        assert_eq!(ranks["0.0"], 0.0);
+        assert_relative_error(ranks["7.0"].as_f64().unwrap(), 4.0 / 7.0 * 100.0);
+        assert_eq!(ranks["100.0"], 100.0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_aggregation_percentiles_keyed_in_request_order() -> crate::Result<()> {
+        let index = get_test_index_with_num_docs(false, 100)?;
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "percentiles": { "percentiles": { "field": "score", "percents": [99, 1, 50] } }
+        }))
+        .unwrap();
+
+        let collector = AggregationCollector::from_aggs(agg_req, None);
+        let searcher = index.reader()?.searcher();
+        let res = searcher.search(&AllQuery, &collector)?;
+        let res_json = serde_json::to_string(&res)?;
+        let key_positions: Vec<usize> = ["\"99.0\"", "\"1.0\"", "\"50.0\""]
+            .iter()
+            .map(|key| res_json.find(key).unwrap())
+            .collect();
+        assert!(key_positions.windows(2).all(|pair| pair[0] < pair[1]));
+
+        let values = match &res.0["percentiles"] {
+            AggregationResult::MetricResult(MetricResult::Percentiles(percentiles)) => {
+                percentiles.values.clone()
+            }
+            _ => panic!("expected a percentiles result"),
+        };
+        let deserialized: PercentileValues =
+            serde_json::from_str(&serde_json::to_string(&values)?)?;
+        let keys = match deserialized {
+            PercentileValues::Keyed(values) => values.into_iter().map(|(key, _)| key).collect(),
+            PercentileValues::Vec(_) => Vec::new(),
+        };
+        assert_eq!(keys, vec!["99.0", "1.0", "50.0"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_aggregation_percentiles_sub_aggregation() -> crate::Result<()> {
+        let index = get_test_index_with_num_docs(false, 100)?;
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "histogram": {
+                "histogram": { "field": "score", "interval": 50.0 },
+                "aggs": {
+                    "percentiles": { "percentiles": { "field": "score", "percents": [50, 99] } }
+                }
+            }
+        }))
+        .unwrap();
+
+        let res = exec_request(agg_req, &index)?;
+        let buckets = &res["histogram"]["buckets"];
+        assert_relative_error(
+            buckets[0]["percentiles"]["values"]["50.0"]
+                .as_f64()
+                .unwrap(),
+            25.0,
+        );
+        assert_relative_error(
+            buckets[1]["percentiles"]["values"]["99.0"]
+                .as_f64()
+                .unwrap(),
+            99.0,
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_aggregation_percentiles_distributed() -> crate::Result<()> {
+        let index = get_test_index_with_num_docs(false, 100)?;
+        let agg_req: Aggregations = vec![(
+            "percentiles".to_string(),
+            Aggregation::Metric(MetricAggregation::Percentiles(
+                PercentilesAggregation::from_field_name("score".to_string()),
+            )),
+        )]
+        .into_iter()
+        .collect();
+
+        let collector = DistributedAggregationCollector::from_aggs(agg_req.clone(), None);
+        let searcher = index.reader()?.searcher();
+        let intermediate_res: IntermediateAggregationResults =
+            searcher.search(&AllQuery, &collector)?;
+
+        let mut merged_res: IntermediateAggregationResults =
+            serde_json::from_str(&serde_json::to_string(&intermediate_res)?)?;
+        merged_res.merge_fruits(intermediate_res);
+
+        let res: Value = serde_json::to_value(merged_res.into_final_bucket_result(agg_req)?)?;
+        let percentiles = &res["percentiles"]["values"];
+        assert_relative_error(percentiles["1.0"].as_f64().unwrap(), 1.0);
+        assert_relative_error(percentiles["50.0"].as_f64().unwrap(), 50.0);
+        assert_relative_error(percentiles["99.0"].as_f64().unwrap(), 98.0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_aggregation_percentiles_invalid_request() -> crate::Result<()> {
+        let index = get_test_index_with_num_docs(false, 10)?;
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "percentiles": { "percentiles": { "field": "score", "percents": [101] } }
+        }))
+        .unwrap();
+
+        let err = exec_request(agg_req, &index).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "An invalid argument was passed: 'percents must be in the range [0, 100], but got 101'"
+        );
+        Ok(())
+    }
+}
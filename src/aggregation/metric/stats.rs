@@ -2,6 +2,7 @@ use fastfield_codecs::Column;
 use serde::{Deserialize, Serialize};
 
 use crate::aggregation::f64_from_fastfield_u64;
+use crate::fastfield::MultiValuedFastFieldReader;
 use crate::schema::Type;
 use crate::{DocId, TantivyError};
 
@@ -150,16 +151,121 @@ impl IntermediateStats {
     }
 }
 
+/// Defines a single-value metric aggregation on a field, which is derived from the
+/// [`IntermediateStats`] of the field values.
+macro_rules! single_stat_aggregation {
+    ($(#[$attr:meta])* $aggregation:ident) => {
+        $(#[$attr])*
+        #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+        pub struct $aggregation {
+            /// The field name to compute the metric on.
+            pub field: String,
+        }
+
+        impl $aggregation {
+            #[doc = concat!("Create new ", stringify!($aggregation), " from a field.")]
+            pub fn from_field_name(field_name: String) -> Self {
+                $aggregation { field: field_name }
+            }
+            /// Return the field name.
+            pub fn field_name(&self) -> &str {
+                &self.field
+            }
+        }
+    };
+}
+
+single_stat_aggregation!(
+    /// A single-value metric aggregation that computes the minimum of numeric values that are
+    /// extracted from the aggregated documents.
+    /// Supported field types are `u64`, `i64`, and `f64`.
+    /// See [`super::SingleMetricResult`] for return value.
+    ///
+    /// # JSON Format
+    /// ```json
+    /// {
+    ///     "min": {
+    ///         "field": "score",
+    ///     }
+    /// }
+    /// ```
+    MinAggregation
+);
+
+single_stat_aggregation!(
+    /// A single-value metric aggregation that computes the maximum of numeric values that are
+    /// extracted from the aggregated documents.
+    /// Supported field types are `u64`, `i64`, and `f64`.
+    /// See [`super::SingleMetricResult`] for return value.
+    ///
+    /// # JSON Format
+    /// ```json
+    /// {
+    ///     "max": {
+    ///         "field": "score",
+    ///     }
+    /// }
+    /// ```
+    MaxAggregation
+);
+
+single_stat_aggregation!(
+    /// A single-value metric aggregation that sums up numeric values that are
+    /// extracted from the aggregated documents. Like in elasticsearch, the sum of no values is 0.
+    /// Supported field types are `u64`, `i64`, and `f64`.
+    /// See [`super::SingleMetricResult`] for return value.
+    ///
+    /// # JSON Format
+    /// ```json
+    /// {
+    ///     "sum": {
+    ///         "field": "score",
+    ///     }
+    /// }
+    /// ```
+    SumAggregation
+);
+
+single_stat_aggregation!(
+    /// A single-value metric aggregation that counts the number of values that are
+    /// extracted from the aggregated documents.
+    /// Supported field types are `u64`, `i64`, and `f64`.
+    /// See [`super::ValueCountMetricResult`] for return value.
+    ///
+    /// # JSON Format
+    /// ```json
+    /// {
+    ///     "value_count": {
+    ///         "field": "score",
+    ///     }
+    /// }
+    /// ```
+    ValueCountAggregation
+);
+
+/// The metric aggregation a [`SegmentStatsCollector`] collects for. All of them are derived from
+/// the same stats.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum SegmentStatsType {
+    Stats,
+    Min,
+    Max,
+    Sum,
+    Count,
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub(crate) struct SegmentStatsCollector {
     pub(crate) stats: IntermediateStats,
+    pub(crate) collecting_for: SegmentStatsType,
     field_type: Type,
 }
 
 impl SegmentStatsCollector {
-    pub fn from_req(field_type: Type) -> Self {
+    pub fn from_req(field_type: Type, collecting_for: SegmentStatsType) -> Self {
         Self {
             field_type,
+            collecting_for,
             stats: IntermediateStats::default(),
         }
     }
@@ -185,6 +291,21 @@ impl SegmentStatsCollector {
             self.stats.collect(val);
         }
     }
+
+    pub(crate) fn collect_block_multi(
+        &mut self,
+        doc: &[DocId],
+        field: &MultiValuedFastFieldReader<u64>,
+    ) {
+        let mut vals = Vec::new();
+        for &doc in doc {
+            field.get_vals(doc, &mut vals);
+            for &val in &vals {
+                let val = f64_from_fastfield_u64(val, &self.field_type);
+                self.stats.collect(val);
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -200,7 +321,10 @@ mod tests {
     };
     use crate::aggregation::agg_result::AggregationResults;
     use crate::aggregation::metric::StatsAggregation;
-    use crate::aggregation::tests::{get_test_index_2_segments, get_test_index_from_values};
+    use crate::aggregation::tests::{
+        exec_request, exec_request_with_query, get_test_index_2_segments,
+        get_test_index_from_values,
+    };
     use crate::aggregation::AggregationCollector;
     use crate::query::{AllQuery, TermQuery};
     use crate::schema::IndexRecordOption;
@@ -368,4 +492,74 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_aggregation_single_value_metrics() -> crate::Result<()> {
+        let index = get_test_index_2_segments(false)?;
+
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "min": { "min": { "field": "score_f64" } },
+            "max": { "max": { "field": "score_f64" } },
+            "sum": { "sum": { "field": "score_i64" } },
+            "count": { "value_count": { "field": "score" } },
+            "range": {
+                "range": {
+                    "field": "score",
+                    "ranges": [{ "from": 7.0, "to": 19.0 }, { "from": 100.0 }]
+                },
+                "aggs": {
+                    "min": { "min": { "field": "score" } },
+                    "max": { "max": { "field": "score" } },
+                    "sum": { "sum": { "field": "score" } },
+                    "count": { "value_count": { "field": "score" } }
+                }
+            }
+        }))
+        .unwrap();
+
+        let res = exec_request_with_query(agg_req, &index, Some(("text", "cool")))?;
+        assert_eq!(res["min"], json!({ "value": 1.0 }));
+        assert_eq!(res["max"], json!({ "value": 44.5 }));
+        assert_eq!(res["sum"], json!({ "value": 85.0 }));
+        assert_eq!(res["count"], json!({ "value": 7 }));
+
+        let buckets = &res["range"]["buckets"];
+        assert_eq!(buckets[1]["min"], json!({ "value": 7.0 }));
+        assert_eq!(buckets[1]["max"], json!({ "value": 14.0 }));
+        assert_eq!(buckets[1]["sum"], json!({ "value": 32.0 }));
+        assert_eq!(buckets[1]["count"], json!({ "value": 3 }));
+        // ES returns null for min/max and 0 for sum on empty buckets
+        assert_eq!(buckets[3]["min"], json!({ "value": Value::Null }));
+        assert_eq!(buckets[3]["max"], json!({ "value": Value::Null }));
+        assert_eq!(buckets[3]["sum"], json!({ "value": 0.0 }));
+        assert_eq!(buckets[3]["count"], json!({ "value": 0 }));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_aggregation_single_value_metrics_order_terms() -> crate::Result<()> {
+        let index = get_test_index_2_segments(false)?;
+
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "terms": {
+                "terms": { "field": "text", "order": { "max_score": "desc" } },
+                "aggs": {
+                    "max_score": { "max": { "field": "score" } }
+                }
+            }
+        }))
+        .unwrap();
+
+        let res = exec_request(agg_req, &index)?;
+        let buckets = res["terms"]["buckets"].as_array().unwrap();
+        let max_scores: Vec<f64> = buckets
+            .iter()
+            .map(|bucket| bucket["max_score"]["value"].as_f64().unwrap())
+            .collect();
+        assert!(!max_scores.is_empty());
+        assert!(max_scores.windows(2).all(|w| w[0] >= w[1]));
+
+        Ok(())
+    }
 }
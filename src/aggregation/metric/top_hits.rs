@@ -0,0 +1,821 @@
+use std::cell::Cell;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use fastfield_codecs::Column;
+use serde::de::{self, Deserializer};
+use serde::ser::{SerializeMap, Serializer};
+use serde::{Deserialize, Serialize};
+
+use crate::aggregation::bucket::Order;
+use crate::aggregation::f64_from_fastfield_u64;
+use crate::collector::{TopCollector, TopSegmentCollector};
+use crate::fastfield::{type_and_cardinality, FastType};
+use crate::schema::{Cardinality, Document, Field, NamedFieldDocument, Schema, Type};
+use crate::store::{StoreReader, DOCSTORE_CACHE_CAPACITY};
+use crate::{DocAddress, DocId, Score, SegmentOrdinal, SegmentReader, TantivyError};
+
+/// The sort key to sort the hits by their score.
+pub const SCORE_SORT_KEY: &str = "_score";
+
+const DEFAULT_TOP_HITS_SIZE: usize = 3;
+
+/// A metric aggregation, which keeps the top hits of the documents it collects, e.g. the latest
+/// events per host, when used as a sub-aggregation of a terms aggregation.
+///
+/// Hits are sorted by their score (the default) or by single valued numeric or date fast fields.
+/// Optionally, the values of stored fields are returned with every hit.
+/// See [TopHitsMetricResult] for return value.
+///
+/// Sorting by score requires scoring to be enabled, which is done automatically by the
+/// aggregation collectors.
+///
+/// # JSON Format
+/// ```json
+/// {
+///     "top_hits": {
+///         "size": 3,
+///         "sort": [{ "timestamp": { "order": "desc" } }],
+///         "stored_fields": ["message"]
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TopHitsAggregation {
+    /// The number of hits to return. Defaults to 3.
+    #[serde(default = "default_top_hits_size")]
+    pub size: usize,
+    /// The number of hits to skip.
+    #[serde(default)]
+    pub from: usize,
+    /// The sort keys. Hits with equal values on the first key are sorted by the next one.
+    /// Defaults to sorting by score descending.
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub sort: Vec<TopHitsSort>,
+    /// The stored fields returned with every hit.
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub stored_fields: Vec<String>,
+}
+
+fn default_top_hits_size() -> usize {
+    DEFAULT_TOP_HITS_SIZE
+}
+
+impl Default for TopHitsAggregation {
+    fn default() -> Self {
+        Self {
+            size: DEFAULT_TOP_HITS_SIZE,
+            from: 0,
+            sort: Vec::new(),
+            stored_fields: Vec::new(),
+        }
+    }
+}
+
+impl TopHitsAggregation {
+    /// Returns the sort keys, which fall back to the score if none are set.
+    pub(crate) fn sort_keys(&self) -> Vec<TopHitsSort> {
+        if self.sort.is_empty() {
+            vec![TopHitsSort::score()]
+        } else {
+            self.sort.clone()
+        }
+    }
+
+    /// Returns true if the hits are sorted by score.
+    pub(crate) fn requires_scoring(&self) -> bool {
+        self.sort_keys()
+            .iter()
+            .any(|sort| sort.field == SCORE_SORT_KEY)
+    }
+
+    /// Returns the fast fields used for sorting.
+    pub(crate) fn fast_field_names(&self) -> impl Iterator<Item = &str> {
+        self.sort
+            .iter()
+            .map(|sort| sort.field.as_str())
+            .filter(|field| *field != SCORE_SORT_KEY)
+    }
+
+    pub(crate) fn validate(&self) -> crate::Result<()> {
+        if self.size == 0 {
+            return Err(TantivyError::InvalidArgument(
+                "top_hits size must be greater than 0".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// A sort key of the [TopHitsAggregation]. Either a fast field name, or `_score`.
+///
+/// # JSON Format
+/// The order defaults to descending for `_score` and to ascending for fields.
+/// ```json
+/// ["_score", "timestamp", { "response_time": { "order": "desc" } }]
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct TopHitsSort {
+    /// The fast field to sort by, or `_score`.
+    pub field: String,
+    /// The sort order.
+    pub order: Order,
+}
+
+impl TopHitsSort {
+    /// Sort by score descending.
+    pub fn score() -> Self {
+        TopHitsSort {
+            field: SCORE_SORT_KEY.to_string(),
+            order: Order::Desc,
+        }
+    }
+
+    /// Sort by a fast field.
+    pub fn field(field: &str, order: Order) -> Self {
+        TopHitsSort {
+            field: field.to_string(),
+            order,
+        }
+    }
+
+    fn default_order(field: &str) -> Order {
+        if field == SCORE_SORT_KEY {
+            Order::Desc
+        } else {
+            Order::Asc
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TopHitsSortOrder {
+    order: Option<Order>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TopHitsSortJson {
+    Field(String),
+    FieldWithOrder(HashMap<String, TopHitsSortOrder>),
+}
+
+impl Serialize for TopHitsSort {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where S: Serializer {
+        let mut map = serializer.serialize_map(Some(1))?;
+        map.serialize_entry(&self.field, &HashMap::from([("order", self.order)]))?;
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for TopHitsSort {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where D: Deserializer<'de> {
+        match TopHitsSortJson::deserialize(deserializer)? {
+            TopHitsSortJson::Field(field) => {
+                let order = TopHitsSort::default_order(&field);
+                Ok(TopHitsSort { field, order })
+            }
+            TopHitsSortJson::FieldWithOrder(map) => {
+                if map.len() != 1 {
+                    return Err(de::Error::custom(format!(
+                        "expected exactly one field per sort key, but got {}",
+                        map.len()
+                    )));
+                }
+                let (field, sort_order) = map.into_iter().next().expect("map has one entry");
+                let order = sort_order
+                    .order
+                    .unwrap_or_else(|| TopHitsSort::default_order(&field));
+                Ok(TopHitsSort { field, order })
+            }
+        }
+    }
+}
+
+/// The scores of the documents of a segment, indexed by `DocId`.
+///
+/// The scores are only known when a document is collected, but the aggregations below a bucket
+/// collect their documents in blocks, so the score needs to be kept until then.
+pub(crate) type DocScores = Rc<[Cell<Score>]>;
+
+pub(crate) fn new_doc_scores(max_doc: DocId) -> DocScores {
+    (0..max_doc).map(|_| Cell::new(0.0)).collect()
+}
+
+#[derive(Clone)]
+enum SortKeyAccessor {
+    Score(DocScores),
+    FastField {
+        column: Arc<dyn Column<u64>>,
+        field_type: Type,
+    },
+}
+
+#[derive(Clone)]
+struct SortKeyWithAccessor {
+    accessor: SortKeyAccessor,
+    order: Order,
+}
+
+impl SortKeyWithAccessor {
+    /// Returns a value, which is greater for documents that rank higher.
+    #[inline]
+    fn rank_value(&self, doc: DocId) -> u64 {
+        let val = match &self.accessor {
+            SortKeyAccessor::Score(doc_scores) => {
+                common::f64_to_u64(doc_scores[doc as usize].get() as f64)
+            }
+            SortKeyAccessor::FastField { column, .. } => column.get_val(doc),
+        };
+        match self.order {
+            Order::Desc => val,
+            Order::Asc => u64::MAX - val,
+        }
+    }
+
+    /// Converts a rank value back into the sort value, which is returned with the hit.
+    fn sort_value(&self, rank_value: u64) -> f64 {
+        let val = match self.order {
+            Order::Desc => rank_value,
+            Order::Asc => u64::MAX - rank_value,
+        };
+        match &self.accessor {
+            SortKeyAccessor::Score(_) => common::u64_to_f64(val),
+            SortKeyAccessor::FastField {
+                field_type: Type::Date,
+                ..
+            } => {
+                // Dates are returned as milliseconds like the keys of the date histogram.
+                common::u64_to_i64(val).div_euclid(1_000) as f64
+            }
+            SortKeyAccessor::FastField {
+                field_type: Type::Bool,
+                ..
+            } => val as f64,
+            SortKeyAccessor::FastField { field_type, .. } => {
+                f64_from_fastfield_u64(val, field_type)
+            }
+        }
+    }
+}
+
+/// Segment level accessors of the [TopHitsAggregation].
+#[derive(Clone)]
+pub(crate) struct TopHitsAccessor {
+    segment_ord: SegmentOrdinal,
+    sort_keys: Vec<SortKeyWithAccessor>,
+    /// The store reader, the schema and the fields, if stored fields are requested.
+    stored_fields: Option<(Rc<StoreReader>, Schema, Vec<Field>)>,
+}
+
+impl TopHitsAccessor {
+    pub(crate) fn try_new(
+        req: &TopHitsAggregation,
+        reader: &SegmentReader,
+        segment_ord: SegmentOrdinal,
+        doc_scores: Option<&DocScores>,
+    ) -> crate::Result<Self> {
+        let schema = reader.schema();
+        let sort_keys = req
+            .sort_keys()
+            .into_iter()
+            .map(|sort| {
+                let accessor = if sort.field == SCORE_SORT_KEY {
+                    let doc_scores = doc_scores.ok_or_else(|| {
+                        TantivyError::InternalError(
+                            "top_hits sorted by score, but scores are not collected".to_string(),
+                        )
+                    })?;
+                    SortKeyAccessor::Score(doc_scores.clone())
+                } else {
+                    get_sort_key_accessor(reader, &sort.field)?
+                };
+                Ok(SortKeyWithAccessor {
+                    accessor,
+                    order: sort.order,
+                })
+            })
+            .collect::<crate::Result<Vec<_>>>()?;
+
+        let stored_fields = if req.stored_fields.is_empty() {
+            None
+        } else {
+            let fields = req
+                .stored_fields
+                .iter()
+                .map(|field_name| {
+                    let field = schema
+                        .get_field(field_name)
+                        .ok_or_else(|| TantivyError::FieldNotFound(field_name.to_string()))?;
+                    if !schema.get_field_entry(field).is_stored() {
+                        return Err(TantivyError::InvalidArgument(format!(
+                            "top_hits can only return stored fields, but {} is not stored",
+                            field_name
+                        )));
+                    }
+                    Ok(field)
+                })
+                .collect::<crate::Result<Vec<_>>>()?;
+            let store_reader = reader.get_store_reader(DOCSTORE_CACHE_CAPACITY)?;
+            Some((Rc::new(store_reader), schema.clone(), fields))
+        };
+
+        Ok(TopHitsAccessor {
+            segment_ord,
+            sort_keys,
+            stored_fields,
+        })
+    }
+
+    fn get_stored_fields(&self, doc: DocId) -> crate::Result<Option<NamedFieldDocument>> {
+        let (store_reader, schema, fields) = match &self.stored_fields {
+            Some(stored_fields) => stored_fields,
+            None => return Ok(None),
+        };
+        let doc: Document = store_reader
+            .get(doc)?
+            .into_iter()
+            .filter(|field_value| fields.contains(&field_value.field()))
+            .collect::<Vec<_>>()
+            .into();
+        Ok(Some(schema.to_named_doc(&doc)))
+    }
+}
+
+fn get_sort_key_accessor(
+    reader: &SegmentReader,
+    field_name: &str,
+) -> crate::Result<SortKeyAccessor> {
+    let field = reader
+        .schema()
+        .get_field(field_name)
+        .ok_or_else(|| TantivyError::FieldNotFound(field_name.to_string()))?;
+    let field_type = reader.schema().get_field_entry(field).field_type();
+    match type_and_cardinality(field_type) {
+        Some((
+            FastType::U64 | FastType::I64 | FastType::F64 | FastType::Bool | FastType::Date,
+            Cardinality::SingleValue,
+        )) if matches!(
+            field_type.value_type(),
+            Type::U64 | Type::I64 | Type::F64 | Type::Bool | Type::Date
+        ) =>
+        {
+            Ok(SortKeyAccessor::FastField {
+                column: reader.fast_fields().u64_lenient(field)?,
+                field_type: field_type.value_type(),
+            })
+        }
+        _ => Err(TantivyError::InvalidArgument(format!(
+            "top_hits can only sort by _score or by single valued numeric or date fast fields, \
+             but {} is of type {:?}",
+            field_name,
+            field_type.value_type()
+        ))),
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct SegmentTopHitsCollector {
+    /// The number of hits to keep, `size + from`.
+    limit: usize,
+    /// The number of sort values of a hit.
+    num_sort_keys: usize,
+    top_n: TopSegmentCollector<Vec<u64>>,
+}
+
+// The heap can't be compared directly, so the collected hits are compared instead.
+impl PartialEq for SegmentTopHitsCollector {
+    fn eq(&self, other: &Self) -> bool {
+        self.limit == other.limit && self.top_n.clone().harvest() == other.top_n.clone().harvest()
+    }
+}
+
+impl SegmentTopHitsCollector {
+    pub fn from_req(req: &TopHitsAggregation, accessor: &TopHitsAccessor) -> crate::Result<Self> {
+        req.validate()?;
+        let limit = req.size + req.from;
+        Ok(Self {
+            limit,
+            num_sort_keys: accessor.sort_keys.len(),
+            top_n: TopSegmentCollector::new(accessor.segment_ord, limit),
+        })
+    }
+
+    /// Returns the estimated memory in bytes of the collector once its heap is full.
+    pub(crate) fn memory_estimate(&self) -> u64 {
+        let hit_memory = std::mem::size_of::<(Vec<u64>, DocId)>()
+            + self.num_sort_keys * std::mem::size_of::<u64>();
+        (std::mem::size_of::<Self>() + self.limit.saturating_mul(hit_memory)) as u64
+    }
+
+    pub(crate) fn collect_block(&mut self, docs: &[DocId], accessor: &TopHitsAccessor) {
+        for &doc in docs {
+            let rank_values = accessor
+                .sort_keys
+                .iter()
+                .map(|sort_key| sort_key.rank_value(doc))
+                .collect();
+            self.top_n.collect(doc, rank_values);
+        }
+    }
+
+    pub(crate) fn into_intermediate_top_hits(
+        self,
+        accessor: &TopHitsAccessor,
+    ) -> crate::Result<IntermediateTopHits> {
+        let hits = self
+            .top_n
+            .harvest()
+            .into_iter()
+            .map(|(rank_values, doc_address)| {
+                let sort = accessor
+                    .sort_keys
+                    .iter()
+                    .zip(rank_values.iter())
+                    .map(|(sort_key, rank_value)| sort_key.sort_value(*rank_value))
+                    .collect();
+                Ok(IntermediateTopHit {
+                    rank_values,
+                    doc_address,
+                    sort,
+                    stored_fields: accessor.get_stored_fields(doc_address.doc_id)?,
+                })
+            })
+            .collect::<crate::Result<_>>()?;
+        Ok(IntermediateTopHits {
+            limit: self.limit,
+            hits,
+        })
+    }
+}
+
+/// A hit of the [IntermediateTopHits].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct IntermediateTopHit {
+    /// The values of the sort keys, mapped so that greater values rank higher.
+    rank_values: Vec<u64>,
+    doc_address: DocAddress,
+    sort: Vec<f64>,
+    stored_fields: Option<NamedFieldDocument>,
+}
+
+/// Orders the hits by their rank values only, as required by the [TopCollector].
+#[derive(Clone)]
+struct RankedHit(IntermediateTopHit);
+
+impl PartialEq for RankedHit {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.rank_values == other.0.rank_values
+    }
+}
+
+impl PartialOrd for RankedHit {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.0.rank_values.partial_cmp(&other.0.rank_values)
+    }
+}
+
+/// Intermediate result of the top hits aggregation, which keeps the `size + from` best hits.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct IntermediateTopHits {
+    limit: usize,
+    /// The hits, best first.
+    hits: Vec<IntermediateTopHit>,
+}
+
+impl IntermediateTopHits {
+    pub(crate) fn from_req(req: &TopHitsAggregation) -> Self {
+        IntermediateTopHits {
+            limit: req.size + req.from,
+            hits: Vec::new(),
+        }
+    }
+
+    /// Merge hits from other intermediate results into this one.
+    pub fn merge_fruits(&mut self, other: IntermediateTopHits) {
+        if other.hits.is_empty() {
+            return;
+        }
+        if self.hits.is_empty() {
+            *self = other;
+            return;
+        }
+        let to_ranked_hits = |hits: Vec<IntermediateTopHit>| {
+            hits.into_iter()
+                .map(|hit| {
+                    let doc_address = hit.doc_address;
+                    (RankedHit(hit), doc_address)
+                })
+                .collect::<Vec<_>>()
+        };
+        let hits = std::mem::take(&mut self.hits);
+        self.hits = TopCollector::with_limit(self.limit)
+            .merge_fruits(vec![to_ranked_hits(hits), to_ranked_hits(other.hits)])
+            .expect("merging top hits can't fail")
+            .into_iter()
+            .map(|(hit, _)| hit.0)
+            .collect();
+    }
+
+    /// Computes the final top hits result.
+    pub fn finalize(self, req: &TopHitsAggregation) -> TopHitsMetricResult {
+        let hits = self
+            .hits
+            .into_iter()
+            .skip(req.from)
+            .take(req.size)
+            .map(|hit| TopHitsHit {
+                doc_address: hit.doc_address,
+                sort: hit.sort,
+                stored_fields: hit.stored_fields,
+            })
+            .collect();
+        TopHitsMetricResult { hits }
+    }
+}
+
+/// The result of the [TopHitsAggregation].
+///
+/// # JSON Format
+/// ```json
+/// {
+///     "hits": [
+///         {
+///             "doc_address": { "segment_ord": 0, "doc_id": 12 },
+///             "sort": [1667314212000.0],
+///             "stored_fields": { "message": ["disk full"] }
+///         }
+///     ]
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TopHitsMetricResult {
+    /// The top hits, best first.
+    pub hits: Vec<TopHitsHit>,
+}
+
+/// A hit of the [TopHitsMetricResult].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TopHitsHit {
+    /// The address of the document, which can be used to fetch it with the `Searcher`.
+    pub doc_address: DocAddress,
+    /// The values of the sort keys of the hit. Dates are returned as milliseconds.
+    pub sort: Vec<f64>,
+    /// The requested stored fields of the document.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub stored_fields: Option<NamedFieldDocument>,
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::aggregation::agg_req::{Aggregation, Aggregations, MetricAggregation};
+    use crate::aggregation::intermediate_agg_result::IntermediateAggregationResults;
+    use crate::aggregation::tests::{
+        exec_request_with_query, get_test_index_from_values_and_terms,
+    };
+    use crate::aggregation::{AggregationCollector, DistributedAggregationCollector};
+    use crate::query::{AllQuery, TermQuery};
+    use crate::schema::{IndexRecordOption, FAST, STORED, STRING};
+    use crate::{doc, Index, Term};
+
+    fn get_test_index() -> crate::Result<Index> {
+        let segment_and_terms = vec![
+            vec![
+                (1.0, "host1".to_string()),
+                (5.0, "host2".to_string()),
+                (7.0, "host1".to_string()),
+            ],
+            vec![
+                (9.0, "host2".to_string()),
+                (11.0, "host1".to_string()),
+                (3.0, "host1".to_string()),
+            ],
+            vec![(20.0, "host2".to_string())],
+        ];
+        get_test_index_from_values_and_terms(false, &segment_and_terms)
+    }
+
+    fn hit_sort_values(res: &Value) -> Vec<f64> {
+        res["hits"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|hit| hit["sort"][0].as_f64().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn top_hits_sort_by_fast_field_in_terms_test() -> crate::Result<()> {
+        let index = get_test_index()?;
+
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "hosts": {
+                "terms": { "field": "string_id" },
+                "aggs": {
+                    "latest": {
+                        "top_hits": {
+                            "size": 2,
+                            "sort": [{ "score": { "order": "desc" } }],
+                            "stored_fields": ["text_id"]
+                        }
+                    }
+                }
+            }
+        }))
+        .unwrap();
+
+        let res = exec_request_with_query(agg_req, &index, None)?;
+        let host1 = &res["hosts"]["buckets"][0];
+        assert_eq!(host1["key"], "host1");
+        assert_eq!(hit_sort_values(&host1["latest"]), vec![11.0, 7.0]);
+        assert_eq!(
+            host1["latest"]["hits"][0]["stored_fields"],
+            json!({ "text_id": ["host1"] })
+        );
+
+        let host2 = &res["hosts"]["buckets"][1];
+        assert_eq!(host2["key"], "host2");
+        assert_eq!(hit_sort_values(&host2["latest"]), vec![20.0, 9.0]);
+        Ok(())
+    }
+
+    #[test]
+    fn top_hits_sort_ascending_with_from_test() -> crate::Result<()> {
+        let index = get_test_index()?;
+
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "lowest": {
+                "top_hits": { "size": 3, "from": 1, "sort": ["score_f64"] }
+            }
+        }))
+        .unwrap();
+
+        let res = exec_request_with_query(agg_req, &index, None)?;
+        assert_eq!(hit_sort_values(&res["lowest"]), vec![3.0, 5.0, 7.0]);
+        assert!(res["lowest"]["hits"][0].get("stored_fields").is_none());
+
+        let reader = index.reader()?;
+        let searcher = reader.searcher();
+        let doc_address: DocAddress =
+            serde_json::from_value(res["lowest"]["hits"][0]["doc_address"].clone())?;
+        let doc = searcher.doc(doc_address)?;
+        let text_id = index.schema().get_field("text_id").unwrap();
+        assert_eq!(
+            doc.get_first(text_id).and_then(|value| value.as_text()),
+            Some("host1")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn top_hits_sort_by_score_test() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text = schema_builder.add_text_field("text", crate::schema::TEXT | STORED);
+        let id = schema_builder.add_u64_field("id", FAST);
+        let host = schema_builder.add_text_field("host", STRING | FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_for_tests()?;
+            index_writer
+                .add_document(doc!(text => "disk full of logs", id => 1u64, host => "a"))?;
+            index_writer.add_document(doc!(text => "disk full disk", id => 2u64, host => "a"))?;
+            index_writer.commit()?;
+            index_writer.add_document(doc!(text => "disk disk disk", id => 3u64, host => "b"))?;
+            index_writer.add_document(doc!(text => "cpu", id => 4u64, host => "a"))?;
+            index_writer.commit()?;
+        }
+
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "hosts": {
+                "terms": { "field": "host" },
+                "aggs": {
+                    "best": { "top_hits": { "size": 1, "sort": ["_score", "id"] } }
+                }
+            }
+        }))
+        .unwrap();
+
+        let collector = AggregationCollector::from_aggs(agg_req, None);
+        let reader = index.reader()?;
+        let searcher = reader.searcher();
+        let query = TermQuery::new(
+            Term::from_field_text(text, "disk"),
+            IndexRecordOption::WithFreqs,
+        );
+        let res = serde_json::to_value(searcher.search(&query, &collector)?)?;
+
+        let best_a = &res["hosts"]["buckets"][0]["best"]["hits"][0];
+        assert_eq!(res["hosts"]["buckets"][0]["key"], "a");
+        assert!(best_a["sort"][0].as_f64().unwrap() > 0.0);
+        assert_eq!(best_a["sort"][1], 2.0);
+        let best_b = &res["hosts"]["buckets"][1]["best"]["hits"][0];
+        assert_eq!(best_b["sort"][1], 3.0);
+        Ok(())
+    }
+
+    #[test]
+    fn top_hits_distributed_test() -> crate::Result<()> {
+        let index = get_test_index()?;
+
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "highest": {
+                "top_hits": { "size": 3, "sort": [{ "score_i64": { "order": "desc" } }] }
+            }
+        }))
+        .unwrap();
+
+        let collector = DistributedAggregationCollector::from_aggs(agg_req.clone(), None);
+        let reader = index.reader()?;
+        let searcher = reader.searcher();
+        let intermediate_res = searcher.search(&AllQuery, &collector)?;
+        let intermediate_res: IntermediateAggregationResults =
+            serde_json::from_str(&serde_json::to_string(&intermediate_res)?)?;
+        let mut merged_res = intermediate_res.clone();
+        merged_res.merge_fruits(intermediate_res);
+
+        let res = serde_json::to_value(merged_res.into_final_bucket_result(agg_req)?)?;
+        // The same hits were merged twice.
+        assert_eq!(hit_sort_values(&res["highest"]), vec![20.0, 20.0, 11.0]);
+        assert_eq!(
+            res["highest"]["hits"][0]["doc_address"],
+            res["highest"]["hits"][1]["doc_address"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn top_hits_validation_test() -> crate::Result<()> {
+        let index = get_test_index()?;
+
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "hits": { "top_hits": { "sort": ["text_id"] } }
+        }))
+        .unwrap();
+        let err = exec_request_with_query(agg_req, &index, None).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "An invalid argument was passed: 'top_hits can only sort by _score or by single \
+             valued numeric or date fast fields, but text_id is of type Str'"
+        );
+
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "hits": { "top_hits": { "size": 0, "sort": ["score"] } }
+        }))
+        .unwrap();
+        let err = exec_request_with_query(agg_req, &index, None).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "An invalid argument was passed: 'top_hits size must be greater than 0'"
+        );
+
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "hits": { "top_hits": { "sort": ["score"], "stored_fields": ["score"] } }
+        }))
+        .unwrap();
+        let err = exec_request_with_query(agg_req, &index, None).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "An invalid argument was passed: 'top_hits can only return stored fields, but score \
+             is not stored'"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn top_hits_serde_test() {
+        let req_json = json!({
+            "hits": {
+                "top_hits": {
+                    "size": 2,
+                    "from": 0,
+                    "sort": [{ "_score": { "order": "desc" } }, { "score": { "order": "asc" } }]
+                }
+            }
+        });
+        let agg_req: Aggregations = serde_json::from_value(json!({
+            "hits": { "top_hits": { "size": 2, "sort": ["_score", { "score": {} }] } }
+        }))
+        .unwrap();
+        assert_eq!(
+            agg_req["hits"],
+            Aggregation::Metric(MetricAggregation::TopHits(TopHitsAggregation {
+                size: 2,
+                sort: vec![
+                    TopHitsSort::score(),
+                    TopHitsSort::field("score", Order::Asc)
+                ],
+                ..Default::default()
+            }))
+        );
+        assert_eq!(serde_json::to_value(&agg_req).unwrap(), req_json);
+    }
+}
@@ -8,11 +8,22 @@
 //! - How many errors with status code 500 do we have per day?
 //! - What is the average listing price of cars grouped by color?
 //!
-//! There are two categories: [Metrics](metric) and [Buckets](bucket).
+//! There are two categories: [Metrics](metric) and [Buckets](bucket). Additionally,
+//! [Pipelines](pipeline) compute values from the results of other aggregations.
 //!
 //! ## Prerequisite
 //! Currently aggregations work only on [fast fields](`crate::fastfield`). Single value fast fields
-//! of type `u64`, `f64`, `i64` and fast fields on text fields.
+//! of type `u64`, `f64`, `i64` and fast fields on text fields. Date fast fields are supported by
+//! the [DateHistogram](bucket::DateHistogramAggregation) aggregation.
+//! The [Filter](bucket::FilterAggregation) and [Filters](bucket::FiltersAggregation)
+//! aggregations use queries instead of fast fields.
+//!
+//! The terms, range, histogram and numeric metric aggregations also accept a path inside a json
+//! field, e.g. `attributes.status`. The values of the path are read from the inverted index, and
+//! the type of each value is taken from its json term. Numeric aggregations use the json numbers
+//! of the path as `f64` and skip text values. Paths inside arrays of objects, e.g. `items.price`,
+//! are multivalued: bucket aggregations count a document once per bucket, and metrics aggregate
+//! all of its values.
 //!
 //! ## Usage
 //! To use aggregations, build an aggregation request by constructing
@@ -36,11 +47,37 @@
 //! ## Supported Aggregations
 //! - [Bucket](bucket)
 //!     - [Histogram](bucket::HistogramAggregation)
+//!     - [DateHistogram](bucket::DateHistogramAggregation)
 //!     - [Range](bucket::RangeAggregation)
 //!     - [Terms](bucket::TermsAggregation)
+//!     - [SignificantTerms](bucket::SignificantTermsAggregation)
+//!     - [Filter](bucket::FilterAggregation)
+//!     - [Filters](bucket::FiltersAggregation)
+//!     - [Composite](bucket::CompositeAggregation)
+//!     - [GeoDistance](bucket::GeoDistanceAggregation)
+//!     - [GeohashGrid](bucket::GeohashGridAggregation)
+//!     - [GeoTileGrid](bucket::GeoTileGridAggregation)
 //! - [Metric](metric)
 //!     - [Average](metric::AverageAggregation)
 //!     - [Stats](metric::StatsAggregation)
+//!     - [Min](metric::MinAggregation)
+//!     - [Max](metric::MaxAggregation)
+//!     - [Sum](metric::SumAggregation)
+//!     - [ValueCount](metric::ValueCountAggregation)
+//!     - [Cardinality](metric::CardinalityAggregation)
+//!     - [Percentiles](metric::PercentilesAggregation)
+//!     - [PercentileRanks](metric::PercentileRanksAggregation)
+//!     - [TopHits](metric::TopHitsAggregation)
+//!     - [GeoBounds](metric::GeoBoundsAggregation)
+//!     - [GeoCentroid](metric::GeoCentroidAggregation)
+//! - [Pipeline](pipeline)
+//!     - [BucketSort](pipeline::BucketSortAggregation)
+//!     - [BucketSelector](pipeline::BucketSelectorAggregation)
+//!     - [Derivative](pipeline::DerivativeAggregation)
+//!     - [CumulativeSum](pipeline::CumulativeSumAggregation)
+//!     - [MovingAvg](pipeline::MovingAvgAggregation)
+//!     - [AvgBucket](pipeline::AvgBucketAggregation)
+//!     - [MaxBucket](pipeline::MaxBucketAggregation)
 //!
 //! # Example
 //! Compute the average metric, by building [`agg_req::Aggregations`], which is built from an
@@ -157,17 +194,25 @@ mod agg_req_with_accessor;
 pub mod agg_result;
 pub mod bucket;
 mod collector;
+mod error;
+mod geo;
 pub mod intermediate_agg_result;
+mod json_path;
 pub mod metric;
+pub mod pipeline;
 mod segment_agg_result;
+use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::fmt::Display;
+use std::hash::{Hash, Hasher};
 
 pub use collector::{
-    AggregationCollector, AggregationSegmentCollector, DistributedAggregationCollector,
-    MAX_BUCKET_COUNT,
+    AggregationCollector, AggregationLimits, AggregationSegmentCollector,
+    DistributedAggregationCollector, DEFAULT_MEMORY_LIMIT, MAX_BUCKET_COUNT,
 };
+pub use error::AggregationError;
 use fastfield_codecs::MonotonicallyMappableToU64;
+pub use geo::GeoPoint;
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 
@@ -202,14 +247,6 @@ impl<T: Clone> From<HashMap<String, T>> for VecWithNames<T> {
 }
 
 impl<T: Clone> VecWithNames<T> {
-    fn from_other<K: Clone + Into<T>>(entries: VecWithNames<K>) -> Self {
-        let values = entries.values.into_iter().map(Into::into).collect();
-        Self {
-            keys: entries.keys,
-            values,
-        }
-    }
-
     fn from_entries(mut entries: Vec<(String, T)>) -> Self {
         // Sort to ensure order of elements match across multiple instances
         entries.sort_by(|left, right| left.0.cmp(&right.0));
@@ -280,6 +317,78 @@ impl Display for Key {
     }
 }
 
+/// The key of a term bucket in the intermediate results.
+///
+/// In contrast to [`Key`], it can be used as key in a `HashMap`, since `f64` values are compared
+/// by their bit representation.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum IntermediateKey {
+    /// String key
+    Str(String),
+    /// `f64` key
+    F64(f64),
+}
+
+impl From<IntermediateKey> for Key {
+    fn from(key: IntermediateKey) -> Self {
+        match key {
+            IntermediateKey::Str(val) => Key::Str(val),
+            IntermediateKey::F64(val) => Key::F64(val),
+        }
+    }
+}
+
+impl From<Key> for IntermediateKey {
+    fn from(key: Key) -> Self {
+        match key {
+            Key::Str(val) => IntermediateKey::Str(val),
+            Key::F64(val) => IntermediateKey::F64(val),
+        }
+    }
+}
+
+impl PartialEq for IntermediateKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for IntermediateKey {}
+
+impl PartialOrd for IntermediateKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for IntermediateKey {
+    /// String keys are ordered before numeric keys, like in [`Key`].
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (IntermediateKey::Str(left), IntermediateKey::Str(right)) => left.cmp(right),
+            (IntermediateKey::F64(left), IntermediateKey::F64(right)) => left.total_cmp(right),
+            (IntermediateKey::Str(_), IntermediateKey::F64(_)) => Ordering::Less,
+            (IntermediateKey::F64(_), IntermediateKey::Str(_)) => Ordering::Greater,
+        }
+    }
+}
+
+impl Hash for IntermediateKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match self {
+            IntermediateKey::Str(val) => {
+                0u8.hash(state);
+                val.hash(state);
+            }
+            IntermediateKey::F64(val) => {
+                1u8.hash(state);
+                val.to_bits().hash(state);
+            }
+        }
+    }
+}
+
 /// Inverse of `to_fastfield_u64`. Used to convert to `f64` for metrics.
 ///
 /// # Panics
@@ -316,7 +425,7 @@ pub(crate) fn f64_to_fastfield_u64(val: f64, field_type: &Type) -> Option<u64> {
 
 #[cfg(test)]
 mod tests {
-    use serde_json::Value;
+    use serde_json::{json, Value};
 
     use super::agg_req::{Aggregation, Aggregations, BucketAggregation};
     use super::bucket::RangeAggregation;
@@ -329,10 +438,12 @@ mod tests {
     use crate::aggregation::bucket::TermsAggregation;
     use crate::aggregation::intermediate_agg_result::IntermediateAggregationResults;
     use crate::aggregation::segment_agg_result::DOC_BLOCK_SIZE;
-    use crate::aggregation::DistributedAggregationCollector;
+    use crate::aggregation::{
+        AggregationError, DistributedAggregationCollector, DEFAULT_MEMORY_LIMIT,
+    };
     use crate::query::{AllQuery, TermQuery};
     use crate::schema::{Cardinality, IndexRecordOption, Schema, TextFieldIndexing, FAST, STRING};
-    use crate::{Index, Term};
+    use crate::{Index, TantivyError, Term};
 
     fn get_avg_req(field_name: &str) -> Aggregation {
         Aggregation::Metric(MetricAggregation::Average(
@@ -350,6 +461,93 @@ mod tests {
         )
     }
 
+    /// Vehicles around Amsterdam in the first segment, and in Utrecht and Berlin in the second.
+    pub fn get_test_index_with_geo_points(merge_segments: bool) -> crate::Result<Index> {
+        let mut schema_builder = Schema::builder();
+        let lat_field = schema_builder.add_f64_field("lat", FAST);
+        let lon_field = schema_builder.add_f64_field("lon", FAST);
+        let fleet_field = schema_builder.add_text_field("fleet", STRING | FAST);
+        let speed_field = schema_builder.add_f64_field("speed", FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 30_000_000)?;
+            let segments = [
+                vec![
+                    (52.3676, 4.9041, "trucks", 10.0),
+                    (52.3700, 4.8950, "vans", 20.0),
+                    (52.3500, 4.9200, "trucks", 30.0),
+                ],
+                vec![
+                    (52.0907, 5.1214, "trucks", 50.0),
+                    (52.5200, 13.4050, "vans", 80.0),
+                ],
+            ];
+            for docs in segments {
+                for (lat, lon, fleet, speed) in docs {
+                    index_writer.add_document(doc!(
+                        lat_field => lat,
+                        lon_field => lon,
+                        fleet_field => fleet,
+                        speed_field => speed,
+                    ))?;
+                }
+                index_writer.commit()?;
+            }
+            if merge_segments {
+                let segment_ids = index.searchable_segment_ids()?;
+                index_writer.merge(&segment_ids).wait()?;
+                index_writer.wait_merging_threads()?;
+            }
+        }
+        Ok(index)
+    }
+
+    /// Events with a json payload. The `items` of the payload are an array of objects, and some
+    /// values of `latency` and `items.price` are text.
+    pub fn get_test_index_with_json_events(merge_segments: bool) -> crate::Result<Index> {
+        let mut schema_builder = Schema::builder();
+        let event_field = schema_builder.add_json_field("event", STRING);
+        let index = Index::create_in_ram(schema_builder.build());
+        {
+            let mut index_writer = index.writer_with_num_threads(1, 30_000_000)?;
+            let segments = [
+                vec![
+                    json!({
+                        "status": "ok",
+                        "latency": 12,
+                        "items": [{ "price": 5 }, { "price": 15.5 }]
+                    }),
+                    json!({
+                        "status": "ok",
+                        "latency": 35.5,
+                        "items": [{ "price": 25 }, { "price": 28 }]
+                    }),
+                ],
+                vec![
+                    json!({
+                        "status": "error",
+                        "latency": 120,
+                        "items": [{ "price": -3 }, { "price": "free" }]
+                    }),
+                    json!({ "status": "ok", "latency": "n/a" }),
+                ],
+            ];
+            for docs in segments {
+                for event in docs {
+                    index_writer
+                        .add_document(doc!(event_field => event.as_object().unwrap().clone()))?;
+                }
+                index_writer.commit()?;
+            }
+            if merge_segments {
+                let segment_ids = index.searchable_segment_ids()?;
+                index_writer.merge(&segment_ids).wait()?;
+                index_writer.wait_merging_threads()?;
+            }
+        }
+        Ok(index)
+    }
+
     pub fn exec_request(agg_req: Aggregations, index: &Index) -> crate::Result<Value> {
         exec_request_with_query(agg_req, index, None)
     }
@@ -1148,6 +1346,80 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_aggregation_limits() -> crate::Result<()> {
+        let values_and_terms = vec![
+            vec![(1.0, "terma".to_string()), (1_000.0, "termb".to_string())],
+            vec![(5.0, "termc".to_string())],
+        ];
+        let index = get_test_index_from_values_and_terms(false, &values_and_terms)?;
+        let searcher = index.reader()?.searcher();
+        let exec = |agg_req: serde_json::Value, collector_limits: (Option<u32>, Option<u64>)| {
+            let agg_req: Aggregations = serde_json::from_value(agg_req).unwrap();
+            let (max_bucket_count, memory_limit) = collector_limits;
+            let mut collector = AggregationCollector::from_aggs(agg_req, max_bucket_count);
+            if let Some(memory_limit) = memory_limit {
+                collector = collector.with_memory_limit(memory_limit);
+            }
+            searcher.search(&AllQuery, &collector)
+        };
+
+        // The buckets of a histogram are allocated upfront for the range of the values.
+        let histogram_req = serde_json::json!({
+            "histogram": {
+                "histogram": {
+                    "field": "score_f64",
+                    "interval": 0.000_001,
+                    "hard_bounds": { "min": 0.0, "max": 1_000_000.0 }
+                }
+            }
+        });
+        let err = exec(histogram_req, (None, None)).unwrap_err();
+        assert!(matches!(
+            err,
+            TantivyError::AggregationError(AggregationError::MemoryExceeded {
+                limit: DEFAULT_MEMORY_LIMIT,
+                ..
+            })
+        ));
+
+        // Every term bucket holds a copy of the preallocated histogram buckets.
+        let terms_histogram_req = serde_json::json!({
+            "my_texts": {
+                "terms": { "field": "string_id" },
+                "aggs": { "histogram": { "histogram": { "field": "score_f64", "interval": 1.0 } } }
+            }
+        });
+        assert!(exec(terms_histogram_req.clone(), (None, Some(100_000))).is_ok());
+        let err = exec(terms_histogram_req, (None, Some(50_000))).unwrap_err();
+        assert!(matches!(
+            err,
+            TantivyError::AggregationError(AggregationError::MemoryExceeded {
+                limit: 50_000,
+                ..
+            })
+        ));
+
+        let terms_req = serde_json::json!({
+            "my_texts": { "terms": { "field": "string_id" } }
+        });
+        assert!(exec(terms_req.clone(), (None, None)).is_ok());
+        let err = exec(terms_req.clone(), (Some(1), None)).unwrap_err();
+        assert!(matches!(
+            err,
+            TantivyError::AggregationError(AggregationError::BucketLimitExceeded {
+                limit: 1,
+                current: 2
+            })
+        ));
+        let err = exec(terms_req, (None, Some(10))).unwrap_err();
+        assert!(matches!(
+            err,
+            TantivyError::AggregationError(AggregationError::MemoryExceeded { limit: 10, .. })
+        ));
+        Ok(())
+    }
+
     #[cfg(all(test, feature = "unstable"))]
     mod bench {
 
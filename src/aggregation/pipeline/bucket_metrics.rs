@@ -0,0 +1,121 @@
+use serde::{Deserialize, Serialize};
+
+use super::GapPolicy;
+use crate::aggregation::Key;
+
+/// A sibling pipeline aggregation, which computes the average of the values of all buckets of a
+/// sibling multi bucket aggregation.
+///
+/// With the default [`GapPolicy::Skip`], buckets without a value are ignored.
+/// See [`SingleMetricResult`](crate::aggregation::metric::SingleMetricResult) for return value.
+///
+/// # JSON Format
+/// ```json
+/// {
+///     "avg_bucket": {
+///         "buckets_path": "sales_per_month>sales"
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AvgBucketAggregation {
+    /// The path to the sibling aggregation, followed by `>` and the path to the value in its
+    /// buckets.
+    pub buckets_path: String,
+    /// How buckets without a value are handled.
+    #[serde(default)]
+    pub gap_policy: GapPolicy,
+}
+
+impl AvgBucketAggregation {
+    /// Create new AvgBucketAggregation from a buckets path.
+    pub fn from_buckets_path(buckets_path: String) -> Self {
+        AvgBucketAggregation {
+            buckets_path,
+            gap_policy: GapPolicy::default(),
+        }
+    }
+
+    pub(crate) fn compute(&self, values: &[(Option<Key>, Option<f64>)]) -> Option<f64> {
+        let (count, sum) = values
+            .iter()
+            .filter_map(|(_, value)| self.gap_policy.apply(*value))
+            .fold((0, 0.0), |(count, sum), value| (count + 1, sum + value));
+        (count > 0).then(|| sum / count as f64)
+    }
+}
+
+/// A sibling pipeline aggregation, which finds the maximum of the values of all buckets of a
+/// sibling multi bucket aggregation, and the keys of the buckets with that value.
+///
+/// With the default [`GapPolicy::Skip`], buckets without a value are ignored.
+/// See [`BucketMetricValueResult`] for return value.
+///
+/// # JSON Format
+/// ```json
+/// {
+///     "max_bucket": {
+///         "buckets_path": "sales_per_month>sales"
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MaxBucketAggregation {
+    /// The path to the sibling aggregation, followed by `>` and the path to the value in its
+    /// buckets.
+    pub buckets_path: String,
+    /// How buckets without a value are handled.
+    #[serde(default)]
+    pub gap_policy: GapPolicy,
+}
+
+impl MaxBucketAggregation {
+    /// Create new MaxBucketAggregation from a buckets path.
+    pub fn from_buckets_path(buckets_path: String) -> Self {
+        MaxBucketAggregation {
+            buckets_path,
+            gap_policy: GapPolicy::default(),
+        }
+    }
+
+    pub(crate) fn compute(&self, values: &[(Option<Key>, Option<f64>)]) -> BucketMetricValueResult {
+        let mut result = BucketMetricValueResult {
+            value: None,
+            keys: Vec::new(),
+        };
+        for (key, value) in values {
+            let value = match self.gap_policy.apply(*value) {
+                Some(value) => value,
+                None => continue,
+            };
+            match result.value {
+                Some(max) if value < max => continue,
+                Some(max) if value == max => {}
+                _ => {
+                    result.value = Some(value);
+                    result.keys.clear();
+                }
+            }
+            result.keys.extend(key.clone());
+        }
+        result
+    }
+}
+
+/// The result of a sibling pipeline aggregation, which selects a value of a bucket, e.g.
+/// [`MaxBucketAggregation`].
+///
+/// # JSON Format
+/// ```json
+/// {
+///     "value": 550.0,
+///     "keys": [1646092800000.0]
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BucketMetricValueResult {
+    /// The selected value, `None` if no bucket has a value.
+    pub value: Option<f64>,
+    /// The keys of the buckets with the selected value.
+    pub keys: Vec<Key>,
+}
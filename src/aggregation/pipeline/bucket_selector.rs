@@ -0,0 +1,53 @@
+use serde::{Deserialize, Serialize};
+
+use super::GapPolicy;
+
+/// A parent pipeline aggregation, which removes the buckets of its parent multi bucket
+/// aggregation whose value is outside of the given bounds.
+///
+/// With the default [`GapPolicy::Skip`], buckets without a value are removed.
+///
+/// # JSON Format
+/// ```json
+/// {
+///     "bucket_selector": {
+///         "buckets_path": "total_sales",
+///         "gte": 200.0,
+///         "lt": 1000.0
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct BucketSelectorAggregation {
+    /// The path to the value in the buckets to select the buckets by.
+    pub buckets_path: String,
+    /// Keep buckets with a value greater than this bound.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub gt: Option<f64>,
+    /// Keep buckets with a value greater than or equal to this bound.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub gte: Option<f64>,
+    /// Keep buckets with a value less than this bound.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub lt: Option<f64>,
+    /// Keep buckets with a value less than or equal to this bound.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub lte: Option<f64>,
+    /// How buckets without a value are handled.
+    #[serde(default)]
+    pub gap_policy: GapPolicy,
+}
+
+impl BucketSelectorAggregation {
+    /// Returns true if a bucket with the value is kept.
+    pub(crate) fn matches(&self, value: Option<f64>) -> bool {
+        let value = match self.gap_policy.apply(value) {
+            Some(value) => value,
+            None => return false,
+        };
+        self.gt.map_or(true, |bound| value > bound)
+            && self.gte.map_or(true, |bound| value >= bound)
+            && self.lt.map_or(true, |bound| value < bound)
+            && self.lte.map_or(true, |bound| value <= bound)
+    }
+}
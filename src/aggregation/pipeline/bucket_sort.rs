@@ -0,0 +1,160 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use serde::de::{self, Deserializer};
+use serde::ser::{SerializeMap, Serializer};
+use serde::{Deserialize, Serialize};
+
+use super::PipelineBucket;
+use crate::aggregation::bucket::Order;
+
+/// A parent pipeline aggregation, which sorts the buckets of its parent multi bucket aggregation
+/// by the values of the buckets and truncates them to `size` buckets, starting at `from`.
+///
+/// Buckets without a value are sorted after the buckets with a value. Without a sort key, the
+/// buckets keep their order and are only truncated.
+///
+/// # JSON Format
+/// ```json
+/// {
+///     "bucket_sort": {
+///         "sort": [{ "total_sales": { "order": "desc" } }, "_key"],
+///         "size": 3
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct BucketSortAggregation {
+    /// The sort keys. Buckets with equal values on the first key are sorted by the next one.
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub sort: Vec<BucketSortField>,
+    /// The number of buckets to skip.
+    #[serde(default)]
+    pub from: usize,
+    /// The number of buckets to return. Defaults to all remaining buckets.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub size: Option<usize>,
+}
+
+impl BucketSortAggregation {
+    pub(crate) fn sort_buckets<B: PipelineBucket>(
+        &self,
+        buckets: &mut Vec<B>,
+    ) -> crate::Result<()> {
+        if !self.sort.is_empty() {
+            let mut buckets_with_values = buckets
+                .drain(..)
+                .map(|bucket| {
+                    let values = self
+                        .sort
+                        .iter()
+                        .map(|sort_field| bucket.value(&sort_field.path))
+                        .collect::<crate::Result<Vec<_>>>()?;
+                    Ok((values, bucket))
+                })
+                .collect::<crate::Result<Vec<_>>>()?;
+            buckets_with_values.sort_by(|(left, _), (right, _)| {
+                self.sort
+                    .iter()
+                    .zip(left.iter().zip(right.iter()))
+                    .map(|(sort_field, (left, right))| sort_field.compare(*left, *right))
+                    .find(|ordering| *ordering != Ordering::Equal)
+                    .unwrap_or(Ordering::Equal)
+            });
+            buckets.extend(buckets_with_values.into_iter().map(|(_, bucket)| bucket));
+        }
+        buckets.drain(..self.from.min(buckets.len()));
+        if let Some(size) = self.size {
+            buckets.truncate(size);
+        }
+        Ok(())
+    }
+}
+
+/// A sort key of the [BucketSortAggregation]. Either `_count`, `_key` or the path to a value of
+/// a sub aggregation.
+///
+/// # JSON Format
+/// The order defaults to ascending.
+/// ```json
+/// ["_count", { "total_sales": { "order": "desc" } }]
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct BucketSortField {
+    /// The path to the value in the buckets to sort by.
+    pub path: String,
+    /// The sort order.
+    pub order: Order,
+}
+
+impl BucketSortField {
+    /// Sort by the value the path points to.
+    pub fn new(path: &str, order: Order) -> Self {
+        BucketSortField {
+            path: path.to_string(),
+            order,
+        }
+    }
+
+    fn compare(&self, left: Option<f64>, right: Option<f64>) -> Ordering {
+        match (left, right) {
+            (Some(left), Some(right)) => {
+                let ordering = left.partial_cmp(&right).unwrap_or(Ordering::Equal);
+                match self.order {
+                    Order::Asc => ordering,
+                    Order::Desc => ordering.reverse(),
+                }
+            }
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BucketSortOrder {
+    order: Option<Order>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum BucketSortFieldJson {
+    Path(String),
+    PathWithOrder(HashMap<String, BucketSortOrder>),
+}
+
+impl Serialize for BucketSortField {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where S: Serializer {
+        let mut map = serializer.serialize_map(Some(1))?;
+        map.serialize_entry(&self.path, &HashMap::from([("order", self.order)]))?;
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for BucketSortField {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where D: Deserializer<'de> {
+        match BucketSortFieldJson::deserialize(deserializer)? {
+            BucketSortFieldJson::Path(path) => Ok(BucketSortField {
+                path,
+                order: Order::Asc,
+            }),
+            BucketSortFieldJson::PathWithOrder(map) => {
+                if map.len() != 1 {
+                    return Err(de::Error::custom(format!(
+                        "expected exactly one path per sort key, but got {}",
+                        map.len()
+                    )));
+                }
+                let (path, sort_order) = map.into_iter().next().expect("map has one entry");
+                Ok(BucketSortField {
+                    path,
+                    order: sort_order.order.unwrap_or(Order::Asc),
+                })
+            }
+        }
+    }
+}
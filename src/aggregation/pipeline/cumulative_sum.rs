@@ -0,0 +1,39 @@
+use serde::{Deserialize, Serialize};
+
+/// A parent pipeline aggregation, which computes for every bucket of its parent histogram or
+/// date histogram the sum of the values of all buckets up to and including the bucket.
+///
+/// Buckets without a value don't change the sum.
+/// See [`SingleMetricResult`](crate::aggregation::metric::SingleMetricResult) for return value.
+///
+/// # JSON Format
+/// ```json
+/// {
+///     "cumulative_sum": {
+///         "buckets_path": "sales"
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CumulativeSumAggregation {
+    /// The path to the value in the buckets to sum up.
+    pub buckets_path: String,
+}
+
+impl CumulativeSumAggregation {
+    /// Create new CumulativeSumAggregation from a buckets path.
+    pub fn from_buckets_path(buckets_path: String) -> Self {
+        CumulativeSumAggregation { buckets_path }
+    }
+
+    pub(crate) fn compute(&self, values: &[Option<f64>]) -> Vec<Option<f64>> {
+        let mut sum = 0.0;
+        values
+            .iter()
+            .map(|value| {
+                sum += value.unwrap_or(0.0);
+                Some(sum)
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,51 @@
+use serde::{Deserialize, Serialize};
+
+use super::GapPolicy;
+
+/// A parent pipeline aggregation, which computes for every bucket of its parent histogram or
+/// date histogram the difference between the value of the bucket and the value of the previous
+/// bucket. The first bucket has no derivative.
+///
+/// With the default [`GapPolicy::Skip`], buckets without a value have no derivative and the
+/// derivative of the next bucket is computed to the last bucket with a value.
+/// See [`SingleMetricResult`](crate::aggregation::metric::SingleMetricResult) for return value.
+///
+/// # JSON Format
+/// ```json
+/// {
+///     "derivative": {
+///         "buckets_path": "sales"
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DerivativeAggregation {
+    /// The path to the value in the buckets to compute the derivative of.
+    pub buckets_path: String,
+    /// How buckets without a value are handled.
+    #[serde(default)]
+    pub gap_policy: GapPolicy,
+}
+
+impl DerivativeAggregation {
+    /// Create new DerivativeAggregation from a buckets path.
+    pub fn from_buckets_path(buckets_path: String) -> Self {
+        DerivativeAggregation {
+            buckets_path,
+            gap_policy: GapPolicy::default(),
+        }
+    }
+
+    pub(crate) fn compute(&self, values: &[Option<f64>]) -> Vec<Option<f64>> {
+        let mut previous = None;
+        values
+            .iter()
+            .map(|value| {
+                let value = self.gap_policy.apply(*value)?;
+                let derivative = previous.map(|previous| value - previous);
+                previous = Some(value);
+                derivative
+            })
+            .collect()
+    }
+}
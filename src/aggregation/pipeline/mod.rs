@@ -0,0 +1,757 @@
+//! Module for all pipeline aggregations.
+//!
+//! Pipeline aggregations compute values from the results of other aggregations instead of from
+//! documents, see [super::agg_req::PipelineAggregation] for details. They are computed on the
+//! final [`AggregationResults`] tree, after the intermediate results of all segments have been
+//! merged, so they don't take part in the collection of a segment.
+//!
+//! The values are referenced via a `buckets_path`:
+//! - `_count` is the doc count of a bucket.
+//! - `_key` is the numeric key of a bucket.
+//! - `my_metric` is the value of a single value metric or pipeline sub aggregation.
+//! - `my_stats.avg` is a value of a multi value metric sub aggregation.
+//!
+//! Sibling pipeline aggregations prefix the path with the name of the multi bucket aggregation
+//! and `>`, e.g. `sales_per_month>sales`.
+
+mod bucket_metrics;
+mod bucket_selector;
+mod bucket_sort;
+mod cumulative_sum;
+mod derivative;
+mod moving_avg;
+
+pub use bucket_metrics::*;
+pub use bucket_selector::*;
+pub use bucket_sort::*;
+pub use cumulative_sum::*;
+pub use derivative::*;
+pub use moving_avg::*;
+use serde::{Deserialize, Serialize};
+
+use super::agg_req::{
+    Aggregation, Aggregations, BucketAggregation, BucketAggregationType, MetricAggregation,
+    PipelineAggregation,
+};
+use super::agg_result::{
+    AggregationResult, AggregationResults, BucketEntries, BucketEntry, BucketResult,
+    CompositeBucketEntry, FilterBucketEntry, PipelineResult, RangeBucketEntry,
+    SignificantTermBucketEntry,
+};
+use super::bucket::get_agg_name_and_property;
+use super::Key;
+use crate::TantivyError;
+
+/// The `buckets_path` to the doc count of a bucket.
+pub const COUNT_PATH: &str = "_count";
+/// The `buckets_path` to the key of a bucket.
+pub const KEY_PATH: &str = "_key";
+
+/// Defines how buckets without a value are handled, e.g. buckets without documents and therefore
+/// without an average.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GapPolicy {
+    /// Buckets without a value are skipped.
+    #[serde(rename = "skip")]
+    #[default]
+    Skip,
+    /// Buckets without a value are treated as if their value was 0.
+    #[serde(rename = "insert_zeros")]
+    InsertZeros,
+}
+
+impl GapPolicy {
+    fn apply(self, value: Option<f64>) -> Option<f64> {
+        match self {
+            GapPolicy::Skip => value,
+            GapPolicy::InsertZeros => Some(value.unwrap_or(0.0)),
+        }
+    }
+}
+
+/// Common access to the final bucket entries of the different bucket aggregations.
+pub(crate) trait PipelineBucket {
+    fn key(&self) -> Option<&Key>;
+    fn doc_count(&self) -> u64;
+    fn sub_aggregation(&self) -> &AggregationResults;
+    fn sub_aggregation_mut(&mut self) -> &mut AggregationResults;
+
+    /// Returns the value the `buckets_path` points to.
+    fn value(&self, buckets_path: &str) -> crate::Result<Option<f64>> {
+        match buckets_path {
+            COUNT_PATH => Ok(Some(self.doc_count() as f64)),
+            KEY_PATH => match self.key() {
+                Some(Key::F64(key)) => Ok(Some(*key)),
+                _ => Err(TantivyError::InvalidArgument(format!(
+                    "buckets_path {} requires buckets with numeric keys",
+                    KEY_PATH
+                ))),
+            },
+            _ => {
+                let (agg_name, agg_property) = get_agg_name_and_property(buckets_path);
+                self.sub_aggregation()
+                    .get_value_from_aggregation(agg_name, agg_property)
+            }
+        }
+    }
+}
+
+macro_rules! impl_pipeline_bucket {
+    ($bucket:ty, |$bucket_ref:pat_param| $key:expr) => {
+        impl PipelineBucket for $bucket {
+            fn key(&self) -> Option<&Key> {
+                let $bucket_ref = self;
+                $key
+            }
+            fn doc_count(&self) -> u64 {
+                self.doc_count
+            }
+            fn sub_aggregation(&self) -> &AggregationResults {
+                &self.sub_aggregation
+            }
+            fn sub_aggregation_mut(&mut self) -> &mut AggregationResults {
+                &mut self.sub_aggregation
+            }
+        }
+    };
+}
+
+impl_pipeline_bucket!(BucketEntry, |bucket| Some(&bucket.key));
+impl_pipeline_bucket!(RangeBucketEntry, |bucket| Some(&bucket.key));
+impl_pipeline_bucket!(CompositeBucketEntry, |_| None);
+impl_pipeline_bucket!(FilterBucketEntry, |_| None);
+impl_pipeline_bucket!(SignificantTermBucketEntry, |bucket| Some(&bucket.key));
+
+/// Validates the pipeline aggregations in the request tree, i.e. that parent pipeline
+/// aggregations are placed below a suitable bucket aggregation and that the `buckets_path` of
+/// every pipeline aggregation points to a value.
+pub(crate) fn validate_pipeline_aggregations(aggs: &Aggregations) -> crate::Result<()> {
+    validate_pipeline_aggregations_with_parent(aggs, None)
+}
+
+fn validate_pipeline_aggregations_with_parent(
+    aggs: &Aggregations,
+    parent: Option<&BucketAggregationType>,
+) -> crate::Result<()> {
+    for (name, agg) in aggs {
+        match agg {
+            Aggregation::Bucket(bucket) => validate_pipeline_aggregations_with_parent(
+                &bucket.sub_aggregation,
+                Some(&bucket.bucket_agg),
+            )?,
+            Aggregation::Metric(_) => {}
+            Aggregation::Pipeline(pipeline) if pipeline.is_sibling() => {
+                let buckets_path = pipeline.buckets_paths()[0];
+                let (_, value_path, bucket_agg) = get_multi_bucket_aggregation(aggs, buckets_path)?;
+                validate_buckets_path(&bucket_agg.sub_aggregation, value_path)?;
+            }
+            Aggregation::Pipeline(pipeline) => {
+                match parent {
+                    Some(BucketAggregationType::Histogram(_))
+                    | Some(BucketAggregationType::DateHistogram(_)) => {}
+                    Some(BucketAggregationType::Filter(_)) | None => {
+                        return Err(TantivyError::InvalidArgument(format!(
+                            "pipeline aggregation {} must be a sub aggregation of a multi bucket \
+                             aggregation",
+                            name
+                        )));
+                    }
+                    Some(_) if pipeline.is_sequential() => {
+                        return Err(TantivyError::InvalidArgument(format!(
+                            "pipeline aggregation {} must be a sub aggregation of a histogram or \
+                             date_histogram aggregation",
+                            name
+                        )));
+                    }
+                    Some(_) => {}
+                }
+                if let PipelineAggregation::MovingAvg(moving_avg) = pipeline {
+                    moving_avg.validate()?;
+                }
+                for buckets_path in pipeline.buckets_paths() {
+                    validate_buckets_path(aggs, buckets_path)?;
+                }
+            }
+        }
+    }
+    let parent_pipelines = aggs
+        .iter()
+        .filter_map(|(name, agg)| match agg {
+            Aggregation::Pipeline(pipeline) if pipeline.is_sequential() => {
+                Some((name.as_str(), pipeline))
+            }
+            _ => None,
+        })
+        .collect();
+    order_by_dependencies(parent_pipelines)?;
+    Ok(())
+}
+
+/// Splits a sibling `buckets_path` into the name of the multi bucket aggregation it starts with
+/// and the remaining path, and returns them together with the multi bucket aggregation.
+fn get_multi_bucket_aggregation<'a, 'b>(
+    aggs: &'a Aggregations,
+    buckets_path: &'b str,
+) -> crate::Result<(&'b str, &'b str, &'a BucketAggregation)> {
+    let (bucket_agg_name, remaining_path) = buckets_path.split_once('>').ok_or_else(|| {
+        TantivyError::InvalidArgument(format!(
+            "buckets_path {} must start with the name of a sibling multi bucket aggregation \
+             followed by '>'",
+            buckets_path
+        ))
+    })?;
+    match aggs.get(bucket_agg_name) {
+        Some(Aggregation::Bucket(bucket))
+            if !matches!(bucket.bucket_agg, BucketAggregationType::Filter(_)) =>
+        {
+            Ok((bucket_agg_name, remaining_path, bucket))
+        }
+        _ => Err(TantivyError::InvalidArgument(format!(
+            "could not find multi bucket aggregation {} of buckets_path {}",
+            bucket_agg_name, buckets_path
+        ))),
+    }
+}
+
+/// Checks that the `buckets_path` points to a value of the buckets with the sub aggregations
+/// `aggs`.
+fn validate_buckets_path(aggs: &Aggregations, buckets_path: &str) -> crate::Result<()> {
+    if buckets_path == COUNT_PATH || buckets_path == KEY_PATH {
+        return Ok(());
+    }
+    let (agg_name, _agg_property) = get_agg_name_and_property(buckets_path);
+    match aggs.get(agg_name) {
+        Some(Aggregation::Metric(MetricAggregation::TopHits(_))) => {
+            Err(TantivyError::InvalidArgument(format!(
+                "buckets_path {} can't point to a top_hits aggregation",
+                buckets_path
+            )))
+        }
+        Some(Aggregation::Metric(_)) => Ok(()),
+        Some(Aggregation::Pipeline(pipeline)) if pipeline.has_value() => Ok(()),
+        _ => Err(TantivyError::InvalidArgument(format!(
+            "could not find metric or pipeline aggregation with name {} of buckets_path {}",
+            agg_name, buckets_path
+        ))),
+    }
+}
+
+/// Orders the pipeline aggregations, so that every aggregation comes after the pipeline
+/// aggregations its `buckets_path` points to.
+fn order_by_dependencies<'a>(
+    mut pending: Vec<(&'a str, &'a PipelineAggregation)>,
+) -> crate::Result<Vec<(&'a str, &'a PipelineAggregation)>> {
+    let mut ordered = Vec::with_capacity(pending.len());
+    while !pending.is_empty() {
+        let ready_pos = pending.iter().position(|(_, pipeline)| {
+            pipeline.buckets_paths().iter().all(|buckets_path| {
+                let (agg_name, _) = get_agg_name_and_property(buckets_path);
+                pending.iter().all(|(name, _)| *name != agg_name)
+            })
+        });
+        match ready_pos {
+            Some(pos) => ordered.push(pending.remove(pos)),
+            None => {
+                return Err(TantivyError::InvalidArgument(format!(
+                    "pipeline aggregations {:?} have cyclic buckets_path dependencies",
+                    pending.iter().map(|(name, _)| *name).collect::<Vec<_>>()
+                )));
+            }
+        }
+    }
+    Ok(ordered)
+}
+
+/// Computes the pipeline aggregations of the request tree and adds them to the final results.
+///
+/// The pipeline aggregations below a bucket aggregation are computed bottom up, so that a pipeline
+/// aggregation can reference the results of the pipeline aggregations in its sub aggregations.
+pub(crate) fn apply_pipeline_aggregations(
+    aggs: &Aggregations,
+    results: &mut AggregationResults,
+) -> crate::Result<()> {
+    if !has_pipeline_aggregations(aggs) {
+        return Ok(());
+    }
+    for (name, agg) in aggs {
+        if let Aggregation::Bucket(bucket) = agg {
+            if let Some(AggregationResult::BucketResult(bucket_result)) = results.0.get_mut(name) {
+                apply_to_bucket_result(&bucket.sub_aggregation, bucket_result)?;
+            }
+        }
+    }
+    for (name, agg) in aggs {
+        if let Aggregation::Pipeline(pipeline) = agg {
+            if pipeline.is_sibling() {
+                let result = compute_sibling_pipeline(aggs, results, pipeline)?;
+                results
+                    .0
+                    .insert(name.to_string(), AggregationResult::PipelineResult(result));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn has_pipeline_aggregations(aggs: &Aggregations) -> bool {
+    aggs.values().any(|agg| match agg {
+        Aggregation::Bucket(bucket) => has_pipeline_aggregations(&bucket.sub_aggregation),
+        Aggregation::Metric(_) => false,
+        Aggregation::Pipeline(_) => true,
+    })
+}
+
+fn apply_to_bucket_result(
+    sub_aggs: &Aggregations,
+    bucket_result: &mut BucketResult,
+) -> crate::Result<()> {
+    match bucket_result {
+        BucketResult::Range { buckets } => apply_to_bucket_entries(sub_aggs, buckets),
+        BucketResult::Histogram { buckets } => apply_to_bucket_entries(sub_aggs, buckets),
+        BucketResult::Terms { buckets, .. } => apply_to_buckets(sub_aggs, buckets),
+        BucketResult::SignificantTerms { buckets, .. } => apply_to_buckets(sub_aggs, buckets),
+        BucketResult::GeoGrid { buckets } => apply_to_buckets(sub_aggs, buckets),
+        BucketResult::Composite { buckets, .. } => apply_to_buckets(sub_aggs, buckets),
+        BucketResult::Filters { buckets } => apply_to_bucket_entries(sub_aggs, buckets),
+        BucketResult::Filter(bucket) => {
+            apply_pipeline_aggregations(sub_aggs, bucket.sub_aggregation_mut())
+        }
+    }
+}
+
+fn apply_to_bucket_entries<B: PipelineBucket>(
+    sub_aggs: &Aggregations,
+    bucket_entries: &mut BucketEntries<B>,
+) -> crate::Result<()> {
+    match bucket_entries {
+        BucketEntries::Vec(buckets) => apply_to_buckets(sub_aggs, buckets),
+        BucketEntries::HashMap(buckets) => {
+            for bucket in buckets.values_mut() {
+                apply_pipeline_aggregations(sub_aggs, bucket.sub_aggregation_mut())?;
+            }
+            let has_parent_pipelines = sub_aggs.values().any(
+                |agg| matches!(agg, Aggregation::Pipeline(pipeline) if !pipeline.is_sibling()),
+            );
+            if has_parent_pipelines {
+                return Err(TantivyError::InvalidArgument(
+                    "parent pipeline aggregations are not supported on keyed buckets".to_string(),
+                ));
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Computes the pipeline aggregations in the sub aggregations of the buckets and then the parent
+/// pipeline aggregations on the buckets.
+///
+/// The parent pipeline aggregations are applied in this order: the ones computing a value per
+/// bucket, `bucket_selector` and finally `bucket_sort`.
+fn apply_to_buckets<B: PipelineBucket>(
+    sub_aggs: &Aggregations,
+    buckets: &mut Vec<B>,
+) -> crate::Result<()> {
+    for bucket in buckets.iter_mut() {
+        apply_pipeline_aggregations(sub_aggs, bucket.sub_aggregation_mut())?;
+    }
+
+    let mut sequential = vec![];
+    let mut selectors = vec![];
+    let mut sorts = vec![];
+    for (name, agg) in sub_aggs {
+        match agg {
+            Aggregation::Pipeline(PipelineAggregation::BucketSelector(selector)) => {
+                selectors.push(selector)
+            }
+            Aggregation::Pipeline(PipelineAggregation::BucketSort(sort)) => sorts.push(sort),
+            Aggregation::Pipeline(pipeline) if pipeline.is_sequential() => {
+                sequential.push((name.as_str(), pipeline))
+            }
+            _ => {}
+        }
+    }
+
+    for (name, pipeline) in order_by_dependencies(sequential)? {
+        let buckets_path = pipeline.buckets_paths()[0];
+        let values = buckets
+            .iter()
+            .map(|bucket| bucket.value(buckets_path))
+            .collect::<crate::Result<Vec<_>>>()?;
+        let results: Vec<PipelineResult> = match pipeline {
+            PipelineAggregation::Derivative(derivative) => derivative
+                .compute(&values)
+                .into_iter()
+                .map(|value| PipelineResult::Derivative(value.into()))
+                .collect(),
+            PipelineAggregation::CumulativeSum(cumulative_sum) => cumulative_sum
+                .compute(&values)
+                .into_iter()
+                .map(|value| PipelineResult::CumulativeSum(value.into()))
+                .collect(),
+            PipelineAggregation::MovingAvg(moving_avg) => moving_avg
+                .compute(&values)
+                .into_iter()
+                .map(|value| PipelineResult::MovingAvg(value.into()))
+                .collect(),
+            _ => unreachable!("only sequential pipeline aggregations are collected"),
+        };
+        for (bucket, result) in buckets.iter_mut().zip(results) {
+            bucket
+                .sub_aggregation_mut()
+                .0
+                .insert(name.to_string(), AggregationResult::PipelineResult(result));
+        }
+    }
+
+    for selector in selectors {
+        let keep = buckets
+            .iter()
+            .map(|bucket| Ok(selector.matches(bucket.value(&selector.buckets_path)?)))
+            .collect::<crate::Result<Vec<bool>>>()?;
+        let mut keep = keep.into_iter();
+        buckets.retain(|_| keep.next().unwrap_or(false));
+    }
+
+    for sort in sorts {
+        sort.sort_buckets(buckets)?;
+    }
+    Ok(())
+}
+
+fn compute_sibling_pipeline(
+    aggs: &Aggregations,
+    results: &AggregationResults,
+    pipeline: &PipelineAggregation,
+) -> crate::Result<PipelineResult> {
+    let buckets_path = pipeline.buckets_paths()[0];
+    let (bucket_agg_name, value_path, _) = get_multi_bucket_aggregation(aggs, buckets_path)?;
+    let bucket_result = match results.0.get(bucket_agg_name) {
+        Some(AggregationResult::BucketResult(bucket_result)) => bucket_result,
+        _ => {
+            return Err(TantivyError::InternalError(format!(
+                "Can't find bucket aggregation {:?} in results",
+                bucket_agg_name
+            )))
+        }
+    };
+    let values = sibling_bucket_values(bucket_result, value_path)?;
+    match pipeline {
+        PipelineAggregation::AvgBucket(avg_bucket) => Ok(PipelineResult::AvgBucket(
+            avg_bucket.compute(&values).into(),
+        )),
+        PipelineAggregation::MaxBucket(max_bucket) => {
+            Ok(PipelineResult::MaxBucket(max_bucket.compute(&values)))
+        }
+        _ => unreachable!("only sibling pipeline aggregations are computed on siblings"),
+    }
+}
+
+/// Returns the keys and the values the `buckets_path` points to of all buckets of a multi bucket
+/// result.
+fn sibling_bucket_values(
+    bucket_result: &BucketResult,
+    buckets_path: &str,
+) -> crate::Result<Vec<(Option<Key>, Option<f64>)>> {
+    fn values_of<'a, B: PipelineBucket + 'a>(
+        buckets: impl Iterator<Item = (Option<Key>, &'a B)>,
+        buckets_path: &str,
+    ) -> crate::Result<Vec<(Option<Key>, Option<f64>)>> {
+        buckets
+            .map(|(key, bucket)| Ok((key, bucket.value(buckets_path)?)))
+            .collect()
+    }
+    fn entries_of<'a, B: PipelineBucket>(
+        bucket_entries: &'a BucketEntries<B>,
+    ) -> Box<dyn Iterator<Item = (Option<Key>, &'a B)> + 'a> {
+        match bucket_entries {
+            BucketEntries::Vec(buckets) => {
+                Box::new(buckets.iter().map(|bucket| (bucket.key().cloned(), bucket)))
+            }
+            BucketEntries::HashMap(buckets) => Box::new(
+                buckets
+                    .iter()
+                    .map(|(key, bucket)| (Some(Key::Str(key.to_string())), bucket)),
+            ),
+        }
+    }
+    match bucket_result {
+        BucketResult::Range { buckets } => values_of(entries_of(buckets), buckets_path),
+        BucketResult::Histogram { buckets } => values_of(entries_of(buckets), buckets_path),
+        BucketResult::Filters { buckets } => values_of(entries_of(buckets), buckets_path),
+        BucketResult::Terms { buckets, .. } => values_of(
+            buckets
+                .iter()
+                .map(|bucket| (Some(bucket.key.clone()), bucket)),
+            buckets_path,
+        ),
+        BucketResult::SignificantTerms { buckets, .. } => values_of(
+            buckets
+                .iter()
+                .map(|bucket| (Some(bucket.key.clone()), bucket)),
+            buckets_path,
+        ),
+        BucketResult::GeoGrid { buckets } => values_of(
+            buckets
+                .iter()
+                .map(|bucket| (Some(bucket.key.clone()), bucket)),
+            buckets_path,
+        ),
+        BucketResult::Composite { buckets, .. } => {
+            values_of(buckets.iter().map(|bucket| (None, bucket)), buckets_path)
+        }
+        BucketResult::Filter(_) => Err(TantivyError::InvalidArgument(
+            "sibling pipeline aggregations require a multi bucket aggregation".to_string(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use crate::aggregation::agg_req::Aggregations;
+    use crate::aggregation::tests::{exec_request, get_test_index_from_values_and_terms};
+    use crate::aggregation::AggregationCollector;
+    use crate::query::AllQuery;
+    use crate::Index;
+
+    fn get_test_index(merge_segments: bool) -> crate::Result<Index> {
+        let segment_and_values = vec![
+            vec![(1.0, "a".to_string()), (2.0, "a".to_string())],
+            vec![(11.0, "b".to_string()), (12.0, "b".to_string())],
+            vec![(15.0, "b".to_string()), (31.0, "c".to_string())],
+            vec![(32.0, "c".to_string()), (33.0, "c".to_string())],
+        ];
+        get_test_index_from_values_and_terms(merge_segments, &segment_and_values)
+    }
+
+    fn exec_json_request(req: Value, merge_segments: bool) -> crate::Result<Value> {
+        let agg_req: Aggregations = serde_json::from_value(req).unwrap();
+        exec_request(agg_req, &get_test_index(merge_segments)?)
+    }
+
+    fn histogram_req(sub_aggs: Value) -> Value {
+        json!({
+            "histogram": {
+                "histogram": {
+                    "field": "score_f64",
+                    "interval": 10.0,
+                },
+                "aggs": sub_aggs
+            }
+        })
+    }
+
+    #[test]
+    fn pipeline_sequential_aggregations_test() -> crate::Result<()> {
+        for merge_segments in [false, true] {
+            let res = exec_json_request(
+                histogram_req(json!({
+                    "sum_score": { "sum": { "field": "score_f64" } },
+                    "sum_deriv": { "derivative": { "buckets_path": "sum_score" } },
+                    "count_deriv": {
+                        "derivative": { "buckets_path": "_count", "gap_policy": "insert_zeros" }
+                    },
+                    "deriv_of_cumsum": { "derivative": { "buckets_path": "cumsum" } },
+                    "cumsum": { "cumulative_sum": { "buckets_path": "_count" } },
+                    "avg": { "moving_avg": { "buckets_path": "_count", "window": 2 } },
+                })),
+                merge_segments,
+            )?;
+
+            let buckets = &res["histogram"]["buckets"];
+            assert_eq!(buckets[0]["sum_score"]["value"], 3.0);
+            assert_eq!(buckets[1]["sum_score"]["value"], 38.0);
+            assert_eq!(buckets[2]["sum_score"]["value"], 0.0);
+            assert_eq!(buckets[3]["sum_score"]["value"], 96.0);
+
+            assert_eq!(buckets[0]["sum_deriv"]["value"], Value::Null);
+            assert_eq!(buckets[1]["sum_deriv"]["value"], 35.0);
+            assert_eq!(buckets[2]["sum_deriv"]["value"], -38.0);
+            assert_eq!(buckets[3]["sum_deriv"]["value"], 96.0);
+
+            assert_eq!(buckets[1]["count_deriv"]["value"], 1.0);
+            assert_eq!(buckets[2]["count_deriv"]["value"], -3.0);
+            assert_eq!(buckets[3]["count_deriv"]["value"], 3.0);
+
+            assert_eq!(buckets[0]["cumsum"]["value"], 2.0);
+            assert_eq!(buckets[1]["cumsum"]["value"], 5.0);
+            assert_eq!(buckets[2]["cumsum"]["value"], 5.0);
+            assert_eq!(buckets[3]["cumsum"]["value"], 8.0);
+            assert_eq!(buckets[3]["deriv_of_cumsum"]["value"], 3.0);
+
+            assert_eq!(buckets[0]["avg"]["value"], Value::Null);
+            assert_eq!(buckets[1]["avg"]["value"], 2.0);
+            assert_eq!(buckets[2]["avg"]["value"], 2.5);
+            assert_eq!(buckets[3]["avg"]["value"], 1.5);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn pipeline_derivative_skips_gaps_test() -> crate::Result<()> {
+        let res = exec_json_request(
+            histogram_req(json!({
+                "avg_score": { "avg": { "field": "score_f64" } },
+                "avg_deriv": { "derivative": { "buckets_path": "avg_score" } },
+            })),
+            true,
+        )?;
+        let buckets = &res["histogram"]["buckets"];
+        assert_eq!(buckets[2]["avg_score"]["value"], Value::Null);
+        assert_eq!(buckets[2]["avg_deriv"]["value"], Value::Null);
+        // The derivative is computed to the last bucket with a value.
+        assert_eq!(buckets[3]["avg_deriv"]["value"], 32.0 - 38.0 / 3.0);
+        Ok(())
+    }
+
+    #[test]
+    fn pipeline_bucket_selector_and_sort_test() -> crate::Result<()> {
+        let res = exec_json_request(
+            json!({
+                "my_texts": {
+                    "terms": { "field": "string_id" },
+                    "aggs": {
+                        "max_score": { "max": { "field": "score_f64" } },
+                        "selector": {
+                            "bucket_selector": { "buckets_path": "max_score", "gt": 10.0 }
+                        },
+                        "sort": {
+                            "bucket_sort": {
+                                "sort": [{ "max_score": { "order": "desc" } }],
+                                "size": 1
+                            }
+                        }
+                    }
+                }
+            }),
+            false,
+        )?;
+        assert_eq!(
+            res["my_texts"]["buckets"],
+            json!([{ "key": "c", "doc_count": 3, "max_score": { "value": 33.0 } }])
+        );
+
+        let res = exec_json_request(
+            histogram_req(json!({
+                "sort": { "bucket_sort": { "sort": ["_count", "_key"], "from": 1 } },
+            })),
+            false,
+        )?;
+        let keys: Vec<&Value> = res["histogram"]["buckets"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|bucket| &bucket["key"])
+            .collect();
+        assert_eq!(keys, vec![&json!(0.0), &json!(10.0), &json!(30.0)]);
+        Ok(())
+    }
+
+    #[test]
+    fn pipeline_sibling_aggregations_test() -> crate::Result<()> {
+        let res = exec_json_request(
+            json!({
+                "outer": {
+                    "histogram": { "field": "score_f64", "interval": 20.0 },
+                    "aggs": {
+                        "histogram": {
+                            "histogram": { "field": "score_f64", "interval": 10.0 },
+                            "aggs": { "sum_score": { "sum": { "field": "score_f64" } } }
+                        },
+                        "max_sum": { "max_bucket": { "buckets_path": "histogram>sum_score" } },
+                        "sort": {
+                            "bucket_sort": { "sort": [{ "max_sum": { "order": "desc" } }] }
+                        }
+                    }
+                },
+                "avg_count": { "avg_bucket": { "buckets_path": "outer>_count" } },
+                "max_sum": { "max_bucket": { "buckets_path": "outer>max_sum" } },
+            }),
+            false,
+        )?;
+
+        assert_eq!(res["avg_count"]["value"], 4.0);
+        assert_eq!(res["max_sum"], json!({ "value": 96.0, "keys": [20.0] }));
+        let buckets = &res["outer"]["buckets"];
+        assert_eq!(buckets[0]["key"], 20.0);
+        assert_eq!(
+            buckets[0]["max_sum"],
+            json!({ "value": 96.0, "keys": [30.0] })
+        );
+        assert_eq!(buckets[1]["key"], 0.0);
+        assert_eq!(
+            buckets[1]["max_sum"],
+            json!({ "value": 38.0, "keys": [10.0] })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn pipeline_distributed_collector_test() -> crate::Result<()> {
+        use crate::aggregation::DistributedAggregationCollector;
+
+        let agg_req: Aggregations = serde_json::from_value(histogram_req(json!({
+            "cumsum": { "cumulative_sum": { "buckets_path": "_count" } },
+        })))
+        .unwrap();
+        let index = get_test_index(false)?;
+        let searcher = index.reader()?.searcher();
+        let collector = DistributedAggregationCollector::from_aggs(agg_req.clone(), None);
+        let intermediate_res = searcher.search(&AllQuery, &collector)?;
+        let res = intermediate_res.into_final_bucket_result(agg_req)?;
+        let res: Value = serde_json::to_value(res)?;
+        assert_eq!(res["histogram"]["buckets"][3]["cumsum"]["value"], 8.0);
+        Ok(())
+    }
+
+    #[test]
+    fn pipeline_invalid_requests_test() -> crate::Result<()> {
+        let index = get_test_index(true)?;
+        let exec_invalid = |req: Value| {
+            let agg_req: Aggregations = serde_json::from_value(req).unwrap();
+            let collector = AggregationCollector::from_aggs(agg_req, None);
+            let searcher = index.reader().unwrap().searcher();
+            searcher
+                .search(&AllQuery, &collector)
+                .unwrap_err()
+                .to_string()
+        };
+
+        let err = exec_invalid(json!({
+            "deriv": { "derivative": { "buckets_path": "_count" } }
+        }));
+        assert!(err.contains("must be a sub aggregation of a multi bucket aggregation"));
+
+        let err = exec_invalid(json!({
+            "my_texts": {
+                "terms": { "field": "string_id" },
+                "aggs": { "deriv": { "derivative": { "buckets_path": "_count" } } }
+            }
+        }));
+        assert!(err.contains("histogram or date_histogram"));
+
+        let err = exec_invalid(histogram_req(json!({
+            "deriv": { "derivative": { "buckets_path": "missing" } }
+        })));
+        assert!(err.contains("could not find metric or pipeline aggregation"));
+
+        let err = exec_invalid(histogram_req(json!({
+            "deriv_1": { "derivative": { "buckets_path": "deriv_2" } },
+            "deriv_2": { "derivative": { "buckets_path": "deriv_1" } },
+        })));
+        assert!(err.contains("cyclic buckets_path dependencies"));
+
+        let err = exec_invalid(json!({
+            "avg_count": { "avg_bucket": { "buckets_path": "_count" } }
+        }));
+        assert!(err.contains("must start with the name of a sibling multi bucket aggregation"));
+
+        let err = exec_invalid(json!({
+            "my_texts": { "terms": { "field": "string_id" } },
+            "avg_key": { "avg_bucket": { "buckets_path": "my_texts>_key" } }
+        }));
+        assert!(err.contains("requires buckets with numeric keys"));
+        Ok(())
+    }
+}
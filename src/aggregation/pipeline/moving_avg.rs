@@ -0,0 +1,81 @@
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+
+use super::GapPolicy;
+use crate::TantivyError;
+
+const DEFAULT_MOVING_AVG_WINDOW: usize = 5;
+
+/// A parent pipeline aggregation, which computes for every bucket of its parent histogram or
+/// date histogram the average of the values of the `window` previous buckets. The value of the
+/// bucket itself is not included, so the first bucket has no moving average.
+///
+/// With the default [`GapPolicy::Skip`], buckets without a value have no moving average and are
+/// not part of the window.
+/// See [`SingleMetricResult`](crate::aggregation::metric::SingleMetricResult) for return value.
+///
+/// # JSON Format
+/// ```json
+/// {
+///     "moving_avg": {
+///         "buckets_path": "sales",
+///         "window": 7
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MovingAvgAggregation {
+    /// The path to the value in the buckets to average.
+    pub buckets_path: String,
+    /// The number of previous buckets to average. Defaults to 5.
+    #[serde(default = "default_moving_avg_window")]
+    pub window: usize,
+    /// How buckets without a value are handled.
+    #[serde(default)]
+    pub gap_policy: GapPolicy,
+}
+
+fn default_moving_avg_window() -> usize {
+    DEFAULT_MOVING_AVG_WINDOW
+}
+
+impl MovingAvgAggregation {
+    /// Create new MovingAvgAggregation from a buckets path.
+    pub fn from_buckets_path(buckets_path: String) -> Self {
+        MovingAvgAggregation {
+            buckets_path,
+            window: DEFAULT_MOVING_AVG_WINDOW,
+            gap_policy: GapPolicy::default(),
+        }
+    }
+
+    pub(crate) fn validate(&self) -> crate::Result<()> {
+        if self.window == 0 {
+            return Err(TantivyError::InvalidArgument(
+                "moving_avg window must be greater than 0".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn compute(&self, values: &[Option<f64>]) -> Vec<Option<f64>> {
+        let mut window: VecDeque<f64> = VecDeque::with_capacity(self.window);
+        values
+            .iter()
+            .map(|value| {
+                let value = self.gap_policy.apply(*value)?;
+                let avg = if window.is_empty() {
+                    None
+                } else {
+                    Some(window.iter().sum::<f64>() / window.len() as f64)
+                };
+                if window.len() == self.window {
+                    window.pop_front();
+                }
+                window.push_back(value);
+                avg
+            })
+            .collect()
+    }
+}
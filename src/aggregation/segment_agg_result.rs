@@ -5,21 +5,34 @@
 
 use std::fmt::Debug;
 use std::rc::Rc;
-use std::sync::atomic::AtomicU32;
+use std::sync::atomic::{AtomicU32, AtomicU64};
+
+use fastfield_codecs::Column;
 
 use super::agg_req::MetricAggregation;
 use super::agg_req_with_accessor::{
-    AggregationsWithAccessor, BucketAggregationWithAccessor, MetricAggregationWithAccessor,
+    AggregationsWithAccessor, BucketAggregationWithAccessor, FastFieldAccessor,
+    MetricAggregationWithAccessor,
+};
+use super::bucket::{
+    SegmentCompositeCollector, SegmentDateHistogramCollector, SegmentFilterCollector,
+    SegmentGeoDistanceCollector, SegmentGeoGridCollector, SegmentHistogramCollector,
+    SegmentRangeCollector, SegmentSignificantTermsCollector, SegmentTermCollector,
+};
+use super::collector::AggregationLimits;
+use super::geo::GeoPointAccessor;
+use super::intermediate_agg_result::{
+    IntermediateAggregationResults, IntermediateBucketResult, IntermediateMetricResult,
 };
-use super::bucket::{SegmentHistogramCollector, SegmentRangeCollector, SegmentTermCollector};
-use super::collector::MAX_BUCKET_COUNT;
-use super::intermediate_agg_result::{IntermediateAggregationResults, IntermediateBucketResult};
 use super::metric::{
-    AverageAggregation, SegmentAverageCollector, SegmentStatsCollector, StatsAggregation,
+    AverageAggregation, CardinalityAggregation, IntermediateAverage, SegmentAverageCollector,
+    SegmentCardinalityCollector, SegmentGeoBoundsCollector, SegmentGeoCentroidCollector,
+    SegmentPercentilesCollector, SegmentStatsCollector, SegmentStatsType, SegmentTopHitsCollector,
+    StatsAggregation, TopHitsAccessor,
 };
-use super::VecWithNames;
+use super::{AggregationError, VecWithNames};
 use crate::aggregation::agg_req::BucketAggregationType;
-use crate::{DocId, TantivyError};
+use crate::DocId;
 
 pub(crate) const DOC_BLOCK_SIZE: usize = 64;
 pub(crate) type DocBlock = [DocId; DOC_BLOCK_SIZE];
@@ -69,7 +82,16 @@ impl SegmentAggregationResultsCollector {
         } else {
             None
         };
-        let metrics = self.metrics.map(VecWithNames::from_other);
+        let metrics = if let Some(metrics) = self.metrics {
+            let entries = metrics
+                .into_iter()
+                .zip(agg_with_accessor.metrics.values())
+                .map(|((key, metric), acc)| Ok((key, metric.into_intermediate_metric_result(acc)?)))
+                .collect::<crate::Result<Vec<(String, _)>>>()?;
+            Some(VecWithNames::from_entries(entries))
+        } else {
+            None
+        };
 
         Ok(IntermediateAggregationResults { metrics, buckets })
     }
@@ -113,6 +135,26 @@ impl SegmentAggregationResultsCollector {
         })
     }
 
+    /// Returns the estimated memory in bytes of a collector which has not collected any document
+    /// yet, i.e. of a copy of this collector when it is the blueprint of a bucket.
+    ///
+    /// The buckets created while collecting are accounted for when they are created.
+    pub(crate) fn memory_estimate(&self) -> u64 {
+        let metrics_memory: u64 = self
+            .metrics
+            .iter()
+            .flat_map(|metrics| metrics.values())
+            .map(|metric| metric.memory_estimate())
+            .sum();
+        let buckets_memory: u64 = self
+            .buckets
+            .iter()
+            .flat_map(|buckets| buckets.values())
+            .map(|bucket| bucket.memory_estimate())
+            .sum();
+        std::mem::size_of::<Self>() as u64 + metrics_memory + buckets_memory
+    }
+
     #[inline]
     pub(crate) fn collect(
         &mut self,
@@ -165,9 +207,54 @@ impl SegmentAggregationResultsCollector {
 pub(crate) enum SegmentMetricResultCollector {
     Average(SegmentAverageCollector),
     Stats(SegmentStatsCollector),
+    Cardinality(Box<SegmentCardinalityCollector>),
+    Percentiles(Box<SegmentPercentilesCollector>),
+    TopHits(Box<SegmentTopHitsCollector>),
+    GeoBounds(SegmentGeoBoundsCollector),
+    GeoCentroid(SegmentGeoCentroidCollector),
 }
 
 impl SegmentMetricResultCollector {
+    pub fn into_intermediate_metric_result(
+        self,
+        agg_with_accessor: &MetricAggregationWithAccessor,
+    ) -> crate::Result<IntermediateMetricResult> {
+        match self {
+            SegmentMetricResultCollector::Average(collector) => Ok(
+                IntermediateMetricResult::Average(IntermediateAverage::from_collector(collector)),
+            ),
+            SegmentMetricResultCollector::Stats(collector) => {
+                let stats = collector.stats;
+                Ok(match collector.collecting_for {
+                    SegmentStatsType::Stats => IntermediateMetricResult::Stats(stats),
+                    SegmentStatsType::Min => IntermediateMetricResult::Min(stats),
+                    SegmentStatsType::Max => IntermediateMetricResult::Max(stats),
+                    SegmentStatsType::Sum => IntermediateMetricResult::Sum(stats),
+                    SegmentStatsType::Count => IntermediateMetricResult::ValueCount(stats),
+                })
+            }
+            SegmentMetricResultCollector::Cardinality(collector) => {
+                Ok(IntermediateMetricResult::Cardinality(
+                    collector.into_intermediate_cardinality(agg_with_accessor)?,
+                ))
+            }
+            SegmentMetricResultCollector::Percentiles(collector) => {
+                Ok(IntermediateMetricResult::Percentiles(collector.percentiles))
+            }
+            SegmentMetricResultCollector::TopHits(collector) => {
+                Ok(IntermediateMetricResult::TopHits(
+                    collector.into_intermediate_top_hits(top_hits_accessor(agg_with_accessor))?,
+                ))
+            }
+            SegmentMetricResultCollector::GeoBounds(collector) => {
+                Ok(IntermediateMetricResult::GeoBounds(collector.bounds))
+            }
+            SegmentMetricResultCollector::GeoCentroid(collector) => {
+                Ok(IntermediateMetricResult::GeoCentroid(collector.centroid))
+            }
+        }
+    }
+
     pub fn from_req_and_validate(req: &MetricAggregationWithAccessor) -> crate::Result<Self> {
         match &req.metric {
             MetricAggregation::Average(AverageAggregation { field: _ }) => {
@@ -177,23 +264,142 @@ impl SegmentMetricResultCollector {
             }
             MetricAggregation::Stats(StatsAggregation { field: _ }) => {
                 Ok(SegmentMetricResultCollector::Stats(
-                    SegmentStatsCollector::from_req(req.field_type),
+                    SegmentStatsCollector::from_req(req.field_type, SegmentStatsType::Stats),
                 ))
             }
+            MetricAggregation::Min(_) => Ok(SegmentMetricResultCollector::Stats(
+                SegmentStatsCollector::from_req(req.field_type, SegmentStatsType::Min),
+            )),
+            MetricAggregation::Max(_) => Ok(SegmentMetricResultCollector::Stats(
+                SegmentStatsCollector::from_req(req.field_type, SegmentStatsType::Max),
+            )),
+            MetricAggregation::Sum(_) => Ok(SegmentMetricResultCollector::Stats(
+                SegmentStatsCollector::from_req(req.field_type, SegmentStatsType::Sum),
+            )),
+            MetricAggregation::ValueCount(_) => Ok(SegmentMetricResultCollector::Stats(
+                SegmentStatsCollector::from_req(req.field_type, SegmentStatsType::Count),
+            )),
+            MetricAggregation::Cardinality(CardinalityAggregation { field: _ }) => {
+                Ok(SegmentMetricResultCollector::Cardinality(Box::new(
+                    SegmentCardinalityCollector::from_req(req.field_type),
+                )))
+            }
+            MetricAggregation::Percentiles(percentiles_req) => {
+                percentiles_req.validate()?;
+                Ok(SegmentMetricResultCollector::Percentiles(Box::new(
+                    SegmentPercentilesCollector::from_req(req.field_type),
+                )))
+            }
+            MetricAggregation::PercentileRanks(percentile_ranks_req) => {
+                percentile_ranks_req.validate()?;
+                Ok(SegmentMetricResultCollector::Percentiles(Box::new(
+                    SegmentPercentilesCollector::from_req(req.field_type),
+                )))
+            }
+            MetricAggregation::TopHits(top_hits_req) => {
+                Ok(SegmentMetricResultCollector::TopHits(Box::new(
+                    SegmentTopHitsCollector::from_req(top_hits_req, top_hits_accessor(req))?,
+                )))
+            }
+            MetricAggregation::GeoBounds(_) => Ok(SegmentMetricResultCollector::GeoBounds(
+                SegmentGeoBoundsCollector::from_req(),
+            )),
+            MetricAggregation::GeoCentroid(_) => Ok(SegmentMetricResultCollector::GeoCentroid(
+                SegmentGeoCentroidCollector::from_req(),
+            )),
         }
     }
+    /// Returns the estimated memory in bytes of the collector, including the maximum size of the
+    /// sketches and of the top hits.
+    pub(crate) fn memory_estimate(&self) -> u64 {
+        let heap_memory = match self {
+            SegmentMetricResultCollector::Cardinality(collector) => collector.memory_estimate(),
+            SegmentMetricResultCollector::Percentiles(collector) => collector.memory_estimate(),
+            SegmentMetricResultCollector::TopHits(collector) => collector.memory_estimate(),
+            SegmentMetricResultCollector::Average(_)
+            | SegmentMetricResultCollector::Stats(_)
+            | SegmentMetricResultCollector::GeoBounds(_)
+            | SegmentMetricResultCollector::GeoCentroid(_) => 0,
+        };
+        std::mem::size_of::<Self>() as u64 + heap_memory
+    }
+
     pub(crate) fn collect_block(&mut self, doc: &[DocId], metric: &MetricAggregationWithAccessor) {
         match self {
             SegmentMetricResultCollector::Average(avg_collector) => {
-                avg_collector.collect_block(doc, &*metric.accessor);
+                match numeric_accessor(metric) {
+                    FastFieldAccessor::Multi(accessor) => {
+                        avg_collector.collect_block_multi(doc, accessor)
+                    }
+                    accessor => avg_collector.collect_block(doc, single_accessor(accessor)),
+                }
             }
             SegmentMetricResultCollector::Stats(stats_collector) => {
-                stats_collector.collect_block(doc, &*metric.accessor);
+                match numeric_accessor(metric) {
+                    FastFieldAccessor::Multi(accessor) => {
+                        stats_collector.collect_block_multi(doc, accessor)
+                    }
+                    accessor => stats_collector.collect_block(doc, single_accessor(accessor)),
+                }
+            }
+            SegmentMetricResultCollector::Cardinality(cardinality_collector) => {
+                cardinality_collector.collect_block(
+                    doc,
+                    metric
+                        .accessor
+                        .as_ref()
+                        .expect("missing fast field accessor"),
+                );
+            }
+            SegmentMetricResultCollector::Percentiles(percentiles_collector) => {
+                match numeric_accessor(metric) {
+                    FastFieldAccessor::Multi(accessor) => {
+                        percentiles_collector.collect_block_multi(doc, accessor)
+                    }
+                    accessor => percentiles_collector.collect_block(doc, single_accessor(accessor)),
+                }
+            }
+            SegmentMetricResultCollector::TopHits(top_hits_collector) => {
+                top_hits_collector.collect_block(doc, top_hits_accessor(metric));
+            }
+            SegmentMetricResultCollector::GeoBounds(geo_bounds_collector) => {
+                geo_bounds_collector.collect_block(doc, geo_point_accessor(metric));
+            }
+            SegmentMetricResultCollector::GeoCentroid(geo_centroid_collector) => {
+                geo_centroid_collector.collect_block(doc, geo_point_accessor(metric));
             }
         }
     }
 }
 
+#[inline]
+fn numeric_accessor(metric: &MetricAggregationWithAccessor) -> &FastFieldAccessor {
+    metric
+        .accessor
+        .as_ref()
+        .expect("missing fast field accessor")
+}
+
+#[inline]
+fn single_accessor(accessor: &FastFieldAccessor) -> &dyn Column<u64> {
+    accessor
+        .as_single()
+        .expect("unexpected fast field cardinality")
+}
+
+#[inline]
+fn top_hits_accessor(metric: &MetricAggregationWithAccessor) -> &TopHitsAccessor {
+    metric.top_hits.as_ref().expect("missing top hits accessor")
+}
+
+#[inline]
+fn geo_point_accessor(metric: &MetricAggregationWithAccessor) -> &GeoPointAccessor {
+    metric
+        .geo_point
+        .as_ref()
+        .expect("missing geo point accessor")
+}
+
 /// SegmentBucketAggregationResultCollectors will have specialized buckets for collection inside
 /// segments.
 /// The typical structure of Map<Key, Bucket> is not suitable during collection for performance
@@ -202,7 +408,13 @@ impl SegmentMetricResultCollector {
 pub(crate) enum SegmentBucketResultCollector {
     Range(SegmentRangeCollector),
     Histogram(Box<SegmentHistogramCollector>),
+    DateHistogram(Box<SegmentDateHistogramCollector>),
     Terms(Box<SegmentTermCollector>),
+    SignificantTerms(Box<SegmentSignificantTermsCollector>),
+    Filter(Box<SegmentFilterCollector>),
+    Composite(Box<SegmentCompositeCollector>),
+    GeoDistance(Box<SegmentGeoDistanceCollector>),
+    GeoGrid(Box<SegmentGeoGridCollector>),
 }
 
 impl SegmentBucketResultCollector {
@@ -214,12 +426,30 @@ impl SegmentBucketResultCollector {
             SegmentBucketResultCollector::Terms(terms) => {
                 terms.into_intermediate_bucket_result(agg_with_accessor)
             }
+            SegmentBucketResultCollector::SignificantTerms(significant_terms) => {
+                significant_terms.into_intermediate_bucket_result(agg_with_accessor)
+            }
             SegmentBucketResultCollector::Range(range) => {
                 range.into_intermediate_bucket_result(agg_with_accessor)
             }
             SegmentBucketResultCollector::Histogram(histogram) => {
                 histogram.into_intermediate_bucket_result(agg_with_accessor)
             }
+            SegmentBucketResultCollector::DateHistogram(date_histogram) => {
+                date_histogram.into_intermediate_bucket_result(agg_with_accessor)
+            }
+            SegmentBucketResultCollector::Filter(filter) => {
+                filter.into_intermediate_bucket_result(agg_with_accessor)
+            }
+            SegmentBucketResultCollector::Composite(composite) => {
+                composite.into_intermediate_bucket_result(agg_with_accessor)
+            }
+            SegmentBucketResultCollector::GeoDistance(geo_distance) => {
+                geo_distance.into_intermediate_bucket_result(agg_with_accessor)
+            }
+            SegmentBucketResultCollector::GeoGrid(geo_grid) => {
+                geo_grid.into_intermediate_bucket_result(agg_with_accessor)
+            }
         }
     }
 
@@ -230,11 +460,17 @@ impl SegmentBucketResultCollector {
                     terms_req,
                     &req.sub_aggregation,
                     req.field_type,
-                    req.accessor
-                        .as_multi()
-                        .expect("unexpected fast field cardinality"),
                 )?,
             ))),
+            BucketAggregationType::SignificantTerms(significant_terms) => {
+                Ok(Self::SignificantTerms(Box::new(
+                    SegmentSignificantTermsCollector::from_req_and_validate(
+                        significant_terms,
+                        &req.sub_aggregation,
+                        req.field_type,
+                    )?,
+                )))
+            }
             BucketAggregationType::Range(range_req) => {
                 Ok(Self::Range(SegmentRangeCollector::from_req_and_validate(
                     range_req,
@@ -249,13 +485,70 @@ impl SegmentBucketResultCollector {
                     &req.sub_aggregation,
                     req.field_type,
                     req.accessor
-                        .as_single()
-                        .expect("unexpected fast field cardinality"),
+                        .as_ref()
+                        .and_then(|accessor| accessor.min_max_value()),
+                    &req.bucket_count,
+                )?,
+            ))),
+            BucketAggregationType::DateHistogram(date_histogram) => Ok(Self::DateHistogram(
+                Box::new(SegmentDateHistogramCollector::from_req_and_validate(
+                    date_histogram,
+                    &req.sub_aggregation,
+                )?),
+            )),
+            BucketAggregationType::Filter(_) | BucketAggregationType::Filters(_) => {
+                Ok(Self::Filter(Box::new(
+                    SegmentFilterCollector::from_req_and_validate(req, &req.bucket_count)?,
+                )))
+            }
+            BucketAggregationType::Composite(composite) => Ok(Self::Composite(Box::new(
+                SegmentCompositeCollector::from_req_and_validate(composite, req)?,
+            ))),
+            BucketAggregationType::GeoDistance(geo_distance) => Ok(Self::GeoDistance(Box::new(
+                SegmentGeoDistanceCollector::from_req_and_validate(
+                    geo_distance,
+                    &req.sub_aggregation,
+                    &req.bucket_count,
+                )?,
+            ))),
+            BucketAggregationType::GeohashGrid(geohash_grid) => Ok(Self::GeoGrid(Box::new(
+                SegmentGeoGridCollector::from_req_and_validate(
+                    geohash_grid.cells()?,
+                    &req.sub_aggregation,
+                )?,
+            ))),
+            BucketAggregationType::GeoTileGrid(geotile_grid) => Ok(Self::GeoGrid(Box::new(
+                SegmentGeoGridCollector::from_req_and_validate(
+                    geotile_grid.cells()?,
+                    &req.sub_aggregation,
                 )?,
             ))),
         }
     }
 
+    /// Returns the estimated memory in bytes of a collector which has not collected any document
+    /// yet, including the buckets allocated upfront and their sub aggregations.
+    pub(crate) fn memory_estimate(&self) -> u64 {
+        let collector_memory = match self {
+            SegmentBucketResultCollector::Range(range) => range.memory_estimate(),
+            SegmentBucketResultCollector::Histogram(histogram) => histogram.memory_estimate(),
+            SegmentBucketResultCollector::DateHistogram(date_histogram) => {
+                date_histogram.memory_estimate()
+            }
+            SegmentBucketResultCollector::Terms(terms) => terms.memory_estimate(),
+            SegmentBucketResultCollector::SignificantTerms(significant_terms) => {
+                significant_terms.memory_estimate()
+            }
+            SegmentBucketResultCollector::Filter(filter) => filter.memory_estimate(),
+            SegmentBucketResultCollector::Composite(composite) => composite.memory_estimate(),
+            SegmentBucketResultCollector::GeoDistance(geo_distance) => {
+                geo_distance.memory_estimate()
+            }
+            SegmentBucketResultCollector::GeoGrid(geo_grid) => geo_grid.memory_estimate(),
+        };
+        std::mem::size_of::<Self>() as u64 + collector_memory
+    }
+
     #[inline]
     pub(crate) fn collect_block(
         &mut self,
@@ -270,9 +563,27 @@ impl SegmentBucketResultCollector {
             SegmentBucketResultCollector::Histogram(histogram) => {
                 histogram.collect_block(doc, bucket_with_accessor, force_flush)?;
             }
+            SegmentBucketResultCollector::DateHistogram(date_histogram) => {
+                date_histogram.collect_block(doc, bucket_with_accessor, force_flush)?;
+            }
             SegmentBucketResultCollector::Terms(terms) => {
                 terms.collect_block(doc, bucket_with_accessor, force_flush)?;
             }
+            SegmentBucketResultCollector::SignificantTerms(significant_terms) => {
+                significant_terms.collect_block(doc, bucket_with_accessor, force_flush)?;
+            }
+            SegmentBucketResultCollector::Filter(filter) => {
+                filter.collect_block(doc, bucket_with_accessor, force_flush)?;
+            }
+            SegmentBucketResultCollector::Composite(composite) => {
+                composite.collect_block(doc, bucket_with_accessor, force_flush)?;
+            }
+            SegmentBucketResultCollector::GeoDistance(geo_distance) => {
+                geo_distance.collect_block(doc, bucket_with_accessor, force_flush)?;
+            }
+            SegmentBucketResultCollector::GeoGrid(geo_grid) => {
+                geo_grid.collect_block(doc, bucket_with_accessor, force_flush)?;
+            }
         }
         Ok(())
     }
@@ -283,29 +594,68 @@ pub(crate) struct BucketCount {
     /// The counter which is shared between the aggregations for one request.
     pub(crate) bucket_count: Rc<AtomicU32>,
     pub(crate) max_bucket_count: u32,
+    /// The estimated memory of the buckets in bytes, which is shared between the aggregations for
+    /// one request.
+    pub(crate) memory_consumption: Rc<AtomicU64>,
+    pub(crate) memory_limit: u64,
 }
 
 impl Default for BucketCount {
     fn default() -> Self {
-        Self {
-            bucket_count: Default::default(),
-            max_bucket_count: MAX_BUCKET_COUNT,
-        }
+        Self::from_limits(AggregationLimits::default())
     }
 }
 
 impl BucketCount {
-    pub(crate) fn validate_bucket_count(&self) -> crate::Result<()> {
+    pub(crate) fn from_limits(limits: AggregationLimits) -> Self {
+        Self {
+            bucket_count: Default::default(),
+            max_bucket_count: limits.max_bucket_count,
+            memory_consumption: Default::default(),
+            memory_limit: limits.memory_limit,
+        }
+    }
+    /// Validates the number of buckets and their memory consumption against the limits.
+    pub(crate) fn validate_limits(&self) -> crate::Result<()> {
         if self.get_count() > self.max_bucket_count {
-            return Err(TantivyError::InvalidArgument(
-                "Aborting aggregation because too many buckets were created".to_string(),
-            ));
+            return Err(AggregationError::BucketLimitExceeded {
+                limit: self.max_bucket_count,
+                current: self.get_count(),
+            }
+            .into());
+        }
+        if self.get_memory_consumed() > self.memory_limit {
+            return Err(AggregationError::MemoryExceeded {
+                limit: self.memory_limit,
+                current: self.get_memory_consumed(),
+            }
+            .into());
         }
         Ok(())
     }
     pub(crate) fn add_count(&self, count: u32) {
+        // Saturate, so that a huge count can't wrap around and pass the limit check
+        let _ = self.bucket_count.fetch_update(
+            std::sync::atomic::Ordering::Relaxed,
+            std::sync::atomic::Ordering::Relaxed,
+            |bucket_count| Some(bucket_count.saturating_add(count)),
+        );
+    }
+    pub(crate) fn remove_count(&self, count: u32) {
         self.bucket_count
-            .fetch_add(count as u32, std::sync::atomic::Ordering::Relaxed);
+            .fetch_sub(count, std::sync::atomic::Ordering::Relaxed);
+    }
+    pub(crate) fn add_memory_consumed(&self, num_bytes: u64) {
+        self.memory_consumption
+            .fetch_add(num_bytes, std::sync::atomic::Ordering::Relaxed);
+    }
+    pub(crate) fn remove_memory_consumed(&self, num_bytes: u64) {
+        self.memory_consumption
+            .fetch_sub(num_bytes, std::sync::atomic::Ordering::Relaxed);
+    }
+    pub(crate) fn get_memory_consumed(&self) -> u64 {
+        self.memory_consumption
+            .load(std::sync::atomic::Ordering::Relaxed)
     }
     pub(crate) fn get_count(&self) -> u32 {
         self.bucket_count.load(std::sync::atomic::Ordering::Relaxed)
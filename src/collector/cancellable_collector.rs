@@ -0,0 +1,121 @@
+use crate::collector::{Collector, SegmentCollector};
+use crate::query::Weight;
+use crate::{CancellationToken, DocId, Score, SegmentOrdinal, SegmentReader};
+
+/// The fruit of a search that may have been interrupted by a [`CancellationToken`].
+///
+/// See [`Searcher::search_with_cancellation`](crate::Searcher::search_with_cancellation).
+#[derive(Debug)]
+pub struct PartialFruit<TFruit> {
+    /// The fruit merged from the documents collected before the search was interrupted.
+    pub fruit: TFruit,
+    /// True if the search was interrupted, in which case `fruit` only covers part of the
+    /// matching documents.
+    pub timed_out: bool,
+}
+
+/// Wraps a collector so that the collection stops once the cancellation token is cancelled.
+///
+/// The token is checked before each segment. The segment is then collected by the wrapped
+/// collector, with a [`SegmentReader`] carrying the token, so that the weights check it while
+/// building their scorers and once per block of visited documents. This keeps the
+/// [`Collector::collect_segment`] implementation of the wrapped collector, e.g. the block-WAND
+/// pruning of [`TopDocs`](crate::collector::TopDocs).
+pub(crate) struct CancellableCollector<'a, TCollector> {
+    collector: &'a TCollector,
+    cancellation_token: CancellationToken,
+}
+
+impl<'a, TCollector: Collector> CancellableCollector<'a, TCollector> {
+    pub fn new(
+        collector: &'a TCollector,
+        cancellation_token: CancellationToken,
+    ) -> CancellableCollector<'a, TCollector> {
+        CancellableCollector {
+            collector,
+            cancellation_token,
+        }
+    }
+}
+
+impl<'a, TCollector: Collector> Collector for CancellableCollector<'a, TCollector> {
+    type Fruit = PartialFruit<TCollector::Fruit>;
+
+    type Child = CancellableSegmentCollector<TCollector::Child>;
+
+    fn for_segment(
+        &self,
+        segment_local_id: SegmentOrdinal,
+        segment: &SegmentReader,
+    ) -> crate::Result<Self::Child> {
+        let segment_collector = self.collector.for_segment(segment_local_id, segment)?;
+        Ok(CancellableSegmentCollector {
+            segment_collector,
+            cancellation_token: self.cancellation_token.clone(),
+        })
+    }
+
+    fn requires_scoring(&self) -> bool {
+        self.collector.requires_scoring()
+    }
+
+    fn merge_fruits(
+        &self,
+        segment_fruits: Vec<(<TCollector::Child as SegmentCollector>::Fruit, bool)>,
+    ) -> crate::Result<PartialFruit<TCollector::Fruit>> {
+        let mut timed_out = false;
+        let mut fruits = Vec::with_capacity(segment_fruits.len());
+        for (segment_fruit, segment_timed_out) in segment_fruits {
+            timed_out |= segment_timed_out;
+            fruits.push(segment_fruit);
+        }
+        let fruit = self.collector.merge_fruits(fruits)?;
+        Ok(PartialFruit { fruit, timed_out })
+    }
+
+    fn collect_segment(
+        &self,
+        weight: &dyn Weight,
+        segment_ord: u32,
+        reader: &SegmentReader,
+    ) -> crate::Result<<Self::Child as SegmentCollector>::Fruit> {
+        if self.cancellation_token.is_cancelled() {
+            let segment_collector = self.collector.for_segment(segment_ord, reader)?;
+            return Ok((segment_collector.harvest(), true));
+        }
+        let reader = reader.with_cancellation_token(self.cancellation_token.clone());
+        let segment_fruit = self
+            .collector
+            .collect_segment(weight, segment_ord, &reader)?;
+        // The weights stop silently once the token is cancelled.
+        Ok((segment_fruit, self.cancellation_token.is_cancelled()))
+    }
+}
+
+/// Segment collector associated with the [`CancellableCollector`].
+///
+/// The documents collected through it are not interrupted, the token is only checked when
+/// harvesting.
+pub(crate) struct CancellableSegmentCollector<TSegmentCollector> {
+    segment_collector: TSegmentCollector,
+    cancellation_token: CancellationToken,
+}
+
+impl<TSegmentCollector: SegmentCollector> SegmentCollector
+    for CancellableSegmentCollector<TSegmentCollector>
+{
+    type Fruit = (TSegmentCollector::Fruit, bool);
+
+    fn collect(&mut self, doc: DocId, score: Score) {
+        self.segment_collector.collect(doc, score);
+    }
+
+    fn is_done(&self) -> bool {
+        self.segment_collector.is_done()
+    }
+
+    fn harvest(self) -> Self::Fruit {
+        let timed_out = self.cancellation_token.is_cancelled();
+        (self.segment_collector.harvest(), timed_out)
+    }
+}
@@ -0,0 +1,502 @@
+use std::collections::HashMap;
+use std::io;
+use std::ops::Range;
+
+use super::facet_collector::FacetSegmentCollector;
+use crate::collector::{Collector, FacetCollector, FacetCounts, SegmentCollector};
+use crate::fastfield::FacetReader;
+use crate::schema::{Facet, Field};
+use crate::termdict::TermOrdinal;
+use crate::{DocId, Score, SegmentOrdinal, SegmentReader};
+
+/// Collector computing the facet counts of several facet fields at once,
+/// with drill-sideways semantics.
+///
+/// Each facet field is a dimension of the faceted navigation. A dimension can be
+/// drilled down, by calling `.add_drill_down(...)`, to only keep the documents
+/// that have one of the drilled down facets, or one of their descendants.
+///
+/// The wrapped collector only receives the documents matching all of the drill downs,
+/// while the facet counts of a dimension are computed as if its own drill downs
+/// were not applied. This makes it possible for a faceted navigation UI to display
+/// the alternatives to the facets selected by the user with a single search.
+///
+/// The query passed to the searcher should therefore not contain the drill downs.
+///
+/// ```rust
+/// use tantivy::collector::{Count, DrillSidewaysCollector};
+/// use tantivy::query::AllQuery;
+/// use tantivy::schema::{Facet, FacetOptions, Schema, TEXT};
+/// use tantivy::{doc, Index};
+///
+/// # fn main() -> tantivy::Result<()> {
+/// let mut schema_builder = Schema::builder();
+/// let title = schema_builder.add_text_field("title", TEXT);
+/// let lang = schema_builder.add_facet_field("lang", FacetOptions::default());
+/// let category = schema_builder.add_facet_field("category", FacetOptions::default());
+/// let schema = schema_builder.build();
+/// let index = Index::create_in_ram(schema);
+///
+/// let mut index_writer = index.writer(3_000_000)?;
+/// index_writer.add_document(doc!(
+///     title => "The Name of the Wind",
+///     lang => Facet::from("/lang/en"),
+///     category => Facet::from("/category/fiction/fantasy")
+/// ))?;
+/// index_writer.add_document(doc!(
+///     title => "Dune",
+///     lang => Facet::from("/lang/en"),
+///     category => Facet::from("/category/fiction/sci-fi")
+/// ))?;
+/// index_writer.add_document(doc!(
+///     title => "La Vénus d'Ille",
+///     lang => Facet::from("/lang/fr"),
+///     category => Facet::from("/category/fiction/horror")
+/// ))?;
+/// index_writer.add_document(doc!(
+///     title => "The Diary of a Young Girl",
+///     lang => Facet::from("/lang/en"),
+///     category => Facet::from("/category/biography")
+/// ))?;
+/// index_writer.commit()?;
+///
+/// let searcher = index.reader()?.searcher();
+///
+/// let mut collector = DrillSidewaysCollector::new(Count);
+/// collector.add_facet(lang, "/lang");
+/// collector.add_facet(category, "/category");
+/// collector.add_drill_down(lang, "/lang/en");
+/// let (count, facet_counts) = searcher.search(&AllQuery, &collector)?;
+///
+/// // Only the english books match the drill down.
+/// assert_eq!(count, 3);
+///
+/// // The languages are counted as if no language had been selected...
+/// let langs: Vec<(&Facet, u64)> = facet_counts.for_field(lang).unwrap().get("/lang").collect();
+/// assert_eq!(langs, vec![
+///     (&Facet::from("/lang/en"), 3),
+///     (&Facet::from("/lang/fr"), 1)
+/// ]);
+///
+/// // ... while the categories only count the english books.
+/// let categories: Vec<(&Facet, u64)> = facet_counts
+///     .for_field(category)
+///     .unwrap()
+///     .get("/category")
+///     .collect();
+/// assert_eq!(categories, vec![
+///     (&Facet::from("/category/biography"), 1),
+///     (&Facet::from("/category/fiction"), 2)
+/// ]);
+/// # Ok(())
+/// # }
+/// ```
+pub struct DrillSidewaysCollector<TCollector> {
+    collector: TCollector,
+    dimensions: Vec<Dimension>,
+}
+
+/// A facet field, with the facets to count and the drilled down facets.
+struct Dimension {
+    field: Field,
+    facet_collector: FacetCollector,
+    drill_downs: Vec<Facet>,
+}
+
+impl<TCollector: Collector> DrillSidewaysCollector<TCollector> {
+    /// Creates a drill sideways collector, passing the documents matching
+    /// all of the drill downs to `collector`.
+    pub fn new(collector: TCollector) -> DrillSidewaysCollector<TCollector> {
+        DrillSidewaysCollector {
+            collector,
+            dimensions: Vec::new(),
+        }
+    }
+
+    fn dimension_mut(&mut self, field: Field) -> &mut Dimension {
+        let dimension_ord = match self
+            .dimensions
+            .iter()
+            .position(|dimension| dimension.field == field)
+        {
+            Some(dimension_ord) => dimension_ord,
+            None => {
+                self.dimensions.push(Dimension {
+                    field,
+                    facet_collector: FacetCollector::for_field(field),
+                    drill_downs: Vec::new(),
+                });
+                self.dimensions.len() - 1
+            }
+        };
+        &mut self.dimensions[dimension_ord]
+    }
+
+    /// Adds a facet of `field` that we want to record counts for.
+    ///
+    /// See [`FacetCollector::add_facet`].
+    pub fn add_facet<T>(&mut self, field: Field, facet_from: T)
+    where Facet: From<T> {
+        self.dimension_mut(field).facet_collector.add_facet(facet_from);
+    }
+
+    /// Only keeps the documents having the facet `facet_from`, or one of its descendants,
+    /// in `field`.
+    ///
+    /// Drilling down several times on the same field keeps the documents having any of
+    /// the drilled down facets.
+    pub fn add_drill_down<T>(&mut self, field: Field, facet_from: T)
+    where Facet: From<T> {
+        self.dimension_mut(field)
+            .drill_downs
+            .push(Facet::from(facet_from));
+    }
+}
+
+impl<TCollector: Collector> Collector for DrillSidewaysCollector<TCollector> {
+    type Fruit = (TCollector::Fruit, DrillSidewaysCounts);
+
+    type Child = DrillSidewaysSegmentCollector<TCollector::Child>;
+
+    fn for_segment(
+        &self,
+        segment_local_id: SegmentOrdinal,
+        reader: &SegmentReader,
+    ) -> crate::Result<Self::Child> {
+        let segment_collector = self.collector.for_segment(segment_local_id, reader)?;
+        let dimensions = self
+            .dimensions
+            .iter()
+            .map(|dimension| {
+                let drill_down = if dimension.drill_downs.is_empty() {
+                    None
+                } else {
+                    let facet_reader = reader.facet_reader(dimension.field)?;
+                    let drill_down_ords = drill_down_ords(&facet_reader, &dimension.drill_downs)?;
+                    Some((facet_reader, drill_down_ords))
+                };
+                let facet_segment_collector = dimension
+                    .facet_collector
+                    .for_segment(segment_local_id, reader)?;
+                Ok(SegmentDimension {
+                    drill_down,
+                    facet_segment_collector,
+                })
+            })
+            .collect::<crate::Result<Vec<_>>>()?;
+        Ok(DrillSidewaysSegmentCollector {
+            segment_collector,
+            dimensions,
+            facet_ords_buf: Vec::new(),
+        })
+    }
+
+    fn requires_scoring(&self) -> bool {
+        self.collector.requires_scoring()
+    }
+
+    fn merge_fruits(
+        &self,
+        segment_fruits: Vec<(
+            <TCollector::Child as SegmentCollector>::Fruit,
+            Vec<FacetCounts>,
+        )>,
+    ) -> crate::Result<Self::Fruit> {
+        let mut fruits = Vec::with_capacity(segment_fruits.len());
+        let mut dimensions_facet_counts: Vec<Vec<FacetCounts>> = self
+            .dimensions
+            .iter()
+            .map(|_| Vec::with_capacity(segment_fruits.len()))
+            .collect();
+        for (fruit, segment_facet_counts) in segment_fruits {
+            fruits.push(fruit);
+            for (dimension_facet_counts, facet_counts) in
+                dimensions_facet_counts.iter_mut().zip(segment_facet_counts)
+            {
+                dimension_facet_counts.push(facet_counts);
+            }
+        }
+        let fruit = self.collector.merge_fruits(fruits)?;
+        let facet_counts = self
+            .dimensions
+            .iter()
+            .zip(dimensions_facet_counts)
+            .map(|(dimension, dimension_facet_counts)| {
+                let facet_counts = dimension
+                    .facet_collector
+                    .merge_fruits(dimension_facet_counts)?;
+                Ok((dimension.field, facet_counts))
+            })
+            .collect::<crate::Result<HashMap<_, _>>>()?;
+        Ok((fruit, DrillSidewaysCounts { facet_counts }))
+    }
+}
+
+/// Returns the ranges of facet ordinals of the drilled down facets and of their descendants.
+fn drill_down_ords(
+    facet_reader: &FacetReader,
+    drill_downs: &[Facet],
+) -> io::Result<Vec<Range<TermOrdinal>>> {
+    let mut drill_down_ords = Vec::with_capacity(drill_downs.len());
+    for facet in drill_downs {
+        let mut range = facet_reader
+            .facet_dict()
+            .range()
+            .ge(facet.encoded_str().as_bytes());
+        if !facet.is_root() {
+            let mut facet_after: String = facet.encoded_str().to_owned();
+            facet_after.push('\u{1}');
+            range = range.lt(facet_after.as_bytes());
+        }
+        let mut facet_streamer = range.into_stream()?;
+        if facet_streamer.advance() {
+            let start = facet_streamer.term_ord();
+            let mut end = start + 1;
+            while facet_streamer.advance() {
+                end = facet_streamer.term_ord() + 1;
+            }
+            drill_down_ords.push(start..end);
+        }
+    }
+    Ok(drill_down_ords)
+}
+
+pub struct DrillSidewaysSegmentCollector<TSegmentCollector> {
+    segment_collector: TSegmentCollector,
+    dimensions: Vec<SegmentDimension>,
+    facet_ords_buf: Vec<u64>,
+}
+
+struct SegmentDimension {
+    // `None` if the dimension is not drilled down.
+    drill_down: Option<(FacetReader, Vec<Range<TermOrdinal>>)>,
+    facet_segment_collector: FacetSegmentCollector,
+}
+
+impl SegmentDimension {
+    fn matches(&self, doc: DocId, facet_ords_buf: &mut Vec<u64>) -> bool {
+        let (facet_reader, drill_down_ords) = match &self.drill_down {
+            Some(drill_down) => drill_down,
+            None => return true,
+        };
+        facet_reader.facet_ords(doc, facet_ords_buf);
+        facet_ords_buf.iter().any(|facet_ord| {
+            drill_down_ords
+                .iter()
+                .any(|drill_down_range| drill_down_range.contains(facet_ord))
+        })
+    }
+}
+
+impl<TSegmentCollector: SegmentCollector> SegmentCollector
+    for DrillSidewaysSegmentCollector<TSegmentCollector>
+{
+    type Fruit = (TSegmentCollector::Fruit, Vec<FacetCounts>);
+
+    fn collect(&mut self, doc: DocId, score: Score) {
+        // A document missing the drill downs of a single dimension still counts
+        // for the facets of that dimension.
+        let mut missed_dimension_ord = None;
+        for (dimension_ord, dimension) in self.dimensions.iter().enumerate() {
+            if !dimension.matches(doc, &mut self.facet_ords_buf) {
+                if missed_dimension_ord.is_some() {
+                    return;
+                }
+                missed_dimension_ord = Some(dimension_ord);
+            }
+        }
+        match missed_dimension_ord {
+            Some(dimension_ord) => {
+                self.dimensions[dimension_ord]
+                    .facet_segment_collector
+                    .collect(doc, score);
+            }
+            None => {
+                self.segment_collector.collect(doc, score);
+                for dimension in &mut self.dimensions {
+                    dimension.facet_segment_collector.collect(doc, score);
+                }
+            }
+        }
+    }
+
+    fn harvest(self) -> Self::Fruit {
+        let facet_counts = self
+            .dimensions
+            .into_iter()
+            .map(|dimension| dimension.facet_segment_collector.harvest())
+            .collect();
+        (self.segment_collector.harvest(), facet_counts)
+    }
+}
+
+/// Facet counts of each of the fields of a [`DrillSidewaysCollector`].
+pub struct DrillSidewaysCounts {
+    facet_counts: HashMap<Field, FacetCounts>,
+}
+
+impl DrillSidewaysCounts {
+    /// Returns the facet counts of `field`, or `None` if the field
+    /// was not added to the collector.
+    pub fn for_field(&self, field: Field) -> Option<&FacetCounts> {
+        self.facet_counts.get(&field)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DrillSidewaysCollector, DrillSidewaysCounts};
+    use crate::collector::{Count, TopDocs};
+    use crate::query::{AllQuery, QueryParser};
+    use crate::schema::{Facet, FacetOptions, Field, Schema, STORED, TEXT};
+    use crate::{DocAddress, Index, Score, Searcher};
+
+    fn facet_counts(
+        facet_counts: &DrillSidewaysCounts,
+        field: Field,
+        facet: &str,
+    ) -> Vec<(String, u64)> {
+        facet_counts
+            .for_field(field)
+            .unwrap()
+            .get(facet)
+            .map(|(facet, count)| (facet.to_string(), count))
+            .collect()
+    }
+
+    fn titles(searcher: &Searcher, title: Field, top_docs: &[(Score, DocAddress)]) -> Vec<String> {
+        let mut titles: Vec<String> = top_docs
+            .iter()
+            .map(|(_score, doc_address)| {
+                let doc = searcher.doc(*doc_address).unwrap();
+                doc.get_first(title).unwrap().as_text().unwrap().to_string()
+            })
+            .collect();
+        titles.sort();
+        titles
+    }
+
+    #[test]
+    fn test_drill_sideways_collector() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let title = schema_builder.add_text_field("title", TEXT | STORED);
+        let lang = schema_builder.add_facet_field("lang", FacetOptions::default());
+        let category = schema_builder.add_facet_field("category", FacetOptions::default());
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(
+            title => "wind book",
+            lang => Facet::from("/lang/en"),
+            category => Facet::from("/category/fiction/fantasy"),
+        ))?;
+        index_writer.add_document(doc!(
+            title => "dune book",
+            lang => Facet::from("/lang/en"),
+            category => Facet::from("/category/fiction/sci-fi"),
+        ))?;
+        index_writer.commit()?;
+        index_writer.add_document(doc!(
+            title => "venus book",
+            lang => Facet::from("/lang/fr"),
+            category => Facet::from("/category/fiction/horror"),
+        ))?;
+        index_writer.add_document(doc!(
+            title => "diary book",
+            lang => Facet::from("/lang/en"),
+            lang => Facet::from("/lang/nl"),
+            category => Facet::from("/category/biography"),
+        ))?;
+        index_writer.add_document(doc!(title => "untagged book"))?;
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+
+        // Without drill downs, all of the fields are counted on the matching documents.
+        let mut collector = DrillSidewaysCollector::new(Count);
+        collector.add_facet(lang, "/lang");
+        collector.add_facet(category, "/category");
+        let (count, counts) = searcher.search(&AllQuery, &collector)?;
+        assert_eq!(count, 5);
+        assert_eq!(
+            facet_counts(&counts, lang, "/lang"),
+            vec![
+                ("/lang/en".to_string(), 3),
+                ("/lang/fr".to_string(), 1),
+                ("/lang/nl".to_string(), 1),
+            ]
+        );
+        assert_eq!(
+            facet_counts(&counts, category, "/category"),
+            vec![
+                ("/category/biography".to_string(), 1),
+                ("/category/fiction".to_string(), 3),
+            ]
+        );
+
+        // Each dimension is counted as if its own drill downs were not applied.
+        let mut collector = DrillSidewaysCollector::new(TopDocs::with_limit(10));
+        collector.add_facet(lang, "/lang");
+        collector.add_facet(category, "/category/fiction");
+        collector.add_drill_down(lang, "/lang/en");
+        collector.add_drill_down(category, "/category/fiction");
+        let query = QueryParser::for_index(&index, vec![title]).parse_query("book")?;
+        let (top_docs, counts) = searcher.search(&query, &collector)?;
+        assert_eq!(
+            titles(&searcher, title, &top_docs),
+            vec!["dune book", "wind book"]
+        );
+        assert_eq!(
+            facet_counts(&counts, lang, "/lang"),
+            vec![("/lang/en".to_string(), 2), ("/lang/fr".to_string(), 1)]
+        );
+        assert_eq!(
+            facet_counts(&counts, category, "/category/fiction"),
+            vec![
+                ("/category/fiction/fantasy".to_string(), 1),
+                ("/category/fiction/sci-fi".to_string(), 1),
+            ]
+        );
+
+        // Drilling down several times on a field keeps the documents having any of the facets.
+        let mut collector = DrillSidewaysCollector::new(TopDocs::with_limit(10));
+        collector.add_facet(category, "/category");
+        collector.add_drill_down(lang, "/lang/fr");
+        collector.add_drill_down(lang, "/lang/nl");
+        let (top_docs, counts) = searcher.search(&AllQuery, &collector)?;
+        assert_eq!(
+            titles(&searcher, title, &top_docs),
+            vec!["diary book", "venus book"]
+        );
+        assert_eq!(
+            facet_counts(&counts, category, "/category"),
+            vec![
+                ("/category/biography".to_string(), 1),
+                ("/category/fiction".to_string(), 1),
+            ]
+        );
+        assert_eq!(facet_counts(&counts, lang, "/lang"), vec![]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_drill_sideways_collector_unknown_facet() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let lang = schema_builder.add_facet_field("lang", FacetOptions::default());
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(lang => Facet::from("/lang/en")))?;
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+
+        let mut collector = DrillSidewaysCollector::new(Count);
+        collector.add_facet(lang, "/lang");
+        collector.add_drill_down(lang, "/lang/de");
+        let (count, counts) = searcher.search(&AllQuery, &collector)?;
+        assert_eq!(count, 0);
+        assert_eq!(
+            facet_counts(&counts, lang, "/lang"),
+            vec![("/lang/en".to_string(), 1)]
+        );
+        assert!(counts.for_field(Field::from_field_id(1)).is_none());
+        Ok(())
+    }
+}
@@ -183,6 +183,10 @@ where
         }
     }
 
+    fn is_done(&self) -> bool {
+        self.segment_collector.is_done()
+    }
+
     fn harvest(self) -> <TSegmentCollector as SegmentCollector>::Fruit {
         self.segment_collector.harvest()
     }
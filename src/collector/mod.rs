@@ -6,7 +6,9 @@
 //! Your fruit could for instance be :
 //! - [the count of matching documents](crate::collector::Count)
 //! - [the top 10 documents, by relevancy or by a fast field](crate::collector::TopDocs)
+//! - [the top 10 groups of documents sharing a fast field value](crate::collector::TopGroups)
 //! - [facet counts](FacetCollector)
+//! - [facet counts of several fields, for faceted navigation](DrillSidewaysCollector)
 //!
 //! At some point in your code, you will trigger the actual search operation by calling
 //! [`Searcher::search()`](crate::Searcher::search).
@@ -81,8 +83,11 @@
 //!
 //! See the `custom_collector` example.
 
+use std::ops::ControlFlow;
+
 use downcast_rs::impl_downcast;
 
+use crate::core::CancellationCheck;
 use crate::{DocId, Score, SegmentOrdinal, SegmentReader};
 
 mod count_collector;
@@ -95,9 +100,10 @@ mod multi_collector;
 pub use self::multi_collector::{FruitHandle, MultiCollector, MultiFruit};
 
 mod top_collector;
+pub(crate) use self::top_collector::{TopCollector, TopSegmentCollector};
 
 mod top_score_collector;
-pub use self::top_score_collector::TopDocs;
+pub use self::top_score_collector::{TopDocs, TotalHits};
 
 mod custom_score_top_collector;
 pub use self::custom_score_top_collector::{CustomScorer, CustomSegmentScorer};
@@ -105,8 +111,20 @@ pub use self::custom_score_top_collector::{CustomScorer, CustomSegmentScorer};
 mod tweak_score_top_collector;
 pub use self::tweak_score_top_collector::{ScoreSegmentTweaker, ScoreTweaker};
 
+mod rescore_top_collector;
+pub use self::rescore_top_collector::{QueryRescorer, QuerySegmentRescorer};
+
+mod sort_key_top_collector;
+pub use self::sort_key_top_collector::{MissingValues, SortKey, SortValue};
+
+mod top_groups_collector;
+pub use self::top_groups_collector::{Group, TopGroups};
+
 mod facet_collector;
 pub use self::facet_collector::{FacetCollector, FacetCounts};
+
+mod drill_sideways_collector;
+pub use self::drill_sideways_collector::{DrillSidewaysCollector, DrillSidewaysCounts};
 use crate::query::Weight;
 
 mod docset_collector;
@@ -115,6 +133,10 @@ pub use self::docset_collector::DocSetCollector;
 mod filter_collector_wrapper;
 pub use self::filter_collector_wrapper::FilterCollector;
 
+mod cancellable_collector;
+pub(crate) use self::cancellable_collector::CancellableCollector;
+pub use self::cancellable_collector::PartialFruit;
+
 /// `Fruit` is the type for the result of our collection.
 /// e.g. `usize` for the `Count` collector.
 pub trait Fruit: Send + downcast_rs::Downcast {}
@@ -164,6 +186,9 @@ pub trait Collector: Sync + Send {
     ) -> crate::Result<Self::Fruit>;
 
     /// Created a segment collector and
+    ///
+    /// The collection of the segment stops as soon as the segment collector
+    /// [is done](SegmentCollector::is_done), or once the search is cancelled.
     fn collect_segment(
         &self,
         weight: &dyn Weight,
@@ -171,30 +196,38 @@ pub trait Collector: Sync + Send {
         reader: &SegmentReader,
     ) -> crate::Result<<Self::Child as SegmentCollector>::Fruit> {
         let mut segment_collector = self.for_segment(segment_ord as u32, reader)?;
+        if segment_collector.is_done() {
+            return Ok(segment_collector.harvest());
+        }
+        let mut cancellation_check = CancellationCheck::new(reader.cancellation_token());
 
         match (reader.alive_bitset(), self.requires_scoring()) {
             (Some(alive_bitset), true) => {
-                weight.for_each(reader, &mut |doc, score| {
+                weight.for_each_until(reader, &mut |doc, score| {
                     if alive_bitset.is_alive(doc) {
                         segment_collector.collect(doc, score);
                     }
+                    control_flow(&segment_collector, &mut cancellation_check)
                 })?;
             }
             (Some(alive_bitset), false) => {
-                weight.for_each_no_score(reader, &mut |doc| {
+                weight.for_each_no_score_until(reader, &mut |doc| {
                     if alive_bitset.is_alive(doc) {
                         segment_collector.collect(doc, 0.0);
                     }
+                    control_flow(&segment_collector, &mut cancellation_check)
                 })?;
             }
             (None, true) => {
-                weight.for_each(reader, &mut |doc, score| {
+                weight.for_each_until(reader, &mut |doc, score| {
                     segment_collector.collect(doc, score);
+                    control_flow(&segment_collector, &mut cancellation_check)
                 })?;
             }
             (None, false) => {
-                weight.for_each_no_score(reader, &mut |doc| {
+                weight.for_each_no_score_until(reader, &mut |doc| {
                     segment_collector.collect(doc, 0.0);
+                    control_flow(&segment_collector, &mut cancellation_check)
                 })?;
             }
         }
@@ -212,6 +245,12 @@ impl<TSegmentCollector: SegmentCollector> SegmentCollector for Option<TSegmentCo
         }
     }
 
+    fn is_done(&self) -> bool {
+        self.as_ref()
+            .map(|segment_collector| segment_collector.is_done())
+            .unwrap_or(true)
+    }
+
     fn harvest(self) -> Self::Fruit {
         self.map(|segment_collector| segment_collector.harvest())
     }
@@ -271,10 +310,33 @@ pub trait SegmentCollector: 'static {
     /// The query pushes the scored document to the collector via this method.
     fn collect(&mut self, doc: DocId, score: Score);
 
+    /// Returns true once the segment collector does not need any more documents.
+    ///
+    /// It is checked after every collected document, and the collection of the segment stops
+    /// early as soon as it returns true. `.collect(doc, score)` may still be called afterwards,
+    /// e.g. if the collector is combined with collectors which are not done.
+    fn is_done(&self) -> bool {
+        false
+    }
+
     /// Extract the fruit of the collection from the `SegmentCollector`.
     fn harvest(self) -> Self::Fruit;
 }
 
+/// Stops the collection of the segment once the segment collector is done, or once the search
+/// is cancelled.
+#[inline]
+fn control_flow<TSegmentCollector: SegmentCollector>(
+    segment_collector: &TSegmentCollector,
+    cancellation_check: &mut CancellationCheck,
+) -> ControlFlow<()> {
+    if segment_collector.is_done() || cancellation_check.visit_doc() {
+        ControlFlow::Break(())
+    } else {
+        ControlFlow::Continue(())
+    }
+}
+
 // -----------------------------------------------
 // Tuple implementations.
 
@@ -329,6 +391,10 @@ where
         self.1.collect(doc, score);
     }
 
+    fn is_done(&self) -> bool {
+        self.0.is_done() && self.1.is_done()
+    }
+
     fn harvest(self) -> <Self as SegmentCollector>::Fruit {
         (self.0.harvest(), self.1.harvest())
     }
@@ -394,6 +460,10 @@ where
         self.2.collect(doc, score);
     }
 
+    fn is_done(&self) -> bool {
+        self.0.is_done() && self.1.is_done() && self.2.is_done()
+    }
+
     fn harvest(self) -> <Self as SegmentCollector>::Fruit {
         (self.0.harvest(), self.1.harvest(), self.2.harvest())
     }
@@ -469,6 +539,10 @@ where
         self.3.collect(doc, score);
     }
 
+    fn is_done(&self) -> bool {
+        self.0.is_done() && self.1.is_done() && self.2.is_done() && self.3.is_done()
+    }
+
     fn harvest(self) -> <Self as SegmentCollector>::Fruit {
         (
             self.0.harvest(),
@@ -56,6 +56,10 @@ impl SegmentCollector for Box<dyn BoxableSegmentCollector> {
         self.as_mut().collect(doc, score);
     }
 
+    fn is_done(&self) -> bool {
+        self.as_ref().is_done()
+    }
+
     fn harvest(self) -> Box<dyn Fruit> {
         BoxableSegmentCollector::harvest_from_box(self)
     }
@@ -63,6 +67,7 @@ impl SegmentCollector for Box<dyn BoxableSegmentCollector> {
 
 pub trait BoxableSegmentCollector {
     fn collect(&mut self, doc: u32, score: Score);
+    fn is_done(&self) -> bool;
     fn harvest_from_box(self: Box<Self>) -> Box<dyn Fruit>;
 }
 
@@ -75,6 +80,10 @@ impl<TSegmentCollector: SegmentCollector> BoxableSegmentCollector
         self.0.collect(doc, score);
     }
 
+    fn is_done(&self) -> bool {
+        self.0.is_done()
+    }
+
     fn harvest_from_box(self: Box<Self>) -> Box<dyn Fruit> {
         Box::new(self.0.harvest())
     }
@@ -234,6 +243,10 @@ impl SegmentCollector for MultiCollectorChild {
         }
     }
 
+    fn is_done(&self) -> bool {
+        self.children.iter().all(|child| child.is_done())
+    }
+
     fn harvest(self) -> MultiFruit {
         MultiFruit {
             sub_fruits: self
@@ -0,0 +1,194 @@
+use std::marker::PhantomData;
+
+use crate::collector::top_collector::{ComparableDoc, TopCollector};
+use crate::collector::top_score_collector::TopScoreSegmentCollector;
+use crate::collector::{Collector, ScoreSegmentTweaker, ScoreTweaker, SegmentCollector, TopDocs};
+use crate::query::{EnableScoring, Query, Scorer, Weight};
+use crate::{DocAddress, DocId, DocSet, Result, Score, Searcher, SegmentOrdinal, SegmentReader};
+
+/// Collects the top `window_size` documents by score, rescores them with a
+/// [`ScoreTweaker`] and returns the top documents by rescored score.
+///
+/// See [`TopDocs::rescore`].
+pub(crate) struct RescoreTopCollector<TScoreTweaker, TScore = Score> {
+    window_collector: TopDocs,
+    window_size: usize,
+    score_tweaker: TScoreTweaker,
+    collector: TopCollector<TScore>,
+}
+
+impl<TScoreTweaker, TScore> RescoreTopCollector<TScoreTweaker, TScore>
+where TScore: Clone + PartialOrd
+{
+    pub fn new(
+        window_size: usize,
+        score_tweaker: TScoreTweaker,
+        collector: TopCollector<TScore>,
+    ) -> RescoreTopCollector<TScoreTweaker, TScore> {
+        RescoreTopCollector {
+            window_collector: TopDocs::with_limit(window_size),
+            window_size,
+            score_tweaker,
+            collector,
+        }
+    }
+}
+
+/// Rescores the candidates of a segment, in increasing doc id order.
+fn rescore_candidates<TSegmentScoreTweaker, TScore>(
+    mut candidates: Vec<(Score, DocAddress)>,
+    segment_score_tweaker: &mut TSegmentScoreTweaker,
+) -> Vec<(Score, TScore, DocAddress)>
+where TSegmentScoreTweaker: ScoreSegmentTweaker<TScore> {
+    candidates.sort_unstable_by_key(|(_score, doc_address)| doc_address.doc_id);
+    candidates
+        .into_iter()
+        .map(|(score, doc_address)| {
+            let rescored = segment_score_tweaker.score(doc_address.doc_id, score);
+            (score, rescored, doc_address)
+        })
+        .collect()
+}
+
+impl<TScoreTweaker, TScore> Collector for RescoreTopCollector<TScoreTweaker, TScore>
+where
+    TScoreTweaker: ScoreTweaker<TScore> + Send + Sync,
+    TScore: 'static + PartialOrd + Clone + Send + Sync,
+{
+    type Fruit = Vec<(TScore, DocAddress)>;
+
+    type Child = RescoreSegmentCollector<TScoreTweaker::Child, TScore>;
+
+    fn for_segment(
+        &self,
+        segment_local_id: SegmentOrdinal,
+        segment_reader: &SegmentReader,
+    ) -> Result<Self::Child> {
+        self.collector.validate_search_after()?;
+        let segment_collector = self
+            .window_collector
+            .for_segment(segment_local_id, segment_reader)?;
+        let segment_score_tweaker = self.score_tweaker.segment_tweaker(segment_reader)?;
+        Ok(RescoreSegmentCollector {
+            segment_collector,
+            segment_score_tweaker,
+            _score: PhantomData,
+        })
+    }
+
+    fn requires_scoring(&self) -> bool {
+        true
+    }
+
+    fn merge_fruits(
+        &self,
+        segment_fruits: Vec<Vec<(Score, TScore, DocAddress)>>,
+    ) -> Result<Self::Fruit> {
+        // The candidates are the top `window_size` documents by original score
+        // among the candidates of all of the segments.
+        let mut candidates: Vec<ComparableDoc<Score, (DocAddress, TScore)>> = segment_fruits
+            .into_iter()
+            .flatten()
+            .map(|(score, rescored, doc_address)| ComparableDoc {
+                feature: score,
+                doc: (doc_address, rescored),
+            })
+            .collect();
+        candidates.sort_unstable();
+        candidates.truncate(self.window_size);
+        let rescored_candidates = candidates
+            .into_iter()
+            .map(|candidate| {
+                let (doc_address, rescored) = candidate.doc;
+                (rescored, doc_address)
+            })
+            .collect();
+        self.collector.merge_fruits(vec![rescored_candidates])
+    }
+
+    fn collect_segment(
+        &self,
+        weight: &dyn Weight,
+        segment_ord: u32,
+        reader: &SegmentReader,
+    ) -> Result<<Self::Child as SegmentCollector>::Fruit> {
+        self.collector.validate_search_after()?;
+        let candidates = self
+            .window_collector
+            .collect_segment(weight, segment_ord, reader)?;
+        let mut segment_score_tweaker = self.score_tweaker.segment_tweaker(reader)?;
+        Ok(rescore_candidates(candidates, &mut segment_score_tweaker))
+    }
+}
+
+pub struct RescoreSegmentCollector<TSegmentScoreTweaker, TScore>
+where TSegmentScoreTweaker: ScoreSegmentTweaker<TScore>
+{
+    segment_collector: TopScoreSegmentCollector,
+    segment_score_tweaker: TSegmentScoreTweaker,
+    _score: PhantomData<TScore>,
+}
+
+impl<TSegmentScoreTweaker, TScore> SegmentCollector
+    for RescoreSegmentCollector<TSegmentScoreTweaker, TScore>
+where
+    TScore: 'static + PartialOrd + Clone + Send + Sync,
+    TSegmentScoreTweaker: 'static + ScoreSegmentTweaker<TScore>,
+{
+    type Fruit = Vec<(Score, TScore, DocAddress)>;
+
+    fn collect(&mut self, doc: DocId, score: Score) {
+        self.segment_collector.collect(doc, score);
+    }
+
+    fn harvest(mut self) -> Vec<(Score, TScore, DocAddress)> {
+        let candidates = self.segment_collector.harvest();
+        rescore_candidates(candidates, &mut self.segment_score_tweaker)
+    }
+}
+
+/// Rescores documents by adding the score of a query to their original score.
+///
+/// Documents which do not match the query keep their original score.
+/// The query can be boosted with a [`BoostQuery`](crate::query::BoostQuery)
+/// to weigh its score against the original score.
+///
+/// See [`TopDocs::rescore`].
+pub struct QueryRescorer {
+    weight: Box<dyn Weight>,
+}
+
+impl QueryRescorer {
+    /// Creates a rescorer for `query`, using the statistics of `searcher` to score it.
+    pub fn new(query: &dyn Query, searcher: &Searcher) -> crate::Result<QueryRescorer> {
+        let weight = query.weight(EnableScoring::Enabled(searcher))?;
+        Ok(QueryRescorer { weight })
+    }
+}
+
+impl ScoreTweaker<Score> for QueryRescorer {
+    type Child = QuerySegmentRescorer;
+
+    fn segment_tweaker(&self, segment_reader: &SegmentReader) -> Result<QuerySegmentRescorer> {
+        let scorer = self.weight.scorer(segment_reader, 1.0)?;
+        Ok(QuerySegmentRescorer { scorer })
+    }
+}
+
+/// Segment local version of the [`QueryRescorer`].
+pub struct QuerySegmentRescorer {
+    scorer: Box<dyn Scorer>,
+}
+
+impl ScoreSegmentTweaker<Score> for QuerySegmentRescorer {
+    fn score(&mut self, doc: DocId, score: Score) -> Score {
+        if self.scorer.doc() < doc {
+            self.scorer.seek(doc);
+        }
+        if self.scorer.doc() == doc {
+            score + self.scorer.score()
+        } else {
+            score
+        }
+    }
+}
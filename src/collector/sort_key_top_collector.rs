@@ -0,0 +1,451 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::net::Ipv6Addr;
+use std::sync::Arc;
+
+use fastfield_codecs::{Column, MonotonicallyMappableToU64};
+
+use crate::collector::top_collector::{ComparableDoc, TopCollector};
+use crate::collector::{Collector, SegmentCollector};
+use crate::fastfield::{
+    type_and_cardinality, FastType, MultiValuedFastFieldReader, MultiValuedU128FastFieldReader,
+};
+use crate::schema::{Cardinality, Facet, Field, FieldType};
+use crate::{
+    DateTime, DocAddress, DocId, InvertedIndexReader, Order, Score, SegmentOrdinal, SegmentReader,
+    TantivyError,
+};
+
+/// A criterion to sort the documents by, see [`TopDocs::order_by`](super::TopDocs::order_by).
+///
+/// ```rust
+/// use tantivy::collector::{MissingValues, SortKey};
+/// use tantivy::schema::{Schema, FAST};
+/// use tantivy::Order;
+///
+/// let mut schema_builder = Schema::builder();
+/// let priority = schema_builder.add_u64_field("priority", FAST);
+/// let sort_keys = vec![
+///     SortKey::field(priority, Order::Desc).missing(MissingValues::First),
+///     SortKey::score(Order::Desc),
+/// ];
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SortKey {
+    target: SortTarget,
+    order: Order,
+    missing: MissingValues,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum SortTarget {
+    Score,
+    Field(Field),
+}
+
+/// Defines where the documents without a value for a [`SortKey`] are sorted.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MissingValues {
+    /// Before all the documents with a value.
+    First,
+    /// After all the documents with a value.
+    #[default]
+    Last,
+}
+
+impl SortKey {
+    /// Sorts the documents by their score.
+    pub fn score(order: Order) -> SortKey {
+        SortKey {
+            target: SortTarget::Score,
+            order,
+            missing: MissingValues::default(),
+        }
+    }
+
+    /// Sorts the documents by the value of a fast field.
+    ///
+    /// All fast field types except bytes are supported: `u64`, `i64`, `f64`, `bool`, date, ip
+    /// address, text and facet fields. Text and facet values are sorted lexicographically.
+    ///
+    /// A multivalued field sorts a document by its smallest value in ascending order, and by its
+    /// largest value in descending order. Documents without a value in a multivalued field are
+    /// missing. Single valued fast fields have a value for every document.
+    pub fn field(field: Field, order: Order) -> SortKey {
+        SortKey {
+            target: SortTarget::Field(field),
+            order,
+            missing: MissingValues::default(),
+        }
+    }
+
+    /// Sets where the documents without a value are sorted. Defaults to
+    /// [`MissingValues::Last`].
+    #[must_use]
+    pub fn missing(mut self, missing: MissingValues) -> SortKey {
+        self.missing = missing;
+        self
+    }
+
+    /// Compares the values of two documents, the document which comes first is `Less`.
+    fn compare(&self, left: &Option<SortValue>, right: &Option<SortValue>) -> Ordering {
+        let missing_first = match self.missing {
+            MissingValues::First => Ordering::Less,
+            MissingValues::Last => Ordering::Greater,
+        };
+        match (left, right) {
+            (Some(left), Some(right)) => {
+                let ordering = left.partial_cmp(right).unwrap_or(Ordering::Equal);
+                if self.order.is_desc() {
+                    ordering.reverse()
+                } else {
+                    ordering
+                }
+            }
+            (None, None) => Ordering::Equal,
+            (None, Some(_)) => missing_first,
+            (Some(_), None) => missing_first.reverse(),
+        }
+    }
+}
+
+/// The value of a document for a [`SortKey`].
+///
+/// The variant depends on the type of the sort key, `None` is used for missing values.
+#[derive(Clone, Debug, PartialEq, PartialOrd)]
+pub enum SortValue {
+    /// The score of the document.
+    Score(Score),
+    /// The value of a `u64` field.
+    U64(u64),
+    /// The value of an `i64` field.
+    I64(i64),
+    /// The value of an `f64` field.
+    F64(f64),
+    /// The value of a `bool` field.
+    Bool(bool),
+    /// The value of a date field.
+    Date(DateTime),
+    /// The value of an ip address field.
+    IpAddr(Ipv6Addr),
+    /// The value of a text field.
+    Str(String),
+    /// The value of a facet field.
+    Facet(Facet),
+}
+
+/// The rank of a present value in a [`SegmentSortValue`].
+const PRESENT_RANK: u8 = 1;
+
+/// The comparable representation of a value in a segment.
+///
+/// The values are mapped to `u128`, so that a greater value is sorted first, and the rank sorts
+/// the missing values before or after the present values.
+pub(crate) type SegmentSortValue = (u8, u128);
+
+pub(crate) struct SortKeyTopCollector {
+    sort_keys: Vec<SortKey>,
+    collector: TopCollector<Vec<SegmentSortValue>>,
+}
+
+impl SortKeyTopCollector {
+    pub(crate) fn new(
+        sort_keys: Vec<SortKey>,
+        collector: TopCollector<Vec<SegmentSortValue>>,
+    ) -> SortKeyTopCollector {
+        SortKeyTopCollector {
+            sort_keys,
+            collector,
+        }
+    }
+
+    fn compare(
+        &self,
+        (left_values, left_doc): &(Vec<Option<SortValue>>, DocAddress),
+        (right_values, right_doc): &(Vec<Option<SortValue>>, DocAddress),
+    ) -> Ordering {
+        self.sort_keys
+            .iter()
+            .zip(left_values.iter().zip(right_values))
+            .map(|(sort_key, (left, right))| sort_key.compare(left, right))
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or(Ordering::Equal)
+            // In case of a tie, we sort by ascending `DocAddress` like the other top collectors.
+            .then_with(|| left_doc.cmp(right_doc))
+    }
+}
+
+impl Collector for SortKeyTopCollector {
+    type Fruit = Vec<(Vec<Option<SortValue>>, DocAddress)>;
+
+    type Child = SortKeyTopSegmentCollector;
+
+    fn for_segment(
+        &self,
+        segment_local_id: SegmentOrdinal,
+        segment_reader: &SegmentReader,
+    ) -> crate::Result<Self::Child> {
+        self.collector.validate_search_after()?;
+        let sort_keys = self
+            .sort_keys
+            .iter()
+            .map(|sort_key| SegmentSortKey::open(sort_key, segment_reader))
+            .collect::<crate::Result<_>>()?;
+        let limit = self.collector.limit + self.collector.offset;
+        Ok(SortKeyTopSegmentCollector {
+            segment_ord: segment_local_id,
+            limit,
+            heap: BinaryHeap::with_capacity(limit),
+            sort_keys,
+            sort_values: Vec::with_capacity(self.sort_keys.len()),
+            vals: Vec::new(),
+            u128_vals: Vec::new(),
+        })
+    }
+
+    fn requires_scoring(&self) -> bool {
+        self.sort_keys
+            .iter()
+            .any(|sort_key| sort_key.target == SortTarget::Score)
+    }
+
+    fn merge_fruits(
+        &self,
+        segment_fruits: Vec<crate::Result<Self::Fruit>>,
+    ) -> crate::Result<Self::Fruit> {
+        let mut top_docs = Vec::new();
+        for segment_fruit in segment_fruits {
+            top_docs.extend(segment_fruit?);
+        }
+        top_docs.sort_by(|left, right| self.compare(left, right));
+        Ok(top_docs
+            .into_iter()
+            .skip(self.collector.offset)
+            .take(self.collector.limit)
+            .collect())
+    }
+}
+
+/// Reads the values of a sort key in a segment.
+enum SortKeyReader {
+    Score,
+    Single(Arc<dyn Column<u64>>),
+    Multi(MultiValuedFastFieldReader<u64>),
+    SingleU128(Arc<dyn Column<u128>>),
+    MultiU128(MultiValuedU128FastFieldReader<u128>),
+}
+
+/// Converts the values of a sort key back into a [`SortValue`].
+enum SortValueType {
+    Score,
+    U64,
+    I64,
+    F64,
+    Bool,
+    Date,
+    IpAddr,
+    Str(Arc<InvertedIndexReader>),
+    Facet(Arc<InvertedIndexReader>),
+}
+
+pub(crate) struct SegmentSortKey {
+    reader: SortKeyReader,
+    value_type: SortValueType,
+    order: Order,
+    missing_rank: u8,
+}
+
+impl SegmentSortKey {
+    pub(crate) fn open(
+        sort_key: &SortKey,
+        segment_reader: &SegmentReader,
+    ) -> crate::Result<SegmentSortKey> {
+        let (reader, value_type) = match sort_key.target {
+            SortTarget::Score => (SortKeyReader::Score, SortValueType::Score),
+            SortTarget::Field(field) => open_field(field, segment_reader)?,
+        };
+        let missing_rank = match sort_key.missing {
+            MissingValues::First => PRESENT_RANK + 1,
+            MissingValues::Last => PRESENT_RANK - 1,
+        };
+        Ok(SegmentSortKey {
+            reader,
+            value_type,
+            order: sort_key.order.clone(),
+            missing_rank,
+        })
+    }
+
+    #[inline]
+    pub(crate) fn sort_value(
+        &self,
+        doc: DocId,
+        score: Score,
+        vals: &mut Vec<u64>,
+        u128_vals: &mut Vec<u128>,
+    ) -> SegmentSortValue {
+        let value = match &self.reader {
+            SortKeyReader::Score => Some(u128::from(f64::from(score).to_u64())),
+            SortKeyReader::Single(reader) => Some(u128::from(reader.get_val(doc))),
+            SortKeyReader::Multi(reader) => {
+                reader.get_vals(doc, vals);
+                self.select_value(vals.iter().copied()).map(u128::from)
+            }
+            SortKeyReader::SingleU128(reader) => Some(reader.get_val(doc)),
+            SortKeyReader::MultiU128(reader) => {
+                reader.get_vals(doc, u128_vals);
+                self.select_value(u128_vals.iter().copied())
+            }
+        };
+        match value {
+            Some(value) if self.order.is_asc() => (PRESENT_RANK, !value),
+            Some(value) => (PRESENT_RANK, value),
+            None => (self.missing_rank, 0),
+        }
+    }
+
+    /// Selects the value of a multivalued field, which sorts the document first.
+    #[inline]
+    fn select_value<T: Ord>(&self, values: impl Iterator<Item = T>) -> Option<T> {
+        if self.order.is_asc() {
+            values.min()
+        } else {
+            values.max()
+        }
+    }
+
+    pub(crate) fn to_sort_value(
+        &self,
+        (rank, value): SegmentSortValue,
+        bytes: &mut Vec<u8>,
+    ) -> crate::Result<Option<SortValue>> {
+        if rank != PRESENT_RANK {
+            return Ok(None);
+        }
+        let value = if self.order.is_asc() { !value } else { value };
+        let sort_value = match &self.value_type {
+            SortValueType::Score => SortValue::Score(f64::from_u64(value as u64) as Score),
+            SortValueType::U64 => SortValue::U64(value as u64),
+            SortValueType::I64 => SortValue::I64(i64::from_u64(value as u64)),
+            SortValueType::F64 => SortValue::F64(f64::from_u64(value as u64)),
+            SortValueType::Bool => SortValue::Bool(bool::from_u64(value as u64)),
+            SortValueType::Date => SortValue::Date(DateTime::from_u64(value as u64)),
+            SortValueType::IpAddr => SortValue::IpAddr(Ipv6Addr::from(value)),
+            SortValueType::Str(inverted_index) => {
+                inverted_index.terms().ord_to_term(value as u64, bytes)?;
+                let text = std::str::from_utf8(bytes).map_err(|_| {
+                    TantivyError::InvalidArgument("text term is not valid utf-8".to_string())
+                })?;
+                SortValue::Str(text.to_string())
+            }
+            SortValueType::Facet(inverted_index) => {
+                inverted_index.terms().ord_to_term(value as u64, bytes)?;
+                let facet = Facet::from_encoded(bytes.clone()).map_err(|_| {
+                    TantivyError::InvalidArgument("facet term is not valid utf-8".to_string())
+                })?;
+                SortValue::Facet(facet)
+            }
+        };
+        Ok(Some(sort_value))
+    }
+}
+
+fn open_field(
+    field: Field,
+    segment_reader: &SegmentReader,
+) -> crate::Result<(SortKeyReader, SortValueType)> {
+    let field_entry = segment_reader.schema().get_field_entry(field);
+    let field_type = field_entry.field_type();
+    let (fast_type, cardinality) = type_and_cardinality(field_type).ok_or_else(|| {
+        TantivyError::SchemaError(format!(
+            "Field {:?} is not a fast field.",
+            field_entry.name()
+        ))
+    })?;
+    let value_type = match field_type {
+        FieldType::Str(_) => SortValueType::Str(segment_reader.inverted_index(field)?),
+        FieldType::Facet(_) => SortValueType::Facet(segment_reader.inverted_index(field)?),
+        FieldType::I64(_) => SortValueType::I64,
+        FieldType::F64(_) => SortValueType::F64,
+        FieldType::Bool(_) => SortValueType::Bool,
+        FieldType::Date(_) => SortValueType::Date,
+        FieldType::IpAddr(_) => SortValueType::IpAddr,
+        FieldType::GeoPoint(_) => {
+            return Err(TantivyError::SchemaError(format!(
+                "Sorting by the geo point field {:?} is not supported.",
+                field_entry.name()
+            )));
+        }
+        _ => SortValueType::U64,
+    };
+    let fast_fields = segment_reader.fast_fields();
+    let reader = match (fast_type, cardinality) {
+        (FastType::U128, Cardinality::SingleValue) => {
+            SortKeyReader::SingleU128(fast_fields.u128(field)?)
+        }
+        (FastType::U128, Cardinality::MultiValues) => {
+            SortKeyReader::MultiU128(fast_fields.u128s(field)?)
+        }
+        (_, Cardinality::SingleValue) => SortKeyReader::Single(fast_fields.u64_lenient(field)?),
+        (_, Cardinality::MultiValues) => SortKeyReader::Multi(fast_fields.u64s_lenient(field)?),
+    };
+    Ok((reader, value_type))
+}
+
+/// Segment collector associated with [`TopDocs::order_by`](super::TopDocs::order_by).
+pub struct SortKeyTopSegmentCollector {
+    segment_ord: SegmentOrdinal,
+    limit: usize,
+    heap: BinaryHeap<ComparableDoc<Vec<SegmentSortValue>, DocId>>,
+    sort_keys: Vec<SegmentSortKey>,
+    /// The sort values of the collected document, reused to avoid an allocation per document.
+    sort_values: Vec<SegmentSortValue>,
+    vals: Vec<u64>,
+    u128_vals: Vec<u128>,
+}
+
+impl SegmentCollector for SortKeyTopSegmentCollector {
+    type Fruit = crate::Result<Vec<(Vec<Option<SortValue>>, DocAddress)>>;
+
+    fn collect(&mut self, doc: DocId, score: Score) {
+        self.sort_values.clear();
+        for sort_key in &self.sort_keys {
+            let sort_value = sort_key.sort_value(doc, score, &mut self.vals, &mut self.u128_vals);
+            self.sort_values.push(sort_value);
+        }
+        if self.heap.len() < self.limit {
+            self.heap.push(ComparableDoc {
+                feature: self.sort_values.clone(),
+                doc,
+            });
+        } else if let Some(mut head) = self.heap.peek_mut() {
+            if head.feature < self.sort_values {
+                head.feature.clone_from(&self.sort_values);
+                head.doc = doc;
+            }
+        }
+    }
+
+    fn harvest(self) -> Self::Fruit {
+        let mut bytes = Vec::new();
+        self.heap
+            .into_sorted_vec()
+            .into_iter()
+            .map(|comparable_doc| {
+                let sort_values = self
+                    .sort_keys
+                    .iter()
+                    .zip(comparable_doc.feature)
+                    .map(|(sort_key, sort_value)| sort_key.to_sort_value(sort_value, &mut bytes))
+                    .collect::<crate::Result<_>>()?;
+                Ok((
+                    sort_values,
+                    DocAddress {
+                        segment_ord: self.segment_ord,
+                        doc_id: comparable_doc.doc,
+                    },
+                ))
+            })
+            .collect()
+    }
+}
@@ -1,4 +1,5 @@
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use fastfield_codecs::Column;
 
@@ -10,7 +11,10 @@ use crate::query::{AllQuery, QueryParser};
 use crate::schema::{Field, Schema, FAST, TEXT};
 use crate::time::format_description::well_known::Rfc3339;
 use crate::time::OffsetDateTime;
-use crate::{doc, DateTime, DocAddress, DocId, Document, Index, Score, Searcher, SegmentOrdinal};
+use crate::{
+    doc, CancellationToken, DateTime, DocAddress, DocId, Document, Index, Score, Searcher,
+    SegmentOrdinal,
+};
 
 pub const TEST_COLLECTOR_WITH_SCORE: TestCollector = TestCollector {
     compute_score: true,
@@ -293,3 +297,182 @@ fn test_option_collector_none() -> crate::Result<()> {
     assert_eq!(counts, None);
     Ok(())
 }
+
+/// Collects the first `limit` documents of each segment.
+struct FirstDocsCollector {
+    limit: usize,
+}
+
+struct FirstDocsSegmentCollector {
+    limit: usize,
+    docs: Vec<DocId>,
+}
+
+impl Collector for FirstDocsCollector {
+    type Fruit = Vec<DocId>;
+    type Child = FirstDocsSegmentCollector;
+
+    fn for_segment(
+        &self,
+        _segment_id: SegmentOrdinal,
+        _reader: &SegmentReader,
+    ) -> crate::Result<FirstDocsSegmentCollector> {
+        Ok(FirstDocsSegmentCollector {
+            limit: self.limit,
+            docs: Vec::new(),
+        })
+    }
+
+    fn requires_scoring(&self) -> bool {
+        false
+    }
+
+    fn merge_fruits(&self, segment_docs: Vec<Vec<DocId>>) -> crate::Result<Vec<DocId>> {
+        Ok(segment_docs.into_iter().flatten().collect())
+    }
+}
+
+impl SegmentCollector for FirstDocsSegmentCollector {
+    type Fruit = Vec<DocId>;
+
+    fn collect(&mut self, doc: DocId, _score: Score) {
+        self.docs.push(doc);
+    }
+
+    fn is_done(&self) -> bool {
+        self.docs.len() >= self.limit
+    }
+
+    fn harvest(self) -> Vec<DocId> {
+        self.docs
+    }
+}
+
+#[test]
+fn test_segment_collector_is_done() -> crate::Result<()> {
+    let mut schema_builder = Schema::builder();
+    let text = schema_builder.add_text_field("text", TEXT);
+    let index = Index::create_in_ram(schema_builder.build());
+    let mut index_writer = index.writer_for_tests()?;
+    index_writer.add_document(doc!(text => "hello bye"))?;
+    for _ in 0..9 {
+        index_writer.add_document(doc!(text => "hello"))?;
+    }
+    index_writer.commit()?;
+    for _ in 0..4 {
+        index_writer.add_document(doc!(text => "hello"))?;
+    }
+    index_writer.commit()?;
+    index_writer.delete_term(crate::Term::from_field_text(text, "bye"));
+    index_writer.commit()?;
+    let searcher = index.reader()?.searcher();
+    assert_eq!(searcher.segment_readers().len(), 2);
+    let query = QueryParser::for_index(&index, vec![text]).parse_query("hello")?;
+
+    let mut first_docs = searcher.search(&query, &FirstDocsCollector { limit: 3 })?;
+    first_docs.sort_unstable();
+    assert_eq!(first_docs, vec![0, 1, 1, 2, 2, 3]);
+
+    // The collection goes on as long as one of the collectors is not done.
+    let (first_docs, count) = searcher.search(&query, &(FirstDocsCollector { limit: 1 }, Count))?;
+    assert_eq!(first_docs.len(), 13);
+    assert_eq!(count, 13);
+    Ok(())
+}
+
+/// Cancels the token once `limit` documents have been collected in a segment.
+struct CancellingCollector {
+    cancellation_token: CancellationToken,
+    limit: usize,
+}
+
+struct CancellingSegmentCollector {
+    cancellation_token: CancellationToken,
+    limit: usize,
+    num_docs: usize,
+}
+
+impl Collector for CancellingCollector {
+    type Fruit = ();
+    type Child = CancellingSegmentCollector;
+
+    fn for_segment(
+        &self,
+        _segment_id: SegmentOrdinal,
+        _reader: &SegmentReader,
+    ) -> crate::Result<CancellingSegmentCollector> {
+        Ok(CancellingSegmentCollector {
+            cancellation_token: self.cancellation_token.clone(),
+            limit: self.limit,
+            num_docs: 0,
+        })
+    }
+
+    fn requires_scoring(&self) -> bool {
+        false
+    }
+
+    fn merge_fruits(&self, _segment_fruits: Vec<()>) -> crate::Result<()> {
+        Ok(())
+    }
+}
+
+impl SegmentCollector for CancellingSegmentCollector {
+    type Fruit = ();
+
+    fn collect(&mut self, _doc: DocId, _score: Score) {
+        self.num_docs += 1;
+        if self.num_docs == self.limit {
+            self.cancellation_token.cancel();
+        }
+    }
+
+    fn harvest(self) {}
+}
+
+#[test]
+fn test_search_with_cancellation() -> crate::Result<()> {
+    let mut schema_builder = Schema::builder();
+    let text = schema_builder.add_text_field("text", TEXT);
+    let index = Index::create_in_ram(schema_builder.build());
+    let mut index_writer = index.writer_for_tests()?;
+    for _ in 0..1_000 {
+        index_writer.add_document(doc!(text => "hello"))?;
+    }
+    index_writer.commit()?;
+    let searcher = index.reader()?.searcher();
+    let query = QueryParser::for_index(&index, vec![text]).parse_query("hello")?;
+
+    let cancellation_token = CancellationToken::with_timeout(Duration::from_secs(3_600));
+    let partial_fruit = searcher.search_with_cancellation(&query, &Count, &cancellation_token)?;
+    assert!(!partial_fruit.timed_out);
+    assert_eq!(partial_fruit.fruit, 1_000);
+
+    let cancellation_token = CancellationToken::with_deadline(Instant::now());
+    let partial_fruit = searcher.search_with_cancellation(&query, &Count, &cancellation_token)?;
+    assert!(partial_fruit.timed_out);
+    assert_eq!(partial_fruit.fruit, 0);
+
+    // The token is checked once per block of visited documents.
+    let cancellation_token = CancellationToken::new();
+    let cancelling_collector = CancellingCollector {
+        cancellation_token: cancellation_token.clone(),
+        limit: 200,
+    };
+    let partial_fruit = searcher.search_with_cancellation(
+        &query,
+        &(Count, cancelling_collector),
+        &cancellation_token,
+    )?;
+    assert!(partial_fruit.timed_out);
+    assert_eq!(partial_fruit.fruit.0, 256);
+
+    // The top docs collector keeps skipping the non-competitive documents.
+    let cancellation_token = CancellationToken::with_timeout(Duration::from_secs(3_600));
+    let top_docs = searcher.search(&query, &TopDocs::with_limit(3))?;
+    let partial_fruit =
+        searcher.search_with_cancellation(&query, &TopDocs::with_limit(3), &cancellation_token)?;
+    assert!(!partial_fruit.timed_out);
+    assert_eq!(partial_fruit.fruit, top_docs);
+    Ok(())
+}
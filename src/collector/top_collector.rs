@@ -1,8 +1,7 @@
 use std::cmp::Ordering;
 use std::collections::BinaryHeap;
-use std::marker::PhantomData;
 
-use crate::{DocAddress, DocId, SegmentOrdinal, SegmentReader};
+use crate::{DocAddress, DocId, SegmentOrdinal, SegmentReader, TantivyError};
 
 /// Contains a feature (field, score, etc.) of a document along with the document address.
 ///
@@ -16,6 +15,7 @@ use crate::{DocAddress, DocId, SegmentOrdinal, SegmentReader};
 /// Two elements are equal if their feature is equal, and regardless of whether `doc`
 /// is equal. This should be perfectly fine for this usage, but let's make sure this
 /// struct is never public.
+#[derive(Clone, Debug)]
 pub(crate) struct ComparableDoc<T, D> {
     pub feature: T,
     pub doc: D,
@@ -53,10 +53,29 @@ impl<T: PartialOrd, D: PartialOrd> PartialEq for ComparableDoc<T, D> {
 
 impl<T: PartialOrd, D: PartialOrd> Eq for ComparableDoc<T, D> {}
 
+/// Returns true if a document sorts strictly after the `search_after` cursor, i.e. if it has a
+/// lower feature, or the same feature and a greater document address.
+#[inline]
+pub(crate) fn is_after<T: PartialOrd>(
+    search_after: &(T, DocAddress),
+    feature: &T,
+    doc_address: DocAddress,
+) -> bool {
+    let (after_feature, after_doc_address) = search_after;
+    match feature.partial_cmp(after_feature) {
+        Some(Ordering::Less) => true,
+        Some(Ordering::Greater) => false,
+        _ => doc_address > *after_doc_address,
+    }
+}
+
 pub(crate) struct TopCollector<T> {
     pub limit: usize,
     pub offset: usize,
-    _marker: PhantomData<T>,
+    pub search_after: Option<(T, DocAddress)>,
+    /// Set if the `search_after` cursor does not match the sort of the collector. The builders of
+    /// the sorts do not return errors, so it is returned at the moment of collection.
+    pub invalid_search_after: Option<TantivyError>,
 }
 
 impl<T> TopCollector<T>
@@ -71,7 +90,8 @@ where T: PartialOrd + Clone
         Self {
             limit,
             offset: 0,
-            _marker: PhantomData,
+            search_after: None,
+            invalid_search_after: None,
         }
     }
 
@@ -84,6 +104,14 @@ where T: PartialOrd + Clone
         self
     }
 
+    /// Only collect the documents sorting after the given feature and document address.
+    ///
+    /// This is equivalent to `search_after` in Elasticsearch.
+    pub fn and_search_after(mut self, feature: T, doc_address: DocAddress) -> TopCollector<T> {
+        self.search_after = Some((feature, doc_address));
+        self
+    }
+
     pub fn merge_fruits(
         &self,
         children: Vec<Vec<(T, DocAddress)>>,
@@ -111,16 +139,29 @@ where T: PartialOrd + Clone
             .collect())
     }
 
-    pub(crate) fn for_segment<F: PartialOrd>(
+    /// Returns an error if the `search_after` cursor does not match the sort of the collector.
+    pub(crate) fn validate_search_after(&self) -> crate::Result<()> {
+        match &self.invalid_search_after {
+            Some(err) => Err(err.clone()),
+            None => Ok(()),
+        }
+    }
+
+    pub(crate) fn for_segment(
         &self,
         segment_id: SegmentOrdinal,
         _: &SegmentReader,
-    ) -> TopSegmentCollector<F> {
-        TopSegmentCollector::new(segment_id, self.limit + self.offset)
+    ) -> crate::Result<TopSegmentCollector<T>> {
+        self.validate_search_after()?;
+        Ok(TopSegmentCollector::new(segment_id, self.limit + self.offset)
+            .with_search_after(self.search_after.clone()))
     }
 
     /// Create a new TopCollector with the same limit and offset.
     ///
+    /// The `search_after` cursor is not carried over, as it is a feature of type `T`, but its
+    /// validation error is.
+    ///
     /// Ideally we would use Into but the blanket implementation seems to cause the Scorer traits
     /// to fail.
     #[doc(hidden)]
@@ -128,7 +169,8 @@ where T: PartialOrd + Clone
         TopCollector {
             limit: self.limit,
             offset: self.offset,
-            _marker: PhantomData,
+            search_after: None,
+            invalid_search_after: self.invalid_search_after,
         }
     }
 }
@@ -139,20 +181,32 @@ where T: PartialOrd + Clone
 /// The implementation is based on a `BinaryHeap`.
 /// The theoretical complexity for collecting the top `K` out of `n` documents
 /// is `O(n log K)`.
+#[derive(Clone, Debug)]
 pub(crate) struct TopSegmentCollector<T> {
     limit: usize,
     heap: BinaryHeap<ComparableDoc<T, DocId>>,
     segment_ord: u32,
+    search_after: Option<(T, DocAddress)>,
 }
 
 impl<T: PartialOrd> TopSegmentCollector<T> {
-    fn new(segment_ord: SegmentOrdinal, limit: usize) -> TopSegmentCollector<T> {
+    pub(crate) fn new(segment_ord: SegmentOrdinal, limit: usize) -> TopSegmentCollector<T> {
         TopSegmentCollector {
             limit,
             heap: BinaryHeap::with_capacity(limit),
             segment_ord,
+            search_after: None,
         }
     }
+
+    /// Only collect the documents sorting after the given feature and document address, if any.
+    pub(crate) fn with_search_after(
+        mut self,
+        search_after: Option<(T, DocAddress)>,
+    ) -> TopSegmentCollector<T> {
+        self.search_after = search_after;
+        self
+    }
 }
 
 impl<T: PartialOrd + Clone> TopSegmentCollector<T> {
@@ -179,12 +233,29 @@ impl<T: PartialOrd + Clone> TopSegmentCollector<T> {
         self.heap.len() >= self.limit
     }
 
+    /// Returns the lowest feature of the collected documents once at capacity, i.e. the feature
+    /// a document has to exceed to be collected.
+    #[inline]
+    pub(crate) fn pruning_threshold(&self) -> Option<T> {
+        if self.at_capacity() {
+            self.heap.peek().map(|head| head.feature.clone())
+        } else {
+            None
+        }
+    }
+
     /// Collects a document scored by the given feature
     ///
     /// It collects documents until it has reached the max capacity. Once it reaches capacity, it
     /// will compare the lowest scoring item with the given one and keep whichever is greater.
     #[inline]
     pub fn collect(&mut self, doc: DocId, feature: T) {
+        if let Some(search_after) = &self.search_after {
+            let doc_address = DocAddress::new(self.segment_ord, doc);
+            if !is_after(search_after, &feature, doc_address) {
+                return;
+            }
+        }
         if self.at_capacity() {
             // It's ok to unwrap as long as a limit of 0 is forbidden.
             if let Some(limit_feature) = self.heap.peek().map(|head| head.feature.clone()) {
@@ -287,6 +358,21 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_top_segment_collector_search_after() {
+        let mut top_collector =
+            TopSegmentCollector::new(1, 4).with_search_after(Some((0.5, DocAddress::new(1, 3))));
+        top_collector.collect(1, 0.8);
+        top_collector.collect(2, 0.5);
+        top_collector.collect(3, 0.5);
+        top_collector.collect(4, 0.5);
+        top_collector.collect(5, 0.3);
+        assert_eq!(
+            top_collector.harvest(),
+            vec![(0.5, DocAddress::new(1, 4)), (0.3, DocAddress::new(1, 5))]
+        );
+    }
+
     #[test]
     fn test_top_collector_with_limit_larger_than_set_and_offset() {
         let collector = TopCollector::with_limit(2).and_offset(1);
@@ -0,0 +1,405 @@
+use std::cmp::Ordering;
+
+use fastfield_codecs::MonotonicallyMappableToU64;
+use rustc_hash::FxHashMap;
+
+use crate::collector::sort_key_top_collector::{SegmentSortKey, SegmentSortValue};
+use crate::collector::top_collector::{ComparableDoc, TopCollector, TopSegmentCollector};
+use crate::collector::{Collector, SegmentCollector, SortKey, SortValue};
+use crate::schema::Field;
+use crate::{DocAddress, DocId, Order, Score, SegmentOrdinal, SegmentReader};
+
+/// The `TopGroups` collector groups the documents by the value of a fast field, and keeps track
+/// of the top `K` groups.
+///
+/// The groups are ranked by the score of their best hit. For each group, the collector returns
+/// the number of matching documents and its best hits, one by default.
+///
+/// The field can be a fast field of any type. For a multivalued field, the documents are grouped
+/// by their smallest value, and the documents without a value are grouped in a group of their own.
+///
+/// ```rust
+/// use tantivy::collector::{SortValue, TopGroups};
+/// use tantivy::query::QueryParser;
+/// use tantivy::schema::{Schema, FAST, STRING, TEXT};
+/// use tantivy::{doc, DocAddress, Index};
+///
+/// # fn main() -> tantivy::Result<()> {
+/// let mut schema_builder = Schema::builder();
+/// let title = schema_builder.add_text_field("title", TEXT);
+/// let family = schema_builder.add_text_field("family", STRING | FAST);
+/// let schema = schema_builder.build();
+/// let index = Index::create_in_ram(schema);
+///
+/// let mut index_writer = index.writer_with_num_threads(1, 10_000_000)?;
+/// index_writer.add_document(doc!(title => "red shirt", family => "shirt"))?;
+/// index_writer.add_document(doc!(title => "red red shirt", family => "shirt"))?;
+/// index_writer.add_document(doc!(title => "red hat", family => "hat"))?;
+/// index_writer.add_document(doc!(title => "blue hat", family => "hat"))?;
+/// index_writer.commit()?;
+///
+/// let reader = index.reader()?;
+/// let searcher = reader.searcher();
+///
+/// let query_parser = QueryParser::for_index(&index, vec![title]);
+/// let query = query_parser.parse_query("red")?;
+/// let top_groups = searcher.search(&query, &TopGroups::with_limit(family, 10))?;
+///
+/// assert_eq!(top_groups.len(), 2);
+/// assert_eq!(top_groups[0].value, Some(SortValue::Str("shirt".to_string())));
+/// assert_eq!(top_groups[0].count, 2);
+/// assert_eq!(top_groups[0].hits[0].1, DocAddress::new(0, 1));
+/// assert_eq!(top_groups[1].value, Some(SortValue::Str("hat".to_string())));
+/// assert_eq!(top_groups[1].count, 1);
+/// # Ok(())
+/// # }
+/// ```
+pub struct TopGroups {
+    field: Field,
+    collector: TopCollector<Score>,
+    hits_per_group: usize,
+}
+
+/// A group of documents sharing the same value, see [`TopGroups`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Group {
+    /// The value of the field, `None` for the documents without a value.
+    pub value: Option<SortValue>,
+    /// The number of documents of the group matching the query.
+    ///
+    /// Only the top groups of each segment are returned, so the documents of the segments in
+    /// which the group isn't among the top groups are not counted.
+    pub count: u64,
+    /// The best hits of the group, sorted by decreasing score. Like for the count, the segments
+    /// in which the group isn't among the top groups are skipped.
+    pub hits: Vec<(Score, DocAddress)>,
+}
+
+impl TopGroups {
+    /// Creates a top groups collector, grouping by the given fast field, with a number of groups
+    /// equal to "limit".
+    ///
+    /// # Panics
+    /// The method panics if limit is 0
+    pub fn with_limit(field: Field, limit: usize) -> TopGroups {
+        TopGroups {
+            field,
+            collector: TopCollector::with_limit(limit),
+            hits_per_group: 1,
+        }
+    }
+
+    /// Skip the first "offset" groups when collecting.
+    #[must_use]
+    pub fn and_offset(self, offset: usize) -> TopGroups {
+        TopGroups {
+            collector: self.collector.and_offset(offset),
+            ..self
+        }
+    }
+
+    /// Set the number of best hits returned for each group.
+    ///
+    /// # Panics
+    /// The method panics if hits_per_group is 0
+    #[must_use]
+    pub fn and_hits_per_group(self, hits_per_group: usize) -> TopGroups {
+        assert!(
+            hits_per_group >= 1,
+            "Hits per group must be strictly greater than 0."
+        );
+        TopGroups {
+            hits_per_group,
+            ..self
+        }
+    }
+}
+
+/// Sorts the hits by decreasing score, and by ascending `DocAddress` in case of a tie like
+/// [`TopDocs`](super::TopDocs).
+fn compare_hits(left: &(Score, DocAddress), right: &(Score, DocAddress)) -> Ordering {
+    let as_comparable_doc = |&(feature, doc): &(Score, DocAddress)| ComparableDoc { feature, doc };
+    as_comparable_doc(left).cmp(&as_comparable_doc(right))
+}
+
+/// A hashable representation of the value of a group.
+#[derive(Hash, PartialEq, Eq)]
+enum GroupKey {
+    Missing,
+    Numeric(u128),
+    Text(String),
+}
+
+impl From<&Option<SortValue>> for GroupKey {
+    fn from(value: &Option<SortValue>) -> Self {
+        let value = match value {
+            Some(value) => value,
+            None => return GroupKey::Missing,
+        };
+        match value {
+            SortValue::Score(score) => GroupKey::Numeric(u128::from(score.to_bits())),
+            SortValue::U64(val) => GroupKey::Numeric(u128::from(*val)),
+            SortValue::I64(val) => GroupKey::Numeric(u128::from(val.to_u64())),
+            SortValue::F64(val) => GroupKey::Numeric(u128::from(val.to_u64())),
+            SortValue::Bool(val) => GroupKey::Numeric(u128::from(*val)),
+            SortValue::Date(val) => GroupKey::Numeric(u128::from(val.to_u64())),
+            SortValue::IpAddr(val) => GroupKey::Numeric(u128::from(*val)),
+            SortValue::Str(text) => GroupKey::Text(text.clone()),
+            SortValue::Facet(facet) => GroupKey::Text(facet.encoded_str().to_string()),
+        }
+    }
+}
+
+impl Collector for TopGroups {
+    type Fruit = Vec<Group>;
+
+    type Child = TopGroupsSegmentCollector;
+
+    fn for_segment(
+        &self,
+        segment_local_id: SegmentOrdinal,
+        segment_reader: &SegmentReader,
+    ) -> crate::Result<Self::Child> {
+        let group_key =
+            SegmentSortKey::open(&SortKey::field(self.field, Order::Asc), segment_reader)?;
+        Ok(TopGroupsSegmentCollector {
+            segment_ord: segment_local_id,
+            num_groups: self.collector.offset + self.collector.limit,
+            hits_per_group: self.hits_per_group,
+            group_key,
+            groups: FxHashMap::default(),
+            vals: Vec::new(),
+            u128_vals: Vec::new(),
+        })
+    }
+
+    fn requires_scoring(&self) -> bool {
+        true
+    }
+
+    fn merge_fruits(
+        &self,
+        segment_fruits: Vec<crate::Result<Vec<Group>>>,
+    ) -> crate::Result<Self::Fruit> {
+        let mut groups: FxHashMap<GroupKey, Group> = FxHashMap::default();
+        for segment_fruit in segment_fruits {
+            for segment_group in segment_fruit? {
+                let group_key = GroupKey::from(&segment_group.value);
+                if let Some(group) = groups.get_mut(&group_key) {
+                    group.count += segment_group.count;
+                    group.hits.extend(segment_group.hits);
+                    group.hits.sort_by(compare_hits);
+                    group.hits.truncate(self.hits_per_group);
+                } else {
+                    groups.insert(group_key, segment_group);
+                }
+            }
+        }
+        let mut groups: Vec<Group> = groups.into_values().collect();
+        groups.sort_by(|left, right| compare_hits(&left.hits[0], &right.hits[0]));
+        Ok(groups
+            .into_iter()
+            .skip(self.collector.offset)
+            .take(self.collector.limit)
+            .collect())
+    }
+}
+
+struct SegmentGroup {
+    count: u64,
+    hits: TopSegmentCollector<Score>,
+}
+
+/// Segment collector associated with [`TopGroups`].
+pub struct TopGroupsSegmentCollector {
+    segment_ord: SegmentOrdinal,
+    num_groups: usize,
+    hits_per_group: usize,
+    group_key: SegmentSortKey,
+    groups: FxHashMap<SegmentSortValue, SegmentGroup>,
+    vals: Vec<u64>,
+    u128_vals: Vec<u128>,
+}
+
+impl SegmentCollector for TopGroupsSegmentCollector {
+    type Fruit = crate::Result<Vec<Group>>;
+
+    fn collect(&mut self, doc: DocId, score: Score) {
+        let group_key = self
+            .group_key
+            .sort_value(doc, score, &mut self.vals, &mut self.u128_vals);
+        let segment_ord = self.segment_ord;
+        let hits_per_group = self.hits_per_group;
+        let group = self
+            .groups
+            .entry(group_key)
+            .or_insert_with(|| SegmentGroup {
+                count: 0,
+                hits: TopSegmentCollector::new(segment_ord, hits_per_group),
+            });
+        group.count += 1;
+        group.hits.collect(doc, score);
+    }
+
+    fn harvest(self) -> Self::Fruit {
+        // A group ranks by its best hit, so only the top groups of the segment can be among the
+        // top groups overall. The values of the other groups are not resolved.
+        let mut groups: Vec<_> = self
+            .groups
+            .into_iter()
+            .map(|(group_key, segment_group)| {
+                (group_key, segment_group.count, segment_group.hits.harvest())
+            })
+            .collect();
+        if groups.len() > self.num_groups {
+            groups.select_nth_unstable_by(self.num_groups, |left, right| {
+                compare_hits(&left.2[0], &right.2[0])
+            });
+            groups.truncate(self.num_groups);
+        }
+        let mut bytes = Vec::new();
+        groups
+            .into_iter()
+            .map(|(group_key, count, hits)| {
+                Ok(Group {
+                    value: self.group_key.to_sort_value(group_key, &mut bytes)?,
+                    count,
+                    hits,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Group, TopGroups};
+    use crate::collector::SortValue;
+    use crate::query::{AllQuery, QueryParser};
+    use crate::schema::{Facet, FacetOptions, Field, Schema, FAST, STORED, TEXT};
+    use crate::{Index, Searcher};
+
+    /// The value, count and hit titles of a group.
+    type GroupSummary = (Option<SortValue>, u64, Vec<String>);
+
+    /// Summarizes the groups, with the titles of their hits as the segment ordinals are not
+    /// deterministic.
+    fn group_summaries(
+        searcher: &Searcher,
+        title: Field,
+        groups: &[Group],
+    ) -> crate::Result<Vec<GroupSummary>> {
+        groups
+            .iter()
+            .map(|group| {
+                let titles = group
+                    .hits
+                    .iter()
+                    .map(|(_score, doc_address)| {
+                        let doc = searcher.doc(*doc_address)?;
+                        Ok(doc.get_first(title).unwrap().as_text().unwrap().to_string())
+                    })
+                    .collect::<crate::Result<_>>()?;
+                Ok((group.value.clone(), group.count, titles))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_top_groups_across_segments() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let title = schema_builder.add_text_field("title", TEXT | STORED);
+        let family = schema_builder.add_u64_field("family", FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(title => "shirt", family => 1u64))?;
+        index_writer.add_document(doc!(title => "shirt shirt shirt", family => 2u64))?;
+        index_writer.add_document(doc!(title => "shirt and pants", family => 1u64))?;
+        index_writer.commit()?;
+        index_writer.add_document(doc!(title => "shirt shirt", family => 1u64))?;
+        index_writer.add_document(doc!(title => "pants", family => 3u64))?;
+        index_writer.add_document(doc!(title => "shirt for cats"))?;
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        assert_eq!(searcher.segment_readers().len(), 2);
+        let query = QueryParser::for_index(&index, vec![title]).parse_query("shirt")?;
+
+        let top_groups = searcher.search(&query, &TopGroups::with_limit(family, 10))?;
+        assert_eq!(
+            group_summaries(&searcher, title, &top_groups)?,
+            vec![
+                (
+                    Some(SortValue::U64(2)),
+                    1,
+                    vec!["shirt shirt shirt".to_string()]
+                ),
+                (Some(SortValue::U64(1)), 3, vec!["shirt shirt".to_string()]),
+                (
+                    Some(SortValue::U64(0)),
+                    1,
+                    vec!["shirt for cats".to_string()]
+                ),
+            ]
+        );
+
+        let top_groups = searcher.search(
+            &query,
+            &TopGroups::with_limit(family, 1)
+                .and_offset(1)
+                .and_hits_per_group(2),
+        )?;
+        assert_eq!(
+            group_summaries(&searcher, title, &top_groups)?,
+            vec![(
+                Some(SortValue::U64(1)),
+                3,
+                vec!["shirt shirt".to_string(), "shirt".to_string()]
+            )]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_top_groups_missing_values() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let title = schema_builder.add_text_field("title", TEXT | STORED);
+        let tags = schema_builder.add_facet_field("tags", FacetOptions::default());
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(title => "a", tags => Facet::from("/b")))?;
+        index_writer.add_document(doc!(title => "b"))?;
+        index_writer.add_document(doc!(title => "c"))?;
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let mut top_groups = searcher.search(&AllQuery, &TopGroups::with_limit(tags, 10))?;
+        top_groups.sort_by_key(|group| group.count);
+        assert_eq!(
+            group_summaries(&searcher, title, &top_groups)?,
+            vec![
+                (
+                    Some(SortValue::Facet(Facet::from("/b"))),
+                    1,
+                    vec!["a".to_string()]
+                ),
+                (None, 2, vec!["b".to_string()]),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_top_groups_not_fast_field() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let family = schema_builder.add_u64_field("family", STORED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(family => 1u64))?;
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        assert!(matches!(
+            searcher.search(&AllQuery, &TopGroups::with_limit(family, 10)),
+            Err(crate::TantivyError::SchemaError(_))
+        ));
+        Ok(())
+    }
+}
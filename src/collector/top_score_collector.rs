@@ -3,19 +3,25 @@ use std::fmt;
 use std::marker::PhantomData;
 use std::sync::Arc;
 
-use fastfield_codecs::Column;
+use fastfield_codecs::{Column, MonotonicallyMappableToU64};
 
 use super::Collector;
 use crate::collector::custom_score_top_collector::CustomScoreTopCollector;
-use crate::collector::top_collector::{ComparableDoc, TopCollector, TopSegmentCollector};
+use crate::collector::rescore_top_collector::RescoreTopCollector;
+use crate::collector::sort_key_top_collector::SortKeyTopCollector;
+use crate::collector::top_collector::{is_after, ComparableDoc, TopCollector, TopSegmentCollector};
 use crate::collector::tweak_score_top_collector::TweakedScoreTopCollector;
 use crate::collector::{
     CustomScorer, CustomSegmentScorer, ScoreSegmentTweaker, ScoreTweaker, SegmentCollector,
+    SortKey, SortValue,
 };
+use crate::core::CancellationCheck;
 use crate::fastfield::FastValue;
 use crate::query::Weight;
-use crate::schema::Field;
-use crate::{DocAddress, DocId, Score, SegmentOrdinal, SegmentReader, TantivyError};
+use crate::schema::{Field, Type};
+use crate::{
+    DocAddress, DocId, DocSet, Score, SegmentOrdinal, SegmentReader, TantivyError, TERMINATED,
+};
 
 struct FastFieldConvertCollector<
     TCollector: Collector<Fruit = Vec<(u64, DocAddress)>>,
@@ -119,7 +125,7 @@ where
 /// # Ok(())
 /// # }
 /// ```
-pub struct TopDocs(TopCollector<Score>);
+pub struct TopDocs(TopCollector<Score>, Option<(SortValue, DocAddress)>);
 
 impl fmt::Debug for TopDocs {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -161,13 +167,31 @@ impl CustomScorer<u64> for ScorerByField {
     }
 }
 
+/// Converts a `search_after` value to the `u64` representation of a fast field of type
+/// `TFastValue`.
+///
+/// Returns an error if the value is not of type `TFastValue`.
+fn fast_field_search_after<TFastValue: FastValue>(sort_value: &SortValue) -> crate::Result<u64> {
+    match (TFastValue::to_type(), sort_value) {
+        (Type::U64, SortValue::U64(val)) => Ok(val.to_u64()),
+        (Type::I64, SortValue::I64(val)) => Ok(val.to_u64()),
+        (Type::F64, SortValue::F64(val)) => Ok(val.to_u64()),
+        (Type::Bool, SortValue::Bool(val)) => Ok(val.to_u64()),
+        (Type::Date, SortValue::Date(val)) => Ok(val.to_u64()),
+        (value_type, _) => Err(TantivyError::InvalidArgument(format!(
+            "The search_after value {:?} is not of the fast field type {:?}.",
+            sort_value, value_type
+        ))),
+    }
+}
+
 impl TopDocs {
     /// Creates a top score collector, with a number of documents equal to "limit".
     ///
     /// # Panics
     /// The method panics if limit is 0
     pub fn with_limit(limit: usize) -> TopDocs {
-        TopDocs(TopCollector::with_limit(limit))
+        TopDocs(TopCollector::with_limit(limit), None)
     }
 
     /// Skip the first "offset" documents when collecting.
@@ -212,7 +236,173 @@ impl TopDocs {
     /// ```
     #[must_use]
     pub fn and_offset(self, offset: usize) -> TopDocs {
-        TopDocs(self.0.and_offset(offset))
+        TopDocs(self.0.and_offset(offset), self.1)
+    }
+
+    /// Only collect the documents sorting after the given sort value and document address.
+    ///
+    /// This makes it possible to page through the results by passing the sort value and the
+    /// address of the last hit of a page to get the next one. Unlike with
+    /// [`and_offset`](TopDocs::and_offset), the size of the heap of the collector does not grow
+    /// with the number of skipped documents, which makes deep pagination cheap.
+    ///
+    /// The sort value has to match the sort of the collector:
+    /// - a [`SortValue::Score`] when sorting by score,
+    /// - a [`SortValue::U64`] when sorting by [`order_by_u64_field`](TopDocs::order_by_u64_field),
+    /// - the value of the type of the fast field when sorting by
+    ///   [`order_by_fast_field`](TopDocs::order_by_fast_field).
+    ///
+    /// Other sorts, such as [`order_by`](TopDocs::order_by), do not support `search_after`: an
+    /// error is returned at the moment of collection. The same goes for a sort value of the wrong
+    /// type.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use tantivy::collector::{SortValue, TopDocs};
+    /// use tantivy::query::QueryParser;
+    /// use tantivy::schema::{Schema, TEXT};
+    /// use tantivy::{doc, DocAddress, Index};
+    ///
+    /// # fn main() -> tantivy::Result<()> {
+    /// let mut schema_builder = Schema::builder();
+    /// let title = schema_builder.add_text_field("title", TEXT);
+    /// let schema = schema_builder.build();
+    /// let index = Index::create_in_ram(schema);
+    ///
+    /// let mut index_writer = index.writer_with_num_threads(1, 10_000_000)?;
+    /// index_writer.add_document(doc!(title => "The Name of the Wind"))?;
+    /// index_writer.add_document(doc!(title => "The Diary of Muadib"))?;
+    /// index_writer.add_document(doc!(title => "A Dairy Cow"))?;
+    /// index_writer.add_document(doc!(title => "The Diary of a Young Girl"))?;
+    /// index_writer.add_document(doc!(title => "The Diary of Lena Mukhina"))?;
+    /// index_writer.commit()?;
+    ///
+    /// let reader = index.reader()?;
+    /// let searcher = reader.searcher();
+    ///
+    /// let query_parser = QueryParser::for_index(&index, vec![title]);
+    /// let query = query_parser.parse_query("diary")?;
+    /// let first_page = searcher.search(&query, &TopDocs::with_limit(2))?;
+    /// let (last_score, last_doc_address) = *first_page.last().unwrap();
+    /// let second_page = searcher.search(
+    ///     &query,
+    ///     &TopDocs::with_limit(2).search_after(SortValue::Score(last_score), last_doc_address),
+    /// )?;
+    ///
+    /// assert_eq!(second_page.len(), 1);
+    /// assert_eq!(second_page[0].1, DocAddress::new(0, 3));
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn search_after(self, sort_value: SortValue, doc_address: DocAddress) -> TopDocs {
+        TopDocs(self.0, Some((sort_value, doc_address)))
+    }
+
+    /// Set top-K to also count the matching documents, up to a threshold.
+    ///
+    /// Counting all of the matching documents, e.g. with a [`Count`](super::Count) collector,
+    /// requires to score every one of them, while the top-K alone can skip the documents which
+    /// cannot make it to the top-K (e.g. with block-WAND). With a threshold, the documents of a
+    /// segment are counted exactly until `threshold` hits have been found. The segment is then
+    /// scanned again for the top-K alone, skipping the documents which were already counted, and
+    /// the returned count is a lower bound.
+    ///
+    /// The threshold applies to each segment separately, so the count of a search over several
+    /// segments may exceed `threshold`.
+    ///
+    /// With a threshold of 0, no document is counted and the count is always
+    /// [`TotalHits::AtLeast(0)`](TotalHits::AtLeast).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use tantivy::collector::{TopDocs, TotalHits};
+    /// use tantivy::query::QueryParser;
+    /// use tantivy::schema::{Schema, TEXT};
+    /// use tantivy::{doc, Index};
+    ///
+    /// # fn main() -> tantivy::Result<()> {
+    /// let mut schema_builder = Schema::builder();
+    /// let title = schema_builder.add_text_field("title", TEXT);
+    /// let schema = schema_builder.build();
+    /// let index = Index::create_in_ram(schema);
+    ///
+    /// let mut index_writer = index.writer_with_num_threads(1, 10_000_000)?;
+    /// index_writer.add_document(doc!(title => "The Name of the Wind"))?;
+    /// index_writer.add_document(doc!(title => "The Diary of Muadib"))?;
+    /// index_writer.add_document(doc!(title => "A Dairy Cow"))?;
+    /// index_writer.add_document(doc!(title => "The Diary of a Young Girl"))?;
+    /// index_writer.add_document(doc!(title => "The Diary of Lena Mukhina"))?;
+    /// index_writer.commit()?;
+    ///
+    /// let reader = index.reader()?;
+    /// let searcher = reader.searcher();
+    ///
+    /// let query_parser = QueryParser::for_index(&index, vec![title]);
+    /// let query = query_parser.parse_query("diary")?;
+    /// let (top_docs, total_hits) =
+    ///     searcher.search(&query, &TopDocs::with_limit(1).with_total_hits_threshold(2))?;
+    ///
+    /// assert_eq!(top_docs.len(), 1);
+    /// assert_eq!(total_hits, TotalHits::AtLeast(2));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_total_hits_threshold(
+        self,
+        threshold: usize,
+    ) -> impl Collector<Fruit = (Vec<(Score, DocAddress)>, TotalHits)> {
+        TotalHitsTopCollector {
+            top_docs: self,
+            threshold,
+        }
+    }
+
+    /// Returns the `search_after` cursor of the sort by score.
+    fn score_search_after(&self) -> crate::Result<Option<(Score, DocAddress)>> {
+        match &self.1 {
+            None => Ok(None),
+            Some((SortValue::Score(score), doc_address)) => Ok(Some((*score, *doc_address))),
+            Some((sort_value, _)) => Err(TantivyError::InvalidArgument(format!(
+                "The search_after value {:?} is not a score.",
+                sort_value
+            ))),
+        }
+    }
+
+    /// Returns the top collector of a sort which does not support `search_after`.
+    ///
+    /// If a `search_after` cursor was set, the collector returns an error at the moment of
+    /// collection.
+    fn into_tscore<TScore: PartialOrd + Clone>(self) -> TopCollector<TScore> {
+        let mut collector = self.0.into_tscore();
+        if self.1.is_some() {
+            collector.invalid_search_after = Some(TantivyError::InvalidArgument(
+                "search_after is only supported when sorting by score or by a fast field."
+                    .to_string(),
+            ));
+        }
+        collector
+    }
+
+    /// Sorts by the `u64` representation of a fast field, the `search_after` value being of type
+    /// `TFastValue`.
+    fn order_by_u64_representation<TFastValue: FastValue>(
+        self,
+        field: Field,
+    ) -> CustomScoreTopCollector<ScorerByField, u64> {
+        let mut collector = self.0.into_tscore();
+        if let Some((sort_value, doc_address)) = self.1 {
+            match fast_field_search_after::<TFastValue>(&sort_value) {
+                Ok(search_after) => {
+                    collector = collector.and_search_after(search_after, doc_address);
+                }
+                Err(err) => collector.invalid_search_after = Some(err),
+            }
+        }
+        CustomScoreTopCollector::new(ScorerByField { field }, collector)
     }
 
     /// Set top-K to rank documents by a given fast field.
@@ -288,11 +478,14 @@ impl TopDocs {
     ///
     /// To comfortably work with `u64`s, `i64`s, `f64`s, or `date`s, please refer to
     /// the [.order_by_fast_field(...)](TopDocs::order_by_fast_field) method.
+    ///
+    /// If a [`search_after`](TopDocs::search_after) value other than a [`SortValue::U64`] was set,
+    /// an error is returned at the moment of collection.
     pub fn order_by_u64_field(
         self,
         field: Field,
     ) -> impl Collector<Fruit = Vec<(u64, DocAddress)>> {
-        CustomScoreTopCollector::new(ScorerByField { field }, self.0.into_tscore())
+        self.order_by_u64_representation::<u64>(field)
     }
 
     /// Set top-K to rank documents by a given fast field.
@@ -365,6 +558,9 @@ impl TopDocs {
     ///     Ok(resulting_docs)
     /// }
     /// ```
+    ///
+    /// If a [`search_after`](TopDocs::search_after) value not of type `TFastValue` was set, an
+    /// error is returned at the moment of collection.
     pub fn order_by_fast_field<TFastValue>(
         self,
         fast_field: Field,
@@ -372,7 +568,7 @@ impl TopDocs {
     where
         TFastValue: FastValue,
     {
-        let u64_collector = self.order_by_u64_field(fast_field);
+        let u64_collector = self.order_by_u64_representation::<TFastValue>(fast_field);
         FastFieldConvertCollector {
             collector: u64_collector,
             field: fast_field,
@@ -380,6 +576,136 @@ impl TopDocs {
         }
     }
 
+    /// Set top-K to rank documents by a list of sort keys.
+    ///
+    /// The documents are sorted by the first [`SortKey`], ties are broken by the following sort
+    /// keys, and finally by the ascending document address. A sort key is either the score or a
+    /// fast field of any type, including text fields and dates.
+    ///
+    /// Each document is returned along with its values for the sort keys, `None` standing for a
+    /// missing value.
+    ///
+    /// If a field is not a fast field, this method does not panic, but an explicit error will be
+    /// returned at the moment of collection. The same goes for a
+    /// [`search_after`](TopDocs::search_after) cursor, which is not supported by this sort.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use tantivy::schema::{Schema, FAST, STRING, TEXT};
+    /// # use tantivy::{doc, Index, DocAddress};
+    /// # use tantivy::query::QueryParser;
+    /// use tantivy::collector::{SortKey, SortValue, TopDocs};
+    /// use tantivy::Order;
+    ///
+    /// # fn main() -> tantivy::Result<()> {
+    /// #   let mut schema_builder = Schema::builder();
+    /// #   let title = schema_builder.add_text_field("title", TEXT);
+    /// #   let priority = schema_builder.add_u64_field("priority", FAST);
+    /// #   let author = schema_builder.add_text_field("author", STRING | FAST);
+    /// #   let index = Index::create_in_ram(schema_builder.build());
+    /// #   let mut index_writer = index.writer_with_num_threads(1, 10_000_000)?;
+    /// #   index_writer.add_document(doc!(title => "Diary of Muadib", priority => 1u64, author => "irulan"))?;
+    /// #   index_writer.add_document(doc!(title => "Diary of a Young Girl", priority => 2u64, author => "frank"))?;
+    /// #   index_writer.add_document(doc!(title => "A Dairy Cow", priority => 2u64, author => "cow"))?;
+    /// #   index_writer.add_document(doc!(title => "Diary of Lena Mukhina", priority => 2u64, author => "mukhina"))?;
+    /// #   index_writer.commit()?;
+    /// #   let reader = index.reader()?;
+    /// #   let searcher = reader.searcher();
+    /// #   let query = QueryParser::for_index(&index, vec![title]).parse_query("diary")?;
+    /// // Sorts by priority descending, then by author ascending.
+    /// let top_docs_by_priority_and_author = TopDocs::with_limit(2).order_by(vec![
+    ///     SortKey::field(priority, Order::Desc),
+    ///     SortKey::field(author, Order::Asc),
+    /// ]);
+    /// let top_docs = searcher.search(&query, &top_docs_by_priority_and_author)?;
+    /// assert_eq!(
+    ///     top_docs,
+    ///     vec![
+    ///         (
+    ///             vec![Some(SortValue::U64(2)), Some(SortValue::Str("frank".to_string()))],
+    ///             DocAddress::new(0, 1)
+    ///         ),
+    ///         (
+    ///             vec![Some(SortValue::U64(2)), Some(SortValue::Str("mukhina".to_string()))],
+    ///             DocAddress::new(0, 3)
+    ///         ),
+    ///     ]
+    /// );
+    /// #   Ok(())
+    /// # }
+    /// ```
+    pub fn order_by(
+        self,
+        sort_keys: Vec<SortKey>,
+    ) -> impl Collector<Fruit = Vec<(Vec<Option<SortValue>>, DocAddress)>> {
+        SortKeyTopCollector::new(sort_keys, self.into_tscore())
+    }
+
+    /// Rescores the top `window_size` documents by relevancy `Score` with a
+    /// [`ScoreTweaker`], and returns the top documents by rescored score.
+    ///
+    /// Unlike [`tweak_score(...)`](TopDocs::tweak_score), which runs the tweaker on every
+    /// matching document, the documents are first ranked by the cheap relevancy score,
+    /// and only the best `window_size` of them are rescored. This makes it possible to
+    /// use expensive scoring functions, such as a learning-to-rank model.
+    ///
+    /// The window is computed for each segment, so that up to `window_size` documents
+    /// per segment are rescored, but only the global top `window_size` documents are
+    /// ranked by their rescored score. Within a segment, the documents are rescored in
+    /// increasing doc id order. `window_size` should be at least `limit + offset`.
+    ///
+    /// The tweaker can be a closure, as for [`tweak_score(...)`](TopDocs::tweak_score), or a
+    /// [`QueryRescorer`](crate::collector::QueryRescorer) adding the score of another query.
+    ///
+    /// ```rust
+    /// # use tantivy::schema::{Schema, TEXT};
+    /// # use tantivy::{doc, Index, DocAddress, Score};
+    /// use tantivy::collector::{QueryRescorer, TopDocs};
+    /// use tantivy::query::QueryParser;
+    ///
+    /// # fn main() -> tantivy::Result<()> {
+    /// let mut schema_builder = Schema::builder();
+    /// let title = schema_builder.add_text_field("title", TEXT);
+    /// let index = Index::create_in_ram(schema_builder.build());
+    /// let mut index_writer = index.writer_with_num_threads(1, 10_000_000)?;
+    /// index_writer.add_document(doc!(title => "The Diary of a Young Girl"))?;
+    /// index_writer.add_document(doc!(title => "A Young Diary"))?;
+    /// index_writer.add_document(doc!(title => "Diary"))?;
+    /// index_writer.commit()?;
+    ///
+    /// let searcher = index.reader()?.searcher();
+    /// let query_parser = QueryParser::for_index(&index, vec![title]);
+    /// let query = query_parser.parse_query("diary young")?;
+    ///
+    /// // The documents containing the phrase "young girl" are moved to the top.
+    /// let phrase_query = query_parser.parse_query("\"young girl\"^10")?;
+    /// let rescorer = QueryRescorer::new(phrase_query.as_ref(), &searcher)?;
+    /// let top_docs: Vec<(Score, DocAddress)> =
+    ///     searcher.search(&query, &TopDocs::with_limit(1).rescore(100, rescorer))?;
+    /// assert_eq!(top_docs[0].1, DocAddress::new(0, 0));
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// A [`search_after`](TopDocs::search_after) cursor is not supported, an error is returned at
+    /// the moment of collection if one was set.
+    ///
+    /// # Panics
+    /// The method panics if `window_size` is 0.
+    pub fn rescore<TScore, TScoreSegmentTweaker, TScoreTweaker>(
+        self,
+        window_size: usize,
+        score_tweaker: TScoreTweaker,
+    ) -> impl Collector<Fruit = Vec<(TScore, DocAddress)>>
+    where
+        TScore: 'static + Send + Sync + Clone + PartialOrd,
+        TScoreSegmentTweaker: ScoreSegmentTweaker<TScore> + 'static,
+        TScoreTweaker: ScoreTweaker<TScore, Child = TScoreSegmentTweaker> + Send + Sync,
+    {
+        RescoreTopCollector::new(window_size, score_tweaker, self.into_tscore())
+    }
+
     /// Ranks the documents using a custom score.
     ///
     /// This method offers a convenient way to tweak or replace
@@ -484,7 +810,7 @@ impl TopDocs {
         TScoreSegmentTweaker: ScoreSegmentTweaker<TScore> + 'static,
         TScoreTweaker: ScoreTweaker<TScore, Child = TScoreSegmentTweaker> + Send + Sync,
     {
-        TweakedScoreTopCollector::new(score_tweaker, self.0.into_tscore())
+        TweakedScoreTopCollector::new(score_tweaker, self.into_tscore())
     }
 
     /// Ranks the documents using a custom score.
@@ -597,7 +923,7 @@ impl TopDocs {
         TCustomSegmentScorer: CustomSegmentScorer<TScore> + 'static,
         TCustomScorer: CustomScorer<TScore, Child = TCustomSegmentScorer> + Send + Sync,
     {
-        CustomScoreTopCollector::new(custom_score, self.0.into_tscore())
+        CustomScoreTopCollector::new(custom_score, self.into_tscore())
     }
 }
 
@@ -611,7 +937,10 @@ impl Collector for TopDocs {
         segment_local_id: SegmentOrdinal,
         reader: &SegmentReader,
     ) -> crate::Result<Self::Child> {
-        let collector = self.0.for_segment(segment_local_id, reader);
+        let collector = self
+            .0
+            .for_segment(segment_local_id, reader)?
+            .with_search_after(self.score_search_after()?);
         Ok(TopScoreSegmentCollector(collector))
     }
 
@@ -634,11 +963,17 @@ impl Collector for TopDocs {
     ) -> crate::Result<<Self::Child as SegmentCollector>::Fruit> {
         let heap_len = self.0.limit + self.0.offset;
         let mut heap: BinaryHeap<ComparableDoc<Score, DocId>> = BinaryHeap::with_capacity(heap_len);
+        let search_after = self.score_search_after()?;
+        let is_after_cursor = |doc: DocId, score: Score| {
+            search_after.as_ref().map_or(true, |search_after| {
+                is_after(search_after, &score, DocAddress::new(segment_ord, doc))
+            })
+        };
 
         if let Some(alive_bitset) = reader.alive_bitset() {
             let mut threshold = Score::MIN;
             weight.for_each_pruning(threshold, reader, &mut |doc, score| {
-                if alive_bitset.is_deleted(doc) {
+                if alive_bitset.is_deleted(doc) || !is_after_cursor(doc, score) {
                     return threshold;
                 }
                 let heap_item = ComparableDoc {
@@ -658,6 +993,13 @@ impl Collector for TopDocs {
             })?;
         } else {
             weight.for_each_pruning(Score::MIN, reader, &mut |doc, score| {
+                if !is_after_cursor(doc, score) {
+                    return if heap.len() == heap_len {
+                        heap.peek().map(|el| el.feature).unwrap_or(Score::MIN)
+                    } else {
+                        Score::MIN
+                    };
+                }
                 let heap_item = ComparableDoc {
                     feature: score,
                     doc,
@@ -708,15 +1050,171 @@ impl SegmentCollector for TopScoreSegmentCollector {
     }
 }
 
+/// The number of documents matching a query, see
+/// [`TopDocs::with_total_hits_threshold`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TotalHits {
+    /// The exact number of matching documents.
+    Exact(usize),
+    /// A lower bound of the number of matching documents, the threshold having been reached.
+    AtLeast(usize),
+}
+
+impl TotalHits {
+    /// Returns the number of matching documents, or its lower bound.
+    pub fn count(&self) -> usize {
+        match *self {
+            TotalHits::Exact(count) | TotalHits::AtLeast(count) => count,
+        }
+    }
+
+    fn merge(self, other: TotalHits) -> TotalHits {
+        match (self, other) {
+            (TotalHits::Exact(left), TotalHits::Exact(right)) => TotalHits::Exact(left + right),
+            _ => TotalHits::AtLeast(self.count() + other.count()),
+        }
+    }
+}
+
+struct TotalHitsTopCollector {
+    top_docs: TopDocs,
+    threshold: usize,
+}
+
+impl Collector for TotalHitsTopCollector {
+    type Fruit = (Vec<(Score, DocAddress)>, TotalHits);
+
+    type Child = TotalHitsTopSegmentCollector;
+
+    fn for_segment(
+        &self,
+        segment_local_id: SegmentOrdinal,
+        reader: &SegmentReader,
+    ) -> crate::Result<Self::Child> {
+        Ok(TotalHitsTopSegmentCollector {
+            collector: self.top_docs.for_segment(segment_local_id, reader)?,
+            num_hits: 0,
+        })
+    }
+
+    fn requires_scoring(&self) -> bool {
+        true
+    }
+
+    fn merge_fruits(
+        &self,
+        segment_fruits: Vec<(Vec<(Score, DocAddress)>, TotalHits)>,
+    ) -> crate::Result<Self::Fruit> {
+        let mut top_docs = Vec::with_capacity(segment_fruits.len());
+        let mut total_hits = TotalHits::Exact(0);
+        for (segment_top_docs, segment_total_hits) in segment_fruits {
+            top_docs.push(segment_top_docs);
+            total_hits = total_hits.merge(segment_total_hits);
+        }
+        Ok((self.top_docs.merge_fruits(top_docs)?, total_hits))
+    }
+
+    fn collect_segment(
+        &self,
+        weight: &dyn Weight,
+        segment_ord: u32,
+        reader: &SegmentReader,
+    ) -> crate::Result<<Self::Child as SegmentCollector>::Fruit> {
+        let mut segment_collector = self.top_docs.for_segment(segment_ord, reader)?;
+        let alive_bitset = reader.alive_bitset();
+        let is_alive =
+            |doc: DocId| alive_bitset.map_or(true, |alive_bitset| alive_bitset.is_alive(doc));
+        let top_collector = &mut segment_collector.0;
+        let mut collect = |doc: DocId, score: Score| {
+            if is_alive(doc) {
+                top_collector.collect(doc, score);
+            }
+            top_collector.pruning_threshold().unwrap_or(Score::MIN)
+        };
+
+        // Nothing is counted, the segment is only scanned for the top-K.
+        if self.threshold == 0 {
+            weight.for_each_pruning(Score::MIN, reader, &mut collect)?;
+            return Ok((segment_collector.harvest(), TotalHits::AtLeast(0)));
+        }
+
+        // The documents are counted, and collected, until the threshold is reached.
+        let mut scorer = weight.scorer(reader, 1.0)?;
+        let mut cancellation_check = CancellationCheck::new(reader.cancellation_token());
+        let mut num_hits = 0;
+        let mut threshold = Score::MIN;
+        let mut cancelled = false;
+        let mut doc = scorer.doc();
+        let mut last_counted_doc = None;
+        while doc != TERMINATED && num_hits < self.threshold {
+            if cancellation_check.visit_doc() {
+                cancelled = true;
+                break;
+            }
+            if is_alive(doc) {
+                num_hits += 1;
+            }
+            threshold = collect(doc, scorer.score());
+            last_counted_doc = Some(doc);
+            doc = scorer.advance();
+        }
+        if doc == TERMINATED {
+            return Ok((segment_collector.harvest(), TotalHits::Exact(num_hits)));
+        }
+
+        // The segment is scanned again for the top-K only, so that the weight can skip the
+        // documents which cannot make it to the top-K (e.g. with block-WAND). The documents
+        // which have already been counted are also already collected.
+        if !cancelled {
+            let mut pruning_threshold = threshold;
+            weight.for_each_pruning(threshold, reader, &mut |doc, score| {
+                if Some(doc) > last_counted_doc {
+                    pruning_threshold = collect(doc, score);
+                }
+                pruning_threshold
+            })?;
+        }
+        Ok((segment_collector.harvest(), TotalHits::AtLeast(num_hits)))
+    }
+}
+
+/// Segment Collector associated with [`TopDocs::with_total_hits_threshold`].
+///
+/// The threshold only applies to [`Collector::collect_segment`], the documents collected
+/// through this segment collector are all counted.
+pub struct TotalHitsTopSegmentCollector {
+    collector: TopScoreSegmentCollector,
+    num_hits: usize,
+}
+
+impl SegmentCollector for TotalHitsTopSegmentCollector {
+    type Fruit = (Vec<(Score, DocAddress)>, TotalHits);
+
+    fn collect(&mut self, doc: DocId, score: Score) {
+        self.collector.collect(doc, score);
+        self.num_hits += 1;
+    }
+
+    fn harvest(self) -> Self::Fruit {
+        (self.collector.harvest(), TotalHits::Exact(self.num_hits))
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::TopDocs;
-    use crate::collector::Collector;
-    use crate::query::{AllQuery, Query, QueryParser};
-    use crate::schema::{Field, Schema, FAST, STORED, TEXT};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use super::{TopDocs, TotalHits};
+    use crate::collector::{Collector, MissingValues, QueryRescorer, SortKey, SortValue};
+    use crate::query::{AllQuery, EnableScoring, Explanation, Query, QueryParser, Scorer, Weight};
+    use crate::schema::Cardinality;
+    use crate::schema::{Field, NumericOptions, Schema, FAST, STORED, STRING, TEXT};
     use crate::time::format_description::well_known::Rfc3339;
     use crate::time::OffsetDateTime;
-    use crate::{DateTime, DocAddress, DocId, Index, IndexWriter, Score, SegmentReader};
+    use crate::{
+        DateTime, DocAddress, DocId, DocSet, Index, IndexWriter, Order, Score, SegmentReader,
+    };
 
     fn make_index() -> crate::Result<Index> {
         let mut schema_builder = Schema::builder();
@@ -1087,6 +1585,594 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_order_by_multiple_sort_keys() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let title = schema_builder.add_text_field("title", TEXT);
+        let priority = schema_builder.add_u64_field("priority", FAST);
+        let rating = schema_builder.add_f64_field("rating", FAST);
+        let schema = schema_builder.build();
+        let (index, query) = index("diary", title, schema, |index_writer| {
+            index_writer
+                .add_document(doc!(title => "diary one", priority => 1u64, rating => 4.5f64))
+                .unwrap();
+            index_writer
+                .add_document(doc!(title => "diary two", priority => 2u64, rating => 3.0f64))
+                .unwrap();
+            index_writer
+                .add_document(doc!(title => "diary three", priority => 2u64, rating => 1.5f64))
+                .unwrap();
+            index_writer
+                .add_document(doc!(title => "diary four", priority => 2u64, rating => 3.0f64))
+                .unwrap();
+        });
+        let searcher = index.reader()?.searcher();
+        let top_collector = TopDocs::with_limit(4).order_by(vec![
+            SortKey::field(priority, Order::Desc),
+            SortKey::field(rating, Order::Asc),
+        ]);
+        let top_docs = searcher.search(&query, &top_collector)?;
+        assert_eq!(
+            top_docs,
+            vec![
+                (
+                    vec![Some(SortValue::U64(2)), Some(SortValue::F64(1.5))],
+                    DocAddress::new(0, 2)
+                ),
+                (
+                    vec![Some(SortValue::U64(2)), Some(SortValue::F64(3.0))],
+                    DocAddress::new(0, 1)
+                ),
+                (
+                    vec![Some(SortValue::U64(2)), Some(SortValue::F64(3.0))],
+                    DocAddress::new(0, 3)
+                ),
+                (
+                    vec![Some(SortValue::U64(1)), Some(SortValue::F64(4.5))],
+                    DocAddress::new(0, 0)
+                ),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_order_by_text_field_across_segments() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let author = schema_builder.add_text_field("author", STRING | FAST);
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema);
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(author => "irulan"))?;
+        index_writer.add_document(doc!(author => "frank"))?;
+        index_writer.commit()?;
+        index_writer.add_document(doc!(author => "mukhina"))?;
+        index_writer.add_document(doc!(author => "anne"))?;
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let top_collector =
+            TopDocs::with_limit(3).order_by(vec![SortKey::field(author, Order::Asc)]);
+        let top_docs = searcher.search(&AllQuery, &top_collector)?;
+        let authors: Vec<Option<SortValue>> = top_docs
+            .into_iter()
+            .map(|(mut sort_values, _doc_address)| sort_values.remove(0))
+            .collect();
+        assert_eq!(
+            authors,
+            vec![
+                Some(SortValue::Str("anne".to_string())),
+                Some(SortValue::Str("frank".to_string())),
+                Some(SortValue::Str("irulan".to_string())),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_order_by_date_field_desc() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let name = schema_builder.add_text_field("name", TEXT);
+        let birthday = schema_builder.add_date_field("birthday", FAST);
+        let schema = schema_builder.build();
+        let pr_birthday = DateTime::from_utc(OffsetDateTime::parse(
+            "1898-04-09T00:00:00+00:00",
+            &Rfc3339,
+        )?);
+        let mr_birthday = DateTime::from_utc(OffsetDateTime::parse(
+            "1947-11-08T00:00:00+00:00",
+            &Rfc3339,
+        )?);
+        let (index, query) = index("celebrity", name, schema, |index_writer| {
+            index_writer
+                .add_document(doc!(name => "Paul Robeson celebrity", birthday => pr_birthday))
+                .unwrap();
+            index_writer
+                .add_document(doc!(name => "Minnie Riperton celebrity", birthday => mr_birthday))
+                .unwrap();
+        });
+        let searcher = index.reader()?.searcher();
+        let top_collector =
+            TopDocs::with_limit(2).order_by(vec![SortKey::field(birthday, Order::Desc)]);
+        let top_docs = searcher.search(&query, &top_collector)?;
+        assert_eq!(
+            top_docs,
+            vec![
+                (
+                    vec![Some(SortValue::Date(mr_birthday))],
+                    DocAddress::new(0, 1)
+                ),
+                (
+                    vec![Some(SortValue::Date(pr_birthday))],
+                    DocAddress::new(0, 0)
+                ),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_order_by_multivalued_field_missing_values() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let sizes = schema_builder.add_i64_field(
+            "sizes",
+            NumericOptions::default().set_fast(Cardinality::MultiValues),
+        );
+        let schema = schema_builder.build();
+        let index = Index::create_in_ram(schema);
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(sizes => 3i64, sizes => -2i64))?;
+        index_writer.add_document(doc!())?;
+        index_writer.add_document(doc!(sizes => 1i64))?;
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+
+        let top_collector =
+            TopDocs::with_limit(3).order_by(vec![SortKey::field(sizes, Order::Asc)]);
+        let top_docs = searcher.search(&AllQuery, &top_collector)?;
+        assert_eq!(
+            top_docs,
+            vec![
+                (vec![Some(SortValue::I64(-2))], DocAddress::new(0, 0)),
+                (vec![Some(SortValue::I64(1))], DocAddress::new(0, 2)),
+                (vec![None], DocAddress::new(0, 1)),
+            ]
+        );
+
+        let top_collector =
+            TopDocs::with_limit(3).order_by(vec![
+                SortKey::field(sizes, Order::Desc).missing(MissingValues::First)
+            ]);
+        let top_docs = searcher.search(&AllQuery, &top_collector)?;
+        assert_eq!(
+            top_docs,
+            vec![
+                (vec![None], DocAddress::new(0, 1)),
+                (vec![Some(SortValue::I64(3))], DocAddress::new(0, 0)),
+                (vec![Some(SortValue::I64(1))], DocAddress::new(0, 2)),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_order_by_score_with_offset() -> crate::Result<()> {
+        let index = make_index()?;
+        let field = index.schema().get_field("text").unwrap();
+        let query_parser = QueryParser::for_index(&index, vec![field]);
+        let text_query = query_parser.parse_query("droopy tax")?;
+        let searcher = index.reader()?.searcher();
+        let score_docs = searcher.search(&text_query, &TopDocs::with_limit(4))?;
+        let top_collector = TopDocs::with_limit(2)
+            .and_offset(1)
+            .order_by(vec![SortKey::score(Order::Desc)]);
+        let sorted_docs = searcher.search(&text_query, &top_collector)?;
+        let expected_docs: Vec<(Vec<Option<SortValue>>, DocAddress)> = score_docs
+            .into_iter()
+            .skip(1)
+            .map(|(score, doc_address)| (vec![Some(SortValue::Score(score))], doc_address))
+            .collect();
+        assert_eq!(sorted_docs, expected_docs);
+        Ok(())
+    }
+
+    #[test]
+    fn test_order_by_not_fast_field() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let title = schema_builder.add_text_field("title", TEXT);
+        let size = schema_builder.add_u64_field("size", STORED);
+        let schema = schema_builder.build();
+        let (index, _) = index("beer", title, schema, |index_writer| {
+            index_writer
+                .add_document(doc!(title => "bottle of beer", size => 12u64))
+                .unwrap();
+        });
+        let searcher = index.reader()?.searcher();
+        let segment = searcher.segment_reader(0);
+        let top_collector = TopDocs::with_limit(4).order_by(vec![SortKey::field(size, Order::Asc)]);
+        assert!(matches!(
+            top_collector.for_segment(0, segment),
+            Err(crate::TantivyError::SchemaError(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_top_collector_search_after_score() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        for i in 0..20 {
+            let body = format!("droopy {}", "tax ".repeat(i % 4));
+            index_writer.add_document(doc!(text => body))?;
+            if i == 9 {
+                index_writer.commit()?;
+            }
+        }
+        index_writer.commit()?;
+        let query_parser = QueryParser::for_index(&index, vec![text]);
+        let text_query = query_parser.parse_query("droopy tax")?;
+        let searcher = index.reader()?.searcher();
+        assert_eq!(searcher.segment_readers().len(), 2);
+
+        let all_docs = searcher.search(&text_query, &TopDocs::with_limit(20))?;
+        let mut paged_docs = Vec::new();
+        let mut top_collector = TopDocs::with_limit(3);
+        loop {
+            let page = searcher.search(&text_query, &top_collector)?;
+            let (last_score, last_doc_address) = match page.last() {
+                Some(last_hit) => *last_hit,
+                None => break,
+            };
+            paged_docs.extend(page);
+            top_collector =
+                TopDocs::with_limit(3).search_after(SortValue::Score(last_score), last_doc_address);
+        }
+        assert_eq!(paged_docs, all_docs);
+        Ok(())
+    }
+
+    #[test]
+    fn test_top_field_collector_search_after() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let size = schema_builder.add_i64_field("size", FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        for val in [3i64, -1, 3, 7, -1, 3] {
+            index_writer.add_document(doc!(size => val))?;
+        }
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let top_collector = TopDocs::with_limit(3)
+            .search_after(SortValue::I64(3), DocAddress::new(0, 2))
+            .order_by_fast_field::<i64>(size);
+        let top_docs = searcher.search(&AllQuery, &top_collector)?;
+        assert_eq!(
+            top_docs,
+            vec![
+                (3, DocAddress::new(0, 5)),
+                (-1, DocAddress::new(0, 1)),
+                (-1, DocAddress::new(0, 4)),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_top_collector_search_after_not_a_score() -> crate::Result<()> {
+        let index = make_index()?;
+        let searcher = index.reader()?.searcher();
+        let top_collector =
+            TopDocs::with_limit(4).search_after(SortValue::U64(3), DocAddress::new(0, 0));
+        assert!(matches!(
+            searcher.search(&AllQuery, &top_collector),
+            Err(crate::TantivyError::InvalidArgument(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_top_field_collector_search_after_wrong_type() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let size = schema_builder.add_i64_field("size", FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(size => 3i64))?;
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let top_collector = TopDocs::with_limit(4)
+            .search_after(SortValue::F64(3.0), DocAddress::new(0, 0))
+            .order_by_fast_field::<i64>(size);
+        let err = searcher.search(&AllQuery, &top_collector).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "An invalid argument was passed: 'The search_after value F64(3.0) is not of the fast \
+             field type I64.'"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_top_collector_search_after_unsupported_sort() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let size = schema_builder.add_i64_field("size", FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(size => 3i64))?;
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let search_after =
+            || TopDocs::with_limit(4).search_after(SortValue::I64(3), DocAddress::new(0, 0));
+        let by_sort_keys = search_after().order_by(vec![SortKey::field(size, Order::Desc)]);
+        let err = searcher.search(&AllQuery, &by_sort_keys).unwrap_err();
+        assert!(matches!(err, crate::TantivyError::InvalidArgument(_)));
+        let by_custom_score = search_after().custom_score(move |segment_reader: &SegmentReader| {
+            let reader = segment_reader.fast_fields().i64(size).unwrap();
+            move |doc: DocId| reader.get_val(doc)
+        });
+        let err = searcher.search(&AllQuery, &by_custom_score).unwrap_err();
+        assert!(matches!(err, crate::TantivyError::InvalidArgument(_)));
+        let by_tweaked_score =
+            search_after().tweak_score(|_: &SegmentReader| |_doc: DocId, score: Score| score);
+        let err = searcher.search(&AllQuery, &by_tweaked_score).unwrap_err();
+        assert!(matches!(err, crate::TantivyError::InvalidArgument(_)));
+        let rescored =
+            search_after().rescore(10, |_: &SegmentReader| |_doc: DocId, score: Score| score);
+        let err = searcher.search(&AllQuery, &rescored).unwrap_err();
+        assert!(matches!(err, crate::TantivyError::InvalidArgument(_)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_top_collector_total_hits_threshold() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        for i in 0..100 {
+            let body = format!("droopy {}", "tax ".repeat(i % 7));
+            index_writer.add_document(doc!(text => body))?;
+            if i == 49 {
+                index_writer.commit()?;
+            }
+        }
+        index_writer.commit()?;
+        let query_parser = QueryParser::for_index(&index, vec![text]);
+        let text_query = query_parser.parse_query("droopy tax")?;
+        let searcher = index.reader()?.searcher();
+        assert_eq!(searcher.segment_readers().len(), 2);
+        let top_docs = searcher.search(&text_query, &TopDocs::with_limit(5))?;
+
+        let (threshold_top_docs, total_hits) = searcher.search(
+            &text_query,
+            &TopDocs::with_limit(5).with_total_hits_threshold(1_000),
+        )?;
+        assert_eq!(threshold_top_docs, top_docs);
+        assert_eq!(total_hits, TotalHits::Exact(100));
+
+        let (threshold_top_docs, total_hits) = searcher.search(
+            &text_query,
+            &TopDocs::with_limit(5).with_total_hits_threshold(10),
+        )?;
+        assert_eq!(threshold_top_docs, top_docs);
+        assert_eq!(total_hits, TotalHits::AtLeast(20));
+
+        // Each segment has 50 matching documents, which are all counted.
+        let (_, total_hits) = searcher.search(
+            &text_query,
+            &TopDocs::with_limit(5).with_total_hits_threshold(50),
+        )?;
+        assert_eq!(total_hits, TotalHits::Exact(100));
+
+        let (threshold_top_docs, total_hits) = searcher.search(
+            &text_query,
+            &TopDocs::with_limit(5).with_total_hits_threshold(0),
+        )?;
+        assert_eq!(threshold_top_docs, top_docs);
+        assert_eq!(total_hits, TotalHits::AtLeast(0));
+        Ok(())
+    }
+
+    /// Counts the documents scored by the wrapped query.
+    #[derive(Debug)]
+    struct CountingQuery {
+        query: Box<dyn Query>,
+        num_scored: Arc<AtomicUsize>,
+    }
+
+    impl Clone for CountingQuery {
+        fn clone(&self) -> Self {
+            CountingQuery {
+                query: self.query.box_clone(),
+                num_scored: self.num_scored.clone(),
+            }
+        }
+    }
+
+    impl Query for CountingQuery {
+        fn weight(&self, enable_scoring: EnableScoring<'_>) -> crate::Result<Box<dyn Weight>> {
+            Ok(Box::new(CountingWeight {
+                weight: self.query.weight(enable_scoring)?,
+                num_scored: self.num_scored.clone(),
+            }))
+        }
+    }
+
+    struct CountingWeight {
+        weight: Box<dyn Weight>,
+        num_scored: Arc<AtomicUsize>,
+    }
+
+    impl Weight for CountingWeight {
+        fn scorer(&self, reader: &SegmentReader, boost: Score) -> crate::Result<Box<dyn Scorer>> {
+            Ok(Box::new(CountingScorer {
+                scorer: self.weight.scorer(reader, boost)?,
+                num_scored: self.num_scored.clone(),
+            }))
+        }
+
+        fn explain(&self, reader: &SegmentReader, doc: DocId) -> crate::Result<Explanation> {
+            self.weight.explain(reader, doc)
+        }
+
+        fn for_each_pruning(
+            &self,
+            threshold: Score,
+            reader: &SegmentReader,
+            callback: &mut dyn FnMut(DocId, Score) -> Score,
+        ) -> crate::Result<()> {
+            self.weight
+                .for_each_pruning(threshold, reader, &mut |doc, score| {
+                    self.num_scored.fetch_add(1, Ordering::Relaxed);
+                    callback(doc, score)
+                })
+        }
+    }
+
+    struct CountingScorer {
+        scorer: Box<dyn Scorer>,
+        num_scored: Arc<AtomicUsize>,
+    }
+
+    impl DocSet for CountingScorer {
+        fn advance(&mut self) -> DocId {
+            self.scorer.advance()
+        }
+
+        fn seek(&mut self, target: DocId) -> DocId {
+            self.scorer.seek(target)
+        }
+
+        fn doc(&self) -> DocId {
+            self.scorer.doc()
+        }
+
+        fn size_hint(&self) -> u32 {
+            self.scorer.size_hint()
+        }
+    }
+
+    impl Scorer for CountingScorer {
+        fn score(&mut self) -> Score {
+            self.num_scored.fetch_add(1, Ordering::Relaxed);
+            self.scorer.score()
+        }
+    }
+
+    #[test]
+    fn test_top_collector_total_hits_threshold_skips_documents() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        // The first documents score higher than all of the others.
+        for i in 0..10_000 {
+            let body = if i < 5 {
+                "droopy droopy droopy"
+            } else {
+                "droopy"
+            };
+            index_writer.add_document(doc!(text => body))?;
+        }
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let query = CountingQuery {
+            query: QueryParser::for_index(&index, vec![text]).parse_query("droopy")?,
+            num_scored: Arc::default(),
+        };
+        let top_docs = searcher.search(&query, &TopDocs::with_limit(5))?;
+        query.num_scored.store(0, Ordering::Relaxed);
+
+        let (threshold_top_docs, total_hits) = searcher.search(
+            &query,
+            &TopDocs::with_limit(5).with_total_hits_threshold(10),
+        )?;
+        assert_eq!(threshold_top_docs, top_docs);
+        assert_eq!(total_hits, TotalHits::AtLeast(10));
+        // Past the threshold, the blocks of documents which cannot make it to the top-K are
+        // skipped.
+        assert!(query.num_scored.load(Ordering::Relaxed) < 1_000);
+        Ok(())
+    }
+
+    #[test]
+    fn test_rescore_top_collector_window() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text = schema_builder.add_text_field("text", TEXT);
+        let rank = schema_builder.add_u64_field("rank", FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        for i in 0..10u64 {
+            // Longer documents have a lower relevancy score.
+            let body = format!("droopy {}", "filler ".repeat(i as usize));
+            index_writer.add_document(doc!(text => body, rank => i))?;
+            if i == 4 {
+                index_writer.commit()?;
+            }
+        }
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        assert_eq!(searcher.segment_readers().len(), 2);
+        let query_parser = QueryParser::for_index(&index, vec![text]);
+        let text_query = query_parser.parse_query("droopy")?;
+
+        let rescore_by_rank = move |segment_reader: &SegmentReader| {
+            let rank_reader = segment_reader.fast_fields().u64(rank).unwrap();
+            move |doc: DocId, _original_score: Score| rank_reader.get_val(doc) as Score
+        };
+        // Only the 4 most relevant documents are rescored, even though each segment
+        // holds 4 candidates.
+        let top_docs: Vec<(Score, DocAddress)> = searcher.search(
+            &text_query,
+            &TopDocs::with_limit(2).rescore(4, rescore_by_rank),
+        )?;
+        let ranks: Vec<Score> = top_docs.iter().map(|(score, _)| *score).collect();
+        assert_eq!(ranks, vec![3.0, 2.0]);
+
+        let top_docs: Vec<(Score, DocAddress)> = searcher.search(
+            &text_query,
+            &TopDocs::with_limit(2)
+                .and_offset(1)
+                .rescore(4, rescore_by_rank),
+        )?;
+        let ranks: Vec<Score> = top_docs.iter().map(|(score, _)| *score).collect();
+        assert_eq!(ranks, vec![2.0, 1.0]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_rescore_top_collector_query_rescorer() -> crate::Result<()> {
+        let index = make_index()?;
+        let field = index.schema().get_field("text").unwrap();
+        let query_parser = QueryParser::for_index(&index, vec![field]);
+        let text_query = query_parser.parse_query("droopy tax")?;
+        let rescore_query = query_parser.parse_query("says")?;
+        let searcher = index.reader()?.searcher();
+        let top_docs = searcher.search(&text_query, &TopDocs::with_limit(3))?;
+        let says_score = searcher.search(&rescore_query, &TopDocs::with_limit(1))?[0].0;
+
+        let rescorer = QueryRescorer::new(rescore_query.as_ref(), &searcher)?;
+        let rescored_top_docs =
+            searcher.search(&text_query, &TopDocs::with_limit(3).rescore(3, rescorer))?;
+        // Only the document containing "says" gets boosted.
+        let mut expected_top_docs: Vec<(Score, DocAddress)> = top_docs
+            .into_iter()
+            .map(|(score, doc_address)| {
+                let boost = if doc_address.doc_id == 1 {
+                    says_score
+                } else {
+                    0.0
+                };
+                (score + boost, doc_address)
+            })
+            .collect();
+        expected_top_docs.sort_by(|left, right| right.0.partial_cmp(&left.0).unwrap());
+        assert_eq!(expected_top_docs[0].1, DocAddress::new(0, 1));
+        assert_results_equals(&rescored_top_docs, &expected_top_docs);
+        assert_eq!(rescored_top_docs.len(), 3);
+        Ok(())
+    }
+
     fn index(
         query: &str,
         query_field: Field,
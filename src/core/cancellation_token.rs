@@ -0,0 +1,127 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::postings::compression::COMPRESSION_BLOCK_SIZE;
+
+/// Interrupts a search, either once a deadline has passed or when
+/// [`cancel`](CancellationToken::cancel) is called.
+///
+/// The token is cheap to clone, and all of the clones share the same cancellation state,
+/// so that a search running in one thread can be cancelled from another one.
+///
+/// See [`Searcher::search_with_cancellation`](crate::Searcher::search_with_cancellation).
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+    deadline: Option<Instant>,
+}
+
+impl CancellationToken {
+    /// Creates a token that is only cancelled by calling
+    /// [`cancel`](CancellationToken::cancel).
+    pub fn new() -> CancellationToken {
+        CancellationToken::default()
+    }
+
+    /// Creates a token that is cancelled once `deadline` has passed.
+    pub fn with_deadline(deadline: Instant) -> CancellationToken {
+        CancellationToken {
+            cancelled: Arc::default(),
+            deadline: Some(deadline),
+        }
+    }
+
+    /// Creates a token that is cancelled once `timeout` has elapsed from now.
+    pub fn with_timeout(timeout: Duration) -> CancellationToken {
+        CancellationToken::with_deadline(Instant::now() + timeout)
+    }
+
+    /// Cancels the token and all of its clones.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns true if the token was cancelled or if its deadline has passed.
+    pub fn is_cancelled(&self) -> bool {
+        if self.cancelled.load(Ordering::Relaxed) {
+            return true;
+        }
+        match self.deadline {
+            Some(deadline) => Instant::now() >= deadline,
+            None => false,
+        }
+    }
+}
+
+/// Checks a cancellation token while visiting the documents of a segment.
+///
+/// The token is only checked once per block of [`COMPRESSION_BLOCK_SIZE`] visited documents, so
+/// that the cost of the check does not depend on the number of hits.
+pub(crate) struct CancellationCheck<'a> {
+    cancellation_token: Option<&'a CancellationToken>,
+    num_docs_before_check: usize,
+}
+
+impl<'a> CancellationCheck<'a> {
+    pub fn new(cancellation_token: Option<&'a CancellationToken>) -> CancellationCheck<'a> {
+        CancellationCheck {
+            cancellation_token,
+            num_docs_before_check: COMPRESSION_BLOCK_SIZE,
+        }
+    }
+
+    /// Returns true if the search is cancelled. The token is checked once every
+    /// [`COMPRESSION_BLOCK_SIZE`] calls.
+    #[inline]
+    pub fn visit_doc(&mut self) -> bool {
+        let cancellation_token = match self.cancellation_token {
+            Some(cancellation_token) => cancellation_token,
+            None => return false,
+        };
+        self.num_docs_before_check -= 1;
+        if self.num_docs_before_check > 0 {
+            return false;
+        }
+        self.num_docs_before_check = COMPRESSION_BLOCK_SIZE;
+        cancellation_token.is_cancelled()
+    }
+
+    /// Returns true if the search is cancelled, checking the token right away.
+    ///
+    /// This is meant to be called once per visited block of documents, e.g. when skipping
+    /// blocks.
+    #[inline]
+    pub fn visit_block(&mut self) -> bool {
+        self.num_docs_before_check = COMPRESSION_BLOCK_SIZE;
+        self.cancellation_token.map_or(false, |cancellation_token| {
+            cancellation_token.is_cancelled()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::CancellationToken;
+
+    #[test]
+    fn test_cancellation_token_cancel() {
+        let token = CancellationToken::new();
+        let token_clone = token.clone();
+        assert!(!token.is_cancelled());
+        token_clone.cancel();
+        assert!(token.is_cancelled());
+        assert!(token_clone.is_cancelled());
+    }
+
+    #[test]
+    fn test_cancellation_token_deadline() {
+        assert!(CancellationToken::with_deadline(Instant::now()).is_cancelled());
+        let token = CancellationToken::with_timeout(Duration::from_secs(3_600));
+        assert!(!token.is_cancelled());
+        token.cancel();
+        assert!(token.is_cancelled());
+    }
+}
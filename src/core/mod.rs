@@ -1,3 +1,4 @@
+mod cancellation_token;
 mod executor;
 pub mod index;
 mod index_meta;
@@ -13,6 +14,8 @@ use std::path::Path;
 
 use once_cell::sync::Lazy;
 
+pub(crate) use self::cancellation_token::CancellationCheck;
+pub use self::cancellation_token::CancellationToken;
 pub use self::executor::Executor;
 pub use self::index::{Index, IndexBuilder};
 pub use self::index_meta::{
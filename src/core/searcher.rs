@@ -2,8 +2,8 @@ use std::collections::BTreeMap;
 use std::sync::Arc;
 use std::{fmt, io};
 
-use crate::collector::Collector;
-use crate::core::{Executor, SegmentReader};
+use crate::collector::{CancellableCollector, Collector, PartialFruit};
+use crate::core::{CancellationToken, Executor, SegmentReader};
 use crate::query::{EnableScoring, Query};
 use crate::schema::{Document, Schema, Term};
 use crate::space_usage::SearcherSpaceUsage;
@@ -215,6 +215,41 @@ impl Searcher {
         collector.merge_fruits(fruits)
     }
 
+    /// Same as [`search(...)`](Searcher::search), but the search is interrupted once
+    /// `cancellation_token` is cancelled or once its deadline has passed.
+    ///
+    /// The token is checked before each segment, while building the scorers of the
+    /// weights which visit many terms (e.g. regex and fuzzy queries), and then once per block of
+    /// visited documents. When the search is interrupted, the fruits of the documents collected
+    /// so far are merged and returned with `timed_out` set to true.
+    ///
+    /// Collectors keep their own [`Collector::collect_segment`] implementation, e.g. the
+    /// [`TopDocs`](crate::collector::TopDocs) collector still skips the non-competitive
+    /// documents.
+    pub fn search_with_cancellation<C: Collector>(
+        &self,
+        query: &dyn Query,
+        collector: &C,
+        cancellation_token: &CancellationToken,
+    ) -> crate::Result<PartialFruit<C::Fruit>> {
+        let executor = self.inner.index.search_executor();
+        self.search_with_executor_and_cancellation(query, collector, executor, cancellation_token)
+    }
+
+    /// Same as [`search_with_cancellation(...)`](Searcher::search_with_cancellation) but
+    /// running on the given executor.
+    pub fn search_with_executor_and_cancellation<C: Collector>(
+        &self,
+        query: &dyn Query,
+        collector: &C,
+        executor: &Executor,
+        cancellation_token: &CancellationToken,
+    ) -> crate::Result<PartialFruit<C::Fruit>> {
+        let cancellable_collector =
+            CancellableCollector::new(collector, cancellation_token.clone());
+        self.search_with_executor(query, &cancellable_collector, executor)
+    }
+
     /// Summarize total space usage of this searcher.
     pub fn space_usage(&self) -> io::Result<SearcherSpaceUsage> {
         let mut space_usage = SearcherSpaceUsage::new();
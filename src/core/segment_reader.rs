@@ -4,7 +4,7 @@ use std::{fmt, io};
 
 use fail::fail_point;
 
-use crate::core::{InvertedIndexReader, Segment, SegmentComponent, SegmentId};
+use crate::core::{CancellationToken, InvertedIndexReader, Segment, SegmentComponent, SegmentId};
 use crate::directory::{CompositeFile, FileSlice};
 use crate::error::DataCorruption;
 use crate::fastfield::{intersect_alive_bitsets, AliveBitSet, FacetReader, FastFieldReaders};
@@ -44,6 +44,9 @@ pub struct SegmentReader {
     store_file: FileSlice,
     alive_bitset_opt: Option<AliveBitSet>,
     schema: Schema,
+    /// Only set for the searches which may be cancelled, see
+    /// [`Searcher::search_with_cancellation`](crate::Searcher::search_with_cancellation).
+    cancellation_token: Option<CancellationToken>,
 }
 
 impl SegmentReader {
@@ -205,6 +208,7 @@ impl SegmentReader {
             alive_bitset_opt,
             positions_composite,
             schema,
+            cancellation_token: None,
         })
     }
 
@@ -301,6 +305,25 @@ impl SegmentReader {
         self.alive_bitset_opt.as_ref()
     }
 
+    /// Returns a copy of the reader, whose search stops once `cancellation_token` is cancelled.
+    ///
+    /// The token is checked by the weights while building their scorers and while visiting the
+    /// documents of the segment.
+    pub(crate) fn with_cancellation_token(
+        &self,
+        cancellation_token: CancellationToken,
+    ) -> SegmentReader {
+        SegmentReader {
+            cancellation_token: Some(cancellation_token),
+            ..self.clone()
+        }
+    }
+
+    /// Returns the cancellation token of the search of the segment, if any.
+    pub(crate) fn cancellation_token(&self) -> Option<&CancellationToken> {
+        self.cancellation_token.as_ref()
+    }
+
     /// Returns true if the `doc` is marked
     /// as deleted.
     pub fn is_deleted(&self, doc: DocId) -> bool {
@@ -35,7 +35,7 @@ pub(crate) use self::readers::{type_and_cardinality, FastType};
 pub use self::serializer::{Column, CompositeFastFieldSerializer};
 use self::writer::unexpected_value;
 pub use self::writer::{FastFieldsWriter, IntFastFieldWriter};
-use crate::schema::{Type, Value};
+use crate::schema::{GeoPoint, Type, Value};
 use crate::DateTime;
 
 mod alive_bitset;
@@ -48,7 +48,7 @@ mod serializer;
 mod writer;
 
 /// Trait for types that are allowed for fast fields:
-/// (u64, i64 and f64, bool, DateTime).
+/// (u64, i64 and f64, bool, DateTime, GeoPoint).
 pub trait FastValue:
     MonotonicallyMappableToU64 + Copy + Send + Sync + PartialOrd + 'static
 {
@@ -109,6 +109,22 @@ impl FastValue for DateTime {
     }
 }
 
+impl MonotonicallyMappableToU64 for GeoPoint {
+    fn to_u64(self) -> u64 {
+        self.to_z_order()
+    }
+
+    fn from_u64(val: u64) -> Self {
+        GeoPoint::from_z_order(val)
+    }
+}
+
+impl FastValue for GeoPoint {
+    fn to_type() -> Type {
+        Type::GeoPoint
+    }
+}
+
 fn value_to_u64(value: &Value) -> crate::Result<u64> {
     let value = match value {
         Value::U64(val) => val.to_u64(),
@@ -116,7 +132,8 @@ fn value_to_u64(value: &Value) -> crate::Result<u64> {
         Value::F64(val) => val.to_u64(),
         Value::Bool(val) => val.to_u64(),
         Value::Date(val) => val.to_u64(),
-        _ => return Err(unexpected_value("u64/i64/f64/bool/date", value)),
+        Value::GeoPoint(val) => val.to_u64(),
+        _ => return Err(unexpected_value("u64/i64/f64/bool/date/geo_point", value)),
     };
     Ok(value)
 }
@@ -8,7 +8,7 @@ use crate::directory::{CompositeFile, FileSlice};
 use crate::fastfield::{
     BytesFastFieldReader, FastFieldNotAvailableError, FastValue, MultiValuedFastFieldReader,
 };
-use crate::schema::{Cardinality, Field, FieldType, Schema};
+use crate::schema::{Cardinality, Field, FieldType, GeoPoint, Schema};
 use crate::space_usage::PerFieldSpaceUsage;
 use crate::{DateTime, TantivyError};
 
@@ -29,6 +29,7 @@ pub(crate) enum FastType {
     F64,
     Bool,
     Date,
+    GeoPoint,
 }
 
 pub(crate) fn type_and_cardinality(field_type: &FieldType) -> Option<(FastType, Cardinality)> {
@@ -48,6 +49,9 @@ pub(crate) fn type_and_cardinality(field_type: &FieldType) -> Option<(FastType,
         FieldType::Date(options) => options
             .get_fastfield_cardinality()
             .map(|cardinality| (FastType::Date, cardinality)),
+        FieldType::GeoPoint(options) => options
+            .get_fastfield_cardinality()
+            .map(|cardinality| (FastType::GeoPoint, cardinality)),
         FieldType::Facet(_) => Some((FastType::U64, Cardinality::MultiValues)),
         FieldType::Str(options) if options.is_fast() => {
             Some((FastType::U64, Cardinality::MultiValues))
@@ -227,6 +231,14 @@ impl FastFieldReaders {
         self.typed_fast_field_reader(field)
     }
 
+    /// Returns the `geo point` fast field reader reader associated with `field`.
+    ///
+    /// If `field` is not a geo point fast field, this method returns an Error.
+    pub fn geo_point(&self, field: Field) -> crate::Result<Arc<dyn Column<GeoPoint>>> {
+        self.check_type(field, FastType::GeoPoint, Cardinality::SingleValue)?;
+        self.typed_fast_field_reader(field)
+    }
+
     /// Returns the `f64` fast field reader reader associated with `field`.
     ///
     /// If `field` is not a f64 fast field, this method returns an Error.
@@ -293,6 +305,14 @@ impl FastFieldReaders {
         self.typed_fast_field_multi_reader(field)
     }
 
+    /// Returns a `geo point` multi-valued fast field reader reader associated with `field`.
+    ///
+    /// If `field` is not a geo point multi-valued fast field, this method returns an Error.
+    pub fn geo_points(&self, field: Field) -> crate::Result<MultiValuedFastFieldReader<GeoPoint>> {
+        self.check_type(field, FastType::GeoPoint, Cardinality::MultiValues)?;
+        self.typed_fast_field_multi_reader(field)
+    }
+
     /// Returns the `bytes` fast field reader associated with `field`.
     ///
     /// If `field` is not a bytes fast field, returns an Error.
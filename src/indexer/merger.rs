@@ -282,6 +282,15 @@ impl IndexMerger {
                     }
                     None => {}
                 },
+                FieldType::GeoPoint(ref options) => match options.get_fastfield_cardinality() {
+                    Some(Cardinality::SingleValue) => {
+                        self.write_single_fast_field(field, fast_field_serializer, doc_id_mapping)?;
+                    }
+                    Some(Cardinality::MultiValues) => {
+                        self.write_multi_fast_field(field, fast_field_serializer, doc_id_mapping)?;
+                    }
+                    None => {}
+                },
                 FieldType::Date(ref options) => match options.get_fastfield_cardinality() {
                     Some(Cardinality::SingleValue) => {
                         self.write_single_fast_field(field, fast_field_serializer, doc_id_mapping)?;
@@ -332,6 +332,36 @@ impl SegmentWriter {
                         self.fieldnorms_writer.record(doc_id, field, num_vals);
                     }
                 }
+                FieldType::GeoPoint(_) => {
+                    let mut num_vals = 0;
+                    for value in values {
+                        num_vals += 1;
+                        let geo_point = value.as_geo_point().ok_or_else(make_schema_error)?;
+                        term_buffer.set_geo_point(geo_point);
+                        postings_writer.subscribe(doc_id, 0u32, term_buffer, ctx);
+                    }
+                    if field_entry.has_fieldnorms() {
+                        self.fieldnorms_writer.record(doc_id, field, num_vals);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that the geo points of the document are within bounds, before any of them gets
+    /// written.
+    fn validate_geo_points(&self, doc: &Document) -> crate::Result<()> {
+        for field_value in doc.field_values() {
+            if let Value::GeoPoint(geo_point) = field_value.value() {
+                if !geo_point.is_valid() {
+                    return Err(crate::TantivyError::SchemaError(format!(
+                        "Invalid geo point {:?} for field {:?}, the latitude must be in [-90, 90] \
+                         and the longitude in [-180, 180]",
+                        geo_point,
+                        self.schema.get_field_name(field_value.field())
+                    )));
+                }
             }
         }
         Ok(())
@@ -342,6 +372,7 @@ impl SegmentWriter {
     /// As a user, you should rather use `IndexWriter`'s add_document.
     pub fn add_document(&mut self, add_operation: AddOperation) -> crate::Result<()> {
         let doc = add_operation.document;
+        self.validate_geo_points(&doc)?;
         self.doc_opstamps.push(add_operation.opstamp);
         self.fast_field_writers.add_document(&doc)?;
         self.index_document(&doc)?;
@@ -453,7 +484,9 @@ mod tests {
     use crate::indexer::json_term_writer::JsonTermWriter;
     use crate::postings::TermInfo;
     use crate::query::PhraseQuery;
-    use crate::schema::{IndexRecordOption, Schema, Type, STORED, STRING, TEXT};
+    use crate::schema::{
+        GeoPoint, IndexRecordOption, Schema, Type, FAST, INDEXED, STORED, STRING, TEXT,
+    };
     use crate::store::{Compressor, StoreReader, StoreWriter};
     use crate::time::format_description::well_known::Rfc3339;
     use crate::time::OffsetDateTime;
@@ -868,4 +901,26 @@ mod tests {
         postings.positions(&mut positions);
         assert_eq!(positions, &[4]); //< as opposed to 3 if we had a position length of 1.
     }
+
+    #[test]
+    fn test_invalid_geo_point_rejected() {
+        let mut schema_builder = Schema::builder();
+        let location = schema_builder.add_geo_point_field("location", INDEXED | FAST);
+        let schema = schema_builder.build();
+        for geo_point in [
+            GeoPoint::new(90.5, 0.0),
+            GeoPoint::new(0.0, -180.5),
+            GeoPoint::new(f64::NAN, 0.0),
+        ] {
+            let index = Index::create_in_ram(schema.clone());
+            let mut index_writer = index.writer_for_tests().unwrap();
+            let mut doc = Document::default();
+            doc.add_geo_point(location, geo_point);
+            index_writer.add_document(doc).unwrap();
+            assert!(matches!(
+                index_writer.commit(),
+                Err(crate::TantivyError::SchemaError(_))
+            ));
+        }
+    }
 }
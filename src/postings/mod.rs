@@ -17,6 +17,7 @@ mod serializer;
 mod skip;
 mod stacker;
 mod term_info;
+mod union_postings;
 
 pub use self::block_segment_postings::BlockSegmentPostings;
 pub(crate) use self::indexing_context::IndexingContext;
@@ -28,6 +29,7 @@ pub use self::serializer::{FieldSerializer, InvertedIndexSerializer};
 pub(crate) use self::skip::{BlockInfo, SkipReader};
 pub(crate) use self::stacker::compute_table_size;
 pub use self::term_info::TermInfo;
+pub(crate) use self::union_postings::UnionPostings;
 
 pub(crate) type UnorderedTermId = u64;
 
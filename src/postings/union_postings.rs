@@ -0,0 +1,137 @@
+use crate::docset::{DocSet, TERMINATED};
+use crate::postings::{Postings, SegmentPostings};
+use crate::DocId;
+
+/// Postings of the union of several terms.
+///
+/// A document is visited if it contains any of the terms, and its positions
+/// are the merged positions of the terms it contains.
+pub(crate) struct UnionPostings {
+    postings: Vec<SegmentPostings>,
+    doc: DocId,
+    positions_buffer: Vec<u32>,
+}
+
+impl UnionPostings {
+    pub fn new(postings: Vec<SegmentPostings>) -> UnionPostings {
+        let mut union_postings = UnionPostings {
+            postings,
+            doc: TERMINATED,
+            positions_buffer: Vec::new(),
+        };
+        union_postings.update_doc();
+        union_postings
+    }
+
+    fn update_doc(&mut self) -> DocId {
+        self.doc = self
+            .postings
+            .iter()
+            .map(|postings| postings.doc())
+            .min()
+            .unwrap_or(TERMINATED);
+        self.doc
+    }
+}
+
+impl DocSet for UnionPostings {
+    fn advance(&mut self) -> DocId {
+        let doc = self.doc;
+        for postings in &mut self.postings {
+            if postings.doc() == doc {
+                postings.advance();
+            }
+        }
+        self.update_doc()
+    }
+
+    fn seek(&mut self, target: DocId) -> DocId {
+        for postings in &mut self.postings {
+            if postings.doc() < target {
+                postings.seek(target);
+            }
+        }
+        self.update_doc()
+    }
+
+    fn doc(&self) -> DocId {
+        self.doc
+    }
+
+    fn size_hint(&self) -> u32 {
+        self.postings
+            .iter()
+            .map(|postings| postings.size_hint())
+            .max()
+            .unwrap_or(0u32)
+    }
+}
+
+impl Postings for UnionPostings {
+    fn term_freq(&self) -> u32 {
+        self.postings
+            .iter()
+            .filter(|postings| postings.doc() == self.doc)
+            .map(|postings| postings.term_freq())
+            .sum()
+    }
+
+    fn positions_with_offset(&mut self, offset: u32, output: &mut Vec<u32>) {
+        output.clear();
+        let doc = self.doc;
+        for postings in &mut self.postings {
+            if postings.doc() == doc {
+                postings.positions_with_offset(offset, &mut self.positions_buffer);
+                output.extend_from_slice(&self.positions_buffer);
+            }
+        }
+        output.sort_unstable();
+        output.dedup();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::UnionPostings;
+    use crate::docset::{DocSet, TERMINATED};
+    use crate::postings::Postings;
+    use crate::schema::{IndexRecordOption, Schema, Term, TEXT};
+    use crate::Index;
+
+    #[test]
+    fn test_union_postings() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(text_field => "a b a"))?;
+        index_writer.add_document(doc!(text_field => "c"))?;
+        index_writer.add_document(doc!(text_field => "b"))?;
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let inverted_index = searcher.segment_reader(0).inverted_index(text_field)?;
+        let postings = ["a", "b"]
+            .iter()
+            .map(|text| {
+                inverted_index
+                    .read_postings(
+                        &Term::from_field_text(text_field, text),
+                        IndexRecordOption::WithFreqsAndPositions,
+                    )
+                    .map(Option::unwrap)
+            })
+            .collect::<std::io::Result<Vec<_>>>()?;
+        let mut union_postings = UnionPostings::new(postings);
+        let mut positions = Vec::new();
+        assert_eq!(union_postings.doc(), 0);
+        assert_eq!(union_postings.term_freq(), 3);
+        union_postings.positions_with_offset(1, &mut positions);
+        assert_eq!(positions, vec![1, 2, 3]);
+        assert_eq!(union_postings.advance(), 2);
+        assert_eq!(union_postings.term_freq(), 1);
+        union_postings.positions(&mut positions);
+        assert_eq!(positions, vec![0]);
+        assert_eq!(union_postings.advance(), TERMINATED);
+        Ok(())
+    }
+}
@@ -4,7 +4,7 @@ use std::sync::Arc;
 use common::BitSet;
 use tantivy_fst::Automaton;
 
-use crate::core::SegmentReader;
+use crate::core::{CancellationToken, SegmentReader};
 use crate::query::{BitSetDocSet, ConstScorer, Explanation, Scorer, Weight};
 use crate::schema::{Field, IndexRecordOption};
 use crate::termdict::{TermDictionary, TermStreamer};
@@ -50,7 +50,13 @@ where
         let inverted_index = reader.inverted_index(self.field)?;
         let term_dict = inverted_index.terms();
         let mut term_stream = self.automaton_stream(term_dict)?;
+        let cancellation_token = reader.cancellation_token();
         while term_stream.advance() {
+            // The automaton may match a lot of terms, the scorer is then built from the terms
+            // visited so far.
+            if cancellation_token.map_or(false, CancellationToken::is_cancelled) {
+                break;
+            }
             let term_info = term_stream.value();
             let mut block_segment_postings = inverted_index
                 .read_block_postings_from_terminfo(term_info, IndexRecordOption::Basic)?;
@@ -84,13 +90,15 @@ where
 
 #[cfg(test)]
 mod tests {
-    use tantivy_fst::Automaton;
+    use std::time::Instant;
+
+    use tantivy_fst::{Automaton, Regex};
 
     use super::AutomatonWeight;
     use crate::docset::TERMINATED;
     use crate::query::Weight;
     use crate::schema::{Schema, STRING};
-    use crate::Index;
+    use crate::{CancellationToken, DocSet, Index};
 
     fn create_index() -> crate::Result<Index> {
         let mut schema = Schema::builder();
@@ -168,4 +176,29 @@ mod tests {
         assert_eq!(scorer.score(), 1.32);
         Ok(())
     }
+
+    #[test]
+    fn test_automaton_weight_cancelled() -> crate::Result<()> {
+        let mut schema = Schema::builder();
+        let title = schema.add_text_field("title", STRING);
+        let index = Index::create_in_ram(schema.build());
+        let mut index_writer = index.writer_for_tests()?;
+        for i in 0..1_000 {
+            index_writer.add_document(doc!(title => format!("term{:04}", i)))?;
+        }
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let automaton_weight = AutomatonWeight::new(title, Regex::new("term.*").unwrap());
+
+        let segment_reader = searcher.segment_reader(0u32);
+        let mut scorer = automaton_weight.scorer(segment_reader, 1.0)?;
+        assert_eq!(scorer.count_including_deleted(), 1_000);
+
+        // The term stream is interrupted before the postings of the first term are read.
+        let segment_reader = segment_reader
+            .with_cancellation_token(CancellationToken::with_deadline(Instant::now()));
+        let scorer = automaton_weight.scorer(&segment_reader, 1.0)?;
+        assert_eq!(scorer.doc(), TERMINATED);
+        Ok(())
+    }
 }
@@ -1,5 +1,6 @@
 use std::ops::{Deref, DerefMut};
 
+use crate::core::{CancellationCheck, CancellationToken};
 use crate::query::term_query::TermScorer;
 use crate::query::Scorer;
 use crate::{DocId, DocSet, Score, TERMINATED};
@@ -145,11 +146,15 @@ fn advance_all_scorers_on_pivot(term_scorers: &mut Vec<TermScorerWithMaxScore>,
 /// Implements the WAND (Weak AND) algorithm for dynamic pruning
 /// described in the paper "Faster Top-k Document Retrieval Using Block-Max Indexes".
 /// Link: <http://engineering.nyu.edu/~suel/papers/bmw.pdf>
+///
+/// The search stops early once `cancellation_token` is cancelled.
 pub fn block_wand(
     mut scorers: Vec<TermScorer>,
     mut threshold: Score,
     callback: &mut dyn FnMut(u32, Score) -> Score,
+    cancellation_token: Option<&CancellationToken>,
 ) {
+    let mut cancellation_check = CancellationCheck::new(cancellation_token);
     let mut scorers: Vec<TermScorerWithMaxScore> = scorers
         .iter_mut()
         .map(TermScorerWithMaxScore::from)
@@ -164,6 +169,10 @@ pub fn block_wand(
         debug_assert_ne!(pivot_doc, TERMINATED);
         debug_assert!(before_pivot_len < pivot_len);
 
+        if cancellation_check.visit_doc() {
+            return;
+        }
+
         let block_max_score_upperbound: Score = scorers[..pivot_len]
             .iter_mut()
             .map(|scorer| {
@@ -219,13 +228,20 @@ pub fn block_wand(
 ///   - While the block max score is under the `threshold`, go to the next block.
 ///   - On a block, advance until the end and execute `callback` when the doc score is greater or
 ///     equal to the `threshold`.
+///
+/// The search stops early once `cancellation_token` is cancelled, which is checked once per block.
 pub fn block_wand_single_scorer(
     mut scorer: TermScorer,
     mut threshold: Score,
     callback: &mut dyn FnMut(u32, Score) -> Score,
+    cancellation_token: Option<&CancellationToken>,
 ) {
+    let mut cancellation_check = CancellationCheck::new(cancellation_token);
     let mut doc = scorer.doc();
     loop {
+        if cancellation_check.visit_block() {
+            return;
+        }
         // We position the scorer on a block that can reach
         // the threshold.
         while scorer.block_max_score() < threshold {
@@ -361,9 +377,9 @@ mod tests {
 
         if term_scorers.len() == 1 {
             let scorer = term_scorers.pop().unwrap();
-            super::block_wand_single_scorer(scorer, Score::MIN, callback);
+            super::block_wand_single_scorer(scorer, Score::MIN, callback, None);
         } else {
-            super::block_wand(term_scorers, Score::MIN, callback);
+            super::block_wand(term_scorers, Score::MIN, callback, None);
         }
         checkpoints
     }
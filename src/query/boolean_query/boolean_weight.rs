@@ -1,11 +1,15 @@
 use std::collections::HashMap;
+use std::ops::ControlFlow;
 
 use crate::core::SegmentReader;
 use crate::postings::FreqReadingOption;
 use crate::query::explanation::does_not_match;
 use crate::query::score_combiner::{DoNothingCombiner, ScoreCombiner};
 use crate::query::term_query::TermScorer;
-use crate::query::weight::{for_each_docset, for_each_pruning_scorer, for_each_scorer};
+use crate::query::weight::{
+    for_each_docset, for_each_docset_until, for_each_pruning_scorer, for_each_scorer,
+    for_each_scorer_until,
+};
 use crate::query::{
     intersect_scorers, EmptyScorer, Exclude, Explanation, Occur, RequiredOptionalScorer, Scorer,
     Union, Weight,
@@ -237,6 +241,42 @@ impl<TScoreCombiner: ScoreCombiner + Sync> Weight for BooleanWeight<TScoreCombin
         Ok(())
     }
 
+    fn for_each_until(
+        &self,
+        reader: &SegmentReader,
+        callback: &mut dyn FnMut(DocId, Score) -> ControlFlow<()>,
+    ) -> crate::Result<()> {
+        let scorer = self.complex_scorer(reader, 1.0, &self.score_combiner_fn)?;
+        match scorer {
+            SpecializedScorer::TermUnion(term_scorers) => {
+                let mut union_scorer = Union::build(term_scorers, &self.score_combiner_fn);
+                for_each_scorer_until(&mut union_scorer, callback);
+            }
+            SpecializedScorer::Other(mut scorer) => {
+                for_each_scorer_until(scorer.as_mut(), callback);
+            }
+        }
+        Ok(())
+    }
+
+    fn for_each_no_score_until(
+        &self,
+        reader: &SegmentReader,
+        callback: &mut dyn FnMut(DocId) -> ControlFlow<()>,
+    ) -> crate::Result<()> {
+        let scorer = self.complex_scorer(reader, 1.0, || DoNothingCombiner)?;
+        match scorer {
+            SpecializedScorer::TermUnion(term_scorers) => {
+                let mut union_scorer = Union::build(term_scorers, &self.score_combiner_fn);
+                for_each_docset_until(&mut union_scorer, callback);
+            }
+            SpecializedScorer::Other(mut scorer) => {
+                for_each_docset_until(scorer.as_mut(), callback);
+            }
+        }
+        Ok(())
+    }
+
     /// Calls `callback` with all of the `(doc, score)` for which score
     /// is exceeding a given threshold.
     ///
@@ -256,10 +296,20 @@ impl<TScoreCombiner: ScoreCombiner + Sync> Weight for BooleanWeight<TScoreCombin
         let scorer = self.complex_scorer(reader, 1.0, &self.score_combiner_fn)?;
         match scorer {
             SpecializedScorer::TermUnion(term_scorers) => {
-                super::block_wand(term_scorers, threshold, callback);
+                super::block_wand(
+                    term_scorers,
+                    threshold,
+                    callback,
+                    reader.cancellation_token(),
+                );
             }
             SpecializedScorer::Other(mut scorer) => {
-                for_each_pruning_scorer(scorer.as_mut(), threshold, callback);
+                for_each_pruning_scorer(
+                    scorer.as_mut(),
+                    threshold,
+                    callback,
+                    reader.cancellation_token(),
+                );
             }
         }
         Ok(())
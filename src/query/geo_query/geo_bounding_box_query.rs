@@ -0,0 +1,153 @@
+use super::geo_weight::{build_geo_weight, GeoRect, GeoShape};
+use crate::query::{EnableScoring, Query, Weight};
+use crate::schema::{Field, GeoPoint};
+
+/// `GeoBoundingBoxQuery` matches the documents with a geo point within a bounding box.
+///
+/// The bounding box is given by its top left (north-west) and bottom right (south-east)
+/// corners. If the longitude of the top left corner is greater than the one of the bottom
+/// right corner, the bounding box crosses the antimeridian.
+///
+/// The field needs to be an indexed geo point field.
+///
+/// ```rust
+/// use tantivy::collector::Count;
+/// use tantivy::query::GeoBoundingBoxQuery;
+/// use tantivy::schema::{GeoPoint, Schema, INDEXED};
+/// use tantivy::{doc, Index};
+/// # fn test() -> tantivy::Result<()> {
+/// let mut schema_builder = Schema::builder();
+/// let location = schema_builder.add_geo_point_field("location", INDEXED);
+/// let schema = schema_builder.build();
+///
+/// let index = Index::create_in_ram(schema);
+/// let mut index_writer = index.writer(3_000_000)?;
+/// index_writer.add_document(doc!(location => GeoPoint::new(48.8566, 2.3522)))?;
+/// index_writer.add_document(doc!(location => GeoPoint::new(51.5074, -0.1278)))?;
+/// index_writer.commit()?;
+///
+/// let reader = index.reader()?;
+/// let searcher = reader.searcher();
+/// let france = GeoBoundingBoxQuery::new(
+///     location,
+///     GeoPoint::new(51.1, -5.2),
+///     GeoPoint::new(42.3, 8.2),
+/// );
+/// let count = searcher.search(&france, &Count)?;
+/// assert_eq!(count, 1);
+/// # Ok(())
+/// # }
+/// # assert!(test().is_ok());
+/// ```
+#[derive(Clone, Debug)]
+pub struct GeoBoundingBoxQuery {
+    field: Field,
+    top_left: GeoPoint,
+    bottom_right: GeoPoint,
+}
+
+impl GeoBoundingBoxQuery {
+    /// Creates a new `GeoBoundingBoxQuery` matching the geo points of `field` within the
+    /// bounding box with the corners `top_left` and `bottom_right`.
+    pub fn new(field: Field, top_left: GeoPoint, bottom_right: GeoPoint) -> GeoBoundingBoxQuery {
+        GeoBoundingBoxQuery {
+            field,
+            top_left,
+            bottom_right,
+        }
+    }
+
+    fn crosses_antimeridian(&self) -> bool {
+        self.top_left.lon > self.bottom_right.lon
+    }
+}
+
+impl GeoShape for GeoBoundingBoxQuery {
+    fn bounding_boxes(&self) -> Vec<GeoRect> {
+        let min_lat = self.bottom_right.lat;
+        let max_lat = self.top_left.lat;
+        if self.crosses_antimeridian() {
+            vec![
+                GeoRect {
+                    min_lat,
+                    max_lat,
+                    min_lon: self.top_left.lon,
+                    max_lon: 180.0,
+                },
+                GeoRect {
+                    min_lat,
+                    max_lat,
+                    min_lon: -180.0,
+                    max_lon: self.bottom_right.lon,
+                },
+            ]
+        } else {
+            vec![GeoRect {
+                min_lat,
+                max_lat,
+                min_lon: self.top_left.lon,
+                max_lon: self.bottom_right.lon,
+            }]
+        }
+    }
+
+    fn contains(&self, geo_point: &GeoPoint) -> bool {
+        if geo_point.lat < self.bottom_right.lat || geo_point.lat > self.top_left.lat {
+            return false;
+        }
+        if self.crosses_antimeridian() {
+            geo_point.lon >= self.top_left.lon || geo_point.lon <= self.bottom_right.lon
+        } else {
+            geo_point.lon >= self.top_left.lon && geo_point.lon <= self.bottom_right.lon
+        }
+    }
+}
+
+impl Query for GeoBoundingBoxQuery {
+    fn weight(&self, enable_scoring: EnableScoring<'_>) -> crate::Result<Box<dyn Weight>> {
+        build_geo_weight(self.field, self.clone(), enable_scoring)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GeoBoundingBoxQuery;
+    use crate::query::geo_query::tests::{create_cities_index, matching_cities};
+    use crate::schema::GeoPoint;
+
+    #[test]
+    fn test_geo_bounding_box_query() -> crate::Result<()> {
+        let (index, location) = create_cities_index()?;
+        let europe = GeoBoundingBoxQuery::new(
+            location,
+            GeoPoint::new(60.0, -10.0),
+            GeoPoint::new(35.0, 30.0),
+        );
+        assert_eq!(
+            matching_cities(&index, &europe)?,
+            vec!["Paris", "London", "Berlin", "Madrid"]
+        );
+        let nowhere = GeoBoundingBoxQuery::new(
+            location,
+            GeoPoint::new(10.0, -40.0),
+            GeoPoint::new(0.0, -30.0),
+        );
+        assert!(matching_cities(&index, &nowhere)?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_geo_bounding_box_query_crossing_antimeridian() -> crate::Result<()> {
+        let (index, location) = create_cities_index()?;
+        let pacific = GeoBoundingBoxQuery::new(
+            location,
+            GeoPoint::new(70.0, 170.0),
+            GeoPoint::new(-50.0, -145.0),
+        );
+        assert_eq!(
+            matching_cities(&index, &pacific)?,
+            vec!["Auckland", "Honolulu", "Anchorage"]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,138 @@
+use super::geo_weight::{build_geo_weight, GeoRect, GeoShape};
+use crate::query::{EnableScoring, Query, Weight};
+use crate::schema::geo_point::EARTH_RADIUS_METERS;
+use crate::schema::{Field, GeoPoint};
+
+/// `GeoDistanceQuery` matches the documents with a geo point within a given distance of a
+/// center.
+///
+/// Distances are great-circle distances in meters, as computed by [`GeoPoint::distance`].
+///
+/// The field needs to be an indexed geo point field.
+///
+/// ```rust
+/// use tantivy::collector::Count;
+/// use tantivy::query::GeoDistanceQuery;
+/// use tantivy::schema::{GeoPoint, Schema, INDEXED};
+/// use tantivy::{doc, Index};
+/// # fn test() -> tantivy::Result<()> {
+/// let mut schema_builder = Schema::builder();
+/// let location = schema_builder.add_geo_point_field("location", INDEXED);
+/// let schema = schema_builder.build();
+///
+/// let index = Index::create_in_ram(schema);
+/// let mut index_writer = index.writer(3_000_000)?;
+/// index_writer.add_document(doc!(location => GeoPoint::new(48.8566, 2.3522)))?;
+/// index_writer.add_document(doc!(location => GeoPoint::new(51.5074, -0.1278)))?;
+/// index_writer.commit()?;
+///
+/// let reader = index.reader()?;
+/// let searcher = reader.searcher();
+/// let near_versailles = GeoDistanceQuery::new(location, GeoPoint::new(48.8049, 2.1204), 50_000.0);
+/// let count = searcher.search(&near_versailles, &Count)?;
+/// assert_eq!(count, 1);
+/// # Ok(())
+/// # }
+/// # assert!(test().is_ok());
+/// ```
+#[derive(Clone, Debug)]
+pub struct GeoDistanceQuery {
+    field: Field,
+    center: GeoPoint,
+    distance: f64,
+}
+
+impl GeoDistanceQuery {
+    /// Creates a new `GeoDistanceQuery` matching the geo points of `field` within
+    /// `distance` meters of `center`.
+    pub fn new(field: Field, center: GeoPoint, distance: f64) -> GeoDistanceQuery {
+        GeoDistanceQuery {
+            field,
+            center,
+            distance,
+        }
+    }
+}
+
+impl GeoShape for GeoDistanceQuery {
+    fn bounding_boxes(&self) -> Vec<GeoRect> {
+        // Angular distance, in radians.
+        let angular_distance = self.distance / EARTH_RADIUS_METERS;
+        let delta_lat = angular_distance.to_degrees();
+        let min_lat = self.center.lat - delta_lat;
+        let max_lat = self.center.lat + delta_lat;
+        let all_lons = |min_lat: f64, max_lat: f64| GeoRect {
+            min_lat: min_lat.max(-90.0),
+            max_lat: max_lat.min(90.0),
+            min_lon: -180.0,
+            max_lon: 180.0,
+        };
+        if min_lat <= -90.0 || max_lat >= 90.0 {
+            // The circle contains a pole.
+            return vec![all_lons(min_lat, max_lat)];
+        }
+        let sin_delta_lon = angular_distance.sin() / self.center.lat.to_radians().cos();
+        if angular_distance >= std::f64::consts::FRAC_PI_2 || sin_delta_lon >= 1.0 {
+            return vec![all_lons(min_lat, max_lat)];
+        }
+        let delta_lon = sin_delta_lon.asin().to_degrees();
+        let min_lon = self.center.lon - delta_lon;
+        let max_lon = self.center.lon + delta_lon;
+        let rect = |min_lon: f64, max_lon: f64| GeoRect {
+            min_lat,
+            max_lat,
+            min_lon,
+            max_lon,
+        };
+        if min_lon < -180.0 {
+            vec![rect(min_lon + 360.0, 180.0), rect(-180.0, max_lon)]
+        } else if max_lon > 180.0 {
+            vec![rect(min_lon, 180.0), rect(-180.0, max_lon - 360.0)]
+        } else {
+            vec![rect(min_lon, max_lon)]
+        }
+    }
+
+    fn contains(&self, geo_point: &GeoPoint) -> bool {
+        self.center.distance(geo_point) <= self.distance
+    }
+}
+
+impl Query for GeoDistanceQuery {
+    fn weight(&self, enable_scoring: EnableScoring<'_>) -> crate::Result<Box<dyn Weight>> {
+        build_geo_weight(self.field, self.clone(), enable_scoring)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GeoDistanceQuery;
+    use crate::query::geo_query::tests::{create_cities_index, matching_cities};
+    use crate::schema::GeoPoint;
+
+    #[test]
+    fn test_geo_distance_query() -> crate::Result<()> {
+        let (index, location) = create_cities_index()?;
+        let brussels = GeoPoint::new(50.8503, 4.3517);
+        let near_brussels = GeoDistanceQuery::new(location, brussels, 400_000.0);
+        assert_eq!(
+            matching_cities(&index, &near_brussels)?,
+            vec!["Paris", "London"]
+        );
+        let far_from_brussels = GeoDistanceQuery::new(location, brussels, 1_500_000.0);
+        assert_eq!(
+            matching_cities(&index, &far_from_brussels)?,
+            vec!["Paris", "London", "Berlin", "Madrid"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_geo_distance_query_crossing_antimeridian() -> crate::Result<()> {
+        let (index, location) = create_cities_index()?;
+        let fiji = GeoPoint::new(-17.7134, 178.065);
+        let near_fiji = GeoDistanceQuery::new(location, fiji, 2_500_000.0);
+        assert_eq!(matching_cities(&index, &near_fiji)?, vec!["Auckland"]);
+        Ok(())
+    }
+}
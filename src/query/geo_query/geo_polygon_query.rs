@@ -0,0 +1,164 @@
+use super::geo_weight::{build_geo_weight, GeoRect, GeoShape};
+use crate::query::{EnableScoring, Query, Weight};
+use crate::schema::{Field, GeoPoint};
+use crate::TantivyError;
+
+/// `GeoPolygonQuery` matches the documents with a geo point within a polygon.
+///
+/// The polygon is given by its vertices, in order. It is closed implicitly, and its edges are
+/// straight lines in the latitude/longitude plane. Polygons crossing the antimeridian are not
+/// supported.
+///
+/// The field needs to be an indexed geo point field, and the polygon needs at least three
+/// vertices.
+///
+/// ```rust
+/// use tantivy::collector::Count;
+/// use tantivy::query::GeoPolygonQuery;
+/// use tantivy::schema::{GeoPoint, Schema, INDEXED};
+/// use tantivy::{doc, Index};
+/// # fn test() -> tantivy::Result<()> {
+/// let mut schema_builder = Schema::builder();
+/// let location = schema_builder.add_geo_point_field("location", INDEXED);
+/// let schema = schema_builder.build();
+///
+/// let index = Index::create_in_ram(schema);
+/// let mut index_writer = index.writer(3_000_000)?;
+/// index_writer.add_document(doc!(location => GeoPoint::new(48.8566, 2.3522)))?;
+/// index_writer.add_document(doc!(location => GeoPoint::new(51.5074, -0.1278)))?;
+/// index_writer.commit()?;
+///
+/// let reader = index.reader()?;
+/// let searcher = reader.searcher();
+/// let triangle = GeoPolygonQuery::new(
+///     location,
+///     vec![
+///         GeoPoint::new(50.0, 0.0),
+///         GeoPoint::new(47.0, 8.0),
+///         GeoPoint::new(47.0, -2.0),
+///     ],
+/// );
+/// let count = searcher.search(&triangle, &Count)?;
+/// assert_eq!(count, 1);
+/// # Ok(())
+/// # }
+/// # assert!(test().is_ok());
+/// ```
+#[derive(Clone, Debug)]
+pub struct GeoPolygonQuery {
+    field: Field,
+    vertices: Vec<GeoPoint>,
+}
+
+impl GeoPolygonQuery {
+    /// Creates a new `GeoPolygonQuery` matching the geo points of `field` within the
+    /// polygon with the given `vertices`.
+    pub fn new(field: Field, vertices: Vec<GeoPoint>) -> GeoPolygonQuery {
+        GeoPolygonQuery { field, vertices }
+    }
+}
+
+impl GeoShape for GeoPolygonQuery {
+    fn bounding_boxes(&self) -> Vec<GeoRect> {
+        let mut bbox = GeoRect {
+            min_lat: f64::MAX,
+            max_lat: f64::MIN,
+            min_lon: f64::MAX,
+            max_lon: f64::MIN,
+        };
+        for vertex in &self.vertices {
+            bbox.min_lat = bbox.min_lat.min(vertex.lat);
+            bbox.max_lat = bbox.max_lat.max(vertex.lat);
+            bbox.min_lon = bbox.min_lon.min(vertex.lon);
+            bbox.max_lon = bbox.max_lon.max(vertex.lon);
+        }
+        vec![bbox]
+    }
+
+    /// Checks whether the point is within the polygon by casting a ray towards the east
+    /// and counting the edges it crosses.
+    fn contains(&self, geo_point: &GeoPoint) -> bool {
+        let mut inside = false;
+        let mut previous = match self.vertices.last() {
+            Some(vertex) => vertex,
+            None => return false,
+        };
+        for vertex in &self.vertices {
+            if (vertex.lat > geo_point.lat) != (previous.lat > geo_point.lat) {
+                let crossing_lon = vertex.lon
+                    + (geo_point.lat - vertex.lat) * (previous.lon - vertex.lon)
+                        / (previous.lat - vertex.lat);
+                if geo_point.lon < crossing_lon {
+                    inside = !inside;
+                }
+            }
+            previous = vertex;
+        }
+        inside
+    }
+}
+
+impl Query for GeoPolygonQuery {
+    fn weight(&self, enable_scoring: EnableScoring<'_>) -> crate::Result<Box<dyn Weight>> {
+        if self.vertices.len() < 3 {
+            return Err(TantivyError::InvalidArgument(format!(
+                "A geo polygon requires at least 3 vertices, got {}.",
+                self.vertices.len()
+            )));
+        }
+        build_geo_weight(self.field, self.clone(), enable_scoring)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GeoPolygonQuery;
+    use crate::query::geo_query::tests::{create_cities_index, matching_cities};
+    use crate::query::{EnableScoring, Query};
+    use crate::schema::GeoPoint;
+    use crate::TantivyError;
+
+    #[test]
+    fn test_geo_polygon_query() -> crate::Result<()> {
+        let (index, location) = create_cities_index()?;
+        // A concave polygon whose bounding box contains Paris, but not the polygon itself.
+        let polygon = GeoPolygonQuery::new(
+            location,
+            vec![
+                GeoPoint::new(54.0, 15.0),
+                GeoPoint::new(47.0, 15.0),
+                GeoPoint::new(47.0, 0.0),
+                GeoPoint::new(48.0, 0.0),
+                GeoPoint::new(48.0, 10.0),
+                GeoPoint::new(54.0, 10.0),
+            ],
+        );
+        assert_eq!(matching_cities(&index, &polygon)?, vec!["Berlin"]);
+        let polygon = GeoPolygonQuery::new(
+            location,
+            vec![
+                GeoPoint::new(54.0, 15.0),
+                GeoPoint::new(47.0, 15.0),
+                GeoPoint::new(47.0, 0.0),
+                GeoPoint::new(54.0, 0.0),
+            ],
+        );
+        assert_eq!(matching_cities(&index, &polygon)?, vec!["Paris", "Berlin"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_geo_polygon_query_too_few_vertices() -> crate::Result<()> {
+        let (index, location) = create_cities_index()?;
+        let searcher = index.reader()?.searcher();
+        let segment = GeoPolygonQuery::new(
+            location,
+            vec![GeoPoint::new(54.0, 15.0), GeoPoint::new(47.0, 15.0)],
+        );
+        assert!(matches!(
+            segment.weight(EnableScoring::Disabled(searcher.schema())),
+            Err(TantivyError::InvalidArgument(_))
+        ));
+        Ok(())
+    }
+}
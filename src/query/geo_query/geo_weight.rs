@@ -0,0 +1,241 @@
+use common::BitSet;
+
+use crate::core::SegmentReader;
+use crate::query::explanation::does_not_match;
+use crate::query::{BitSetDocSet, ConstScorer, EnableScoring, Explanation, Scorer, Weight};
+use crate::schema::{Field, FieldType, GeoPoint, IndexRecordOption};
+use crate::{DocId, Score, TantivyError};
+
+/// Deepest level of the quadtree used to decompose a shape into Z-order code ranges.
+///
+/// At this level, a cell spans about 300 meters of latitude.
+const MAX_LEVEL: u32 = 16;
+
+/// Maximum number of cells a shape is decomposed into.
+const MAX_NUM_CELLS: usize = 256;
+
+/// A rectangle in the latitude/longitude plane, which does not cross the antimeridian.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct GeoRect {
+    pub min_lat: f64,
+    pub max_lat: f64,
+    pub min_lon: f64,
+    pub max_lon: f64,
+}
+
+impl GeoRect {
+    fn intersects(&self, other: &GeoRect) -> bool {
+        self.min_lat <= other.max_lat
+            && other.min_lat <= self.max_lat
+            && self.min_lon <= other.max_lon
+            && other.min_lon <= self.max_lon
+    }
+
+    fn contains_rect(&self, other: &GeoRect) -> bool {
+        self.min_lat <= other.min_lat
+            && other.max_lat <= self.max_lat
+            && self.min_lon <= other.min_lon
+            && other.max_lon <= self.max_lon
+    }
+}
+
+/// A shape matched by a geo query.
+pub(crate) trait GeoShape: Clone + Send + Sync + 'static {
+    /// Returns rectangles covering the shape.
+    ///
+    /// They are used to select the terms to check, and do not need to be tight.
+    fn bounding_boxes(&self) -> Vec<GeoRect>;
+
+    /// Returns true if the shape contains `geo_point`.
+    fn contains(&self, geo_point: &GeoPoint) -> bool;
+}
+
+/// A cell of the quadtree over the Z-order codes, made of the codes sharing the
+/// `2 * level` highest bits of `code`.
+#[derive(Clone, Copy)]
+struct GeoCell {
+    code: u64,
+    level: u32,
+}
+
+impl GeoCell {
+    fn rect(&self) -> GeoRect {
+        let (south_west, north_east) = GeoPoint::z_order_cell_corners(self.code, self.level);
+        GeoRect {
+            min_lat: south_west.lat,
+            max_lat: north_east.lat,
+            min_lon: south_west.lon,
+            max_lon: north_east.lon,
+        }
+    }
+
+    fn last_code(&self) -> u64 {
+        self.code | (u64::MAX >> (2 * self.level))
+    }
+
+    fn children(&self) -> impl Iterator<Item = GeoCell> {
+        let level = self.level + 1;
+        let code = self.code;
+        (0..4u64).map(move |child| GeoCell {
+            code: code | (child << (64 - 2 * level)),
+            level,
+        })
+    }
+}
+
+/// Returns the sorted and disjoint ranges of Z-order codes, bounds included,
+/// covering the `bounding_boxes`.
+fn z_order_ranges(bounding_boxes: &[GeoRect]) -> Vec<(u64, u64)> {
+    let intersects = |cell: &GeoCell| {
+        let rect = cell.rect();
+        bounding_boxes.iter().any(|bbox| bbox.intersects(&rect))
+    };
+    let mut covered_cells: Vec<GeoCell> = Vec::new();
+    let mut frontier: Vec<GeoCell> = vec![GeoCell { code: 0, level: 0 }];
+    frontier.retain(intersects);
+    for _ in 0..MAX_LEVEL {
+        let mut next_covered_cells = Vec::new();
+        let mut next_frontier = Vec::new();
+        for cell in &frontier {
+            let rect = cell.rect();
+            if bounding_boxes.iter().any(|bbox| bbox.contains_rect(&rect)) {
+                next_covered_cells.push(*cell);
+            } else {
+                next_frontier.extend(cell.children().filter(intersects));
+            }
+        }
+        if covered_cells.len() + next_covered_cells.len() + next_frontier.len() > MAX_NUM_CELLS {
+            break;
+        }
+        covered_cells.extend(next_covered_cells);
+        frontier = next_frontier;
+    }
+    covered_cells.extend(frontier);
+    covered_cells.sort_unstable_by_key(|cell| cell.code);
+    let mut ranges: Vec<(u64, u64)> = Vec::with_capacity(covered_cells.len());
+    for cell in covered_cells {
+        match ranges.last_mut() {
+            Some((_, last)) if last.checked_add(1) == Some(cell.code) => {
+                *last = cell.last_code();
+            }
+            _ => ranges.push((cell.code, cell.last_code())),
+        }
+    }
+    ranges
+}
+
+/// Weight shared by the geo queries.
+///
+/// The shape is decomposed into ranges of Z-order codes. The terms within these ranges
+/// are then decoded, and the documents of the terms whose geo point is within the shape
+/// are matched.
+pub(crate) struct GeoWeight<TShape> {
+    field: Field,
+    shape: TShape,
+}
+
+/// Creates the weight of a geo query matching the geo points of `field` within `shape`.
+///
+/// Returns an error if `field` is not an indexed geo point field.
+pub(crate) fn build_geo_weight<TShape: GeoShape>(
+    field: Field,
+    shape: TShape,
+    enable_scoring: EnableScoring<'_>,
+) -> crate::Result<Box<dyn Weight>> {
+    let field_entry = enable_scoring.schema().get_field_entry(field);
+    match field_entry.field_type() {
+        FieldType::GeoPoint(options) if options.is_indexed() => {
+            Ok(Box::new(GeoWeight { field, shape }))
+        }
+        FieldType::GeoPoint(_) => Err(TantivyError::SchemaError(format!(
+            "Field {:?} is not indexed.",
+            field_entry.name()
+        ))),
+        field_type => Err(TantivyError::SchemaError(format!(
+            "Geo queries require a geo point field, but the field {:?} is of type {:?}.",
+            field_entry.name(),
+            field_type.value_type()
+        ))),
+    }
+}
+
+impl<TShape: GeoShape> Weight for GeoWeight<TShape> {
+    fn scorer(&self, reader: &SegmentReader, boost: Score) -> crate::Result<Box<dyn Scorer>> {
+        let max_doc = reader.max_doc();
+        let mut doc_bitset = BitSet::with_max_value(max_doc);
+
+        let inverted_index = reader.inverted_index(self.field)?;
+        let term_dict = inverted_index.terms();
+        for (first_code, last_code) in z_order_ranges(&self.shape.bounding_boxes()) {
+            let mut term_range = term_dict
+                .range()
+                .ge(first_code.to_be_bytes())
+                .le(last_code.to_be_bytes())
+                .into_stream()?;
+            while term_range.advance() {
+                let mut code_bytes = [0u8; 8];
+                code_bytes.copy_from_slice(term_range.key());
+                let geo_point = GeoPoint::from_z_order(u64::from_be_bytes(code_bytes));
+                if !self.shape.contains(&geo_point) {
+                    continue;
+                }
+                let term_info = term_range.value();
+                let mut block_segment_postings = inverted_index
+                    .read_block_postings_from_terminfo(term_info, IndexRecordOption::Basic)?;
+                loop {
+                    let docs = block_segment_postings.docs();
+                    if docs.is_empty() {
+                        break;
+                    }
+                    for &doc in docs {
+                        doc_bitset.insert(doc);
+                    }
+                    block_segment_postings.advance();
+                }
+            }
+        }
+        let doc_bitset = BitSetDocSet::from(doc_bitset);
+        Ok(Box::new(ConstScorer::new(doc_bitset, boost)))
+    }
+
+    fn explain(&self, reader: &SegmentReader, doc: DocId) -> crate::Result<Explanation> {
+        let mut scorer = self.scorer(reader, 1.0)?;
+        if scorer.seek(doc) != doc {
+            return Err(does_not_match(doc));
+        }
+        Ok(Explanation::new("GeoQuery", 1.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{z_order_ranges, GeoRect};
+    use crate::schema::GeoPoint;
+
+    #[test]
+    fn test_z_order_ranges_cover_bounding_box() {
+        let bbox = GeoRect {
+            min_lat: 48.0,
+            max_lat: 49.0,
+            min_lon: 2.0,
+            max_lon: 3.0,
+        };
+        let ranges = z_order_ranges(&[bbox]);
+        assert!(!ranges.is_empty());
+        assert!(ranges.len() <= super::MAX_NUM_CELLS);
+        assert!(ranges.windows(2).all(|pair| pair[0].1 < pair[1].0));
+        let is_covered = |geo_point: GeoPoint| {
+            let code = geo_point.to_z_order();
+            ranges
+                .iter()
+                .any(|&(first_code, last_code)| first_code <= code && code <= last_code)
+        };
+        for lat in [48.0, 48.3, 48.999] {
+            for lon in [2.0, 2.5, 2.999] {
+                assert!(is_covered(GeoPoint::new(lat, lon)));
+            }
+        }
+        assert!(!is_covered(GeoPoint::new(0.0, 0.0)));
+        assert!(!is_covered(GeoPoint::new(48.5, -2.5)));
+    }
+}
@@ -0,0 +1,97 @@
+mod geo_bounding_box_query;
+mod geo_distance_query;
+mod geo_polygon_query;
+mod geo_weight;
+
+pub use self::geo_bounding_box_query::GeoBoundingBoxQuery;
+pub use self::geo_distance_query::GeoDistanceQuery;
+pub use self::geo_polygon_query::GeoPolygonQuery;
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use crate::collector::DocSetCollector;
+    use crate::query::{GeoDistanceQuery, Query, TermQuery};
+    use crate::schema::{Field, GeoPoint, IndexRecordOption, Schema, FAST, INDEXED, STORED};
+    use crate::{DocAddress, Index, TantivyError, Term};
+
+    pub(crate) const CITIES: [(&str, f64, f64); 9] = [
+        ("Paris", 48.8566, 2.3522),
+        ("London", 51.5074, -0.1278),
+        ("Berlin", 52.52, 13.405),
+        ("Madrid", 40.4168, -3.7038),
+        ("New York", 40.7128, -74.006),
+        ("Auckland", -36.8485, 174.7633),
+        ("Honolulu", 21.3069, -157.8583),
+        ("Anchorage", 61.2181, -149.9003),
+        ("Sydney", -33.8688, 151.2093),
+    ];
+
+    /// Creates an index with one document per city, in the order of `CITIES`.
+    pub(crate) fn create_cities_index() -> crate::Result<(Index, Field)> {
+        let mut schema_builder = Schema::builder();
+        let location = schema_builder.add_geo_point_field("location", INDEXED | STORED | FAST);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        for (_, lat, lon) in CITIES {
+            index_writer.add_document(doc!(location => GeoPoint::new(lat, lon)))?;
+        }
+        index_writer.commit()?;
+        Ok((index, location))
+    }
+
+    /// Returns the names of the cities matching `query`, in the order of `CITIES`.
+    pub(crate) fn matching_cities(
+        index: &Index,
+        query: &dyn Query,
+    ) -> crate::Result<Vec<&'static str>> {
+        let searcher = index.reader()?.searcher();
+        let mut doc_addresses: Vec<DocAddress> = searcher
+            .search(query, &DocSetCollector)?
+            .into_iter()
+            .collect();
+        doc_addresses.sort();
+        Ok(doc_addresses
+            .into_iter()
+            .map(|doc_address| CITIES[doc_address.doc_id as usize].0)
+            .collect())
+    }
+
+    #[test]
+    fn test_geo_point_field_stored_and_fast() -> crate::Result<()> {
+        let (index, location) = create_cities_index()?;
+        let searcher = index.reader()?.searcher();
+        let segment_reader = searcher.segment_reader(0);
+        let geo_point_reader = segment_reader.fast_fields().geo_point(location)?;
+        let (_, lat, lon) = CITIES[2];
+        let geo_point = geo_point_reader.get_val(2);
+        assert!((geo_point.lat - lat).abs() < 1e-7);
+        assert!((geo_point.lon - lon).abs() < 1e-7);
+        let doc = searcher.doc(DocAddress::new(0, 2))?;
+        assert_eq!(
+            doc.get_first(location)
+                .and_then(|value| value.as_geo_point()),
+            Some(GeoPoint::new(lat, lon))
+        );
+        let term = Term::from_field_geo_point(location, GeoPoint::new(lat, lon));
+        let term_query = TermQuery::new(term, IndexRecordOption::Basic);
+        assert_eq!(searcher.search(&term_query, &DocSetCollector)?.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_geo_query_requires_indexed_geo_point_field() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let not_indexed = schema_builder.add_geo_point_field("not_indexed", STORED);
+        let not_geo = schema_builder.add_u64_field("not_geo", INDEXED);
+        let index = Index::create_in_ram(schema_builder.build());
+        let searcher = index.reader()?.searcher();
+        for field in [not_indexed, not_geo] {
+            let query = GeoDistanceQuery::new(field, GeoPoint::new(0.0, 0.0), 1_000.0);
+            assert!(matches!(
+                searcher.search(&query, &DocSetCollector),
+                Err(TantivyError::SchemaError(_))
+            ));
+        }
+        Ok(())
+    }
+}
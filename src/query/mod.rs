@@ -10,9 +10,11 @@ mod empty_query;
 mod exclude;
 mod explanation;
 mod fuzzy_query;
+mod geo_query;
 mod intersection;
 mod more_like_this;
 mod phrase_query;
+mod prefix_query;
 mod query;
 mod query_parser;
 mod range_query;
@@ -21,9 +23,11 @@ mod regex_query;
 mod reqopt_scorer;
 mod scorer;
 mod set_query;
+mod span_query;
 mod term_query;
 mod union;
 mod weight;
+mod wildcard_query;
 
 #[cfg(test)]
 mod vec_docset;
@@ -46,9 +50,11 @@ pub use self::explanation::Explanation;
 #[cfg(test)]
 pub(crate) use self::fuzzy_query::DfaWrapper;
 pub use self::fuzzy_query::FuzzyTermQuery;
+pub use self::geo_query::{GeoBoundingBoxQuery, GeoDistanceQuery, GeoPolygonQuery};
 pub use self::intersection::{intersect_scorers, Intersection};
 pub use self::more_like_this::{MoreLikeThisQuery, MoreLikeThisQueryBuilder};
-pub use self::phrase_query::PhraseQuery;
+pub use self::phrase_query::{MultiPhraseQuery, PhrasePrefixQuery, PhraseQuery};
+pub use self::prefix_query::PrefixQuery;
 pub use self::query::{EnableScoring, Query, QueryClone};
 pub use self::query_parser::{QueryParser, QueryParserError};
 pub use self::range_query::RangeQuery;
@@ -59,11 +65,16 @@ pub use self::score_combiner::{
 };
 pub use self::scorer::Scorer;
 pub use self::set_query::TermSetQuery;
+pub use self::span_query::{
+    Span, SpanFirstQuery, SpanNearQuery, SpanNotQuery, SpanOrQuery, SpanQuery, SpanQueryClone,
+    SpanScorer, SpanTermQuery, SpanWeight, Spans,
+};
 pub use self::term_query::TermQuery;
 pub use self::union::Union;
 #[cfg(test)]
 pub use self::vec_docset::VecDocSet;
 pub use self::weight::Weight;
+pub use self::wildcard_query::WildcardQuery;
 
 #[cfg(test)]
 mod tests {
@@ -1,7 +1,15 @@
+mod multi_phrase_query;
+mod multi_phrase_weight;
+mod phrase_prefix_query;
+mod phrase_prefix_weight;
 mod phrase_query;
 mod phrase_scorer;
 mod phrase_weight;
 
+pub use self::multi_phrase_query::MultiPhraseQuery;
+pub use self::multi_phrase_weight::MultiPhraseWeight;
+pub use self::phrase_prefix_query::PhrasePrefixQuery;
+pub use self::phrase_prefix_weight::PhrasePrefixWeight;
 pub use self::phrase_query::PhraseQuery;
 pub use self::phrase_scorer::PhraseScorer;
 pub use self::phrase_weight::PhraseWeight;
@@ -0,0 +1,244 @@
+use super::MultiPhraseWeight;
+use crate::query::bm25::Bm25Weight;
+use crate::query::{EnableScoring, Query, Weight};
+use crate::schema::{Field, IndexRecordOption, Term};
+
+/// `MultiPhraseQuery` matches a sequence of words, where each position
+/// of the sequence can accept several terms.
+///
+/// For instance the multi phrase query for `"new (york|jersey)"` will match
+/// both the sentences
+///
+/// **I live in New York.**
+///
+/// **I live in New Jersey.**
+///
+/// [Slop](MultiPhraseQuery::set_slop) allows leniency in term proximity,
+/// like for a [`PhraseQuery`](crate::query::PhraseQuery).
+///
+/// Using a `MultiPhraseQuery` on a field requires positions
+/// to be indexed for this field.
+///
+/// ```rust
+/// use tantivy::collector::Count;
+/// use tantivy::query::MultiPhraseQuery;
+/// use tantivy::schema::{Schema, TEXT};
+/// use tantivy::{doc, Index, Term};
+/// # fn test() -> tantivy::Result<()> {
+/// let mut schema_builder = Schema::builder();
+/// let title = schema_builder.add_text_field("title", TEXT);
+/// let schema = schema_builder.build();
+///
+/// let index = Index::create_in_ram(schema);
+/// let mut index_writer = index.writer(3_000_000)?;
+/// index_writer.add_document(doc!(title => "A night in New York"))?;
+/// index_writer.add_document(doc!(title => "New Jersey Turnpike"))?;
+/// index_writer.add_document(doc!(title => "York is not new"))?;
+/// index_writer.commit()?;
+///
+/// let reader = index.reader()?;
+/// let searcher = reader.searcher();
+/// let query = MultiPhraseQuery::new(vec![
+///     vec![Term::from_field_text(title, "new")],
+///     vec![
+///         Term::from_field_text(title, "york"),
+///         Term::from_field_text(title, "jersey"),
+///     ],
+/// ]);
+/// let count = searcher.search(&query, &Count)?;
+/// assert_eq!(count, 2);
+/// # Ok(())
+/// # }
+/// # assert!(test().is_ok());
+/// ```
+#[derive(Clone, Debug)]
+pub struct MultiPhraseQuery {
+    field: Field,
+    phrase_terms: Vec<(usize, Vec<Term>)>,
+    slop: u32,
+}
+
+impl MultiPhraseQuery {
+    /// Creates a new `MultiPhraseQuery` given, for each position, the list of
+    /// the terms it accepts.
+    ///
+    /// There must be at least two positions, each accepting at least one term,
+    /// and all terms must belong to the same field.
+    /// Offset for each position will be same as index in the Vector
+    pub fn new(terms: Vec<Vec<Term>>) -> MultiPhraseQuery {
+        let terms_with_offset = terms.into_iter().enumerate().collect();
+        MultiPhraseQuery::new_with_offset(terms_with_offset)
+    }
+
+    /// Creates a new `MultiPhraseQuery` given, for each position, its offset and
+    /// the list of the terms it accepts.
+    ///
+    /// Can be used to provide custom offset for each position.
+    pub fn new_with_offset(mut terms: Vec<(usize, Vec<Term>)>) -> MultiPhraseQuery {
+        assert!(
+            terms.len() > 1,
+            "A multi phrase query is required to have strictly more than one position."
+        );
+        assert!(
+            terms
+                .iter()
+                .all(|(_, position_terms)| !position_terms.is_empty()),
+            "Each position of a multi phrase query must accept at least one term."
+        );
+        terms.sort_by_key(|&(offset, _)| offset);
+        let field = terms[0].1[0].field();
+        assert!(
+            terms
+                .iter()
+                .flat_map(|(_, position_terms)| position_terms)
+                .all(|term| term.field() == field),
+            "All terms from a multi phrase query must belong to the same field"
+        );
+        MultiPhraseQuery {
+            field,
+            phrase_terms: terms,
+            slop: 0,
+        }
+    }
+
+    /// Slop allowed for the phrase.
+    ///
+    /// The query will match if its terms are separated by `slop` terms at most.
+    /// By default the slop is 0 meaning query terms need to be adjacent.
+    pub fn set_slop(&mut self, value: u32) {
+        self.slop = value;
+    }
+
+    /// The [`Field`] this `MultiPhraseQuery` is targeting.
+    pub fn field(&self) -> Field {
+        self.field
+    }
+
+    /// Returns the [`MultiPhraseWeight`] for the given multi phrase query given a specific
+    /// `searcher`.
+    pub(crate) fn multi_phrase_weight(
+        &self,
+        enable_scoring: EnableScoring<'_>,
+    ) -> crate::Result<MultiPhraseWeight> {
+        let schema = enable_scoring.schema();
+        let field_entry = schema.get_field_entry(self.field);
+        let has_positions = field_entry
+            .field_type()
+            .get_index_record_option()
+            .map(IndexRecordOption::has_positions)
+            .unwrap_or(false);
+        if !has_positions {
+            let field_name = field_entry.name();
+            return Err(crate::TantivyError::SchemaError(format!(
+                "Applied multi phrase query on field {:?}, which does not have positions indexed",
+                field_name
+            )));
+        }
+        let bm25_weight_opt = match enable_scoring {
+            EnableScoring::Enabled(searcher) => {
+                let mut terms: Vec<Term> = self
+                    .phrase_terms
+                    .iter()
+                    .flat_map(|(_, position_terms)| position_terms.iter().cloned())
+                    .collect();
+                terms.sort();
+                terms.dedup();
+                Some(Bm25Weight::for_terms(searcher, &terms)?)
+            }
+            EnableScoring::Disabled(_) => None,
+        };
+        Ok(MultiPhraseWeight::new(
+            self.phrase_terms.clone(),
+            bm25_weight_opt,
+            self.slop,
+        ))
+    }
+}
+
+impl Query for MultiPhraseQuery {
+    /// Create the weight associated with a query.
+    ///
+    /// See [`Weight`].
+    fn weight(&self, enable_scoring: EnableScoring<'_>) -> crate::Result<Box<dyn Weight>> {
+        let multi_phrase_weight = self.multi_phrase_weight(enable_scoring)?;
+        Ok(Box::new(multi_phrase_weight))
+    }
+
+    fn query_terms<'a>(&'a self, visitor: &mut dyn FnMut(&'a Term, bool)) {
+        for (_, position_terms) in &self.phrase_terms {
+            for term in position_terms {
+                visitor(term, true);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::create_index;
+    use super::MultiPhraseQuery;
+    use crate::collector::TopDocs;
+    use crate::query::{EnableScoring, Query};
+    use crate::schema::{Schema, Term, STRING};
+    use crate::{Index, TantivyError};
+
+    fn matching_docs(index: &Index, query: &MultiPhraseQuery) -> crate::Result<Vec<u32>> {
+        let searcher = index.reader()?.searcher();
+        let mut docs: Vec<u32> = searcher
+            .search(query, &TopDocs::with_limit(10))?
+            .into_iter()
+            .map(|(_score, doc_address)| doc_address.doc_id)
+            .collect();
+        docs.sort_unstable();
+        Ok(docs)
+    }
+
+    #[test]
+    fn test_multi_phrase_query() -> crate::Result<()> {
+        let index = create_index(&["a b c", "a c b", "d c", "b a d", "c a"])?;
+        let text_field = index.schema().get_field("text").unwrap();
+        let terms = |texts: &[&str]| -> Vec<Term> {
+            texts
+                .iter()
+                .map(|text| Term::from_field_text(text_field, text))
+                .collect()
+        };
+        let query = MultiPhraseQuery::new(vec![terms(&["a", "d"]), terms(&["b", "c"])]);
+        assert_eq!(matching_docs(&index, &query)?, vec![0, 1, 2]);
+        let query = MultiPhraseQuery::new(vec![terms(&["a", "b"]), terms(&["d"])]);
+        assert_eq!(matching_docs(&index, &query)?, vec![3]);
+        let mut query = MultiPhraseQuery::new(vec![terms(&["a"]), terms(&["c", "d"])]);
+        assert_eq!(matching_docs(&index, &query)?, vec![1, 3]);
+        query.set_slop(1);
+        assert_eq!(matching_docs(&index, &query)?, vec![0, 1, 3]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_multi_phrase_query_with_offset() -> crate::Result<()> {
+        let index = create_index(&["a b c", "a x c", "a c"])?;
+        let text_field = index.schema().get_field("text").unwrap();
+        let query = MultiPhraseQuery::new_with_offset(vec![
+            (0, vec![Term::from_field_text(text_field, "a")]),
+            (2, vec![Term::from_field_text(text_field, "c")]),
+        ]);
+        assert_eq!(matching_docs(&index, &query)?, vec![0, 1]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_multi_phrase_query_no_positions() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text_field = schema_builder.add_text_field("text", STRING);
+        let index = Index::create_in_ram(schema_builder.build());
+        let query = MultiPhraseQuery::new(vec![
+            vec![Term::from_field_text(text_field, "a")],
+            vec![Term::from_field_text(text_field, "b")],
+        ]);
+        assert!(matches!(
+            query.weight(EnableScoring::Disabled(&index.schema())),
+            Err(TantivyError::SchemaError(_))
+        ));
+        Ok(())
+    }
+}
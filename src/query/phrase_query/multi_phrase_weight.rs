@@ -0,0 +1,103 @@
+use super::PhraseScorer;
+use crate::core::SegmentReader;
+use crate::fieldnorm::FieldNormReader;
+use crate::postings::UnionPostings;
+use crate::query::bm25::Bm25Weight;
+use crate::query::explanation::does_not_match;
+use crate::query::{EmptyScorer, Explanation, Scorer, Weight};
+use crate::schema::{IndexRecordOption, Term};
+use crate::{DocId, DocSet, Score};
+
+pub struct MultiPhraseWeight {
+    phrase_terms: Vec<(usize, Vec<Term>)>,
+    similarity_weight_opt: Option<Bm25Weight>,
+    slop: u32,
+}
+
+impl MultiPhraseWeight {
+    /// Creates a new multi phrase weight.
+    /// If `similarity_weight_opt` is None, then scoring is disabled
+    pub fn new(
+        phrase_terms: Vec<(usize, Vec<Term>)>,
+        similarity_weight_opt: Option<Bm25Weight>,
+        slop: u32,
+    ) -> MultiPhraseWeight {
+        MultiPhraseWeight {
+            phrase_terms,
+            similarity_weight_opt,
+            slop,
+        }
+    }
+
+    fn fieldnorm_reader(&self, reader: &SegmentReader) -> crate::Result<FieldNormReader> {
+        let field = self.phrase_terms[0].1[0].field();
+        if self.similarity_weight_opt.is_some() {
+            if let Some(fieldnorm_reader) = reader.fieldnorms_readers().get_field(field)? {
+                return Ok(fieldnorm_reader);
+            }
+        }
+        Ok(FieldNormReader::constant(reader.max_doc(), 1))
+    }
+
+    pub(crate) fn phrase_scorer(
+        &self,
+        reader: &SegmentReader,
+        boost: Score,
+    ) -> crate::Result<Option<PhraseScorer<UnionPostings>>> {
+        let similarity_weight_opt = self
+            .similarity_weight_opt
+            .as_ref()
+            .map(|similarity_weight| similarity_weight.boost_by(boost));
+        let fieldnorm_reader = self.fieldnorm_reader(reader)?;
+        let mut term_postings_list = Vec::new();
+        for (offset, terms) in &self.phrase_terms {
+            let mut postings_list = Vec::new();
+            for term in terms {
+                if let Some(postings) = reader
+                    .inverted_index(term.field())?
+                    .read_postings(term, IndexRecordOption::WithFreqsAndPositions)?
+                {
+                    postings_list.push(postings);
+                }
+            }
+            if postings_list.is_empty() {
+                return Ok(None);
+            }
+            term_postings_list.push((*offset, UnionPostings::new(postings_list)));
+        }
+        Ok(Some(PhraseScorer::new(
+            term_postings_list,
+            similarity_weight_opt,
+            fieldnorm_reader,
+            self.slop,
+        )))
+    }
+}
+
+impl Weight for MultiPhraseWeight {
+    fn scorer(&self, reader: &SegmentReader, boost: Score) -> crate::Result<Box<dyn Scorer>> {
+        if let Some(scorer) = self.phrase_scorer(reader, boost)? {
+            Ok(Box::new(scorer))
+        } else {
+            Ok(Box::new(EmptyScorer))
+        }
+    }
+
+    fn explain(&self, reader: &SegmentReader, doc: DocId) -> crate::Result<Explanation> {
+        let mut scorer = match self.phrase_scorer(reader, 1.0)? {
+            Some(scorer) => scorer,
+            None => return Err(does_not_match(doc)),
+        };
+        if scorer.seek(doc) != doc {
+            return Err(does_not_match(doc));
+        }
+        let fieldnorm_reader = self.fieldnorm_reader(reader)?;
+        let fieldnorm_id = fieldnorm_reader.fieldnorm_id(doc);
+        let phrase_count = scorer.phrase_count();
+        let mut explanation = Explanation::new("Multi Phrase Scorer", scorer.score());
+        if let Some(similarity_weight) = self.similarity_weight_opt.as_ref() {
+            explanation.add_detail(similarity_weight.explain(fieldnorm_id, phrase_count));
+        }
+        Ok(explanation)
+    }
+}
@@ -0,0 +1,248 @@
+use super::PhrasePrefixWeight;
+use crate::query::bm25::Bm25Weight;
+use crate::query::{EnableScoring, Query, Weight};
+use crate::schema::{Field, IndexRecordOption, Term};
+
+/// Default maximum number of terms the prefix of a [`PhrasePrefixQuery`] is expanded to.
+const DEFAULT_MAX_EXPANSIONS: u32 = 50;
+
+/// `PhrasePrefixQuery` matches a sequence of words, where the last word
+/// is only a prefix.
+///
+/// For instance the phrase prefix query for `"new yo"` will match
+/// the sentence
+///
+/// **I live in New York.**
+///
+/// The prefix is expanded, in each segment, to the terms of the term dictionary
+/// starting with it, in lexicographic order. The number of expansions is bounded
+/// by [`set_max_expansions`](PhrasePrefixQuery::set_max_expansions), so that short
+/// prefixes stay cheap.
+///
+/// The prefix is not taken into account by the score of the matching documents.
+///
+/// Using a `PhrasePrefixQuery` on a field requires positions
+/// to be indexed for this field.
+///
+/// ```rust
+/// use tantivy::collector::Count;
+/// use tantivy::query::PhrasePrefixQuery;
+/// use tantivy::schema::{Schema, TEXT};
+/// use tantivy::{doc, Index, Term};
+/// # fn test() -> tantivy::Result<()> {
+/// let mut schema_builder = Schema::builder();
+/// let title = schema_builder.add_text_field("title", TEXT);
+/// let schema = schema_builder.build();
+///
+/// let index = Index::create_in_ram(schema);
+/// let mut index_writer = index.writer(3_000_000)?;
+/// index_writer.add_document(doc!(title => "A night in New York"))?;
+/// index_writer.add_document(doc!(title => "New Jersey Turnpike"))?;
+/// index_writer.add_document(doc!(title => "York is not new"))?;
+/// index_writer.commit()?;
+///
+/// let reader = index.reader()?;
+/// let searcher = reader.searcher();
+/// let query = PhrasePrefixQuery::new(vec![
+///     Term::from_field_text(title, "new"),
+///     Term::from_field_text(title, "yo"),
+/// ]);
+/// let count = searcher.search(&query, &Count)?;
+/// assert_eq!(count, 1);
+/// # Ok(())
+/// # }
+/// # assert!(test().is_ok());
+/// ```
+#[derive(Clone, Debug)]
+pub struct PhrasePrefixQuery {
+    field: Field,
+    phrase_terms: Vec<(usize, Term)>,
+    prefix: (usize, Term),
+    max_expansions: u32,
+}
+
+impl PhrasePrefixQuery {
+    /// Creates a new `PhrasePrefixQuery` given a list of terms,
+    /// the last one being the prefix.
+    ///
+    /// There must be at least two terms, and all terms
+    /// must belong to the same field.
+    /// Offset for each term will be same as index in the Vector
+    pub fn new(terms: Vec<Term>) -> PhrasePrefixQuery {
+        let terms_with_offset = terms.into_iter().enumerate().collect();
+        PhrasePrefixQuery::new_with_offset(terms_with_offset)
+    }
+
+    /// Creates a new `PhrasePrefixQuery` given a list of terms and their offsets,
+    /// the term with the largest offset being the prefix.
+    ///
+    /// Can be used to provide custom offset for each term.
+    pub fn new_with_offset(mut terms: Vec<(usize, Term)>) -> PhrasePrefixQuery {
+        assert!(
+            terms.len() > 1,
+            "A phrase prefix query is required to have strictly more than one term."
+        );
+        terms.sort_by_key(|&(offset, _)| offset);
+        let field = terms[0].1.field();
+        assert!(
+            terms[1..].iter().all(|term| term.1.field() == field),
+            "All terms from a phrase prefix query must belong to the same field"
+        );
+        let prefix = terms.pop().unwrap();
+        PhrasePrefixQuery {
+            field,
+            phrase_terms: terms,
+            prefix,
+            max_expansions: DEFAULT_MAX_EXPANSIONS,
+        }
+    }
+
+    /// Maximum number of terms the prefix is expanded to, in each segment.
+    ///
+    /// By default, the prefix is expanded to 50 terms at most.
+    pub fn set_max_expansions(&mut self, value: u32) {
+        self.max_expansions = value;
+    }
+
+    /// The [`Field`] this `PhrasePrefixQuery` is targeting.
+    pub fn field(&self) -> Field {
+        self.field
+    }
+
+    /// `Term`s in the phrase without the associated offsets, and without the prefix.
+    pub fn phrase_terms(&self) -> Vec<Term> {
+        self.phrase_terms
+            .iter()
+            .map(|(_, term)| term.clone())
+            .collect::<Vec<Term>>()
+    }
+
+    /// The prefix `Term` of the phrase.
+    pub fn prefix(&self) -> &Term {
+        &self.prefix.1
+    }
+
+    /// Returns the [`PhrasePrefixWeight`] for the given phrase prefix query given a specific
+    /// `searcher`.
+    pub(crate) fn phrase_prefix_weight(
+        &self,
+        enable_scoring: EnableScoring<'_>,
+    ) -> crate::Result<PhrasePrefixWeight> {
+        let schema = enable_scoring.schema();
+        let field_entry = schema.get_field_entry(self.field);
+        let has_positions = field_entry
+            .field_type()
+            .get_index_record_option()
+            .map(IndexRecordOption::has_positions)
+            .unwrap_or(false);
+        if !has_positions {
+            let field_name = field_entry.name();
+            return Err(crate::TantivyError::SchemaError(format!(
+                "Applied phrase prefix query on field {:?}, which does not have positions indexed",
+                field_name
+            )));
+        }
+        let terms = self.phrase_terms();
+        let bm25_weight_opt = match enable_scoring {
+            EnableScoring::Enabled(searcher) => Some(Bm25Weight::for_terms(searcher, &terms)?),
+            EnableScoring::Disabled(_) => None,
+        };
+        Ok(PhrasePrefixWeight::new(
+            self.phrase_terms.clone(),
+            self.prefix.clone(),
+            bm25_weight_opt,
+            self.max_expansions,
+        ))
+    }
+}
+
+impl Query for PhrasePrefixQuery {
+    /// Create the weight associated with a query.
+    ///
+    /// See [`Weight`].
+    fn weight(&self, enable_scoring: EnableScoring<'_>) -> crate::Result<Box<dyn Weight>> {
+        let phrase_prefix_weight = self.phrase_prefix_weight(enable_scoring)?;
+        Ok(Box::new(phrase_prefix_weight))
+    }
+
+    fn query_terms<'a>(&'a self, visitor: &mut dyn FnMut(&'a Term, bool)) {
+        for (_, term) in &self.phrase_terms {
+            visitor(term, true);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::create_index;
+    use super::PhrasePrefixQuery;
+    use crate::collector::TopDocs;
+    use crate::query::{EnableScoring, Query};
+    use crate::schema::Term;
+    use crate::{DocSet, Index, TERMINATED};
+
+    fn matching_docs(index: &Index, query: &PhrasePrefixQuery) -> crate::Result<Vec<u32>> {
+        let searcher = index.reader()?.searcher();
+        let mut docs: Vec<u32> = searcher
+            .search(query, &TopDocs::with_limit(10))?
+            .into_iter()
+            .map(|(_score, doc_address)| doc_address.doc_id)
+            .collect();
+        docs.sort_unstable();
+        Ok(docs)
+    }
+
+    #[test]
+    fn test_phrase_prefix_query() -> crate::Result<()> {
+        let index = create_index(&[
+            "new york",
+            "new yorkshire terrier",
+            "new jersey",
+            "york is new",
+            "brand new yoyo",
+        ])?;
+        let text_field = index.schema().get_field("text").unwrap();
+        let phrase_prefix = |texts: &[&str]| {
+            PhrasePrefixQuery::new(
+                texts
+                    .iter()
+                    .map(|text| Term::from_field_text(text_field, text))
+                    .collect(),
+            )
+        };
+        assert_eq!(
+            matching_docs(&index, &phrase_prefix(&["new", "yo"]))?,
+            vec![0, 1, 4]
+        );
+        assert_eq!(
+            matching_docs(&index, &phrase_prefix(&["new", "york"]))?,
+            vec![0, 1]
+        );
+        assert_eq!(
+            matching_docs(&index, &phrase_prefix(&["brand", "new", "y"]))?,
+            vec![4]
+        );
+        assert!(matching_docs(&index, &phrase_prefix(&["new", "z"]))?.is_empty());
+        assert!(matching_docs(&index, &phrase_prefix(&["old", "yo"]))?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_phrase_prefix_query_max_expansions() -> crate::Result<()> {
+        let index = create_index(&["new york", "new yorkshire terrier", "brand new yoyo"])?;
+        let text_field = index.schema().get_field("text").unwrap();
+        let searcher = index.reader()?.searcher();
+        let mut query = PhrasePrefixQuery::new(vec![
+            Term::from_field_text(text_field, "new"),
+            Term::from_field_text(text_field, "yo"),
+        ]);
+        query.set_max_expansions(2);
+        let weight = query.weight(EnableScoring::Disabled(searcher.schema()))?;
+        let mut scorer = weight.scorer(searcher.segment_reader(0), 1.0)?;
+        // "yoyo" comes after "york" and "yorkshire" in the term dictionary.
+        assert_eq!(scorer.doc(), 0);
+        assert_eq!(scorer.advance(), 1);
+        assert_eq!(scorer.advance(), TERMINATED);
+        Ok(())
+    }
+}
@@ -0,0 +1,121 @@
+use super::PhraseScorer;
+use crate::core::SegmentReader;
+use crate::fieldnorm::FieldNormReader;
+use crate::postings::UnionPostings;
+use crate::query::bm25::Bm25Weight;
+use crate::query::explanation::does_not_match;
+use crate::query::{EmptyScorer, Explanation, Scorer, Weight};
+use crate::schema::{IndexRecordOption, Term};
+use crate::{DocId, DocSet, Score};
+
+pub struct PhrasePrefixWeight {
+    phrase_terms: Vec<(usize, Term)>,
+    prefix: (usize, Term),
+    similarity_weight_opt: Option<Bm25Weight>,
+    max_expansions: u32,
+}
+
+impl PhrasePrefixWeight {
+    /// Creates a new phrase prefix weight.
+    /// If `similarity_weight_opt` is None, then scoring is disabled
+    pub fn new(
+        phrase_terms: Vec<(usize, Term)>,
+        prefix: (usize, Term),
+        similarity_weight_opt: Option<Bm25Weight>,
+        max_expansions: u32,
+    ) -> PhrasePrefixWeight {
+        PhrasePrefixWeight {
+            phrase_terms,
+            prefix,
+            similarity_weight_opt,
+            max_expansions,
+        }
+    }
+
+    fn fieldnorm_reader(&self, reader: &SegmentReader) -> crate::Result<FieldNormReader> {
+        let field = self.prefix.1.field();
+        if self.similarity_weight_opt.is_some() {
+            if let Some(fieldnorm_reader) = reader.fieldnorms_readers().get_field(field)? {
+                return Ok(fieldnorm_reader);
+            }
+        }
+        Ok(FieldNormReader::constant(reader.max_doc(), 1))
+    }
+
+    pub(crate) fn phrase_scorer(
+        &self,
+        reader: &SegmentReader,
+        boost: Score,
+    ) -> crate::Result<Option<PhraseScorer<UnionPostings>>> {
+        let similarity_weight_opt = self
+            .similarity_weight_opt
+            .as_ref()
+            .map(|similarity_weight| similarity_weight.boost_by(boost));
+        let fieldnorm_reader = self.fieldnorm_reader(reader)?;
+        let inverted_index = reader.inverted_index(self.prefix.1.field())?;
+        let mut term_postings_list = Vec::new();
+        for &(offset, ref term) in &self.phrase_terms {
+            if let Some(postings) =
+                inverted_index.read_postings(term, IndexRecordOption::WithFreqsAndPositions)?
+            {
+                term_postings_list.push((offset, UnionPostings::new(vec![postings])));
+            } else {
+                return Ok(None);
+            }
+        }
+        let prefix_bytes = self.prefix.1.value_bytes();
+        let mut prefix_postings = Vec::new();
+        let mut term_stream = inverted_index
+            .terms()
+            .range()
+            .ge(prefix_bytes)
+            .into_stream()?;
+        while prefix_postings.len() < self.max_expansions as usize && term_stream.advance() {
+            if !term_stream.key().starts_with(prefix_bytes) {
+                break;
+            }
+            prefix_postings.push(inverted_index.read_postings_from_terminfo(
+                term_stream.value(),
+                IndexRecordOption::WithFreqsAndPositions,
+            )?);
+        }
+        if prefix_postings.is_empty() {
+            return Ok(None);
+        }
+        term_postings_list.push((self.prefix.0, UnionPostings::new(prefix_postings)));
+        Ok(Some(PhraseScorer::new(
+            term_postings_list,
+            similarity_weight_opt,
+            fieldnorm_reader,
+            0,
+        )))
+    }
+}
+
+impl Weight for PhrasePrefixWeight {
+    fn scorer(&self, reader: &SegmentReader, boost: Score) -> crate::Result<Box<dyn Scorer>> {
+        if let Some(scorer) = self.phrase_scorer(reader, boost)? {
+            Ok(Box::new(scorer))
+        } else {
+            Ok(Box::new(EmptyScorer))
+        }
+    }
+
+    fn explain(&self, reader: &SegmentReader, doc: DocId) -> crate::Result<Explanation> {
+        let mut scorer = match self.phrase_scorer(reader, 1.0)? {
+            Some(scorer) => scorer,
+            None => return Err(does_not_match(doc)),
+        };
+        if scorer.seek(doc) != doc {
+            return Err(does_not_match(doc));
+        }
+        let fieldnorm_reader = self.fieldnorm_reader(reader)?;
+        let fieldnorm_id = fieldnorm_reader.fieldnorm_id(doc);
+        let phrase_count = scorer.phrase_count();
+        let mut explanation = Explanation::new("Phrase Prefix Scorer", scorer.score());
+        if let Some(similarity_weight) = self.similarity_weight_opt.as_ref() {
+            explanation.add_detail(similarity_weight.explain(fieldnorm_id, phrase_count));
+        }
+        Ok(explanation)
+    }
+}
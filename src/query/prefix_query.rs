@@ -0,0 +1,180 @@
+use tantivy_fst::Automaton;
+
+use crate::query::{AutomatonWeight, EnableScoring, Query, Weight};
+use crate::schema::Term;
+
+/// An automaton matching the byte strings starting with a given prefix.
+#[derive(Clone, Debug)]
+pub(crate) struct PrefixAutomaton {
+    prefix: Vec<u8>,
+}
+
+impl PrefixAutomaton {
+    pub fn new(prefix: &[u8]) -> PrefixAutomaton {
+        PrefixAutomaton {
+            prefix: prefix.to_vec(),
+        }
+    }
+}
+
+impl Automaton for PrefixAutomaton {
+    /// Number of bytes of the prefix matched so far, or `None` if the input
+    /// diverged from the prefix.
+    type State = Option<usize>;
+
+    fn start(&self) -> Option<usize> {
+        Some(0)
+    }
+
+    fn is_match(&self, state: &Option<usize>) -> bool {
+        *state == Some(self.prefix.len())
+    }
+
+    fn can_match(&self, state: &Option<usize>) -> bool {
+        state.is_some()
+    }
+
+    fn will_always_match(&self, state: &Option<usize>) -> bool {
+        self.is_match(state)
+    }
+
+    fn accept(&self, state: &Option<usize>, byte: u8) -> Option<usize> {
+        let matched = (*state)?;
+        if matched == self.prefix.len() {
+            Some(matched)
+        } else if self.prefix[matched] == byte {
+            Some(matched + 1)
+        } else {
+            None
+        }
+    }
+}
+
+/// A Prefix Query matches all of the documents
+/// containing a term starting with a given prefix.
+///
+/// The matching terms are found by running an automaton over the
+/// term dictionary, as for a [`RegexQuery`](crate::query::RegexQuery),
+/// and all of the matching documents get a constant score.
+///
+/// ```rust
+/// use tantivy::collector::Count;
+/// use tantivy::query::PrefixQuery;
+/// use tantivy::schema::{Schema, TEXT};
+/// use tantivy::{doc, Index, Term};
+///
+/// # fn test() -> tantivy::Result<()> {
+/// let mut schema_builder = Schema::builder();
+/// let title = schema_builder.add_text_field("title", TEXT);
+/// let schema = schema_builder.build();
+/// let index = Index::create_in_ram(schema);
+/// {
+///     let mut index_writer = index.writer(3_000_000)?;
+///     index_writer.add_document(doc!(
+///         title => "The Name of the Wind",
+///     ))?;
+///     index_writer.add_document(doc!(
+///         title => "The Diary of Muadib",
+///     ))?;
+///     index_writer.add_document(doc!(
+///         title => "A Dairy Cow",
+///     ))?;
+///     index_writer.add_document(doc!(
+///         title => "The Diary of a Young Girl",
+///     ))?;
+///     index_writer.commit()?;
+/// }
+///
+/// let reader = index.reader()?;
+/// let searcher = reader.searcher();
+///
+/// let query = PrefixQuery::new(Term::from_field_text(title, "di"));
+/// let count = searcher.search(&query, &Count)?;
+/// assert_eq!(count, 2);
+/// Ok(())
+/// # }
+/// # assert!(test().is_ok());
+/// ```
+#[derive(Clone, Debug)]
+pub struct PrefixQuery {
+    prefix: Term,
+}
+
+impl PrefixQuery {
+    /// Creates a new PrefixQuery matching the terms of the field of `prefix`
+    /// which start with its value.
+    pub fn new(prefix: Term) -> PrefixQuery {
+        PrefixQuery { prefix }
+    }
+
+    /// The prefix `Term` of the query.
+    pub fn prefix(&self) -> &Term {
+        &self.prefix
+    }
+
+    fn specialized_weight(&self) -> AutomatonWeight<PrefixAutomaton> {
+        let automaton = PrefixAutomaton::new(self.prefix.value_bytes());
+        AutomatonWeight::new(self.prefix.field(), automaton)
+    }
+}
+
+impl Query for PrefixQuery {
+    fn weight(&self, _enabled_scoring: EnableScoring<'_>) -> crate::Result<Box<dyn Weight>> {
+        Ok(Box::new(self.specialized_weight()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use tantivy_fst::Automaton;
+
+    use super::{PrefixAutomaton, PrefixQuery};
+    use crate::collector::Count;
+    use crate::schema::{Schema, Term, TEXT};
+    use crate::Index;
+
+    fn matches(automaton: &PrefixAutomaton, text: &str) -> bool {
+        let mut state = automaton.start();
+        for &byte in text.as_bytes() {
+            if !automaton.can_match(&state) {
+                return false;
+            }
+            state = automaton.accept(&state, byte);
+        }
+        automaton.is_match(&state)
+    }
+
+    #[test]
+    fn test_prefix_automaton() {
+        let automaton = PrefixAutomaton::new(b"ja");
+        assert!(matches(&automaton, "ja"));
+        assert!(matches(&automaton, "japan"));
+        assert!(!matches(&automaton, "j"));
+        assert!(!matches(&automaton, "korea"));
+        assert!(!matches(&automaton, "aja"));
+        assert!(matches(&PrefixAutomaton::new(b""), "korea"));
+    }
+
+    #[test]
+    fn test_prefix_query() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let country_field = schema_builder.add_text_field("country", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(country_field => "japan"))?;
+        index_writer.add_document(doc!(country_field => "jamaica"))?;
+        index_writer.add_document(doc!(country_field => "korea"))?;
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let count = |prefix: &str| {
+            let query = PrefixQuery::new(Term::from_field_text(country_field, prefix));
+            searcher.search(&query, &Count).unwrap()
+        };
+        assert_eq!(count("ja"), 2);
+        assert_eq!(count("jap"), 1);
+        assert_eq!(count("japan"), 1);
+        assert_eq!(count("japanese"), 0);
+        assert_eq!(count(""), 3);
+        Ok(())
+    }
+}
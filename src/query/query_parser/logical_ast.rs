@@ -1,5 +1,8 @@
 use std::fmt;
 use std::ops::Bound;
+use std::sync::Arc;
+
+use tantivy_fst::Regex;
 
 use crate::query::Occur;
 use crate::schema::{Field, Term, Type};
@@ -15,6 +18,20 @@ pub enum LogicalLiteral {
         lower: Bound<Term>,
         upper: Bound<Term>,
     },
+    Prefix(Term),
+    Wildcard {
+        field: Field,
+        pattern: String,
+    },
+    Fuzzy {
+        term: Term,
+        distance: u8,
+    },
+    Regex {
+        field: Field,
+        pattern: String,
+        regex: Arc<Regex>,
+    },
     All,
 }
 
@@ -87,6 +104,24 @@ impl fmt::Debug for LogicalLiteral {
                 ref upper,
                 ..
             } => write!(formatter, "({:?} TO {:?})", lower, upper),
+            LogicalLiteral::Prefix(ref prefix) => write!(formatter, "{:?}*", prefix),
+            LogicalLiteral::Wildcard { field, ref pattern } => write!(
+                formatter,
+                "Wildcard(field={}, {:?})",
+                field.field_id(),
+                pattern
+            ),
+            LogicalLiteral::Fuzzy { ref term, distance } => {
+                write!(formatter, "{:?}~{}", term, distance)
+            }
+            LogicalLiteral::Regex {
+                field, ref pattern, ..
+            } => write!(
+                formatter,
+                "Regex(field={}, {:?})",
+                field.field_id(),
+                pattern
+            ),
             LogicalLiteral::All => write!(formatter, "*"),
         }
     }
@@ -3,7 +3,9 @@ use std::net::{AddrParseError, IpAddr};
 use std::num::{ParseFloatError, ParseIntError};
 use std::ops::Bound;
 use std::str::{FromStr, ParseBoolError};
+use std::sync::Arc;
 
+use tantivy_fst::Regex;
 use tantivy_query_grammar::{UserInputAst, UserInputBound, UserInputLeaf, UserInputLiteral};
 
 use super::logical_ast::*;
@@ -12,11 +14,12 @@ use crate::indexer::{
     convert_to_fast_value_and_get_term, set_string_and_get_terms, JsonTermWriter,
 };
 use crate::query::{
-    AllQuery, BooleanQuery, BoostQuery, EmptyQuery, Occur, PhraseQuery, Query, RangeQuery,
-    TermQuery,
+    AllQuery, BooleanQuery, BoostQuery, EmptyQuery, FuzzyTermQuery, Occur, PhraseQuery,
+    PrefixQuery, Query, RangeQuery, RegexQuery, TermQuery, WildcardQuery,
 };
 use crate::schema::{
-    Facet, FacetParseError, Field, FieldType, IndexRecordOption, IntoIpv6Addr, Schema, Term, Type,
+    Facet, FacetParseError, Field, FieldType, IndexRecordOption, IntoIpv6Addr, Schema, Term,
+    TextFieldIndexing, Type,
 };
 use crate::time::format_description::well_known::Rfc3339;
 use crate::time::OffsetDateTime;
@@ -88,6 +91,9 @@ pub enum QueryParserError {
     /// The format for the ip field is invalid.
     #[error("The ip field is malformed: {0}")]
     IpFormatError(#[from] AddrParseError),
+    /// The query contains a regular expression which is not valid.
+    #[error("Invalid regex: '{0}'")]
+    InvalidRegex(String),
 }
 
 /// Recursively remove empty clause from the AST
@@ -165,6 +171,22 @@ fn trim_ast(logical_ast: LogicalAst) -> Option<LogicalAst> {
 ///
 /// * all docs query: A plain `*` will match all documents in the index.
 ///
+/// * wildcard terms: In a term, `*` matches any sequence of characters and `?` matches any
+///   single character. e.g. `title:obam*` or `title:b?rack`. A `?` ending the term is not a
+///   wildcard, so that `what?` stays a plain term. Wildcard terms are only supported on text
+///   fields, and are not tokenized: they are lowercased, unless the field uses the `raw`
+///   tokenizer, and matched against the indexed terms.
+///
+/// * fuzzy terms: A term followed by `~` matches the terms within a Levenshtein distance of 2,
+///   or of the given distance, up to 2. e.g. `title:barak~` or `title:barak~1`. As wildcard terms,
+///   fuzzy terms are only supported on text fields and are lowercased but not tokenized.
+///
+/// * regex terms: A regular expression enclosed in slashes matches the indexed terms it matches
+///   entirely. e.g. `title:/ob.*a/`. A slash within the regular expression is escaped as `\/`.
+///   Regex terms are neither lowercased nor tokenized. On the fields which are not text
+///   fields, e.g. facets, the slashes are part of the term: `category:/electronics/` is the
+///   term `/electronics/`.
+///
 /// Parts of the queries can be boosted by appending `^boostfactor`.
 /// For instance, `"SRE"^2.0 OR devops^0.4` will boost documents containing `SRE` instead of
 /// devops. Negative boosts are not allowed.
@@ -408,6 +430,9 @@ impl QueryParser {
                 let ip_v6 = IpAddr::from_str(phrase)?.into_ipv6_addr();
                 Ok(Term::from_field_ip_addr(field, ip_v6))
             }
+            FieldType::GeoPoint(_) => Err(QueryParserError::UnsupportedQuery(
+                "Range query are not supported on geo point field.".to_string(),
+            )),
         }
     }
 
@@ -519,6 +544,11 @@ impl QueryParser {
                 let term = Term::from_field_ip_addr(field, ip_v6);
                 Ok(vec![LogicalLiteral::Term(term)])
             }
+            FieldType::GeoPoint(_) => Err(QueryParserError::UnsupportedQuery(
+                "Geo point fields are not supported by the query parser. Use a \
+                 GeoBoundingBoxQuery, a GeoDistanceQuery or a GeoPolygonQuery instead."
+                    .to_string(),
+            )),
         }
     }
 
@@ -634,6 +664,125 @@ impl QueryParser {
         Ok(triplets)
     }
 
+    /// Returns the indexing options of a text field targeted by a wildcard, fuzzy or regex
+    /// term, which are not supported on other fields.
+    fn text_indexing_options_for_term_pattern(
+        &self,
+        field: Field,
+        json_path: &str,
+    ) -> Result<&TextFieldIndexing, QueryParserError> {
+        let field_entry = self.schema.get_field_entry(field);
+        let field_name = field_entry.name();
+        if !field_entry.field_type().is_indexed() {
+            return Err(QueryParserError::FieldNotIndexed(field_name.to_string()));
+        }
+        match *field_entry.field_type() {
+            FieldType::Str(ref str_options) if json_path.is_empty() => {
+                str_options.get_indexing_options().ok_or_else(|| {
+                    // This should have been seen earlier really.
+                    QueryParserError::FieldNotIndexed(field_name.to_string())
+                })
+            }
+            _ => Err(QueryParserError::UnsupportedQuery(format!(
+                "Wildcard, fuzzy and regex terms are only supported on text fields: {field_name:?}."
+            ))),
+        }
+    }
+
+    fn compute_logical_literal_for_wildcard(
+        &self,
+        field: Field,
+        json_path: &str,
+        pattern: &str,
+    ) -> Result<LogicalLiteral, QueryParserError> {
+        let text_indexing_options =
+            self.text_indexing_options_for_term_pattern(field, json_path)?;
+        let pattern = lowercase_unless_raw(text_indexing_options, pattern);
+        if let Some(prefix) = pattern.strip_suffix('*') {
+            if !prefix.contains(['*', '?', '\\']) {
+                return Ok(LogicalLiteral::Prefix(Term::from_field_text(field, prefix)));
+            }
+        }
+        Ok(LogicalLiteral::Wildcard { field, pattern })
+    }
+
+    fn compute_logical_literal_for_fuzzy(
+        &self,
+        field: Field,
+        json_path: &str,
+        term_text: &str,
+        distance: u8,
+    ) -> Result<LogicalLiteral, QueryParserError> {
+        let text_indexing_options =
+            self.text_indexing_options_for_term_pattern(field, json_path)?;
+        if distance > MAX_FUZZY_DISTANCE {
+            return Err(QueryParserError::UnsupportedQuery(format!(
+                "Fuzzy term distance cannot exceed {MAX_FUZZY_DISTANCE}: {term_text}~{distance}."
+            )));
+        }
+        let term_text = lowercase_unless_raw(text_indexing_options, term_text);
+        Ok(LogicalLiteral::Fuzzy {
+            term: Term::from_field_text(field, &term_text),
+            distance,
+        })
+    }
+
+    fn compute_logical_literals_for_regex(
+        &self,
+        field: Field,
+        json_path: &str,
+        pattern: &str,
+    ) -> Result<Vec<LogicalLiteral>, QueryParserError> {
+        let field_type = self.schema.get_field_entry(field).field_type();
+        if !matches!(field_type, FieldType::Str(_) | FieldType::JsonObject(_)) {
+            // Not a regex, but a term enclosed in slashes, like the facet `/electronics/`.
+            let phrase = format!("/{pattern}/");
+            return self.compute_logical_ast_for_leaf(field, json_path, &phrase, 0);
+        }
+        self.text_indexing_options_for_term_pattern(field, json_path)?;
+        let regex =
+            Regex::new(pattern).map_err(|_| QueryParserError::InvalidRegex(pattern.to_string()))?;
+        Ok(vec![LogicalLiteral::Regex {
+            field,
+            pattern: pattern.to_string(),
+            regex: Arc::new(regex),
+        }])
+    }
+
+    /// Builds the logical ast of a wildcard, fuzzy or regex term, by calling
+    /// `literals_for_field` on each of the fields it targets.
+    fn compute_logical_ast_for_term_pattern<F>(
+        &self,
+        field_name: Option<String>,
+        text: String,
+        literals_for_field: F,
+    ) -> Result<LogicalAst, QueryParserError>
+    where
+        F: Fn(Field, &str, &str) -> Result<Vec<LogicalLiteral>, QueryParserError>,
+    {
+        let literal = UserInputLiteral {
+            field_name,
+            phrase: text,
+            slop: 0,
+        };
+        let term_patterns: Vec<(Field, &str, &str)> =
+            self.compute_path_triplets_for_literal(&literal)?;
+        let mut asts: Vec<LogicalAst> = Vec::new();
+        for (field, json_path, text) in term_patterns {
+            for ast in literals_for_field(field, json_path, text)? {
+                // Apply some field specific boost defined at the query parser level.
+                let boost = self.field_boost(field);
+                asts.push(LogicalAst::Leaf(Box::new(ast)).boost(boost));
+            }
+        }
+        let result_ast: LogicalAst = if asts.len() == 1 {
+            asts.into_iter().next().unwrap()
+        } else {
+            LogicalAst::Clause(asts.into_iter().map(|ast| (Occur::Should, ast)).collect())
+        };
+        Ok(result_ast)
+    }
+
     fn compute_logical_ast_from_leaf(
         &self,
         leaf: UserInputLeaf,
@@ -660,6 +809,41 @@ impl QueryParser {
                 Ok(result_ast)
             }
             UserInputLeaf::All => Ok(LogicalAst::Leaf(Box::new(LogicalLiteral::All))),
+            UserInputLeaf::Wildcard {
+                field_name,
+                pattern,
+            } => self.compute_logical_ast_for_term_pattern(
+                field_name,
+                pattern,
+                |field, json_path, pattern| {
+                    let literal =
+                        self.compute_logical_literal_for_wildcard(field, json_path, pattern)?;
+                    Ok(vec![literal])
+                },
+            ),
+            UserInputLeaf::Fuzzy {
+                field_name,
+                term,
+                distance,
+            } => self.compute_logical_ast_for_term_pattern(
+                field_name,
+                term,
+                |field, json_path, term_text| {
+                    let literal = self
+                        .compute_logical_literal_for_fuzzy(field, json_path, term_text, distance)?;
+                    Ok(vec![literal])
+                },
+            ),
+            UserInputLeaf::Regex {
+                field_name,
+                pattern,
+            } => self.compute_logical_ast_for_term_pattern(
+                field_name,
+                pattern,
+                |field, json_path, pattern| {
+                    self.compute_logical_literals_for_regex(field, json_path, pattern)
+                },
+            ),
             UserInputLeaf::Range {
                 field: full_field_opt,
                 lower,
@@ -701,10 +885,33 @@ fn convert_literal_to_query(logical_literal: LogicalLiteral) -> Box<dyn Query> {
         } => Box::new(RangeQuery::new_term_bounds(
             field, value_type, &lower, &upper,
         )),
+        LogicalLiteral::Prefix(prefix) => Box::new(PrefixQuery::new(prefix)),
+        LogicalLiteral::Wildcard { field, pattern } => {
+            Box::new(WildcardQuery::new(field, &pattern))
+        }
+        LogicalLiteral::Fuzzy { term, distance } => {
+            Box::new(FuzzyTermQuery::new(term, distance, true))
+        }
+        LogicalLiteral::Regex { field, regex, .. } => {
+            Box::new(RegexQuery::from_regex(regex, field))
+        }
         LogicalLiteral::All => Box::new(AllQuery),
     }
 }
 
+/// Maximum edit distance of a fuzzy term.
+const MAX_FUZZY_DISTANCE: u8 = 2;
+
+/// Wildcard and fuzzy terms are not tokenized, but we mimic the lowercasing
+/// done by all of the default tokenizers except `raw`.
+fn lowercase_unless_raw(text_indexing_options: &TextFieldIndexing, text: &str) -> String {
+    if text_indexing_options.tokenizer() == "raw" {
+        text.to_string()
+    } else {
+        text.to_lowercase()
+    }
+}
+
 fn generate_literals_for_str(
     field_name: &str,
     field: Field,
@@ -973,6 +1180,123 @@ mod test {
         assert_eq!(format!("{:?}", query), "EmptyQuery");
     }
 
+    #[test]
+    pub fn test_parse_query_wildcard() {
+        test_parse_query_to_logical_ast_helper(
+            "title:Bar*",
+            r#"Term(type=Str, field=0, "bar")*"#,
+            false,
+        );
+        test_parse_query_to_logical_ast_helper("title:b?r*", r#"Wildcard(field=0, "b?r*")"#, false);
+        test_parse_query_to_logical_ast_helper(
+            "*bar",
+            r#"(Wildcard(field=0, "*bar") Wildcard(field=1, "*bar"))"#,
+            false,
+        );
+        test_parse_query_to_logical_ast_helper(
+            "nottokenized:Bar*",
+            r#"Term(type=Str, field=7, "Bar")*"#,
+            false,
+        );
+        test_parse_query_to_logical_ast_helper(
+            r"title:a\?b*",
+            r#"Wildcard(field=0, "a\\?b*")"#,
+            false,
+        );
+        let query = make_query_parser().parse_query("title:b?r*").unwrap();
+        assert_eq!(
+            format!("{:?}", query),
+            r#"WildcardQuery { field: Field(0), pattern: "b?r*" }"#
+        );
+    }
+
+    #[test]
+    pub fn test_parse_query_wildcard_unsupported_field() {
+        let query_parser = make_query_parser();
+        assert_matches!(
+            query_parser.parse_query("signed:12*"),
+            Err(QueryParserError::UnsupportedQuery(_))
+        );
+        assert_matches!(
+            query_parser.parse_query("json.title:bar*"),
+            Err(QueryParserError::UnsupportedQuery(_))
+        );
+        assert_matches!(
+            query_parser.parse_query("notindexed_text:bar*"),
+            Err(QueryParserError::FieldNotIndexed(_))
+        );
+    }
+
+    #[test]
+    pub fn test_parse_query_fuzzy() {
+        test_parse_query_to_logical_ast_helper(
+            "title:Barak~",
+            r#"Term(type=Str, field=0, "barak")~2"#,
+            false,
+        );
+        test_parse_query_to_logical_ast_helper(
+            "nottokenized:Barak~1",
+            r#"Term(type=Str, field=7, "Barak")~1"#,
+            false,
+        );
+        test_parse_query_to_logical_ast_helper(
+            "barak~1",
+            r#"(Term(type=Str, field=0, "barak")~1 Term(type=Str, field=1, "barak")~1)"#,
+            false,
+        );
+        let query = make_query_parser().parse_query("title:barak~1").unwrap();
+        assert_eq!(
+            format!("{:?}", query),
+            r#"FuzzyTermQuery { term: Term(type=Str, field=0, "barak"), distance: 1, transposition_cost_one: true, prefix: false }"#
+        );
+        assert_matches!(
+            make_query_parser().parse_query("title:barak~3"),
+            Err(QueryParserError::UnsupportedQuery(_))
+        );
+        assert_matches!(
+            make_query_parser().parse_query("signed:12~1"),
+            Err(QueryParserError::UnsupportedQuery(_))
+        );
+    }
+
+    #[test]
+    pub fn test_parse_query_regex() {
+        test_parse_query_to_logical_ast_helper(
+            "title:/Ba.*k/",
+            r#"Regex(field=0, "Ba.*k")"#,
+            false,
+        );
+        test_parse_query_to_logical_ast_helper(
+            "/ba.*k/",
+            r#"(Regex(field=0, "ba.*k") Regex(field=1, "ba.*k"))"#,
+            false,
+        );
+        assert!(make_query_parser().parse_query("title:/ba.*k/").is_ok());
+        assert_eq!(
+            make_query_parser().parse_query("title:/ba(k/").unwrap_err(),
+            QueryParserError::InvalidRegex("ba(k".to_string())
+        );
+        test_parse_query_to_logical_ast_helper(
+            "facet:/root/branch",
+            r#"Term(type=Facet, field=11, "/root/branch")"#,
+            false,
+        );
+        // The fields which are not text fields take the slashes literally.
+        test_parse_query_to_logical_ast_helper(
+            "facet:/a/",
+            r#"Term(type=Facet, field=11, "/a/")"#,
+            false,
+        );
+        assert_matches!(
+            make_query_parser().parse_query("signed:/2/"),
+            Err(QueryParserError::ExpectedInt(_))
+        );
+        assert_matches!(
+            make_query_parser().parse_query("json.title:/ba.*k/"),
+            Err(QueryParserError::UnsupportedQuery(_))
+        );
+    }
+
     #[test]
     pub fn test_parse_query_ints() {
         let query_parser = make_query_parser();
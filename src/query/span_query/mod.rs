@@ -0,0 +1,237 @@
+//! Span queries match documents on the positions of their terms.
+//!
+//! A span is a range of positions within a document. Span queries are composable:
+//! a [`SpanTermQuery`] matches the positions of a term, and the other span queries
+//! combine the spans of their clauses.
+//!
+//! - [`SpanNearQuery`] matches the spans of its clauses when they are close to each other.
+//! - [`SpanOrQuery`] matches the spans of any of its clauses.
+//! - [`SpanNotQuery`] matches the spans of a clause which do not overlap the spans of another
+//!   one.
+//! - [`SpanFirstQuery`] matches the spans of a clause ending before a given position.
+//!
+//! All of the clauses of a span query must target the same field, which needs
+//! positions to be indexed.
+
+mod span_first_query;
+mod span_near_query;
+mod span_not_query;
+mod span_or_query;
+mod span_term_query;
+mod span_weight;
+
+pub use self::span_first_query::SpanFirstQuery;
+pub use self::span_near_query::SpanNearQuery;
+pub use self::span_not_query::SpanNotQuery;
+pub use self::span_or_query::SpanOrQuery;
+pub use self::span_term_query::SpanTermQuery;
+pub use self::span_weight::{SpanScorer, SpanWeight};
+use crate::core::SegmentReader;
+use crate::query::Query;
+use crate::schema::Field;
+use crate::DocSet;
+
+/// A range of positions within a document, `start` included and `end` excluded.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub struct Span {
+    /// Position of the first token of the span.
+    pub start: u32,
+    /// Position following the last token of the span.
+    pub end: u32,
+}
+
+impl Span {
+    /// Number of positions of the span.
+    pub fn len(&self) -> u32 {
+        self.end - self.start
+    }
+
+    /// Returns true if the span does not contain any position.
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+}
+
+/// The documents matching a span query on a segment, with their spans.
+///
+/// Only documents with at least one span are visited.
+pub trait Spans: DocSet {
+    /// Returns the spans of the current document,
+    /// sorted by start position and then by end position.
+    fn spans(&self) -> &[Span];
+}
+
+/// A [`Query`] matching spans of positions, which can be combined with other span queries.
+pub trait SpanQuery: Query + SpanQueryClone {
+    /// Returns the field targeted by the span query.
+    fn field(&self) -> Field;
+
+    /// Returns the spans of the query on a segment,
+    /// or `None` if the query cannot match any document of the segment.
+    fn spans(&self, reader: &SegmentReader) -> crate::Result<Option<Box<dyn Spans>>>;
+}
+
+/// Implements `box_clone_span_query`.
+pub trait SpanQueryClone {
+    /// Returns a boxed clone of `self`.
+    fn box_clone_span_query(&self) -> Box<dyn SpanQuery>;
+}
+
+impl<T> SpanQueryClone for T
+where T: 'static + SpanQuery + Clone
+{
+    fn box_clone_span_query(&self) -> Box<dyn SpanQuery> {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for Box<dyn SpanQuery> {
+    fn clone(&self) -> Self {
+        self.box_clone_span_query()
+    }
+}
+
+/// Returns the field shared by all of the `clauses`.
+///
+/// # Panics
+///
+/// Panics if there are no clauses, or if they do not all target the same field.
+fn clauses_field(clauses: &[Box<dyn SpanQuery>], query_name: &str) -> Field {
+    assert!(
+        !clauses.is_empty(),
+        "A {query_name} is required to have at least one clause."
+    );
+    let field = clauses[0].field();
+    assert!(
+        clauses[1..].iter().all(|clause| clause.field() == field),
+        "All clauses of a {query_name} must belong to the same field."
+    );
+    field
+}
+
+/// Positions all of the `clauses` on the first document greater or equal to `target`
+/// they all contain, and returns it.
+fn intersect_clauses(clauses: &mut [Box<dyn Spans>], mut target: crate::DocId) -> crate::DocId {
+    'align: loop {
+        if target == crate::TERMINATED {
+            return target;
+        }
+        for clause in clauses.iter_mut() {
+            let doc = if clause.doc() < target {
+                clause.seek(target)
+            } else {
+                clause.doc()
+            };
+            if doc > target {
+                target = doc;
+                continue 'align;
+            }
+        }
+        return target;
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::collector::TopDocs;
+    use crate::query::EnableScoring;
+    use crate::schema::{Schema, Term, STRING, TEXT};
+    use crate::{Index, TantivyError};
+
+    pub(crate) fn create_index(texts: &[&'static str]) -> crate::Result<(Index, Field)> {
+        let mut schema_builder = Schema::builder();
+        let text_field = schema_builder.add_text_field("text", TEXT);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        for &text in texts {
+            index_writer.add_document(doc!(text_field => text))?;
+        }
+        index_writer.commit()?;
+        Ok((index, text_field))
+    }
+
+    pub(crate) fn span_term(field: Field, text: &str) -> Box<dyn SpanQuery> {
+        Box::new(SpanTermQuery::new(Term::from_field_text(field, text)))
+    }
+
+    /// Returns the sorted ids of the documents matching `query`.
+    pub(crate) fn matching_docs(index: &Index, query: &dyn Query) -> crate::Result<Vec<u32>> {
+        let searcher = index.reader()?.searcher();
+        let mut docs: Vec<u32> = searcher
+            .search(query, &TopDocs::with_limit(100))?
+            .into_iter()
+            .map(|(_score, doc_address)| doc_address.doc_id)
+            .collect();
+        docs.sort_unstable();
+        Ok(docs)
+    }
+
+    /// Returns the spans of `query` for each matching document of the first segment.
+    pub(crate) fn doc_spans(
+        index: &Index,
+        query: &dyn SpanQuery,
+    ) -> crate::Result<Vec<(u32, Vec<Span>)>> {
+        let searcher = index.reader()?.searcher();
+        let mut doc_spans = Vec::new();
+        if let Some(mut spans) = query.spans(searcher.segment_reader(0))? {
+            while spans.doc() != crate::TERMINATED {
+                doc_spans.push((spans.doc(), spans.spans().to_vec()));
+                spans.advance();
+            }
+        }
+        Ok(doc_spans)
+    }
+
+    #[test]
+    fn test_span_queries_nested() -> crate::Result<()> {
+        let (index, text_field) = create_index(&[
+            "the contract may be terminated by either party",
+            "the agreement shall terminate after five years of good and loyal service",
+            "termination of this agreement requires a written notice",
+            "the contract is renewed, but the termination clause is in the agreement annex",
+            "a party may terminate",
+        ])?;
+        let contract_or_agreement = SpanOrQuery::new(vec![
+            span_term(text_field, "contract"),
+            span_term(text_field, "agreement"),
+        ]);
+        let terminate = SpanOrQuery::new(vec![
+            span_term(text_field, "terminate"),
+            span_term(text_field, "terminated"),
+            span_term(text_field, "termination"),
+        ]);
+        let query = SpanNearQuery::new(
+            vec![Box::new(contract_or_agreement), Box::new(terminate)],
+            3,
+            false,
+        );
+        assert_eq!(matching_docs(&index, &query)?, vec![0, 1, 2]);
+        let first_words = SpanFirstQuery::new(Box::new(query), 4);
+        assert_eq!(matching_docs(&index, &first_words)?, vec![1, 2]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_span_query_requires_positions() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let text_field = schema_builder.add_text_field("text", STRING);
+        let index = Index::create_in_ram(schema_builder.build());
+        let schema = index.schema();
+        let query = SpanTermQuery::new(Term::from_field_text(text_field, "a"));
+        assert!(matches!(
+            query.weight(EnableScoring::Disabled(&schema)),
+            Err(TantivyError::SchemaError(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    #[should_panic(expected = "All clauses of a SpanOrQuery must belong to the same field.")]
+    fn test_span_query_clauses_on_different_fields() {
+        let mut schema_builder = Schema::builder();
+        let title = schema_builder.add_text_field("title", TEXT);
+        let body = schema_builder.add_text_field("body", TEXT);
+        SpanOrQuery::new(vec![span_term(title, "a"), span_term(body, "a")]);
+    }
+}
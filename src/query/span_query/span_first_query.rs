@@ -0,0 +1,145 @@
+use super::{Span, SpanQuery, SpanWeight, Spans};
+use crate::core::SegmentReader;
+use crate::query::{EnableScoring, Query, Weight};
+use crate::schema::{Field, Term};
+use crate::{DocId, DocSet, TERMINATED};
+
+/// `SpanFirstQuery` matches the spans of a clause ending at most at a given position.
+///
+/// For instance, with `end` set to 3, it matches the spans
+/// of the clause within the first three positions of a document.
+#[derive(Clone, Debug)]
+pub struct SpanFirstQuery {
+    clause: Box<dyn SpanQuery>,
+    end: u32,
+}
+
+impl SpanFirstQuery {
+    /// Creates a new `SpanFirstQuery` matching the spans of `clause` ending at most at `end`.
+    pub fn new(clause: Box<dyn SpanQuery>, end: u32) -> SpanFirstQuery {
+        SpanFirstQuery { clause, end }
+    }
+
+    /// The position the spans must end before, included.
+    pub fn end(&self) -> u32 {
+        self.end
+    }
+}
+
+impl Query for SpanFirstQuery {
+    fn weight(&self, enable_scoring: EnableScoring<'_>) -> crate::Result<Box<dyn Weight>> {
+        let weight = SpanWeight::new(Box::new(self.clone()), enable_scoring)?;
+        Ok(Box::new(weight))
+    }
+
+    fn query_terms<'a>(&'a self, visitor: &mut dyn FnMut(&'a Term, bool)) {
+        self.clause.query_terms(visitor);
+    }
+}
+
+impl SpanQuery for SpanFirstQuery {
+    fn field(&self) -> Field {
+        self.clause.field()
+    }
+
+    fn spans(&self, reader: &SegmentReader) -> crate::Result<Option<Box<dyn Spans>>> {
+        Ok(self
+            .clause
+            .spans(reader)?
+            .map(|clause| Box::new(SpanFirstSpans::new(clause, self.end)) as Box<dyn Spans>))
+    }
+}
+
+struct SpanFirstSpans {
+    clause: Box<dyn Spans>,
+    end: u32,
+    spans: Vec<Span>,
+}
+
+impl SpanFirstSpans {
+    fn new(clause: Box<dyn Spans>, end: u32) -> SpanFirstSpans {
+        let mut first_spans = SpanFirstSpans {
+            clause,
+            end,
+            spans: Vec::new(),
+        };
+        first_spans.go_to_match();
+        first_spans
+    }
+
+    /// Advances the clause until its current document has a span ending early enough.
+    fn go_to_match(&mut self) -> DocId {
+        loop {
+            let doc = self.clause.doc();
+            self.spans.clear();
+            if doc == TERMINATED {
+                return TERMINATED;
+            }
+            let end = self.end;
+            self.spans.extend(
+                self.clause
+                    .spans()
+                    .iter()
+                    .copied()
+                    .filter(|span| span.end <= end),
+            );
+            if !self.spans.is_empty() {
+                return doc;
+            }
+            self.clause.advance();
+        }
+    }
+}
+
+impl DocSet for SpanFirstSpans {
+    fn advance(&mut self) -> DocId {
+        if self.clause.doc() == TERMINATED {
+            return TERMINATED;
+        }
+        self.clause.advance();
+        self.go_to_match()
+    }
+
+    fn seek(&mut self, target: DocId) -> DocId {
+        if self.clause.doc() >= target {
+            return self.clause.doc();
+        }
+        self.clause.seek(target);
+        self.go_to_match()
+    }
+
+    fn doc(&self) -> DocId {
+        self.clause.doc()
+    }
+
+    fn size_hint(&self) -> u32 {
+        self.clause.size_hint()
+    }
+}
+
+impl Spans for SpanFirstSpans {
+    fn spans(&self) -> &[Span] {
+        &self.spans
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SpanFirstQuery;
+    use crate::query::span_query::tests::{create_index, doc_spans, span_term};
+    use crate::query::span_query::Span;
+
+    #[test]
+    fn test_span_first_query() -> crate::Result<()> {
+        let (index, text_field) = create_index(&["a b a", "b b a", "b a"])?;
+        let query = SpanFirstQuery::new(span_term(text_field, "a"), 2);
+        assert_eq!(
+            doc_spans(&index, &query)?,
+            vec![
+                (0, vec![Span { start: 0, end: 1 }]),
+                (2, vec![Span { start: 1, end: 2 }]),
+            ]
+        );
+        Ok(())
+    }
+}
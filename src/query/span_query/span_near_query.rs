@@ -0,0 +1,343 @@
+use super::{clauses_field, intersect_clauses, Span, SpanQuery, SpanWeight, Spans};
+use crate::core::SegmentReader;
+use crate::query::{EnableScoring, Query, Weight};
+use crate::schema::{Field, Term};
+use crate::{DocId, DocSet, TERMINATED};
+
+/// `SpanNearQuery` matches the spans of its clauses when they are close to each other.
+///
+/// A match is a span going from the start of the first matching clause span to the end
+/// of the last one. It is accepted if the number of positions of the match which do not
+/// belong to any of the clause spans is at most `slop`.
+///
+/// If `in_order` is true, the clause spans must appear in the order of the clauses,
+/// without overlapping. Otherwise, they can appear in any order.
+///
+/// All of the clauses must target the same field, which needs positions to be indexed.
+///
+/// ```rust
+/// use tantivy::collector::Count;
+/// use tantivy::query::{SpanNearQuery, SpanOrQuery, SpanQuery, SpanTermQuery};
+/// use tantivy::schema::{Field, Schema, TEXT};
+/// use tantivy::{doc, Index, Term};
+/// # fn test() -> tantivy::Result<()> {
+/// let mut schema_builder = Schema::builder();
+/// let body = schema_builder.add_text_field("body", TEXT);
+/// let schema = schema_builder.build();
+///
+/// let index = Index::create_in_ram(schema);
+/// let mut index_writer = index.writer(3_000_000)?;
+/// index_writer.add_document(doc!(body => "The contract may be terminated by either party."))?;
+/// index_writer.add_document(doc!(body => "Termination of this agreement requires a notice."))?;
+/// index_writer.add_document(doc!(body => "The contract is renewed every year."))?;
+/// index_writer.commit()?;
+///
+/// let span_or = |field: Field, words: &[&str]| -> Box<dyn SpanQuery> {
+///     let clauses = words
+///         .iter()
+///         .map(|word| {
+///             Box::new(SpanTermQuery::new(Term::from_field_text(field, word))) as Box<dyn SpanQuery>
+///         })
+///         .collect();
+///     Box::new(SpanOrQuery::new(clauses))
+/// };
+/// // (contract OR agreement) within 5 words of (terminate OR terminated OR termination)
+/// let query = SpanNearQuery::new(
+///     vec![
+///         span_or(body, &["contract", "agreement"]),
+///         span_or(body, &["terminate", "terminated", "termination"]),
+///     ],
+///     5,
+///     false,
+/// );
+/// let searcher = index.reader()?.searcher();
+/// let count = searcher.search(&query, &Count)?;
+/// assert_eq!(count, 2);
+/// # Ok(())
+/// # }
+/// # assert!(test().is_ok());
+/// ```
+#[derive(Clone, Debug)]
+pub struct SpanNearQuery {
+    field: Field,
+    clauses: Vec<Box<dyn SpanQuery>>,
+    slop: u32,
+    in_order: bool,
+}
+
+impl SpanNearQuery {
+    /// Creates a new `SpanNearQuery` given its clauses, a slop and whether
+    /// the clauses must match in order.
+    ///
+    /// There must be at least one clause, and all clauses
+    /// must belong to the same field.
+    pub fn new(clauses: Vec<Box<dyn SpanQuery>>, slop: u32, in_order: bool) -> SpanNearQuery {
+        let field = clauses_field(&clauses, "SpanNearQuery");
+        SpanNearQuery {
+            field,
+            clauses,
+            slop,
+            in_order,
+        }
+    }
+
+    /// Slop allowed between the clause spans.
+    pub fn slop(&self) -> u32 {
+        self.slop
+    }
+
+    /// Returns true if the clauses must match in order.
+    pub fn in_order(&self) -> bool {
+        self.in_order
+    }
+}
+
+impl Query for SpanNearQuery {
+    fn weight(&self, enable_scoring: EnableScoring<'_>) -> crate::Result<Box<dyn Weight>> {
+        let weight = SpanWeight::new(Box::new(self.clone()), enable_scoring)?;
+        Ok(Box::new(weight))
+    }
+
+    fn query_terms<'a>(&'a self, visitor: &mut dyn FnMut(&'a Term, bool)) {
+        for clause in &self.clauses {
+            clause.query_terms(visitor);
+        }
+    }
+}
+
+impl SpanQuery for SpanNearQuery {
+    fn field(&self) -> Field {
+        self.field
+    }
+
+    fn spans(&self, reader: &SegmentReader) -> crate::Result<Option<Box<dyn Spans>>> {
+        let mut clauses = Vec::with_capacity(self.clauses.len());
+        for clause in &self.clauses {
+            if let Some(spans) = clause.spans(reader)? {
+                clauses.push(spans);
+            } else {
+                return Ok(None);
+            }
+        }
+        Ok(Some(Box::new(SpanNearSpans::new(
+            clauses,
+            self.slop,
+            self.in_order,
+        ))))
+    }
+}
+
+struct SpanNearSpans {
+    clauses: Vec<Box<dyn Spans>>,
+    slop: u32,
+    in_order: bool,
+    doc: DocId,
+    spans: Vec<Span>,
+}
+
+impl SpanNearSpans {
+    fn new(clauses: Vec<Box<dyn Spans>>, slop: u32, in_order: bool) -> SpanNearSpans {
+        let mut near_spans = SpanNearSpans {
+            clauses,
+            slop,
+            in_order,
+            doc: 0,
+            spans: Vec::new(),
+        };
+        let target = near_spans.clauses[0].doc();
+        near_spans.go_to_match(target);
+        near_spans
+    }
+
+    /// Positions the spans on the first document greater or equal to `target` with a match.
+    fn go_to_match(&mut self, target: DocId) -> DocId {
+        let mut doc = intersect_clauses(&mut self.clauses, target);
+        while doc != TERMINATED {
+            self.compute_spans();
+            if !self.spans.is_empty() {
+                break;
+            }
+            doc = intersect_clauses(&mut self.clauses, doc + 1);
+        }
+        if doc == TERMINATED {
+            self.spans.clear();
+        }
+        self.doc = doc;
+        doc
+    }
+
+    /// Returns true if the gaps between the clause spans of a match fit in the slop.
+    fn fits_slop(&self, width: u32, clauses_len: u32) -> bool {
+        i64::from(width) - i64::from(clauses_len) <= i64::from(self.slop)
+    }
+
+    fn compute_spans(&mut self) {
+        self.spans.clear();
+        if self.in_order {
+            self.compute_ordered_spans();
+        } else {
+            self.compute_unordered_spans();
+        }
+    }
+
+    /// For each span of the first clause, picks the following span of each clause
+    /// with the smallest end.
+    fn compute_ordered_spans(&mut self) {
+        'first_spans: for first_span in self.clauses[0].spans() {
+            let mut end = first_span.end;
+            let mut clauses_len = first_span.len();
+            for clause in &self.clauses[1..] {
+                let next_span = clause
+                    .spans()
+                    .iter()
+                    .filter(|span| span.start >= end)
+                    .min_by_key(|span| span.end);
+                if let Some(next_span) = next_span {
+                    end = next_span.end;
+                    clauses_len += next_span.len();
+                } else {
+                    continue 'first_spans;
+                }
+            }
+            if self.fits_slop(end - first_span.start, clauses_len) {
+                self.spans.push(Span {
+                    start: first_span.start,
+                    end,
+                });
+            }
+        }
+        self.spans.sort_unstable();
+        self.spans.dedup();
+    }
+
+    /// Slides a window over the clause spans, advancing the clause whose current span
+    /// starts first.
+    fn compute_unordered_spans(&mut self) {
+        let mut cursors = vec![0usize; self.clauses.len()];
+        loop {
+            let mut start = u32::MAX;
+            let mut end = 0u32;
+            let mut clauses_len = 0u32;
+            let mut first_clause = 0;
+            for (ord, clause) in self.clauses.iter().enumerate() {
+                let span = clause.spans()[cursors[ord]];
+                if span.start < start {
+                    start = span.start;
+                    first_clause = ord;
+                }
+                end = end.max(span.end);
+                clauses_len += span.len();
+            }
+            if self.fits_slop(end - start, clauses_len) {
+                self.spans.push(Span { start, end });
+            }
+            cursors[first_clause] += 1;
+            if cursors[first_clause] == self.clauses[first_clause].spans().len() {
+                break;
+            }
+        }
+        self.spans.sort_unstable();
+        self.spans.dedup();
+    }
+}
+
+impl DocSet for SpanNearSpans {
+    fn advance(&mut self) -> DocId {
+        if self.doc == TERMINATED {
+            return TERMINATED;
+        }
+        self.go_to_match(self.doc + 1)
+    }
+
+    fn seek(&mut self, target: DocId) -> DocId {
+        if self.doc >= target {
+            return self.doc;
+        }
+        self.go_to_match(target)
+    }
+
+    fn doc(&self) -> DocId {
+        self.doc
+    }
+
+    fn size_hint(&self) -> u32 {
+        self.clauses
+            .iter()
+            .map(|clause| clause.size_hint())
+            .min()
+            .unwrap_or(0)
+    }
+}
+
+impl Spans for SpanNearSpans {
+    fn spans(&self) -> &[Span] {
+        &self.spans
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SpanNearQuery;
+    use crate::query::span_query::tests::{create_index, doc_spans, matching_docs, span_term};
+    use crate::query::span_query::Span;
+
+    #[test]
+    fn test_span_near_query_ordered() -> crate::Result<()> {
+        let (index, text_field) = create_index(&["a b c", "a x b", "b a", "a x x b", "c"])?;
+        let exact = SpanNearQuery::new(
+            vec![span_term(text_field, "a"), span_term(text_field, "b")],
+            0,
+            true,
+        );
+        assert_eq!(
+            doc_spans(&index, &exact)?,
+            vec![(0, vec![Span { start: 0, end: 2 }])]
+        );
+        let sloppy = SpanNearQuery::new(
+            vec![span_term(text_field, "a"), span_term(text_field, "b")],
+            1,
+            true,
+        );
+        assert_eq!(matching_docs(&index, &sloppy)?, vec![0, 1]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_span_near_query_unordered() -> crate::Result<()> {
+        let (index, text_field) = create_index(&["a b c", "a x b", "b a", "a x x b", "c"])?;
+        let exact = SpanNearQuery::new(
+            vec![span_term(text_field, "b"), span_term(text_field, "a")],
+            0,
+            false,
+        );
+        assert_eq!(
+            doc_spans(&index, &exact)?,
+            vec![
+                (0, vec![Span { start: 0, end: 2 }]),
+                (2, vec![Span { start: 0, end: 2 }]),
+            ]
+        );
+        let sloppy = SpanNearQuery::new(
+            vec![span_term(text_field, "b"), span_term(text_field, "a")],
+            2,
+            false,
+        );
+        assert_eq!(matching_docs(&index, &sloppy)?, vec![0, 1, 2, 3]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_span_near_query_repeated_terms() -> crate::Result<()> {
+        let (index, text_field) = create_index(&["a x a x b", "b x x x a"])?;
+        let query = SpanNearQuery::new(
+            vec![span_term(text_field, "a"), span_term(text_field, "b")],
+            1,
+            true,
+        );
+        assert_eq!(
+            doc_spans(&index, &query)?,
+            vec![(0, vec![Span { start: 2, end: 5 }])]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,232 @@
+use super::{Span, SpanQuery, SpanWeight, Spans};
+use crate::core::SegmentReader;
+use crate::query::{EnableScoring, Query, Weight};
+use crate::schema::{Field, Term};
+use crate::{DocId, DocSet, TERMINATED};
+
+/// `SpanNotQuery` matches the spans of a clause which are not close to the spans
+/// of another clause.
+///
+/// A span of the `include` clause is dropped if a span of the `exclude` clause overlaps it,
+/// or starts less than `post` positions after its end, or ends less than `pre` positions
+/// before its start. With `pre` and `post` set to zero, only overlapping spans are dropped.
+///
+/// Both clauses must target the same field, which needs positions to be indexed.
+#[derive(Clone, Debug)]
+pub struct SpanNotQuery {
+    include: Box<dyn SpanQuery>,
+    exclude: Box<dyn SpanQuery>,
+    pre: u32,
+    post: u32,
+}
+
+impl SpanNotQuery {
+    /// Creates a new `SpanNotQuery` matching the spans of `include`
+    /// which do not overlap the spans of `exclude`.
+    ///
+    /// Both clauses must belong to the same field.
+    pub fn new(include: Box<dyn SpanQuery>, exclude: Box<dyn SpanQuery>) -> SpanNotQuery {
+        SpanNotQuery::new_with_distance(include, exclude, 0, 0)
+    }
+
+    /// Creates a new `SpanNotQuery` matching the spans of `include` which are not within
+    /// `pre` positions after, or `post` positions before, a span of `exclude`.
+    ///
+    /// Both clauses must belong to the same field.
+    pub fn new_with_distance(
+        include: Box<dyn SpanQuery>,
+        exclude: Box<dyn SpanQuery>,
+        pre: u32,
+        post: u32,
+    ) -> SpanNotQuery {
+        assert_eq!(
+            include.field(),
+            exclude.field(),
+            "All clauses of a SpanNotQuery must belong to the same field."
+        );
+        SpanNotQuery {
+            include,
+            exclude,
+            pre,
+            post,
+        }
+    }
+}
+
+impl Query for SpanNotQuery {
+    fn weight(&self, enable_scoring: EnableScoring<'_>) -> crate::Result<Box<dyn Weight>> {
+        let weight = SpanWeight::new(Box::new(self.clone()), enable_scoring)?;
+        Ok(Box::new(weight))
+    }
+
+    fn query_terms<'a>(&'a self, visitor: &mut dyn FnMut(&'a Term, bool)) {
+        self.include.query_terms(visitor);
+    }
+}
+
+impl SpanQuery for SpanNotQuery {
+    fn field(&self) -> Field {
+        self.include.field()
+    }
+
+    fn spans(&self, reader: &SegmentReader) -> crate::Result<Option<Box<dyn Spans>>> {
+        let include = match self.include.spans(reader)? {
+            Some(include) => include,
+            None => return Ok(None),
+        };
+        let exclude = self.exclude.spans(reader)?;
+        Ok(Some(Box::new(SpanNotSpans::new(
+            include, exclude, self.pre, self.post,
+        ))))
+    }
+}
+
+struct SpanNotSpans {
+    include: Box<dyn Spans>,
+    exclude: Option<Box<dyn Spans>>,
+    pre: u32,
+    post: u32,
+    spans: Vec<Span>,
+}
+
+impl SpanNotSpans {
+    fn new(
+        include: Box<dyn Spans>,
+        exclude: Option<Box<dyn Spans>>,
+        pre: u32,
+        post: u32,
+    ) -> SpanNotSpans {
+        let mut not_spans = SpanNotSpans {
+            include,
+            exclude,
+            pre,
+            post,
+            spans: Vec::new(),
+        };
+        not_spans.go_to_match();
+        not_spans
+    }
+
+    /// Advances the include clause until its current document has a span
+    /// which is not excluded.
+    fn go_to_match(&mut self) -> DocId {
+        loop {
+            let doc = self.include.doc();
+            self.spans.clear();
+            if doc == TERMINATED {
+                return TERMINATED;
+            }
+            let exclude_spans: &[Span] = match self.exclude.as_mut() {
+                Some(exclude) => {
+                    if exclude.doc() < doc {
+                        exclude.seek(doc);
+                    }
+                    if exclude.doc() == doc {
+                        exclude.spans()
+                    } else {
+                        &[]
+                    }
+                }
+                None => &[],
+            };
+            let (pre, post) = (self.pre, self.post);
+            self.spans
+                .extend(self.include.spans().iter().copied().filter(|span| {
+                    !exclude_spans.iter().any(|excluded| {
+                        excluded.end + pre > span.start && span.end + post > excluded.start
+                    })
+                }));
+            if !self.spans.is_empty() {
+                return doc;
+            }
+            self.include.advance();
+        }
+    }
+}
+
+impl DocSet for SpanNotSpans {
+    fn advance(&mut self) -> DocId {
+        if self.include.doc() == TERMINATED {
+            return TERMINATED;
+        }
+        self.include.advance();
+        self.go_to_match()
+    }
+
+    fn seek(&mut self, target: DocId) -> DocId {
+        if self.include.doc() >= target {
+            return self.include.doc();
+        }
+        self.include.seek(target);
+        self.go_to_match()
+    }
+
+    fn doc(&self) -> DocId {
+        self.include.doc()
+    }
+
+    fn size_hint(&self) -> u32 {
+        self.include.size_hint()
+    }
+}
+
+impl Spans for SpanNotSpans {
+    fn spans(&self) -> &[Span] {
+        &self.spans
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SpanNotQuery;
+    use crate::query::span_query::tests::{create_index, doc_spans, span_term};
+    use crate::query::span_query::Span;
+    use crate::query::SpanNearQuery;
+
+    #[test]
+    fn test_span_not_query() -> crate::Result<()> {
+        let (index, text_field) = create_index(&["a b", "a c b", "a", "b c"])?;
+        let a_near_b = SpanNearQuery::new(
+            vec![span_term(text_field, "a"), span_term(text_field, "b")],
+            1,
+            true,
+        );
+        let query = SpanNotQuery::new(Box::new(a_near_b), span_term(text_field, "c"));
+        assert_eq!(
+            doc_spans(&index, &query)?,
+            vec![(0, vec![Span { start: 0, end: 2 }])]
+        );
+        let missing_exclude =
+            SpanNotQuery::new(span_term(text_field, "a"), span_term(text_field, "missing"));
+        assert_eq!(doc_spans(&index, &missing_exclude)?.len(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_span_not_query_with_distance() -> crate::Result<()> {
+        let (index, text_field) = create_index(&["a b", "b a", "a x b", "b x a"])?;
+        let not_before_b = SpanNotQuery::new_with_distance(
+            span_term(text_field, "a"),
+            span_term(text_field, "b"),
+            0,
+            1,
+        );
+        let docs: Vec<u32> = doc_spans(&index, &not_before_b)?
+            .into_iter()
+            .map(|(doc, _)| doc)
+            .collect();
+        assert_eq!(docs, vec![1, 2, 3]);
+        let not_after_b = SpanNotQuery::new_with_distance(
+            span_term(text_field, "a"),
+            span_term(text_field, "b"),
+            2,
+            0,
+        );
+        let docs: Vec<u32> = doc_spans(&index, &not_after_b)?
+            .into_iter()
+            .map(|(doc, _)| doc)
+            .collect();
+        assert_eq!(docs, vec![0, 2]);
+        Ok(())
+    }
+}
@@ -0,0 +1,175 @@
+use super::{clauses_field, Span, SpanQuery, SpanWeight, Spans};
+use crate::core::SegmentReader;
+use crate::query::{EnableScoring, Query, Weight};
+use crate::schema::{Field, Term};
+use crate::{DocId, DocSet, TERMINATED};
+
+/// `SpanOrQuery` matches the spans of any of its clauses.
+///
+/// All of the clauses must target the same field, which needs positions to be indexed.
+#[derive(Clone, Debug)]
+pub struct SpanOrQuery {
+    field: Field,
+    clauses: Vec<Box<dyn SpanQuery>>,
+}
+
+impl SpanOrQuery {
+    /// Creates a new `SpanOrQuery` given its clauses.
+    ///
+    /// There must be at least one clause, and all clauses
+    /// must belong to the same field.
+    pub fn new(clauses: Vec<Box<dyn SpanQuery>>) -> SpanOrQuery {
+        let field = clauses_field(&clauses, "SpanOrQuery");
+        SpanOrQuery { field, clauses }
+    }
+}
+
+impl Query for SpanOrQuery {
+    fn weight(&self, enable_scoring: EnableScoring<'_>) -> crate::Result<Box<dyn Weight>> {
+        let weight = SpanWeight::new(Box::new(self.clone()), enable_scoring)?;
+        Ok(Box::new(weight))
+    }
+
+    fn query_terms<'a>(&'a self, visitor: &mut dyn FnMut(&'a Term, bool)) {
+        for clause in &self.clauses {
+            clause.query_terms(visitor);
+        }
+    }
+}
+
+impl SpanQuery for SpanOrQuery {
+    fn field(&self) -> Field {
+        self.field
+    }
+
+    fn spans(&self, reader: &SegmentReader) -> crate::Result<Option<Box<dyn Spans>>> {
+        let mut clauses = Vec::with_capacity(self.clauses.len());
+        for clause in &self.clauses {
+            if let Some(spans) = clause.spans(reader)? {
+                clauses.push(spans);
+            }
+        }
+        if clauses.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(Box::new(SpanOrSpans::new(clauses))))
+    }
+}
+
+struct SpanOrSpans {
+    clauses: Vec<Box<dyn Spans>>,
+    doc: DocId,
+    spans: Vec<Span>,
+}
+
+impl SpanOrSpans {
+    fn new(clauses: Vec<Box<dyn Spans>>) -> SpanOrSpans {
+        let mut or_spans = SpanOrSpans {
+            clauses,
+            doc: 0,
+            spans: Vec::new(),
+        };
+        or_spans.merge_spans();
+        or_spans
+    }
+
+    /// Positions the spans on the smallest document of the clauses,
+    /// and merges the spans of the clauses on this document.
+    fn merge_spans(&mut self) -> DocId {
+        self.doc = self
+            .clauses
+            .iter()
+            .map(|clause| clause.doc())
+            .min()
+            .unwrap_or(TERMINATED);
+        self.spans.clear();
+        if self.doc == TERMINATED {
+            return TERMINATED;
+        }
+        for clause in &self.clauses {
+            if clause.doc() == self.doc {
+                self.spans.extend_from_slice(clause.spans());
+            }
+        }
+        self.spans.sort_unstable();
+        self.spans.dedup();
+        self.doc
+    }
+}
+
+impl DocSet for SpanOrSpans {
+    fn advance(&mut self) -> DocId {
+        if self.doc == TERMINATED {
+            return TERMINATED;
+        }
+        for clause in &mut self.clauses {
+            if clause.doc() == self.doc {
+                clause.advance();
+            }
+        }
+        self.merge_spans()
+    }
+
+    fn seek(&mut self, target: DocId) -> DocId {
+        if self.doc >= target {
+            return self.doc;
+        }
+        for clause in &mut self.clauses {
+            if clause.doc() < target {
+                clause.seek(target);
+            }
+        }
+        self.merge_spans()
+    }
+
+    fn doc(&self) -> DocId {
+        self.doc
+    }
+
+    fn size_hint(&self) -> u32 {
+        self.clauses
+            .iter()
+            .map(|clause| clause.size_hint())
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+impl Spans for SpanOrSpans {
+    fn spans(&self) -> &[Span] {
+        &self.spans
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SpanOrQuery;
+    use crate::query::span_query::tests::{create_index, doc_spans, matching_docs, span_term};
+    use crate::query::span_query::Span;
+
+    #[test]
+    fn test_span_or_query() -> crate::Result<()> {
+        let (index, text_field) = create_index(&["a b", "c", "b c a", "d"])?;
+        let query = SpanOrQuery::new(vec![
+            span_term(text_field, "a"),
+            span_term(text_field, "b"),
+            span_term(text_field, "missing"),
+        ]);
+        assert_eq!(
+            doc_spans(&index, &query)?,
+            vec![
+                (
+                    0,
+                    vec![Span { start: 0, end: 1 }, Span { start: 1, end: 2 }]
+                ),
+                (
+                    2,
+                    vec![Span { start: 0, end: 1 }, Span { start: 2, end: 3 }]
+                ),
+            ]
+        );
+        let missing = SpanOrQuery::new(vec![span_term(text_field, "missing")]);
+        assert!(matching_docs(&index, &missing)?.is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,169 @@
+use super::{Span, SpanQuery, SpanWeight, Spans};
+use crate::core::SegmentReader;
+use crate::postings::{Postings, SegmentPostings};
+use crate::query::{EnableScoring, Query, Weight};
+use crate::schema::{Field, IndexRecordOption, Term};
+use crate::{DocId, DocSet, TERMINATED};
+
+/// `SpanTermQuery` matches the positions of a term.
+///
+/// Each occurrence of the term is a span of length one.
+/// It is the building block of the other span queries.
+///
+/// ```rust
+/// use tantivy::collector::Count;
+/// use tantivy::query::SpanTermQuery;
+/// use tantivy::schema::{Schema, TEXT};
+/// use tantivy::{doc, Index, Term};
+/// # fn test() -> tantivy::Result<()> {
+/// let mut schema_builder = Schema::builder();
+/// let title = schema_builder.add_text_field("title", TEXT);
+/// let schema = schema_builder.build();
+///
+/// let index = Index::create_in_ram(schema);
+/// let mut index_writer = index.writer(3_000_000)?;
+/// index_writer.add_document(doc!(title => "The Name of the Wind"))?;
+/// index_writer.add_document(doc!(title => "The Diary of Muadib"))?;
+/// index_writer.commit()?;
+///
+/// let reader = index.reader()?;
+/// let searcher = reader.searcher();
+/// let query = SpanTermQuery::new(Term::from_field_text(title, "wind"));
+/// let count = searcher.search(&query, &Count)?;
+/// assert_eq!(count, 1);
+/// # Ok(())
+/// # }
+/// # assert!(test().is_ok());
+/// ```
+#[derive(Clone, Debug)]
+pub struct SpanTermQuery {
+    term: Term,
+}
+
+impl SpanTermQuery {
+    /// Creates a new `SpanTermQuery` matching the positions of `term`.
+    pub fn new(term: Term) -> SpanTermQuery {
+        SpanTermQuery { term }
+    }
+
+    /// The [`Term`] this `SpanTermQuery` is targeting.
+    pub fn term(&self) -> &Term {
+        &self.term
+    }
+}
+
+impl Query for SpanTermQuery {
+    fn weight(&self, enable_scoring: EnableScoring<'_>) -> crate::Result<Box<dyn Weight>> {
+        let weight = SpanWeight::new(Box::new(self.clone()), enable_scoring)?;
+        Ok(Box::new(weight))
+    }
+
+    fn query_terms<'a>(&'a self, visitor: &mut dyn FnMut(&'a Term, bool)) {
+        visitor(&self.term, true);
+    }
+}
+
+impl SpanQuery for SpanTermQuery {
+    fn field(&self) -> Field {
+        self.term.field()
+    }
+
+    fn spans(&self, reader: &SegmentReader) -> crate::Result<Option<Box<dyn Spans>>> {
+        let inverted_index = reader.inverted_index(self.term.field())?;
+        let postings_opt = if reader.has_deletes() {
+            inverted_index.read_postings(&self.term, IndexRecordOption::WithFreqsAndPositions)?
+        } else {
+            inverted_index
+                .read_postings_no_deletes(&self.term, IndexRecordOption::WithFreqsAndPositions)?
+        };
+        Ok(postings_opt.map(|postings| Box::new(SpanTermSpans::new(postings)) as Box<dyn Spans>))
+    }
+}
+
+/// The spans of a term, read from its postings.
+struct SpanTermSpans {
+    postings: SegmentPostings,
+    positions: Vec<u32>,
+    spans: Vec<Span>,
+}
+
+impl SpanTermSpans {
+    fn new(postings: SegmentPostings) -> SpanTermSpans {
+        let mut term_spans = SpanTermSpans {
+            postings,
+            positions: Vec::new(),
+            spans: Vec::new(),
+        };
+        term_spans.load_spans();
+        term_spans
+    }
+
+    fn load_spans(&mut self) {
+        self.spans.clear();
+        if self.postings.doc() == TERMINATED {
+            return;
+        }
+        self.postings.positions(&mut self.positions);
+        self.spans
+            .extend(self.positions.iter().map(|&position| Span {
+                start: position,
+                end: position + 1,
+            }));
+    }
+}
+
+impl DocSet for SpanTermSpans {
+    fn advance(&mut self) -> DocId {
+        let doc = self.postings.advance();
+        self.load_spans();
+        doc
+    }
+
+    fn seek(&mut self, target: DocId) -> DocId {
+        let doc = self.postings.seek(target);
+        self.load_spans();
+        doc
+    }
+
+    fn doc(&self) -> DocId {
+        self.postings.doc()
+    }
+
+    fn size_hint(&self) -> u32 {
+        self.postings.size_hint()
+    }
+}
+
+impl Spans for SpanTermSpans {
+    fn spans(&self) -> &[Span] {
+        &self.spans
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SpanTermQuery;
+    use crate::query::span_query::tests::{create_index, doc_spans, matching_docs};
+    use crate::query::span_query::Span;
+    use crate::Term;
+
+    #[test]
+    fn test_span_term_query_spans() -> crate::Result<()> {
+        let (index, text_field) = create_index(&["a b a", "b c", "c a"])?;
+        let query = SpanTermQuery::new(Term::from_field_text(text_field, "a"));
+        assert_eq!(
+            doc_spans(&index, &query)?,
+            vec![
+                (
+                    0,
+                    vec![Span { start: 0, end: 1 }, Span { start: 2, end: 3 }]
+                ),
+                (2, vec![Span { start: 1, end: 2 }]),
+            ]
+        );
+        assert_eq!(matching_docs(&index, &query)?, vec![0, 2]);
+        let missing = SpanTermQuery::new(Term::from_field_text(text_field, "d"));
+        assert!(matching_docs(&index, &missing)?.is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,187 @@
+use super::{SpanQuery, Spans};
+use crate::core::SegmentReader;
+use crate::fieldnorm::FieldNormReader;
+use crate::query::bm25::Bm25Weight;
+use crate::query::explanation::does_not_match;
+use crate::query::{EmptyScorer, EnableScoring, Explanation, Scorer, Weight};
+use crate::schema::{IndexRecordOption, Term};
+use crate::{DocId, DocSet, Score};
+
+/// Weight shared by the span queries.
+///
+/// Documents are scored with BM25, using the number of spans matched in the
+/// document as the term frequency.
+pub struct SpanWeight {
+    query: Box<dyn SpanQuery>,
+    similarity_weight_opt: Option<Bm25Weight>,
+}
+
+impl SpanWeight {
+    /// Creates a new span weight.
+    ///
+    /// Returns an error if the field of the span query does not have positions indexed.
+    pub fn new(
+        query: Box<dyn SpanQuery>,
+        enable_scoring: EnableScoring<'_>,
+    ) -> crate::Result<SpanWeight> {
+        let field_entry = enable_scoring.schema().get_field_entry(query.field());
+        let has_positions = field_entry
+            .field_type()
+            .get_index_record_option()
+            .map(IndexRecordOption::has_positions)
+            .unwrap_or(false);
+        if !has_positions {
+            return Err(crate::TantivyError::SchemaError(format!(
+                "Applied span query on field {:?}, which does not have positions indexed",
+                field_entry.name()
+            )));
+        }
+        let similarity_weight_opt = match enable_scoring {
+            EnableScoring::Enabled(searcher) => {
+                let mut terms: Vec<Term> = Vec::new();
+                query.query_terms(&mut |term, _| terms.push(term.clone()));
+                terms.sort();
+                terms.dedup();
+                Some(Bm25Weight::for_terms(searcher, &terms)?)
+            }
+            EnableScoring::Disabled(_) => None,
+        };
+        Ok(SpanWeight {
+            query,
+            similarity_weight_opt,
+        })
+    }
+
+    fn fieldnorm_reader(&self, reader: &SegmentReader) -> crate::Result<FieldNormReader> {
+        if self.similarity_weight_opt.is_some() {
+            if let Some(fieldnorm_reader) =
+                reader.fieldnorms_readers().get_field(self.query.field())?
+            {
+                return Ok(fieldnorm_reader);
+            }
+        }
+        Ok(FieldNormReader::constant(reader.max_doc(), 1))
+    }
+
+    fn span_scorer(
+        &self,
+        reader: &SegmentReader,
+        boost: Score,
+    ) -> crate::Result<Option<SpanScorer>> {
+        let spans = match self.query.spans(reader)? {
+            Some(spans) => spans,
+            None => return Ok(None),
+        };
+        let similarity_weight_opt = self
+            .similarity_weight_opt
+            .as_ref()
+            .map(|similarity_weight| similarity_weight.boost_by(boost));
+        let fieldnorm_reader = self.fieldnorm_reader(reader)?;
+        Ok(Some(SpanScorer {
+            spans,
+            similarity_weight_opt,
+            fieldnorm_reader,
+        }))
+    }
+}
+
+impl Weight for SpanWeight {
+    fn scorer(&self, reader: &SegmentReader, boost: Score) -> crate::Result<Box<dyn Scorer>> {
+        if let Some(scorer) = self.span_scorer(reader, boost)? {
+            Ok(Box::new(scorer))
+        } else {
+            Ok(Box::new(EmptyScorer))
+        }
+    }
+
+    fn explain(&self, reader: &SegmentReader, doc: DocId) -> crate::Result<Explanation> {
+        let mut scorer = match self.span_scorer(reader, 1.0)? {
+            Some(scorer) => scorer,
+            None => return Err(does_not_match(doc)),
+        };
+        if scorer.seek(doc) != doc {
+            return Err(does_not_match(doc));
+        }
+        let fieldnorm_id = scorer.fieldnorm_reader.fieldnorm_id(doc);
+        let span_count = scorer.span_count();
+        let mut explanation = Explanation::new("Span Scorer", scorer.score());
+        if let Some(similarity_weight) = self.similarity_weight_opt.as_ref() {
+            explanation.add_detail(similarity_weight.explain(fieldnorm_id, span_count));
+        }
+        Ok(explanation)
+    }
+}
+
+/// Scorer of the span queries.
+pub struct SpanScorer {
+    spans: Box<dyn Spans>,
+    similarity_weight_opt: Option<Bm25Weight>,
+    fieldnorm_reader: FieldNormReader,
+}
+
+impl SpanScorer {
+    /// Returns the number of spans matched in the current document.
+    pub fn span_count(&self) -> u32 {
+        self.spans.spans().len() as u32
+    }
+}
+
+impl DocSet for SpanScorer {
+    fn advance(&mut self) -> DocId {
+        self.spans.advance()
+    }
+
+    fn seek(&mut self, target: DocId) -> DocId {
+        self.spans.seek(target)
+    }
+
+    fn doc(&self) -> DocId {
+        self.spans.doc()
+    }
+
+    fn size_hint(&self) -> u32 {
+        self.spans.size_hint()
+    }
+}
+
+impl Scorer for SpanScorer {
+    fn score(&mut self) -> Score {
+        if let Some(similarity_weight) = self.similarity_weight_opt.as_ref() {
+            let fieldnorm_id = self.fieldnorm_reader.fieldnorm_id(self.doc());
+            similarity_weight.score(fieldnorm_id, self.span_count())
+        } else {
+            1.0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::query::span_query::tests::{create_index, span_term};
+    use crate::query::span_query::SpanWeight;
+    use crate::query::{EnableScoring, SpanNearQuery, Weight};
+    use crate::DocSet;
+
+    #[test]
+    fn test_span_weight_scores_span_count() -> crate::Result<()> {
+        let (index, text_field) = create_index(&["a b c a b", "a b c c c", "b a"])?;
+        let searcher = index.reader()?.searcher();
+        let query = SpanNearQuery::new(
+            vec![span_term(text_field, "a"), span_term(text_field, "b")],
+            0,
+            true,
+        );
+        let weight = SpanWeight::new(Box::new(query), EnableScoring::Enabled(&searcher))?;
+        let mut scorer = weight.scorer(searcher.segment_reader(0), 1.0)?;
+        assert_eq!(scorer.doc(), 0);
+        let score_two_spans = scorer.score();
+        assert_eq!(scorer.advance(), 1);
+        let score_one_span = scorer.score();
+        assert!(score_two_spans > score_one_span);
+        assert_eq!(scorer.advance(), crate::TERMINATED);
+        let explanation = weight.explain(searcher.segment_reader(0), 0)?;
+        assert_eq!(explanation.value(), score_two_spans);
+        assert!(weight.explain(searcher.segment_reader(0), 2).is_err());
+        Ok(())
+    }
+}
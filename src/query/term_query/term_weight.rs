@@ -1,3 +1,5 @@
+use std::ops::ControlFlow;
+
 use super::term_scorer::TermScorer;
 use crate::core::SegmentReader;
 use crate::docset::DocSet;
@@ -5,7 +7,9 @@ use crate::fieldnorm::FieldNormReader;
 use crate::postings::SegmentPostings;
 use crate::query::bm25::Bm25Weight;
 use crate::query::explanation::does_not_match;
-use crate::query::weight::{for_each_docset, for_each_scorer};
+use crate::query::weight::{
+    for_each_docset, for_each_docset_until, for_each_scorer, for_each_scorer_until,
+};
 use crate::query::{Explanation, Scorer, Weight};
 use crate::schema::IndexRecordOption;
 use crate::{DocId, Score, Term};
@@ -68,6 +72,26 @@ impl Weight for TermWeight {
         Ok(())
     }
 
+    fn for_each_until(
+        &self,
+        reader: &SegmentReader,
+        callback: &mut dyn FnMut(DocId, Score) -> ControlFlow<()>,
+    ) -> crate::Result<()> {
+        let mut scorer = self.specialized_scorer(reader, 1.0)?;
+        for_each_scorer_until(&mut scorer, callback);
+        Ok(())
+    }
+
+    fn for_each_no_score_until(
+        &self,
+        reader: &SegmentReader,
+        callback: &mut dyn FnMut(DocId) -> ControlFlow<()>,
+    ) -> crate::Result<()> {
+        let mut scorer = self.specialized_scorer(reader, 1.0)?;
+        for_each_docset_until(&mut scorer, callback);
+        Ok(())
+    }
+
     /// Calls `callback` with all of the `(doc, score)` for which score
     /// is exceeding a given threshold.
     ///
@@ -85,7 +109,12 @@ impl Weight for TermWeight {
         callback: &mut dyn FnMut(DocId, Score) -> Score,
     ) -> crate::Result<()> {
         let scorer = self.specialized_scorer(reader, 1.0)?;
-        crate::query::boolean_query::block_wand_single_scorer(scorer, threshold, callback);
+        crate::query::boolean_query::block_wand_single_scorer(
+            scorer,
+            threshold,
+            callback,
+            reader.cancellation_token(),
+        );
         Ok(())
     }
 }
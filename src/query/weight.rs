@@ -1,5 +1,7 @@
+use std::ops::ControlFlow;
+
 use super::Scorer;
-use crate::core::SegmentReader;
+use crate::core::{CancellationCheck, CancellationToken, SegmentReader};
 use crate::query::Explanation;
 use crate::{DocId, DocSet, Score, TERMINATED};
 
@@ -26,6 +28,36 @@ pub(crate) fn for_each_docset<T: DocSet + ?Sized>(docset: &mut T, callback: &mut
     }
 }
 
+/// Iterates through all of the documents and scores matched by the DocSet
+/// `DocSet`, until the callback breaks.
+pub(crate) fn for_each_scorer_until<TScorer: Scorer + ?Sized>(
+    scorer: &mut TScorer,
+    callback: &mut dyn FnMut(DocId, Score) -> ControlFlow<()>,
+) {
+    let mut doc = scorer.doc();
+    while doc != TERMINATED {
+        if callback(doc, scorer.score()).is_break() {
+            return;
+        }
+        doc = scorer.advance();
+    }
+}
+
+/// Iterates through all of the documents matched by the DocSet
+/// `DocSet`, until the callback breaks.
+pub(crate) fn for_each_docset_until<T: DocSet + ?Sized>(
+    docset: &mut T,
+    callback: &mut dyn FnMut(DocId) -> ControlFlow<()>,
+) {
+    let mut doc = docset.doc();
+    while doc != TERMINATED {
+        if callback(doc).is_break() {
+            return;
+        }
+        doc = docset.advance();
+    }
+}
+
 /// Calls `callback` with all of the `(doc, score)` for which score
 /// is exceeding a given threshold.
 ///
@@ -36,13 +68,20 @@ pub(crate) fn for_each_docset<T: DocSet + ?Sized>(docset: &mut T, callback: &mut
 ///
 /// More importantly, it makes it possible for scorers to implement
 /// important optimization (e.g. BlockWAND for union).
+///
+/// The iteration stops early once `cancellation_token` is cancelled.
 pub(crate) fn for_each_pruning_scorer<TScorer: Scorer + ?Sized>(
     scorer: &mut TScorer,
     mut threshold: Score,
     callback: &mut dyn FnMut(DocId, Score) -> Score,
+    cancellation_token: Option<&CancellationToken>,
 ) {
+    let mut cancellation_check = CancellationCheck::new(cancellation_token);
     let mut doc = scorer.doc();
     while doc != TERMINATED {
+        if cancellation_check.visit_doc() {
+            return;
+        }
         let score = scorer.score();
         if score > threshold {
             threshold = callback(doc, score);
@@ -100,6 +139,38 @@ pub trait Weight: Send + Sync + 'static {
         Ok(())
     }
 
+    /// Iterates through all of the document matched by the DocSet
+    /// `DocSet` and push the scored documents to the collector, until the
+    /// callback returns `ControlFlow::Break`.
+    ///
+    /// This method is used to stop the collection of a segment early, see
+    /// [`SegmentCollector::is_done`](crate::collector::SegmentCollector::is_done).
+    fn for_each_until(
+        &self,
+        reader: &SegmentReader,
+        callback: &mut dyn FnMut(DocId, Score) -> ControlFlow<()>,
+    ) -> crate::Result<()> {
+        let mut scorer = self.scorer(reader, 1.0)?;
+        for_each_scorer_until(scorer.as_mut(), callback);
+        Ok(())
+    }
+
+    /// Iterates through all of the document matched by the DocSet
+    /// `DocSet` and push the documents to the collector, until the
+    /// callback returns `ControlFlow::Break`.
+    ///
+    /// This method is used to stop the collection of a segment early, see
+    /// [`SegmentCollector::is_done`](crate::collector::SegmentCollector::is_done).
+    fn for_each_no_score_until(
+        &self,
+        reader: &SegmentReader,
+        callback: &mut dyn FnMut(DocId) -> ControlFlow<()>,
+    ) -> crate::Result<()> {
+        let mut docset = self.scorer(reader, 1.0)?;
+        for_each_docset_until(docset.as_mut(), callback);
+        Ok(())
+    }
+
     /// Calls `callback` with all of the `(doc, score)` for which score
     /// is exceeding a given threshold.
     ///
@@ -117,7 +188,12 @@ pub trait Weight: Send + Sync + 'static {
         callback: &mut dyn FnMut(DocId, Score) -> Score,
     ) -> crate::Result<()> {
         let mut scorer = self.scorer(reader, 1.0)?;
-        for_each_pruning_scorer(scorer.as_mut(), threshold, callback);
+        for_each_pruning_scorer(
+            scorer.as_mut(),
+            threshold,
+            callback,
+            reader.cancellation_token(),
+        );
         Ok(())
     }
 }
@@ -0,0 +1,223 @@
+use std::fmt::Write;
+
+use tantivy_fst::Regex;
+
+use crate::query::{AutomatonWeight, EnableScoring, Query, Weight};
+use crate::schema::Field;
+use crate::TantivyError;
+
+/// Translates a wildcard pattern into an equivalent regular expression.
+///
+/// `*` and `?` are the wildcards, and a backslash escapes the following character.
+/// The literal characters, except the alphanumeric ones, are written as hexadecimal escapes
+/// so that they never get interpreted as regex syntax.
+fn wildcard_to_regex(pattern: &str) -> String {
+    // `.` has to match new lines, as `?` does.
+    let mut regex = String::from("(?s)");
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        let literal = match c {
+            '*' => {
+                // Consecutive `*` are equivalent to a single one.
+                if !regex.ends_with(".*") {
+                    regex.push_str(".*");
+                }
+                continue;
+            }
+            '?' => {
+                regex.push('.');
+                continue;
+            }
+            '\\' => chars.next().unwrap_or('\\'),
+            c => c,
+        };
+        if literal.is_alphanumeric() {
+            regex.push(literal);
+        } else {
+            write!(regex, "\\x{{{:x}}}", literal as u32).unwrap();
+        }
+    }
+    regex
+}
+
+/// A Wildcard Query matches all of the documents
+/// containing a term matching a wildcard pattern.
+///
+/// In the pattern, `*` matches any sequence of characters, including the empty one,
+/// and `?` matches any single character. A backslash escapes the following character,
+/// so that `\*` matches a literal `*`.
+///
+/// The matching terms are found by running an automaton over the
+/// term dictionary, as for a [`RegexQuery`](crate::query::RegexQuery),
+/// and all of the matching documents get a constant score.
+///
+/// The pattern is matched against the terms as they are indexed.
+/// For instance, the default tokenizer lowercases the terms,
+/// so the pattern should be lowercase too.
+///
+/// ```rust
+/// use tantivy::collector::Count;
+/// use tantivy::query::WildcardQuery;
+/// use tantivy::schema::{Schema, TEXT};
+/// use tantivy::{doc, Index};
+///
+/// # fn test() -> tantivy::Result<()> {
+/// let mut schema_builder = Schema::builder();
+/// let title = schema_builder.add_text_field("title", TEXT);
+/// let schema = schema_builder.build();
+/// let index = Index::create_in_ram(schema);
+/// {
+///     let mut index_writer = index.writer(3_000_000)?;
+///     index_writer.add_document(doc!(
+///         title => "The Name of the Wind",
+///     ))?;
+///     index_writer.add_document(doc!(
+///         title => "The Diary of Muadib",
+///     ))?;
+///     index_writer.add_document(doc!(
+///         title => "A Dairy Cow",
+///     ))?;
+///     index_writer.add_document(doc!(
+///         title => "The Diary of a Young Girl",
+///     ))?;
+///     index_writer.commit()?;
+/// }
+///
+/// let reader = index.reader()?;
+/// let searcher = reader.searcher();
+///
+/// let query = WildcardQuery::new(title, "d??ry");
+/// let count = searcher.search(&query, &Count)?;
+/// assert_eq!(count, 3);
+/// Ok(())
+/// # }
+/// # assert!(test().is_ok());
+/// ```
+#[derive(Clone, Debug)]
+pub struct WildcardQuery {
+    field: Field,
+    pattern: String,
+}
+
+impl WildcardQuery {
+    /// Creates a new WildcardQuery matching the terms of `field` matching `pattern`.
+    pub fn new(field: Field, pattern: &str) -> WildcardQuery {
+        WildcardQuery {
+            field,
+            pattern: pattern.to_string(),
+        }
+    }
+
+    /// The wildcard pattern of the query.
+    pub fn pattern(&self) -> &str {
+        &self.pattern
+    }
+
+    /// The pattern is compiled to the same automaton as a [`RegexQuery`](crate::query::RegexQuery).
+    fn specialized_weight(&self) -> crate::Result<AutomatonWeight<Regex>> {
+        let regex = Regex::new(&wildcard_to_regex(&self.pattern))
+            .map_err(|_| TantivyError::InvalidArgument(self.pattern.clone()))?;
+        Ok(AutomatonWeight::new(self.field, regex))
+    }
+}
+
+impl Query for WildcardQuery {
+    fn weight(&self, _enabled_scoring: EnableScoring<'_>) -> crate::Result<Box<dyn Weight>> {
+        Ok(Box::new(self.specialized_weight()?))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use tantivy_fst::{Automaton, Regex};
+
+    use super::{wildcard_to_regex, WildcardQuery};
+    use crate::collector::Count;
+    use crate::schema::{Schema, STRING};
+    use crate::Index;
+
+    fn matches(pattern: &str, text: &str) -> bool {
+        let automaton = Regex::new(&wildcard_to_regex(pattern)).unwrap();
+        let mut state = automaton.start();
+        for &byte in text.as_bytes() {
+            state = automaton.accept(&state, byte);
+        }
+        automaton.is_match(&state)
+    }
+
+    #[test]
+    fn test_wildcard_to_regex() {
+        assert_eq!(wildcard_to_regex("ja*"), "(?s)ja.*");
+        assert_eq!(wildcard_to_regex("j**?n"), "(?s)j.*.n");
+        assert_eq!(wildcard_to_regex(r"a\*.b"), r"(?s)a\x{2a}\x{2e}b");
+    }
+
+    #[test]
+    fn test_wildcard_automaton() {
+        assert!(matches("japan", "japan"));
+        assert!(!matches("japan", "japa"));
+        assert!(matches("ja*", "japan"));
+        assert!(matches("ja*", "ja"));
+        assert!(!matches("ja*", "korea"));
+        assert!(matches("*an", "japan"));
+        assert!(!matches("*an", "korea"));
+        assert!(matches("j*p*n", "japan"));
+        assert!(matches("*", ""));
+        assert!(matches("**", "korea"));
+        assert!(matches("j?pan", "japan"));
+        assert!(!matches("j?pan", "jpan"));
+        assert!(!matches("j?pan", "jaapan"));
+        assert!(matches("?????", "japan"));
+        assert!(!matches("????", "japan"));
+        assert!(matches("j*c?", "jamaica"));
+        assert!(!matches("j*c?", "japan"));
+    }
+
+    #[test]
+    fn test_wildcard_automaton_multibyte_chars() {
+        assert!(matches("caf?", "café"));
+        assert!(!matches("caf??", "café"));
+        assert!(matches("?ber", "über"));
+        assert!(matches("*é", "café"));
+        assert!(matches("東?", "東京"));
+        assert!(!matches("*??", "東"));
+    }
+
+    #[test]
+    fn test_wildcard_automaton_escape() {
+        assert!(matches(r"a\*", "a*"));
+        assert!(!matches(r"a\*", "ab"));
+        assert!(matches(r"a\?b", "a?b"));
+        assert!(!matches(r"a\?b", "acb"));
+        assert!(matches(r"a\\", r"a\"));
+        assert!(matches("a.[b]", "a.[b]"));
+        assert!(!matches("a.b", "acb"));
+        assert!(matches("a?b", "a\nb"));
+    }
+
+    #[test]
+    fn test_wildcard_query() -> crate::Result<()> {
+        let mut schema_builder = Schema::builder();
+        let country_field = schema_builder.add_text_field("country", STRING);
+        let index = Index::create_in_ram(schema_builder.build());
+        let mut index_writer = index.writer_for_tests()?;
+        index_writer.add_document(doc!(country_field => "japan"))?;
+        index_writer.add_document(doc!(country_field => "jamaica"))?;
+        index_writer.add_document(doc!(country_field => "korea"))?;
+        index_writer.add_document(doc!(country_field => "south korea"))?;
+        index_writer.commit()?;
+        let searcher = index.reader()?.searcher();
+        let count = |pattern: &str| {
+            let query = WildcardQuery::new(country_field, pattern);
+            searcher.search(&query, &Count).unwrap()
+        };
+        assert_eq!(count("ja*"), 2);
+        assert_eq!(count("*a"), 3);
+        assert_eq!(count("*korea"), 2);
+        assert_eq!(count("?orea"), 1);
+        assert_eq!(count("*"), 4);
+        assert_eq!(count("j?p*"), 1);
+        assert_eq!(count("x*"), 0);
+        Ok(())
+    }
+}
@@ -103,6 +103,11 @@ impl Document {
         self.add_field_value(field, value);
     }
 
+    /// Add a geo point field
+    pub fn add_geo_point(&mut self, field: Field, value: GeoPoint) {
+        self.add_field_value(field, value);
+    }
+
     /// Add a i64 field
     pub fn add_i64(&mut self, field: Field, value: i64) {
         self.add_field_value(field, value);
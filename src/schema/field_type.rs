@@ -5,13 +5,14 @@ use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
 use thiserror::Error;
 
+use super::geo_point_options::GeoPointOptions;
 use super::ip_options::IpAddrOptions;
 use super::{Cardinality, IntoIpv6Addr};
 use crate::schema::bytes_options::BytesOptions;
 use crate::schema::facet_options::FacetOptions;
 use crate::schema::{
-    DateOptions, Facet, IndexRecordOption, JsonObjectOptions, NumericOptions, TextFieldIndexing,
-    TextOptions, Value,
+    DateOptions, Facet, GeoPoint, IndexRecordOption, JsonObjectOptions, NumericOptions,
+    TextFieldIndexing, TextOptions, Value,
 };
 use crate::time::format_description::well_known::Rfc3339;
 use crate::time::OffsetDateTime;
@@ -68,9 +69,11 @@ pub enum Type {
     Json = b'j',
     /// IpAddr
     IpAddr = b'p',
+    /// `tantivy::schema::GeoPoint`
+    GeoPoint = b'g',
 }
 
-const ALL_TYPES: [Type; 10] = [
+const ALL_TYPES: [Type; 11] = [
     Type::Str,
     Type::U64,
     Type::I64,
@@ -81,6 +84,7 @@ const ALL_TYPES: [Type; 10] = [
     Type::Bytes,
     Type::Json,
     Type::IpAddr,
+    Type::GeoPoint,
 ];
 
 impl Type {
@@ -108,6 +112,7 @@ impl Type {
             Type::Bytes => "Bytes",
             Type::Json => "Json",
             Type::IpAddr => "IpAddr",
+            Type::GeoPoint => "GeoPoint",
         }
     }
 
@@ -125,6 +130,7 @@ impl Type {
             b'b' => Some(Type::Bytes),
             b'j' => Some(Type::Json),
             b'p' => Some(Type::IpAddr),
+            b'g' => Some(Type::GeoPoint),
             _ => None,
         }
     }
@@ -157,6 +163,8 @@ pub enum FieldType {
     JsonObject(JsonObjectOptions),
     /// IpAddr field
     IpAddr(IpAddrOptions),
+    /// Geo point field
+    GeoPoint(GeoPointOptions),
 }
 
 impl FieldType {
@@ -173,6 +181,7 @@ impl FieldType {
             FieldType::Bytes(_) => Type::Bytes,
             FieldType::JsonObject(_) => Type::Json,
             FieldType::IpAddr(_) => Type::IpAddr,
+            FieldType::GeoPoint(_) => Type::GeoPoint,
         }
     }
 
@@ -194,6 +203,7 @@ impl FieldType {
             FieldType::Bytes(ref bytes_options) => bytes_options.is_indexed(),
             FieldType::JsonObject(ref json_object_options) => json_object_options.is_indexed(),
             FieldType::IpAddr(ref ip_addr_options) => ip_addr_options.is_indexed(),
+            FieldType::GeoPoint(ref geo_point_options) => geo_point_options.is_indexed(),
         }
     }
 
@@ -229,6 +239,7 @@ impl FieldType {
             | FieldType::Bool(ref int_options) => int_options.is_fast(),
             FieldType::Date(ref date_options) => date_options.is_fast(),
             FieldType::IpAddr(ref ip_addr_options) => ip_addr_options.is_fast(),
+            FieldType::GeoPoint(ref geo_point_options) => geo_point_options.is_fast(),
             FieldType::Facet(_) => true,
             FieldType::JsonObject(_) => false,
         }
@@ -251,6 +262,9 @@ impl FieldType {
             FieldType::Facet(_) => Some(Cardinality::MultiValues),
             FieldType::JsonObject(_) => None,
             FieldType::IpAddr(ref ip_addr_options) => ip_addr_options.get_fastfield_cardinality(),
+            FieldType::GeoPoint(ref geo_point_options) => {
+                geo_point_options.get_fastfield_cardinality()
+            }
         }
     }
 
@@ -270,6 +284,7 @@ impl FieldType {
             FieldType::Bytes(ref bytes_options) => bytes_options.fieldnorms(),
             FieldType::JsonObject(ref _json_object_options) => false,
             FieldType::IpAddr(ref ip_addr_options) => ip_addr_options.fieldnorms(),
+            FieldType::GeoPoint(ref geo_point_options) => geo_point_options.fieldnorms(),
         }
     }
 
@@ -321,6 +336,13 @@ impl FieldType {
                     None
                 }
             }
+            FieldType::GeoPoint(ref geo_point_options) => {
+                if geo_point_options.is_indexed() {
+                    Some(IndexRecordOption::Basic)
+                } else {
+                    None
+                }
+            }
         }
     }
 
@@ -370,6 +392,15 @@ impl FieldType {
 
                         Ok(Value::IpAddr(ip_addr.into_ipv6_addr()))
                     }
+                    FieldType::GeoPoint(_) => {
+                        let geo_point = parse_geo_point_str(&field_text).ok_or_else(|| {
+                            ValueParsingError::TypeError {
+                                expected: "a \"lat,lon\" string",
+                                json: JsonValue::String(field_text.clone()),
+                            }
+                        })?;
+                        validate_geo_point(geo_point, JsonValue::String(field_text))
+                    }
                 }
             }
             JsonValue::Number(field_val_num) => match self {
@@ -421,6 +452,10 @@ impl FieldType {
                     expected: "a string with an ip addr",
                     json: JsonValue::Number(field_val_num),
                 }),
+                FieldType::GeoPoint(_) => Err(ValueParsingError::TypeError {
+                    expected: "a geo point object or a \"lat,lon\" string",
+                    json: JsonValue::Number(field_val_num),
+                }),
             },
             JsonValue::Object(json_map) => match self {
                 FieldType::Str(_) => {
@@ -436,6 +471,16 @@ impl FieldType {
                     }
                 }
                 FieldType::JsonObject(_) => Ok(Value::JsonObject(json_map)),
+                FieldType::GeoPoint(_) => {
+                    let geo_point = serde_json::from_value::<GeoPoint>(serde_json::Value::Object(
+                        json_map.clone(),
+                    ))
+                    .map_err(|_| ValueParsingError::TypeError {
+                        expected: "a geo point object with a \"lat\" and a \"lon\"",
+                        json: JsonValue::Object(json_map.clone()),
+                    })?;
+                    validate_geo_point(geo_point, JsonValue::Object(json_map))
+                }
                 _ => Err(ValueParsingError::TypeError {
                     expected: self.value_type().name(),
                     json: JsonValue::Object(json_map),
@@ -456,13 +501,32 @@ impl FieldType {
     }
 }
 
+/// Rejects the geo points with a coordinate out of bounds or NaN.
+fn validate_geo_point(geo_point: GeoPoint, json: JsonValue) -> Result<Value, ValueParsingError> {
+    if !geo_point.is_valid() {
+        return Err(ValueParsingError::OverflowError {
+            expected: "a latitude in [-90, 90] and a longitude in [-180, 180]",
+            json,
+        });
+    }
+    Ok(Value::GeoPoint(geo_point))
+}
+
+/// Parses a geo point given as a `"lat,lon"` string.
+fn parse_geo_point_str(text: &str) -> Option<GeoPoint> {
+    let (lat, lon) = text.split_once(',')?;
+    let lat = f64::from_str(lat.trim()).ok()?;
+    let lon = f64::from_str(lon.trim()).ok()?;
+    Some(GeoPoint::new(lat, lon))
+}
+
 #[cfg(test)]
 mod tests {
     use serde_json::json;
 
     use super::FieldType;
     use crate::schema::field_type::ValueParsingError;
-    use crate::schema::{Schema, TextOptions, Type, Value, INDEXED};
+    use crate::schema::{GeoPoint, Schema, TextOptions, Type, Value, INDEXED};
     use crate::time::{Date, Month, PrimitiveDateTime, Time};
     use crate::tokenizer::{PreTokenizedString, Token};
     use crate::{DateTime, Document};
@@ -513,6 +577,37 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_geo_point_value_from_json() {
+        let field_type = FieldType::GeoPoint(Default::default());
+        let expected = Value::GeoPoint(GeoPoint::new(48.85, 2.35));
+        let result = field_type
+            .value_from_json(json!({"lat": 48.85, "lon": 2.35}))
+            .unwrap();
+        assert_eq!(result, expected);
+        let result = field_type.value_from_json(json!("48.85, 2.35")).unwrap();
+        assert_eq!(result, expected);
+
+        for invalid_json in [json!(48.85), json!("48.85"), json!({"lat": 48.85})] {
+            match field_type.value_from_json(invalid_json) {
+                Err(ValueParsingError::TypeError { .. }) => {}
+                _ => panic!("Expected parse failure for invalid geo point"),
+            }
+        }
+        for out_of_bounds_json in [
+            json!({"lat": 90.5, "lon": 2.35}),
+            json!({"lat": 48.85, "lon": -180.5}),
+            json!("-91, 2.35"),
+            json!("NaN, 2.35"),
+            json!("48.85, inf"),
+        ] {
+            match field_type.value_from_json(out_of_bounds_json) {
+                Err(ValueParsingError::OverflowError { .. }) => {}
+                _ => panic!("Expected parse failure for out of bounds geo point"),
+            }
+        }
+    }
+
     #[test]
     fn test_pre_tok_str_value_from_json() {
         let pre_tokenized_string_json = r#"{
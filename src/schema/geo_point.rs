@@ -0,0 +1,180 @@
+use std::cmp::Ordering;
+
+use serde::{Deserialize, Serialize};
+
+use crate::TantivyError;
+
+/// Mean radius of the earth, in meters, as used by elasticsearch.
+pub(crate) const EARTH_RADIUS_METERS: f64 = 6_371_008.771_4;
+
+/// Number of distinct quantized values for the latitude and the longitude.
+const NUM_QUANTIZED_VALUES: f64 = (1u64 << 32) as f64;
+
+/// A point on the earth, given by its latitude and its longitude in degrees.
+///
+/// Geo points are indexed and stored in fast fields as a 64-bit
+/// [Z-order curve](https://en.wikipedia.org/wiki/Z-order_curve) code interleaving the
+/// bits of the latitude and of the longitude, each quantized on 32 bits.
+/// The precision of the decoded points is therefore about one centimeter.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GeoPoint {
+    /// Latitude, in degrees, between -90 and 90.
+    pub lat: f64,
+    /// Longitude, in degrees, between -180 and 180.
+    pub lon: f64,
+}
+
+impl GeoPoint {
+    /// Creates a new geo point from a latitude and a longitude in degrees.
+    pub fn new(lat: f64, lon: f64) -> GeoPoint {
+        GeoPoint { lat, lon }
+    }
+
+    /// Returns true iff the latitude is in `[-90, 90]` and the longitude in `[-180, 180]`.
+    ///
+    /// In particular, a point with a NaN coordinate is not valid.
+    pub fn is_valid(&self) -> bool {
+        (-90.0..=90.0).contains(&self.lat) && (-180.0..=180.0).contains(&self.lon)
+    }
+
+    /// Returns an error if the latitude or the longitude is out of bounds.
+    pub(crate) fn validate(&self) -> crate::Result<()> {
+        if !self.is_valid() {
+            return Err(TantivyError::InvalidArgument(format!(
+                "invalid geo point {:?}, the latitude must be in [-90, 90] and the longitude in \
+                 [-180, 180]",
+                self
+            )));
+        }
+        Ok(())
+    }
+
+    /// Returns the great-circle distance to `other`, in meters.
+    ///
+    /// The distance is computed with the haversine formula.
+    pub fn distance(&self, other: &GeoPoint) -> f64 {
+        let lat1 = self.lat.to_radians();
+        let lat2 = other.lat.to_radians();
+        let delta_lat = lat2 - lat1;
+        let delta_lon = (other.lon - self.lon).to_radians();
+        let a = (delta_lat / 2.0).sin().powi(2)
+            + lat1.cos() * lat2.cos() * (delta_lon / 2.0).sin().powi(2);
+        2.0 * EARTH_RADIUS_METERS * a.sqrt().min(1.0).asin()
+    }
+
+    /// Returns the Z-order code of the point.
+    pub(crate) fn to_z_order(self) -> u64 {
+        let lat_quantized = quantize(self.lat, -90.0, 180.0);
+        let lon_quantized = quantize(self.lon, -180.0, 360.0);
+        spread_bits(lon_quantized) | (spread_bits(lat_quantized) << 1)
+    }
+
+    /// Returns the center of the cell associated with the Z-order code `code`.
+    pub(crate) fn from_z_order(code: u64) -> GeoPoint {
+        let lon_quantized = squash_bits(code);
+        let lat_quantized = squash_bits(code >> 1);
+        GeoPoint {
+            lat: dequantize(lat_quantized, -90.0, 180.0),
+            lon: dequantize(lon_quantized, -180.0, 360.0),
+        }
+    }
+
+    /// Returns the south-west and the north-east corners of the cell made of the Z-order codes
+    /// sharing the `2 * level` highest bits of `code`.
+    pub(crate) fn z_order_cell_corners(code: u64, level: u32) -> (GeoPoint, GeoPoint) {
+        let width = 1u64 << (32 - level);
+        let mask = !(width - 1);
+        let lat_quantized = (squash_bits(code >> 1) as u64 & mask) as f64;
+        let lon_quantized = (squash_bits(code) as u64 & mask) as f64;
+        let width = width as f64;
+        let south_west = GeoPoint {
+            lat: -90.0 + lat_quantized / NUM_QUANTIZED_VALUES * 180.0,
+            lon: -180.0 + lon_quantized / NUM_QUANTIZED_VALUES * 360.0,
+        };
+        let north_east = GeoPoint {
+            lat: -90.0 + (lat_quantized + width) / NUM_QUANTIZED_VALUES * 180.0,
+            lon: -180.0 + (lon_quantized + width) / NUM_QUANTIZED_VALUES * 360.0,
+        };
+        (south_west, north_east)
+    }
+}
+
+/// Geo points are ordered by Z-order code, so that their order is consistent
+/// with their fast field and term representation.
+impl PartialOrd for GeoPoint {
+    fn partial_cmp(&self, other: &GeoPoint) -> Option<Ordering> {
+        match self.to_z_order().cmp(&other.to_z_order()) {
+            Ordering::Equal => (self.lat, self.lon).partial_cmp(&(other.lat, other.lon)),
+            ordering => Some(ordering),
+        }
+    }
+}
+
+/// Maps `val`, within `[min, min + width]`, to one of the 2^32 cells of the interval.
+fn quantize(val: f64, min: f64, width: f64) -> u32 {
+    let cell = ((val - min) / width * NUM_QUANTIZED_VALUES).floor();
+    cell.max(0.0).min(u32::MAX as f64) as u32
+}
+
+fn dequantize(cell: u32, min: f64, width: f64) -> f64 {
+    min + (cell as f64 + 0.5) / NUM_QUANTIZED_VALUES * width
+}
+
+/// Spreads the bits of `val` over the even bits of a u64.
+fn spread_bits(val: u32) -> u64 {
+    let mut val = val as u64;
+    val = (val | (val << 16)) & 0x0000_FFFF_0000_FFFF;
+    val = (val | (val << 8)) & 0x00FF_00FF_00FF_00FF;
+    val = (val | (val << 4)) & 0x0F0F_0F0F_0F0F_0F0F;
+    val = (val | (val << 2)) & 0x3333_3333_3333_3333;
+    (val | (val << 1)) & 0x5555_5555_5555_5555
+}
+
+/// Inverse of [`spread_bits`]: gathers the even bits of `val`.
+fn squash_bits(val: u64) -> u32 {
+    let mut val = val & 0x5555_5555_5555_5555;
+    val = (val | (val >> 1)) & 0x3333_3333_3333_3333;
+    val = (val | (val >> 2)) & 0x0F0F_0F0F_0F0F_0F0F;
+    val = (val | (val >> 4)) & 0x00FF_00FF_00FF_00FF;
+    val = (val | (val >> 8)) & 0x0000_FFFF_0000_FFFF;
+    (val | (val >> 16)) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GeoPoint;
+
+    #[test]
+    fn test_geo_point_z_order_roundtrip() {
+        let points = [
+            GeoPoint::new(0.0, 0.0),
+            GeoPoint::new(48.8566, 2.3522),
+            GeoPoint::new(-33.8688, 151.2093),
+            GeoPoint::new(90.0, 180.0),
+            GeoPoint::new(-90.0, -180.0),
+        ];
+        for point in points {
+            let decoded = GeoPoint::from_z_order(point.to_z_order());
+            assert!((decoded.lat - point.lat).abs() < 1e-7);
+            assert!((decoded.lon - point.lon).abs() < 1e-7);
+            assert_eq!(decoded.to_z_order(), point.to_z_order());
+        }
+    }
+
+    #[test]
+    fn test_geo_point_distance() {
+        let paris = GeoPoint::new(48.8566, 2.3522);
+        let london = GeoPoint::new(51.5074, -0.1278);
+        let distance = paris.distance(&london);
+        assert!((distance - 343_500.0).abs() < 1_000.0, "{distance}");
+        assert_eq!(paris.distance(&paris), 0.0);
+    }
+
+    #[test]
+    fn test_geo_point_serde() {
+        let point = GeoPoint::new(1.5, -2.5);
+        let json = serde_json::to_string(&point).unwrap();
+        assert_eq!(json, r#"{"lat":1.5,"lon":-2.5}"#);
+        assert_eq!(serde_json::from_str::<GeoPoint>(&json).unwrap(), point);
+    }
+}
@@ -118,6 +118,8 @@ mod bytes_options;
 mod date_time_options;
 mod field;
 mod flags;
+pub(crate) mod geo_point;
+mod geo_point_options;
 mod index_record_option;
 mod ip_options;
 mod json_object_options;
@@ -137,6 +139,8 @@ pub use self::field_entry::FieldEntry;
 pub use self::field_type::{FieldType, Type};
 pub use self::field_value::FieldValue;
 pub use self::flags::{FAST, INDEXED, STORED};
+pub use self::geo_point::GeoPoint;
+pub use self::geo_point_options::GeoPointOptions;
 pub use self::index_record_option::IndexRecordOption;
 pub use self::ip_options::{IntoIpv6Addr, IpAddrOptions};
 pub use self::json_object_options::JsonObjectOptions;
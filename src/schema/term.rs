@@ -7,7 +7,7 @@ use fastfield_codecs::MonotonicallyMappableToU128;
 
 use super::Field;
 use crate::fastfield::FastValue;
-use crate::schema::{Facet, Type};
+use crate::schema::{Facet, GeoPoint, Type};
 use crate::{DatePrecision, DateTime};
 
 /// Separates the different segments of
@@ -103,6 +103,13 @@ impl Term {
         Term::from_fast_value(field, &val.truncate(DatePrecision::Seconds))
     }
 
+    /// Builds a term given a field, and a `GeoPoint` value
+    ///
+    /// The term holds the Z-order code of the geo point.
+    pub fn from_field_geo_point(field: Field, val: GeoPoint) -> Term {
+        Term::from_fast_value(field, &val)
+    }
+
     /// Creates a `Term` given a facet.
     pub fn from_facet(field: Field, facet: &Facet) -> Term {
         let facet_encoded_str = facet.encoded_str();
@@ -165,6 +172,11 @@ impl Term {
         self.set_bytes(val.to_u64().to_be_bytes().as_ref());
     }
 
+    /// Sets a `GeoPoint` value in the term.
+    pub fn set_geo_point(&mut self, val: GeoPoint) {
+        self.set_fast_value(val);
+    }
+
     /// Sets a `Ipv6Addr` value in the term.
     pub fn set_ip_addr(&mut self, val: Ipv6Addr) {
         self.set_bytes(val.to_u128().to_be_bytes().as_ref());
@@ -317,6 +329,17 @@ where B: AsRef<[u8]>
         self.get_fast_type::<DateTime>()
     }
 
+    /// Returns the `GeoPoint` value stored in a term.
+    ///
+    /// The geo point is the center of the cell of its Z-order code, within about one centimeter
+    /// of the indexed geo point.
+    ///
+    /// Returns `None` if the term is not of the GeoPoint type, or if the term byte representation
+    /// is invalid.
+    pub fn as_geo_point(&self) -> Option<GeoPoint> {
+        self.get_fast_type::<GeoPoint>()
+    }
+
     /// Returns the text associated with the term.
     ///
     /// Returns `None` if the field is not of string type
@@ -438,6 +461,9 @@ fn debug_value_bytes(typ: Type, bytes: &[u8], f: &mut fmt::Formatter) -> fmt::Re
         Type::IpAddr => {
             write!(f, "")?; // TODO change once we actually have IP address terms.
         }
+        Type::GeoPoint => {
+            write_opt(f, get_fast_type::<GeoPoint>(bytes))?;
+        }
     }
     Ok(())
 }
@@ -5,7 +5,7 @@ use serde::de::Visitor;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use serde_json::Map;
 
-use crate::schema::Facet;
+use crate::schema::{Facet, GeoPoint};
 use crate::tokenizer::PreTokenizedString;
 use crate::DateTime;
 
@@ -35,6 +35,8 @@ pub enum Value {
     JsonObject(serde_json::Map<String, serde_json::Value>),
     /// IpV6 Address. Internally there is no IpV4, it needs to be converted to `Ipv6Addr`.
     IpAddr(Ipv6Addr),
+    /// Geo point, given by its latitude and its longitude.
+    GeoPoint(GeoPoint),
 }
 
 impl Eq for Value {}
@@ -61,6 +63,7 @@ impl Serialize for Value {
                     obj.serialize(serializer)
                 }
             }
+            Value::GeoPoint(ref geo_point) => geo_point.serialize(serializer),
         }
     }
 }
@@ -222,6 +225,16 @@ impl Value {
             None
         }
     }
+
+    /// Returns the geo point, provided the value is of the `GeoPoint` type.
+    /// (Returns None if the value is not of the `GeoPoint` type)
+    pub fn as_geo_point(&self) -> Option<GeoPoint> {
+        if let Value::GeoPoint(val) = self {
+            Some(*val)
+        } else {
+            None
+        }
+    }
 }
 
 impl From<String> for Value {
@@ -236,6 +249,12 @@ impl From<Ipv6Addr> for Value {
     }
 }
 
+impl From<GeoPoint> for Value {
+    fn from(v: GeoPoint) -> Value {
+        Value::GeoPoint(v)
+    }
+}
+
 impl From<u64> for Value {
     fn from(v: u64) -> Value {
         Value::U64(v)
@@ -321,7 +340,7 @@ mod binary_serialize {
     use fastfield_codecs::MonotonicallyMappableToU128;
 
     use super::Value;
-    use crate::schema::Facet;
+    use crate::schema::{Facet, GeoPoint};
     use crate::tokenizer::PreTokenizedString;
     use crate::DateTime;
 
@@ -336,6 +355,7 @@ mod binary_serialize {
     const JSON_OBJ_CODE: u8 = 8;
     const BOOL_CODE: u8 = 9;
     const IP_CODE: u8 = 10;
+    const GEO_POINT_CODE: u8 = 11;
 
     // extended types
 
@@ -400,6 +420,11 @@ mod binary_serialize {
                     IP_CODE.serialize(writer)?;
                     ip.to_u128().serialize(writer)
                 }
+                Value::GeoPoint(ref geo_point) => {
+                    GEO_POINT_CODE.serialize(writer)?;
+                    f64_to_u64(geo_point.lat).serialize(writer)?;
+                    f64_to_u64(geo_point.lon).serialize(writer)
+                }
             }
         }
 
@@ -474,6 +499,11 @@ mod binary_serialize {
                     let value = u128::deserialize(reader)?;
                     Ok(Value::IpAddr(Ipv6Addr::from_u128(value)))
                 }
+                GEO_POINT_CODE => {
+                    let lat = u64_to_f64(u64::deserialize(reader)?);
+                    let lon = u64_to_f64(u64::deserialize(reader)?);
+                    Ok(Value::GeoPoint(GeoPoint::new(lat, lon)))
+                }
 
                 _ => Err(io::Error::new(
                     io::ErrorKind::InvalidData,